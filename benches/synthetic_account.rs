@@ -0,0 +1,70 @@
+#![recursion_limit = "256"]
+use criterion::{black_box, criterion_group, criterion_main, Criterion};
+use deltachat::chat::{self, ChatId};
+use deltachat::chatlist::Chatlist;
+use deltachat::context::Context;
+use deltachat::stock_str::StockStrings;
+use deltachat::synthetic::SyntheticAccount;
+use deltachat::Events;
+use tempfile::{tempdir, TempDir};
+
+async fn create_context(dir: &TempDir) -> Context {
+    let dbfile = dir.path().join("db.sqlite");
+    Context::new(dbfile.as_path(), 100, Events::new(), StockStrings::new())
+        .await
+        .unwrap()
+}
+
+async fn receive_small_account() {
+    let dir = tempdir().unwrap();
+    let context = create_context(&dir).await;
+    let account = SyntheticAccount {
+        chats: 10,
+        msgs_per_chat: 10,
+        ..Default::default()
+    };
+    black_box(account.populate(&context).await.unwrap());
+}
+
+async fn get_chat_msgs(context: &Context, chats: &[ChatId]) {
+    for chat_id in chats.iter().take(10) {
+        black_box(chat::get_chat_msgs(context, *chat_id).await.unwrap());
+    }
+}
+
+fn criterion_benchmark(c: &mut Criterion) {
+    let rt = tokio::runtime::Runtime::new().unwrap();
+    let dir = tempdir().unwrap();
+    let (context, chats) = rt.block_on(async {
+        let context = create_context(&dir).await;
+        let chats = SyntheticAccount::default()
+            .populate(&context)
+            .await
+            .unwrap();
+        (context, chats)
+    });
+
+    let mut group = c.benchmark_group("Synthetic account");
+    group.sample_size(10);
+    group.bench_function("Receive 10 chats with 10 messages each", |b| {
+        b.to_async(&rt).iter(receive_small_account)
+    });
+    group.finish();
+
+    c.bench_function("synthetic: chatlist:try_load", |b| {
+        b.to_async(&rt).iter(|| async {
+            black_box(Chatlist::try_load(&context, 0, None, None).await.unwrap())
+        })
+    });
+    c.bench_function("synthetic: chat::get_chat_msgs", |b| {
+        b.to_async(&rt)
+            .iter(|| get_chat_msgs(black_box(&context), black_box(&chats)))
+    });
+    c.bench_function("synthetic: search_msgs", |b| {
+        b.to_async(&rt)
+            .iter(|| async { black_box(context.search_msgs(None, "chat 42").await.unwrap()) })
+    });
+}
+
+criterion_group!(benches, criterion_benchmark);
+criterion_main!(benches);
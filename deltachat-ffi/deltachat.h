@@ -425,6 +425,27 @@ char*           dc_get_blobdir               (const dc_context_t* context);
  *                    1=send a copy of outgoing messages to self.
  *                    Sending messages to self is needed for a proper multi-account setup,
  *                    however, on the other hand, may lead to unwanted notifications in non-delta clients.
+ * - `report_spam` = 1=when blocking a contact request,
+ *                    send an abuse report about the last message
+ *                    to the abuse report address of the provider,
+ *                    0=do not report blocked contact requests (default).
+ *                    The report contains the message headers, but not the message text.
+ *                    If the provider is not known to accept abuse reports
+ *                    and `spam_report_addr` is not set, an error is shown instead.
+ * - `spam_report_addr` = address accepting abuse reports,
+ *                    overrides the address from the provider database.
+ * - `persist_outbox` = 1=store messages not sent yet, encrypted to self,
+ *                    in the `DeltaChat-Outbox` folder on the server,
+ *                    so they are restored and sent after reinstalling the app,
+ *                    0=keep unsent messages on this device only (default).
+ * - `auto_backup_interval` = interval in seconds between automatic backups,
+ *                    0=no automatic backups (default).
+ *                    Backups are exported while IO is running, to `auto_backup_dir`,
+ *                    and #DC_EVENT_IMEX_FILE_WRITTEN is emitted for each backup.
+ * - `auto_backup_dir` = directory to export automatic backups to.
+ * - `auto_backup_keep` = number of automatic backups to keep in `auto_backup_dir`,
+ *                    older backups of the account are deleted, defaults to 3,
+ *                    0=keep all backups.
  * - `sentbox_watch`= 1=watch `Sent`-folder for changes,
  *                    0=do not watch the `Sent`-folder (default).
  * - `mvbox_move`   = 1=detect chat messages,
@@ -464,6 +485,9 @@ char*           dc_get_blobdir               (const dc_context_t* context);
  *                    "Saved messages" are deleted from the server as well as
  *                    e-mails matching the `show_emails` settings above, the UI should clearly point that out.
  *                    See also dc_estimate_deletion_cnt().
+ * - `delete_for_all_window` = seconds after sending during which own messages
+ *                    can be deleted for everyone with dc_delete_msgs_for_all(),
+ *                    defaults to 86400 (one day), 0=no limit.
  * - `media_quality` = DC_MEDIA_QUALITY_BALANCED (0) =
  *                    good outgoing images/videos/voice quality at reasonable sizes (default)
  *                    DC_MEDIA_QUALITY_WORSE (1)
@@ -472,6 +496,12 @@ char*           dc_get_blobdir               (const dc_context_t* context);
  *                    The library uses the `media_quality` setting to use different defaults
  *                    for recoding images sent with type #DC_MSG_IMAGE.
  *                    If needed, recoding other file types is up to the UI.
+ * - `imap_id`      = what to send in the IMAP `ID` command if the server supports it:
+ *                    DC_IMAP_ID_NAME (0) = send the client name only (default),
+ *                    DC_IMAP_ID_ANONYMOUS (1) = do not identify the client,
+ *                    DC_IMAP_ID_FULL (2) = send the client name and version,
+ *                    useful for debugging on the server side.
+ *                    Changes take effect on the next IMAP connection.
  * - `webrtc_instance` = webrtc instance to use for videochats in the form
  *                    `[basicwebrtc:|jitsi:]https://example.com/subdir#roomname=$ROOM`
  *                    if the URL is prefixed by `basicwebrtc`, the server is assumed to be of the type
@@ -484,6 +514,10 @@ char*           dc_get_blobdir               (const dc_context_t* context);
  *                    accepts contact requests automatically (calling dc_accept_chat() is not needed),
  *                    does not cut large incoming text messages,
  *                    handles existing messages the same way as new ones if `fetch_existing_msgs=1`.
+ * - `bot_commands` = JSON array of commands understood by the bot,
+ *                    e.g. `[{"name":"help","params":"","description":"Shows help"}]`.
+ *                    The commands are sent along with all messages if `bot` is set
+ *                    and can be retrieved by the receiver using dc_contact_get_bot_commands().
  * - `last_msg_id` = database ID of the last message processed by the bot.
  *                   This ID and IDs below it are guaranteed not to be returned
  *                   by dc_get_next_msgs() and dc_wait_next_msgs().
@@ -529,9 +563,25 @@ char*           dc_get_blobdir               (const dc_context_t* context);
  *                    e.g. `ui.desktop.foo`, `ui.desktop.linux.bar`, `ui.android.foo`, `ui.dc40.bar`, `ui.bot.simplebot.baz`.
  *                    These keys go to backups and allow easy per-account settings when using @ref dc_accounts_t,
  *                    however, are not handled by the core otherwise.
+ *                    Keys prefixed by `ui.sync.`, e.g. `ui.sync.desktop.theme`,
+ *                    are synced to other devices if `sync_msgs` is enabled, other `ui.*` keys are device-specific.
  * - `webxdc_realtime_enabled` = Whether the realtime APIs should be enabled.
  *                               0 = WebXDC realtime API is disabled and behaves as noop (default).
  *                               1 = WebXDC realtime API is enabled.
+ * - `connect_timeout` = Timeout in seconds for establishing TCP connections, default 60.
+ * - `tls_handshake_timeout` = Timeout in seconds for TLS handshakes, default 60.
+ * - `imap_timeout` = Read and write timeout in seconds for IMAP commands, default 60.
+ * - `smtp_timeout` = Read and write timeout in seconds for SMTP commands, default 60.
+ *                    For all timeouts, 0 means the built-in default.
+ * - `wkd_lookup` = 1=look up keys of recipients without a known key in the Web Key Directory
+ *                    of their domain before sending, the keys are used provisionally
+ *                    until a key is received via Autocrypt,
+ *                    0=do not look up keys in the Web Key Directory (default).
+ * - `provider_overrides` = provider database entries in JSON or TOML format
+ *                    taking precedence over the built-in provider database of the core,
+ *                    e.g. to fix broken server settings without waiting for a release.
+ *                    Invalid values are rejected and the previous value is kept.
+ *                    The format is documented in the `provider::overrides` module of the core.
  *
  * If you want to retrieve a value, use dc_get_config().
  *
@@ -594,6 +644,7 @@ int             dc_set_stock_translation(dc_context_t* context, uint32_t stock_i
  * Internally, the function will call dc_set_config() with the appropriate keys,
  * e.g. `addr` and `mail_pw` for DC_QR_ACCOUNT and DC_QR_LOGIN
  * or `webrtc_instance` for DC_QR_WEBRTC_INSTANCE.
+ * For DC_QR_VCARD, the contacts from the vCard are imported.
  *
  * @memberof dc_context_t
  * @param context The context object.
@@ -778,6 +829,28 @@ int             dc_all_work_done             (dc_context_t* context);
 void            dc_configure                 (dc_context_t* context);
 
 
+/**
+ * Configure the account from a QR code of the type #DC_QR_ACCOUNT or #DC_QR_LOGIN.
+ *
+ * For #DC_QR_ACCOUNT, an account is created on the chatmail server
+ * and the address and password returned by the server
+ * are stored as `addr` and `mail_pw`.
+ * For #DC_QR_LOGIN, the address, password and server parameters
+ * from the QR code are stored.
+ * Then dc_configure() is run.
+ * Progress of both steps is reported with #DC_EVENT_CONFIGURE_PROGRESS events,
+ * on errors, progress 0 is reported as for dc_configure().
+ *
+ * The function returns immediately and must not be called while IO is running,
+ * stop it with dc_stop_io() before.
+ *
+ * @memberof dc_context_t
+ * @param context The context object.
+ * @param qr The text of the scanned QR code, typically checked with dc_check_qr() before.
+ */
+void            dc_configure_from_qr         (dc_context_t* context, const char* qr);
+
+
 /**
  * Check if the context is already configured.
  *
@@ -1071,6 +1144,32 @@ uint32_t        dc_send_msg                  (dc_context_t* context, uint32_t ch
  */
 uint32_t        dc_send_msg_sync                  (dc_context_t* context, uint32_t chat_id, dc_msg_t* msg);
 
+/**
+ * Send a message defined by a dc_msg_t object to a chat
+ * without waiting for the message to be prepared.
+ *
+ * Unlike dc_send_msg(), the file is neither copied nor recoded
+ * and the message is not encrypted before the function returns.
+ * Instead, the message is added to the chat in the #DC_STATE_OUT_PENDING state
+ * so that it can be shown immediately,
+ * and it is prepared in the background before sending.
+ * The file must not be deleted until the message is prepared.
+ * Once prepared, #DC_EVENT_MSGS_CHANGED is emitted for the message;
+ * if the preparation fails, the message state is changed to #DC_STATE_OUT_FAILED.
+ *
+ * Messages prepared with dc_prepare_msg() cannot be sent with this function.
+ *
+ * @memberof dc_context_t
+ * @param context The context object as returned from dc_context_new().
+ * @param chat_id The chat ID to send the message to.
+ * @param msg The message object to send to the chat defined by the chat ID.
+ *     On success, msg_id of the object is set up,
+ *     The function does not take ownership of the object,
+ *     so you have to free it using dc_msg_unref() as usual.
+ * @return The ID of the message that is about to be sent. 0 in case of errors.
+ */
+uint32_t        dc_send_msg_deferred              (dc_context_t* context, uint32_t chat_id, dc_msg_t* msg);
+
 
 /**
  * Send a simple text message a given chat.
@@ -1094,6 +1193,25 @@ uint32_t        dc_send_msg_sync                  (dc_context_t* context, uint32
 uint32_t        dc_send_text_msg             (dc_context_t* context, uint32_t chat_id, const char* text_to_send);
 
 
+/**
+ * Replace the text of an own message on all devices and for all chat members.
+ *
+ * The edit is sent as a hidden message to the chat of the message.
+ * The previous text is kept and can be retrieved with dc_get_edit_history_json().
+ * Edited messages are flagged as such, see dc_msg_is_edited().
+ *
+ * Only sent text messages can be edited,
+ * messages deleted for everyone, info messages and polls cannot be edited.
+ *
+ * @memberof dc_context_t
+ * @param context The context object.
+ * @param msg_id The ID of the message to edit.
+ * @param new_text The new text of the message, must not be empty.
+ * @return The ID of the hidden message transporting the edit, 0 on errors.
+ */
+uint32_t        dc_send_edit                 (dc_context_t* context, uint32_t msg_id, const char* new_text);
+
+
 /**
  * Send invitation to a videochat.
  *
@@ -1552,6 +1670,49 @@ void            dc_marknoticed_chat          (dc_context_t* context, uint32_t ch
 dc_array_t*     dc_get_chat_media            (dc_context_t* context, uint32_t chat_id, int msg_type, int msg_type2, int msg_type3);
 
 
+/**
+ * Pin a message in its chat for all chat members.
+ * Typically, UIs show the pinned messages in a banner above the chat.
+ *
+ * An info message with the type #DC_INFO_MESSAGE_PINNED is added to the chat
+ * and sent to the other members.
+ * Calling this function results in the event #DC_EVENT_CHAT_MODIFIED.
+ *
+ * @memberof dc_context_t
+ * @param context The context object as returned from dc_context_new().
+ * @param msg_id The ID of the message to pin.
+ * @return 1=success, 0=error
+ */
+int             dc_pin_msg                   (dc_context_t* context, uint32_t msg_id);
+
+
+/**
+ * Unpin a message pinned before with dc_pin_msg().
+ *
+ * An info message with the type #DC_INFO_MESSAGE_UNPINNED is added to the chat
+ * and sent to the other members.
+ *
+ * @memberof dc_context_t
+ * @param context The context object as returned from dc_context_new().
+ * @param msg_id The ID of the message to unpin.
+ * @return 1=success, 0=error
+ */
+int             dc_unpin_msg                 (dc_context_t* context, uint32_t msg_id);
+
+
+/**
+ * Returns the IDs of the messages pinned in a chat.
+ * The list starts with the most recently pinned message.
+ *
+ * @memberof dc_context_t
+ * @param context The context object as returned from dc_context_new().
+ * @param chat_id The chat ID to get the pinned messages for.
+ * @return An array of message IDs, must be dc_array_unref()'d when no longer used.
+ *     On errors, the list is empty. NULL is never returned.
+ */
+dc_array_t*     dc_get_pinned_msgs           (dc_context_t* context, uint32_t chat_id);
+
+
 /**
  * Search next/previous message based on a given message and a list of types.
  * Typically used to implement the "next" and "previous" buttons
@@ -1720,6 +1881,23 @@ uint32_t dc_get_chat_ephemeral_timer (dc_context_t* context, uint32_t chat_id);
 dc_array_t*     dc_search_msgs               (dc_context_t* context, uint32_t chat_id, const char* query);
 
 
+/**
+ * Get an excerpt of the message text or attachment filename
+ * around the match of a query passed to dc_search_msgs() before.
+ * Omitted parts are marked with an ellipsis.
+ *
+ * @memberof dc_context_t
+ * @param context The context object as returned from dc_context_new().
+ * @param msg_id The ID of a message returned by dc_search_msgs().
+ * @param query The query passed to dc_search_msgs().
+ * @return The excerpt, must be released using dc_str_unref() after usage.
+ *     NULL if the message does not match the query
+ *     or if the excerpt is not available yet,
+ *     eg. for short queries or while existing messages are still being indexed.
+ */
+char*           dc_get_msg_search_snippet    (dc_context_t* context, uint32_t msg_id, const char* query);
+
+
 /**
  * Get a chat object by a chat ID.
  *
@@ -2028,9 +2206,45 @@ char*           dc_get_mime_headers          (dc_context_t* context, uint32_t ms
  * @param msg_ids An array of uint32_t containing all message IDs that should be deleted.
  * @param msg_cnt The number of messages IDs in the msg_ids array.
  */
+/**
+ * Get the previous texts of an edited message, see dc_send_edit().
+ *
+ * The result is a JSON array of objects with the fields
+ * `text`, the text before it was replaced,
+ * and `timestamp`, the time it was replaced in seconds since the epoch.
+ * The entries are ordered oldest first, the first entry holds the original text.
+ *
+ * @memberof dc_context_t
+ * @param context The context object.
+ * @param msg_id The message ID.
+ * @return JSON array, empty for messages that were not edited.
+ *     Must be released using dc_str_unref() after usage.
+ */
+char*           dc_get_edit_history_json     (dc_context_t* context, uint32_t msg_id);
+
+
 void            dc_delete_msgs               (dc_context_t* context, const uint32_t* msg_ids, int msg_cnt);
 
 
+/**
+ * Delete own messages for everyone.
+ *
+ * A deletion request is sent to the chats of the messages
+ * and the messages are replaced by tombstones, see dc_msg_is_retracted().
+ * Receivers not supporting the request keep the messages.
+ *
+ * Messages can only be deleted for everyone
+ * during `delete_for_all_window` after sending them, see dc_set_config().
+ *
+ * @memberof dc_context_t
+ * @param context The context object.
+ * @param msg_ids An array of uint32_t containing all message IDs that should be deleted.
+ * @param msg_cnt The number of messages IDs in the msg_ids array.
+ * @return 1=success, 0=error, e.g. a message was not sent by us or is too old.
+ */
+int             dc_delete_msgs_for_all       (dc_context_t* context, const uint32_t* msg_ids, int msg_cnt);
+
+
 /**
  * Forward messages to another chat.
  *
@@ -2301,8 +2515,8 @@ dc_contact_t*   dc_get_contact               (dc_context_t* context, uint32_t co
 
 // import/export and tools
 
-#define         DC_IMEX_EXPORT_SELF_KEYS      1 // param1 is a directory where the keys are written to
-#define         DC_IMEX_IMPORT_SELF_KEYS      2 // param1 is a directory where the keys are searched in and read from
+#define         DC_IMEX_EXPORT_SELF_KEYS      1 // param1 is a directory where the keys are written to, param2 is an optional passphrase to encrypt the secret keys
+#define         DC_IMEX_IMPORT_SELF_KEYS      2 // param1 is a directory where the keys are searched in and read from, param2 is the passphrase of protected key files
 #define         DC_IMEX_EXPORT_BACKUP        11 // param1 is a directory where the backup is written to, param2 is a passphrase to encrypt the backup
 #define         DC_IMEX_IMPORT_BACKUP        12 // param1 is the file with the backup to import, param2 is the backup's passphrase
 
@@ -2315,6 +2529,8 @@ dc_contact_t*   dc_get_contact               (dc_context_t* context, uint32_t co
  * - **DC_IMEX_EXPORT_BACKUP** (11) - Export a backup to the directory given as `param1`
  *   encrypted with the passphrase given as `param2`. If `param2` is NULL or empty string,
  *   the backup is not encrypted.
+ *   Only the database, including the private keys, is encrypted;
+ *   attachments contained in the backup are not.
  *   The backup contains all contacts, chats, images and other data and device independent settings.
  *   The backup does not contain device dependent settings as ringtones or LED notification settings.
  *   The name of the backup is `delta-chat-backup-<day>-<number>-<addr>.tar`.
@@ -2326,11 +2542,14 @@ dc_contact_t*   dc_get_contact               (dc_context_t* context, uint32_t co
  * - **DC_IMEX_EXPORT_SELF_KEYS** (1) - Export all private keys and all public keys of the user to the
  *   directory given as `param1`. The default key is written to the files `public-key-default.asc`
  *   and `private-key-default.asc`, if there are more keys, they are written to files as
- *   `public-key-<id>.asc` and `private-key-<id>.asc`
+ *   `public-key-<id>.asc` and `private-key-<id>.asc`.
+ *   If `param2` is set, the private keys are symmetrically encrypted with this passphrase.
  *
  * - **DC_IMEX_IMPORT_SELF_KEYS** (2) - Import private keys found in the directory given as `param1`.
  *   The last imported key is made the default keys unless its name contains the string `legacy`. Public keys are not imported.
  *   If `param1` is a filename, import the private key from the file and make it the default.
+ *   Passphrase-protected key files are decrypted using the passphrase given as `param2`;
+ *   if it is missing or wrong, the import fails.
  *
  * While dc_imex() returns immediately, the started job may take a while,
  * you can stop it using dc_stop_ongoing_process(). During execution of the job,
@@ -2476,6 +2695,42 @@ char*           dc_initiate_key_transfer     (dc_context_t* context);
 int             dc_continue_key_transfer     (dc_context_t* context, uint32_t msg_id, const char* setup_code);
 
 
+/**
+ * Replace the own key by a newly generated one.
+ *
+ * The old key is kept so that messages encrypted to it can still be decrypted.
+ * All new messages are signed with the new key and the new key is sent in the Autocrypt header.
+ * Moreover, the new key is gossiped to all protected groups with the next message
+ * so that other members can switch to it without the chat getting broken.
+ *
+ * While the function is running, #DC_EVENT_KEY_ROTATION_PROGRESS events are emitted.
+ * Other devices using the same account need to get the new key
+ * by dc_initiate_key_transfer() or by adding them again as a second device.
+ *
+ * @memberof dc_context_t
+ * @param context The context object.
+ * @return The fingerprint of the new key, NULL on errors.
+ *     Must be released using dc_str_unref() after usage.
+ */
+char*           dc_rotate_key                (dc_context_t* context);
+
+
+/**
+ * Replace the encryption subkey of the own key by a newly generated one.
+ *
+ * Unlike dc_rotate_key(), the fingerprint of the key does not change,
+ * so existing verifications stay valid.
+ * The old subkey is kept so that messages encrypted to it can still be decrypted.
+ * Other devices using the same account need to get the renewed key
+ * by dc_initiate_key_transfer() or by adding them again as a second device.
+ *
+ * @memberof dc_context_t
+ * @param context The context object.
+ * @return 1=success, 0=error.
+ */
+int             dc_renew_encryption_subkey   (dc_context_t* context);
+
+
 /**
  * Signal an ongoing process to stop.
  *
@@ -2507,6 +2762,7 @@ void            dc_stop_ongoing_process      (dc_context_t* context);
 #define         DC_QR_BACKUP2                252
 #define         DC_QR_WEBRTC_INSTANCE        260 // text1=domain, text2=instance pattern
 #define         DC_QR_ADDR                   320 // id=contact
+#define         DC_QR_VCARD                  322 // text1=first email_address
 #define         DC_QR_TEXT                   330 // text1=text
 #define         DC_QR_URL                    332 // text1=URL
 #define         DC_QR_ERROR                  400 // text1=error string
@@ -2565,6 +2821,12 @@ void            dc_stop_ongoing_process      (dc_context_t* context);
  *   ask the user if they want to start chatting;
  *   if so, call dc_create_chat_by_contact_id().
  *
+ * - DC_QR_VCARD with dc_lot_t::text1=first email_address:
+ *   vCard with several contacts or with a key scanned;
+ *   ask the user if they want to import the contacts;
+ *   if so, call dc_set_config_from_qr().
+ *   vCards with a single contact and no key result in DC_QR_ADDR.
+ *
  * - DC_QR_TEXT with dc_lot_t::text1=Text:
  *   Text scanned,
  *   ask the user e.g. if they want copy to clipboard.
@@ -2643,6 +2905,24 @@ char*           dc_get_securejoin_qr         (dc_context_t* context, uint32_t ch
  */
 char*           dc_get_securejoin_qr_svg         (dc_context_t* context, uint32_t chat_id);
 
+
+/**
+ * Get QR code image as PNG from the QR code text generated by dc_get_securejoin_qr().
+ * Like dc_get_securejoin_qr_svg(), the image contains the chat or profile avatar in the middle,
+ * or a dot in the chat or profile color if there is no avatar.
+ * Unlike the SVG, there is no description and no footer logo.
+ *
+ * @memberof dc_context_t
+ * @param context The context object.
+ * @param chat_id group-chat-id for secure-join or 0 for setup-contact,
+ *     see dc_get_securejoin_qr() for details.
+ * @param ret_bytes A pointer to a size_t. The size of the PNG will be written here.
+ * @return PNG-Image with the QR code.
+ *     Must be released using dc_str_unref() after usage.
+ *     NULL on errors.
+ */
+char*           dc_get_securejoin_qr_png         (dc_context_t* context, uint32_t chat_id, size_t* ret_bytes);
+
 /**
  * Continue a Setup-Contact or Verified-Group-Invite protocol
  * started on another device with dc_get_securejoin_qr().
@@ -3087,6 +3367,28 @@ uint32_t       dc_accounts_migrate_account      (dc_accounts_t* accounts, const
 int            dc_accounts_remove_account       (dc_accounts_t* accounts, uint32_t account_id);
 
 
+/**
+ * Destroy an account irrecoverably and remove it from the account manager.
+ *
+ * Other than dc_accounts_remove_account(),
+ * the database-file and all blobs are overwritten before they are deleted.
+ * Optionally, all messages on the server are deleted
+ * and all folders are unsubscribed;
+ * this is only supported for chatmail accounts.
+ *
+ * While the account is destroyed, #DC_EVENT_ACCOUNT_DESTROY_PROGRESS events are emitted.
+ * If destroying the account fails, the account is kept in the account manager,
+ * but must not be used anymore.
+ *
+ * @memberof dc_accounts_t
+ * @param accounts The account manager as created by dc_accounts_new().
+ * @param account_id The account ID as returned e.g. by dc_accounts_add_account().
+ * @param delete_server_messages 1=delete all messages on the server, 0=keep them.
+ * @return 1=success, 0=error
+ */
+int            dc_accounts_destroy_account      (dc_accounts_t* accounts, uint32_t account_id, int delete_server_messages);
+
+
 /**
  * List all accounts.
  *
@@ -4383,6 +4685,21 @@ char*           dc_msg_get_summarytext        (const dc_msg_t* msg, int approx_c
 char*           dc_msg_get_override_sender_name(const dc_msg_t* msg);
 
 
+/**
+ * Get the alternative text describing the attached file,
+ * as set by the sender using dc_msg_set_alt_text().
+ *
+ * UIs should pass the text to screen readers
+ * and may show it e.g. when an image cannot be displayed.
+ *
+ * @memberof dc_msg_t
+ * @param msg The message object.
+ * @return The alternative text or NULL if there is none.
+ *     The returned string must be released using dc_str_unref().
+ */
+char*           dc_msg_get_alt_text           (const dc_msg_t* msg);
+
+
 
 /**
  * Check if a message has a deviating timestamp.
@@ -4445,6 +4762,34 @@ int             dc_msg_is_sent                (const dc_msg_t* msg);
 int             dc_msg_is_forwarded           (const dc_msg_t* msg);
 
 
+/**
+ * Check if the message was deleted for everyone by its sender,
+ * see dc_delete_msgs_for_all().
+ *
+ * The text and the attachment of such messages are removed,
+ * UIs typically show a placeholder as "Message deleted" instead.
+ *
+ * @memberof dc_msg_t
+ * @param msg The message object.
+ * @return 1=message was deleted for everyone, 0=message was not deleted.
+ */
+int             dc_msg_is_retracted           (const dc_msg_t* msg);
+
+
+/**
+ * Check if the text of the message was edited by its sender,
+ * see dc_send_edit().
+ *
+ * UIs typically show a little "edited" label next to such messages.
+ * The previous texts can be retrieved with dc_get_edit_history_json().
+ *
+ * @memberof dc_msg_t
+ * @param msg The message object.
+ * @return 1=message was edited, 0=message was not edited.
+ */
+int             dc_msg_is_edited              (const dc_msg_t* msg);
+
+
 /**
  * Check if the message is an informational message, created by the
  * device or by another users. Such messages are not "typed" by the user but
@@ -4479,6 +4824,9 @@ int             dc_msg_is_info                (const dc_msg_t* msg);
  * - DC_INFO_INVALID_UNENCRYPTED_MAIL (13) - Info-message for "Provider requires end-to-end encryption which is not setup yet",
  *   the UI should change the corresponding string using #DC_STR_INVALID_UNENCRYPTED_MAIL
  *   and also offer a way to fix the encryption, eg. by a button offering a QR scan
+ * - DC_INFO_MESSAGE_PINNED (50) - Info-message for "Message pinned",
+ *   the pinned messages of the chat can be listed with dc_get_pinned_msgs()
+ * - DC_INFO_MESSAGE_UNPINNED (51) - Info-message for "Message unpinned"
  *
  * Even when you display an icon,
  * you should still display the text of the informational message using dc_msg_get_text()
@@ -4507,6 +4855,8 @@ int             dc_msg_get_info_type          (const dc_msg_t* msg);
 #define         DC_INFO_PROTECTION_DISABLED       12
 #define         DC_INFO_INVALID_UNENCRYPTED_MAIL  13
 #define         DC_INFO_WEBXDC_INFO_MESSAGE       32
+#define         DC_INFO_MESSAGE_PINNED            50
+#define         DC_INFO_MESSAGE_UNPINNED          51
 
 /**
  * Check if a message is still in creation. A message is in creation between
@@ -4749,6 +5099,20 @@ void            dc_msg_set_subject            (dc_msg_t* msg, const char* subjec
 void            dc_msg_set_override_sender_name(dc_msg_t* msg, const char* name);
 
 
+/**
+ * Set the alternative text describing the attached file,
+ * e.g. the content of an image for users of screen readers.
+ *
+ * The text is sent in the `Content-Description` header of the attachment.
+ * Line breaks are replaced by spaces.
+ *
+ * @memberof dc_msg_t
+ * @param msg The message object.
+ * @param text The alternative text or NULL to remove it.
+ */
+void            dc_msg_set_alt_text           (dc_msg_t* msg, const char* text);
+
+
 /**
  * Set the file associated with a message object.
  * This does not alter any information in the database
@@ -4906,6 +5270,47 @@ dc_msg_t*       dc_msg_get_quoted_msg         (const dc_msg_t* msg);
 dc_msg_t*       dc_msg_get_parent             (const dc_msg_t* msg);
 
 
+/**
+ * Set the contacts mentioned in the message.
+ *
+ * Must be called after setting the text with dc_msg_set_text().
+ * For each contact, the first occurrence of `@` followed by the display name of the contact
+ * is highlighted as the mention by the receiving UIs.
+ * Mentioned contacts are notified even if they muted the chat.
+ *
+ * @memberof dc_msg_t
+ * @param msg The message object.
+ * @param contact_ids An array of uint32_t containing the contact IDs to mention.
+ * @param contact_cnt The number of contact IDs in the array.
+ *     0 removes all mentions.
+ */
+void            dc_msg_set_mentions           (dc_msg_t* msg, const uint32_t* contact_ids, int contact_cnt);
+
+
+/**
+ * Get the contacts mentioned in the message.
+ *
+ * @memberof dc_msg_t
+ * @param msg The message object.
+ * @return An array of contact IDs.
+ *     Must be freed using dc_array_unref() when no longer used.
+ */
+dc_array_t*     dc_msg_get_mentions           (const dc_msg_t* msg);
+
+
+/**
+ * Check if the message mentions the user.
+ *
+ * UIs may use this to highlight the message
+ * and to notify about it even if the chat is muted.
+ *
+ * @memberof dc_msg_t
+ * @param msg The message object.
+ * @return 1=the message mentions the user, 0=the message does not mention the user.
+ */
+int             dc_msg_is_mentioning_self     (const dc_msg_t* msg);
+
+
 /**
  * Force the message to be sent in plain text.
  *
@@ -5161,6 +5566,23 @@ int             dc_contact_is_verified       (dc_contact_t* contact);
 int             dc_contact_is_bot            (dc_contact_t* contact);
 
 
+/**
+ * Get the commands advertised by a bot contact.
+ * UIs may use the commands to offer autocompletion
+ * when the user starts typing `/` in a chat with the bot.
+ *
+ * @memberof dc_contact_t
+ * @param contact The contact object.
+ * @return JSON array of objects with the fields
+ *     `name` (command name without the leading `/`),
+ *     `params` (optional description of the parameters, e.g. `<city>`)
+ *     and `description` (optional short description of the command).
+ *     The array is empty if the contact is not a bot or did not advertise any commands.
+ *     Must be released using dc_str_unref() after usage.
+ */
+char*           dc_contact_get_bot_commands  (dc_contact_t* contact);
+
+
 /**
  * Return the contact ID that verified a contact.
  *
@@ -6020,6 +6442,20 @@ void dc_event_unref(dc_event_t* event);
 #define DC_EVENT_ERROR                    400
 
 
+/**
+ * An error occurred repeatedly.
+ *
+ * Identical errors of background tasks are reported as #DC_EVENT_ERROR only once per minute,
+ * the repetitions are summarized by this event.
+ * It should be handled like #DC_EVENT_ERROR,
+ * e.g. by updating a bubble showing the error.
+ *
+ * @param data1 (int) Number of occurrences since the error was last reported.
+ * @param data2 (char*) Error string, always set, never NULL.
+ */
+#define DC_EVENT_ERROR_REPEATED           401
+
+
 /**
  * An action cannot be performed because the user is not in the group.
  * Reported e.g. after a call to
@@ -6154,6 +6590,18 @@ void dc_event_unref(dc_event_t* event);
  */
 #define DC_EVENT_CHAT_EPHEMERAL_TIMER_MODIFIED 2021
 
+/**
+ * Chat protection status changed,
+ * e.g. a 1:1 chat became protected after a successful Secure-Join
+ * or the protection was broken by a message from an unverified key,
+ * see dc_chat_is_protected() and dc_chat_is_protection_broken().
+ *
+ * @param data1 (int) chat_id
+ * @param data2 (int) The new protection status:
+ *     0=unprotected, 1=protected, 3=protection broken.
+ */
+#define DC_EVENT_CHAT_PROTECTION_CHANGED 2022
+
 
 /**
  * Contact(s) created, renamed, verified, blocked or deleted.
@@ -6164,6 +6612,19 @@ void dc_event_unref(dc_event_t* event);
 #define DC_EVENT_CONTACTS_CHANGED         2030
 
 
+/**
+ * A key of a contact was discovered outside of Autocrypt headers,
+ * e.g. in the Web Key Directory of the contact's domain
+ * if the `wkd_lookup` option is enabled.
+ *
+ * The key is used provisionally until the contact sends a key via Autocrypt.
+ *
+ * @param data1 (int) contact_id
+ * @param data2 (char*) Where the key was discovered, e.g. the URL of the Web Key Directory.
+ */
+#define DC_EVENT_CONTACT_KEY_DISCOVERED   2032
+
+
 
 /**
  * Location of one or more contact has changed.
@@ -6207,6 +6668,26 @@ void dc_event_unref(dc_event_t* event);
 #define DC_EVENT_IMEX_FILE_WRITTEN        2052
 
 
+/**
+ * Inform about the key rotation progress started by dc_rotate_key().
+ *
+ * @param data1 (int) 0=error, 1-999=progress in permille, 1000=success and done
+ * @param data2 (int) Once the rotation is done, the number of contacts that are known
+ *     to still use the old key, 0 otherwise.
+ */
+#define DC_EVENT_KEY_ROTATION_PROGRESS    2055
+
+
+/**
+ * Inform about the progress of destroying an account
+ * started by dc_accounts_destroy_account().
+ *
+ * @param data1 (int) 0=error, 1-999=progress in permille, 1000=success and done
+ * @param data2 0
+ */
+#define DC_EVENT_ACCOUNT_DESTROY_PROGRESS 2056
+
+
 /**
  * Progress information of a secure-join handshake from the view of the inviter
  * (Alice, the person who shows the QR code).
@@ -6216,6 +6697,7 @@ void dc_event_unref(dc_event_t* event);
  *
  * @param data1 (int) The ID of the contact that wants to join.
  * @param data2 (int) The progress as:
+ *     0=Error, followed by #DC_EVENT_SECUREJOIN_FAILED with the reason.
  *     300=vg-/vc-request received, typically shown as "bob@addr joins".
  *     600=vg-/vc-request-with-auth received, vg-member-added/vc-contact-confirm sent, typically shown as "bob@addr verified".
  *     800=contact added to chat, shown as "bob@addr securely joined GROUP". Only for the verified-group-protocol.
@@ -6233,6 +6715,7 @@ void dc_event_unref(dc_event_t* event);
  *
  * @param data1 (int) The ID of the inviting contact.
  * @param data2 (int) The progress as:
+ *     0=Error, followed by #DC_EVENT_SECUREJOIN_FAILED with the reason.
  *     400=vg-/vc-request-with-auth sent, typically shown as "alice@addr verified, introducing myself."
  *     (Bob has verified alice and waits until Alice does the same for him)
  *     1000=vg-member-added/vc-contact-confirm received
@@ -6240,6 +6723,26 @@ void dc_event_unref(dc_event_t* event);
 #define DC_EVENT_SECUREJOIN_JOINER_PROGRESS       2061
 
 
+/**
+ * A secure-join handshake failed, either on the inviter or on the joiner side.
+ *
+ * Except for timeouts, this follows #DC_EVENT_SECUREJOIN_INVITER_PROGRESS
+ * or #DC_EVENT_SECUREJOIN_JOINER_PROGRESS with progress 0.
+ *
+ * @param data1 (int) The ID of the contact on the other side of the handshake.
+ * @param data2 (int) The reason as:
+ *     0=other error.
+ *     1=the inviter did not reply in time, the handshake may still complete later.
+ *     2=the key of the peer does not match the fingerprint from the QR code.
+ *     3=the scanned invite was withdrawn by the inviter.
+ *     4=a handshake message was not encrypted and signed as required.
+ *     5=the auth code is missing or not valid.
+ *     6=the handshake was aborted because another QR code was scanned.
+ *     To show the reason to the user, use the stock string `DC_STR_SECUREJOIN_FAILURE_OTHER + data2`.
+ */
+#define DC_EVENT_SECUREJOIN_FAILED                2062
+
+
 /**
  * The connectivity to the server changed.
  * This means that you should refresh the connectivity view
@@ -6252,6 +6755,17 @@ void dc_event_unref(dc_event_t* event);
 #define DC_EVENT_CONNECTIVITY_CHANGED             2100
 
 
+/**
+ * Connecting through the active proxy failed
+ * and the next proxy of the `proxy_urls` list is used now.
+ *
+ * @param data1 (int) Port of the new proxy.
+ * @param data2 (char*) Hostname of the new proxy.
+ *     Must not be unref'd or modified and is valid only until the callback returns.
+ */
+#define DC_EVENT_PROXY_CHANGED                    2101
+
+
 /**
  * The user's avatar changed.
  * You can get the new avatar file with `dc_get_config(context, "selfavatar")`.
@@ -6265,6 +6779,9 @@ void dc_event_unref(dc_event_t* event);
  * which might not be good for privacy. You can get the new value with
  * `dc_get_config(context, data2)`.
  *
+ * This event is also emitted when an ui-specific value with a `ui.` prefix changes.
+ * Values with a `ui.sync.` prefix are synced to other devices, but not interpreted by the core.
+ *
  * @param data1 0
  * @param data2 (char*) Configuration key.
  */
@@ -6349,7 +6866,7 @@ void dc_event_unref(dc_event_t* event);
 
 
 #define DC_EVENT_DATA1_IS_STRING(e)  0    // not used anymore 
-#define DC_EVENT_DATA2_IS_STRING(e)  ((e)==DC_EVENT_CONFIGURE_PROGRESS || (e)==DC_EVENT_IMEX_FILE_WRITTEN || ((e)>=100 && (e)<=499))
+#define DC_EVENT_DATA2_IS_STRING(e)  ((e)==DC_EVENT_CONFIGURE_PROGRESS || (e)==DC_EVENT_IMEX_FILE_WRITTEN || (e)==DC_EVENT_PROXY_CHANGED || ((e)>=100 && (e)<=499))
 
 
 /*
@@ -6367,6 +6884,14 @@ void dc_event_unref(dc_event_t* event);
 #define DC_MEDIA_QUALITY_WORSE    1
 
 
+/*
+ * Values for dc_get|set_config("imap_id")
+ */
+#define DC_IMAP_ID_NAME      0
+#define DC_IMAP_ID_ANONYMOUS 1
+#define DC_IMAP_ID_FULL      2
+
+
 /*
  * Values for dc_get|set_config("key_gen_type")
  */
@@ -7378,9 +7903,108 @@ void dc_event_unref(dc_event_t* event);
 /// Used as info message.
 #define DC_STR_SECUREJOIN_WAIT_TIMEOUT 191
 
+/// "⚠️ Unauthenticated messages from %1$s"
+///
+/// `%1$s` will be replaced by the address of the contact.
+///
+/// Used as name of the chat with possibly forged messages.
+#define DC_STR_SPOOFING_QUARANTINE 192
+
+/// "⚠️ This message claims to be from %1$s, but it is not signed with their key and may be forged."
+///
+/// `%1$s` will be replaced by the address of the contact.
+///
+/// Used as error of possibly forged messages.
+#define DC_STR_SPOOFED_MSG 193
+
+/// "The invitation of %1$s was withdrawn, ask them for a new one."
+///
+/// `%1$s` will be replaced by name and address of the inviter.
+///
+/// Used as info message when scanning a withdrawn QR code.
+#define DC_STR_SECUREJOIN_INVITE_WITHDRAWN 194
+
+/// "⚠️ Your device is running out of storage, only %1$s left. Downloading attachments is paused until you free up some space."
+///
+/// `%1$s` will be replaced by the human-readable free space, e.g. `80 MiB`.
+///
+/// Used as device message when the disk space gets low.
+#define DC_STR_LOW_DISK_SPACE 195
+
+/// "⚠️ Messages from %1$s are sent via the servers of %2$s, which usually cannot sign them for this domain. Recipients' providers may reject them or sort them into spam. If your messages do not arrive, ask your email provider how to send from this address."
+///
+/// `%1$s` will be replaced by the configured email address.
+/// `%2$s` will be replaced by the hostname of the SMTP server, e.g. `smtp.gmail.com`.
+///
+/// Used as device message after configuration
+/// if the SMTP server belongs to a provider not responsible for the address.
+#define DC_STR_SENDING_DOMAIN_NOT_ALIGNED 196
+
+/// "🚮 This message was deleted."
+///
+/// Used in summaries of messages deleted for everyone by their sender.
+#define DC_STR_MSG_RETRACTED 197
+
+/// "You pinned a message."
+///
+/// Used in status messages.
+#define DC_STR_MSG_YOU_PINNED_MSG 198
+
+/// "Message pinned by %1$s."
+///
+/// Used in status messages.
+/// - %1$s will be replaced by the name/address of the contact who pinned the message.
+#define DC_STR_MSG_PINNED_BY 199
+
 /// "Contact". Deprecated, currently unused.
 #define DC_STR_CONTACT 200
 
+/// "You unpinned a message."
+///
+/// Used in status messages.
+#define DC_STR_MSG_YOU_UNPINNED_MSG 201
+
+/// "Message unpinned by %1$s."
+///
+/// Used in status messages.
+/// - %1$s will be replaced by the name/address of the contact who unpinned the message.
+#define DC_STR_MSG_UNPINNED_BY 202
+
+/// "An unexpected error occurred."
+///
+/// Used to describe reason 0 of #DC_EVENT_SECUREJOIN_FAILED.
+#define DC_STR_SECUREJOIN_FAILURE_OTHER 203
+
+/// "The other side did not reply in time."
+///
+/// Used to describe reason 1 of #DC_EVENT_SECUREJOIN_FAILED.
+#define DC_STR_SECUREJOIN_FAILURE_TIMEOUT 204
+
+/// "The key of the other side does not match the QR code."
+///
+/// Used to describe reason 2 of #DC_EVENT_SECUREJOIN_FAILED.
+#define DC_STR_SECUREJOIN_FAILURE_FINGERPRINT_MISMATCH 205
+
+/// "The invitation was withdrawn."
+///
+/// Used to describe reason 3 of #DC_EVENT_SECUREJOIN_FAILED.
+#define DC_STR_SECUREJOIN_FAILURE_INVITE_WITHDRAWN 206
+
+/// "A message of the other side was not encrypted and signed."
+///
+/// Used to describe reason 4 of #DC_EVENT_SECUREJOIN_FAILED.
+#define DC_STR_SECUREJOIN_FAILURE_NOT_ENCRYPTED 207
+
+/// "The invitation is not valid."
+///
+/// Used to describe reason 5 of #DC_EVENT_SECUREJOIN_FAILED.
+#define DC_STR_SECUREJOIN_FAILURE_AUTH_INVALID 208
+
+/// "Another QR code was scanned."
+///
+/// Used to describe reason 6 of #DC_EVENT_SECUREJOIN_FAILED.
+#define DC_STR_SECUREJOIN_FAILURE_ABORTED 209
+
 /**
  * @}
  */
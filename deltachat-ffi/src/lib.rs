@@ -30,7 +30,9 @@
 use deltachat::imex::BackupProvider;
 use deltachat::key::preconfigure_keypair;
 use deltachat::message::MsgId;
-use deltachat::qr_code_generator::{generate_backup_qr, get_securejoin_qr_svg};
+use deltachat::qr_code_generator::{
+    generate_backup_qr, get_securejoin_qr_png, get_securejoin_qr_svg,
+};
 use deltachat::stock_str::StockMessage;
 use deltachat::webxdc::StatusUpdateSerial;
 use deltachat::*;
@@ -469,6 +471,23 @@ fn spawn_configure(ctx: Context) {
     spawn_configure(ctx.clone());
 }
 
+#[no_mangle]
+pub unsafe extern "C" fn dc_configure_from_qr(context: *mut dc_context_t, qr: *const libc::c_char) {
+    if context.is_null() || qr.is_null() {
+        eprintln!("ignoring careless call to dc_configure_from_qr()");
+        return;
+    }
+
+    let ctx = (*context).clone();
+    let qr = to_string_lossy(qr);
+    spawn(async move {
+        ctx.configure_from_qr(&qr)
+            .await
+            .context("Configure from QR code failed")
+            .log_err(&ctx)
+    });
+}
+
 #[no_mangle]
 pub unsafe extern "C" fn dc_is_configured(context: *mut dc_context_t) -> libc::c_int {
     if context.is_null() {
@@ -538,6 +557,7 @@ fn spawn_configure(ctx: Context) {
         EventType::DeletedBlobFile(_) => 151,
         EventType::Warning(_) => 300,
         EventType::Error(_) => 400,
+        EventType::ErrorRepeated { .. } => 401,
         EventType::ErrorSelfNotInGroup(_) => 410,
         EventType::MsgsChanged { .. } => 2000,
         EventType::ReactionsChanged { .. } => 2001,
@@ -550,14 +570,20 @@ fn spawn_configure(ctx: Context) {
         EventType::MsgDeleted { .. } => 2016,
         EventType::ChatModified(_) => 2020,
         EventType::ChatEphemeralTimerModified { .. } => 2021,
+        EventType::ChatProtectionChanged { .. } => 2022,
         EventType::ContactsChanged(_) => 2030,
+        EventType::ContactKeyDiscovered { .. } => 2032,
         EventType::LocationChanged(_) => 2035,
         EventType::ConfigureProgress { .. } => 2041,
         EventType::ImexProgress(_) => 2051,
         EventType::ImexFileWritten(_) => 2052,
+        EventType::KeyRotationProgress { .. } => 2055,
+        EventType::AccountDestroyProgress(_) => 2056,
         EventType::SecurejoinInviterProgress { .. } => 2060,
         EventType::SecurejoinJoinerProgress { .. } => 2061,
+        EventType::SecurejoinFailed { .. } => 2062,
         EventType::ConnectivityChanged => 2100,
+        EventType::ProxyChanged { .. } => 2101,
         EventType::SelfavatarChanged => 2110,
         EventType::ConfigSynced { .. } => 2111,
         EventType::WebxdcStatusUpdate { .. } => 2120,
@@ -597,6 +623,7 @@ fn spawn_configure(ctx: Context) {
         | EventType::ErrorSelfNotInGroup(_)
         | EventType::AccountsBackgroundFetchDone => 0,
         EventType::ChatlistChanged => 0,
+        EventType::ErrorRepeated { count, .. } => *count as libc::c_int,
         EventType::MsgsChanged { chat_id, .. }
         | EventType::ReactionsChanged { chat_id, .. }
         | EventType::IncomingMsg { chat_id, .. }
@@ -606,19 +633,22 @@ fn spawn_configure(ctx: Context) {
         | EventType::MsgRead { chat_id, .. }
         | EventType::MsgDeleted { chat_id, .. }
         | EventType::ChatModified(chat_id)
-        | EventType::ChatEphemeralTimerModified { chat_id, .. } => chat_id.to_u32() as libc::c_int,
+        | EventType::ChatEphemeralTimerModified { chat_id, .. }
+        | EventType::ChatProtectionChanged { chat_id, .. } => chat_id.to_u32() as libc::c_int,
         EventType::ContactsChanged(id) | EventType::LocationChanged(id) => {
             let id = id.unwrap_or_default();
             id.to_u32() as libc::c_int
         }
-        EventType::ConfigureProgress { progress, .. } | EventType::ImexProgress(progress) => {
-            *progress as libc::c_int
-        }
+        EventType::ConfigureProgress { progress, .. }
+        | EventType::ImexProgress(progress)
+        | EventType::AccountDestroyProgress(progress) => *progress as libc::c_int,
         EventType::ImexFileWritten(_) => 0,
+        EventType::ProxyChanged { port, .. } => *port as libc::c_int,
+        EventType::KeyRotationProgress { progress, .. } => *progress as libc::c_int,
         EventType::SecurejoinInviterProgress { contact_id, .. }
-        | EventType::SecurejoinJoinerProgress { contact_id, .. } => {
-            contact_id.to_u32() as libc::c_int
-        }
+        | EventType::SecurejoinJoinerProgress { contact_id, .. }
+        | EventType::SecurejoinFailed { contact_id, .. }
+        | EventType::ContactKeyDiscovered { contact_id, .. } => contact_id.to_u32() as libc::c_int,
         EventType::WebxdcRealtimeData { msg_id, .. }
         | EventType::WebxdcStatusUpdate { msg_id, .. }
         | EventType::WebxdcInstanceDeleted { msg_id, .. } => msg_id.to_u32() as libc::c_int,
@@ -650,14 +680,18 @@ fn spawn_configure(ctx: Context) {
         | EventType::DeletedBlobFile(_)
         | EventType::Warning(_)
         | EventType::Error(_)
+        | EventType::ErrorRepeated { .. }
         | EventType::ErrorSelfNotInGroup(_)
         | EventType::ContactsChanged(_)
+        | EventType::ContactKeyDiscovered { .. }
         | EventType::LocationChanged(_)
         | EventType::ConfigureProgress { .. }
         | EventType::ImexProgress(_)
+        | EventType::AccountDestroyProgress(_)
         | EventType::ImexFileWritten(_)
         | EventType::MsgsNoticed(_)
         | EventType::ConnectivityChanged
+        | EventType::ProxyChanged { .. }
         | EventType::WebxdcInstanceDeleted { .. }
         | EventType::IncomingMsgBunch { .. }
         | EventType::SelfavatarChanged
@@ -676,7 +710,13 @@ fn spawn_configure(ctx: Context) {
         | EventType::MsgDeleted { msg_id, .. } => msg_id.to_u32() as libc::c_int,
         EventType::SecurejoinInviterProgress { progress, .. }
         | EventType::SecurejoinJoinerProgress { progress, .. } => *progress as libc::c_int,
+        EventType::SecurejoinFailed { reason, .. } => *reason as libc::c_int,
         EventType::ChatEphemeralTimerModified { timer, .. } => timer.to_u32() as libc::c_int,
+        EventType::ChatProtectionChanged { protection, .. } => *protection as libc::c_int,
+        EventType::KeyRotationProgress {
+            contacts_with_old_key,
+            ..
+        } => *contacts_with_old_key as libc::c_int,
         EventType::WebxdcStatusUpdate {
             status_update_serial,
             ..
@@ -705,6 +745,7 @@ fn spawn_configure(ctx: Context) {
         | EventType::DeletedBlobFile(msg)
         | EventType::Warning(msg)
         | EventType::Error(msg)
+        | EventType::ErrorRepeated { msg, .. }
         | EventType::ErrorSelfNotInGroup(msg) => {
             let data2 = msg.to_c_string().unwrap_or_default();
             data2.into_raw()
@@ -722,14 +763,18 @@ fn spawn_configure(ctx: Context) {
         | EventType::ContactsChanged(_)
         | EventType::LocationChanged(_)
         | EventType::ImexProgress(_)
+        | EventType::AccountDestroyProgress(_)
+        | EventType::KeyRotationProgress { .. }
         | EventType::SecurejoinInviterProgress { .. }
         | EventType::SecurejoinJoinerProgress { .. }
+        | EventType::SecurejoinFailed { .. }
         | EventType::ConnectivityChanged
         | EventType::SelfavatarChanged
         | EventType::WebxdcStatusUpdate { .. }
         | EventType::WebxdcInstanceDeleted { .. }
         | EventType::AccountsBackgroundFetchDone
         | EventType::ChatEphemeralTimerModified { .. }
+        | EventType::ChatProtectionChanged { .. }
         | EventType::IncomingMsgBunch { .. }
         | EventType::ChatlistItemChanged { .. }
         | EventType::ChatlistChanged
@@ -745,8 +790,16 @@ fn spawn_configure(ctx: Context) {
             let data2 = file.to_c_string().unwrap_or_default();
             data2.into_raw()
         }
+        EventType::ProxyChanged { host, .. } => {
+            let data2 = host.to_c_string().unwrap_or_default();
+            data2.into_raw()
+        }
+        EventType::ContactKeyDiscovered { source, .. } => {
+            let data2 = source.to_c_string().unwrap_or_default();
+            data2.into_raw()
+        }
         EventType::ConfigSynced { key } => {
-            let data2 = key.to_string().to_c_string().unwrap_or_default();
+            let data2 = key.to_c_string().unwrap_or_default();
             data2.into_raw()
         }
         EventType::WebxdcRealtimeData { data, .. } => {
@@ -993,6 +1046,27 @@ fn spawn_configure(ctx: Context) {
     .to_u32()
 }
 
+#[no_mangle]
+pub unsafe extern "C" fn dc_send_msg_deferred(
+    context: *mut dc_context_t,
+    chat_id: u32,
+    msg: *mut dc_msg_t,
+) -> u32 {
+    if context.is_null() || msg.is_null() {
+        eprintln!("ignoring careless call to dc_send_msg_deferred()");
+        return 0;
+    }
+    let ctx = &mut *context;
+    let ffi_msg = &mut *msg;
+
+    block_on(async move {
+        chat::send_msg_deferred(ctx, ChatId::new(chat_id), &mut ffi_msg.message)
+            .await
+            .unwrap_or_log_default(ctx, "Failed to send message")
+    })
+    .to_u32()
+}
+
 #[no_mangle]
 pub unsafe extern "C" fn dc_send_text_msg(
     context: *mut dc_context_t,
@@ -1014,6 +1088,27 @@ fn spawn_configure(ctx: Context) {
     })
 }
 
+#[no_mangle]
+pub unsafe extern "C" fn dc_send_edit(
+    context: *mut dc_context_t,
+    msg_id: u32,
+    new_text: *const libc::c_char,
+) -> u32 {
+    if context.is_null() || new_text.is_null() {
+        eprintln!("ignoring careless call to dc_send_edit()");
+        return 0;
+    }
+    let ctx = &*context;
+    let new_text = to_string_lossy(new_text);
+
+    block_on(async move {
+        chat::send_edit(ctx, MsgId::new(msg_id), new_text)
+            .await
+            .map(|msg_id| msg_id.to_u32())
+            .unwrap_or_log_default(ctx, "Failed to send edit")
+    })
+}
+
 #[no_mangle]
 pub unsafe extern "C" fn dc_send_videochat_invitation(
     context: *mut dc_context_t,
@@ -1446,6 +1541,53 @@ fn from_prim<S, T>(s: S) -> Option<T>
     })
 }
 
+#[no_mangle]
+pub unsafe extern "C" fn dc_pin_msg(context: *mut dc_context_t, msg_id: u32) -> libc::c_int {
+    if context.is_null() {
+        eprintln!("ignoring careless call to dc_pin_msg()");
+        return 0;
+    }
+    let ctx = &*context;
+    block_on(chat::pin_message(ctx, MsgId::new(msg_id)))
+        .context("Failed to pin message")
+        .log_err(ctx)
+        .is_ok() as libc::c_int
+}
+
+#[no_mangle]
+pub unsafe extern "C" fn dc_unpin_msg(context: *mut dc_context_t, msg_id: u32) -> libc::c_int {
+    if context.is_null() {
+        eprintln!("ignoring careless call to dc_unpin_msg()");
+        return 0;
+    }
+    let ctx = &*context;
+    block_on(chat::unpin_message(ctx, MsgId::new(msg_id)))
+        .context("Failed to unpin message")
+        .log_err(ctx)
+        .is_ok() as libc::c_int
+}
+
+#[no_mangle]
+pub unsafe extern "C" fn dc_get_pinned_msgs(
+    context: *mut dc_context_t,
+    chat_id: u32,
+) -> *mut dc_array::dc_array_t {
+    if context.is_null() {
+        eprintln!("ignoring careless call to dc_get_pinned_msgs()");
+        return ptr::null_mut();
+    }
+    let ctx = &*context;
+
+    block_on(async move {
+        Box::into_raw(Box::new(
+            chat::get_pinned_messages(ctx, ChatId::new(chat_id))
+                .await
+                .unwrap_or_log_default(ctx, "Failed get_pinned_messages")
+                .into(),
+        ))
+    })
+}
+
 #[no_mangle]
 #[allow(deprecated)]
 pub unsafe extern "C" fn dc_get_next_media(
@@ -1630,6 +1772,23 @@ fn from_prim<S, T>(s: S) -> Option<T>
     })
 }
 
+#[no_mangle]
+pub unsafe extern "C" fn dc_get_msg_search_snippet(
+    context: *mut dc_context_t,
+    msg_id: u32,
+    query: *const libc::c_char,
+) -> *mut libc::c_char {
+    if context.is_null() || query.is_null() {
+        eprintln!("ignoring careless call to dc_get_msg_search_snippet()");
+        return ptr::null_mut();
+    }
+    let ctx = &*context;
+
+    block_on(ctx.get_msg_search_snippet(MsgId::new(msg_id), &to_string_lossy(query)))
+        .unwrap_or_log_default(ctx, "Failed get_msg_search_snippet")
+        .strdup()
+}
+
 #[no_mangle]
 pub unsafe extern "C" fn dc_get_chat(context: *mut dc_context_t, chat_id: u32) -> *mut dc_chat_t {
     if context.is_null() {
@@ -1954,6 +2113,28 @@ fn from_prim<S, T>(s: S) -> Option<T>
     })
 }
 
+#[no_mangle]
+pub unsafe extern "C" fn dc_get_edit_history_json(
+    context: *mut dc_context_t,
+    msg_id: u32,
+) -> *mut libc::c_char {
+    if context.is_null() {
+        eprintln!("ignoring careless call to dc_get_edit_history_json()");
+        return "".strdup();
+    }
+    let ctx = &*context;
+
+    block_on(async move {
+        let history = MsgId::new(msg_id)
+            .get_edit_history(ctx)
+            .await
+            .unwrap_or_log_default(ctx, "Failed to get edit history");
+        serde_json::to_string(&history)
+            .unwrap_or_log_default(ctx, "Failed to serialise edit history")
+            .strdup()
+    })
+}
+
 #[no_mangle]
 pub unsafe extern "C" fn dc_delete_msgs(
     context: *mut dc_context_t,
@@ -1973,6 +2154,25 @@ fn from_prim<S, T>(s: S) -> Option<T>
         .ok();
 }
 
+#[no_mangle]
+pub unsafe extern "C" fn dc_delete_msgs_for_all(
+    context: *mut dc_context_t,
+    msg_ids: *const u32,
+    msg_cnt: libc::c_int,
+) -> libc::c_int {
+    if context.is_null() || msg_ids.is_null() || msg_cnt <= 0 {
+        eprintln!("ignoring careless call to dc_delete_msgs_for_all()");
+        return 0;
+    }
+    let ctx = &*context;
+    let msg_ids = convert_and_prune_message_ids(msg_ids, msg_cnt);
+
+    block_on(message::delete_for_all(ctx, &msg_ids))
+        .context("failed dc_delete_msgs_for_all() call")
+        .log_err(ctx)
+        .is_ok() as libc::c_int
+}
+
 #[no_mangle]
 pub unsafe extern "C" fn dc_forward_msgs(
     context: *mut dc_context_t,
@@ -2387,6 +2587,39 @@ fn spawn_imex(ctx: Context, what: imex::ImexMode, param1: String, passphrase: Op
     })
 }
 
+#[no_mangle]
+pub unsafe extern "C" fn dc_rotate_key(context: *mut dc_context_t) -> *mut libc::c_char {
+    if context.is_null() {
+        eprintln!("ignoring careless call to dc_rotate_key()");
+        return ptr::null_mut();
+    }
+    let ctx = &*context;
+
+    block_on(async move {
+        match ctx.rotate_key().await {
+            Ok(fingerprint) => fingerprint.hex().strdup(),
+            Err(err) => {
+                error!(ctx, "dc_rotate_key(): {err:#}");
+                ptr::null_mut()
+            }
+        }
+    })
+}
+
+#[no_mangle]
+pub unsafe extern "C" fn dc_renew_encryption_subkey(context: *mut dc_context_t) -> libc::c_int {
+    if context.is_null() {
+        eprintln!("ignoring careless call to dc_renew_encryption_subkey()");
+        return 0;
+    }
+    let ctx = &*context;
+
+    block_on(ctx.renew_encryption_subkey())
+        .context("dc_renew_encryption_subkey() failed")
+        .log_err(ctx)
+        .is_ok() as libc::c_int
+}
+
 #[no_mangle]
 pub unsafe extern "C" fn dc_continue_key_transfer(
     context: *mut dc_context_t,
@@ -2482,6 +2715,41 @@ fn spawn_imex(ctx: Context, what: imex::ImexMode, param1: String, passphrase: Op
         .strdup()
 }
 
+#[no_mangle]
+pub unsafe extern "C" fn dc_get_securejoin_qr_png(
+    context: *mut dc_context_t,
+    chat_id: u32,
+    ret_bytes: *mut libc::size_t,
+) -> *mut libc::c_char {
+    if context.is_null() || ret_bytes.is_null() {
+        eprintln!("ignoring careless call to dc_get_securejoin_qr_png()");
+        return ptr::null_mut();
+    }
+    let ctx = &*context;
+    let chat_id = if chat_id == 0 {
+        None
+    } else {
+        Some(ChatId::new(chat_id))
+    };
+
+    match block_on(get_securejoin_qr_png(ctx, chat_id))
+        .context("failed dc_get_securejoin_qr_png() call")
+        .log_err(ctx)
+    {
+        Ok(png) => {
+            let ptr = libc::malloc(png.len());
+            if ptr.is_null() {
+                eprintln!("dc_get_securejoin_qr_png(): failed to allocate memory");
+                return ptr::null_mut();
+            }
+            *ret_bytes = png.len();
+            libc::memcpy(ptr, png.as_ptr() as *mut libc::c_void, png.len());
+            ptr as *mut libc::c_char
+        }
+        Err(_) => ptr::null_mut(),
+    }
+}
+
 #[no_mangle]
 pub unsafe extern "C" fn dc_join_securejoin(
     context: *mut dc_context_t,
@@ -3630,6 +3898,17 @@ pub struct MessageWrapper {
     ffi_msg.message.get_override_sender_name().strdup()
 }
 
+#[no_mangle]
+pub unsafe extern "C" fn dc_msg_get_alt_text(msg: *mut dc_msg_t) -> *mut libc::c_char {
+    if msg.is_null() {
+        eprintln!("ignoring careless call to dc_msg_get_alt_text()");
+        return "".strdup();
+    }
+    let ffi_msg = &mut *msg;
+
+    ffi_msg.message.get_alt_text().strdup()
+}
+
 #[no_mangle]
 pub unsafe extern "C" fn dc_msg_has_deviating_timestamp(msg: *mut dc_msg_t) -> libc::c_int {
     if msg.is_null() {
@@ -3670,6 +3949,26 @@ pub struct MessageWrapper {
     ffi_msg.message.is_forwarded().into()
 }
 
+#[no_mangle]
+pub unsafe extern "C" fn dc_msg_is_retracted(msg: *mut dc_msg_t) -> libc::c_int {
+    if msg.is_null() {
+        eprintln!("ignoring careless call to dc_msg_is_retracted()");
+        return 0;
+    }
+    let ffi_msg = &*msg;
+    ffi_msg.message.is_retracted().into()
+}
+
+#[no_mangle]
+pub unsafe extern "C" fn dc_msg_is_edited(msg: *mut dc_msg_t) -> libc::c_int {
+    if msg.is_null() {
+        eprintln!("ignoring careless call to dc_msg_is_edited()");
+        return 0;
+    }
+    let ffi_msg = &*msg;
+    ffi_msg.message.is_edited().into()
+}
+
 #[no_mangle]
 pub unsafe extern "C" fn dc_msg_is_info(msg: *mut dc_msg_t) -> libc::c_int {
     if msg.is_null() {
@@ -3804,6 +4103,16 @@ pub struct MessageWrapper {
         .set_override_sender_name(to_opt_string_lossy(name))
 }
 
+#[no_mangle]
+pub unsafe extern "C" fn dc_msg_set_alt_text(msg: *mut dc_msg_t, text: *const libc::c_char) {
+    if msg.is_null() {
+        eprintln!("ignoring careless call to dc_msg_set_alt_text()");
+        return;
+    }
+    let ffi_msg = &mut *msg;
+    ffi_msg.message.set_alt_text(to_opt_string_lossy(text))
+}
+
 #[no_mangle]
 pub unsafe extern "C" fn dc_msg_set_file(
     msg: *mut dc_msg_t,
@@ -3986,6 +4295,65 @@ pub struct MessageWrapper {
     }
 }
 
+#[no_mangle]
+pub unsafe extern "C" fn dc_msg_set_mentions(
+    msg: *mut dc_msg_t,
+    contact_ids: *const u32,
+    contact_cnt: libc::c_int,
+) {
+    if msg.is_null() || (contact_ids.is_null() && contact_cnt > 0) || contact_cnt < 0 {
+        eprintln!("ignoring careless call to dc_msg_set_mentions()");
+        return;
+    }
+    let ffi_msg = &mut *msg;
+    let contact_ids: Vec<ContactId> = if contact_cnt == 0 {
+        Vec::new()
+    } else {
+        std::slice::from_raw_parts(contact_ids, contact_cnt as usize)
+            .iter()
+            .map(|id| ContactId::new(*id))
+            .collect()
+    };
+
+    block_on(async move {
+        ffi_msg
+            .message
+            .set_mentions(&*ffi_msg.context, &contact_ids)
+            .await
+            .context("failed to set mentions")
+            .log_err(&*ffi_msg.context)
+            .ok();
+    });
+}
+
+#[no_mangle]
+pub unsafe extern "C" fn dc_msg_get_mentions(msg: *const dc_msg_t) -> *mut dc_array::dc_array_t {
+    if msg.is_null() {
+        eprintln!("ignoring careless call to dc_msg_get_mentions()");
+        return ptr::null_mut();
+    }
+    let ffi_msg = &*msg;
+    let arr = dc_array_t::from(
+        ffi_msg
+            .message
+            .get_mentions()
+            .iter()
+            .map(|mention| mention.contact_id.to_u32())
+            .collect::<Vec<u32>>(),
+    );
+    Box::into_raw(Box::new(arr))
+}
+
+#[no_mangle]
+pub unsafe extern "C" fn dc_msg_is_mentioning_self(msg: *const dc_msg_t) -> libc::c_int {
+    if msg.is_null() {
+        eprintln!("ignoring careless call to dc_msg_is_mentioning_self()");
+        return 0;
+    }
+    let ffi_msg = &*msg;
+    ffi_msg.message.is_mentioning_self() as libc::c_int
+}
+
 #[no_mangle]
 pub unsafe extern "C" fn dc_msg_force_plaintext(msg: *mut dc_msg_t) {
     if msg.is_null() {
@@ -4188,6 +4556,23 @@ pub struct ContactWrapper {
     (*contact).contact.is_bot() as libc::c_int
 }
 
+#[no_mangle]
+pub unsafe extern "C" fn dc_contact_get_bot_commands(
+    contact: *mut dc_contact_t,
+) -> *mut libc::c_char {
+    if contact.is_null() {
+        eprintln!("ignoring careless call to dc_contact_get_bot_commands()");
+        return "".strdup();
+    }
+    let ffi_contact = &*contact;
+    let ctx = &*ffi_contact.context;
+    serde_json::to_string(&ffi_contact.contact.get_bot_commands())
+        .context("failed to serialize bot commands")
+        .log_err(ctx)
+        .unwrap_or_default()
+        .strdup()
+}
+
 #[no_mangle]
 pub unsafe extern "C" fn dc_contact_get_verifier_id(contact: *mut dc_contact_t) -> u32 {
     if contact.is_null() {
@@ -4795,6 +5180,36 @@ fn new(accounts: Accounts) -> Self {
     })
 }
 
+#[no_mangle]
+pub unsafe extern "C" fn dc_accounts_destroy_account(
+    accounts: *mut dc_accounts_t,
+    id: u32,
+    delete_server_messages: libc::c_int,
+) -> libc::c_int {
+    if accounts.is_null() {
+        eprintln!("ignoring careless call to dc_accounts_destroy_account()");
+        return 0;
+    }
+
+    let accounts = &mut *accounts;
+
+    block_on(async move {
+        let mut accounts = accounts.write().await;
+        match accounts
+            .destroy_account(id, delete_server_messages != 0)
+            .await
+        {
+            Ok(()) => 1,
+            Err(err) => {
+                accounts.emit_event(EventType::Error(format!(
+                    "Failed to destroy account: {err:#}"
+                )));
+                0
+            }
+        }
+    })
+}
+
 #[no_mangle]
 pub unsafe extern "C" fn dc_accounts_migrate_account(
     accounts: *mut dc_accounts_t,
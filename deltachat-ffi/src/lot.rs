@@ -53,6 +53,7 @@ pub fn get_text1(&self) -> Option<&str> {
                 Qr::Backup2 { .. } => None,
                 Qr::WebrtcInstance { domain, .. } => Some(domain),
                 Qr::Addr { draft, .. } => draft.as_deref(),
+                Qr::Vcard { addrs, .. } => addrs.first().map(String::as_str),
                 Qr::Url { url } => Some(url),
                 Qr::Text { text } => Some(text),
                 Qr::WithdrawVerifyContact { .. } => None,
@@ -106,6 +107,7 @@ pub fn get_state(&self) -> LotState {
                 Qr::Backup2 { .. } => LotState::QrBackup2,
                 Qr::WebrtcInstance { .. } => LotState::QrWebrtcInstance,
                 Qr::Addr { .. } => LotState::QrAddr,
+                Qr::Vcard { .. } => LotState::QrVcard,
                 Qr::Url { .. } => LotState::QrUrl,
                 Qr::Text { .. } => LotState::QrText,
                 Qr::WithdrawVerifyContact { .. } => LotState::QrWithdrawVerifyContact,
@@ -132,6 +134,7 @@ pub fn get_id(&self) -> u32 {
                 Qr::Backup2 { .. } => Default::default(),
                 Qr::WebrtcInstance { .. } => Default::default(),
                 Qr::Addr { contact_id, .. } => contact_id.to_u32(),
+                Qr::Vcard { .. } => Default::default(),
                 Qr::Url { .. } => Default::default(),
                 Qr::Text { .. } => Default::default(),
                 Qr::WithdrawVerifyContact { contact_id, .. } => contact_id.to_u32(),
@@ -188,6 +191,9 @@ pub enum LotState {
     /// id=contact
     QrAddr = 320,
 
+    /// text1=first email_address
+    QrVcard = 322,
+
     /// text1=text
     QrText = 330,
 
@@ -7,6 +7,7 @@
 
 use anyhow::{anyhow, bail, ensure, Context, Result};
 pub use deltachat::accounts::Accounts;
+use deltachat::accounts::WarmAccountsPolicy;
 use deltachat::chat::{
     self, add_contact_to_chat, forward_msgs, get_chat_media, get_chat_msgs, get_chat_msgs_ex,
     marknoticed_chat, remove_contact_from_chat, Chat, ChatId, ChatItem, MessageListOptions,
@@ -28,9 +29,12 @@
 };
 use deltachat::provider::get_provider_info;
 use deltachat::qr::{self, Qr};
-use deltachat::qr_code_generator::{generate_backup_qr, get_securejoin_qr_svg};
+use deltachat::qr_code_generator::{
+    generate_backup_qr, get_securejoin_qr_png, get_securejoin_qr_svg,
+};
 use deltachat::reaction::{get_msg_reactions, send_reaction};
 use deltachat::securejoin;
+use deltachat::sticker;
 use deltachat::stock_str::StockMessage;
 use deltachat::webxdc::StatusUpdateSerial;
 use deltachat::EventEmitter;
@@ -44,26 +48,36 @@
 pub mod types;
 
 use num_traits::FromPrimitive;
-use types::account::Account;
+use types::account::{Account, RecoveryCode};
+use types::backup::BackupReport;
 use types::chat::FullChat;
-use types::contact::{ContactObject, VcardContact};
-use types::events::Event;
+use types::config::ConfigProblem;
+use types::connectivity::JsonrpcConnectionStatus;
+use types::contact::{ContactList, ContactObject, KeyChange, VcardContact};
+use types::events::{Event, SecurejoinFailure};
 use types::http::HttpResponse;
-use types::message::{MessageData, MessageObject, MessageReadReceipt};
+use types::message::{MessageData, MessageEdit, MessageObject, MessageReadReceipt, MessageReceipt};
 use types::provider_info::ProviderInfo;
 use types::reactions::JSONRPCReactions;
 use types::webxdc::WebxdcMessageInfo;
 
-use self::types::message::{MessageInfo, MessageLoadResult};
+use self::types::message::{ChatAssignment, MessageInfo, MessageLoadResult, SystemMessageType};
 use self::types::{
-    chat::{BasicChat, JSONRPCChatVisibility, MuteDuration},
-    location::JsonrpcLocation,
+    chat::{
+        BasicChat, ChatLabel, FreshCounts, JSONRPCChatVisibility, JSONRPCEncryptionPolicy,
+        JSONRPCMediaCategory, MediaCounts, MuteDuration,
+    },
+    location::{JsonrpcLocation, JsonrpcLocationExportFormat, JsonrpcLocationFreshness},
     message::{
-        JSONRPCMessageListItem, MessageNotificationInfo, MessageSearchResult, MessageViewtype,
+        JSONRPCMessageListItem, MessageAuthenticationResults, MessageNotificationInfo,
+        MessageRenderHints, MessageSearchResult, MessageSecurityInfo, MessageViewtype,
     },
+    sticker::{JsonrpcSticker, JsonrpcStickerPack},
 };
-use crate::api::types::chat_list::{get_chat_list_item_by_id, ChatListItemFetchResult};
-use crate::api::types::qr::QrObject;
+use crate::api::types::chat_list::{
+    get_chat_list_item_by_id, ChatListItemFetchResult, ChatListPage,
+};
+use crate::api::types::qr::{IssuedInviteObject, QrObject};
 
 #[derive(Debug)]
 struct AccountState {
@@ -208,6 +222,22 @@ async fn remove_account(&self, account_id: u32) -> Result<()> {
         Ok(())
     }
 
+    /// Destroys the account irrecoverably and removes it.
+    ///
+    /// Other than `remove_account()`, the database and all blobs are overwritten
+    /// before they are deleted.
+    /// If `delete_server_messages` is set, all messages on the server are deleted
+    /// and all folders are unsubscribed; this is only supported for chatmail accounts.
+    async fn destroy_account(&self, account_id: u32, delete_server_messages: bool) -> Result<()> {
+        self.accounts
+            .write()
+            .await
+            .destroy_account(account_id, delete_server_messages)
+            .await?;
+        self.states.lock().await.remove(&account_id);
+        Ok(())
+    }
+
     async fn get_all_account_ids(&self) -> Vec<u32> {
         self.accounts.read().await.get_all()
     }
@@ -248,6 +278,26 @@ async fn stop_io_for_all_accounts(&self) -> Result<()> {
         Ok(())
     }
 
+    /// Sets the policy for keeping recently selected accounts warm,
+    /// i.e. with open database but stopped background tasks,
+    /// so that switching to them is fast.
+    ///
+    /// At most `max_warm_accounts` accounts besides the selected one are kept open,
+    /// their estimated memory usage is limited by `max_memory_bytes` if set.
+    /// Other accounts are stopped and closed until selected again.
+    /// Passing `null` as `max_warm_accounts` disables the policy and opens all accounts.
+    async fn set_warm_accounts_policy(
+        &self,
+        max_warm_accounts: Option<u32>,
+        max_memory_bytes: Option<u64>,
+    ) -> Result<()> {
+        let policy = max_warm_accounts.map(|max_warm| WarmAccountsPolicy {
+            max_warm: max_warm as usize,
+            max_memory: max_memory_bytes,
+        });
+        self.accounts.write().await.set_warm_policy(policy).await
+    }
+
     /// Performs a background fetch for all accounts in parallel with a timeout.
     ///
     /// The `AccountsBackgroundFetchDone` event is emitted at the end even in case of timeout.
@@ -280,6 +330,21 @@ async fn stop_io(&self, account_id: u32) -> Result<()> {
         Ok(())
     }
 
+    /// Pauses background tasks and closes the database of a single account
+    /// so that it can be safely copied or inspected by external tools.
+    async fn enter_maintenance(&self, account_id: u32) -> Result<()> {
+        let ctx = self.get_context(account_id).await?;
+        ctx.enter_maintenance().await
+    }
+
+    /// Reopens the database closed by `enter_maintenance()` and resumes background tasks.
+    ///
+    /// Returns false if the passphrase is wrong.
+    async fn exit_maintenance(&self, account_id: u32, passphrase: Option<String>) -> Result<bool> {
+        let ctx = self.get_context(account_id).await?;
+        ctx.exit_maintenance(passphrase.unwrap_or_default()).await
+    }
+
     /// Get top-level info for an account.
     async fn get_account_info(&self, account_id: u32) -> Result<Account> {
         let context_option = self.accounts.read().await.get_account(account_id);
@@ -373,6 +438,47 @@ async fn batch_set_config(
         Ok(())
     }
 
+    /// Exports the explicitly set configuration values as a JSON object.
+    ///
+    /// Passwords are only included if `include_secrets` is true.
+    async fn export_config_json(&self, account_id: u32, include_secrets: bool) -> Result<String> {
+        let ctx = self.get_context(account_id).await?;
+        ctx.export_config_json(include_secrets).await
+    }
+
+    /// Imports configuration values from a JSON object
+    /// as returned by `export_config_json()`.
+    async fn import_config_json(&self, account_id: u32, json: String) -> Result<()> {
+        let ctx = self.get_context(account_id).await?;
+        ctx.import_config_json(&json).await
+    }
+
+    /// Returns the list of additional proxy URLs
+    /// which are tried in order if the proxy configured with `socks5_host` fails.
+    async fn get_proxy_urls(&self, account_id: u32) -> Result<Vec<String>> {
+        let ctx = self.get_context(account_id).await?;
+        ctx.get_proxy_urls().await
+    }
+
+    /// Adds a proxy URL such as `socks5://127.0.0.1:9050`, `http://proxy.example.org:8080`
+    /// or a Shadowsocks `ss://` URL to the end of the proxy list.
+    async fn add_proxy_url(&self, account_id: u32, url: String) -> Result<()> {
+        let ctx = self.get_context(account_id).await?;
+        ctx.add_proxy_url(&url).await
+    }
+
+    /// Removes a proxy URL from the proxy list.
+    async fn remove_proxy_url(&self, account_id: u32, url: String) -> Result<()> {
+        let ctx = self.get_context(account_id).await?;
+        ctx.remove_proxy_url(&url).await
+    }
+
+    /// Moves a proxy URL to the given position of the proxy list.
+    async fn move_proxy_url(&self, account_id: u32, url: String, index: u32) -> Result<()> {
+        let ctx = self.get_context(account_id).await?;
+        ctx.move_proxy_url(&url, index as usize).await
+    }
+
     /// Set configuration values from a QR code. (technically from the URI that is stored in the qrcode)
     /// Before this function is called, `checkQr()` should confirm the type of the
     /// QR code is `account` or `webrtcInstance`.
@@ -437,6 +543,35 @@ async fn configure(&self, account_id: u32) -> Result<()> {
         Ok(())
     }
 
+    /// Checks the currently set parameters without configuring the account.
+    ///
+    /// Validates the parameters and tries to log into the IMAP and SMTP servers.
+    /// Nothing is saved and the running connections of the account are not affected.
+    /// Returns the list of problems, which is empty if the parameters work.
+    async fn check_config(&self, account_id: u32) -> Result<Vec<ConfigProblem>> {
+        let ctx = self.get_context(account_id).await?;
+        let problems = ctx.check_config().await?;
+        Ok(problems.into_iter().map(Into::into).collect())
+    }
+
+    /// Configures the account from a `DCACCOUNT:` or `DCLOGIN:` QR code.
+    ///
+    /// For `DCACCOUNT:`, the account is created on the chatmail server first.
+    /// Progress is reported with `ConfigureProgress` events like for `configure()`.
+    async fn configure_from_qr(&self, account_id: u32, qr_content: String) -> Result<()> {
+        let ctx = self.get_context(account_id).await?;
+        ctx.stop_io().await;
+        let result = ctx.configure_from_qr(&qr_content).await;
+        if result.is_err() {
+            if let Ok(true) = ctx.is_configured().await {
+                ctx.start_io().await;
+            }
+            return result;
+        }
+        ctx.start_io().await;
+        Ok(())
+    }
+
     /// Signal an ongoing process to stop.
     async fn stop_ongoing_process(&self, account_id: u32) -> Result<()> {
         let ctx = self.get_context(account_id).await?;
@@ -444,6 +579,8 @@ async fn stop_ongoing_process(&self, account_id: u32) -> Result<()> {
         Ok(())
     }
 
+    /// Exports the self keys to the directory `path`.
+    /// If `passphrase` is set, the secret keys are encrypted with it.
     async fn export_self_keys(
         &self,
         account_id: u32,
@@ -460,6 +597,8 @@ async fn export_self_keys(
         .await
     }
 
+    /// Imports secret keys from the file or directory `path`.
+    /// `passphrase` is used to decrypt passphrase-protected key files.
     async fn import_self_keys(
         &self,
         account_id: u32,
@@ -497,6 +636,15 @@ async fn get_fresh_msgs(&self, account_id: u32) -> Result<Vec<u32>> {
             .collect())
     }
 
+    /// Returns the numbers of fresh messages in unblocked chats,
+    /// broken down by muted chats and mentions, e.g. for an app badge.
+    ///
+    /// The result is cached, so this is cheap to call on every event.
+    async fn get_fresh_counts(&self, account_id: u32) -> Result<FreshCounts> {
+        let ctx = self.get_context(account_id).await?;
+        Ok(ctx.get_fresh_counts().await?.into())
+    }
+
     /// Get the number of _fresh_ messages in a chat.
     /// Typically used to implement a badge with a number in the chatlist.
     ///
@@ -584,6 +732,46 @@ async fn continue_autocrypt_key_transfer(
         deltachat::imex::continue_key_transfer(&ctx, MsgId::new(message_id), &setup_code).await
     }
 
+    /// Generates a recovery code and a bundle with the secret key and login configuration
+    /// encrypted with the code, to be stored offline.
+    async fn generate_recovery_code(&self, account_id: u32) -> Result<RecoveryCode> {
+        let ctx = self.get_context(account_id).await?;
+        Ok(ctx.generate_recovery_code().await?.into())
+    }
+
+    /// Restores the secret key and login configuration of an unconfigured account
+    /// from a bundle created by `generate_recovery_code()`.
+    ///
+    /// Call `configure()` afterwards to log in.
+    async fn recover_from_code(&self, account_id: u32, bundle: String, code: String) -> Result<()> {
+        let ctx = self.get_context(account_id).await?;
+        ctx.recover_from_code(&bundle, &code).await
+    }
+
+    /// Replaces the own key with a newly generated one, keeping the old key for decryption.
+    ///
+    /// Returns the fingerprint of the new key.
+    /// Progress is reported with `KeyRotationProgress` events.
+    async fn rotate_key(&self, account_id: u32) -> Result<String> {
+        let ctx = self.get_context(account_id).await?;
+        Ok(ctx.rotate_key().await?.hex())
+    }
+
+    /// Replaces the encryption subkey of the own key with a newly generated one.
+    ///
+    /// The fingerprint of the key does not change.
+    async fn renew_encryption_subkey(&self, account_id: u32) -> Result<()> {
+        let ctx = self.get_context(account_id).await?;
+        ctx.renew_encryption_subkey().await
+    }
+
+    /// Returns the IDs of contacts known to still use one of our older keys.
+    async fn get_contacts_with_old_self_key(&self, account_id: u32) -> Result<Vec<u32>> {
+        let ctx = self.get_context(account_id).await?;
+        let contact_ids = ctx.get_contacts_with_old_self_key().await?;
+        Ok(contact_ids.into_iter().map(|id| id.to_u32()).collect())
+    }
+
     // ---------------------------------------------
     //   chat list
     // ---------------------------------------------
@@ -610,6 +798,56 @@ async fn get_chatlist_entries(
         Ok(l)
     }
 
+    /// Returns a page of at most `limit` entries of the chatlist.
+    ///
+    /// Only the `DC_GCL_ARCHIVED_ONLY`, `DC_GCL_NO_SPECIALS` and `DC_GCL_ADD_ALLDONE_HINT`
+    /// flags are supported.
+    /// Pass `next` of the returned page as `after` to load the next page.
+    async fn get_chatlist_page(
+        &self,
+        account_id: u32,
+        list_flags: Option<u32>,
+        after: Option<String>,
+        limit: u32,
+    ) -> Result<ChatListPage> {
+        let ctx = self.get_context(account_id).await?;
+        let page = Chatlist::try_load_page(
+            &ctx,
+            list_flags.unwrap_or(0) as usize,
+            after.as_deref(),
+            limit as usize,
+        )
+        .await?;
+        page.try_into()
+    }
+
+    /// Returns true if the chatlist may have changed since `change_token`
+    /// was returned by `get_chatlist_page`.
+    async fn chatlist_has_changes_since(
+        &self,
+        account_id: u32,
+        change_token: String,
+    ) -> Result<bool> {
+        let ctx = self.get_context(account_id).await?;
+        Ok(Chatlist::has_changes_since(&ctx, &change_token))
+    }
+
+    /// Returns the IDs of the chats having the given label,
+    /// including archived chats.
+    async fn get_chatlist_entries_by_label(
+        &self,
+        account_id: u32,
+        label_id: u32,
+    ) -> Result<Vec<u32>> {
+        let ctx = self.get_context(account_id).await?;
+        let list = Chatlist::try_load_by_label(&ctx, label_id).await?;
+        let mut l: Vec<u32> = Vec::with_capacity(list.len());
+        for i in 0..list.len() {
+            l.push(list.get_chat_id(i)?.to_u32());
+        }
+        Ok(l)
+    }
+
     /// Returns chats similar to the given one.
     ///
     /// Experimental API, subject to change without notice.
@@ -722,6 +960,58 @@ async fn get_chat_securejoin_qr_code(
         Ok(qr)
     }
 
+    /// Get a `https://i.delta.chat/#...` link that will offer a [SecureJoin](https://securejoin.delta.chat/) invitation
+    /// when clicked, e.g. to share it in an email or on a website.
+    ///
+    /// If `chat_id` is a group chat ID, the link invites to the group,
+    /// if it is unset, the link offers to setup a contact.
+    ///
+    /// If `validity_secs` is unset, the link is valid as long as the QR code.
+    /// Otherwise the link expires after the given number of seconds.
+    async fn get_chat_securejoin_invite_link(
+        &self,
+        account_id: u32,
+        chat_id: Option<u32>,
+        validity_secs: Option<u64>,
+    ) -> Result<String> {
+        let ctx = self.get_context(account_id).await?;
+        let chat = chat_id.map(ChatId::new);
+        let validity = validity_secs.map(Duration::from_secs);
+        securejoin::get_securejoin_invite_link(&ctx, chat, validity).await
+    }
+
+    /// Get the list of issued SecureJoin invites which are not expired, most recent first.
+    async fn get_securejoin_invites(&self, account_id: u32) -> Result<Vec<IssuedInviteObject>> {
+        let ctx = self.get_context(account_id).await?;
+        let invites = securejoin::get_issued_invites(&ctx).await?;
+        Ok(invites.into_iter().map(Into::into).collect())
+    }
+
+    /// Withdraw an issued SecureJoin invite.
+    ///
+    /// Joiners scanning the invite afterwards are told that it was withdrawn.
+    async fn withdraw_securejoin_invite(&self, account_id: u32, invite_id: u32) -> Result<()> {
+        let ctx = self.get_context(account_id).await?;
+        securejoin::withdraw_invite(&ctx, invite_id).await
+    }
+
+    /// Re-issue a withdrawn SecureJoin invite and return its QR code text.
+    async fn reissue_securejoin_invite(&self, account_id: u32, invite_id: u32) -> Result<String> {
+        let ctx = self.get_context(account_id).await?;
+        securejoin::reissue_invite(&ctx, invite_id).await
+    }
+
+    /// Get the translated description of the reason reported in the `SecurejoinFailed` event.
+    async fn get_securejoin_failure_text(
+        &self,
+        account_id: u32,
+        reason: SecurejoinFailure,
+    ) -> Result<String> {
+        let ctx = self.get_context(account_id).await?;
+        let reason: securejoin::SecurejoinFailure = reason.into();
+        Ok(reason.get_text(&ctx).await)
+    }
+
     /// Get QR code (text and SVG) that will offer a Setup-Contact or Verified-Group invitation.
     /// The QR code is compatible to the OPENPGP4FPR format
     /// so that a basic fingerprint comparison also works e.g. with OpenKeychain.
@@ -749,6 +1039,25 @@ async fn get_chat_securejoin_qr_code_svg(
         Ok((qr, svg))
     }
 
+    /// Like `get_chat_securejoin_qr_code_svg()`,
+    /// but returns the QR code as base64-encoded PNG image
+    /// for UIs that cannot render SVG.
+    ///
+    /// return format: `[code, png]`
+    async fn get_chat_securejoin_qr_code_png(
+        &self,
+        account_id: u32,
+        chat_id: Option<u32>,
+    ) -> Result<(String, String)> {
+        let ctx = self.get_context(account_id).await?;
+        let chat = chat_id.map(ChatId::new);
+        let qr = securejoin::get_securejoin_qr(&ctx, chat).await?;
+        let png = get_securejoin_qr_png(&ctx, chat).await?;
+
+        use base64::{engine::general_purpose, Engine as _};
+        Ok((qr, general_purpose::STANDARD.encode(png)))
+    }
+
     /// Continue a Setup-Contact or Verified-Group-Invite protocol
     /// started on another device with `get_chat_securejoin_qr_code_svg()`.
     /// This function is typically called when `check_qr()` returns
@@ -929,6 +1238,27 @@ async fn set_chat_visibility(
             .await
     }
 
+    /// Sets the encryption policy of a chat,
+    /// overriding the global `e2ee_enabled` setting.
+    ///
+    /// `Required` refuses to send unencrypted messages,
+    /// `Disabled` always sends cleartext,
+    /// e.g. to ticketing systems that cannot handle encryption.
+    /// `Clearsigned` sends cleartext with a PGP/MIME signature,
+    /// e.g. for bot output that should be readable in any mail client.
+    async fn set_chat_encryption_policy(
+        &self,
+        account_id: u32,
+        chat_id: u32,
+        policy: JSONRPCEncryptionPolicy,
+    ) -> Result<()> {
+        let ctx = self.get_context(account_id).await?;
+
+        ChatId::new(chat_id)
+            .set_encryption_policy(&ctx, policy.into_core_type())
+            .await
+    }
+
     async fn set_chat_ephemeral_timer(
         &self,
         account_id: u32,
@@ -1180,6 +1510,42 @@ async fn get_message_notification_info(
         MessageNotificationInfo::from_msg_id(&ctx, MsgId::new(message_id)).await
     }
 
+    /// Returns rendering hints for a message bubble in a single call,
+    /// intended for assistive-technology frontends.
+    async fn get_message_render_hints(
+        &self,
+        account_id: u32,
+        message_id: u32,
+    ) -> Result<MessageRenderHints> {
+        let ctx = self.get_context(account_id).await?;
+        MessageRenderHints::from_msg_id(&ctx, MsgId::new(message_id)).await
+    }
+
+    /// Returns structured encryption and signature details of a message,
+    /// e.g. for a padlock detail screen.
+    async fn get_message_security_info(
+        &self,
+        account_id: u32,
+        message_id: u32,
+    ) -> Result<MessageSecurityInfo> {
+        let ctx = self.get_context(account_id).await?;
+        let info = deltachat::message::get_security_info(&ctx, MsgId::new(message_id)).await?;
+        Ok(info.into())
+    }
+
+    /// Returns the DKIM, SPF and DMARC results of an incoming message
+    /// as reported by the user's email server.
+    /// If `dkimWarning` is set, UI should warn that the message may be spoofed.
+    async fn get_message_authentication_results(
+        &self,
+        account_id: u32,
+        message_id: u32,
+    ) -> Result<MessageAuthenticationResults> {
+        let ctx = self.get_context(account_id).await?;
+        let msg = Message::load_from_db(&ctx, MsgId::new(message_id)).await?;
+        Ok(msg.get_authentication_results().into())
+    }
+
     /// Delete messages. The messages are deleted on the current device and
     /// on the IMAP server.
     async fn delete_messages(&self, account_id: u32, message_ids: Vec<u32>) -> Result<()> {
@@ -1188,6 +1554,37 @@ async fn delete_messages(&self, account_id: u32, message_ids: Vec<u32>) -> Resul
         delete_msgs(&ctx, &msgs).await
     }
 
+    /// Replace the text of our message on all devices and for all chat members.
+    ///
+    /// The previous text is kept, see `get_edit_history()`.
+    /// Returns the ID of the hidden message transporting the edit.
+    async fn send_edit(&self, account_id: u32, message_id: u32, new_text: String) -> Result<u32> {
+        let ctx = self.get_context(account_id).await?;
+        let msg_id = chat::send_edit(&ctx, MsgId::new(message_id), new_text).await?;
+        Ok(msg_id.to_u32())
+    }
+
+    /// Get the previous texts of an edited message, oldest first.
+    ///
+    /// The first entry holds the original text.
+    async fn get_edit_history(&self, account_id: u32, message_id: u32) -> Result<Vec<MessageEdit>> {
+        let ctx = self.get_context(account_id).await?;
+        let history = MsgId::new(message_id).get_edit_history(&ctx).await?;
+        Ok(history.into_iter().map(Into::into).collect())
+    }
+
+    /// Delete our messages for everyone.
+    ///
+    /// A deletion request is sent to the chats of the messages
+    /// and the messages are replaced by tombstones, see `MessageObject.isRetracted`.
+    /// Messages can only be deleted for everyone
+    /// during the `delete_for_all_window` after sending them.
+    async fn delete_messages_for_all(&self, account_id: u32, message_ids: Vec<u32>) -> Result<()> {
+        let ctx = self.get_context(account_id).await?;
+        let msgs: Vec<MsgId> = message_ids.into_iter().map(MsgId::new).collect();
+        message::delete_for_all(&ctx, &msgs).await
+    }
+
     /// Get an informational text for a single message. The text is multiline and may
     /// contain e.g. the raw text of the message.
     ///
@@ -1198,6 +1595,18 @@ async fn get_message_info(&self, account_id: u32, message_id: u32) -> Result<Str
         MsgId::new(message_id).get_info(&ctx).await
     }
 
+    /// Returns why a message was assigned to its chat,
+    /// e.g. because of a `Chat-Group-ID` header or because it is a reply.
+    async fn explain_message_assignment(
+        &self,
+        account_id: u32,
+        message_id: u32,
+    ) -> Result<ChatAssignment> {
+        let ctx = self.get_context(account_id).await?;
+        let assignment = MsgId::new(message_id).explain_assignment(&ctx).await?;
+        Ok(assignment.into())
+    }
+
     /// Returns additional information for single message.
     async fn get_message_info_object(
         &self,
@@ -1226,6 +1635,28 @@ async fn get_message_read_receipts(
         Ok(receipts)
     }
 
+    /// Returns the members of the group the message was delivered to
+    /// or that sent read receipts, with the time of delivery and reading.
+    async fn get_message_receipts(
+        &self,
+        account_id: u32,
+        message_id: u32,
+    ) -> Result<Vec<MessageReceipt>> {
+        let ctx = self.get_context(account_id).await?;
+        let receipts = message::get_read_receipts(&ctx, MsgId::new(message_id))
+            .await?
+            .into_iter()
+            .map(Into::into)
+            .collect();
+        Ok(receipts)
+    }
+
+    /// Deletes the attachment of a received view-once message after it was displayed.
+    async fn mark_view_once_viewed(&self, account_id: u32, message_id: u32) -> Result<()> {
+        let ctx = self.get_context(account_id).await?;
+        MsgId::new(message_id).mark_view_once_viewed(&ctx).await
+    }
+
     /// Asks the core to start downloading a message fully.
     /// This function is typically called when the user hits the "Download" button
     /// that is shown by the UI in case `download_state` is `'Available'` or `'Failure'`
@@ -1268,6 +1699,49 @@ async fn search_messages(
             .collect::<Vec<u32>>())
     }
 
+    /// Returns up to `before` messages preceding and `after` messages following the given message
+    /// in its chat, together with the message itself, in chronological order.
+    ///
+    /// Can be used to show a result of the chat search without loading the whole chat.
+    async fn get_message_ids_around(
+        &self,
+        account_id: u32,
+        msg_id: u32,
+        before: u32,
+        after: u32,
+    ) -> Result<Vec<u32>> {
+        let ctx = self.get_context(account_id).await?;
+        let msg_ids =
+            chat::get_msgs_around(&ctx, MsgId::new(msg_id), before as usize, after as usize)
+                .await?;
+        Ok(msg_ids.iter().map(|msg_id| msg_id.to_u32()).collect())
+    }
+
+    /// Returns the first unread message of the chat, or null if there is none.
+    ///
+    /// Can be used to show an "unread messages" divider and to scroll to it.
+    async fn get_first_unread_message_id(
+        &self,
+        account_id: u32,
+        chat_id: u32,
+    ) -> Result<Option<u32>> {
+        let ctx = self.get_context(account_id).await?;
+        let msg_id = chat::get_first_unread(&ctx, ChatId::new(chat_id)).await?;
+        Ok(msg_id.map(|msg_id| msg_id.to_u32()))
+    }
+
+    /// Returns an excerpt of the message text or attachment filename around the match of the
+    /// query passed to `search_messages()`, or null if not available.
+    async fn get_message_search_snippet(
+        &self,
+        account_id: u32,
+        msg_id: u32,
+        query: String,
+    ) -> Result<Option<String>> {
+        let ctx = self.get_context(account_id).await?;
+        ctx.get_msg_search_snippet(MsgId::new(msg_id), &query).await
+    }
+
     async fn message_ids_to_search_results(
         &self,
         account_id: u32,
@@ -1444,6 +1918,140 @@ async fn get_contact_encryption_info(
         Contact::get_encrinfo(&ctx, ContactId::new(contact_id)).await
     }
 
+    /// Returns a short numeric representation of your and the contact's fingerprint
+    /// that can be compared verbally, or null if the contact's key is unknown.
+    /// The result changes when any of the keys changes.
+    async fn get_contact_verification_words(
+        &self,
+        account_id: u32,
+        contact_id: u32,
+    ) -> Result<Option<String>> {
+        let ctx = self.get_context(account_id).await?;
+        Contact::get_verification_words(&ctx, ContactId::new(contact_id)).await
+    }
+
+    /// Returns the observed key changes of a contact, oldest first.
+    async fn get_contact_key_history(
+        &self,
+        account_id: u32,
+        contact_id: u32,
+    ) -> Result<Vec<KeyChange>> {
+        let ctx = self.get_context(account_id).await?;
+        let history = Contact::get_key_history(&ctx, ContactId::new(contact_id)).await?;
+        Ok(history.into_iter().map(Into::into).collect())
+    }
+
+    /// Creates a reusable contact list and returns its ID.
+    ///
+    /// Contact lists are synchronised across own devices
+    /// and can be used to create groups and broadcast lists with the same members.
+    async fn create_contact_list(
+        &self,
+        account_id: u32,
+        name: String,
+        contact_ids: Vec<u32>,
+    ) -> Result<u32> {
+        let ctx = self.get_context(account_id).await?;
+        let contact_ids: Vec<ContactId> = contact_ids.into_iter().map(ContactId::new).collect();
+        deltachat::contact::create_contact_list(&ctx, &name, &contact_ids).await
+    }
+
+    /// Replaces name and members of a contact list.
+    async fn set_contact_list(
+        &self,
+        account_id: u32,
+        list_id: u32,
+        name: String,
+        contact_ids: Vec<u32>,
+    ) -> Result<()> {
+        let ctx = self.get_context(account_id).await?;
+        let contact_ids: Vec<ContactId> = contact_ids.into_iter().map(ContactId::new).collect();
+        deltachat::contact::set_contact_list(&ctx, list_id, &name, &contact_ids).await
+    }
+
+    async fn delete_contact_list(&self, account_id: u32, list_id: u32) -> Result<()> {
+        let ctx = self.get_context(account_id).await?;
+        deltachat::contact::delete_contact_list(&ctx, list_id).await
+    }
+
+    /// Returns all contact lists, sorted by name.
+    async fn get_contact_lists(&self, account_id: u32) -> Result<Vec<ContactList>> {
+        let ctx = self.get_context(account_id).await?;
+        let lists = deltachat::contact::get_contact_lists(&ctx).await?;
+        Ok(lists.into_iter().map(Into::into).collect())
+    }
+
+    /// Creates a label to organize the chatlist and returns its ID.
+    ///
+    /// Labels and their assignment to chats are synchronised across own devices.
+    async fn create_chat_label(&self, account_id: u32, name: String) -> Result<u32> {
+        let ctx = self.get_context(account_id).await?;
+        chat::create_chat_label(&ctx, &name).await
+    }
+
+    async fn rename_chat_label(&self, account_id: u32, label_id: u32, name: String) -> Result<()> {
+        let ctx = self.get_context(account_id).await?;
+        chat::rename_chat_label(&ctx, label_id, &name).await
+    }
+
+    async fn delete_chat_label(&self, account_id: u32, label_id: u32) -> Result<()> {
+        let ctx = self.get_context(account_id).await?;
+        chat::delete_chat_label(&ctx, label_id).await
+    }
+
+    /// Returns all chat labels, sorted by name.
+    async fn get_chat_labels(&self, account_id: u32) -> Result<Vec<ChatLabel>> {
+        let ctx = self.get_context(account_id).await?;
+        let labels = chat::get_chat_labels(&ctx).await?;
+        Ok(labels.into_iter().map(Into::into).collect())
+    }
+
+    /// Replaces the labels of a chat.
+    async fn set_chat_labels(
+        &self,
+        account_id: u32,
+        chat_id: u32,
+        label_ids: Vec<u32>,
+    ) -> Result<()> {
+        let ctx = self.get_context(account_id).await?;
+        ChatId::new(chat_id).set_labels(&ctx, &label_ids).await
+    }
+
+    /// Returns the IDs of the labels of a chat.
+    async fn get_labels_of_chat(&self, account_id: u32, chat_id: u32) -> Result<Vec<u32>> {
+        let ctx = self.get_context(account_id).await?;
+        ChatId::new(chat_id).get_labels(&ctx).await
+    }
+
+    /// Creates a new group chat with the members of a contact list.
+    async fn create_group_chat_from_contact_list(
+        &self,
+        account_id: u32,
+        list_id: u32,
+        protect: bool,
+    ) -> Result<u32> {
+        let ctx = self.get_context(account_id).await?;
+        let protect = match protect {
+            true => ProtectionStatus::Protected,
+            false => ProtectionStatus::Unprotected,
+        };
+        chat::create_group_chat_from_contact_list(&ctx, protect, list_id)
+            .await
+            .map(|id| id.to_u32())
+    }
+
+    /// Creates a new broadcast list with the members of a contact list.
+    async fn create_broadcast_list_from_contact_list(
+        &self,
+        account_id: u32,
+        list_id: u32,
+    ) -> Result<u32> {
+        let ctx = self.get_context(account_id).await?;
+        chat::create_broadcast_list_from_contact_list(&ctx, list_id)
+            .await
+            .map(|id| id.to_u32())
+    }
+
     /// Check if an e-mail address belongs to a known and unblocked contact.
     /// To get a list of all known and unblocked contacts, use contacts_get_contacts().
     ///
@@ -1552,6 +2160,103 @@ async fn get_chat_media(
         Ok(media.iter().map(|msg_id| msg_id.to_u32()).collect())
     }
 
+    /// Returns up to `limit` message IDs of the media `category` for a gallery,
+    /// starting with the newest message.
+    ///
+    /// To get the next page, pass the last returned message ID as `older_than`.
+    /// Setting `chat_id` to `None` (`null` in typescript) means get messages
+    /// from any chat of the currently used account.
+    async fn get_media_page(
+        &self,
+        account_id: u32,
+        chat_id: Option<u32>,
+        category: JSONRPCMediaCategory,
+        older_than: Option<u32>,
+        limit: u32,
+    ) -> Result<Vec<u32>> {
+        let ctx = self.get_context(account_id).await?;
+        let chat_id = match chat_id {
+            None | Some(0) => None,
+            Some(id) => Some(ChatId::new(id)),
+        };
+        let media = chat::get_media_page(
+            &ctx,
+            chat_id,
+            category.into(),
+            older_than.map(MsgId::new),
+            limit as usize,
+        )
+        .await?;
+        Ok(media.iter().map(|msg_id| msg_id.to_u32()).collect())
+    }
+
+    /// Returns the number of messages per media category in a chat,
+    /// or in all chats if `chat_id` is `None`.
+    async fn get_media_counts(&self, account_id: u32, chat_id: Option<u32>) -> Result<MediaCounts> {
+        let ctx = self.get_context(account_id).await?;
+        let chat_id = match chat_id {
+            None | Some(0) => None,
+            Some(id) => Some(ChatId::new(id)),
+        };
+        Ok(chat::get_media_counts(&ctx, chat_id).await?.into())
+    }
+
+    /// Returns the info messages of a chat, e.g. about member or timer changes,
+    /// sorted by timestamp.
+    ///
+    /// If `kind` is set, only info messages of this type are returned.
+    async fn get_info_msgs(
+        &self,
+        account_id: u32,
+        chat_id: u32,
+        kind: Option<SystemMessageType>,
+    ) -> Result<Vec<u32>> {
+        let ctx = self.get_context(account_id).await?;
+        let msg_ids = chat::get_info_msgs(&ctx, ChatId::new(chat_id), kind.map(Into::into)).await?;
+        Ok(msg_ids.iter().map(|msg_id| msg_id.to_u32()).collect())
+    }
+
+    /// Returns the root message and all replies of a topic thread.
+    ///
+    /// The list is sorted and starts with the root message.
+    async fn get_thread_msgs(&self, account_id: u32, root_msg_id: u32) -> Result<Vec<u32>> {
+        let ctx = self.get_context(account_id).await?;
+        let msg_ids = chat::get_thread_msgs(&ctx, MsgId::new(root_msg_id)).await?;
+        Ok(msg_ids.iter().map(|msg_id| msg_id.to_u32()).collect())
+    }
+
+    /// Pins the message in its chat for all chat members.
+    async fn pin_message(&self, account_id: u32, msg_id: u32) -> Result<()> {
+        let ctx = self.get_context(account_id).await?;
+        chat::pin_message(&ctx, MsgId::new(msg_id)).await
+    }
+
+    /// Unpins the message pinned before.
+    async fn unpin_message(&self, account_id: u32, msg_id: u32) -> Result<()> {
+        let ctx = self.get_context(account_id).await?;
+        chat::unpin_message(&ctx, MsgId::new(msg_id)).await
+    }
+
+    /// Returns the messages pinned in the chat, the most recently pinned first.
+    async fn get_pinned_messages(&self, account_id: u32, chat_id: u32) -> Result<Vec<u32>> {
+        let ctx = self.get_context(account_id).await?;
+        let msg_ids = chat::get_pinned_messages(&ctx, ChatId::new(chat_id)).await?;
+        Ok(msg_ids.iter().map(|msg_id| msg_id.to_u32()).collect())
+    }
+
+    /// Returns the messages quoting the given message, sorted by timestamp.
+    async fn get_replies(&self, account_id: u32, msg_id: u32) -> Result<Vec<u32>> {
+        let ctx = self.get_context(account_id).await?;
+        let msg_ids = message::get_replies(&ctx, MsgId::new(msg_id)).await?;
+        Ok(msg_ids.iter().map(|msg_id| msg_id.to_u32()).collect())
+    }
+
+    /// Returns the number of fresh messages in a topic thread.
+    async fn get_thread_fresh_msg_cnt(&self, account_id: u32, root_msg_id: u32) -> Result<usize> {
+        let ctx = self.get_context(account_id).await?;
+        chat::get_thread_fresh_msg_cnt(&ctx, MsgId::new(root_msg_id)).await
+    }
+
     /// Search next/previous message based on a given message and a list of types.
     /// Typically used to implement the "next" and "previous" buttons
     /// in a gallery or in a media player.
@@ -1621,6 +2326,91 @@ async fn export_backup(
         .await
     }
 
+    /// Verifies the backup file at `path` without importing it.
+    ///
+    /// Fails if the backup cannot be read at all, e.g. because of a wrong passphrase,
+    /// otherwise the problems found are listed in the returned report.
+    async fn verify_backup(
+        &self,
+        account_id: u32,
+        path: String,
+        passphrase: Option<String>,
+    ) -> Result<BackupReport> {
+        let ctx = self.get_context(account_id).await?;
+        let report = imex::verify_backup(&ctx, path.as_ref(), passphrase).await?;
+        Ok(report.into())
+    }
+
+    /// Imports Autocrypt peer states of other clients from the JSON file at `path`.
+    ///
+    /// Returns the number of created or updated peer states.
+    async fn import_peerstates(&self, account_id: u32, path: String) -> Result<usize> {
+        let ctx = self.get_context(account_id).await?;
+        imex::import_peerstates(&ctx, path.as_ref()).await
+    }
+
+    /// Exports the messages of a single chat as an archive into the `destination` directory.
+    ///
+    /// Attached files are only included if `include_media` is set.
+    /// Returns the path of the written archive.
+    async fn export_chat(
+        &self,
+        account_id: u32,
+        chat_id: u32,
+        destination: String,
+        include_media: bool,
+    ) -> Result<String> {
+        let ctx = self.get_context(account_id).await?;
+        let path = imex::export_chat(
+            &ctx,
+            ChatId::new(chat_id),
+            destination.as_ref(),
+            include_media,
+        )
+        .await?;
+        Ok(path.to_string_lossy().into_owned())
+    }
+
+    /// Saves a message as `.eml` file at `path`, including the attached file.
+    ///
+    /// If `original` is set and the received MIME message is stored
+    /// (see `save_mime_headers` config option), it is written unchanged,
+    /// otherwise the file is rendered from the message contents.
+    async fn save_msg_as_eml(
+        &self,
+        account_id: u32,
+        msg_id: u32,
+        path: String,
+        original: bool,
+    ) -> Result<()> {
+        let ctx = self.get_context(account_id).await?;
+        imex::save_as_eml(&ctx, MsgId::new(msg_id), path.as_ref(), original).await
+    }
+
+    /// Exports the messages of a single chat as mbox file at `path`,
+    /// see `save_msg_as_eml()` for the meaning of `original`.
+    async fn export_chat_mbox(
+        &self,
+        account_id: u32,
+        chat_id: u32,
+        path: String,
+        original: bool,
+    ) -> Result<()> {
+        let ctx = self.get_context(account_id).await?;
+        imex::export_mbox(&ctx, ChatId::new(chat_id), path.as_ref(), original).await
+    }
+
+    /// Imports the messages of an `.eml` or mbox file at `path`
+    /// as if they were received, e.g. to migrate history from another mail client.
+    ///
+    /// Messages that already exist or cannot be imported are skipped.
+    /// Returns the IDs of the imported messages.
+    async fn import_mail_file(&self, account_id: u32, path: String) -> Result<Vec<u32>> {
+        let ctx = self.get_context(account_id).await?;
+        let msg_ids = imex::import_mail_file(&ctx, path.as_ref()).await?;
+        Ok(msg_ids.iter().map(|msg_id| msg_id.to_u32()).collect())
+    }
+
     async fn import_backup(
         &self,
         account_id: u32,
@@ -1766,6 +2556,25 @@ async fn get_connectivity_html(&self, account_id: u32) -> Result<String> {
         ctx.get_connectivity_html().await
     }
 
+    /// Get the connectivity of each IMAP folder and the SMTP connection separately,
+    /// including the last error and the time of the last state change.
+    ///
+    /// Returns an empty list if IO is not started.
+    ///
+    /// If the states change, a #DC_EVENT_CONNECTIVITY_CHANGED will be emitted.
+    async fn get_connectivity_details(
+        &self,
+        account_id: u32,
+    ) -> Result<Vec<JsonrpcConnectionStatus>> {
+        let ctx = self.get_context(account_id).await?;
+        Ok(ctx
+            .get_connectivity_details()
+            .await
+            .into_iter()
+            .map(Into::into)
+            .collect())
+    }
+
     // ---------------------------------------------
     //                  locations
     // ---------------------------------------------
@@ -1792,6 +2601,99 @@ async fn get_locations(
         Ok(locations.into_iter().map(|l| l.into()).collect())
     }
 
+    /// Exports the locations of a chat in the given time range as a KML or GPX document.
+    ///
+    /// If `timestamp_end` is 0, all locations up to now are exported.
+    async fn export_locations(
+        &self,
+        account_id: u32,
+        chat_id: u32,
+        timestamp_begin: i64,
+        timestamp_end: i64,
+        format: JsonrpcLocationExportFormat,
+    ) -> Result<String> {
+        let ctx = self.get_context(account_id).await?;
+        location::export(
+            &ctx,
+            ChatId::new(chat_id),
+            timestamp_begin,
+            timestamp_end,
+            format.into(),
+        )
+        .await
+    }
+
+    /// Returns the freshness of the newest location of each contact,
+    /// optionally filtering by chat.
+    async fn get_location_freshness(
+        &self,
+        account_id: u32,
+        chat_id: Option<u32>,
+    ) -> Result<Vec<JsonrpcLocationFreshness>> {
+        let ctx = self.get_context(account_id).await?;
+        let freshness = location::get_freshness(&ctx, chat_id.map(ChatId::new)).await?;
+        Ok(freshness.into_iter().map(Into::into).collect())
+    }
+
+    /// Sets the battery level of the device in percent, `null` if unknown or charging.
+    ///
+    /// Locations are streamed less often if the battery is low.
+    async fn set_battery_level(&self, account_id: u32, percent: Option<u32>) -> Result<()> {
+        let ctx = self.get_context(account_id).await?;
+        location::set_battery_level(&ctx, percent).await;
+        Ok(())
+    }
+
+    // ---------------------------------------------
+    //                  stickers
+    // ---------------------------------------------
+
+    /// Imports all PNG, WebP and GIF images of a folder as a new sticker pack.
+    ///
+    /// Returns the ID of the new pack.
+    async fn import_sticker_pack_from_dir(&self, account_id: u32, path: String) -> Result<u32> {
+        let ctx = self.get_context(account_id).await?;
+        sticker::import_pack_from_dir(&ctx, Path::new(&path)).await
+    }
+
+    /// Imports the stickers of a received ZIP archive as a new sticker pack.
+    ///
+    /// Returns the ID of the new pack.
+    async fn import_sticker_pack_from_message(&self, account_id: u32, msg_id: u32) -> Result<u32> {
+        let ctx = self.get_context(account_id).await?;
+        sticker::import_pack_from_msg(&ctx, MsgId::new(msg_id)).await
+    }
+
+    /// Returns all sticker packs, the most recently imported first.
+    async fn get_sticker_packs(&self, account_id: u32) -> Result<Vec<JsonrpcStickerPack>> {
+        let ctx = self.get_context(account_id).await?;
+        let packs = sticker::get_packs(&ctx).await?;
+        Ok(packs.into_iter().map(Into::into).collect())
+    }
+
+    /// Returns the stickers of a pack.
+    async fn get_stickers(&self, account_id: u32, pack_id: u32) -> Result<Vec<JsonrpcSticker>> {
+        let ctx = self.get_context(account_id).await?;
+        let stickers = sticker::get_stickers(&ctx, pack_id).await?;
+        Ok(stickers
+            .into_iter()
+            .map(|sticker| JsonrpcSticker::from_sticker(&ctx, sticker))
+            .collect())
+    }
+
+    /// Deletes a sticker pack.
+    async fn delete_sticker_pack(&self, account_id: u32, pack_id: u32) -> Result<()> {
+        let ctx = self.get_context(account_id).await?;
+        sticker::delete_pack(&ctx, pack_id).await
+    }
+
+    /// Sends a sticker from a sticker pack to the chat.
+    async fn send_sticker(&self, account_id: u32, chat_id: u32, sticker_id: u32) -> Result<u32> {
+        let ctx = self.get_context(account_id).await?;
+        let msg_id = sticker::send_sticker(&ctx, ChatId::new(chat_id), sticker_id).await?;
+        Ok(msg_id.to_u32())
+    }
+
     // ---------------------------------------------
     //                   webxdc
     // ---------------------------------------------
@@ -2002,6 +2904,24 @@ async fn send_msg(&self, account_id: u32, chat_id: u32, data: MessageData) -> Re
         Ok(msg_id)
     }
 
+    /// Sends a message without waiting for it to be prepared.
+    ///
+    /// The message is shown in the chat immediately in the pending state,
+    /// the attachment is copied, recoded and sent in the background.
+    async fn send_msg_deferred(
+        &self,
+        account_id: u32,
+        chat_id: u32,
+        data: MessageData,
+    ) -> Result<u32> {
+        let ctx = self.get_context(account_id).await?;
+        let mut message = data.create_message(&ctx).await?;
+        let msg_id = chat::send_msg_deferred(&ctx, ChatId::new(chat_id), &mut message)
+            .await?
+            .to_u32();
+        Ok(msg_id)
+    }
+
     /// Checks if messages can be sent to a given chat.
     async fn can_send(&self, account_id: u32, chat_id: u32) -> Result<bool> {
         let ctx = self.get_context(account_id).await?;
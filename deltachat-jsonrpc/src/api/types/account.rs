@@ -43,3 +43,21 @@ pub async fn from_context(ctx: &deltachat::context::Context, id: u32) -> Result<
         }
     }
 }
+
+#[derive(Serialize, TypeDef, schemars::JsonSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct RecoveryCode {
+    /// Code the bundle is encrypted with.
+    code: String,
+    /// ASCII-armored encrypted bundle with the secret key and login configuration.
+    bundle: String,
+}
+
+impl From<deltachat::imex::RecoveryCode> for RecoveryCode {
+    fn from(recovery: deltachat::imex::RecoveryCode) -> Self {
+        RecoveryCode {
+            code: recovery.code,
+            bundle: recovery.bundle,
+        }
+    }
+}
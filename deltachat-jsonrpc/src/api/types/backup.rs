@@ -0,0 +1,39 @@
+use serde::Serialize;
+use typescript_type_def::TypeDef;
+
+/// Result of a backup verification.
+#[derive(Serialize, TypeDef, schemars::JsonSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct BackupReport {
+    /// True if no problems were found.
+    pub is_ok: bool,
+    pub has_database: bool,
+    /// Problems found by the database integrity check.
+    pub database_errors: Vec<String>,
+    /// Configured address of the account in the backup.
+    pub addr: Option<String>,
+    pub has_secret_key: bool,
+    pub blob_count: usize,
+    /// False for backups created by older versions, then blobs are not verified.
+    pub has_manifest: bool,
+    pub missing_blobs: Vec<String>,
+    pub corrupted_blobs: Vec<String>,
+    pub unexpected_entries: Vec<String>,
+}
+
+impl From<deltachat::imex::BackupReport> for BackupReport {
+    fn from(report: deltachat::imex::BackupReport) -> Self {
+        Self {
+            is_ok: report.is_ok(),
+            has_database: report.has_database,
+            database_errors: report.database_errors,
+            addr: report.addr,
+            has_secret_key: report.has_secret_key,
+            blob_count: report.blob_count,
+            has_manifest: report.has_manifest,
+            missing_blobs: report.missing_blobs,
+            corrupted_blobs: report.corrupted_blobs,
+            unexpected_entries: report.unexpected_entries,
+        }
+    }
+}
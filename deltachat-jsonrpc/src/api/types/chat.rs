@@ -1,7 +1,7 @@
 use std::time::{Duration, SystemTime};
 
 use anyhow::{bail, Context as _, Result};
-use deltachat::chat::{self, get_chat_contacts, ChatVisibility};
+use deltachat::chat::{self, get_chat_contacts, ChatVisibility, EncryptionPolicy};
 use deltachat::chat::{Chat, ChatId};
 use deltachat::constants::Chattype;
 use deltachat::contact::{Contact, ContactId};
@@ -51,6 +51,7 @@ pub struct FullChat {
     can_send: bool,
     was_seen_recently: bool,
     mailing_list_address: Option<String>,
+    encryption_policy: JSONRPCEncryptionPolicy,
 }
 
 impl FullChat {
@@ -122,6 +123,7 @@ pub async fn try_from_dc_chat_id(context: &Context, chat_id: u32) -> Result<Self
             can_send,
             was_seen_recently,
             mailing_list_address,
+            encryption_policy: chat.get_encryption_policy().into(),
         })
     }
 }
@@ -239,3 +241,118 @@ pub fn into_core_type(self) -> ChatVisibility {
         }
     }
 }
+
+#[derive(Clone, Serialize, Deserialize, TypeDef, schemars::JsonSchema)]
+#[serde(rename = "EncryptionPolicy")]
+pub enum JSONRPCEncryptionPolicy {
+    Opportunistic,
+    Required,
+    Disabled,
+    Clearsigned,
+}
+
+impl JSONRPCEncryptionPolicy {
+    pub fn into_core_type(self) -> EncryptionPolicy {
+        match self {
+            JSONRPCEncryptionPolicy::Opportunistic => EncryptionPolicy::Opportunistic,
+            JSONRPCEncryptionPolicy::Required => EncryptionPolicy::Required,
+            JSONRPCEncryptionPolicy::Disabled => EncryptionPolicy::Disabled,
+            JSONRPCEncryptionPolicy::Clearsigned => EncryptionPolicy::Clearsigned,
+        }
+    }
+}
+
+impl From<EncryptionPolicy> for JSONRPCEncryptionPolicy {
+    fn from(policy: EncryptionPolicy) -> Self {
+        match policy {
+            EncryptionPolicy::Opportunistic => JSONRPCEncryptionPolicy::Opportunistic,
+            EncryptionPolicy::Required => JSONRPCEncryptionPolicy::Required,
+            EncryptionPolicy::Disabled => JSONRPCEncryptionPolicy::Disabled,
+            EncryptionPolicy::Clearsigned => JSONRPCEncryptionPolicy::Clearsigned,
+        }
+    }
+}
+
+#[derive(Serialize, TypeDef, schemars::JsonSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct ChatLabel {
+    id: u32,
+    name: String,
+}
+
+impl From<chat::ChatLabel> for ChatLabel {
+    fn from(label: chat::ChatLabel) -> Self {
+        ChatLabel {
+            id: label.id,
+            name: label.name,
+        }
+    }
+}
+
+#[derive(Clone, Copy, Serialize, Deserialize, TypeDef, schemars::JsonSchema)]
+#[serde(rename = "MediaCategory")]
+pub enum JSONRPCMediaCategory {
+    /// Images and GIFs.
+    Images,
+    Videos,
+    /// Audio files and voice messages.
+    Audio,
+    /// Other files.
+    Documents,
+    Webxdc,
+}
+
+impl From<JSONRPCMediaCategory> for chat::MediaCategory {
+    fn from(category: JSONRPCMediaCategory) -> Self {
+        match category {
+            JSONRPCMediaCategory::Images => chat::MediaCategory::Images,
+            JSONRPCMediaCategory::Videos => chat::MediaCategory::Videos,
+            JSONRPCMediaCategory::Audio => chat::MediaCategory::Audio,
+            JSONRPCMediaCategory::Documents => chat::MediaCategory::Documents,
+            JSONRPCMediaCategory::Webxdc => chat::MediaCategory::Webxdc,
+        }
+    }
+}
+
+#[derive(Serialize, TypeDef, schemars::JsonSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct MediaCounts {
+    images: usize,
+    videos: usize,
+    audio: usize,
+    documents: usize,
+    webxdc: usize,
+}
+
+#[derive(Serialize, TypeDef, schemars::JsonSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct FreshCounts {
+    /// Number of all fresh messages, including muted chats.
+    total: usize,
+    /// Number of fresh messages in unmuted chats.
+    unmuted: usize,
+    /// Number of fresh messages mentioning us, including muted chats.
+    mentions: usize,
+}
+
+impl From<deltachat::context::FreshCounts> for FreshCounts {
+    fn from(counts: deltachat::context::FreshCounts) -> Self {
+        FreshCounts {
+            total: counts.total,
+            unmuted: counts.unmuted,
+            mentions: counts.mentions,
+        }
+    }
+}
+
+impl From<chat::MediaCounts> for MediaCounts {
+    fn from(counts: chat::MediaCounts) -> Self {
+        MediaCounts {
+            images: counts.images,
+            videos: counts.videos,
+            audio: counts.audio,
+            documents: counts.documents,
+            webxdc: counts.webxdc,
+        }
+    }
+}
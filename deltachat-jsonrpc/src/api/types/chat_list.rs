@@ -54,6 +54,33 @@ pub enum ChatListItemFetchResult {
     Error { id: u32, error: String },
 }
 
+#[derive(Serialize, TypeDef, schemars::JsonSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct ChatListPage {
+    /// Chatlist entries of the page, as returned by `get_chatlist_entries`.
+    entries: Vec<u32>,
+    /// Cursor to load the next page, `None` if this is the last page.
+    next: Option<String>,
+    /// Token to pass to `chatlist_has_changes_since`.
+    change_token: String,
+}
+
+impl TryFrom<deltachat::chatlist::ChatlistPage> for ChatListPage {
+    type Error = anyhow::Error;
+
+    fn try_from(page: deltachat::chatlist::ChatlistPage) -> Result<Self> {
+        let mut entries = Vec::with_capacity(page.chatlist.len());
+        for i in 0..page.chatlist.len() {
+            entries.push(page.chatlist.get_chat_id(i)?.to_u32());
+        }
+        Ok(ChatListPage {
+            entries,
+            next: page.next,
+            change_token: page.change_token,
+        })
+    }
+}
+
 pub(crate) async fn get_chat_list_item_by_id(
     ctx: &deltachat::context::Context,
     entry: u32,
@@ -0,0 +1,22 @@
+use deltachat::config::ConfigProblem as CoreConfigProblem;
+use serde::Serialize;
+use typescript_type_def::TypeDef;
+
+#[derive(Serialize, TypeDef, schemars::JsonSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct ConfigProblem {
+    /// Configuration key, e.g. "mail_server".
+    key: String,
+
+    /// Human-readable description of the problem.
+    msg: String,
+}
+
+impl From<CoreConfigProblem> for ConfigProblem {
+    fn from(problem: CoreConfigProblem) -> Self {
+        ConfigProblem {
+            key: problem.key.to_string(),
+            msg: problem.msg,
+        }
+    }
+}
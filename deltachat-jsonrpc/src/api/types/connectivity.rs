@@ -0,0 +1,40 @@
+use deltachat::ConnectionStatus;
+use serde::Serialize;
+use typescript_type_def::TypeDef;
+
+/// Connectivity of a single IMAP folder or SMTP connection.
+#[derive(Serialize, TypeDef, schemars::JsonSchema)]
+#[serde(rename = "ConnectionStatus", rename_all = "camelCase")]
+pub struct JsonrpcConnectionStatus {
+    /// Name of the connection, e.g. "Inbox" or "SMTP".
+    name: String,
+
+    /// Basic connectivity as returned by `getConnectivity()`,
+    /// `null` if the connection is not configured.
+    connectivity: Option<u32>,
+
+    /// Localized description of the current state.
+    state: String,
+
+    /// Last error, kept even after the connection has recovered.
+    last_error: Option<String>,
+
+    /// Timestamp of the last error, 0 if there was none.
+    last_error_timestamp: i64,
+
+    /// Timestamp of the last state change.
+    state_timestamp: i64,
+}
+
+impl From<ConnectionStatus> for JsonrpcConnectionStatus {
+    fn from(status: ConnectionStatus) -> Self {
+        Self {
+            name: status.name,
+            connectivity: status.connectivity.map(|c| c as u32),
+            state: status.state,
+            last_error: status.last_error,
+            last_error_timestamp: status.last_error_timestamp,
+            state_timestamp: status.state_timestamp,
+        }
+    }
+}
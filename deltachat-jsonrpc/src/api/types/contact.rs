@@ -49,6 +49,71 @@ pub struct ContactObject {
 
     /// If the contact is a bot.
     is_bot: bool,
+
+    /// Commands advertised by the bot.
+    ///
+    /// Empty if the contact is not a bot.
+    bot_commands: Vec<BotCommand>,
+}
+
+#[derive(Serialize, TypeDef, schemars::JsonSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct BotCommand {
+    /// Command name without the leading `/`.
+    name: String,
+    /// Parameters of the command as displayed to the user, e.g. `<city>`.
+    params: String,
+    /// Short description of the command.
+    description: String,
+}
+
+impl From<deltachat::bot_commands::BotCommand> for BotCommand {
+    fn from(command: deltachat::bot_commands::BotCommand) -> Self {
+        BotCommand {
+            name: command.name,
+            params: command.params,
+            description: command.description,
+        }
+    }
+}
+
+#[derive(Serialize, TypeDef, schemars::JsonSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct KeyChange {
+    old_fingerprint: String,
+    new_fingerprint: String,
+    timestamp: i64,
+    /// ID of the message that caused the change, if known.
+    msg_id: Option<u32>,
+}
+
+impl From<deltachat::contact::KeyChange> for KeyChange {
+    fn from(change: deltachat::contact::KeyChange) -> Self {
+        KeyChange {
+            old_fingerprint: change.old_fingerprint,
+            new_fingerprint: change.new_fingerprint,
+            timestamp: change.timestamp,
+            msg_id: change.msg_id.map(|id| id.to_u32()),
+        }
+    }
+}
+
+#[derive(Serialize, TypeDef, schemars::JsonSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct ContactList {
+    id: u32,
+    name: String,
+    contact_ids: Vec<u32>,
+}
+
+impl From<deltachat::contact::ContactList> for ContactList {
+    fn from(list: deltachat::contact::ContactList) -> Self {
+        ContactList {
+            id: list.id,
+            name: list.name,
+            contact_ids: list.contact_ids.iter().map(|id| id.to_u32()).collect(),
+        }
+    }
 }
 
 impl ContactObject {
@@ -85,6 +150,11 @@ pub async fn try_from_dc_contact(
             last_seen: contact.last_seen(),
             was_seen_recently: contact.was_seen_recently(),
             is_bot: contact.is_bot(),
+            bot_commands: contact
+                .get_bot_commands()
+                .into_iter()
+                .map(Into::into)
+                .collect(),
         })
     }
 }
@@ -1,5 +1,7 @@
+use deltachat::chat::ProtectionStatus;
+use deltachat::securejoin::SecurejoinFailure as CoreSecurejoinFailure;
 use deltachat::{Event as CoreEvent, EventType as CoreEventType};
-use serde::Serialize;
+use serde::{Deserialize, Serialize};
 use typescript_type_def::TypeDef;
 
 #[derive(Serialize, TypeDef, schemars::JsonSchema)]
@@ -72,6 +74,24 @@ pub enum EventType {
     /// in a messasge box then.
     Error { msg: String },
 
+    /// An error occurred repeatedly.
+    ///
+    /// Identical errors of background tasks are reported as `Error` only once per minute,
+    /// the repetitions are summarized by this event.
+    #[serde(rename_all = "camelCase")]
+    ErrorRepeated {
+        msg: String,
+
+        /// Number of occurrences since the error was last reported.
+        count: usize,
+
+        /// Timestamp of the first summarized occurrence.
+        first_timestamp: i64,
+
+        /// Timestamp of the last summarized occurrence.
+        last_timestamp: i64,
+    },
+
     /// An action cannot be performed because the user is not in the group.
     /// Reported eg. after a call to
     /// setChatName(), setChatProfileImage(),
@@ -149,12 +169,32 @@ pub enum EventType {
     #[serde(rename_all = "camelCase")]
     ChatEphemeralTimerModified { chat_id: u32, timer: u32 },
 
+    /// Chat protection status changed,
+    /// e.g. a 1:1 chat became protected after a successful Secure-Join
+    /// or the protection was broken by a message from an unverified key.
+    #[serde(rename_all = "camelCase")]
+    ChatProtectionChanged {
+        chat_id: u32,
+        is_protected: bool,
+        is_protection_broken: bool,
+    },
+
     /// Contact(s) created, renamed, blocked or deleted.
     ///
     /// @param data1 (int) If set, this is the contact_id of an added contact that should be selected.
     #[serde(rename_all = "camelCase")]
     ContactsChanged { contact_id: Option<u32> },
 
+    /// A key of a contact was discovered outside of Autocrypt headers,
+    /// e.g. in the Web Key Directory of the contact's domain.
+    #[serde(rename_all = "camelCase")]
+    ContactKeyDiscovered {
+        contact_id: u32,
+
+        /// Where the key was discovered, e.g. the URL of the Web Key Directory.
+        source: String,
+    },
+
     /// Location of one or more contact has changed.
     ///
     /// @param data1 (u32) contact_id of the contact for which the location has changed.
@@ -181,6 +221,13 @@ pub enum EventType {
     #[serde(rename_all = "camelCase")]
     ImexProgress { progress: usize },
 
+    /// Inform about the progress of destroying the account
+    /// started by `destroy_account()`.
+    ///
+    /// @param data1 (usize) 0=error, 1-999=progress in permille, 1000=success and done
+    #[serde(rename_all = "camelCase")]
+    AccountDestroyProgress { progress: usize },
+
     /// A file has been exported. A file has been written by imex().
     /// This event may be sent multiple times by a single call to imex().
     ///
@@ -191,6 +238,16 @@ pub enum EventType {
     #[serde(rename_all = "camelCase")]
     ImexFileWritten { path: String },
 
+    /// Inform about the key rotation progress started by rotate_key().
+    #[serde(rename_all = "camelCase")]
+    KeyRotationProgress {
+        /// 0=error, 1-999=progress in permille, 1000=success and done
+        progress: usize,
+
+        /// Number of contacts still using the old key, set once the rotation is done.
+        contacts_with_old_key: usize,
+    },
+
     /// Progress information of a secure-join handshake from the view of the inviter
     /// (Alice, the person who shows the QR code).
     ///
@@ -217,18 +274,42 @@ pub enum EventType {
     #[serde(rename_all = "camelCase")]
     SecurejoinJoinerProgress { contact_id: u32, progress: usize },
 
+    /// A secure-join handshake failed, either on the inviter or on the joiner side.
+    ///
+    /// Except for timeouts, this follows `SecurejoinInviterProgress`
+    /// or `SecurejoinJoinerProgress` with progress 0.
+    #[serde(rename_all = "camelCase")]
+    SecurejoinFailed {
+        /// ID of the contact on the other side of the handshake.
+        contact_id: u32,
+        reason: SecurejoinFailure,
+    },
+
     /// The connectivity to the server changed.
     /// This means that you should refresh the connectivity view
     /// and possibly the connectivtiy HTML; see getConnectivity() and
     /// getConnectivityHtml() for details.
     ConnectivityChanged,
 
+    /// Connecting through the active proxy failed
+    /// and the next proxy of the list is used now.
+    ProxyChanged {
+        /// Hostname of the new proxy.
+        host: String,
+
+        /// Port of the new proxy.
+        port: u16,
+    },
+
     /// Deprecated by `ConfigSynced`.
     SelfavatarChanged,
 
     /// A multi-device synced config value changed. Maybe the app needs to refresh smth. For
     /// uniformity this is emitted on the source device too. The value isn't here, otherwise it
     /// would be logged which might not be good for privacy.
+    ///
+    /// This is also emitted when an ui-specific value with a `ui.` prefix changes,
+    /// values with a `ui.sync.` prefix are synced to other devices.
     ConfigSynced {
         /// Configuration key.
         key: String,
@@ -283,6 +364,17 @@ fn from(event: CoreEventType) -> Self {
             CoreEventType::DeletedBlobFile(file) => DeletedBlobFile { file },
             CoreEventType::Warning(msg) => Warning { msg },
             CoreEventType::Error(msg) => Error { msg },
+            CoreEventType::ErrorRepeated {
+                msg,
+                count,
+                first_timestamp,
+                last_timestamp,
+            } => ErrorRepeated {
+                msg,
+                count,
+                first_timestamp,
+                last_timestamp,
+            },
             CoreEventType::ErrorSelfNotInGroup(msg) => ErrorSelfNotInGroup { msg },
             CoreEventType::MsgsChanged { chat_id, msg_id } => MsgsChanged {
                 chat_id: chat_id.to_u32(),
@@ -330,9 +422,21 @@ fn from(event: CoreEventType) -> Self {
                     timer: timer.to_u32(),
                 }
             }
+            CoreEventType::ChatProtectionChanged {
+                chat_id,
+                protection,
+            } => ChatProtectionChanged {
+                chat_id: chat_id.to_u32(),
+                is_protected: protection == ProtectionStatus::Protected,
+                is_protection_broken: protection == ProtectionStatus::ProtectionBroken,
+            },
             CoreEventType::ContactsChanged(contact) => ContactsChanged {
                 contact_id: contact.map(|c| c.to_u32()),
             },
+            CoreEventType::ContactKeyDiscovered { contact_id, source } => ContactKeyDiscovered {
+                contact_id: contact_id.to_u32(),
+                source,
+            },
             CoreEventType::LocationChanged(contact) => LocationChanged {
                 contact_id: contact.map(|c| c.to_u32()),
             },
@@ -340,9 +444,17 @@ fn from(event: CoreEventType) -> Self {
                 ConfigureProgress { progress, comment }
             }
             CoreEventType::ImexProgress(progress) => ImexProgress { progress },
+            CoreEventType::AccountDestroyProgress(progress) => AccountDestroyProgress { progress },
             CoreEventType::ImexFileWritten(path) => ImexFileWritten {
                 path: path.to_str().unwrap_or_default().to_owned(),
             },
+            CoreEventType::KeyRotationProgress {
+                progress,
+                contacts_with_old_key,
+            } => KeyRotationProgress {
+                progress,
+                contacts_with_old_key,
+            },
             CoreEventType::SecurejoinInviterProgress {
                 contact_id,
                 progress,
@@ -357,11 +469,14 @@ fn from(event: CoreEventType) -> Self {
                 contact_id: contact_id.to_u32(),
                 progress,
             },
+            CoreEventType::SecurejoinFailed { contact_id, reason } => SecurejoinFailed {
+                contact_id: contact_id.to_u32(),
+                reason: reason.into(),
+            },
             CoreEventType::ConnectivityChanged => ConnectivityChanged,
+            CoreEventType::ProxyChanged { host, port } => ProxyChanged { host, port },
             CoreEventType::SelfavatarChanged => SelfavatarChanged,
-            CoreEventType::ConfigSynced { key } => ConfigSynced {
-                key: key.to_string(),
-            },
+            CoreEventType::ConfigSynced { key } => ConfigSynced { key },
             CoreEventType::WebxdcStatusUpdate {
                 msg_id,
                 status_update_serial,
@@ -385,3 +500,58 @@ fn from(event: CoreEventType) -> Self {
         }
     }
 }
+
+/// Reason of a failed secure-join handshake.
+#[derive(Serialize, Deserialize, TypeDef, schemars::JsonSchema)]
+pub enum SecurejoinFailure {
+    /// Other error.
+    Other,
+
+    /// The inviter did not reply in time.
+    ///
+    /// The handshake is not aborted and may still complete later.
+    Timeout,
+
+    /// The key of the peer does not match the fingerprint from the QR code.
+    FingerprintMismatch,
+
+    /// The scanned invite was withdrawn by the inviter.
+    InviteWithdrawn,
+
+    /// A handshake message was not encrypted and signed as required.
+    NotEncrypted,
+
+    /// The auth code is missing or not valid.
+    AuthInvalid,
+
+    /// The handshake was aborted because another QR code was scanned.
+    Aborted,
+}
+
+impl From<CoreSecurejoinFailure> for SecurejoinFailure {
+    fn from(reason: CoreSecurejoinFailure) -> Self {
+        match reason {
+            CoreSecurejoinFailure::Other => SecurejoinFailure::Other,
+            CoreSecurejoinFailure::Timeout => SecurejoinFailure::Timeout,
+            CoreSecurejoinFailure::FingerprintMismatch => SecurejoinFailure::FingerprintMismatch,
+            CoreSecurejoinFailure::InviteWithdrawn => SecurejoinFailure::InviteWithdrawn,
+            CoreSecurejoinFailure::NotEncrypted => SecurejoinFailure::NotEncrypted,
+            CoreSecurejoinFailure::AuthInvalid => SecurejoinFailure::AuthInvalid,
+            CoreSecurejoinFailure::Aborted => SecurejoinFailure::Aborted,
+        }
+    }
+}
+
+impl From<SecurejoinFailure> for CoreSecurejoinFailure {
+    fn from(reason: SecurejoinFailure) -> Self {
+        match reason {
+            SecurejoinFailure::Other => CoreSecurejoinFailure::Other,
+            SecurejoinFailure::Timeout => CoreSecurejoinFailure::Timeout,
+            SecurejoinFailure::FingerprintMismatch => CoreSecurejoinFailure::FingerprintMismatch,
+            SecurejoinFailure::InviteWithdrawn => CoreSecurejoinFailure::InviteWithdrawn,
+            SecurejoinFailure::NotEncrypted => CoreSecurejoinFailure::NotEncrypted,
+            SecurejoinFailure::AuthInvalid => CoreSecurejoinFailure::AuthInvalid,
+            SecurejoinFailure::Aborted => CoreSecurejoinFailure::Aborted,
+        }
+    }
+}
@@ -1,5 +1,5 @@
-use deltachat::location::Location;
-use serde::Serialize;
+use deltachat::location::{ExportFormat, Location, LocationFreshness};
+use serde::{Deserialize, Serialize};
 use typescript_type_def::TypeDef;
 
 #[derive(Serialize, TypeDef, schemars::JsonSchema)]
@@ -45,3 +45,39 @@ fn from(location: Location) -> Self {
         }
     }
 }
+
+#[derive(Serialize, TypeDef, schemars::JsonSchema)]
+#[serde(rename = "LocationFreshness", rename_all = "camelCase")]
+pub struct JsonrpcLocationFreshness {
+    pub contact_id: u32,
+    /// Timestamp of the newest location of the contact.
+    pub timestamp: i64,
+    /// Whether the location is outdated and should be grayed out on the map.
+    pub is_stale: bool,
+}
+
+impl From<LocationFreshness> for JsonrpcLocationFreshness {
+    fn from(freshness: LocationFreshness) -> Self {
+        Self {
+            contact_id: freshness.contact_id.to_u32(),
+            timestamp: freshness.timestamp,
+            is_stale: freshness.is_stale,
+        }
+    }
+}
+
+#[derive(Clone, Copy, Serialize, Deserialize, TypeDef, schemars::JsonSchema)]
+#[serde(rename = "LocationExportFormat")]
+pub enum JsonrpcLocationExportFormat {
+    Kml,
+    Gpx,
+}
+
+impl From<JsonrpcLocationExportFormat> for ExportFormat {
+    fn from(format: JsonrpcLocationExportFormat) -> Self {
+        match format {
+            JsonrpcLocationExportFormat::Kml => ExportFormat::Kml,
+            JsonrpcLocationExportFormat::Gpx => ExportFormat::Gpx,
+        }
+    }
+}
@@ -3,9 +3,11 @@
 use deltachat::chat::Chat;
 use deltachat::chat::ChatItem;
 use deltachat::chat::ChatVisibility;
-use deltachat::contact::Contact;
+use deltachat::contact::{Contact, ContactId};
 use deltachat::context::Context;
 use deltachat::download;
+use deltachat::mention::Mention;
+use deltachat::message;
 use deltachat::message::Message;
 use deltachat::message::MsgId;
 use deltachat::message::Viewtype;
@@ -34,6 +36,8 @@ pub struct MessageObject {
     from_id: u32,
     quote: Option<MessageQuote>,
     parent_id: Option<u32>,
+    /// Root message id of the topic thread, if the message is a thread reply.
+    thread_root_id: Option<u32>,
 
     text: String,
 
@@ -56,10 +60,24 @@ pub struct MessageObject {
     // summary - use/create another function if you need it
     subject: String,
     show_padlock: bool,
+    /// True if the message is unencrypted, but has a valid signature of the sender.
+    is_clearsigned: bool,
     is_setupmessage: bool,
     is_info: bool,
     is_forwarded: bool,
 
+    /// True if the message was deleted for everyone, see `delete_messages_for_all()`.
+    ///
+    /// Text and attachment of such messages are removed.
+    is_retracted: bool,
+
+    /// True if the text was edited by the sender, see `send_edit()`.
+    is_edited: bool,
+
+    /// True if the attachment should be shown only once.
+    /// The file is removed after calling `mark_view_once_viewed()`.
+    is_view_once: bool,
+
     /// True if the message was sent by a bot.
     is_bot: bool,
 
@@ -67,6 +85,8 @@ pub struct MessageObject {
     system_message_type: SystemMessageType,
 
     duration: i32,
+    /// Number of frames of an animated image, 0 for still images.
+    frame_count: i32,
     dimensions_height: i32,
     dimensions_width: i32,
 
@@ -76,6 +96,9 @@ pub struct MessageObject {
     override_sender_name: Option<String>,
     sender: ContactObject,
 
+    /// Alternative text describing the attached file.
+    alt_text: Option<String>,
+
     setup_code_begin: Option<String>,
 
     file: Option<String>,
@@ -83,6 +106,12 @@ pub struct MessageObject {
     file_bytes: u64,
     file_name: Option<String>,
 
+    /// Path to a JPEG preview of the attached video or PDF file, if there is one.
+    ///
+    /// Previews are only created from embedded images,
+    /// i.e. cover art of videos and page thumbnails of PDF files.
+    preview_file: Option<String>,
+
     webxdc_info: Option<WebxdcMessageInfo>,
 
     download_state: DownloadState,
@@ -90,6 +119,30 @@ pub struct MessageObject {
     reactions: Option<JSONRPCReactions>,
 
     vcard_contact: Option<VcardContact>,
+
+    /// Contacts mentioned in the message.
+    mentions: Vec<MessageMention>,
+    /// True if the message mentions the user.
+    is_mentioning_self: bool,
+}
+
+#[derive(Serialize, TypeDef, schemars::JsonSchema)]
+#[serde(rename_all = "camelCase")]
+struct MessageMention {
+    contact_id: u32,
+    /// Range of the mention in the message text as `[start, end)`,
+    /// counted in Unicode scalar values.
+    /// `None` if the mention is not visible in the text.
+    range: Option<(usize, usize)>,
+}
+
+impl From<Mention> for MessageMention {
+    fn from(mention: Mention) -> Self {
+        MessageMention {
+            contact_id: mention.contact_id.to_u32(),
+            range: mention.range.map(|range| (range.start, range.end)),
+        }
+    }
 }
 
 #[derive(Serialize, TypeDef, schemars::JsonSchema)]
@@ -131,6 +184,10 @@ pub async fn from_msg_id(context: &Context, msg_id: MsgId) -> Result<Self> {
         };
 
         let parent_id = message.parent(context).await?.map(|m| m.get_id().to_u32());
+        let thread_root_id = message
+            .get_thread_root(context)
+            .await?
+            .map(|id| id.to_u32());
 
         let download_state = message.download_state().into();
 
@@ -189,6 +246,7 @@ pub async fn from_msg_id(context: &Context, msg_id: MsgId) -> Result<Self> {
             from_id: message.get_from_id().to_u32(),
             quote,
             parent_id,
+            thread_root_id,
             text: message.get_text(),
             has_location: message.has_location(),
             has_html: message.has_html(),
@@ -206,13 +264,18 @@ pub async fn from_msg_id(context: &Context, msg_id: MsgId) -> Result<Self> {
 
             subject: message.get_subject().to_owned(),
             show_padlock: message.get_showpadlock(),
+            is_clearsigned: message.is_clearsigned(),
             is_setupmessage: message.is_setupmessage(),
             is_info: message.is_info(),
             is_forwarded: message.is_forwarded(),
+            is_retracted: message.is_retracted(),
+            is_edited: message.is_edited(),
+            is_view_once: message.is_view_once(),
             is_bot: message.is_bot(),
             system_message_type: message.get_info_type().into(),
 
             duration: message.get_duration(),
+            frame_count: message.get_frame_count(),
             dimensions_height: message.get_height(),
             dimensions_width: message.get_width(),
 
@@ -228,6 +291,8 @@ pub async fn from_msg_id(context: &Context, msg_id: MsgId) -> Result<Self> {
             override_sender_name,
             sender,
 
+            alt_text: message.get_alt_text(),
+
             setup_code_begin: message.get_setupcodebegin(context).await,
 
             file: match message.get_file(context) {
@@ -237,6 +302,9 @@ pub async fn from_msg_id(context: &Context, msg_id: MsgId) -> Result<Self> {
             file_mime: message.get_filemime(),
             file_bytes,
             file_name: message.get_filename(),
+            preview_file: message
+                .get_preview_blob(context)
+                .and_then(|path_buf| path_buf.to_str().map(|s| s.to_owned())),
             webxdc_info,
 
             download_state,
@@ -244,6 +312,9 @@ pub async fn from_msg_id(context: &Context, msg_id: MsgId) -> Result<Self> {
             reactions,
 
             vcard_contact: vcard_contacts.first().cloned(),
+
+            mentions: message.get_mentions().into_iter().map(Into::into).collect(),
+            is_mentioning_self: message.is_mentioning_self(),
         })
     }
 }
@@ -353,6 +424,35 @@ fn from(state: download::DownloadState) -> Self {
 }
 
 #[derive(Serialize, TypeDef, schemars::JsonSchema)]
+pub enum ChatAssignment {
+    Unknown,
+    Trash,
+    GroupId,
+    Reply,
+    AdHocGroup,
+    MailingList,
+    SpoofingQuarantine,
+    OneOneChat,
+    SelfChat,
+}
+
+impl From<message::ChatAssignment> for ChatAssignment {
+    fn from(assignment: message::ChatAssignment) -> Self {
+        match assignment {
+            message::ChatAssignment::Unknown => ChatAssignment::Unknown,
+            message::ChatAssignment::Trash => ChatAssignment::Trash,
+            message::ChatAssignment::GroupId => ChatAssignment::GroupId,
+            message::ChatAssignment::Reply => ChatAssignment::Reply,
+            message::ChatAssignment::AdHocGroup => ChatAssignment::AdHocGroup,
+            message::ChatAssignment::MailingList => ChatAssignment::MailingList,
+            message::ChatAssignment::SpoofingQuarantine => ChatAssignment::SpoofingQuarantine,
+            message::ChatAssignment::OneOneChat => ChatAssignment::OneOneChat,
+            message::ChatAssignment::SelfChat => ChatAssignment::SelfChat,
+        }
+    }
+}
+
+#[derive(Serialize, Deserialize, TypeDef, schemars::JsonSchema)]
 pub enum SystemMessageType {
     Unknown,
     GroupNameChanged,
@@ -394,6 +494,12 @@ pub enum SystemMessageType {
 
     /// This message contains a users iroh node address.
     IrohNodeAddr,
+
+    /// A message was pinned in the chat.
+    MessagePinned,
+
+    /// A message was unpinned in the chat.
+    MessageUnpinned,
 }
 
 impl From<deltachat::mimeparser::SystemMessage> for SystemMessageType {
@@ -417,12 +523,133 @@ fn from(system_message_type: deltachat::mimeparser::SystemMessage) -> Self {
             SystemMessage::WebxdcInfoMessage => SystemMessageType::WebxdcInfoMessage,
             SystemMessage::InvalidUnencryptedMail => SystemMessageType::InvalidUnencryptedMail,
             SystemMessage::IrohNodeAddr => SystemMessageType::IrohNodeAddr,
+            SystemMessage::MessagePinned => SystemMessageType::MessagePinned,
+            SystemMessage::MessageUnpinned => SystemMessageType::MessageUnpinned,
             SystemMessage::SecurejoinWait => SystemMessageType::SecurejoinWait,
             SystemMessage::SecurejoinWaitTimeout => SystemMessageType::SecurejoinWaitTimeout,
         }
     }
 }
 
+impl From<SystemMessageType> for deltachat::mimeparser::SystemMessage {
+    fn from(system_message_type: SystemMessageType) -> Self {
+        use deltachat::mimeparser::SystemMessage;
+        match system_message_type {
+            SystemMessageType::Unknown => SystemMessage::Unknown,
+            SystemMessageType::GroupNameChanged => SystemMessage::GroupNameChanged,
+            SystemMessageType::GroupImageChanged => SystemMessage::GroupImageChanged,
+            SystemMessageType::MemberAddedToGroup => SystemMessage::MemberAddedToGroup,
+            SystemMessageType::MemberRemovedFromGroup => SystemMessage::MemberRemovedFromGroup,
+            SystemMessageType::AutocryptSetupMessage => SystemMessage::AutocryptSetupMessage,
+            SystemMessageType::SecurejoinMessage => SystemMessage::SecurejoinMessage,
+            SystemMessageType::LocationStreamingEnabled => SystemMessage::LocationStreamingEnabled,
+            SystemMessageType::LocationOnly => SystemMessage::LocationOnly,
+            SystemMessageType::EphemeralTimerChanged => SystemMessage::EphemeralTimerChanged,
+            SystemMessageType::ChatProtectionEnabled => SystemMessage::ChatProtectionEnabled,
+            SystemMessageType::ChatProtectionDisabled => SystemMessage::ChatProtectionDisabled,
+            SystemMessageType::MultiDeviceSync => SystemMessage::MultiDeviceSync,
+            SystemMessageType::WebxdcStatusUpdate => SystemMessage::WebxdcStatusUpdate,
+            SystemMessageType::WebxdcInfoMessage => SystemMessage::WebxdcInfoMessage,
+            SystemMessageType::InvalidUnencryptedMail => SystemMessage::InvalidUnencryptedMail,
+            SystemMessageType::IrohNodeAddr => SystemMessage::IrohNodeAddr,
+            SystemMessageType::MessagePinned => SystemMessage::MessagePinned,
+            SystemMessageType::MessageUnpinned => SystemMessage::MessageUnpinned,
+            SystemMessageType::SecurejoinWait => SystemMessage::SecurejoinWait,
+            SystemMessageType::SecurejoinWaitTimeout => SystemMessage::SecurejoinWaitTimeout,
+        }
+    }
+}
+
+#[derive(Serialize, TypeDef, schemars::JsonSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct MessageRenderHints {
+    is_forwarded: bool,
+    is_info: bool,
+    system_message_type: SystemMessageType,
+    has_ephemeral_timer: bool,
+    is_edited: bool,
+    /// Emoji reactions with their count, most frequent first.
+    reactions: Vec<(String, usize)>,
+}
+
+impl MessageRenderHints {
+    pub async fn from_msg_id(context: &Context, msg_id: MsgId) -> Result<Self> {
+        let message = Message::load_from_db(context, msg_id).await?;
+        let hints = message.get_render_hints(context).await?;
+        Ok(MessageRenderHints {
+            is_forwarded: hints.is_forwarded,
+            is_info: hints.is_info,
+            system_message_type: hints.info_type.into(),
+            has_ephemeral_timer: hints.has_ephemeral_timer,
+            is_edited: hints.is_edited,
+            reactions: hints.reactions,
+        })
+    }
+}
+
+#[derive(Serialize, TypeDef, schemars::JsonSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct MessageSecurityInfo {
+    encrypted: bool,
+    /// Hex fingerprint of the key that made a valid signature, if any.
+    signing_fingerprint: Option<String>,
+    verified: bool,
+    pq_hybrid: bool,
+    gossiped: bool,
+}
+
+impl From<deltachat::message::SecurityInfo> for MessageSecurityInfo {
+    fn from(info: deltachat::message::SecurityInfo) -> Self {
+        MessageSecurityInfo {
+            encrypted: info.encrypted,
+            signing_fingerprint: info.signing_fingerprint.map(|fp| fp.hex()),
+            verified: info.verified,
+            pq_hybrid: info.pq_hybrid,
+            gossiped: info.gossiped,
+        }
+    }
+}
+
+#[derive(Serialize, TypeDef, schemars::JsonSchema)]
+pub enum AuthResult {
+    Unknown,
+    Pass,
+    Fail,
+    None,
+}
+
+impl From<deltachat::authres::AuthResult> for AuthResult {
+    fn from(result: deltachat::authres::AuthResult) -> Self {
+        match result {
+            deltachat::authres::AuthResult::Unknown => AuthResult::Unknown,
+            deltachat::authres::AuthResult::Pass => AuthResult::Pass,
+            deltachat::authres::AuthResult::Fail => AuthResult::Fail,
+            deltachat::authres::AuthResult::None => AuthResult::None,
+        }
+    }
+}
+
+#[derive(Serialize, TypeDef, schemars::JsonSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct MessageAuthenticationResults {
+    dkim: AuthResult,
+    spf: AuthResult,
+    dmarc: AuthResult,
+    /// The sender is a known contact, but DKIM failed, the message may be spoofed.
+    dkim_warning: bool,
+}
+
+impl From<deltachat::authres::AuthenticationResults> for MessageAuthenticationResults {
+    fn from(results: deltachat::authres::AuthenticationResults) -> Self {
+        MessageAuthenticationResults {
+            dkim: results.dkim.into(),
+            spf: results.spf.into(),
+            dmarc: results.dmarc.into(),
+            dkim_warning: results.dkim_warning,
+        }
+    }
+}
+
 #[derive(Serialize, TypeDef, schemars::JsonSchema)]
 #[serde(rename_all = "camelCase")]
 pub struct MessageNotificationInfo {
@@ -577,9 +804,17 @@ pub struct MessageData {
     pub file: Option<String>,
     pub location: Option<(f64, f64)>,
     pub override_sender_name: Option<String>,
+    /// Alternative text describing the attached file.
+    pub alt_text: Option<String>,
     /// Quoted message id. Takes preference over `quoted_text` (see below).
     pub quoted_message_id: Option<u32>,
     pub quoted_text: Option<String>,
+    /// Root message id of the topic thread to reply in.
+    pub thread_root_id: Option<u32>,
+    /// Show the attached image, video or audio only once.
+    pub view_once: Option<bool>,
+    /// Contact ids to mention, see `Message::set_mentions()`.
+    pub mentions: Option<Vec<u32>>,
 }
 
 impl MessageData {
@@ -598,12 +833,22 @@ pub(crate) async fn create_message(self, context: &Context) -> Result<Message> {
         if self.override_sender_name.is_some() {
             message.set_override_sender_name(self.override_sender_name);
         }
+        if self.alt_text.is_some() {
+            message.set_alt_text(self.alt_text);
+        }
         if let Some(file) = self.file {
             message.set_file(file, None);
         }
         if let Some((latitude, longitude)) = self.location {
             message.set_location(latitude, longitude);
         }
+        if let Some(view_once) = self.view_once {
+            message.set_view_once(view_once);
+        }
+        if let Some(mentions) = self.mentions {
+            let contact_ids: Vec<ContactId> = mentions.into_iter().map(ContactId::new).collect();
+            message.set_mentions(context, &contact_ids).await?;
+        }
         if let Some(id) = self.quoted_message_id {
             message
                 .set_quote(
@@ -619,10 +864,36 @@ pub(crate) async fn create_message(self, context: &Context) -> Result<Message> {
             let protect = false;
             message.set_quote_text(Some((text, protect)));
         }
+        if let Some(id) = self.thread_root_id {
+            message.set_thread_root(Some(
+                &Message::load_from_db(context, MsgId::new(id))
+                    .await
+                    .context("thread root message could not be loaded")?,
+            ))?;
+        }
         Ok(message)
     }
 }
 
+/// Previous text of an edited message.
+#[derive(Serialize, TypeDef, schemars::JsonSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct MessageEdit {
+    /// The text before it was replaced.
+    pub text: String,
+    /// Time the text was replaced, in seconds since the epoch.
+    pub timestamp: i64,
+}
+
+impl From<deltachat::message::MsgEdit> for MessageEdit {
+    fn from(edit: deltachat::message::MsgEdit) -> Self {
+        MessageEdit {
+            text: edit.text,
+            timestamp: edit.timestamp,
+        }
+    }
+}
+
 #[derive(Serialize, TypeDef, schemars::JsonSchema)]
 #[serde(rename_all = "camelCase")]
 pub struct MessageReadReceipt {
@@ -630,6 +901,26 @@ pub struct MessageReadReceipt {
     pub timestamp: i64,
 }
 
+#[derive(Serialize, TypeDef, schemars::JsonSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct MessageReceipt {
+    pub contact_id: u32,
+    /// Time when the message was delivered to the contact over SMTP.
+    pub delivered_timestamp: Option<i64>,
+    /// Time when the contact sent a read receipt.
+    pub read_timestamp: Option<i64>,
+}
+
+impl From<deltachat::message::ReadReceipt> for MessageReceipt {
+    fn from(receipt: deltachat::message::ReadReceipt) -> Self {
+        MessageReceipt {
+            contact_id: receipt.contact_id.to_u32(),
+            delivered_timestamp: receipt.delivered_timestamp,
+            read_timestamp: receipt.read_timestamp,
+        }
+    }
+}
+
 #[derive(Serialize, TypeDef, schemars::JsonSchema)]
 #[serde(rename_all = "camelCase")]
 pub struct MessageInfo {
@@ -1,6 +1,9 @@
 pub mod account;
+pub mod backup;
 pub mod chat;
 pub mod chat_list;
+pub mod config;
+pub mod connectivity;
 pub mod contact;
 pub mod events;
 pub mod http;
@@ -9,6 +12,7 @@
 pub mod provider_info;
 pub mod qr;
 pub mod reactions;
+pub mod sticker;
 pub mod webxdc;
 
 pub fn color_int_to_hex_string(color: u32) -> String {
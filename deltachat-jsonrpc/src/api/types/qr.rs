@@ -1,4 +1,5 @@
 use deltachat::qr::Qr;
+use deltachat::securejoin::IssuedInvite;
 use serde::Serialize;
 use typescript_type_def::TypeDef;
 
@@ -48,6 +49,10 @@ pub enum QrObject {
         contact_id: u32,
         draft: Option<String>,
     },
+    Vcard {
+        vcard: String,
+        addrs: Vec<String>,
+    },
     Url {
         url: String,
     },
@@ -156,6 +161,7 @@ fn from(qr: Qr) -> Self {
                 let contact_id = contact_id.to_u32();
                 QrObject::Addr { contact_id, draft }
             }
+            Qr::Vcard { vcard, addrs } => QrObject::Vcard { vcard, addrs },
             Qr::Url { url } => QrObject::Url { url },
             Qr::Text { text } => QrObject::Text { text },
             Qr::WithdrawVerifyContact {
@@ -230,3 +236,27 @@ fn from(qr: Qr) -> Self {
         }
     }
 }
+
+#[derive(Serialize, TypeDef, schemars::JsonSchema)]
+#[serde(rename = "IssuedInvite", rename_all = "camelCase")]
+pub struct IssuedInviteObject {
+    id: u32,
+    /// Group chat ID, `null` for setup-contact invites.
+    chat_id: Option<u32>,
+    timestamp: i64,
+    /// Expiry timestamp, 0 if the invite never expires.
+    expires: i64,
+    withdrawn: bool,
+}
+
+impl From<IssuedInvite> for IssuedInviteObject {
+    fn from(invite: IssuedInvite) -> Self {
+        IssuedInviteObject {
+            id: invite.id,
+            chat_id: invite.chat_id.map(|chat_id| chat_id.to_u32()),
+            timestamp: invite.timestamp,
+            expires: invite.expires,
+            withdrawn: invite.withdrawn,
+        }
+    }
+}
@@ -0,0 +1,45 @@
+use deltachat::context::Context;
+use deltachat::sticker::{Sticker, StickerPack};
+use serde::Serialize;
+use typescript_type_def::TypeDef;
+
+#[derive(Serialize, TypeDef, schemars::JsonSchema)]
+#[serde(rename = "StickerPack", rename_all = "camelCase")]
+pub struct JsonrpcStickerPack {
+    pub id: u32,
+    pub name: String,
+    /// Timestamp of the import.
+    pub timestamp: i64,
+    /// Number of stickers in the pack.
+    pub count: u32,
+}
+
+impl From<StickerPack> for JsonrpcStickerPack {
+    fn from(pack: StickerPack) -> Self {
+        Self {
+            id: pack.id,
+            name: pack.name,
+            timestamp: pack.timestamp,
+            count: u32::try_from(pack.count).unwrap_or(u32::MAX),
+        }
+    }
+}
+
+#[derive(Serialize, TypeDef, schemars::JsonSchema)]
+#[serde(rename = "Sticker", rename_all = "camelCase")]
+pub struct JsonrpcSticker {
+    pub id: u32,
+    pub pack_id: u32,
+    /// Path to the image file of the sticker.
+    pub file: String,
+}
+
+impl JsonrpcSticker {
+    pub fn from_sticker(context: &Context, sticker: Sticker) -> Self {
+        Self {
+            id: sticker.id,
+            pack_id: sticker.pack_id,
+            file: sticker.get_file(context).to_string_lossy().into_owned(),
+        }
+    }
+}
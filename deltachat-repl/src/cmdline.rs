@@ -33,14 +33,14 @@
 /// Argument is a bitmask, executing single or multiple actions in one call.
 /// e.g. bitmask 7 triggers actions defined with bits 1, 2 and 4.
 async fn reset_tables(context: &Context, bits: i32) {
-    println!("Resetting tables ({bits})...");
+    outputln!("Resetting tables ({bits})...");
     if 0 != bits & 2 {
         context
             .sql()
             .execute("DELETE FROM acpeerstates;", ())
             .await
             .unwrap();
-        println!("(2) Peerstates reset.");
+        outputln!("(2) Peerstates reset.");
     }
     if 0 != bits & 4 {
         context
@@ -48,7 +48,7 @@ async fn reset_tables(context: &Context, bits: i32) {
             .execute("DELETE FROM keypairs;", ())
             .await
             .unwrap();
-        println!("(4) Private keypairs reset.");
+        outputln!("(4) Private keypairs reset.");
     }
     if 0 != bits & 8 {
         context
@@ -85,7 +85,7 @@ async fn reset_tables(context: &Context, bits: i32) {
             .execute("DELETE FROM leftgrps;", ())
             .await
             .unwrap();
-        println!("(8) Rest but server config reset.");
+        outputln!("(8) Rest but server config reset.");
     }
 
     context.emit_msgs_changed_without_ids();
@@ -95,7 +95,7 @@ async fn poke_eml_file(context: &Context, filename: impl AsRef<Path>) -> Result<
     let data = read_file(context, filename).await?;
 
     if let Err(err) = receive_imf(context, &data, false).await {
-        println!("receive_imf errored: {err:?}");
+        outputln!("receive_imf errored: {err:?}");
     }
     Ok(())
 }
@@ -138,7 +138,7 @@ async fn poke_spec(context: &Context, spec: Option<&str>) -> bool {
                 let name = name_f.to_string_lossy();
                 if name.ends_with(".eml") {
                     let path_plus_name = format!("{}/{}", &real_spec, name);
-                    println!("Import: {path_plus_name}");
+                    outputln!("Import: {path_plus_name}");
                     if poke_eml_file(context, path_plus_name).await.is_ok() {
                         read_cnt += 1
                     }
@@ -149,7 +149,7 @@ async fn poke_spec(context: &Context, spec: Option<&str>) -> bool {
             return false;
         }
     }
-    println!("Import: {} items read from \"{}\".", read_cnt, &real_spec);
+    outputln!("Import: {} items read from \"{}\".", read_cnt, &real_spec);
     if read_cnt > 0 {
         context.emit_msgs_changed_without_ids();
     }
@@ -185,7 +185,7 @@ async fn log_msg(context: &Context, prefix: impl AsRef<str>, msg: &Message) {
 
     let temp2 = timestamp_to_str(msg.get_timestamp());
     let msgtext = msg.get_text();
-    println!(
+    outputln!(
         "{}{}{}{}: {} (Contact#{}): {} {}{}{}{}{}{}{} [{}]",
         prefix.as_ref(),
         msg.get_id(),
@@ -247,14 +247,14 @@ async fn log_msglist(context: &Context, msglist: &[MsgId]) -> Result<()> {
     let mut lines_out = 0;
     for &msg_id in msglist {
         if msg_id == MsgId::new(DC_MSG_ID_DAYMARKER) {
-            println!(
+            outputln!(
                 "--------------------------------------------------------------------------------"
             );
 
             lines_out += 1
         } else if !msg_id.is_special() {
             if lines_out == 0 {
-                println!(
+                outputln!(
                     "--------------------------------------------------------------------------------",
                 );
                 lines_out += 1
@@ -264,7 +264,7 @@ async fn log_msglist(context: &Context, msglist: &[MsgId]) -> Result<()> {
         }
     }
     if lines_out > 0 {
-        println!(
+        outputln!(
             "--------------------------------------------------------------------------------"
         );
     }
@@ -302,7 +302,7 @@ async fn log_contactlist(context: &Context, contacts: &[ContactId]) -> Result<()
             );
         }
 
-        println!("Contact#{}: {}{}", *contact_id, line, line2);
+        outputln!("Contact#{}: {}{}", *contact_id, line, line2);
     }
     Ok(())
 }
@@ -327,7 +327,7 @@ pub async fn cmdline(context: Context, line: &str, chat_id: &mut ChatId) -> Resu
     match arg0 {
         "help" | "?" => match arg1 {
             // TODO: reuse commands definition in main.rs.
-            "imex" => println!(
+            "imex" => outputln!(
                 "====================Import/Export commands==\n\
                  initiate-key-transfer\n\
                  get-setupcodebegin <msg-id>\n\
@@ -335,20 +335,26 @@ pub async fn cmdline(context: Context, line: &str, chat_id: &mut ChatId) -> Resu
                  has-backup\n\
                  export-backup\n\
                  import-backup <backup-file>\n\
+                 verify-backup <backup-file> [<passphrase>]\n\
                  send-backup\n\
                  receive-backup <qr>\n\
-                 export-keys\n\
-                 import-keys\n\
+                 export-keys [<passphrase>]\n\
+                 import-keys <file-or-dir> [<passphrase>]\n\
+                 import-armored-key\n\
+                 import-peerstates <json-file>\n\
+                 rotate-key\n\
                  poke [<eml-file>|<folder>|<addr> <key-file>]\n\
                  reset <flags>\n\
                  stop\n\
                  ============================================="
             ),
-            _ => println!(
+            _ => outputln!(
                 "==========================Database commands==\n\
                  info\n\
                  open <file to open or create>\n\
                  close\n\
+                 open2 <file to open or create>\n\
+                 switch [<account-id>]\n\
                  set <configuration-key> [<value>]\n\
                  get <configuration-key>\n\
                  oauth2\n\
@@ -399,6 +405,8 @@ pub async fn cmdline(context: Context, line: &str, chat_id: &mut ChatId) -> Resu
                  ===========================Message commands==\n\
                  listmsgs <query>\n\
                  msginfo <msg-id>\n\
+                 dumpmime <msg-id>\n\
+                 verifymime <msg-id>\n\
                  download <msg-id>\n\
                  html <msg-id>\n\
                  listfresh\n\
@@ -434,7 +442,7 @@ pub async fn cmdline(context: Context, line: &str, chat_id: &mut ChatId) -> Resu
         },
         "initiate-key-transfer" => match initiate_key_transfer(&context).await {
             Ok(setup_code) => {
-                println!("Setup code for the transferred setup message: {setup_code}",)
+                outputln!("Setup code for the transferred setup message: {setup_code}",)
             }
             Err(err) => bail!("Failed to generate setup code: {}", err),
         },
@@ -444,7 +452,7 @@ pub async fn cmdline(context: Context, line: &str, chat_id: &mut ChatId) -> Resu
             let msg = Message::load_from_db(&context, msg_id).await?;
             if msg.is_setupmessage() {
                 let setupcodebegin = msg.get_setupcodebegin(&context).await;
-                println!(
+                outputln!(
                     "The setup code for setup message {} starts with: {}",
                     msg_id,
                     setupcodebegin.unwrap_or_default(),
@@ -472,7 +480,7 @@ pub async fn cmdline(context: Context, line: &str, chat_id: &mut ChatId) -> Resu
                 Some(arg2.to_string()),
             )
             .await?;
-            println!("Exported to {}.", dir.to_string_lossy());
+            outputln!("Exported to {}.", dir.to_string_lossy());
         }
         "import-backup" => {
             ensure!(!arg1.is_empty(), "Argument <backup-file> missing.");
@@ -484,10 +492,21 @@ pub async fn cmdline(context: Context, line: &str, chat_id: &mut ChatId) -> Resu
             )
             .await?;
         }
+        "verify-backup" => {
+            ensure!(!arg1.is_empty(), "Argument <backup-file> missing.");
+            let passphrase = Some(arg2.to_string()).filter(|p| !p.is_empty());
+            let report = verify_backup(&context, arg1.as_ref(), passphrase).await?;
+            outputln!("{report:#?}");
+            if report.is_ok() {
+                outputln!("Backup is OK.");
+            } else {
+                outputln!("Backup has problems.");
+            }
+        }
         "send-backup" => {
             let provider = BackupProvider::prepare(&context).await?;
             let qr = provider.qr();
-            println!("QR code: {}", format_backup(&qr)?);
+            outputln!("QR code: {}", format_backup(&qr)?);
             provider.await?;
         }
         "receive-backup" => {
@@ -497,11 +516,56 @@ pub async fn cmdline(context: Context, line: &str, chat_id: &mut ChatId) -> Resu
         }
         "export-keys" => {
             let dir = dirs::home_dir().unwrap_or_default();
-            imex(&context, ImexMode::ExportSelfKeys, dir.as_ref(), None).await?;
-            println!("Exported to {}.", dir.to_string_lossy());
+            let passphrase = Some(arg1.to_string()).filter(|p| !p.is_empty());
+            imex(&context, ImexMode::ExportSelfKeys, dir.as_ref(), passphrase).await?;
+            outputln!("Exported to {}.", dir.to_string_lossy());
         }
         "import-keys" => {
-            imex(&context, ImexMode::ImportSelfKeys, arg1.as_ref(), None).await?;
+            ensure!(!arg1.is_empty(), "Argument <file-or-dir> missing.");
+            let passphrase = Some(arg2.to_string()).filter(|p| !p.is_empty());
+            imex(
+                &context,
+                ImexMode::ImportSelfKeys,
+                arg1.as_ref(),
+                passphrase,
+            )
+            .await?;
+        }
+        "import-armored-key" => {
+            outputln!("Paste the ASCII-armored key, it is read until the END line:");
+            let mut armored = String::new();
+            for line in std::io::stdin().lines() {
+                let line = line?;
+                armored.push_str(&line);
+                armored.push('\n');
+                if line.starts_with("-----END PGP") {
+                    break;
+                }
+            }
+            let imported = context.import_key_from_armored(&armored).await?;
+            outputln!(
+                "Imported {} key {}",
+                if imported.is_secret {
+                    "secret"
+                } else {
+                    "public"
+                },
+                imported.fingerprint
+            );
+            for user_id in &imported.user_ids {
+                outputln!("  {user_id}");
+            }
+        }
+        "import-peerstates" => {
+            ensure!(!arg1.is_empty(), "Argument <json-file> missing.");
+            let imported = import_peerstates(&context, arg1.as_ref()).await?;
+            outputln!("Imported {imported} peer states.");
+        }
+        "rotate-key" => {
+            let fingerprint = context.rotate_key().await?;
+            outputln!("New key: {fingerprint}");
+            let contact_ids = context.get_contacts_with_old_self_key().await?;
+            outputln!("{} contact(s) still use the old key.", contact_ids.len());
         }
         "poke" => {
             ensure!(poke_spec(&context, Some(arg1)).await, "Poke failed");
@@ -525,10 +589,10 @@ pub async fn cmdline(context: Context, line: &str, chat_id: &mut ChatId) -> Resu
             ensure!(!arg1.is_empty(), "Argument <key> missing.");
             let key = config::Config::from_str(arg1)?;
             let val = context.get_config(key).await;
-            println!("{key}={val:?}");
+            outputln!("{key}={val:?}");
         }
         "info" => {
-            println!("{:#?}", context.get_info().await);
+            outputln!("{:#?}", context.get_info().await);
         }
         "connectivity" => {
             let file = dirs::home_dir()
@@ -537,7 +601,7 @@ pub async fn cmdline(context: Context, line: &str, chat_id: &mut ChatId) -> Resu
             match context.get_connectivity_html().await {
                 Ok(html) => {
                     fs::write(&file, html).await?;
-                    println!("Report written to: {file:#?}");
+                    outputln!("Report written to: {file:#?}");
                 }
                 Err(err) => {
                     bail!("Failed to get connectivity html: {}", err);
@@ -568,13 +632,13 @@ pub async fn cmdline(context: Context, line: &str, chat_id: &mut ChatId) -> Resu
 
             let cnt = chatlist.len();
             if cnt > 0 {
-                println!(
+                outputln!(
                     "================================================================================"
                 );
 
                 for i in (0..cnt).rev() {
                     let chat = Chat::load_from_db(&context, chatlist.get_chat_id(i)?).await?;
-                    println!(
+                    outputln!(
                         "{}#{}: {} [{} fresh] {}{}{}{}",
                         chat_prefix(&chat),
                         chat.get_id(),
@@ -606,7 +670,7 @@ pub async fn cmdline(context: Context, line: &str, chat_id: &mut ChatId) -> Resu
                         }
                     };
                     let timestr = timestamp_to_str(summary.timestamp);
-                    println!(
+                    outputln!(
                         "{}{}{} [{}]{}",
                         summary
                             .prefix
@@ -620,16 +684,16 @@ pub async fn cmdline(context: Context, line: &str, chat_id: &mut ChatId) -> Resu
                             ""
                         },
                     );
-                    println!(
+                    outputln!(
                         "================================================================================"
                     );
                 }
             }
             if location::is_sending_locations_to_chat(&context, None).await? {
-                println!("Location streaming enabled.");
+                outputln!("Location streaming enabled.");
             }
-            println!("{cnt} chats");
-            println!("{time_needed:?} to create this list");
+            outputln!("{cnt} chats");
+            outputln!("{time_needed:?} to create this list");
         }
         "start-realtime" => {
             if arg1.is_empty() {
@@ -639,10 +703,10 @@ pub async fn cmdline(context: Context, line: &str, chat_id: &mut ChatId) -> Resu
             let res = send_webxdc_realtime_advertisement(&context, msg_id).await?;
 
             if let Some(res) = res {
-                println!("waiting for peer channel join");
+                outputln!("waiting for peer channel join");
                 res.await?;
             }
-            println!("joined peer channel");
+            outputln!("joined peer channel");
         }
         "send-realtime" => {
             if arg1.is_empty() {
@@ -653,7 +717,7 @@ pub async fn cmdline(context: Context, line: &str, chat_id: &mut ChatId) -> Resu
             }
             let msg_id = MsgId::new(arg1.parse()?);
             send_webxdc_realtime_data(&context, msg_id, arg2.as_bytes().to_vec()).await?;
-            println!("sent realtime message");
+            outputln!("sent realtime message");
         }
         "chat" => {
             if sel_chat.is_none() && arg1.is_empty() {
@@ -661,7 +725,7 @@ pub async fn cmdline(context: Context, line: &str, chat_id: &mut ChatId) -> Resu
             }
             if !arg1.is_empty() {
                 let id = ChatId::new(arg1.parse()?);
-                println!("Selecting chat {id}");
+                outputln!("Selecting chat {id}");
                 sel_chat = Some(Chat::load_from_db(&context, id).await?);
                 *chat_id = id;
             }
@@ -700,7 +764,7 @@ pub async fn cmdline(context: Context, line: &str, chat_id: &mut ChatId) -> Resu
             } else {
                 format!("{} member(s)", members.len())
             };
-            println!(
+            outputln!(
                 "{}#{}: {} [{}]{}{}{} {}",
                 chat_prefix(sel_chat),
                 sel_chat.get_id(),
@@ -730,7 +794,7 @@ pub async fn cmdline(context: Context, line: &str, chat_id: &mut ChatId) -> Resu
                 log_msg(&context, "Draft", &draft).await;
             }
 
-            println!(
+            outputln!(
                 "{} messages.",
                 sel_chat.get_id().get_msg_cnt(&context).await?
             );
@@ -739,7 +803,7 @@ pub async fn cmdline(context: Context, line: &str, chat_id: &mut ChatId) -> Resu
             chat::marknoticed_chat(&context, sel_chat.get_id()).await?;
             let time_noticed_needed = time_noticed_start.elapsed().unwrap_or_default();
 
-            println!(
+            outputln!(
                 "{time_needed:?} to create this list, {time_noticed_needed:?} to mark all messages as noticed."
             );
         }
@@ -748,26 +812,26 @@ pub async fn cmdline(context: Context, line: &str, chat_id: &mut ChatId) -> Resu
             let contact_id = ContactId::new(arg1.parse()?);
             let chat_id = ChatId::create_for_contact(&context, contact_id).await?;
 
-            println!("Single#{chat_id} created successfully.",);
+            outputln!("Single#{chat_id} created successfully.",);
         }
         "creategroup" => {
             ensure!(!arg1.is_empty(), "Argument <name> missing.");
             let chat_id =
                 chat::create_group_chat(&context, ProtectionStatus::Unprotected, arg1).await?;
 
-            println!("Group#{chat_id} created successfully.");
+            outputln!("Group#{chat_id} created successfully.");
         }
         "createbroadcast" => {
             let chat_id = chat::create_broadcast_list(&context).await?;
 
-            println!("Broadcast#{chat_id} created successfully.");
+            outputln!("Broadcast#{chat_id} created successfully.");
         }
         "createprotected" => {
             ensure!(!arg1.is_empty(), "Argument <name> missing.");
             let chat_id =
                 chat::create_group_chat(&context, ProtectionStatus::Protected, arg1).await?;
 
-            println!("Group#{chat_id} created and protected successfully.");
+            outputln!("Group#{chat_id} created and protected successfully.");
         }
         "addmember" => {
             ensure!(sel_chat.is_some(), "No chat selected");
@@ -776,7 +840,7 @@ pub async fn cmdline(context: Context, line: &str, chat_id: &mut ChatId) -> Resu
             let contact_id_0 = ContactId::new(arg1.parse()?);
             chat::add_contact_to_chat(&context, sel_chat.as_ref().unwrap().get_id(), contact_id_0)
                 .await?;
-            println!("Contact added to chat.");
+            outputln!("Contact added to chat.");
         }
         "removemember" => {
             ensure!(sel_chat.is_some(), "No chat selected.");
@@ -789,7 +853,7 @@ pub async fn cmdline(context: Context, line: &str, chat_id: &mut ChatId) -> Resu
             )
             .await?;
 
-            println!("Contact added to chat.");
+            outputln!("Contact added to chat.");
         }
         "groupname" => {
             ensure!(sel_chat.is_some(), "No chat selected.");
@@ -801,7 +865,7 @@ pub async fn cmdline(context: Context, line: &str, chat_id: &mut ChatId) -> Resu
             )
             .await?;
 
-            println!("Chat name set");
+            outputln!("Chat name set");
         }
         "groupimage" => {
             ensure!(sel_chat.is_some(), "No chat selected.");
@@ -810,24 +874,24 @@ pub async fn cmdline(context: Context, line: &str, chat_id: &mut ChatId) -> Resu
             chat::set_chat_profile_image(&context, sel_chat.as_ref().unwrap().get_id(), arg1)
                 .await?;
 
-            println!("Chat image set");
+            outputln!("Chat image set");
         }
         "chatinfo" => {
             ensure!(sel_chat.is_some(), "No chat selected.");
             let sel_chat_id = sel_chat.as_ref().unwrap().get_id();
 
             let contacts = chat::get_chat_contacts(&context, sel_chat_id).await?;
-            println!("Memberlist:");
+            outputln!("Memberlist:");
 
             log_contactlist(&context, &contacts).await?;
-            println!("{} contacts", contacts.len());
+            outputln!("{} contacts", contacts.len());
 
             let similar_chats = sel_chat_id.get_similar_chat_ids(&context).await?;
             if !similar_chats.is_empty() {
-                println!("Similar chats: ");
+                outputln!("Similar chats: ");
                 for (similar_chat_id, metric) in similar_chats {
                     let similar_chat = Chat::load_from_db(&context, similar_chat_id).await?;
-                    println!(
+                    outputln!(
                         "{} (#{}) {:.1}",
                         similar_chat.name,
                         similar_chat_id,
@@ -836,7 +900,7 @@ pub async fn cmdline(context: Context, line: &str, chat_id: &mut ChatId) -> Resu
                 }
             }
 
-            println!(
+            outputln!(
                 "Location streaming: {}",
                 location::is_sending_locations_to_chat(
                     &context,
@@ -860,7 +924,7 @@ pub async fn cmdline(context: Context, line: &str, chat_id: &mut ChatId) -> Resu
             let default_marker = "-".to_string();
             for location in &locations {
                 let marker = location.marker.as_ref().unwrap_or(&default_marker);
-                println!(
+                outputln!(
                     "Loc#{}: {}: lat={} lng={} acc={} Chat#{} Contact#{} {} {}",
                     location.location_id,
                     timestamp_to_str(location.timestamp),
@@ -874,7 +938,7 @@ pub async fn cmdline(context: Context, line: &str, chat_id: &mut ChatId) -> Resu
                 );
             }
             if locations.is_empty() {
-                println!("No locations.");
+                outputln!("No locations.");
             }
         }
         "sendlocations" => {
@@ -888,7 +952,7 @@ pub async fn cmdline(context: Context, line: &str, chat_id: &mut ChatId) -> Resu
                 seconds,
             )
             .await?;
-            println!(
+            outputln!(
                 "Locations will be sent to Chat#{} for {} seconds. Use 'setlocation <lat> <lng>' to play around.",
                 sel_chat.as_ref().unwrap().get_id(),
                 seconds
@@ -904,9 +968,9 @@ pub async fn cmdline(context: Context, line: &str, chat_id: &mut ChatId) -> Resu
 
             let continue_streaming = location::set(&context, latitude, longitude, 0.).await?;
             if continue_streaming {
-                println!("Success, streaming should be continued.");
+                outputln!("Success, streaming should be continued.");
             } else {
-                println!("Success, streaming can be stopped.");
+                outputln!("Success, streaming can be stopped.");
             }
         }
         "dellocations" => {
@@ -956,8 +1020,8 @@ pub async fn cmdline(context: Context, line: &str, chat_id: &mut ChatId) -> Resu
             chat::send_msg(&context, sel_chat.as_ref().unwrap().get_id(), &mut msg).await?;
         }
         "sendsyncmsg" => match context.send_sync_msg().await? {
-            Some(msg_id) => println!("sync message sent as {msg_id}."),
-            None => println!("sync message not needed."),
+            Some(msg_id) => outputln!("sync message sent as {msg_id}."),
+            None => outputln!("sync message not needed."),
         },
         "sendupdate" => {
             ensure!(
@@ -983,7 +1047,7 @@ pub async fn cmdline(context: Context, line: &str, chat_id: &mut ChatId) -> Resu
             let time_needed = time_start.elapsed().unwrap_or_default();
 
             log_msglist(&context, &msglist).await?;
-            println!(
+            outputln!(
                 "{}{} messages for {}search of \"{}\"",
                 msglist.len(),
                 if msglist.len() == 1000 { "+" } else { "" },
@@ -994,7 +1058,7 @@ pub async fn cmdline(context: Context, line: &str, chat_id: &mut ChatId) -> Resu
                 },
                 query,
             );
-            println!("{time_needed:?} to create this list");
+            outputln!("{time_needed:?} to create this list");
         }
         "draft" => {
             ensure!(sel_chat.is_some(), "No chat selected.");
@@ -1008,7 +1072,7 @@ pub async fn cmdline(context: Context, line: &str, chat_id: &mut ChatId) -> Resu
                     .get_id()
                     .set_draft(&context, Some(&mut draft))
                     .await?;
-                println!("Draft saved.");
+                outputln!("Draft saved.");
             } else {
                 sel_chat
                     .as_ref()
@@ -1016,7 +1080,7 @@ pub async fn cmdline(context: Context, line: &str, chat_id: &mut ChatId) -> Resu
                     .get_id()
                     .set_draft(&context, None)
                     .await?;
-                println!("Draft deleted.");
+                outputln!("Draft deleted.");
             }
         }
         "devicemsg" => {
@@ -1037,15 +1101,15 @@ pub async fn cmdline(context: Context, line: &str, chat_id: &mut ChatId) -> Resu
                 Viewtype::Video,
             )
             .await?;
-            println!("{} images or videos: ", images.len());
+            outputln!("{} images or videos: ", images.len());
             for (i, data) in images.iter().enumerate() {
                 if 0 == i {
-                    print!("{data}");
+                    output!("{data}");
                 } else {
-                    print!(", {data}");
+                    output!(", {data}");
                 }
             }
-            println!();
+            outputln!();
         }
         "archive" | "unarchive" | "pin" | "unpin" => {
             ensure!(!arg1.is_empty(), "Argument <chat-id> missing.");
@@ -1099,12 +1163,35 @@ pub async fn cmdline(context: Context, line: &str, chat_id: &mut ChatId) -> Resu
             ensure!(!arg1.is_empty(), "Argument <msg-id> missing.");
             let id = MsgId::new(arg1.parse()?);
             let res = id.get_info(&context).await?;
-            println!("{res}");
+            outputln!("{res}");
+        }
+        "dumpmime" => {
+            ensure!(!arg1.is_empty(), "Argument <msg-id> missing.");
+            let id = MsgId::new(arg1.parse()?);
+            let raw = message::get_mime_headers(&context, id).await?;
+            ensure!(
+                !raw.is_empty(),
+                "No MIME saved for {id}, use `set save_mime_headers 1` before receiving messages."
+            );
+            outputln!("=== Original MIME ===\n{}", String::from_utf8_lossy(&raw));
+            let decrypted = message::get_decrypted_mime(&context, id).await?;
+            if decrypted != raw {
+                outputln!(
+                    "=== Decrypted MIME ===\n{}",
+                    String::from_utf8_lossy(&decrypted)
+                );
+            }
+        }
+        "verifymime" => {
+            ensure!(!arg1.is_empty(), "Argument <msg-id> missing.");
+            let id = MsgId::new(arg1.parse()?);
+            let report = message::verify_mime(&context, id).await?;
+            output!("{report}");
         }
         "download" => {
             ensure!(!arg1.is_empty(), "Argument <msg-id> missing.");
             let id = MsgId::new(arg1.parse()?);
-            println!("Scheduling download for {id:?}");
+            outputln!("Scheduling download for {id:?}");
             id.download_full(&context).await?;
         }
         "html" => {
@@ -1115,13 +1202,13 @@ pub async fn cmdline(context: Context, line: &str, chat_id: &mut ChatId) -> Resu
                 .join(format!("msg-{}.html", id.to_u32()));
             let html = id.get_html(&context).await?.unwrap_or_default();
             fs::write(&file, html).await?;
-            println!("HTML written to: {file:#?}");
+            outputln!("HTML written to: {file:#?}");
         }
         "listfresh" => {
             let msglist = context.get_fresh_msgs().await?;
 
             log_msglist(&context, &msglist).await?;
-            print!("{} fresh messages.", msglist.len());
+            output!("{} fresh messages.", msglist.len());
         }
         "forward" => {
             ensure!(
@@ -1171,7 +1258,7 @@ pub async fn cmdline(context: Context, line: &str, chat_id: &mut ChatId) -> Resu
             )
             .await?;
             log_contactlist(&context, &contacts).await?;
-            println!("{} contacts.", contacts.len());
+            outputln!("{} contacts.", contacts.len());
         }
         "addcontact" => {
             ensure!(!arg1.is_empty(), "Arguments [<name>] <addr> expected.");
@@ -1214,7 +1301,7 @@ pub async fn cmdline(context: Context, line: &str, chat_id: &mut ChatId) -> Resu
                 }
             }
 
-            println!("{res}");
+            outputln!("{res}");
         }
         "delcontact" => {
             ensure!(!arg1.is_empty(), "Argument <contact-id> missing.");
@@ -1233,18 +1320,18 @@ pub async fn cmdline(context: Context, line: &str, chat_id: &mut ChatId) -> Resu
         "listblocked" => {
             let contacts = Contact::get_all_blocked(&context).await?;
             log_contactlist(&context, &contacts).await?;
-            println!("{} blocked contacts.", contacts.len());
+            outputln!("{} blocked contacts.", contacts.len());
         }
         "checkqr" => {
             ensure!(!arg1.is_empty(), "Argument <qr-content> missing.");
             let qr = check_qr(&context, arg1).await?;
-            println!("qr={qr:?}");
+            outputln!("qr={qr:?}");
         }
         "setqr" => {
             ensure!(!arg1.is_empty(), "Argument <qr-content> missing.");
             match set_config_from_qr(&context, arg1).await {
-                Ok(()) => println!("Config set from QR code, you can now call 'configure'"),
-                Err(err) => println!("Cannot set config from QR code: {err:?}"),
+                Ok(()) => outputln!("Config set from QR code, you can now call 'configure'"),
+                Err(err) => outputln!("Cannot set config from QR code: {err:?}"),
             }
         }
         "providerinfo" => {
@@ -1254,17 +1341,17 @@ pub async fn cmdline(context: Context, line: &str, chat_id: &mut ChatId) -> Resu
                 .await?;
             match provider::get_provider_info(&context, arg1, socks5_enabled).await {
                 Some(info) => {
-                    println!("Information for provider belonging to {arg1}:");
-                    println!("status: {}", info.status as u32);
-                    println!("before_login_hint: {}", info.before_login_hint);
-                    println!("after_login_hint: {}", info.after_login_hint);
-                    println!("overview_page: {}", info.overview_page);
+                    outputln!("Information for provider belonging to {arg1}:");
+                    outputln!("status: {}", info.status as u32);
+                    outputln!("before_login_hint: {}", info.before_login_hint);
+                    outputln!("after_login_hint: {}", info.after_login_hint);
+                    outputln!("overview_page: {}", info.overview_page);
                     for server in info.server.iter() {
-                        println!("server: {}:{}", server.hostname, server.port,);
+                        outputln!("server: {}:{}", server.hostname, server.port,);
                     }
                 }
                 None => {
-                    println!("No information for provider belonging to {arg1} found.");
+                    outputln!("No information for provider belonging to {arg1} found.");
                 }
             }
         }
@@ -1273,7 +1360,7 @@ pub async fn cmdline(context: Context, line: &str, chat_id: &mut ChatId) -> Resu
 
             if let Ok(buf) = read_file(&context, &arg1).await {
                 let (width, height) = get_filemeta(&buf)?;
-                println!("width={width}, height={height}");
+                outputln!("width={width}, height={height}");
             } else {
                 bail!("Command failed.");
             }
@@ -1283,7 +1370,7 @@ pub async fn cmdline(context: Context, line: &str, chat_id: &mut ChatId) -> Resu
             let seconds = arg1.parse()?;
             let device_cnt = message::estimate_deletion_cnt(&context, false, seconds).await?;
             let server_cnt = message::estimate_deletion_cnt(&context, true, seconds).await?;
-            println!(
+            outputln!(
                 "estimated count of messages older than {seconds} seconds:\non device: {device_cnt}\non server: {server_cnt}"
             );
         }
@@ -2,25 +2,35 @@
 //! This is a CLI program and a little testing frame.  This file must not be
 //! included when using Delta Chat Core as a library.
 //!
-//! Usage:  cargo run --example repl --release -- <databasefile>
+//! Usage:  cargo run --example repl --release -- <databasefile> [--exec "cmd; cmd"] [--json]
 //! All further options can be set using the set-command (type ? for help).
+//!
+//! With `--exec`, the given `;`-separated commands are executed and the program exits,
+//! `;` can be quoted with `"` or `'` or escaped with `\`.
+//! If standard input is not a terminal, commands are read from it line by line.
+//! `--json` prints a JSON object with the result and the output of each command,
+//! log output is written to standard error in this case.
+//!
+//! `open2 <databasefile>` opens another account and `switch <id>` selects the account
+//! commands are executed on, e.g. to test securejoin between two local accounts.
 
 #[macro_use]
 extern crate deltachat;
 
 use std::borrow::Cow::{self, Borrowed, Owned};
-use std::io::{self, Write};
-use std::process::Command;
+use std::io::{self, BufRead, IsTerminal};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Mutex;
 
 use ansi_term::Color;
-use anyhow::{bail, Error};
+use anyhow::{bail, ensure, Error};
 use deltachat::chat::ChatId;
 use deltachat::config;
 use deltachat::context::*;
 use deltachat::oauth2::*;
-use deltachat::qr_code_generator::get_securejoin_qr_svg;
+use deltachat::qr_code_generator::{generate_qr_text, get_securejoin_qr_svg};
 use deltachat::securejoin::*;
-use deltachat::EventType;
+use deltachat::{Event, EventType};
 use log::{error, info, warn};
 use rustyline::completion::{Completer, FilenameCompleter, Pair};
 use rustyline::error::ReadlineError;
@@ -34,38 +44,81 @@
 use tokio::runtime::Handle;
 use tracing_subscriber::EnvFilter;
 
+/// Output of the command being executed if it is collected for the JSON result.
+static OUTPUT: Mutex<Option<String>> = Mutex::new(None);
+
+/// Prints command output or collects it if a JSON result is created.
+fn write_output(text: &str) {
+    let mut output = OUTPUT.lock().unwrap_or_else(|err| err.into_inner());
+    if let Some(output) = output.as_mut() {
+        output.push_str(text);
+        return;
+    }
+    drop(output);
+    print!("{text}");
+}
+
+/// Like `print!`, but the output is collected in JSON mode.
+macro_rules! output {
+    ($($arg:tt)*) => {
+        $crate::write_output(&format!($($arg)*))
+    };
+}
+
+/// Like `println!`, but the output is collected in JSON mode.
+macro_rules! outputln {
+    () => {
+        $crate::write_output("\n")
+    };
+    ($($arg:tt)*) => {
+        $crate::write_output(&format!("{}\n", format_args!($($arg)*)))
+    };
+}
+
 mod cmdline;
 use self::cmdline::*;
 
+/// Set once more than one account is opened,
+/// from then on events are prefixed with the account ID.
+static MULTI_ACCOUNT: AtomicBool = AtomicBool::new(false);
+
 /// Event Handler
-fn receive_event(event: EventType) {
+fn receive_event(event: Event) {
     let yellow = Color::Yellow.normal();
-    match event {
+    let prefix = if MULTI_ACCOUNT.load(Ordering::Relaxed) {
+        format!("[{}] ", event.id)
+    } else {
+        String::new()
+    };
+    match event.typ {
         EventType::Info(msg) => {
             /* do not show the event as this would fill the screen */
-            info!("{}", msg);
+            info!("{prefix}{}", msg);
         }
         EventType::SmtpConnected(msg) => {
-            info!("[SMTP_CONNECTED] {}", msg);
+            info!("{prefix}[SMTP_CONNECTED] {}", msg);
         }
         EventType::ImapConnected(msg) => {
-            info!("[IMAP_CONNECTED] {}", msg);
+            info!("{prefix}[IMAP_CONNECTED] {}", msg);
         }
         EventType::SmtpMessageSent(msg) => {
-            info!("[SMTP_MESSAGE_SENT] {}", msg);
+            info!("{prefix}[SMTP_MESSAGE_SENT] {}", msg);
         }
         EventType::Warning(msg) => {
-            warn!("{}", msg);
+            warn!("{prefix}{}", msg);
         }
         EventType::Error(msg) => {
-            error!("{}", msg);
+            error!("{prefix}{}", msg);
+        }
+        EventType::ErrorRepeated { msg, count, .. } => {
+            error!("{prefix}{} (repeated {} times)", msg, count);
         }
         EventType::ErrorSelfNotInGroup(msg) => {
-            error!("[SELF_NOT_IN_GROUP] {}", msg);
+            error!("{prefix}[SELF_NOT_IN_GROUP] {}", msg);
         }
         EventType::MsgsChanged { chat_id, msg_id } => {
             info!(
-                "{}",
+                "{prefix}{}",
                 yellow.paint(format!(
                     "Received MSGS_CHANGED(chat_id={chat_id}, msg_id={msg_id})",
                 ))
@@ -77,56 +130,151 @@ fn receive_event(event: EventType) {
             contact_id,
         } => {
             info!(
-                "{}",
+                "{prefix}{}",
                 yellow.paint(format!(
                     "Received REACTIONS_CHANGED(chat_id={chat_id}, msg_id={msg_id}, contact_id={contact_id})"
                 ))
             );
         }
         EventType::ContactsChanged(_) => {
-            info!("{}", yellow.paint("Received CONTACTS_CHANGED()"));
+            info!("{prefix}{}", yellow.paint("Received CONTACTS_CHANGED()"));
         }
         EventType::LocationChanged(contact) => {
             info!(
-                "{}",
+                "{prefix}{}",
                 yellow.paint(format!("Received LOCATION_CHANGED(contact={contact:?})"))
             );
         }
         EventType::ConfigureProgress { progress, comment } => {
             if let Some(comment) = comment {
                 info!(
-                    "{}",
+                    "{prefix}{}",
                     yellow.paint(format!(
                         "Received CONFIGURE_PROGRESS({progress} ‰, {comment})"
                     ))
                 );
             } else {
                 info!(
-                    "{}",
+                    "{prefix}{}",
                     yellow.paint(format!("Received CONFIGURE_PROGRESS({progress} ‰)"))
                 );
             }
         }
         EventType::ImexProgress(progress) => {
             info!(
-                "{}",
+                "{prefix}{}",
                 yellow.paint(format!("Received IMEX_PROGRESS({progress} ‰)"))
             );
         }
         EventType::ImexFileWritten(file) => {
             info!(
-                "{}",
+                "{prefix}{}",
                 yellow.paint(format!("Received IMEX_FILE_WRITTEN({})", file.display()))
             );
         }
+        EventType::ProxyChanged { host, port } => {
+            info!(
+                "{prefix}{}",
+                yellow.paint(format!("Received PROXY_CHANGED({host}:{port})"))
+            );
+        }
         EventType::ChatModified(chat) => {
             info!(
-                "{}",
+                "{prefix}{}",
                 yellow.paint(format!("Received CHAT_MODIFIED({chat})"))
             );
         }
-        _ => {
-            info!("Received {:?}", event);
+        typ => {
+            info!("{prefix}Received {:?}", typ);
+        }
+    }
+}
+
+/// Accounts opened in the REPL.
+///
+/// Commands are executed on the currently selected account,
+/// `open2` opens another one and `switch` changes the selection,
+/// so flows between several local accounts can be tested in one session.
+#[derive(Default)]
+struct Repl {
+    /// Opened contexts together with the chat selected in each of them.
+    accounts: Vec<(Context, ChatId)>,
+
+    /// Index of the selected account in `accounts`.
+    current: usize,
+}
+
+impl Repl {
+    /// Opens a context and starts handling its events.
+    ///
+    /// Returns the ID of the new account.
+    async fn open(&mut self, dbfile: &str) -> Result<u32, Error> {
+        let id = u32::try_from(self.accounts.len())? + 1;
+        let context = ContextBuilder::new(dbfile.into())
+            .with_id(id)
+            .open()
+            .await?;
+
+        let events = context.get_event_emitter();
+        tokio::task::spawn(async move {
+            while let Some(event) = events.recv().await {
+                receive_event(event);
+            }
+        });
+
+        self.accounts.push((context, ChatId::default()));
+        if self.accounts.len() > 1 {
+            MULTI_ACCOUNT.store(true, Ordering::Relaxed);
+        }
+        Ok(id)
+    }
+
+    /// Returns the selected context.
+    fn context(&self) -> &Context {
+        &self.accounts[self.current].0
+    }
+
+    /// Selects the account with the given ID.
+    fn switch(&mut self, id: u32) -> Result<(), Error> {
+        let Some(index) = (id as usize)
+            .checked_sub(1)
+            .filter(|&index| index < self.accounts.len())
+        else {
+            bail!("No account with ID {id}, see `switch` for a list.");
+        };
+        self.current = index;
+        Ok(())
+    }
+
+    /// Prints all opened accounts, marking the selected one.
+    async fn print_accounts(&self) -> Result<(), Error> {
+        for (index, (context, _)) in self.accounts.iter().enumerate() {
+            let marker = if index == self.current { '*' } else { ' ' };
+            let addr = context
+                .get_config(config::Config::ConfiguredAddr)
+                .await?
+                .unwrap_or_else(|| "unconfigured".to_string());
+            println!(
+                "{marker} {}: {addr} ({})",
+                context.get_id(),
+                context.get_dbfile().display()
+            );
+        }
+        Ok(())
+    }
+
+    /// Returns the prompt for the interactive mode.
+    fn prompt(&self) -> String {
+        if self.accounts.len() > 1 {
+            format!("[{}]> ", self.context().get_id())
+        } else {
+            "> ".to_string()
+        }
+    }
+
+    async fn stop_io(&self) {
+        for (context, _) in &self.accounts {
+            context.stop_io().await;
         }
     }
 }
@@ -152,24 +300,30 @@ fn complete(
     }
 }
 
-const IMEX_COMMANDS: [&str; 13] = [
+const IMEX_COMMANDS: [&str; 17] = [
     "initiate-key-transfer",
     "get-setupcodebegin",
     "continue-key-transfer",
     "has-backup",
     "export-backup",
     "import-backup",
+    "verify-backup",
     "send-backup",
     "receive-backup",
     "export-keys",
     "import-keys",
+    "import-armored-key",
+    "import-peerstates",
+    "rotate-key",
     "poke",
     "reset",
     "stop",
 ];
 
-const DB_COMMANDS: [&str; 10] = [
+const DB_COMMANDS: [&str; 12] = [
     "info",
+    "open2",
+    "switch",
     "set",
     "get",
     "oauth2",
@@ -219,9 +373,11 @@ fn complete(
     "accept",
     "blockchat",
 ];
-const MESSAGE_COMMANDS: [&str; 9] = [
+const MESSAGE_COMMANDS: [&str; 11] = [
     "listmsgs",
     "msginfo",
+    "dumpmime",
+    "verifymime",
     "listfresh",
     "forward",
     "resend",
@@ -279,12 +435,15 @@ fn hint(&self, line: &str, pos: usize, ctx: &RustyContext<'_>) -> Option<Self::H
     }
 }
 
-static COLORED_PROMPT: &str = "\x1b[1;32m> \x1b[0m";
+static COLORED_PROMPT_START: &str = "\x1b[1;32m";
+static COLORED_PROMPT_END: &str = "\x1b[0m";
 
 impl Highlighter for DcHelper {
     fn highlight_prompt<'b, 's: 'b, 'p: 'b>(&self, prompt: &'p str, default: bool) -> Cow<'b, str> {
         if default {
-            Borrowed(COLORED_PROMPT)
+            Owned(format!(
+                "{COLORED_PROMPT_START}{prompt}{COLORED_PROMPT_END}"
+            ))
         } else {
             Borrowed(prompt)
         }
@@ -306,33 +465,155 @@ fn highlight_char(&self, line: &str, pos: usize, forced: bool) -> bool {
 impl Helper for DcHelper {}
 impl Validator for DcHelper {}
 
-async fn start(args: Vec<String>) -> Result<(), Error> {
-    if args.len() < 2 {
-        println!("Error: Bad arguments, expected [db-name].");
-        bail!("No db-name specified");
+/// Command line options.
+#[derive(Debug, Default)]
+struct Options {
+    /// Path to the database file.
+    dbfile: String,
+
+    /// Commands to execute non-interactively, separated by `;`.
+    exec: Option<String>,
+
+    /// Whether to print a JSON object with the result of each command.
+    json: bool,
+}
+
+impl Options {
+    fn parse(args: Vec<String>) -> Result<Self, Error> {
+        let mut options = Options::default();
+        let mut args = args.into_iter().skip(1);
+        while let Some(arg) = args.next() {
+            match arg.as_str() {
+                "--exec" => {
+                    let Some(commands) = args.next() else {
+                        bail!("--exec requires an argument");
+                    };
+                    options.exec = Some(commands);
+                }
+                "--json" => options.json = true,
+                _ if arg.starts_with("--") => bail!("Unknown option {arg:?}"),
+                _ if options.dbfile.is_empty() => options.dbfile = arg,
+                _ => bail!("Unexpected argument {arg:?}"),
+            }
+        }
+        if options.dbfile.is_empty() {
+            println!("Error: Bad arguments, expected [db-name].");
+            bail!("No db-name specified");
+        }
+        Ok(options)
     }
-    let context = ContextBuilder::new(args[1].clone().into())
-        .with_id(1)
-        .open()
-        .await?;
+}
 
-    let events = context.get_event_emitter();
-    tokio::task::spawn(async move {
-        while let Some(event) = events.recv().await {
-            receive_event(event.typ);
+/// Splits `commands` at `;`, removing quotes and escapes.
+///
+/// Inside quotes and after `\`, `;` does not separate commands.
+fn split_commands(commands: &str) -> Result<Vec<String>, Error> {
+    let mut result = Vec::new();
+    let mut current = String::new();
+    let mut quote = None;
+    let mut chars = commands.chars();
+    while let Some(c) = chars.next() {
+        match (c, quote) {
+            ('\\', _) => {
+                let Some(escaped) = chars.next() else {
+                    bail!("Trailing backslash in {commands:?}");
+                };
+                current.push(escaped);
+            }
+            ('"' | '\'', None) => quote = Some(c),
+            (c, Some(q)) if c == q => quote = None,
+            (';', None) => result.push(std::mem::take(&mut current)),
+            (c, _) => current.push(c),
         }
-    });
+    }
+    ensure!(quote.is_none(), "Unterminated quote in {commands:?}");
+    result.push(current);
+    Ok(result)
+}
+
+/// Executes a command, collecting its output if `json` is set,
+/// and prints the result as a single line JSON object then.
+async fn run_cmd(line: &str, repl: &mut Repl, json: bool) -> Result<ExitResult, Error> {
+    if !json {
+        return handle_cmd(line, repl).await;
+    }
+    *OUTPUT.lock().unwrap_or_else(|err| err.into_inner()) = Some(String::new());
+    let res = handle_cmd(line, repl).await;
+    let output = OUTPUT
+        .lock()
+        .unwrap_or_else(|err| err.into_inner())
+        .take()
+        .unwrap_or_default();
+    let json = match &res {
+        Ok(_) => serde_json::json!({ "command": line, "ok": true, "output": output }),
+        Err(err) => serde_json::json!({
+            "command": line,
+            "ok": false,
+            "output": output,
+            "error": format!("{err:#}"),
+        }),
+    };
+    println!("{json}");
+    res
+}
+
+/// Executes commands without user interaction.
+///
+/// Returns an error if any of the commands failed.
+async fn run_batch(
+    repl: &mut Repl,
+    commands: impl Iterator<Item = String>,
+    json: bool,
+) -> Result<(), Error> {
+    let mut failed = 0;
+    for line in commands {
+        let line = line.trim();
+        if line.is_empty() || line.starts_with('#') {
+            continue;
+        }
+        match run_cmd(line, repl, json).await {
+            Ok(ExitResult::Continue) => {}
+            Ok(ExitResult::Exit) => break,
+            Err(err) => {
+                if !json {
+                    println!("Error: {err:#}");
+                }
+                failed += 1;
+            }
+        }
+    }
+    if failed > 0 {
+        bail!("{failed} command(s) failed");
+    }
+    Ok(())
+}
+
+async fn start(options: Options) -> Result<(), Error> {
+    let mut repl = Repl::default();
+    repl.open(&options.dbfile).await?;
+
+    if let Some(exec) = &options.exec {
+        let commands = split_commands(exec)?;
+        let res = run_batch(&mut repl, commands.into_iter(), options.json).await;
+        repl.stop_io().await;
+        return res;
+    }
+    if !io::stdin().is_terminal() {
+        let commands: Vec<String> = io::stdin().lock().lines().collect::<Result<_, _>>()?;
+        let res = run_batch(&mut repl, commands.into_iter(), options.json).await;
+        repl.stop_io().await;
+        return res;
+    }
 
     println!("Delta Chat Core is awaiting your commands.");
+    let json = options.json;
 
     let config = Config::builder()
         .history_ignore_space(true)
         .completion_type(CompletionType::List)
         .edit_mode(EditMode::Emacs)
         .build();
-    let mut selected_chat = ChatId::default();
 
-    let ctx = context.clone();
     let input_loop = tokio::task::spawn_blocking(move || {
         let h = DcHelper {
             completer: FilenameCompleter::new(),
@@ -348,15 +629,15 @@ async fn start(args: Vec<String>) -> Result<(), Error> {
         }
 
         loop {
-            let p = "> ";
-            let readline = rl.readline(p);
+            let p = repl.prompt();
+            let readline = rl.readline(&p);
 
             match readline {
                 Ok(line) => {
                     // TODO: ignore "set mail_pw"
                     rl.add_history_entry(line.as_str())?;
                     let should_continue = Handle::current().block_on(async {
-                        match handle_cmd(line.trim(), ctx.clone(), &mut selected_chat).await {
+                        match run_cmd(line.trim(), &mut repl, json).await {
                             Ok(ExitResult::Continue) => true,
                             Ok(ExitResult::Exit) => {
                                 println!("Exiting ...");
@@ -386,11 +667,11 @@ async fn start(args: Vec<String>) -> Result<(), Error> {
 
         rl.save_history(".dc-history.txt")?;
         println!("history saved");
-        Ok::<_, Error>(())
+        Ok::<_, Error>(repl)
     });
 
-    context.stop_io().await;
-    input_loop.await??;
+    let repl = input_loop.await??;
+    repl.stop_io().await;
 
     Ok(())
 }
@@ -401,16 +682,24 @@ enum ExitResult {
     Exit,
 }
 
-async fn handle_cmd(
-    line: &str,
-    ctx: Context,
-    selected_chat: &mut ChatId,
-) -> Result<ExitResult, Error> {
+async fn handle_cmd(line: &str, repl: &mut Repl) -> Result<ExitResult, Error> {
     let mut args = line.splitn(2, ' ');
     let arg0 = args.next().unwrap_or_default();
     let arg1 = args.next().unwrap_or_default();
+    let ctx = repl.context().clone();
 
     match arg0 {
+        "open2" => {
+            ensure!(!arg1.is_empty(), "Argument <file> missing.");
+            let id = repl.open(arg1).await?;
+            outputln!("Opened account {id}, use `switch {id}` to select it.");
+        }
+        "switch" => {
+            if !arg1.is_empty() {
+                repl.switch(arg1.parse()?)?;
+            }
+            repl.print_accounts().await?;
+        }
         "connect" => {
             ctx.start_io().await;
         }
@@ -425,17 +714,17 @@ async fn handle_cmd(
                 let oauth2_url =
                     get_oauth2_url(&ctx, &addr, "chat.delta:/com.b44t.messenger").await?;
                 if oauth2_url.is_none() {
-                    println!("OAuth2 not available for {}.", &addr);
+                    outputln!("OAuth2 not available for {}.", &addr);
                 } else {
-                    println!("Open the following url, set mail_pw to the generated token and server_flags to 2:\n{}", oauth2_url.unwrap());
+                    outputln!("Open the following url, set mail_pw to the generated token and server_flags to 2:\n{}", oauth2_url.unwrap());
                 }
             } else {
-                println!("oauth2: set addr first.");
+                outputln!("oauth2: set addr first.");
             }
         }
         "clear" => {
-            println!("\n\n\n");
-            print!("\x1b[1;1H\x1b[2J");
+            outputln!("\n\n\n");
+            output!("\x1b[1;1H\x1b[2J");
         }
         "getqr" | "getbadqr" => {
             ctx.start_io().await;
@@ -445,13 +734,8 @@ async fn handle_cmd(
                 if arg0 == "getbadqr" && qr.len() > 40 {
                     qr.replace_range(12..22, "0000000000")
                 }
-                println!("{qr}");
-                let output = Command::new("qrencode")
-                    .args(["-t", "ansiutf8", qr.as_str(), "-o", "-"])
-                    .output()
-                    .expect("failed to execute process");
-                io::stdout().write_all(&output.stdout).unwrap();
-                io::stderr().write_all(&output.stderr).unwrap();
+                outputln!("{qr}");
+                output!("{}", generate_qr_text(&qr)?);
             }
         }
         "getqrsvg" => {
@@ -461,7 +745,7 @@ async fn handle_cmd(
             match get_securejoin_qr_svg(&ctx, group).await {
                 Ok(svg) => {
                     fs::write(&file, svg).await?;
-                    println!("QR code svg written to: {file:#?}");
+                    outputln!("QR code svg written to: {file:#?}");
                 }
                 Err(err) => {
                     bail!("Failed to get QR code svg: {}", err);
@@ -475,7 +759,10 @@ async fn handle_cmd(
             }
         }
         "exit" | "quit" => return Ok(ExitResult::Exit),
-        _ => cmdline(ctx.clone(), line, selected_chat).await?,
+        _ => {
+            let selected_chat = &mut repl.accounts[repl.current].1;
+            cmdline(ctx.clone(), line, selected_chat).await?
+        }
     }
 
     Ok(ExitResult::Continue)
@@ -483,14 +770,19 @@ async fn handle_cmd(
 
 #[tokio::main]
 async fn main() -> Result<(), Error> {
-    tracing_subscriber::fmt()
-        .with_env_filter(
-            EnvFilter::from_default_env().add_directive("deltachat_repl=info".parse()?),
-        )
-        .init();
-
-    let args = std::env::args().collect();
-    start(args).await?;
+    let options = Options::parse(std::env::args().collect())?;
+
+    let subscriber = tracing_subscriber::fmt().with_env_filter(
+        EnvFilter::from_default_env().add_directive("deltachat_repl=info".parse()?),
+    );
+    if options.json {
+        // Keep standard output machine-readable.
+        subscriber.with_writer(io::stderr).init();
+    } else {
+        subscriber.init();
+    }
+
+    start(options).await?;
 
     Ok(())
 }
@@ -36,6 +36,7 @@ class EventType(str, Enum):
     DELETED_BLOB_FILE = "DeletedBlobFile"
     WARNING = "Warning"
     ERROR = "Error"
+    ERROR_REPEATED = "ErrorRepeated"
     ERROR_SELF_NOT_IN_GROUP = "ErrorSelfNotInGroup"
     MSGS_CHANGED = "MsgsChanged"
     REACTIONS_CHANGED = "ReactionsChanged"
@@ -48,14 +49,20 @@ class EventType(str, Enum):
     MSG_DELETED = "MsgDeleted"
     CHAT_MODIFIED = "ChatModified"
     CHAT_EPHEMERAL_TIMER_MODIFIED = "ChatEphemeralTimerModified"
+    CHAT_PROTECTION_CHANGED = "ChatProtectionChanged"
     CONTACTS_CHANGED = "ContactsChanged"
+    CONTACT_KEY_DISCOVERED = "ContactKeyDiscovered"
     LOCATION_CHANGED = "LocationChanged"
     CONFIGURE_PROGRESS = "ConfigureProgress"
     IMEX_PROGRESS = "ImexProgress"
     IMEX_FILE_WRITTEN = "ImexFileWritten"
+    KEY_ROTATION_PROGRESS = "KeyRotationProgress"
+    ACCOUNT_DESTROY_PROGRESS = "AccountDestroyProgress"
     SECUREJOIN_INVITER_PROGRESS = "SecurejoinInviterProgress"
     SECUREJOIN_JOINER_PROGRESS = "SecurejoinJoinerProgress"
+    SECUREJOIN_FAILED = "SecurejoinFailed"
     CONNECTIVITY_CHANGED = "ConnectivityChanged"
+    PROXY_CHANGED = "ProxyChanged"
     SELFAVATAR_CHANGED = "SelfavatarChanged"
     WEBXDC_STATUS_UPDATE = "WebxdcStatusUpdate"
     WEBXDC_INSTANCE_DELETED = "WebxdcInstanceDeleted"
@@ -0,0 +1,9 @@
+use bolero::check;
+
+use deltachat::fuzzing::parse_mime;
+
+fn main() {
+    check!().for_each(|data: &[u8]| {
+        parse_mime(data);
+    });
+}
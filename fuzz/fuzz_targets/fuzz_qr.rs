@@ -0,0 +1,11 @@
+use bolero::check;
+
+use deltachat::fuzzing::parse_qr;
+
+fn main() {
+    check!().for_each(|data: &[u8]| {
+        if let Ok(input) = std::str::from_utf8(data) {
+            parse_qr(input);
+        }
+    });
+}
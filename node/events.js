@@ -13,6 +13,7 @@ module.exports = {
   151: 'DC_EVENT_DELETED_BLOB_FILE',
   300: 'DC_EVENT_WARNING',
   400: 'DC_EVENT_ERROR',
+  401: 'DC_EVENT_ERROR_REPEATED',
   410: 'DC_EVENT_ERROR_SELF_NOT_IN_GROUP',
   2000: 'DC_EVENT_MSGS_CHANGED',
   2001: 'DC_EVENT_REACTIONS_CHANGED',
@@ -25,14 +26,20 @@ module.exports = {
   2016: 'DC_EVENT_MSG_DELETED',
   2020: 'DC_EVENT_CHAT_MODIFIED',
   2021: 'DC_EVENT_CHAT_EPHEMERAL_TIMER_MODIFIED',
+  2022: 'DC_EVENT_CHAT_PROTECTION_CHANGED',
   2030: 'DC_EVENT_CONTACTS_CHANGED',
+  2032: 'DC_EVENT_CONTACT_KEY_DISCOVERED',
   2035: 'DC_EVENT_LOCATION_CHANGED',
   2041: 'DC_EVENT_CONFIGURE_PROGRESS',
   2051: 'DC_EVENT_IMEX_PROGRESS',
   2052: 'DC_EVENT_IMEX_FILE_WRITTEN',
+  2055: 'DC_EVENT_KEY_ROTATION_PROGRESS',
+  2056: 'DC_EVENT_ACCOUNT_DESTROY_PROGRESS',
   2060: 'DC_EVENT_SECUREJOIN_INVITER_PROGRESS',
   2061: 'DC_EVENT_SECUREJOIN_JOINER_PROGRESS',
+  2062: 'DC_EVENT_SECUREJOIN_FAILED',
   2100: 'DC_EVENT_CONNECTIVITY_CHANGED',
+  2101: 'DC_EVENT_PROXY_CHANGED',
   2110: 'DC_EVENT_SELFAVATAR_CHANGED',
   2111: 'DC_EVENT_CONFIG_SYNCED',
   2120: 'DC_EVENT_WEBXDC_STATUS_UPDATE',
@@ -30,17 +30,21 @@ export enum C {
   DC_DOWNLOAD_IN_PROGRESS = 1000,
   DC_DOWNLOAD_UNDECIPHERABLE = 30,
   DC_EVENT_ACCOUNTS_BACKGROUND_FETCH_DONE = 2200,
+  DC_EVENT_ACCOUNT_DESTROY_PROGRESS = 2056,
   DC_EVENT_CHANNEL_OVERFLOW = 2400,
   DC_EVENT_CHATLIST_CHANGED = 2300,
   DC_EVENT_CHATLIST_ITEM_CHANGED = 2301,
   DC_EVENT_CHAT_EPHEMERAL_TIMER_MODIFIED = 2021,
   DC_EVENT_CHAT_MODIFIED = 2020,
+  DC_EVENT_CHAT_PROTECTION_CHANGED = 2022,
   DC_EVENT_CONFIGURE_PROGRESS = 2041,
   DC_EVENT_CONFIG_SYNCED = 2111,
   DC_EVENT_CONNECTIVITY_CHANGED = 2100,
   DC_EVENT_CONTACTS_CHANGED = 2030,
+  DC_EVENT_CONTACT_KEY_DISCOVERED = 2032,
   DC_EVENT_DELETED_BLOB_FILE = 151,
   DC_EVENT_ERROR = 400,
+  DC_EVENT_ERROR_REPEATED = 401,
   DC_EVENT_ERROR_SELF_NOT_IN_GROUP = 410,
   DC_EVENT_IMAP_CONNECTED = 102,
   DC_EVENT_IMAP_INBOX_IDLE = 106,
@@ -51,6 +55,7 @@ export enum C {
   DC_EVENT_INCOMING_MSG = 2005,
   DC_EVENT_INCOMING_MSG_BUNCH = 2006,
   DC_EVENT_INFO = 100,
+  DC_EVENT_KEY_ROTATION_PROGRESS = 2055,
   DC_EVENT_LOCATION_CHANGED = 2035,
   DC_EVENT_MSGS_CHANGED = 2000,
   DC_EVENT_MSGS_NOTICED = 2008,
@@ -59,7 +64,9 @@ export enum C {
   DC_EVENT_MSG_FAILED = 2012,
   DC_EVENT_MSG_READ = 2015,
   DC_EVENT_NEW_BLOB_FILE = 150,
+  DC_EVENT_PROXY_CHANGED = 2101,
   DC_EVENT_REACTIONS_CHANGED = 2001,
+  DC_EVENT_SECUREJOIN_FAILED = 2062,
   DC_EVENT_SECUREJOIN_INVITER_PROGRESS = 2060,
   DC_EVENT_SECUREJOIN_JOINER_PROGRESS = 2061,
   DC_EVENT_SELFAVATAR_CHANGED = 2110,
@@ -77,6 +84,9 @@ export enum C {
   DC_GCL_VERIFIED_ONLY = 1,
   DC_GCM_ADDDAYMARKER = 1,
   DC_GCM_INFO_ONLY = 2,
+  DC_IMAP_ID_ANONYMOUS = 1,
+  DC_IMAP_ID_FULL = 2,
+  DC_IMAP_ID_NAME = 0,
   DC_IMEX_EXPORT_BACKUP = 11,
   DC_IMEX_EXPORT_SELF_KEYS = 1,
   DC_IMEX_IMPORT_BACKUP = 12,
@@ -90,6 +100,8 @@ export enum C {
   DC_INFO_LOCATION_ONLY = 9,
   DC_INFO_MEMBER_ADDED_TO_GROUP = 4,
   DC_INFO_MEMBER_REMOVED_FROM_GROUP = 5,
+  DC_INFO_MESSAGE_PINNED = 50,
+  DC_INFO_MESSAGE_UNPINNED = 51,
   DC_INFO_PROTECTION_DISABLED = 12,
   DC_INFO_PROTECTION_ENABLED = 11,
   DC_INFO_SECURE_JOIN_MESSAGE = 7,
@@ -138,6 +150,7 @@ export enum C {
   DC_QR_REVIVE_VERIFYGROUP = 512,
   DC_QR_TEXT = 330,
   DC_QR_URL = 332,
+  DC_QR_VCARD = 322,
   DC_QR_WEBRTC_INSTANCE = 260,
   DC_QR_WITHDRAW_VERIFYCONTACT = 500,
   DC_QR_WITHDRAW_VERIFYGROUP = 502,
@@ -317,6 +330,7 @@ export const EventId2EventName: { [key: number]: string } = {
   151: 'DC_EVENT_DELETED_BLOB_FILE',
   300: 'DC_EVENT_WARNING',
   400: 'DC_EVENT_ERROR',
+  401: 'DC_EVENT_ERROR_REPEATED',
   410: 'DC_EVENT_ERROR_SELF_NOT_IN_GROUP',
   2000: 'DC_EVENT_MSGS_CHANGED',
   2001: 'DC_EVENT_REACTIONS_CHANGED',
@@ -329,14 +343,20 @@ export const EventId2EventName: { [key: number]: string } = {
   2016: 'DC_EVENT_MSG_DELETED',
   2020: 'DC_EVENT_CHAT_MODIFIED',
   2021: 'DC_EVENT_CHAT_EPHEMERAL_TIMER_MODIFIED',
+  2022: 'DC_EVENT_CHAT_PROTECTION_CHANGED',
   2030: 'DC_EVENT_CONTACTS_CHANGED',
+  2032: 'DC_EVENT_CONTACT_KEY_DISCOVERED',
   2035: 'DC_EVENT_LOCATION_CHANGED',
   2041: 'DC_EVENT_CONFIGURE_PROGRESS',
   2051: 'DC_EVENT_IMEX_PROGRESS',
   2052: 'DC_EVENT_IMEX_FILE_WRITTEN',
+  2055: 'DC_EVENT_KEY_ROTATION_PROGRESS',
+  2056: 'DC_EVENT_ACCOUNT_DESTROY_PROGRESS',
   2060: 'DC_EVENT_SECUREJOIN_INVITER_PROGRESS',
   2061: 'DC_EVENT_SECUREJOIN_JOINER_PROGRESS',
+  2062: 'DC_EVENT_SECUREJOIN_FAILED',
   2100: 'DC_EVENT_CONNECTIVITY_CHANGED',
+  2101: 'DC_EVENT_PROXY_CHANGED',
   2110: 'DC_EVENT_SELFAVATAR_CHANGED',
   2111: 'DC_EVENT_CONFIG_SYNCED',
   2120: 'DC_EVENT_WEBXDC_STATUS_UPDATE',
@@ -3,6 +3,7 @@
 use std::collections::BTreeMap;
 use std::future::Future;
 use std::path::{Path, PathBuf};
+use std::sync::Arc;
 
 use anyhow::{ensure, Context as _, Result};
 use futures::future::join_all;
@@ -41,6 +42,34 @@ pub struct Accounts {
 
     /// Push notification subscriber shared between accounts.
     push_subscriber: PushSubscriber,
+
+    /// Policy for keeping recently selected accounts warm.
+    ///
+    /// If `None`, all accounts are kept open.
+    warm_policy: Option<WarmAccountsPolicy>,
+
+    /// IDs of recently selected accounts, most recently selected first.
+    recently_selected: Vec<u32>,
+}
+
+/// Policy for keeping recently selected accounts "warm".
+///
+/// A warm account has its database open, but its IO scheduler stopped,
+/// so switching to it does not require opening the database again.
+/// Accounts that are neither selected nor warm are stopped and their databases are closed.
+/// Passphrase-protected accounts are never closed
+/// because they cannot be reopened without the passphrase.
+/// Accounts still in use, i.e. with a [`Context`] held outside of the account manager,
+/// are only stopped.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct WarmAccountsPolicy {
+    /// Maximum number of warm accounts, not counting the selected account.
+    pub max_warm: usize,
+
+    /// Memory budget for all warm accounts in bytes, `None` for no limit.
+    ///
+    /// Memory used by an open account is estimated by the size of its database file.
+    pub max_memory: Option<u64>,
 }
 
 impl Accounts {
@@ -90,6 +119,8 @@ async fn open(dir: PathBuf, writable: bool) -> Result<Self> {
             events,
             stockstrings,
             push_subscriber,
+            warm_policy: None,
+            recently_selected: Vec::new(),
         })
     }
 
@@ -113,12 +144,106 @@ pub fn get_selected_account_id(&self) -> Option<u32> {
     }
 
     /// Selects the given account.
+    ///
+    /// If a [`WarmAccountsPolicy`] is set, the selected account is opened
+    /// if needed and accounts falling out of the warm set are closed.
+    /// If IO of any account was running, IO of the selected account is started.
+    ///
+    /// Fails if the policy is set and the selected account is closed and passphrase-protected.
     pub async fn select_account(&mut self, id: u32) -> Result<()> {
         self.config.select_account(id).await?;
+        self.recently_selected.retain(|&x| x != id);
+        self.recently_selected.insert(0, id);
+        self.apply_warm_policy().await?;
 
         Ok(())
     }
 
+    /// Sets the policy for keeping recently selected accounts warm.
+    ///
+    /// `None` disables the policy, then all accounts are opened again.
+    /// Closed passphrase-protected accounts are not opened.
+    pub async fn set_warm_policy(&mut self, policy: Option<WarmAccountsPolicy>) -> Result<()> {
+        self.warm_policy = policy;
+        if policy.is_some() {
+            self.apply_warm_policy().await?;
+        } else {
+            for ctx in self.accounts.values() {
+                if !ctx.is_encrypted().await? {
+                    open_if_closed(ctx).await?;
+                }
+            }
+        }
+        Ok(())
+    }
+
+    /// Returns the policy for keeping recently selected accounts warm.
+    pub fn get_warm_policy(&self) -> Option<WarmAccountsPolicy> {
+        self.warm_policy
+    }
+
+    /// Opens the selected and warm accounts, stops and closes all the others.
+    async fn apply_warm_policy(&self) -> Result<()> {
+        let Some(policy) = self.warm_policy else {
+            return Ok(());
+        };
+        let selected_id = self.config.get_selected_account();
+
+        let mut warm = Vec::new();
+        let mut memory: u64 = 0;
+        for id in &self.recently_selected {
+            if *id == selected_id {
+                continue;
+            }
+            if warm.len() >= policy.max_warm {
+                break;
+            }
+            let Some(ctx) = self.accounts.get(id) else {
+                continue;
+            };
+            if let Some(max_memory) = policy.max_memory {
+                let size = fs::metadata(ctx.get_dbfile())
+                    .await
+                    .map(|m| m.len())
+                    .unwrap_or_default();
+                memory = memory.saturating_add(size);
+                if memory > max_memory {
+                    break;
+                }
+            }
+            warm.push(*id);
+        }
+
+        let mut io_running = false;
+        for ctx in self.accounts.values() {
+            io_running |= ctx.scheduler.is_running().await;
+        }
+
+        for (id, ctx) in &self.accounts {
+            if *id == selected_id {
+                continue;
+            }
+            ctx.stop_io().await;
+            if ctx.is_encrypted().await? {
+                continue;
+            }
+            if warm.contains(id) {
+                open_if_closed(ctx).await?;
+            } else if ctx.is_open().await && Arc::strong_count(&ctx.inner) == 1 {
+                // Only the account manager holds the context, so nobody uses the database.
+                ctx.close().await;
+            }
+        }
+
+        if let Some(ctx) = self.accounts.get(&selected_id) {
+            open_if_closed(ctx).await?;
+            if io_running {
+                ctx.start_io().await;
+            }
+        }
+        Ok(())
+    }
+
     /// Adds a new account and opens it.
     ///
     /// Returns account ID.
@@ -167,6 +292,7 @@ pub async fn remove_account(&mut self, id: u32) -> Result<()> {
             .with_context(|| format!("no account with id {id}"))?;
         ctx.stop_io().await;
         drop(ctx);
+        self.recently_selected.retain(|&x| x != id);
 
         if let Some(cfg) = self.config.get_account(id) {
             let account_path = self.dir.join(cfg.dir);
@@ -180,6 +306,30 @@ pub async fn remove_account(&mut self, id: u32) -> Result<()> {
         Ok(())
     }
 
+    /// Destroys the account irrecoverably and removes it.
+    ///
+    /// See [`Context::destroy_account`] for details.
+    /// If destroying the account fails before its local data is touched,
+    /// the account is not removed.
+    /// If it fails later, the partially destroyed account is removed nevertheless.
+    pub async fn destroy_account(&mut self, id: u32, delete_server_messages: bool) -> Result<()> {
+        let ctx = self
+            .get_account(id)
+            .with_context(|| format!("no account with id {id}"))?;
+        open_if_closed(&ctx).await?;
+        if let Err(err) = ctx.destroy_account(delete_server_messages).await {
+            // The database is closed before the local data is wiped,
+            // so a closed database means that the account is unusable now.
+            if !ctx.is_open().await {
+                drop(ctx);
+                // The error of destroying the account is reported instead.
+                self.remove_account(id).await.ok();
+            }
+            return Err(err);
+        }
+        self.remove_account(id).await
+    }
+
     /// Migrates an existing account into this structure.
     ///
     /// Returns the ID of new account.
@@ -254,8 +404,14 @@ pub fn get_all(&self) -> Vec<u32> {
     }
 
     /// Starts background tasks such as IMAP and SMTP loops for all accounts.
+    ///
+    /// If a [`WarmAccountsPolicy`] is set, only the selected account is started.
     pub async fn start_io(&mut self) {
-        for account in self.accounts.values_mut() {
+        let selected_id = self.config.get_selected_account();
+        for (id, account) in self.accounts.iter_mut() {
+            if self.warm_policy.is_some() && *id != selected_id {
+                continue;
+            }
             account.start_io().await;
         }
     }
@@ -337,6 +493,21 @@ pub async fn set_push_device_token(&mut self, token: &str) -> Result<()> {
     }
 }
 
+/// Opens the account database if it is closed.
+///
+/// Fails if the account is passphrase-protected,
+/// such accounts must be opened with [`Context::open`].
+async fn open_if_closed(ctx: &Context) -> Result<()> {
+    if !ctx.is_open().await {
+        ensure!(
+            ctx.open("".to_string()).await?,
+            "Account {} is closed and passphrase-protected",
+            ctx.get_id()
+        );
+    }
+    Ok(())
+}
+
 /// Configuration file name.
 const CONFIG_NAME: &str = "accounts.toml";
 
@@ -765,6 +936,115 @@ async fn test_accounts_remove_last() -> Result<()> {
         Ok(())
     }
 
+    #[tokio::test(flavor = "multi_thread", worker_threads = 2)]
+    async fn test_accounts_destroy_account() -> Result<()> {
+        let dir = tempfile::tempdir()?;
+        let p: PathBuf = dir.path().join("accounts");
+
+        let writable = true;
+        let mut accounts = Accounts::new(p.clone(), writable).await?;
+        let id = accounts.add_account().await?;
+        let ctx = accounts.get_account(id).unwrap();
+        let dbfile = ctx.get_dbfile().to_path_buf();
+        let blob = ctx.get_blobdir().join("blob.txt");
+        fs::write(&blob, b"secret").await?;
+        drop(ctx);
+
+        // Server messages can only be deleted for chatmail accounts.
+        assert!(accounts.destroy_account(id, true).await.is_err());
+        assert!(accounts.get_account(id).is_some());
+        assert!(dbfile.exists());
+        assert!(blob.exists());
+
+        accounts.destroy_account(id, false).await?;
+        assert!(accounts.get_account(id).is_none());
+        assert!(!dbfile.exists());
+        assert!(!blob.exists());
+
+        // Destroying fails after the database is wiped
+        // because the blobdir cannot be read.
+        // The partially destroyed account is removed nevertheless.
+        let id = accounts.add_account().await?;
+        let ctx = accounts.get_account(id).unwrap();
+        let dbfile = ctx.get_dbfile().to_path_buf();
+        let blobdir = ctx.get_blobdir().to_path_buf();
+        drop(ctx);
+        fs::remove_dir_all(&blobdir).await?;
+        fs::write(&blobdir, b"not a directory").await?;
+        assert!(accounts.destroy_account(id, false).await.is_err());
+        assert!(accounts.get_account(id).is_none());
+        assert!(!dbfile.exists());
+        assert!(!blobdir.exists());
+        Ok(())
+    }
+
+    #[tokio::test(flavor = "multi_thread", worker_threads = 2)]
+    async fn test_warm_policy() -> Result<()> {
+        let dir = tempfile::tempdir()?;
+        let p: PathBuf = dir.path().join("accounts");
+
+        let writable = true;
+        let mut accounts = Accounts::new(p.clone(), writable).await?;
+        let id1 = accounts.add_account().await?;
+        let id2 = accounts.add_account().await?;
+        let id3 = accounts.add_account().await?;
+        let is_open = |accounts: &Accounts, id| {
+            let ctx = accounts.get_account(id).unwrap();
+            async move { ctx.is_open().await }
+        };
+
+        accounts
+            .set_warm_policy(Some(WarmAccountsPolicy {
+                max_warm: 1,
+                max_memory: None,
+            }))
+            .await?;
+        for id in [id1, id2, id3] {
+            accounts.select_account(id).await?;
+        }
+        assert!(is_open(&accounts, id3).await);
+        assert!(is_open(&accounts, id2).await);
+        assert!(!is_open(&accounts, id1).await);
+
+        // Switching to a closed account opens it again.
+        accounts.select_account(id1).await?;
+        assert!(is_open(&accounts, id1).await);
+        assert!(is_open(&accounts, id3).await);
+        assert!(!is_open(&accounts, id2).await);
+
+        // Memory budget too small for any warm account.
+        accounts
+            .set_warm_policy(Some(WarmAccountsPolicy {
+                max_warm: 2,
+                max_memory: Some(1),
+            }))
+            .await?;
+        assert!(is_open(&accounts, id1).await);
+        assert!(!is_open(&accounts, id2).await);
+        assert!(!is_open(&accounts, id3).await);
+
+        accounts.set_warm_policy(None).await?;
+        for id in [id1, id2, id3] {
+            assert!(is_open(&accounts, id).await);
+        }
+
+        // Accounts in use are not closed.
+        let ctx2 = accounts.get_account(id2).unwrap();
+        accounts
+            .set_warm_policy(Some(WarmAccountsPolicy {
+                max_warm: 0,
+                max_memory: None,
+            }))
+            .await?;
+        assert!(ctx2.is_open().await);
+        assert!(!is_open(&accounts, id3).await);
+        drop(ctx2);
+        accounts.select_account(id1).await?;
+        assert!(!is_open(&accounts, id2).await);
+
+        Ok(())
+    }
+
     #[tokio::test(flavor = "multi_thread", worker_threads = 2)]
     async fn test_migrate_account() {
         let dir = tempfile::tempdir().unwrap();
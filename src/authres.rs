@@ -4,6 +4,7 @@
 use std::borrow::Cow;
 use std::collections::BTreeSet;
 use std::fmt;
+use std::str::FromStr;
 
 use anyhow::Result;
 use deltachat_contact_tools::EmailAddress;
@@ -45,6 +46,14 @@ pub(crate) async fn handle_authres(
 pub(crate) struct DkimResults {
     /// Whether DKIM passed for this particular e-mail.
     pub dkim_passed: bool,
+
+    /// Whether our email server explicitly reported a DKIM failure for this e-mail.
+    ///
+    /// Unlike `!dkim_passed`, this is false if there is no DKIM result at all.
+    pub dkim_failed: bool,
+
+    /// Normalized DKIM/SPF/DMARC verdicts of our email server.
+    pub auth_results: AuthenticationResults,
 }
 
 impl fmt::Display for DkimResults {
@@ -54,22 +63,127 @@ fn fmt(&self, fmt: &mut fmt::Formatter) -> fmt::Result {
     }
 }
 
+/// Normalized result of a single authentication method
+/// as reported by our email server.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub enum AuthResult {
+    /// Our email server did not report a result
+    /// or could not check because of a temporary error (`temperror`).
+    #[default]
+    Unknown,
+
+    /// The check passed.
+    Pass,
+
+    /// The check failed, this includes `fail`, `softfail`, `neutral`, `permerror` etc.
+    /// For DKIM, a valid signature of a domain other than the From domain is a failure as well.
+    Fail,
+
+    /// The message was not signed or the sender domain does not publish a policy.
+    None,
+}
+
+impl AuthResult {
+    fn from_value(value: &str) -> Self {
+        match value.to_ascii_lowercase().as_str() {
+            "pass" => Self::Pass,
+            "none" => Self::None,
+            "temperror" => Self::Unknown,
+            _ => Self::Fail,
+        }
+    }
+
+    fn as_str(self) -> &'static str {
+        match self {
+            Self::Unknown => "unknown",
+            Self::Pass => "pass",
+            Self::Fail => "fail",
+            Self::None => "none",
+        }
+    }
+}
+
+/// DKIM, SPF and DMARC results of an incoming message,
+/// taken from the Authentication-Results headers added by our email server.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub struct AuthenticationResults {
+    /// DKIM result for the From domain.
+    pub dkim: AuthResult,
+
+    /// SPF result.
+    pub spf: AuthResult,
+
+    /// DMARC result.
+    pub dmarc: AuthResult,
+
+    /// The sender is a known contact, but DKIM or DMARC failed.
+    /// The message may be spoofed and UI should show a warning.
+    pub dkim_warning: bool,
+}
+
+impl AuthenticationResults {
+    /// Returns true if the authentication results say that the From address is forged,
+    /// either because DKIM failed or because DMARC failed for an unsigned message.
+    pub(crate) fn dkim_failed(&self) -> bool {
+        self.dkim == AuthResult::Fail || self.dmarc == AuthResult::Fail
+    }
+}
+
+impl fmt::Display for AuthenticationResults {
+    fn fmt(&self, fmt: &mut fmt::Formatter) -> fmt::Result {
+        write!(
+            fmt,
+            "dkim={} spf={} dmarc={}",
+            self.dkim.as_str(),
+            self.spf.as_str(),
+            self.dmarc.as_str()
+        )?;
+        if self.dkim_warning {
+            write!(fmt, " warning=1")?;
+        }
+        Ok(())
+    }
+}
+
+impl FromStr for AuthenticationResults {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<Self> {
+        let mut res = Self::default();
+        for (key, value) in s.split_whitespace().filter_map(|part| part.split_once('=')) {
+            let result = match value {
+                "unknown" => AuthResult::Unknown,
+                value => AuthResult::from_value(value),
+            };
+            match key {
+                "dkim" => res.dkim = result,
+                "spf" => res.spf = result,
+                "dmarc" => res.dmarc = result,
+                "warning" => res.dkim_warning = value == "1",
+                _ => {}
+            }
+        }
+        Ok(res)
+    }
+}
+
 type AuthservId = String;
 
 #[derive(Debug, PartialEq)]
 enum DkimResult {
     /// The header explicitly said that DKIM passed
     Passed,
-    /// The header explicitly said that DKIM failed
+    /// The header explicitly said `dkim=fail`
     Failed,
-    /// The header didn't say anything about DKIM; this might mean that it wasn't
+    /// The header didn't say that DKIM passed or failed; this might mean that it wasn't
     /// checked, but it might also mean that it failed. This is because some providers
     /// (e.g. ik.me, mail.ru, posteo.de) don't add `dkim=none` to their
     /// Authentication-Results if there was no DKIM.
+    /// Results like `dkim=none`, `dkim=neutral` or `dkim=temperror` are treated the same way.
     Nothing,
 }
 
-type ParsedAuthresHeaders = Vec<(AuthservId, DkimResult)>;
+type ParsedAuthresHeaders = Vec<(AuthservId, DkimResult, AuthenticationResults)>;
 
 fn parse_authres_headers(
     headers: &mailparse::headers::Headers<'_>,
@@ -79,26 +193,31 @@ fn parse_authres_headers(
     for header_value in headers.get_all_values(HeaderDef::AuthenticationResults.into()) {
         let header_value = remove_comments(&header_value);
 
-        if let Some(mut authserv_id) = header_value.split(';').next() {
-            if authserv_id.contains(char::is_whitespace) || authserv_id.is_empty() {
-                // Outlook violates the RFC by not adding an authserv-id at all, which we notice
-                // because there is whitespace in the first identifier before the ';'.
-                // Authentication-Results-parsing still works securely because they remove incoming
-                // Authentication-Results headers.
-                // We just use an arbitrary authserv-id, it will work for Outlook, and in general,
-                // with providers not implementing the RFC correctly, someone can trick us
-                // into thinking that an incoming email is DKIM-correct, anyway.
-                // The most important thing here is that we have some valid `authserv_id`.
-                authserv_id = "invalidAuthservId";
-            }
-            let dkim_passed = parse_one_authres_header(&header_value, from_domain);
-            res.push((authserv_id.to_string(), dkim_passed));
+        if let Some(authserv_id) = get_authserv_id(&header_value) {
+            let (dkim_result, results) = parse_one_authres_header(&header_value, from_domain);
+            res.push((authserv_id.to_string(), dkim_result, results));
         }
     }
 
     res
 }
 
+fn get_authserv_id(header_value: &str) -> Option<&str> {
+    let authserv_id = header_value.split(';').next()?;
+    if authserv_id.contains(char::is_whitespace) || authserv_id.is_empty() {
+        // Outlook violates the RFC by not adding an authserv-id at all, which we notice
+        // because there is whitespace in the first identifier before the ';'.
+        // Authentication-Results-parsing still works securely because they remove incoming
+        // Authentication-Results headers.
+        // We just use an arbitrary authserv-id, it will work for Outlook, and in general,
+        // with providers not implementing the RFC correctly, someone can trick us
+        // into thinking that an incoming email is DKIM-correct, anyway.
+        // The most important thing here is that we have some valid `authserv_id`.
+        return Some("invalidAuthservId");
+    }
+    Some(authserv_id)
+}
+
 /// The headers can contain comments that look like this:
 /// ```text
 /// Authentication-Results: (this is a comment) gmx.net; (another; comment) dkim=pass;
@@ -115,34 +234,55 @@ fn remove_comments(header: &str) -> Cow<'_, str> {
 /// Parses a single Authentication-Results header, like:
 ///
 /// ```text
-/// Authentication-Results:  gmx.net; dkim=pass header.i=@slack.com
+/// Authentication-Results: gmx.net; dkim=pass header.i=@slack.com; spf=pass; dmarc=pass
 /// ```
-fn parse_one_authres_header(header_value: &str, from_domain: &str) -> DkimResult {
-    if let Some((before_dkim_part, dkim_to_end)) = header_value.split_once("dkim=") {
-        // Check that the character right before `dkim=` is a space or a tab
-        // so that we wouldn't e.g. mistake `notdkim=pass` for `dkim=pass`
-        if before_dkim_part.ends_with(' ') || before_dkim_part.ends_with('\t') {
-            let dkim_part = dkim_to_end.split(';').next().unwrap_or_default();
-            let dkim_parts: Vec<_> = dkim_part.split_whitespace().collect();
-            if let Some(&"pass") = dkim_parts.first() {
-                // DKIM headers contain a header.d or header.i field
-                // that says which domain signed. We have to check ourselves
-                // that this is the same domain as in the From header.
-                let header_d: &str = &format!("header.d={}", &from_domain);
-                let header_i: &str = &format!("header.i=@{}", &from_domain);
-
-                if dkim_parts.contains(&header_d) || dkim_parts.contains(&header_i) {
-                    // We have found a `dkim=pass` header!
-                    return DkimResult::Passed;
+///
+/// Returns whether DKIM passed for the From domain
+/// and the DKIM, SPF and DMARC results.
+fn parse_one_authres_header(
+    header_value: &str,
+    from_domain: &str,
+) -> (DkimResult, AuthenticationResults) {
+    // DKIM headers contain a header.d or header.i field
+    // that says which domain signed. We have to check ourselves
+    // that this is the same domain as in the From header.
+    let header_d = format!("header.d={from_domain}");
+    let header_i = format!("header.i=@{from_domain}");
+
+    let mut dkim_result = DkimResult::Nothing;
+    let mut res = AuthenticationResults::default();
+    // Splitting at `;` and whitespace makes sure
+    // that we don't e.g. mistake `notdkim=pass` for `dkim=pass`.
+    for resinfo in header_value.split(';') {
+        let mut parts = resinfo.split_whitespace();
+        let Some((method, value)) = parts.next().and_then(|part| part.split_once('=')) else {
+            continue;
+        };
+        let result = AuthResult::from_value(value);
+        match method.to_ascii_lowercase().as_str() {
+            "dkim" => {
+                let aligned = parts.any(|part| part == header_d || part == header_i);
+                // There may be several DKIM signatures, one aligned passing signature is enough.
+                if result == AuthResult::Pass && aligned {
+                    dkim_result = DkimResult::Passed;
+                } else if value.eq_ignore_ascii_case("fail") && dkim_result == DkimResult::Nothing {
+                    dkim_result = DkimResult::Failed;
+                }
+                if res.dkim != AuthResult::Pass {
+                    // A valid signature of another domain does not authenticate the From address.
+                    res.dkim = if result == AuthResult::Pass && !aligned {
+                        AuthResult::Fail
+                    } else {
+                        result
+                    };
                 }
-            } else {
-                // dkim=fail, dkim=none, ...
-                return DkimResult::Failed;
             }
+            "spf" if res.spf == AuthResult::Unknown => res.spf = result,
+            "dmarc" if res.dmarc == AuthResult::Unknown => res.dmarc = result,
+            _ => {}
         }
     }
-
-    DkimResult::Nothing
+    (dkim_result, res)
 }
 
 /// ## About authserv-ids
@@ -178,7 +318,7 @@ async fn update_authservid_candidates(
 ) -> Result<()> {
     let mut new_ids: BTreeSet<&str> = authres
         .iter()
-        .map(|(authserv_id, _dkim_passed)| authserv_id.as_str())
+        .map(|(authserv_id, _dkim_result, _results)| authserv_id.as_str())
         .collect();
     if new_ids.is_empty() {
         // The incoming message doesn't contain any authentication results, maybe it's a
@@ -218,12 +358,13 @@ async fn compute_dkim_results(
     mut authres: ParsedAuthresHeaders,
 ) -> Result<DkimResults> {
     let mut dkim_passed = false;
+    let mut dkim_failed = false;
 
     let ids_config = context.get_config(Config::AuthservIdCandidates).await?;
     let ids = parse_authservid_candidates_config(&ids_config);
 
     // Remove all foreign authentication results
-    authres.retain(|(authserv_id, _dkim_passed)| ids.contains(authserv_id.as_str()));
+    authres.retain(|(authserv_id, _dkim_result, _results)| ids.contains(authserv_id.as_str()));
 
     if authres.is_empty() {
         // If the authentication results are empty, then our provider doesn't add them
@@ -231,14 +372,15 @@ async fn compute_dkim_results(
         // think that DKIM passed. So, in this case, we can as well assume that DKIM passed.
         dkim_passed = true;
     } else {
-        for (_authserv_id, current_dkim_passed) in authres {
-            match current_dkim_passed {
+        for (_authserv_id, current_dkim_result, _results) in &authres {
+            match current_dkim_result {
                 DkimResult::Passed => {
                     dkim_passed = true;
                     break;
                 }
                 DkimResult::Failed => {
                     dkim_passed = false;
+                    dkim_failed = true;
                     break;
                 }
                 DkimResult::Nothing => {
@@ -248,7 +390,26 @@ async fn compute_dkim_results(
         }
     }
 
-    Ok(DkimResults { dkim_passed })
+    // Combine the DKIM/SPF/DMARC results of our own email server,
+    // the first header reporting a result for a method wins.
+    let mut auth_results = AuthenticationResults::default();
+    for (_authserv_id, _dkim_result, results) in authres {
+        if auth_results.dkim == AuthResult::Unknown {
+            auth_results.dkim = results.dkim;
+        }
+        if auth_results.spf == AuthResult::Unknown {
+            auth_results.spf = results.spf;
+        }
+        if auth_results.dmarc == AuthResult::Unknown {
+            auth_results.dmarc = results.dmarc;
+        }
+    }
+
+    Ok(DkimResults {
+        dkim_passed,
+        dkim_failed,
+        auth_results,
+    })
 }
 
 fn parse_authservid_candidates_config(config: &Option<String>) -> BTreeSet<&str> {
@@ -296,6 +457,26 @@ fn test_remove_comments() {
         assert_eq!(remove_comments(&header), "  no comment  ");
     }
 
+    fn parse_dkim_results(
+        headers: &mailparse::headers::Headers<'_>,
+        from_domain: &str,
+    ) -> Vec<(AuthservId, DkimResult)> {
+        parse_authres_headers(headers, from_domain)
+            .into_iter()
+            .map(|(authserv_id, dkim_result, _results)| (authserv_id, dkim_result))
+            .collect()
+    }
+
+    fn parse_method_results(
+        headers: &mailparse::headers::Headers<'_>,
+        from_domain: &str,
+    ) -> Vec<(AuthservId, AuthenticationResults)> {
+        parse_authres_headers(headers, from_domain)
+            .into_iter()
+            .map(|(authserv_id, _dkim_result, results)| (authserv_id, results))
+            .collect()
+    }
+
     #[tokio::test(flavor = "multi_thread", worker_threads = 2)]
     async fn test_parse_authentication_results() -> Result<()> {
         let t = TestContext::new().await;
@@ -303,7 +484,7 @@ async fn test_parse_authentication_results() -> Result<()> {
         let bytes = b"Authentication-Results:  gmx.net; dkim=pass header.i=@slack.com
 Authentication-Results:  gmx.net; dkim=pass header.i=@amazonses.com";
         let mail = mailparse::parse_mail(bytes)?;
-        let actual = parse_authres_headers(&mail.get_headers(), "slack.com");
+        let actual = parse_dkim_results(&mail.get_headers(), "slack.com");
         assert_eq!(
             actual,
             vec![
@@ -315,7 +496,7 @@ async fn test_parse_authentication_results() -> Result<()> {
         let bytes = b"Authentication-Results:  gmx.net; notdkim=pass header.i=@slack.com
 Authentication-Results:  gmx.net; notdkim=pass header.i=@amazonses.com";
         let mail = mailparse::parse_mail(bytes)?;
-        let actual = parse_authres_headers(&mail.get_headers(), "slack.com");
+        let actual = parse_dkim_results(&mail.get_headers(), "slack.com");
         assert_eq!(
             actual,
             vec![
@@ -326,7 +507,7 @@ async fn test_parse_authentication_results() -> Result<()> {
 
         let bytes = b"Authentication-Results:  gmx.net; dkim=pass header.i=@amazonses.com";
         let mail = mailparse::parse_mail(bytes)?;
-        let actual = parse_authres_headers(&mail.get_headers(), "slack.com");
+        let actual = parse_dkim_results(&mail.get_headers(), "slack.com");
         assert_eq!(actual, vec![("gmx.net".to_string(), DkimResult::Nothing)],);
 
         // Weird Authentication-Results from Outlook without an authserv-id
@@ -335,7 +516,7 @@ async fn test_parse_authentication_results() -> Result<()> {
     header.d=hotmail.com;dmarc=pass action=none
     header.from=hotmail.com;compauth=pass reason=100";
         let mail = mailparse::parse_mail(bytes)?;
-        let actual = parse_authres_headers(&mail.get_headers(), "hotmail.com");
+        let actual = parse_dkim_results(&mail.get_headers(), "hotmail.com");
         // At this point, the most important thing to test is that there are no
         // authserv-ids with whitespace in them.
         assert_eq!(
@@ -346,11 +527,11 @@ async fn test_parse_authentication_results() -> Result<()> {
         let bytes = b"Authentication-Results:  gmx.net; dkim=none header.i=@slack.com
 Authentication-Results:  gmx.net; dkim=pass header.i=@slack.com";
         let mail = mailparse::parse_mail(bytes)?;
-        let actual = parse_authres_headers(&mail.get_headers(), "slack.com");
+        let actual = parse_dkim_results(&mail.get_headers(), "slack.com");
         assert_eq!(
             actual,
             vec![
-                ("gmx.net".to_string(), DkimResult::Failed),
+                ("gmx.net".to_string(), DkimResult::Nothing),
                 ("gmx.net".to_string(), DkimResult::Passed)
             ]
         );
@@ -360,7 +541,7 @@ async fn test_parse_authentication_results() -> Result<()> {
 	dkim=pass (1024-bit key; unprotected) header.d=yandex.ru header.i=@yandex.ru header.a=rsa-sha256 header.s=mail header.b=avNJu6sw;
 	dkim-atps=neutral";
         let mail = mailparse::parse_mail(bytes)?;
-        let actual = parse_authres_headers(&mail.get_headers(), "yandex.ru");
+        let actual = parse_dkim_results(&mail.get_headers(), "yandex.ru");
         assert_eq!(
             actual,
             vec![("mx1.riseup.net".to_string(), DkimResult::Passed)]
@@ -372,7 +553,7 @@ async fn test_parse_authentication_results() -> Result<()> {
 Authentication-Results: box.hispanilandia.net; dmarc=pass (p=quarantine dis=none) header.from=disroot.org
 Authentication-Results: box.hispanilandia.net; spf=pass smtp.mailfrom=adbenitez@disroot.org"#;
         let mail = mailparse::parse_mail(bytes)?;
-        let actual = parse_authres_headers(&mail.get_headers(), "disroot.org");
+        let actual = parse_dkim_results(&mail.get_headers(), "disroot.org");
         assert_eq!(
             actual,
             vec![
@@ -385,6 +566,73 @@ async fn test_parse_authentication_results() -> Result<()> {
         Ok(())
     }
 
+    #[test]
+    fn test_parse_method_results() -> Result<()> {
+        let bytes = br#"Authentication-Results: box.hispanilandia.net;
+	dkim=fail reason="signature verification failed" (2048-bit key; secure) header.d=disroot.org header.i=@disroot.org header.b="kqh3WUKq";
+	dkim-atps=neutral
+Authentication-Results: box.hispanilandia.net; dmarc=pass (p=quarantine dis=none) header.from=disroot.org
+Authentication-Results: box.hispanilandia.net; spf=pass smtp.mailfrom=adbenitez@disroot.org"#;
+        let mail = mailparse::parse_mail(bytes)?;
+        let actual = parse_method_results(&mail.get_headers(), "disroot.org");
+        assert_eq!(actual.len(), 3);
+        assert_eq!(actual[0].1.dkim, AuthResult::Fail);
+        assert_eq!(actual[1].1.dmarc, AuthResult::Pass);
+        assert_eq!(actual[2].1.spf, AuthResult::Pass);
+
+        // Outlook puts SPF results where the authserv-id should be.
+        let bytes = b"Authentication-Results: spf=pass (sender IP is 40.92.73.85)
+    smtp.mailfrom=hotmail.com; dkim=pass (signature was verified)
+    header.d=hotmail.com;dmarc=none action=none
+    header.from=hotmail.com;compauth=pass reason=100";
+        let mail = mailparse::parse_mail(bytes)?;
+        let actual = parse_method_results(&mail.get_headers(), "hotmail.com");
+        let expected = AuthenticationResults {
+            dkim: AuthResult::Pass,
+            spf: AuthResult::Pass,
+            dmarc: AuthResult::None,
+            dkim_warning: false,
+        };
+        assert_eq!(actual, vec![("invalidAuthservId".to_string(), expected)]);
+
+        // A valid signature of another domain does not authenticate the From address.
+        let bytes =
+            b"Authentication-Results: gmx.net; dkim=pass header.i=@amazonses.com; spf=softfail";
+        let mail = mailparse::parse_mail(bytes)?;
+        let actual = parse_method_results(&mail.get_headers(), "slack.com");
+        assert_eq!(actual[0].1.dkim, AuthResult::Fail);
+        assert_eq!(actual[0].1.spf, AuthResult::Fail);
+        assert_eq!(actual[0].1.dmarc, AuthResult::Unknown);
+
+        // A temporary error is not a failure.
+        let bytes =
+            b"Authentication-Results: gmx.net; dkim=temperror header.d=slack.com; dmarc=temperror";
+        let mail = mailparse::parse_mail(bytes)?;
+        let actual = parse_method_results(&mail.get_headers(), "slack.com");
+        assert_eq!(actual[0].1, AuthenticationResults::default());
+        assert!(!actual[0].1.dkim_failed());
+
+        // Round trip through the message param.
+        let results = AuthenticationResults {
+            dkim_warning: true,
+            ..expected
+        };
+        assert_eq!(
+            results.to_string(),
+            "dkim=pass spf=pass dmarc=none warning=1"
+        );
+        assert_eq!(
+            results.to_string().parse::<AuthenticationResults>()?,
+            results
+        );
+        assert_eq!(
+            "".parse::<AuthenticationResults>()?,
+            AuthenticationResults::default()
+        );
+
+        Ok(())
+    }
+
     #[tokio::test(flavor = "multi_thread", worker_threads = 2)]
     async fn test_update_authservid_candidates() -> Result<()> {
         let t = TestContext::new_alice().await;
@@ -420,7 +668,13 @@ async fn test_update_authservid_candidates() -> Result<()> {
     async fn update_authservid_candidates_test(context: &Context, incoming_ids: &[&str]) {
         let v = incoming_ids
             .iter()
-            .map(|id| (id.to_string(), DkimResult::Passed))
+            .map(|id| {
+                (
+                    id.to_string(),
+                    DkimResult::Passed,
+                    AuthenticationResults::default(),
+                )
+            })
             .collect();
         update_authservid_candidates(context, &v).await.unwrap()
     }
@@ -598,4 +852,66 @@ async fn test_authres_in_mailinglist_ignored() -> Result<()> {
 
         Ok(())
     }
+
+    #[tokio::test(flavor = "multi_thread", worker_threads = 2)]
+    async fn test_get_authentication_results() -> Result<()> {
+        let mut tcm = TestContextManager::new();
+        let alice = tcm.alice().await;
+        let bob = tcm.bob().await;
+        bob.set_config(Config::AuthservIdCandidates, Some("example.net"))
+            .await?;
+
+        // Authentication-Results of foreign servers are ignored.
+        let alice_bob_chat = alice.create_chat(&bob).await;
+        let mut sent = alice.send_text(alice_bob_chat.id, "hi").await;
+        sent.payload.insert_str(
+            0,
+            "Authentication-Results: attacker.org; dkim=pass header.d=example.org\n\
+             Authentication-Results: example.net; spf=pass\n",
+        );
+        let rcvd = bob.recv_msg(&sent).await;
+        let results = rcvd.get_authentication_results();
+        assert_eq!(results.dkim, AuthResult::Unknown);
+        assert_eq!(results.spf, AuthResult::Pass);
+
+        let mut sent = alice.send_text(alice_bob_chat.id, "hi again").await;
+        sent.payload.insert_str(
+            0,
+            "Authentication-Results: example.net; dkim=pass header.d=example.org; spf=pass\n",
+        );
+        let rcvd = bob.recv_msg(&sent).await;
+        let results = rcvd.get_authentication_results();
+        assert_eq!(results.dkim, AuthResult::Pass);
+        assert_eq!(results.spf, AuthResult::Pass);
+        assert_eq!(results.dmarc, AuthResult::Unknown);
+        assert!(!results.dkim_warning);
+
+        // Bob accepts the chat, a message from Alice with failing DKIM is suspicious now.
+        bob.create_chat(&alice).await;
+        let mut sent = alice.send_text(alice_bob_chat.id, "spoofed?").await;
+        sent.payload.insert_str(
+            0,
+            "Authentication-Results: example.net; dkim=fail header.d=example.org; dmarc=fail\n",
+        );
+        let rcvd = bob.recv_msg(&sent).await;
+        let results = rcvd.get_authentication_results();
+        assert_eq!(results.dkim, AuthResult::Fail);
+        assert_eq!(results.dmarc, AuthResult::Fail);
+        assert!(results.dkim_warning);
+
+        // Failing DKIM from an unknown sender is not flagged.
+        let fiona = tcm.fiona().await;
+        let fiona_bob_chat = fiona.create_chat(&bob).await;
+        let mut sent = fiona.send_text(fiona_bob_chat.id, "hello").await;
+        sent.payload.insert_str(
+            0,
+            "Authentication-Results: example.net; dkim=fail header.d=example.net\n",
+        );
+        let rcvd = bob.recv_msg(&sent).await;
+        let results = rcvd.get_authentication_results();
+        assert_eq!(results.dkim, AuthResult::Fail);
+        assert!(!results.dkim_warning);
+
+        Ok(())
+    }
 }
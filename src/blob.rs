@@ -56,6 +56,7 @@ pub async fn create(
         suggested_name: &str,
         data: &[u8],
     ) -> Result<BlobObject<'a>> {
+        context.ensure_disk_space(data.len() as u64).await?;
         let blobdir = context.get_blobdir();
         let (stem, ext) = BlobObject::sanitise_name(suggested_name);
         let (name, mut file) = BlobObject::create_new_file(context, blobdir, &stem, &ext).await?;
@@ -117,6 +118,8 @@ pub async fn create_and_copy(context: &'a Context, src: &Path) -> Result<BlobObj
         let mut src_file = fs::File::open(src)
             .await
             .with_context(|| format!("failed to open file {}", src.display()))?;
+        let src_len = src_file.metadata().await?.len();
+        context.ensure_disk_space(src_len).await?;
         let (stem, ext) = BlobObject::sanitise_name(&src.to_string_lossy());
         let (name, mut dst_file) =
             BlobObject::create_new_file(context, context.get_blobdir(), &stem, &ext).await?;
@@ -387,16 +390,7 @@ pub async fn recode_to_image_size(
         maybe_sticker: &mut bool,
     ) -> Result<()> {
         let blob_abs = self.to_abs_path();
-        let (img_wh, max_bytes) =
-            match MediaQuality::from_i32(context.get_config_int(Config::MediaQuality).await?)
-                .unwrap_or_default()
-            {
-                MediaQuality::Balanced => (
-                    constants::BALANCED_IMAGE_SIZE,
-                    constants::BALANCED_IMAGE_BYTES,
-                ),
-                MediaQuality::Worse => (constants::WORSE_IMAGE_SIZE, constants::WORSE_IMAGE_BYTES),
-            };
+        let (img_wh, max_bytes) = image_limits(context).await?;
         let strict_limits = false;
         if let Some(new_name) = self.recode_to_size(
             context,
@@ -411,6 +405,34 @@ pub async fn recode_to_image_size(
         Ok(())
     }
 
+    /// Strips comments and Exif/XMP metadata from an animated GIF or WebP image and checks
+    /// that it fits into the file size limit specified by the config.
+    ///
+    /// Returns `false` if the image is too large to be sent as an animation, in this case it
+    /// should be recoded as a still image.
+    pub async fn prepare_animated_image(&mut self, context: &Context) -> Result<bool> {
+        let blob_abs = self.to_abs_path();
+        let (_, max_bytes) = image_limits(context).await?;
+        let buf = fs::read(&blob_abs).await?;
+        let stripped = if buf.starts_with(b"GIF") {
+            strip_gif_metadata(&buf)
+        } else {
+            strip_webp_metadata(&buf)
+        };
+        let len = match stripped {
+            Some(stripped) if stripped.len() < buf.len() => {
+                fs::write(&blob_abs, &stripped).await?;
+                stripped.len()
+            }
+            Some(_) => buf.len(),
+            None => {
+                warn!(context, "Cannot strip metadata of animated image {self}.");
+                buf.len()
+            }
+        };
+        Ok(len <= max_bytes)
+    }
+
     /// If `!strict_limits`, then if `max_bytes` is exceeded, reduce the image to `img_wh` and just
     /// proceed with the result.
     fn recode_to_size(
@@ -489,9 +511,8 @@ fn recode_to_size(
             // We need to rewrite images with Exif to remove metadata such as location,
             // camera model, etc.
             //
-            // TODO: Fix lost animation and transparency when recoding using the `image` crate. And
-            // also `Viewtype::Gif` (maybe renamed to `Animation`) should be used for animated
-            // images.
+            // TODO: Fix lost transparency when recoding using the `image` crate. Animated images
+            // are sent as `Viewtype::Gif` and thus not recoded.
             let do_scale = exceeds_max_bytes
                 || strict_limits
                     && (exceeds_wh
@@ -593,6 +614,109 @@ fn recode_to_size(
     }
 }
 
+/// Returns the maximum image width/height and file size for the configured media quality.
+async fn image_limits(context: &Context) -> Result<(u32, usize)> {
+    let limits = match MediaQuality::from_i32(context.get_config_int(Config::MediaQuality).await?)
+        .unwrap_or_default()
+    {
+        MediaQuality::Balanced => (
+            constants::BALANCED_IMAGE_SIZE,
+            constants::BALANCED_IMAGE_BYTES,
+        ),
+        MediaQuality::Worse => (constants::WORSE_IMAGE_SIZE, constants::WORSE_IMAGE_BYTES),
+    };
+    Ok(limits)
+}
+
+/// Removes comment extensions and application extensions other than the looping ones
+/// from a GIF file.
+///
+/// Returns `None` if the file cannot be parsed.
+fn strip_gif_metadata(buf: &[u8]) -> Option<Vec<u8>> {
+    // Keep header, logical screen descriptor and global color table.
+    let flags = *buf.get(10)?;
+    let mut pos = 13;
+    if flags & 0x80 != 0 {
+        pos += 3 << ((flags & 0x07) + 1);
+    }
+    let mut stripped = buf.get(..pos)?.to_vec();
+    loop {
+        let start = pos;
+        let keep = match *buf.get(pos)? {
+            0x21 => {
+                let label = *buf.get(pos + 1)?;
+                pos += 2;
+                match label {
+                    // Comment extension.
+                    0xfe => false,
+                    // Application extension, the identifier is in the first sub-block.
+                    0xff => {
+                        let id = buf.get(pos + 1..pos + 12)?;
+                        id == b"NETSCAPE2.0" || id == b"ANIMEXTS1.0"
+                    }
+                    _ => true,
+                }
+            }
+            0x2c => {
+                let flags = *buf.get(pos + 9)?;
+                pos += 10;
+                if flags & 0x80 != 0 {
+                    pos += 3 << ((flags & 0x07) + 1);
+                }
+                pos += 1;
+                true
+            }
+            0x3b => {
+                stripped.push(0x3b);
+                return Some(stripped);
+            }
+            _ => return None,
+        };
+        loop {
+            let len = usize::from(*buf.get(pos)?);
+            pos += 1 + len;
+            if len == 0 {
+                break;
+            }
+        }
+        if keep {
+            stripped.extend_from_slice(buf.get(start..pos)?);
+        }
+    }
+}
+
+/// Removes `EXIF` and `XMP ` chunks from a WebP file.
+///
+/// Returns `None` if the file cannot be parsed.
+fn strip_webp_metadata(buf: &[u8]) -> Option<Vec<u8>> {
+    let mut stripped = buf.get(..12)?.to_vec();
+    let mut pos = 12;
+    while pos < buf.len() {
+        let header = buf.get(pos..pos.checked_add(8)?)?;
+        let (fourcc, size) = header.split_at(4);
+        let size = usize::try_from(u32::from_le_bytes(size.try_into().ok()?)).ok()?;
+        // Chunks are padded to an even size.
+        let end = pos.checked_add(8 + size + size % 2)?;
+        let chunk = buf.get(pos..end)?;
+        match fourcc {
+            b"EXIF" | b"XMP " => {}
+            b"VP8X" => {
+                let mut chunk = chunk.to_vec();
+                // Clear the Exif and XMP flags.
+                *chunk.get_mut(8)? &= !0x0c;
+                stripped.extend_from_slice(&chunk);
+            }
+            _ => stripped.extend_from_slice(chunk),
+        }
+        pos = end;
+    }
+    let riff_size = u32::try_from(stripped.len() - 8).ok()?;
+    stripped
+        .get_mut(4..8)?
+        .copy_from_slice(&riff_size.to_le_bytes());
+    Some(stripped)
+}
+
 /// Returns image file size and Exif.
 pub fn image_metadata(file: &std::fs::File) -> Result<(u64, Option<exif::Exif>)> {
     let len = file.metadata()?.len();
@@ -762,7 +886,7 @@ mod tests {
     use super::*;
     use crate::chat::{self, create_group_chat, ProtectionStatus};
     use crate::message::{Message, Viewtype};
-    use crate::test_utils::{self, TestContext};
+    use crate::test_utils::{self, TestContext, TestContextManager};
 
     fn check_image_size(path: impl AsRef<Path>, width: u32, height: u32) -> image::DynamicImage {
         tokio::task::block_in_place(move || {
@@ -1406,6 +1530,132 @@ pub(crate) async fn test(self) -> anyhow::Result<DynamicImage> {
         }
     }
 
+    #[tokio::test(flavor = "multi_thread", worker_threads = 2)]
+    async fn test_send_animation_as_image() -> Result<()> {
+        let bytes = include_bytes!("../test-data/image/animation.gif");
+        let mut tcm = TestContextManager::new();
+        let alice = &tcm.alice().await;
+        let bob = &tcm.bob().await;
+        // The file has a wrong extension, so the animation can only be detected by its content.
+        let file = alice.get_blobdir().join("animation.jpg");
+        fs::write(&file, &bytes).await?;
+        let mut msg = Message::new(Viewtype::Image);
+        msg.set_file(file.to_str().unwrap(), None);
+        let chat = alice.create_chat(bob).await;
+        let sent = alice.send_msg(chat.id, &mut msg).await;
+        let msg = Message::load_from_db(alice, msg.id).await?;
+        assert_eq!(msg.get_viewtype(), Viewtype::Gif);
+        assert_eq!(msg.get_frame_count(), 2);
+
+        let bob_msg = bob.recv_msg(&sent).await;
+        assert_eq!(bob_msg.get_viewtype(), Viewtype::Gif);
+        assert_eq!(bob_msg.get_frame_count(), 2);
+        assert_eq!((bob_msg.get_width(), bob_msg.get_height()), (1, 1));
+        let blob = bob_msg.get_file(bob).unwrap();
+        assert_eq!(fs::read(blob).await?, bytes);
+        Ok(())
+    }
+
+    #[test]
+    fn test_strip_animation_metadata() {
+        let bytes = include_bytes!("../test-data/image/animation.gif");
+        assert_eq!(strip_gif_metadata(bytes).unwrap(), bytes);
+        // Insert a comment and an XMP application extension before the trailer.
+        let mut with_metadata = bytes[..bytes.len() - 1].to_vec();
+        with_metadata.extend_from_slice(&[0x21, 0xfe, 3, b'a', b'b', b'c', 0]);
+        with_metadata.extend_from_slice(&[0x21, 0xff, 11]);
+        with_metadata.extend_from_slice(b"XMP DataXMP");
+        with_metadata.extend_from_slice(&[2, b'<', b'>', 0, 0x3b]);
+        assert_eq!(strip_gif_metadata(&with_metadata).unwrap(), bytes);
+        assert_eq!(strip_gif_metadata(&with_metadata[..20]), None);
+
+        let chunk = |fourcc: &[u8], payload: &[u8]| {
+            let mut chunk = fourcc.to_vec();
+            chunk.extend_from_slice(&u32::try_from(payload.len()).unwrap().to_le_bytes());
+            chunk.extend_from_slice(payload);
+            if payload.len() % 2 == 1 {
+                chunk.push(0);
+            }
+            chunk
+        };
+        let webp = |chunks: &[Vec<u8>]| {
+            let mut data = b"RIFF".to_vec();
+            let len = 4 + chunks.iter().map(Vec::len).sum::<usize>();
+            data.extend_from_slice(&u32::try_from(len).unwrap().to_le_bytes());
+            data.extend_from_slice(b"WEBP");
+            data.extend(chunks.concat());
+            data
+        };
+        let with_metadata = webp(&[
+            chunk(b"VP8X", &[0x0e, 0, 0, 0, 0, 0, 0, 0, 0, 0]),
+            chunk(b"ANIM", &[0; 6]),
+            chunk(b"ANMF", &[0; 17]),
+            chunk(b"ANMF", &[0; 17]),
+            chunk(b"EXIF", &[0; 5]),
+            chunk(b"XMP ", &[0; 8]),
+        ]);
+        let stripped = webp(&[
+            chunk(b"VP8X", &[0x02, 0, 0, 0, 0, 0, 0, 0, 0, 0]),
+            chunk(b"ANIM", &[0; 6]),
+            chunk(b"ANMF", &[0; 17]),
+            chunk(b"ANMF", &[0; 17]),
+        ]);
+        assert_eq!(strip_webp_metadata(&with_metadata).unwrap(), stripped);
+    }
+
+    #[tokio::test(flavor = "multi_thread", worker_threads = 2)]
+    async fn test_send_too_big_animation_as_image() -> Result<()> {
+        use image::codecs::gif::GifEncoder;
+        use image::{Delay, Frame, RgbaImage};
+
+        let mut tcm = TestContextManager::new();
+        let alice = &tcm.alice().await;
+        let bob = &tcm.bob().await;
+        alice
+            .set_config(
+                Config::MediaQuality,
+                Some(&(MediaQuality::Worse as i32).to_string()),
+            )
+            .await?;
+
+        // Noise does not compress, so the animation exceeds the size limit.
+        let mut seed = 1u32;
+        let mut noise = || {
+            seed = seed.wrapping_mul(1_103_515_245).wrapping_add(12345);
+            (seed >> 16) as u8
+        };
+        let mut bytes = Vec::new();
+        {
+            let mut encoder = GifEncoder::new(&mut bytes);
+            for _ in 0..3 {
+                let img =
+                    RgbaImage::from_fn(300, 300, |_, _| Rgba([noise(), noise(), noise(), 255]));
+                encoder.encode_frame(Frame::from_parts(
+                    img,
+                    0,
+                    0,
+                    Delay::from_numer_denom_ms(100, 1),
+                ))?;
+            }
+        }
+        assert!(bytes.len() > constants::WORSE_IMAGE_BYTES);
+
+        let file = alice.get_blobdir().join("animation.gif");
+        fs::write(&file, &bytes).await?;
+        let mut msg = Message::new(Viewtype::Image);
+        msg.set_file(file.to_str().unwrap(), None);
+        let chat = alice.create_chat(bob).await;
+        let sent = alice.send_msg(chat.id, &mut msg).await;
+        let msg = Message::load_from_db(alice, msg.id).await?;
+        assert_eq!(msg.get_viewtype(), Viewtype::Image);
+        assert_eq!(msg.get_frame_count(), 0);
+
+        let bob_msg = bob.recv_msg(&sent).await;
+        assert_eq!(bob_msg.get_viewtype(), Viewtype::Image);
+        assert!(bob_msg.get_filebytes(bob).await?.unwrap() <= constants::WORSE_IMAGE_BYTES as u64);
+        Ok(())
+    }
+
     #[tokio::test(flavor = "multi_thread", worker_threads = 2)]
     async fn test_send_big_gif_as_image() -> Result<()> {
         let bytes = include_bytes!("../test-data/image/screenshot.gif");
@@ -0,0 +1,177 @@
+//! # Bot commands.
+//!
+//! Bots can advertise the commands they understand by setting [`Config::BotCommands`].
+//! The commands are then sent in the `Chat-Bot-Commands` header
+//! of all outgoing messages as base64-encoded JSON.
+//! On the receiver side they are stored per contact,
+//! so UIs can offer autocompletion of `/`-commands
+//! using [`Contact::get_bot_commands`](crate::contact::Contact::get_bot_commands).
+
+use anyhow::{ensure, Context as _, Result};
+use base64::Engine as _;
+use serde::{Deserialize, Serialize};
+
+use crate::config::Config;
+use crate::contact::{Contact, ContactId};
+use crate::context::Context;
+use crate::events::EventType;
+use crate::mimefactory::wrapped_base64_encode;
+use crate::param::Param;
+
+/// Maximum number of commands a bot can advertise.
+const BOT_COMMANDS_MAX: usize = 100;
+
+/// Maximum size of the received JSON list of commands.
+const BOT_COMMANDS_SIZE_MAX: usize = 16 << 10;
+
+/// Command understood by a bot.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct BotCommand {
+    /// Command name without the leading `/`, e.g. `help`.
+    pub name: String,
+
+    /// Parameters of the command as displayed to the user, e.g. `<city> [<days>]`.
+    #[serde(default, skip_serializing_if = "String::is_empty")]
+    pub params: String,
+
+    /// Short description of the command.
+    #[serde(default, skip_serializing_if = "String::is_empty")]
+    pub description: String,
+}
+
+/// Parses a JSON list of bot commands.
+pub(crate) fn parse_bot_commands(json: &str) -> Result<Vec<BotCommand>> {
+    let commands: Vec<BotCommand> =
+        serde_json::from_str(json).context("Failed to parse bot commands")?;
+    ensure!(
+        commands.len() <= BOT_COMMANDS_MAX,
+        "Too many bot commands, at most {BOT_COMMANDS_MAX} are allowed"
+    );
+    for command in &commands {
+        ensure!(
+            !command.name.is_empty()
+                && !command.name.starts_with('/')
+                && !command.name.contains(char::is_whitespace),
+            "Invalid bot command name {:?}",
+            command.name
+        );
+    }
+    Ok(commands)
+}
+
+impl Context {
+    /// Sets the commands advertised by the bot.
+    ///
+    /// This is the same as setting [`Config::BotCommands`] to the JSON list of `commands`.
+    pub async fn set_bot_commands(&self, commands: &[BotCommand]) -> Result<()> {
+        self.set_config(Config::BotCommands, Some(&serde_json::to_string(commands)?))
+            .await
+    }
+}
+
+/// Returns the value of the `Chat-Bot-Commands` header to send, if any.
+pub(crate) async fn render_bot_commands_header(context: &Context) -> Result<Option<String>> {
+    if !context.get_config_bool(Config::Bot).await? {
+        return Ok(None);
+    }
+    let Some(json) = context.get_config(Config::BotCommands).await? else {
+        return Ok(None);
+    };
+    Ok(Some(wrapped_base64_encode(json.as_bytes())))
+}
+
+/// Stores commands received in the `Chat-Bot-Commands` header from `contact_id`.
+pub(crate) async fn receive_bot_commands(
+    context: &Context,
+    contact_id: ContactId,
+    header_value: &str,
+) -> Result<()> {
+    let json = base64::engine::general_purpose::STANDARD
+        .decode(header_value.split_ascii_whitespace().collect::<String>())
+        .context("Failed to decode bot commands")?;
+    ensure!(
+        json.len() <= BOT_COMMANDS_SIZE_MAX,
+        "Bot commands are too large"
+    );
+    let commands = parse_bot_commands(std::str::from_utf8(&json)?)?;
+    let commands = serde_json::to_string(&commands)?;
+
+    let mut contact = Contact::get_by_id(context, contact_id).await?;
+    if contact.param.get(Param::BotCommands) != Some(commands.as_str()) {
+        contact.param.set(Param::BotCommands, commands);
+        contact.update_param(context).await?;
+        context.emit_event(EventType::ContactsChanged(Some(contact_id)));
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_utils::TestContextManager;
+
+    #[test]
+    fn test_parse_bot_commands() -> Result<()> {
+        let commands =
+            parse_bot_commands(r#"[{"name":"help"},{"name":"weather","params":"<city>"}]"#)?;
+        assert_eq!(
+            commands,
+            vec![
+                BotCommand {
+                    name: "help".to_string(),
+                    params: String::new(),
+                    description: String::new(),
+                },
+                BotCommand {
+                    name: "weather".to_string(),
+                    params: "<city>".to_string(),
+                    description: String::new(),
+                }
+            ]
+        );
+        assert!(parse_bot_commands(r#"[{"name":"/help"}]"#).is_err());
+        assert!(parse_bot_commands(r#"[{"name":"two words"}]"#).is_err());
+        assert!(parse_bot_commands("{}").is_err());
+        Ok(())
+    }
+
+    #[tokio::test(flavor = "multi_thread", worker_threads = 2)]
+    async fn test_bot_commands() -> Result<()> {
+        let mut tcm = TestContextManager::new();
+        let alice = &tcm.alice().await;
+        let bob = &tcm.bob().await;
+        bob.set_config_bool(Config::Bot, true).await?;
+        let commands = vec![BotCommand {
+            name: "echo".to_string(),
+            params: "<text>".to_string(),
+            description: "Sends the text back ✅".to_string(),
+        }];
+        bob.set_bot_commands(&commands).await?;
+        assert!(bob
+            .set_config(Config::BotCommands, Some("not json"))
+            .await
+            .is_err());
+
+        tcm.send_recv_accept(bob, alice, "Hi!").await;
+        let contact = alice.add_or_lookup_contact(bob).await;
+        assert!(contact.is_bot());
+        assert_eq!(contact.get_bot_commands(), commands);
+
+        // Commands are only sent by bots.
+        bob.set_config_bool(Config::Bot, false).await?;
+        bob.set_bot_commands(&[]).await?;
+        tcm.send_recv(bob, alice, "Hi again!").await;
+        let contact = alice.add_or_lookup_contact(bob).await;
+        assert!(!contact.is_bot());
+        assert!(contact.get_bot_commands().is_empty());
+        assert!(contact.param.exists(Param::BotCommands));
+
+        bob.set_config_bool(Config::Bot, true).await?;
+        tcm.send_recv(bob, alice, "No commands").await;
+        let contact = alice.add_or_lookup_contact(bob).await;
+        assert!(contact.is_bot());
+        assert!(contact.get_bot_commands().is_empty());
+        assert!(contact.param.exists(Param::BotCommands));
+        Ok(())
+    }
+}
@@ -31,6 +31,7 @@
 use crate::ephemeral::Timer as EphemeralTimer;
 use crate::events::EventType;
 use crate::html::new_html_mimepart;
+use crate::key::{load_self_public_key, DcKey};
 use crate::location;
 use crate::log::LogExt;
 use crate::message::{self, Message, MessageState, MsgId, Viewtype};
@@ -38,16 +39,18 @@
 use crate::mimeparser::SystemMessage;
 use crate::param::{Param, Params};
 use crate::peerstate::Peerstate;
+use crate::preview;
 use crate::receive_imf::ReceivedMsg;
-use crate::securejoin::BobState;
+use crate::securejoin::{BobState, SecurejoinFailure};
 use crate::smtp::send_msg_to_smtp;
+use crate::spam_report;
 use crate::sql;
 use crate::stock_str;
 use crate::sync::{self, Sync::*, SyncData};
 use crate::tools::{
     buf_compress, create_id, create_outgoing_rfc724_mid, create_smeared_timestamp,
-    create_smeared_timestamps, get_abs_path, gm2local_offset, smeared_time, time, IsNoneOrEmpty,
-    SystemTime,
+    create_smeared_timestamps, get_abs_path, get_animation_frames, gm2local_offset, smeared_time,
+    time, IsNoneOrEmpty, SystemTime,
 };
 use crate::webxdc::StatusUpdateSerial;
 
@@ -105,6 +108,40 @@ pub enum ProtectionStatus {
     ProtectionBroken = 3, // `2` was never used as a value.
 }
 
+/// Chat-level encryption policy overriding the automatic Autocrypt decision.
+#[derive(
+    Debug,
+    Default,
+    Display,
+    Clone,
+    Copy,
+    PartialEq,
+    Eq,
+    FromPrimitive,
+    ToPrimitive,
+    Serialize,
+    Deserialize,
+)]
+#[repr(u32)]
+pub enum EncryptionPolicy {
+    /// Encrypt if all recipients' keys are known
+    /// and the Autocrypt recommendation says so.
+    #[default]
+    Opportunistic = 0,
+
+    /// Always encrypt, refuse to send if a recipient key is missing.
+    Required = 1,
+
+    /// Never encrypt, e.g. for ticketing systems
+    /// that cannot handle encrypted messages.
+    Disabled = 2,
+
+    /// Never encrypt, but sign messages with a PGP/MIME signature,
+    /// so they are readable in any mail client
+    /// and still can be authenticated by Delta Chat recipients.
+    Clearsigned = 3,
+}
+
 /// The reason why messages cannot be sent to the chat.
 ///
 /// The reason is mainly for logging and displaying in debug REPL, thus not translated.
@@ -371,6 +408,43 @@ async fn set_selfavatar_timestamp(self, context: &Context, timestamp: i64) -> Re
         Ok(())
     }
 
+    /// Sets the encryption policy of the chat.
+    ///
+    /// Protected chats and broadcast lists have a fixed policy
+    /// and cannot be changed.
+    pub async fn set_encryption_policy(
+        self,
+        context: &Context,
+        policy: EncryptionPolicy,
+    ) -> Result<()> {
+        ensure!(!self.is_special(), "Invalid chat ID {self}");
+        let mut chat = Chat::load_from_db(context, self).await?;
+        ensure!(
+            !chat.is_protected()
+                || !matches!(
+                    policy,
+                    EncryptionPolicy::Disabled | EncryptionPolicy::Clearsigned
+                ),
+            "Cannot disable encryption in a protected chat"
+        );
+        ensure!(
+            chat.typ != Chattype::Broadcast,
+            "Cannot set encryption policy of a broadcast list"
+        );
+        if chat.get_encryption_policy() == policy {
+            return Ok(());
+        }
+        if policy == EncryptionPolicy::Opportunistic {
+            chat.param.remove(Param::EncryptionPolicy);
+        } else {
+            chat.param.set_int(Param::EncryptionPolicy, policy as i32);
+        }
+        chat.update_param(context).await?;
+        context.emit_event(EventType::ChatModified(self));
+        chatlist_events::emit_chatlist_item_changed(context, self);
+        Ok(())
+    }
+
     /// Updates chat blocked status.
     ///
     /// Returns true if the value was modified.
@@ -402,6 +476,13 @@ pub(crate) async fn block_ex(self, context: &Context, sync: sync::Sync) -> Resul
                 bail!("Can't block chat of type {:?}", chat.typ)
             }
             Chattype::Single => {
+                if chat.blocked == Blocked::Request && sync.into() {
+                    spam_report::queue_report(context, self)
+                        .await
+                        .context("Failed to queue abuse report")
+                        .log_err(context)
+                        .ok();
+                }
                 for contact_id in get_chat_contacts(context, self).await? {
                     if contact_id != ContactId::SELF {
                         info!(
@@ -543,6 +624,10 @@ pub(crate) async fn inner_set_protection(
             .execute("UPDATE chats SET protected=? WHERE id=?;", (protect, self))
             .await?;
 
+        context.emit_event(EventType::ChatProtectionChanged {
+            chat_id: self,
+            protection: protect,
+        });
         context.emit_event(EventType::ChatModified(self));
         chatlist_events::emit_chatlist_item_changed(context, self);
 
@@ -774,6 +859,13 @@ pub async fn delete(self, context: &Context) -> Result<()> {
                 (self,),
             )
             .await?;
+        context
+            .sql
+            .execute(
+                "DELETE FROM msgs_deliveries WHERE msg_id IN (SELECT id FROM msgs WHERE chat_id=?);",
+                (self,),
+            )
+            .await?;
 
         context
             .sql
@@ -785,6 +877,11 @@ pub async fn delete(self, context: &Context) -> Result<()> {
             .execute("DELETE FROM chats_contacts WHERE chat_id=?;", (self,))
             .await?;
 
+        context
+            .sql
+            .execute("DELETE FROM chats_labels WHERE chat_id=?;", (self,))
+            .await?;
+
         context
             .sql
             .execute("DELETE FROM chats WHERE id=?;", (self,))
@@ -1702,6 +1799,12 @@ pub(crate) async fn check_securejoin_wait(
         )
         .await?;
         context.emit_event(EventType::ChatModified(self.id));
+        if let Some(contact_id) = get_chat_contacts(context, self.id).await?.first() {
+            context.emit_event(EventType::SecurejoinFailed {
+                contact_id: *contact_id,
+                reason: SecurejoinFailure::Timeout,
+            });
+        }
         Ok(0)
     }
 
@@ -1741,6 +1844,14 @@ pub fn get_name(&self) -> &str {
         &self.name
     }
 
+    /// Returns the encryption policy of the chat.
+    pub fn get_encryption_policy(&self) -> EncryptionPolicy {
+        self.param
+            .get_int(Param::EncryptionPolicy)
+            .and_then(num_traits::FromPrimitive::from_i32)
+            .unwrap_or_default()
+    }
+
     /// Returns mailing list address where messages are sent to.
     pub fn get_mailinglist_addr(&self) -> Option<&str> {
         self.param.get(Param::ListPost)
@@ -2082,7 +2193,7 @@ async fn prepare_msg_raw(
                          state=?, txt=?, txt_normalized=?, subject=?, param=?,
                          hidden=?, mime_in_reply_to=?, mime_references=?, mime_modified=?,
                          mime_headers=?, mime_compressed=1, location_id=?, ephemeral_timer=?,
-                         ephemeral_timestamp=?
+                         ephemeral_timestamp=?, thread_root=?
                      WHERE id=?;",
                     params_slice![
                         msg.rfc724_mid,
@@ -2104,6 +2215,7 @@ async fn prepare_msg_raw(
                         location_id as i32,
                         ephemeral_timer,
                         ephemeral_timestamp,
+                        msg.thread_root.as_deref().unwrap_or_default(),
                         update_msg_id
                     ],
                 )
@@ -2133,8 +2245,9 @@ async fn prepare_msg_raw(
                         mime_compressed,
                         location_id,
                         ephemeral_timer,
-                        ephemeral_timestamp)
-                        VALUES (?,?,?,?,?,?,?,?,?,?,?,?,?,?,?,?,1,?,?,?);",
+                        ephemeral_timestamp,
+                        thread_root)
+                        VALUES (?,?,?,?,?,?,?,?,?,?,?,?,?,?,?,?,1,?,?,?,?);",
                     params_slice![
                         msg.rfc724_mid,
                         msg.chat_id,
@@ -2154,7 +2267,8 @@ async fn prepare_msg_raw(
                         new_mime_headers.unwrap_or_default(),
                         location_id as i32,
                         ephemeral_timer,
-                        ephemeral_timestamp
+                        ephemeral_timestamp,
+                        msg.thread_root.as_deref().unwrap_or_default()
                     ],
                 )
                 .await?;
@@ -2164,6 +2278,11 @@ async fn prepare_msg_raw(
             maybe_set_logging_xdc(context, msg, self.id).await?;
             context.update_webxdc_integration_database(msg).await?;
         }
+        if msg.param.exists(Param::Quote) && !msg.is_forwarded() {
+            if let Some(in_reply_to) = &msg.in_reply_to {
+                message::set_quoted_msg_id(context, msg.id, in_reply_to).await?;
+            }
+        }
         context.scheduler.interrupt_ephemeral_task().await;
         Ok(msg.id)
     }
@@ -2626,7 +2745,8 @@ pub async fn prepare_msg(context: &Context, chat_id: ChatId, msg: &mut Message)
         "Cannot prepare message for special chat"
     );
 
-    let msg_id = prepare_msg_common(context, chat_id, msg, MessageState::OutPreparing).await?;
+    let msg_id =
+        prepare_msg_common(context, chat_id, msg, MessageState::OutPreparing, true).await?;
     context.emit_msgs_changed(msg.chat_id, msg.id);
 
     Ok(msg_id)
@@ -2671,6 +2791,23 @@ async fn prepare_msg_blob(context: &Context, msg: &mut Message) -> Result<()> {
             msg.try_set_vcard(context, &blob.to_abs_path()).await?;
         }
 
+        if matches!(msg.viewtype, Viewtype::Image | Viewtype::Gif) {
+            let path = blob.to_abs_path();
+            let frames = tokio::task::block_in_place(|| -> Result<_> {
+                let file = std::fs::File::open(&path)?;
+                Ok(get_animation_frames(&mut std::io::BufReader::new(file)))
+            })?;
+            if let Some(frames) = frames {
+                if send_as_is || blob.prepare_animated_image(context).await? {
+                    msg.viewtype = Viewtype::Gif;
+                    msg.param.set_int(Param::Frames, frames as i32);
+                } else {
+                    // Too large to be sent as an animation, send the first frame instead.
+                    msg.viewtype = Viewtype::Image;
+                }
+            }
+        }
+
         let mut maybe_sticker = msg.viewtype == Viewtype::Sticker;
         if !send_as_is
             && (msg.viewtype == Viewtype::Image
@@ -2701,6 +2838,16 @@ async fn prepare_msg_blob(context: &Context, msg: &mut Message) -> Result<()> {
 
         msg.try_calc_and_set_dimensions(context).await?;
 
+        let mime = msg.param.get(Param::MimeType);
+        if !msg.param.exists(Param::PreviewFile) {
+            let path = blob.to_abs_path();
+            let preview =
+                preview::create_preview_from_file(context, msg.viewtype, mime, &path).await;
+            if let Some(preview) = preview {
+                msg.param.set(Param::PreviewFile, preview);
+            }
+        }
+
         info!(
             context,
             "Attaching \"{}\" for message type #{}.",
@@ -2710,15 +2857,33 @@ async fn prepare_msg_blob(context: &Context, msg: &mut Message) -> Result<()> {
     } else {
         bail!("Cannot send messages of type #{}.", msg.viewtype);
     }
+    if msg.is_view_once() {
+        ensure!(
+            matches!(
+                msg.viewtype,
+                Viewtype::Image
+                    | Viewtype::Gif
+                    | Viewtype::Video
+                    | Viewtype::Audio
+                    | Viewtype::Voice
+            ),
+            "Cannot send messages of type #{} as view-once.",
+            msg.viewtype
+        );
+    }
     Ok(())
 }
 
 /// Prepares a message to be sent out.
+///
+/// If `prepare_blob` is false, the attachment is left as is
+/// and has to be prepared with [`prepare_msg_blob`] before sending.
 async fn prepare_msg_common(
     context: &Context,
     chat_id: ChatId,
     msg: &mut Message,
     change_state_to: MessageState,
+    prepare_blob: bool,
 ) -> Result<MsgId> {
     let mut chat = Chat::load_from_db(context, chat_id).await?;
 
@@ -2765,7 +2930,9 @@ async fn prepare_msg_common(
     // ... then change the MessageState in the message object
     msg.state = change_state_to;
 
-    prepare_msg_blob(context, msg).await?;
+    if prepare_blob {
+        prepare_msg_blob(context, msg).await?;
+    }
     if !msg.hidden {
         chat_id.unarchive_if_not_muted(context, msg.state).await?;
     }
@@ -2877,6 +3044,140 @@ async fn send_msg_inner(context: &Context, chat_id: ChatId, msg: &mut Message) -
     Ok(msg.id)
 }
 
+/// Sends a message object to a chat without waiting for the message to be prepared.
+///
+/// Unlike [`send_msg`], the attachment is neither copied to the blob directory nor recoded
+/// and the message is not rendered before returning.
+/// Instead, the message is added to the chat in the [`MessageState::OutPending`] state
+/// so that the UI can show it immediately,
+/// and a background task prepares it and queues it for sending.
+/// Messages sent to the same chat later with [`send_msg`] are queued after it.
+/// If the preparation fails, the state of the message is changed to [`MessageState::OutFailed`].
+///
+/// Messages prepared with [`prepare_msg`] should be sent with [`send_msg`].
+pub async fn send_msg_deferred(
+    context: &Context,
+    chat_id: ChatId,
+    msg: &mut Message,
+) -> Result<MsgId> {
+    ensure!(!chat_id.is_special(), "Cannot send message to special chat");
+    ensure!(
+        msg.state != MessageState::OutPreparing,
+        "Prepared messages cannot be deferred"
+    );
+    if msg.viewtype.has_file() {
+        ensure!(
+            msg.param.exists(Param::File),
+            "Attachment missing for message of type #{}",
+            msg.viewtype
+        );
+    }
+    // protect all system messages against RTLO attacks
+    if msg.is_system_message() {
+        msg.text = sanitize_bidi_characters(&msg.text);
+    }
+
+    prepare_msg_common(context, chat_id, msg, MessageState::OutPending, false).await?;
+    context
+        .sql
+        .insert("INSERT INTO smtp_prepare (msg_id) VALUES (?)", (msg.id,))
+        .await?;
+    if !msg.hidden {
+        context.emit_msgs_changed(msg.chat_id, msg.id);
+    }
+    spawn_prepare_deferred_msgs(context);
+    Ok(msg.id)
+}
+
+/// Prepares a message sent with [`send_msg_deferred`] and creates its SMTP jobs.
+async fn prepare_deferred_msg(context: &Context, msg_id: MsgId) -> Result<()> {
+    let mut msg = Message::load_from_db(context, msg_id).await?;
+    if msg.chat_id.is_trash() || msg.state != MessageState::OutPending {
+        // The message was deleted in the meantime.
+        return Ok(());
+    }
+    prepare_msg_blob(context, &mut msg).await?;
+    context
+        .sql
+        .execute(
+            "UPDATE msgs SET type=?, param=? WHERE id=?",
+            (msg.viewtype, msg.param.to_string(), msg.id),
+        )
+        .await?;
+    create_send_msg_jobs(context, &mut msg).await?;
+    context.emit_msgs_changed(msg.chat_id, msg.id);
+    Ok(())
+}
+
+/// Spawns a task preparing the messages sent with [`send_msg_deferred`].
+///
+/// Recoding images may take a while, so this is not done by the SMTP loop
+/// which would not be able to send other messages in the meantime.
+pub(crate) fn spawn_prepare_deferred_msgs(context: &Context) {
+    let context = context.clone();
+    task::spawn(async move {
+        prepare_deferred_msgs(&context)
+            .await
+            .context("Failed to prepare deferred messages")
+            .log_err(&context)
+            .ok();
+    });
+}
+
+/// Prepares the messages sent with [`send_msg_deferred`] and interrupts the SMTP loop.
+///
+/// Messages which cannot be prepared are marked as failed.
+pub(crate) async fn prepare_deferred_msgs(context: &Context) -> Result<()> {
+    let _guard = context.deferred_msgs_mutex.lock().await;
+    let rows = context
+        .sql
+        .query_map(
+            "SELECT id, msg_id FROM smtp_prepare ORDER BY id",
+            (),
+            |row| {
+                let rowid: i64 = row.get(0)?;
+                let msg_id: MsgId = row.get(1)?;
+                Ok((rowid, msg_id))
+            },
+            |rows| rows.collect::<Result<Vec<_>, _>>().map_err(Into::into),
+        )
+        .await?;
+    let rows_empty = rows.is_empty();
+    for (rowid, msg_id) in rows {
+        if let Err(err) = prepare_deferred_msg(context, msg_id).await {
+            warn!(context, "Failed to prepare message {msg_id}: {err:#}.");
+            if let Ok(mut msg) = Message::load_from_db(context, msg_id).await {
+                message::set_msg_failed(context, &mut msg, &format!("{err:#}"))
+                    .await
+                    .log_err(context)
+                    .ok();
+            }
+        }
+        context
+            .sql
+            .execute("DELETE FROM smtp_prepare WHERE id=?", (rowid,))
+            .await?;
+    }
+    if !rows_empty {
+        context.scheduler.interrupt_smtp().await;
+    }
+    Ok(())
+}
+
+/// Returns whether the chat has messages sent with [`send_msg_deferred`] which are not
+/// prepared yet.
+async fn has_deferred_msgs(context: &Context, chat_id: ChatId) -> Result<bool> {
+    context
+        .sql
+        .exists(
+            "SELECT COUNT(*) FROM smtp_prepare p
+             INNER JOIN msgs m ON m.id=p.msg_id
+             WHERE m.chat_id=?",
+            (chat_id,),
+        )
+        .await
+}
+
 /// Returns row ids of the `smtp` table.
 async fn prepare_send_msg(
     context: &Context,
@@ -2889,7 +3190,7 @@ async fn prepare_send_msg(
     // the state to OutPending.
     if msg.state != MessageState::OutPreparing {
         // automatically prepare normal messages
-        prepare_msg_common(context, chat_id, msg, MessageState::OutPending).await?;
+        prepare_msg_common(context, chat_id, msg, MessageState::OutPending, true).await?;
     } else {
         // update message state of separately prepared messages
         ensure!(
@@ -2898,6 +3199,10 @@ async fn prepare_send_msg(
         );
         message::update_msg_state(context, msg.id, MessageState::OutPending).await?;
     }
+    // Deferred messages to the same chat must be sent first.
+    if has_deferred_msgs(context, msg.chat_id).await? {
+        prepare_deferred_msgs(context).await?;
+    }
     create_send_msg_jobs(context, msg).await
 }
 
@@ -3015,6 +3320,24 @@ pub(crate) async fn create_send_msg_jobs(context: &Context, msg: &mut Message) -
         msg.update_param(context).await?;
     }
 
+    if rendered_msg.is_clearsigned && !msg.is_clearsigned() {
+        msg.param.set_int(Param::Clearsigned, 1);
+        msg.update_param(context).await?;
+    }
+
+    if rendered_msg.is_encrypted || rendered_msg.is_clearsigned {
+        let security_info = message::SecurityInfo {
+            encrypted: rendered_msg.is_encrypted,
+            signing_fingerprint: Some(DcKey::fingerprint(&load_self_public_key(context).await?)),
+            verified: Chat::load_from_db(context, msg.chat_id)
+                .await?
+                .is_protected(),
+            pq_hybrid: rendered_msg.is_pq_hybrid,
+            gossiped: rendered_msg.is_gossiped,
+        };
+        message::save_security_info(context, msg.id, &security_info).await?;
+    }
+
     msg.subject.clone_from(&rendered_msg.subject);
     msg.update_subject(context).await?;
     let chunk_size = context.get_max_smtp_rcpt_to().await?;
@@ -3036,7 +3359,13 @@ pub(crate) async fn create_send_msg_jobs(context: &Context, msg: &mut Message) -
         }
         Ok(row_ids)
     };
-    context.sql.transaction(trans_fn).await
+    let row_ids = context.sql.transaction(trans_fn).await?;
+
+    // The file is only needed for rendering, retries use the rendered message.
+    if msg.is_view_once() {
+        msg.remove_view_once_file(context).await?;
+    }
+    Ok(row_ids)
 }
 
 /// Sends a text message to the given chat.
@@ -3058,6 +3387,232 @@ pub async fn send_text_msg(
     send_msg(context, chat_id, &mut msg).await
 }
 
+/// Replaces the text of our message `msg_id` with `new_text`
+/// on our other devices and for all chat members.
+///
+/// The previous text is kept and can be retrieved with [`MsgId::get_edit_history`].
+pub async fn send_edit(context: &Context, msg_id: MsgId, new_text: String) -> Result<MsgId> {
+    let mut msg = Message::load_from_db(context, msg_id).await?;
+    ensure!(msg.from_id == ContactId::SELF, "Can edit only own messages");
+    ensure!(
+        !msg.is_info() && !msg.hidden && !msg.param.exists(Param::Poll) && !msg.is_retracted(),
+        "Message cannot be edited"
+    );
+    ensure!(
+        matches!(
+            msg.state,
+            MessageState::OutPending | MessageState::OutDelivered | MessageState::OutMdnRcvd
+        ),
+        "Can edit only sent messages"
+    );
+    let new_text = new_text.trim().to_string();
+    ensure!(!new_text.is_empty(), "Edited text is empty");
+
+    let mut edit_msg = Message::new(Viewtype::Text);
+    edit_msg.text = new_text.clone();
+    edit_msg.param.set(Param::TextEditFor, &msg.rfc724_mid);
+    edit_msg.in_reply_to = Some(msg.rfc724_mid.clone());
+    edit_msg.hidden = true;
+    let edit_msg_id = send_msg(context, msg.chat_id, &mut edit_msg).await?;
+
+    apply_edit(context, &mut msg, &new_text, edit_msg.timestamp_sort).await?;
+    Ok(edit_msg_id)
+}
+
+/// Applies the edit received in the `Chat-Edit` header
+/// to the message with `edit_for` Message-ID.
+pub(crate) async fn receive_edit(
+    context: &Context,
+    edit_for: &str,
+    from_id: ContactId,
+    new_text: &str,
+    timestamp: i64,
+    encrypted: bool,
+) -> Result<()> {
+    let Some((msg_id, _)) = message::rfc724_mid_exists(context, edit_for).await? else {
+        info!(context, "Ignoring edit of unknown message {edit_for}.");
+        return Ok(());
+    };
+    let mut msg = Message::load_from_db(context, msg_id).await?;
+    ensure!(
+        msg.from_id == from_id,
+        "Edit is not sent by the sender of the message"
+    );
+    ensure!(
+        encrypted || !msg.get_showpadlock(),
+        "Unencrypted edit of an encrypted message"
+    );
+    ensure!(
+        !msg.is_info() && !msg.param.exists(Param::Poll) && !msg.is_retracted(),
+        "Message cannot be edited"
+    );
+    ensure!(!new_text.is_empty(), "Edited text is empty");
+    apply_edit(context, &mut msg, new_text, timestamp).await
+}
+
+/// Replaces the text of `msg`, keeping the previous text in the edit history.
+async fn apply_edit(
+    context: &Context,
+    msg: &mut Message,
+    new_text: &str,
+    timestamp: i64,
+) -> Result<()> {
+    if msg.text == new_text {
+        return Ok(());
+    }
+    let msg_id = msg.id;
+    let old_text = msg.text.clone();
+    let mut param = msg.param.clone();
+    param.set_int(Param::IsEdited, 1);
+    let param_str = param.to_string();
+    let applied = context
+        .sql
+        .transaction(move |transaction| {
+            let newer_edit_exists = transaction.query_row(
+                "SELECT EXISTS (SELECT 1 FROM msgs_edits WHERE msg_id=? AND timestamp>?)",
+                (msg_id, timestamp),
+                |row| row.get::<_, bool>(0),
+            )?;
+            if newer_edit_exists {
+                return Ok(false);
+            }
+            transaction.execute(
+                "INSERT INTO msgs_edits (msg_id, text, timestamp) VALUES (?, ?, ?)",
+                (msg_id, old_text, timestamp),
+            )?;
+            transaction.execute(
+                "UPDATE msgs SET txt=?, txt_normalized=?, param=? WHERE id=?",
+                (
+                    new_text,
+                    message::normalize_text(new_text),
+                    param_str,
+                    msg_id,
+                ),
+            )?;
+            Ok(true)
+        })
+        .await?;
+    if !applied {
+        info!(context, "Ignoring outdated edit of message {msg_id}.");
+        return Ok(());
+    }
+    msg.text = new_text.to_string();
+    msg.param = param;
+    context.emit_msgs_changed(msg.chat_id, msg.id);
+    Ok(())
+}
+
+/// Pins the message `msg_id` in its chat for all chat members.
+///
+/// Pinned messages can be listed with [`get_pinned_messages`].
+pub async fn pin_message(context: &Context, msg_id: MsgId) -> Result<()> {
+    set_msg_pinned(context, msg_id, true).await
+}
+
+/// Unpins the message `msg_id` pinned before with [`pin_message`].
+pub async fn unpin_message(context: &Context, msg_id: MsgId) -> Result<()> {
+    set_msg_pinned(context, msg_id, false).await
+}
+
+/// Returns the messages pinned in the chat, the most recently pinned first.
+pub async fn get_pinned_messages(context: &Context, chat_id: ChatId) -> Result<Vec<MsgId>> {
+    let list = context
+        .sql
+        .query_map(
+            "SELECT p.msg_id
+               FROM pinned_msgs p
+              INNER JOIN msgs m ON m.id=p.msg_id
+              WHERE p.chat_id=? AND m.chat_id=p.chat_id
+              ORDER BY p.timestamp DESC, p.msg_id DESC",
+            (chat_id,),
+            |row| row.get::<_, MsgId>(0),
+            |ids| Ok(ids.flatten().collect()),
+        )
+        .await?;
+    Ok(list)
+}
+
+async fn set_msg_pinned(context: &Context, msg_id: MsgId, pinned: bool) -> Result<()> {
+    let msg = Message::load_from_db(context, msg_id).await?;
+    ensure!(
+        !msg.chat_id.is_special(),
+        "Messages in special chats cannot be pinned"
+    );
+    ensure!(
+        !msg.is_info() && !msg.hidden,
+        "Info messages cannot be pinned"
+    );
+    ensure!(
+        !msg.rfc724_mid.is_empty(),
+        "Message without Message-Id cannot be pinned"
+    );
+    if !set_msg_pinned_ex(context, &msg, pinned, time()).await? {
+        return Ok(());
+    }
+
+    if msg.chat_id.is_promoted(context).await? {
+        let mut info_msg = Message::new(Viewtype::Text);
+        if pinned {
+            info_msg.text = stock_str::msg_pinned(context, ContactId::SELF).await;
+            info_msg.param.set_cmd(SystemMessage::MessagePinned);
+        } else {
+            info_msg.text = stock_str::msg_unpinned(context, ContactId::SELF).await;
+            info_msg.param.set_cmd(SystemMessage::MessageUnpinned);
+        }
+        info_msg.param.set(Param::Arg, &msg.rfc724_mid);
+        send_msg(context, msg.chat_id, &mut info_msg).await?;
+    }
+    Ok(())
+}
+
+/// Pins or unpins `msg` locally, returns false if it was already (un)pinned.
+async fn set_msg_pinned_ex(
+    context: &Context,
+    msg: &Message,
+    pinned: bool,
+    timestamp: i64,
+) -> Result<bool> {
+    let changed = if pinned {
+        context
+            .sql
+            .execute(
+                "INSERT OR IGNORE INTO pinned_msgs (msg_id, chat_id, timestamp) VALUES (?, ?, ?)",
+                (msg.id, msg.chat_id, timestamp),
+            )
+            .await?
+    } else {
+        context
+            .sql
+            .execute("DELETE FROM pinned_msgs WHERE msg_id=?", (msg.id,))
+            .await?
+    } > 0;
+    if changed {
+        context.emit_event(EventType::ChatModified(msg.chat_id));
+    }
+    Ok(changed)
+}
+
+/// Applies a received pin or unpin of the message with `rfc724_mid` Message-ID in `chat_id`.
+pub(crate) async fn receive_pin(
+    context: &Context,
+    chat_id: ChatId,
+    rfc724_mid: &str,
+    pinned: bool,
+    timestamp: i64,
+) -> Result<()> {
+    let Some((msg_id, _)) = message::rfc724_mid_exists(context, rfc724_mid).await? else {
+        info!(context, "Ignoring pin of unknown message {rfc724_mid}.");
+        return Ok(());
+    };
+    let msg = Message::load_from_db(context, msg_id).await?;
+    ensure!(
+        msg.chat_id == chat_id,
+        "Pinned message belongs to another chat"
+    );
+    set_msg_pinned_ex(context, &msg, pinned, timestamp).await?;
+    Ok(())
+}
+
 /// Sends invitation to a videochat.
 pub async fn send_videochat_invitation(context: &Context, chat_id: ChatId) -> Result<MsgId> {
     ensure!(
@@ -3220,22 +3775,170 @@ pub async fn get_chat_msgs_ex(
     Ok(items)
 }
 
-pub(crate) async fn marknoticed_chat_if_older_than(
-    context: &Context,
-    chat_id: ChatId,
-    timestamp: i64,
+/// Searches for messages in the chat containing `query` case-insensitively,
+/// see [`Context::search_msgs`].
+///
+/// The messages are returned in chronological order as in [`get_chat_msgs`],
+/// so that UIs can navigate to the next or previous result
+/// and load the surrounding messages using [`get_msgs_around`].
+pub async fn search(context: &Context, chat_id: ChatId, query: &str) -> Result<Vec<MsgId>> {
+    ensure!(
+        !chat_id.is_special(),
+        "Cannot search in special chat {chat_id}"
+    );
+    context.search_msgs(Some(chat_id), query).await
+}
+
+/// Returns up to `before` messages preceding and `after` messages following `msg_id`
+/// in its chat, together with `msg_id` itself, in chronological order.
+///
+/// Hidden messages are skipped as in [`get_chat_msgs`].
+pub async fn get_msgs_around(
+    context: &Context,
+    msg_id: MsgId,
+    before: usize,
+    after: usize,
+) -> Result<Vec<MsgId>> {
+    let (chat_id, timestamp): (ChatId, i64) = context
+        .sql
+        .query_row_optional(
+            "SELECT chat_id, timestamp FROM msgs WHERE id=?",
+            (msg_id,),
+            |row| Ok((row.get(0)?, row.get(1)?)),
+        )
+        .await?
+        .with_context(|| format!("Message {msg_id} does not exist"))?;
+    let get_id = |row: &rusqlite::Row| row.get::<_, MsgId>(0);
+    let collect_ids = |ids: rusqlite::MappedRows<_>| {
+        ids.collect::<std::result::Result<Vec<_>, _>>()
+            .map_err(Into::into)
+    };
+
+    let mut msg_ids: Vec<MsgId> = context
+        .sql
+        .query_map(
+            "SELECT id FROM msgs
+             WHERE chat_id=?1 AND hidden=0
+               AND (timestamp<?2 OR (timestamp=?2 AND id<?3))
+             ORDER BY timestamp DESC, id DESC LIMIT ?4",
+            (chat_id, timestamp, msg_id, before),
+            get_id,
+            collect_ids,
+        )
+        .await?;
+    msg_ids.reverse();
+    msg_ids.push(msg_id);
+    msg_ids.extend(
+        context
+            .sql
+            .query_map(
+                "SELECT id FROM msgs
+                 WHERE chat_id=?1 AND hidden=0
+                   AND (timestamp>?2 OR (timestamp=?2 AND id>?3))
+                 ORDER BY timestamp, id LIMIT ?4",
+                (chat_id, timestamp, msg_id, after),
+                get_id,
+                collect_ids,
+            )
+            .await?,
+    );
+    Ok(msg_ids)
+}
+
+/// Returns the first unread message of the chat,
+/// e.g. to show an "unread messages" divider before it and to scroll there.
+///
+/// This is the first fresh or noticed incoming message following the last read message,
+/// so that older messages remaining unread, e.g. because they were read on another device
+/// only partially, do not move the divider up.
+/// Returns `None` if there are no unread messages.
+pub async fn get_first_unread(context: &Context, chat_id: ChatId) -> Result<Option<MsgId>> {
+    context
+        .sql
+        .query_get_value(
+            "SELECT m.id FROM msgs m
+             INNER JOIN chats c ON c.id=m.chat_id
+             LEFT JOIN msgs r ON r.id=c.last_read_msg_id AND r.chat_id=m.chat_id
+             WHERE m.chat_id=?1 AND m.hidden=0 AND m.state IN (?2, ?3)
+               AND (r.id IS NULL OR m.timestamp>r.timestamp
+                    OR (m.timestamp=r.timestamp AND m.id>r.id))
+             ORDER BY m.timestamp, m.id LIMIT 1",
+            (chat_id, MessageState::InFresh, MessageState::InNoticed),
+        )
+        .await
+}
+
+/// Remembers `msg_id` as the last read message of its chat
+/// unless a later message was read already.
+pub(crate) async fn update_last_read_msg(context: &Context, msg_id: MsgId) -> Result<()> {
+    context
+        .sql
+        .execute(
+            "UPDATE chats SET last_read_msg_id=?1
+             WHERE id=(SELECT chat_id FROM msgs WHERE id=?1)
+               AND NOT EXISTS (
+                 SELECT 1 FROM msgs r, msgs m
+                 WHERE r.id=chats.last_read_msg_id AND m.id=?1
+                   AND (r.timestamp>m.timestamp OR (r.timestamp=m.timestamp AND r.id>=m.id)))",
+            (msg_id,),
+        )
+        .await?;
+    Ok(())
+}
+
+pub(crate) async fn marknoticed_chat_if_older_than(
+    context: &Context,
+    chat_id: ChatId,
+    timestamp: i64,
 ) -> Result<()> {
     if let Some(chat_timestamp) = chat_id.get_timestamp(context).await? {
         if timestamp > chat_timestamp {
-            marknoticed_chat(context, chat_id).await?;
+            marknoticed_chat_ex(context, Nosync, chat_id).await?;
         }
     }
     Ok(())
 }
 
+/// Marks messages in the chat not newer than `timestamp` as noticed.
+///
+/// Used for synced [`SyncAction::MarkNoticed`]
+/// so that messages the sending device did not have yet stay fresh.
+async fn marknoticed_chat_until(context: &Context, chat_id: ChatId, timestamp: i64) -> Result<()> {
+    if context
+        .sql
+        .execute(
+            "UPDATE msgs
+            SET state=?
+          WHERE state=?
+            AND hidden=0
+            AND chat_id=?
+            AND timestamp<=?;",
+            (
+                MessageState::InNoticed,
+                MessageState::InFresh,
+                chat_id,
+                timestamp,
+            ),
+        )
+        .await?
+        == 0
+    {
+        return Ok(());
+    }
+
+    context.emit_event(EventType::MsgsNoticed(chat_id));
+    chatlist_events::emit_chatlist_item_changed(context, chat_id);
+    context.on_archived_chats_maybe_noticed();
+    Ok(())
+}
+
 /// Marks all messages in the chat as noticed.
 /// If the given chat-id is the archive-link, marks all messages in all archived chats as noticed.
 pub async fn marknoticed_chat(context: &Context, chat_id: ChatId) -> Result<()> {
+    marknoticed_chat_ex(context, Sync, chat_id).await
+}
+
+async fn marknoticed_chat_ex(context: &Context, sync: sync::Sync, chat_id: ChatId) -> Result<()> {
     // "WHERE" below uses the index `(state, hidden, chat_id)`, see get_fresh_msg_cnt() for reasoning
     // the additional SELECT statement may speed up things as no write-blocking is needed.
     if chat_id.is_archived_link() {
@@ -3267,6 +3970,9 @@ pub async fn marknoticed_chat(context: &Context, chat_id: ChatId) -> Result<()>
         for chat_id_in_archive in chat_ids_in_archive {
             context.emit_event(EventType::MsgsNoticed(chat_id_in_archive));
             chatlist_events::emit_chatlist_item_changed(context, chat_id_in_archive);
+            if sync.into() {
+                sync_marknoticed(context, chat_id_in_archive).await;
+            }
         }
     } else if context
         .sql
@@ -3287,9 +3993,22 @@ pub async fn marknoticed_chat(context: &Context, chat_id: ChatId) -> Result<()>
     context.emit_event(EventType::MsgsNoticed(chat_id));
     chatlist_events::emit_chatlist_item_changed(context, chat_id);
     context.on_archived_chats_maybe_noticed();
+    if sync.into() && !chat_id.is_archived_link() {
+        sync_marknoticed(context, chat_id).await;
+    }
     Ok(())
 }
 
+/// Sends a sync message so that other devices mark the chat as noticed as well.
+async fn sync_marknoticed(context: &Context, chat_id: ChatId) {
+    let res = async {
+        let chat = Chat::load_from_db(context, chat_id).await?;
+        let timestamp = chat_id.get_timestamp(context).await?.unwrap_or_default();
+        chat.sync(context, SyncAction::MarkNoticed(timestamp)).await
+    };
+    res.await.log_err(context).ok();
+}
+
 /// Marks messages preceding outgoing messages as noticed.
 ///
 /// In a chat, if there is an outgoing message, it can be assumed that all previous
@@ -3360,6 +4079,100 @@ pub(crate) async fn mark_old_messages_as_noticed(
     Ok(())
 }
 
+/// Returns the root message and all replies of the topic thread
+/// started by `root_id`, sorted by timestamp.
+pub async fn get_thread_msgs(context: &Context, root_id: MsgId) -> Result<Vec<MsgId>> {
+    let root = Message::load_from_db(context, root_id).await?;
+    let list = context
+        .sql
+        .query_map(
+            "SELECT id
+               FROM msgs
+              WHERE chat_id=?
+                AND (id=? OR thread_root=?)
+                AND hidden=0
+              ORDER BY timestamp, id;",
+            (root.chat_id, root_id, &root.rfc724_mid),
+            |row| row.get::<_, MsgId>(0),
+            |ids| Ok(ids.flatten().collect()),
+        )
+        .await?;
+    Ok(list)
+}
+
+/// Returns the number of fresh messages in the topic thread started by `root_id`.
+pub async fn get_thread_fresh_msg_cnt(context: &Context, root_id: MsgId) -> Result<usize> {
+    let root = Message::load_from_db(context, root_id).await?;
+    let count = context
+        .sql
+        .count(
+            "SELECT COUNT(*)
+               FROM msgs
+              WHERE chat_id=?
+                AND (id=? OR thread_root=?)
+                AND state=?
+                AND hidden=0;",
+            (
+                root.chat_id,
+                root_id,
+                &root.rfc724_mid,
+                MessageState::InFresh,
+            ),
+        )
+        .await?;
+    Ok(count)
+}
+
+/// Returns the info messages of the chat, e.g. about member or timer changes,
+/// sorted by timestamp.
+///
+/// If `kind` is set, only info messages of this [`SystemMessage`] type are returned;
+/// info messages without a specific type have the type [`SystemMessage::Unknown`].
+pub async fn get_info_msgs(
+    context: &Context,
+    chat_id: ChatId,
+    kind: Option<SystemMessage>,
+) -> Result<Vec<MsgId>> {
+    let rows = context
+        .sql
+        .query_map(
+            // GLOB is used here instead of LIKE because it is case-sensitive
+            "SELECT id, from_id, to_id, param
+               FROM msgs
+              WHERE chat_id=?
+                AND hidden=0
+                AND (
+                    param GLOB \"*S=*\"
+                    OR from_id=?
+                    OR to_id=?
+                )
+              ORDER BY timestamp, id;",
+            (chat_id, ContactId::INFO, ContactId::INFO),
+            |row| {
+                let id: MsgId = row.get(0)?;
+                let from_id: ContactId = row.get(1)?;
+                let to_id: ContactId = row.get(2)?;
+                let param: String = row.get(3)?;
+                Ok((id, from_id, to_id, param))
+            },
+            |rows| rows.collect::<Result<Vec<_>, _>>().map_err(Into::into),
+        )
+        .await?;
+
+    let mut list = Vec::new();
+    for (id, from_id, to_id, param) in rows {
+        // is_info logic taken from Message.is_info()
+        let cmd = Params::from_str(&param).unwrap_or_default().get_cmd();
+        let is_info = from_id == ContactId::INFO
+            || to_id == ContactId::INFO
+            || (cmd != SystemMessage::Unknown && cmd != SystemMessage::AutocryptSetupMessage);
+        if is_info && kind.map_or(true, |kind| kind == cmd) {
+            list.push(id);
+        }
+    }
+    Ok(list)
+}
+
 /// Returns all database message IDs of the given types.
 ///
 /// If `chat_id` is None, return messages from any chat.
@@ -3407,6 +4220,155 @@ pub async fn get_chat_media(
     Ok(list)
 }
 
+/// Group of viewtypes shown together in a media gallery, see [`get_media_page`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum MediaCategory {
+    /// Images and GIFs.
+    Images,
+
+    /// Videos.
+    Videos,
+
+    /// Audio files and voice messages.
+    Audio,
+
+    /// Other files.
+    Documents,
+
+    /// Webxdc apps.
+    Webxdc,
+}
+
+impl MediaCategory {
+    /// Returns the viewtypes belonging to the category.
+    pub fn viewtypes(self) -> &'static [Viewtype] {
+        match self {
+            MediaCategory::Images => &[Viewtype::Image, Viewtype::Gif],
+            MediaCategory::Videos => &[Viewtype::Video],
+            MediaCategory::Audio => &[Viewtype::Audio, Viewtype::Voice],
+            MediaCategory::Documents => &[Viewtype::File],
+            MediaCategory::Webxdc => &[Viewtype::Webxdc],
+        }
+    }
+}
+
+/// Number of messages per [`MediaCategory`], see [`get_media_counts`].
+#[derive(Debug, Default, Clone, PartialEq, Eq)]
+pub struct MediaCounts {
+    /// Number of images and GIFs.
+    pub images: usize,
+
+    /// Number of videos.
+    pub videos: usize,
+
+    /// Number of audio files and voice messages.
+    pub audio: usize,
+
+    /// Number of other files.
+    pub documents: usize,
+
+    /// Number of webxdc apps.
+    pub webxdc: usize,
+}
+
+/// Returns up to `limit` messages of the media `category`, starting with the newest one.
+///
+/// To get the next page, pass the last returned message as `older_than`.
+/// If `chat_id` is None, returns messages from any chat.
+pub async fn get_media_page(
+    context: &Context,
+    chat_id: Option<ChatId>,
+    category: MediaCategory,
+    older_than: Option<MsgId>,
+    limit: usize,
+) -> Result<Vec<MsgId>> {
+    let (timestamp, msg_id) = match older_than {
+        Some(msg_id) => {
+            let timestamp: i64 = context
+                .sql
+                .query_get_value("SELECT timestamp FROM msgs WHERE id=?", (msg_id,))
+                .await?
+                .with_context(|| format!("Message {msg_id} does not exist"))?;
+            (timestamp, msg_id)
+        }
+        None => (i64::MAX, MsgId::new(u32::MAX)),
+    };
+    let viewtypes = category
+        .viewtypes()
+        .iter()
+        .map(|viewtype| (*viewtype as u32).to_string())
+        .collect::<Vec<_>>()
+        .join(",");
+    context
+        .sql
+        .query_map(
+            &format!(
+                "SELECT id
+                   FROM msgs
+                  WHERE (1=?1 OR chat_id=?2)
+                    AND chat_id!=?3
+                    AND type IN ({viewtypes})
+                    AND hidden=0
+                    AND (timestamp<?4 OR (timestamp=?4 AND id<?5))
+                  ORDER BY timestamp DESC, id DESC LIMIT ?6"
+            ),
+            (
+                chat_id.is_none(),
+                chat_id.unwrap_or_else(|| ChatId::new(0)),
+                DC_CHAT_ID_TRASH,
+                timestamp,
+                msg_id,
+                limit,
+            ),
+            |row| row.get::<_, MsgId>(0),
+            |ids| {
+                ids.collect::<std::result::Result<Vec<_>, _>>()
+                    .map_err(Into::into)
+            },
+        )
+        .await
+}
+
+/// Returns the number of messages per [`MediaCategory`],
+/// e.g. to show or hide the tabs of a media gallery.
+///
+/// If `chat_id` is None, counts messages from any chat.
+pub async fn get_media_counts(context: &Context, chat_id: Option<ChatId>) -> Result<MediaCounts> {
+    let counts = context
+        .sql
+        .query_map(
+            "SELECT type, COUNT(*)
+               FROM msgs
+              WHERE (1=? OR chat_id=?)
+                AND chat_id!=?
+                AND hidden=0
+              GROUP BY type",
+            (
+                chat_id.is_none(),
+                chat_id.unwrap_or_else(|| ChatId::new(0)),
+                DC_CHAT_ID_TRASH,
+            ),
+            |row| Ok((row.get::<_, Viewtype>(0)?, row.get::<_, usize>(1)?)),
+            |rows| {
+                rows.collect::<std::result::Result<Vec<_>, _>>()
+                    .map_err(Into::into)
+            },
+        )
+        .await?;
+    let mut media_counts = MediaCounts::default();
+    for (viewtype, count) in counts {
+        match viewtype {
+            Viewtype::Image | Viewtype::Gif => media_counts.images += count,
+            Viewtype::Video => media_counts.videos += count,
+            Viewtype::Audio | Viewtype::Voice => media_counts.audio += count,
+            Viewtype::File => media_counts.documents += count,
+            Viewtype::Webxdc => media_counts.webxdc += count,
+            _ => {}
+        }
+    }
+    Ok(media_counts)
+}
+
 /// Indicates the direction over which to iterate.
 #[derive(Debug, Clone, PartialEq, Eq)]
 #[repr(i32)]
@@ -3566,6 +4528,35 @@ pub async fn create_broadcast_list(context: &Context) -> Result<ChatId> {
     create_broadcast_list_ex(context, Sync, grpid, chat_name).await
 }
 
+/// Creates a new group chat named after the contact list
+/// and adds all members of the contact list to it.
+pub async fn create_group_chat_from_contact_list(
+    context: &Context,
+    protect: ProtectionStatus,
+    list_id: u32,
+) -> Result<ChatId> {
+    let list = contact::get_contact_list(context, list_id).await?;
+    let chat_id = create_group_chat(context, protect, &list.name).await?;
+    for contact_id in list.contact_ids {
+        add_contact_to_chat(context, chat_id, contact_id).await?;
+    }
+    Ok(chat_id)
+}
+
+/// Creates a new broadcast list named after the contact list
+/// and adds all members of the contact list to it.
+pub async fn create_broadcast_list_from_contact_list(
+    context: &Context,
+    list_id: u32,
+) -> Result<ChatId> {
+    let list = contact::get_contact_list(context, list_id).await?;
+    let chat_id = create_broadcast_list_ex(context, Sync, create_id(), list.name).await?;
+    for contact_id in list.contact_ids {
+        add_contact_to_chat(context, chat_id, contact_id).await?;
+    }
+    Ok(chat_id)
+}
+
 pub(crate) async fn create_broadcast_list_ex(
     context: &Context,
     sync: sync::Sync,
@@ -3897,7 +4888,226 @@ pub(crate) async fn set_muted_ex(
             .log_err(context)
             .ok();
     }
-    Ok(())
+    Ok(())
+}
+
+/// A user-defined label to organize the chatlist, e.g. "Work" or "Family".
+///
+/// A chat can have several labels.
+/// Labels and their assignment to chats are synchronised across own devices.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ChatLabel {
+    /// Database ID of the label.
+    pub id: u32,
+
+    /// ID of the label used for synchronisation between own devices.
+    pub(crate) uid: String,
+
+    /// Name of the label.
+    pub name: String,
+}
+
+/// Creates a new chat label with the given name.
+///
+/// Returns the ID of the new label.
+pub async fn create_chat_label(context: &Context, name: &str) -> Result<u32> {
+    let uid = create_id();
+    save_chat_label(context, Sync, &uid, name).await
+}
+
+/// Renames the chat label.
+pub async fn rename_chat_label(context: &Context, id: u32, name: &str) -> Result<()> {
+    let label = get_chat_label(context, id).await?;
+    save_chat_label(context, Sync, &label.uid, name).await?;
+    Ok(())
+}
+
+/// Deletes the chat label. The chats having the label are not affected otherwise.
+pub async fn delete_chat_label(context: &Context, id: u32) -> Result<()> {
+    let label = get_chat_label(context, id).await?;
+    delete_chat_label_ex(context, Sync, &label.uid).await
+}
+
+/// Returns the chat label with the given ID.
+pub async fn get_chat_label(context: &Context, id: u32) -> Result<ChatLabel> {
+    context
+        .sql
+        .query_row_optional(
+            "SELECT uid, name FROM chat_labels WHERE id=?",
+            (id,),
+            |row| {
+                Ok(ChatLabel {
+                    id,
+                    uid: row.get(0)?,
+                    name: row.get(1)?,
+                })
+            },
+        )
+        .await?
+        .with_context(|| format!("Chat label {id} does not exist"))
+}
+
+/// Returns all chat labels, sorted by name.
+pub async fn get_chat_labels(context: &Context) -> Result<Vec<ChatLabel>> {
+    context
+        .sql
+        .query_map(
+            "SELECT id, uid, name FROM chat_labels ORDER BY name COLLATE NOCASE, id",
+            (),
+            |row| {
+                Ok(ChatLabel {
+                    id: row.get(0)?,
+                    uid: row.get(1)?,
+                    name: row.get(2)?,
+                })
+            },
+            |rows| {
+                rows.collect::<std::result::Result<Vec<_>, _>>()
+                    .map_err(Into::into)
+            },
+        )
+        .await
+}
+
+/// Creates or renames the chat label identified by `uid`.
+async fn save_chat_label(
+    context: &Context,
+    sync: sync::Sync,
+    uid: &str,
+    name: &str,
+) -> Result<u32> {
+    let name = sanitize_single_line(name);
+    ensure!(!name.is_empty(), "Chat label name must not be empty");
+    let id = context
+        .sql
+        .transaction(|transaction| {
+            let id = transaction.query_row(
+                "INSERT INTO chat_labels (uid, name) VALUES (?, ?)
+                 ON CONFLICT (uid) DO UPDATE SET name=excluded.name
+                 RETURNING id",
+                (uid, &name),
+                |row| row.get(0),
+            )?;
+            Ok(id)
+        })
+        .await?;
+    chatlist_events::emit_chatlist_changed(context);
+
+    if sync.into() {
+        context
+            .add_sync_item(SyncData::ChatLabel {
+                uid: uid.to_string(),
+                name,
+            })
+            .await?;
+        context.scheduler.interrupt_smtp().await;
+    }
+    Ok(id)
+}
+
+async fn delete_chat_label_ex(context: &Context, sync: sync::Sync, uid: &str) -> Result<()> {
+    context
+        .sql
+        .transaction(|transaction| {
+            transaction.execute(
+                "DELETE FROM chats_labels
+                 WHERE label_id IN (SELECT id FROM chat_labels WHERE uid=?)",
+                (uid,),
+            )?;
+            transaction.execute("DELETE FROM chat_labels WHERE uid=?", (uid,))?;
+            Ok(())
+        })
+        .await?;
+    chatlist_events::emit_chatlist_changed(context);
+
+    if sync.into() {
+        context
+            .add_sync_item(SyncData::DeleteChatLabel {
+                uid: uid.to_string(),
+            })
+            .await?;
+        context.scheduler.interrupt_smtp().await;
+    }
+    Ok(())
+}
+
+impl ChatId {
+    /// Replaces the labels of the chat by the labels with the given IDs.
+    pub async fn set_labels(self, context: &Context, label_ids: &[u32]) -> Result<()> {
+        let mut uids = Vec::with_capacity(label_ids.len());
+        for id in label_ids {
+            uids.push(get_chat_label(context, *id).await?.uid);
+        }
+        self.set_labels_ex(context, Sync, &uids).await
+    }
+
+    /// Returns the IDs of the labels of the chat.
+    pub async fn get_labels(self, context: &Context) -> Result<Vec<u32>> {
+        context
+            .sql
+            .query_map(
+                "SELECT cl.label_id
+                   FROM chats_labels cl
+                   INNER JOIN chat_labels l ON l.id=cl.label_id
+                  WHERE cl.chat_id=?
+                  ORDER BY l.name COLLATE NOCASE, l.id",
+                (self,),
+                |row| row.get::<_, u32>(0),
+                |ids| {
+                    ids.collect::<std::result::Result<Vec<_>, _>>()
+                        .map_err(Into::into)
+                },
+            )
+            .await
+    }
+
+    /// Sets the labels of the chat by their `uids`, unknown labels are ignored.
+    async fn set_labels_ex(
+        self,
+        context: &Context,
+        sync: sync::Sync,
+        uids: &[String],
+    ) -> Result<()> {
+        ensure!(!self.is_special(), "Invalid chat ID");
+        context
+            .sql
+            .transaction(|transaction| {
+                transaction.execute("DELETE FROM chats_labels WHERE chat_id=?", (self,))?;
+                let mut stmt = transaction.prepare(
+                    "INSERT OR IGNORE INTO chats_labels (chat_id, label_id)
+                     SELECT ?, id FROM chat_labels WHERE uid=?",
+                )?;
+                for uid in uids {
+                    stmt.execute((self, uid))?;
+                }
+                Ok(())
+            })
+            .await?;
+        context.emit_event(EventType::ChatModified(self));
+        chatlist_events::emit_chatlist_item_changed(context, self);
+
+        if sync.into() {
+            let chat = Chat::load_from_db(context, self).await?;
+            chat.sync(context, SyncAction::SetLabels(uids.to_vec()))
+                .await
+                .log_err(context)
+                .ok();
+        }
+        Ok(())
+    }
+}
+
+impl Context {
+    /// Executes [`SyncData::ChatLabel`] item sent by other device.
+    pub(crate) async fn sync_chat_label(&self, uid: &str, name: &str) -> Result<()> {
+        save_chat_label(self, Nosync, uid, name).await?;
+        Ok(())
+    }
+
+    /// Executes [`SyncData::DeleteChatLabel`] item sent by other device.
+    pub(crate) async fn sync_delete_chat_label(&self, uid: &str) -> Result<()> {
+        delete_chat_label_ex(self, Nosync, uid).await
+    }
 }
 
 /// Removes contact from the chat.
@@ -4157,6 +5367,9 @@ pub async fn forward_msgs(context: &Context, msg_ids: &[MsgId], chat_id: ChatId)
         if msg.state == MessageState::OutDraft {
             bail!("cannot forward drafts.");
         }
+        if msg.is_view_once() {
+            bail!("cannot forward view-once messages.");
+        }
 
         let original_param = msg.param.clone();
 
@@ -4171,7 +5384,9 @@ pub async fn forward_msgs(context: &Context, msg_ids: &[MsgId], chat_id: ChatId)
 
         msg.param.remove(Param::GuaranteeE2ee);
         msg.param.remove(Param::ForcePlaintext);
+        msg.param.remove(Param::Clearsigned);
         msg.param.remove(Param::Cmd);
+        msg.thread_root = None;
         msg.param.remove(Param::OverrideSenderDisplayname);
         msg.param.remove(Param::WebxdcDocument);
         msg.param.remove(Param::WebxdcDocumentTimestamp);
@@ -4629,6 +5844,12 @@ pub(crate) enum SyncAction {
     Rename(String),
     /// Set chat contacts by their addresses.
     SetContacts(Vec<String>),
+    /// Mark the chat as noticed.
+    /// Contains the timestamp of the latest message in the chat on the sending device,
+    /// only messages not newer than it are marked as noticed.
+    MarkNoticed(i64),
+    /// Set chat labels by their uids.
+    SetLabels(Vec<String>),
 }
 
 impl Context {
@@ -4686,6 +5907,10 @@ pub(crate) async fn sync_alter_chat(&self, id: &SyncId, action: &SyncAction) ->
             }
             SyncAction::Rename(to) => rename_ex(self, Nosync, chat_id, to).await,
             SyncAction::SetContacts(addrs) => set_contacts_by_addrs(self, chat_id, addrs).await,
+            SyncAction::MarkNoticed(timestamp) => {
+                marknoticed_chat_until(self, chat_id, *timestamp).await
+            }
+            SyncAction::SetLabels(uids) => chat_id.set_labels_ex(self, Nosync, uids).await,
         }
     }
 
@@ -4703,7 +5928,7 @@ mod tests {
     use super::*;
     use crate::chatlist::get_archived_cnt;
     use crate::constants::{DC_GCL_ARCHIVED_ONLY, DC_GCL_NO_SPECIALS};
-    use crate::message::delete_msgs;
+    use crate::message::{delete_msgs, markseen_msgs};
     use crate::receive_imf::receive_imf;
     use crate::test_utils::{sync, TestContext, TestContextManager};
     use strum::IntoEnumIterator;
@@ -6980,6 +8205,58 @@ async fn test_can_send_group() -> Result<()> {
         Ok(())
     }
 
+    #[tokio::test(flavor = "multi_thread", worker_threads = 2)]
+    async fn test_encryption_policy() -> Result<()> {
+        let mut tcm = TestContextManager::new();
+        let alice = tcm.alice().await;
+        let bob = tcm.bob().await;
+
+        // Alice has no key of Claire.
+        let claire_id = Contact::create(&alice, "", "claire@example.org").await?;
+        let claire_chat_id = ChatId::create_for_contact(&alice, claire_id).await?;
+        let chat = Chat::load_from_db(&alice, claire_chat_id).await?;
+        assert_eq!(
+            chat.get_encryption_policy(),
+            EncryptionPolicy::Opportunistic
+        );
+
+        claire_chat_id
+            .set_encryption_policy(&alice, EncryptionPolicy::Required)
+            .await?;
+        let chat = Chat::load_from_db(&alice, claire_chat_id).await?;
+        assert_eq!(chat.get_encryption_policy(), EncryptionPolicy::Required);
+        let res = send_text_msg(&alice, claire_chat_id, "hi".to_string()).await;
+        assert!(res.is_err());
+        assert!(alice
+            .pop_sent_msg_opt(std::time::Duration::ZERO)
+            .await
+            .is_none());
+
+        // Alice and Bob know each other's keys,
+        // but Alice disables encryption in the chat.
+        let alice_bob_chat_id = tcm.send_recv_accept(&bob, &alice, "hi").await.chat_id;
+        let sent = alice.send_text(alice_bob_chat_id, "encrypted").await;
+        assert!(bob.recv_msg(&sent).await.get_showpadlock());
+
+        alice_bob_chat_id
+            .set_encryption_policy(&alice, EncryptionPolicy::Disabled)
+            .await?;
+        let sent = alice.send_text(alice_bob_chat_id, "cleartext").await;
+        let msg = bob.recv_msg(&sent).await;
+        assert_eq!(msg.get_text(), "cleartext");
+        assert!(!msg.get_showpadlock());
+
+        alice_bob_chat_id
+            .set_encryption_policy(&alice, EncryptionPolicy::Opportunistic)
+            .await?;
+        let chat = Chat::load_from_db(&alice, alice_bob_chat_id).await?;
+        assert_eq!(chat.param.get(Param::EncryptionPolicy), None);
+        let sent = alice.send_text(alice_bob_chat_id, "encrypted again").await;
+        assert!(bob.recv_msg(&sent).await.get_showpadlock());
+
+        Ok(())
+    }
+
     #[tokio::test(flavor = "multi_thread", worker_threads = 2)]
     async fn test_broadcast() -> Result<()> {
         // create two context, send two messages so both know the other
@@ -7176,6 +8453,145 @@ async fn test_chat_get_encryption_info() -> Result<()> {
         Ok(())
     }
 
+    #[tokio::test(flavor = "multi_thread", worker_threads = 2)]
+    async fn test_thread_msgs() -> Result<()> {
+        let mut tcm = TestContextManager::new();
+        let alice = tcm.alice().await;
+        let bob = tcm.bob().await;
+
+        let alice_chat_id = alice.create_chat(&bob).await.id;
+        let sent = alice.send_text(alice_chat_id, "Topic").await;
+        let alice_root = alice.get_last_msg().await;
+        let bob_root = bob.recv_msg(&sent).await;
+        let bob_chat_id = bob_root.chat_id;
+        bob_chat_id.accept(&bob).await?;
+
+        // A reply in the thread and an unrelated message.
+        let mut msg = Message::new(Viewtype::Text);
+        msg.set_text("Reply in thread".to_string());
+        msg.set_thread_root(Some(&bob_root))?;
+        let sent_reply = bob.send_msg(bob_chat_id, &mut msg).await;
+        let sent_other = bob.send_text(bob_chat_id, "Unrelated").await;
+
+        let reply = alice.recv_msg(&sent_reply).await;
+        assert_eq!(reply.get_thread_root(&alice).await?, Some(alice_root.id));
+        let other = alice.recv_msg(&sent_other).await;
+        assert_eq!(other.get_thread_root(&alice).await?, None);
+
+        // Replying to a reply stays in the same thread.
+        let mut msg = Message::new(Viewtype::Text);
+        msg.set_text("Another reply".to_string());
+        msg.set_thread_root(Some(&reply))?;
+        alice.send_msg(alice_chat_id, &mut msg).await;
+        let another = alice.get_last_msg().await;
+
+        assert_eq!(
+            get_thread_msgs(&alice, alice_root.id).await?,
+            vec![alice_root.id, reply.id, another.id]
+        );
+        assert_eq!(get_thread_fresh_msg_cnt(&alice, alice_root.id).await?, 1);
+        assert_eq!(
+            get_thread_msgs(&bob, bob_root.id).await?,
+            vec![bob_root.id, sent_reply.sender_msg_id]
+        );
+
+        Ok(())
+    }
+
+    #[tokio::test(flavor = "multi_thread", worker_threads = 2)]
+    async fn test_thread_msgs_from_references() -> Result<()> {
+        let t = TestContext::new_alice().await;
+        receive_imf(
+            &t,
+            b"From: bob@example.net\n\
+              To: alice@example.org\n\
+              Subject: Topic\n\
+              Message-ID: <root@example.net>\n\
+              Date: Sun, 22 Mar 2020 22:37:55 +0000\n\
+              \n\
+              Root\n",
+            false,
+        )
+        .await?;
+        let root = t.get_last_msg().await;
+        receive_imf(
+            &t,
+            b"From: bob@example.net\n\
+              To: alice@example.org\n\
+              Subject: Re: Topic\n\
+              Message-ID: <reply1@example.net>\n\
+              In-Reply-To: <root@example.net>\n\
+              References: <root@example.net>\n\
+              Date: Sun, 22 Mar 2020 22:38:55 +0000\n\
+              \n\
+              Reply\n",
+            false,
+        )
+        .await?;
+        let reply1 = t.get_last_msg().await;
+        receive_imf(
+            &t,
+            b"From: bob@example.net\n\
+              To: alice@example.org\n\
+              Subject: Re: Topic\n\
+              Message-ID: <reply2@example.net>\n\
+              In-Reply-To: <reply1@example.net>\n\
+              References: <root@example.net> <reply1@example.net>\n\
+              Date: Sun, 22 Mar 2020 22:39:55 +0000\n\
+              \n\
+              Reply to reply\n",
+            false,
+        )
+        .await?;
+        let reply2 = t.get_last_msg().await;
+
+        assert_eq!(root.get_thread_root(&t).await?, None);
+        assert_eq!(reply2.get_thread_root(&t).await?, Some(root.id));
+        assert_eq!(
+            get_thread_msgs(&t, root.id).await?,
+            vec![root.id, reply1.id, reply2.id]
+        );
+
+        Ok(())
+    }
+
+    #[tokio::test(flavor = "multi_thread", worker_threads = 2)]
+    async fn test_get_info_msgs() -> Result<()> {
+        let t = TestContext::new_alice().await;
+        let chat_id = create_group_chat(&t, ProtectionStatus::Unprotected, "foo").await?;
+        let created = t.get_last_msg_in(chat_id).await;
+        send_text_msg(&t, chat_id, "hi".to_string()).await?;
+        let bob_id = Contact::create(&t, "", "bob@example.net").await?;
+        add_contact_to_chat(&t, chat_id, bob_id).await?;
+        let member_added = t.get_last_msg_in(chat_id).await;
+        set_chat_name(&t, chat_id, "bar").await?;
+        let name_changed = t.get_last_msg_in(chat_id).await;
+        send_text_msg(&t, chat_id, "hello".to_string()).await?;
+
+        assert_eq!(
+            get_info_msgs(&t, chat_id, None).await?,
+            vec![created.id, member_added.id, name_changed.id]
+        );
+        assert_eq!(
+            get_info_msgs(&t, chat_id, Some(SystemMessage::Unknown)).await?,
+            vec![created.id]
+        );
+        assert_eq!(
+            get_info_msgs(&t, chat_id, Some(SystemMessage::MemberAddedToGroup)).await?,
+            vec![member_added.id]
+        );
+        assert_eq!(
+            get_info_msgs(&t, chat_id, Some(SystemMessage::GroupNameChanged)).await?,
+            vec![name_changed.id]
+        );
+        assert!(
+            get_info_msgs(&t, chat_id, Some(SystemMessage::EphemeralTimerChanged))
+                .await?
+                .is_empty()
+        );
+        Ok(())
+    }
+
     #[tokio::test(flavor = "multi_thread", worker_threads = 2)]
     async fn test_get_chat_media() -> Result<()> {
         let t = TestContext::new_alice().await;
@@ -7342,6 +8758,44 @@ async fn send_media(
             3
         );
 
+        // Gallery pages start with the newest message.
+        let third_image_msg_id = send_media(
+            &t,
+            chat_id2,
+            Viewtype::Image,
+            "e.jpg",
+            include_bytes!("../test-data/image/avatar64x64.png"),
+        )
+        .await?;
+        let page = get_media_page(&t, None, MediaCategory::Images, None, 1).await?;
+        assert_eq!(page, vec![third_image_msg_id]);
+        let page = get_media_page(&t, None, MediaCategory::Images, Some(page[0]), 10).await?;
+        assert_eq!(page.len(), 1);
+        assert!(
+            get_media_page(&t, None, MediaCategory::Images, Some(page[0]), 10)
+                .await?
+                .is_empty()
+        );
+        assert_eq!(
+            get_media_page(&t, Some(chat_id2), MediaCategory::Images, None, 10).await?,
+            vec![third_image_msg_id]
+        );
+        assert_eq!(
+            get_media_counts(&t, None).await?,
+            MediaCounts {
+                images: 2,
+                webxdc: 1,
+                ..Default::default()
+            }
+        );
+        assert_eq!(
+            get_media_counts(&t, Some(chat_id1)).await?,
+            MediaCounts {
+                images: 1,
+                ..Default::default()
+            }
+        );
+
         Ok(())
     }
 
@@ -7511,6 +8965,105 @@ async fn test_sync_visibility() -> Result<()> {
         Ok(())
     }
 
+    #[tokio::test(flavor = "multi_thread", worker_threads = 2)]
+    async fn test_send_msg_deferred() -> Result<()> {
+        let mut tcm = TestContextManager::new();
+        let alice = &tcm.alice().await;
+        let bob = &tcm.bob().await;
+        let chat_id = alice.create_chat(bob).await.id;
+
+        let dir = tempfile::tempdir()?;
+        let file = dir.path().join("hello.txt");
+        fs::write(&file, "Hello").await?;
+        let mut msg = Message::new(Viewtype::File);
+        msg.set_file(file.to_str().unwrap(), None);
+        let msg_id = send_msg_deferred(alice, chat_id, &mut msg).await?;
+        let msg = Message::load_from_db(alice, msg_id).await?;
+        assert_eq!(msg.state, MessageState::OutPending);
+
+        // Wait for the background task.
+        prepare_deferred_msgs(alice).await?;
+        let sent = alice.pop_sent_msg().await;
+        assert_eq!(sent.sender_msg_id, msg_id);
+        let msg = Message::load_from_db(alice, msg_id).await?;
+        assert!(msg
+            .get_file(alice)
+            .unwrap()
+            .starts_with(alice.get_blobdir()));
+        let received = bob.recv_msg(&sent).await;
+        assert_eq!(received.get_filename().unwrap(), "hello.txt");
+
+        // A missing attachment fails the message.
+        let mut msg = Message::new(Viewtype::File);
+        msg.set_file(dir.path().join("missing.txt").to_str().unwrap(), None);
+        let msg_id = send_msg_deferred(alice, chat_id, &mut msg).await?;
+        prepare_deferred_msgs(alice).await?;
+        let msg = Message::load_from_db(alice, msg_id).await?;
+        assert_eq!(msg.state, MessageState::OutFailed);
+        Ok(())
+    }
+
+    #[tokio::test(flavor = "multi_thread", worker_threads = 2)]
+    async fn test_send_msg_deferred_keeps_order() -> Result<()> {
+        let mut tcm = TestContextManager::new();
+        let alice = &tcm.alice().await;
+        let bob = &tcm.bob().await;
+        let chat_id = alice.create_chat(bob).await.id;
+
+        let dir = tempfile::tempdir()?;
+        let file = dir.path().join("hello.txt");
+        fs::write(&file, "Hello").await?;
+        let mut msg = Message::new(Viewtype::File);
+        msg.set_file(file.to_str().unwrap(), None);
+        let deferred_msg_id = send_msg_deferred(alice, chat_id, &mut msg).await?;
+        let text_msg_id = send_text_msg(alice, chat_id, "Text".to_string()).await?;
+
+        // `pop_sent_msg()` returns the most recently queued message first.
+        let sent = alice.pop_sent_msg().await;
+        assert_eq!(sent.sender_msg_id, text_msg_id);
+        let sent = alice.pop_sent_msg().await;
+        assert_eq!(sent.sender_msg_id, deferred_msg_id);
+        Ok(())
+    }
+
+    #[tokio::test(flavor = "multi_thread", worker_threads = 2)]
+    async fn test_sync_marknoticed() -> Result<()> {
+        let alice0 = &TestContext::new_alice().await;
+        let alice1 = &TestContext::new_alice().await;
+        for a in [alice0, alice1] {
+            a.set_config_bool(Config::SyncMsgs, true).await?;
+        }
+        let bob = TestContext::new_bob().await;
+        let a0b_chat_id = alice0.create_chat(&bob).await.id;
+        let a1b_chat_id = alice1.create_chat(&bob).await.id;
+
+        let sent = bob.send_text(bob.create_chat(alice0).await.id, "Hi").await;
+        alice0.recv_msg(&sent).await;
+        alice1.recv_msg(&sent).await;
+        assert_eq!(a1b_chat_id.get_fresh_msg_cnt(alice1).await?, 1);
+
+        marknoticed_chat(alice0, a0b_chat_id).await?;
+        assert_eq!(a0b_chat_id.get_fresh_msg_cnt(alice0).await?, 0);
+        sync(alice0, alice1).await;
+        assert_eq!(a1b_chat_id.get_fresh_msg_cnt(alice1).await?, 0);
+
+        // If the other device has newer messages, the chat stays fresh there.
+        let bob_chat_id = bob.get_chat(alice0).await.id;
+        let sent = bob.send_text(bob_chat_id, "Hi again").await;
+        alice0.recv_msg(&sent).await;
+        alice1.recv_msg(&sent).await;
+        SystemTime::shift(Duration::from_secs(60));
+        let sent = bob.send_text(bob_chat_id, "Are you there?").await;
+        alice1.recv_msg(&sent).await;
+        marknoticed_chat(alice0, a0b_chat_id).await?;
+        sync(alice0, alice1).await;
+        assert_eq!(a1b_chat_id.get_fresh_msg_cnt(alice1).await?, 1);
+        let msg = alice1.get_last_msg_in(a1b_chat_id).await;
+        assert_eq!(msg.text, "Are you there?");
+        assert_eq!(msg.state, MessageState::InFresh);
+        Ok(())
+    }
+
     #[tokio::test(flavor = "multi_thread", worker_threads = 2)]
     async fn test_sync_muted() -> Result<()> {
         let alice0 = &TestContext::new_alice().await;
@@ -7638,4 +9191,229 @@ async fn test_jpeg_with_png_ext() -> Result<()> {
 
         Ok(())
     }
+
+    #[tokio::test(flavor = "multi_thread", worker_threads = 2)]
+    async fn test_send_edit() -> Result<()> {
+        let mut tcm = TestContextManager::new();
+        let alice = &tcm.alice().await;
+        let bob = &tcm.bob().await;
+
+        let alice_chat = alice.create_chat(bob).await;
+        let sent = alice.send_text(alice_chat.id, "Helo").await;
+        let bob_msg = bob.recv_msg(&sent).await;
+        assert_eq!(bob_msg.text, "Helo");
+        assert!(!bob_msg.is_edited());
+
+        // Only own messages can be edited.
+        assert!(send_edit(bob, bob_msg.id, "Hi".to_string()).await.is_err());
+
+        send_edit(alice, sent.sender_msg_id, "Hello".to_string()).await?;
+        let alice_msg = Message::load_from_db(alice, sent.sender_msg_id).await?;
+        assert_eq!(alice_msg.text, "Hello");
+        assert!(alice_msg.is_edited());
+
+        bob.recv_msg_trash(&alice.pop_sent_msg().await).await;
+        let bob_msg = Message::load_from_db(bob, bob_msg.id).await?;
+        assert_eq!(bob_msg.text, "Hello");
+        assert!(bob_msg.is_edited());
+        assert!(bob_msg.get_render_hints(bob).await?.is_edited);
+        let history = bob_msg.id.get_edit_history(bob).await?;
+        assert_eq!(history.len(), 1);
+        assert_eq!(history[0].text, "Helo");
+
+        // Edits by other contacts are rejected.
+        let bob_id = alice.add_or_lookup_contact(bob).await.id;
+        assert!(
+            receive_edit(alice, &alice_msg.rfc724_mid, bob_id, "Forged", time(), true)
+                .await
+                .is_err()
+        );
+        let alice_msg = Message::load_from_db(alice, alice_msg.id).await?;
+        assert_eq!(alice_msg.text, "Hello");
+
+        // Deleting the message removes the history.
+        delete_msgs(bob, &[bob_msg.id]).await?;
+        assert!(bob_msg.id.get_edit_history(bob).await?.is_empty());
+        Ok(())
+    }
+
+    #[tokio::test(flavor = "multi_thread", worker_threads = 2)]
+    async fn test_edit_retracted_msg() -> Result<()> {
+        let mut tcm = TestContextManager::new();
+        let alice = &tcm.alice().await;
+        let bob = &tcm.bob().await;
+
+        let alice_chat = alice.create_chat(bob).await;
+        let sent = alice.send_text(alice_chat.id, "Oops").await;
+        let bob_msg = bob.recv_msg(&sent).await;
+        message::delete_for_all(alice, &[sent.sender_msg_id]).await?;
+        bob.recv_msg_trash(&alice.pop_sent_msg().await).await;
+
+        assert!(send_edit(alice, sent.sender_msg_id, "Hi".to_string())
+            .await
+            .is_err());
+
+        let bob_msg = Message::load_from_db(bob, bob_msg.id).await?;
+        assert!(bob_msg.is_retracted());
+        assert!(receive_edit(
+            bob,
+            &bob_msg.rfc724_mid,
+            bob_msg.from_id,
+            "Hi",
+            time(),
+            true
+        )
+        .await
+        .is_err());
+        let bob_msg = Message::load_from_db(bob, bob_msg.id).await?;
+        assert_eq!(bob_msg.text, "");
+        assert!(!bob_msg.is_edited());
+        Ok(())
+    }
+
+    #[tokio::test(flavor = "multi_thread", worker_threads = 2)]
+    async fn test_pin_message() -> Result<()> {
+        let mut tcm = TestContextManager::new();
+        let alice = &tcm.alice().await;
+        let bob = &tcm.bob().await;
+
+        let alice_chat_id = alice
+            .create_group_with_members(ProtectionStatus::Unprotected, "Group", &[bob])
+            .await;
+        let sent = alice.send_text(alice_chat_id, "Meeting at 10").await;
+        let bob_msg = bob.recv_msg(&sent).await;
+        bob_msg.chat_id.accept(bob).await?;
+
+        pin_message(alice, sent.sender_msg_id).await?;
+        assert_eq!(
+            get_pinned_messages(alice, alice_chat_id).await?,
+            vec![sent.sender_msg_id]
+        );
+        let info_msg = bob.recv_msg(&alice.pop_sent_msg().await).await;
+        assert!(info_msg.is_info());
+        assert_eq!(info_msg.get_info_type(), SystemMessage::MessagePinned);
+        assert_eq!(
+            get_pinned_messages(bob, bob_msg.chat_id).await?,
+            vec![bob_msg.id]
+        );
+
+        // Info messages cannot be pinned.
+        assert!(pin_message(bob, info_msg.id).await.is_err());
+
+        unpin_message(bob, bob_msg.id).await?;
+        assert!(get_pinned_messages(bob, bob_msg.chat_id).await?.is_empty());
+        let info_msg = alice.recv_msg(&bob.pop_sent_msg().await).await;
+        assert_eq!(info_msg.get_info_type(), SystemMessage::MessageUnpinned);
+        assert!(get_pinned_messages(alice, alice_chat_id).await?.is_empty());
+
+        Ok(())
+    }
+
+    #[tokio::test(flavor = "multi_thread", worker_threads = 2)]
+    async fn test_search_and_get_msgs_around() -> Result<()> {
+        let t = TestContext::new_alice().await;
+        let chat_id = t
+            .create_chat_with_contact("Bob", "bob@example.net")
+            .await
+            .id;
+        let mut msg_ids = Vec::new();
+        for text in ["apple", "banana", "pineapple", "cherry", "Apple pie"] {
+            msg_ids.push(t.send_text(chat_id, text).await.sender_msg_id);
+        }
+
+        let results = search(&t, chat_id, "apple").await?;
+        assert_eq!(results, vec![msg_ids[0], msg_ids[2], msg_ids[4]]);
+        assert!(search(&t, DC_CHAT_ID_TRASH, "apple").await.is_err());
+
+        assert_eq!(
+            get_msgs_around(&t, results[1], 1, 1).await?,
+            msg_ids[1..4].to_vec()
+        );
+        assert_eq!(get_msgs_around(&t, results[0], 2, 10).await?, msg_ids);
+        assert_eq!(
+            get_msgs_around(&t, results[2], 0, 0).await?,
+            vec![msg_ids[4]]
+        );
+
+        Ok(())
+    }
+
+    #[tokio::test(flavor = "multi_thread", worker_threads = 2)]
+    async fn test_get_first_unread() -> Result<()> {
+        let mut tcm = TestContextManager::new();
+        let alice = &tcm.alice().await;
+        let bob = &tcm.bob().await;
+
+        let alice_chat_id = alice.create_chat(bob).await.id;
+        let mut msg_ids = Vec::new();
+        for text in ["one", "two", "three", "four"] {
+            let sent = alice.send_text(alice_chat_id, text).await;
+            msg_ids.push(bob.recv_msg(&sent).await.id);
+        }
+        let bob_chat_id = bob.get_chat(alice).await.id;
+        assert_eq!(get_first_unread(bob, bob_chat_id).await?, Some(msg_ids[0]));
+
+        // Unread messages before the last read one are skipped.
+        markseen_msgs(bob, vec![msg_ids[1]]).await?;
+        assert_eq!(get_first_unread(bob, bob_chat_id).await?, Some(msg_ids[2]));
+        markseen_msgs(bob, vec![msg_ids[0]]).await?;
+        assert_eq!(get_first_unread(bob, bob_chat_id).await?, Some(msg_ids[2]));
+
+        // Noticed messages are still unread.
+        marknoticed_chat(bob, bob_chat_id).await?;
+        assert_eq!(get_first_unread(bob, bob_chat_id).await?, Some(msg_ids[2]));
+
+        markseen_msgs(bob, vec![msg_ids[3]]).await?;
+        assert_eq!(get_first_unread(bob, bob_chat_id).await?, None);
+
+        // Messages seen by another client before they are fetched are read.
+        let sent = alice.send_text(alice_chat_id, "five").await;
+        let five_id = bob.recv_msg(&sent).await.id;
+        assert_eq!(get_first_unread(bob, bob_chat_id).await?, Some(five_id));
+        let sent = alice.send_text(alice_chat_id, "six").await;
+        receive_imf(bob, sent.payload.as_bytes(), true).await?;
+        assert_eq!(get_first_unread(bob, bob_chat_id).await?, None);
+
+        Ok(())
+    }
+
+    #[tokio::test(flavor = "multi_thread", worker_threads = 2)]
+    async fn test_chat_labels() -> Result<()> {
+        let mut tcm = TestContextManager::new();
+        let alice0 = &tcm.alice().await;
+        let alice1 = &tcm.alice().await;
+        for a in [alice0, alice1] {
+            a.set_config_bool(Config::SyncMsgs, true).await?;
+        }
+        let bob = &tcm.bob().await;
+
+        let a0b_chat_id = alice0.create_chat(bob).await.id;
+        let a1b_chat_id = alice1.create_chat(bob).await.id;
+        let work_id = create_chat_label(alice0, "Work").await?;
+        assert!(create_chat_label(alice0, " ").await.is_err());
+        a0b_chat_id.set_labels(alice0, &[work_id]).await?;
+        assert_eq!(a0b_chat_id.get_labels(alice0).await?, vec![work_id]);
+        let chatlist = Chatlist::try_load_by_label(alice0, work_id).await?;
+        assert_eq!(chatlist.len(), 1);
+        assert_eq!(chatlist.get_chat_id(0)?, a0b_chat_id);
+
+        sync(alice0, alice1).await;
+        let labels = get_chat_labels(alice1).await?;
+        assert_eq!(labels.len(), 1);
+        assert_eq!(labels[0].name, "Work");
+        assert_eq!(a1b_chat_id.get_labels(alice1).await?, vec![labels[0].id]);
+
+        rename_chat_label(alice0, work_id, "Job").await?;
+        sync(alice0, alice1).await;
+        assert_eq!(get_chat_label(alice1, labels[0].id).await?.name, "Job");
+
+        delete_chat_label(alice0, work_id).await?;
+        assert!(a0b_chat_id.get_labels(alice0).await?.is_empty());
+        assert_eq!(Chatlist::try_load_by_label(alice0, work_id).await?.len(), 0);
+        sync(alice0, alice1).await;
+        assert!(get_chat_labels(alice1).await?.is_empty());
+        assert!(a1b_chat_id.get_labels(alice1).await?.is_empty());
+
+        Ok(())
+    }
 }
@@ -1,5 +1,8 @@
 //! # Chat list module.
 
+use std::fmt;
+use std::sync::atomic::Ordering;
+
 use anyhow::{ensure, Context as _, Result};
 use once_cell::sync::Lazy;
 
@@ -307,6 +310,160 @@ pub async fn try_load(
         Ok(Chatlist { ids })
     }
 
+    /// Loads the chats having the label `label_id`, see [`crate::chat::ChatLabel`].
+    ///
+    /// Archived chats are included, pinned chats are listed first.
+    pub async fn try_load_by_label(context: &Context, label_id: u32) -> Result<Self> {
+        let ids = context
+            .sql
+            .query_map(
+                "SELECT c.id, m.id
+                 FROM chats c
+                 LEFT JOIN msgs m
+                        ON c.id=m.chat_id
+                       AND m.id=(
+                               SELECT id
+                                 FROM msgs
+                                WHERE chat_id=c.id
+                                  AND (hidden=0 OR state=?1)
+                                  ORDER BY timestamp DESC, id DESC LIMIT 1)
+                 WHERE c.id>9
+                   AND c.blocked!=1
+                   AND c.id IN(SELECT chat_id FROM chats_labels WHERE label_id=?2)
+                 GROUP BY c.id
+                 ORDER BY c.archived=?3 DESC, IFNULL(m.timestamp,c.created_timestamp) DESC, m.id DESC;",
+                (MessageState::OutDraft, label_id, ChatVisibility::Pinned),
+                |row| {
+                    let chat_id: ChatId = row.get(0)?;
+                    let msg_id: Option<MsgId> = row.get(1)?;
+                    Ok((chat_id, msg_id))
+                },
+                |rows| {
+                    rows.collect::<std::result::Result<Vec<_>, _>>()
+                        .map_err(Into::into)
+                },
+            )
+            .await?;
+        Ok(Chatlist { ids })
+    }
+
+    /// Loads a page of at most `limit` chats of the normal or, with `DC_GCL_ARCHIVED_ONLY`,
+    /// the archived chatlist, sorted as by [`Chatlist::try_load`].
+    ///
+    /// `after` is the [`ChatlistPage::next`] cursor of the previous page, `None` for the first page.
+    /// The archive link and the "all done" hint are only added to the first page.
+    ///
+    /// Chats moving while paging may be skipped or repeated,
+    /// use [`Chatlist::has_changes_since`] to find out if the pages should be reloaded.
+    pub async fn try_load_page(
+        context: &Context,
+        listflags: usize,
+        after: Option<&str>,
+        limit: usize,
+    ) -> Result<ChatlistPage> {
+        ensure!(
+            listflags & !(DC_GCL_ARCHIVED_ONLY | DC_GCL_NO_SPECIALS | DC_GCL_ADD_ALLDONE_HINT) == 0,
+            "unsupported listflags {listflags} for paging"
+        );
+        ensure!(limit > 0, "page limit must be positive");
+        let flag_archived_only = 0 != listflags & DC_GCL_ARCHIVED_ONLY;
+        let flag_no_specials = 0 != listflags & DC_GCL_NO_SPECIALS;
+        let flag_add_alldone_hint = 0 != listflags & DC_GCL_ADD_ALLDONE_HINT;
+
+        // Take the token before loading so that changes during loading are not missed.
+        let change_token = context.chatlist_version.load(Ordering::Relaxed).to_string();
+        let cursor = match after {
+            Some(after) => ChatlistCursor::parse(after)?,
+            None => ChatlistCursor::FIRST,
+        };
+        let filter = if flag_archived_only {
+            "c.blocked!=1 AND c.archived=?3"
+        } else {
+            "(c.blocked=0 OR c.blocked=2) AND NOT c.archived=?3"
+        };
+        let rows = context
+            .sql
+            .query_map(
+                &format!(
+                    "SELECT id, msg_id, pinned, ts FROM (
+                     SELECT c.id AS id, m.id AS msg_id, c.archived=?2 AS pinned,
+                            IFNULL(m.timestamp,c.created_timestamp) AS ts
+                     FROM chats c
+                     LEFT JOIN msgs m
+                            ON c.id=m.chat_id
+                           AND m.id=(
+                                   SELECT id
+                                     FROM msgs
+                                    WHERE chat_id=c.id
+                                      AND (hidden=0 OR state=?1)
+                                      ORDER BY timestamp DESC, id DESC LIMIT 1)
+                     WHERE c.id>9 AND {filter}
+                     GROUP BY c.id)
+                     WHERE (pinned, ts, IFNULL(msg_id,0), id) < (?4, ?5, ?6, ?7)
+                     ORDER BY pinned DESC, ts DESC, IFNULL(msg_id,0) DESC, id DESC
+                     LIMIT ?8"
+                ),
+                (
+                    MessageState::OutDraft,
+                    ChatVisibility::Pinned,
+                    ChatVisibility::Archived,
+                    cursor.pinned,
+                    cursor.timestamp,
+                    cursor.msg_id,
+                    cursor.chat_id,
+                    limit,
+                ),
+                |row| {
+                    let chat_id: ChatId = row.get(0)?;
+                    let msg_id: Option<MsgId> = row.get(1)?;
+                    let cursor = ChatlistCursor {
+                        pinned: row.get(2)?,
+                        timestamp: row.get(3)?,
+                        msg_id: msg_id.map_or(0, |msg_id| msg_id.to_u32()),
+                        chat_id: chat_id.to_u32(),
+                    };
+                    Ok((chat_id, msg_id, cursor))
+                },
+                |rows| {
+                    rows.collect::<std::result::Result<Vec<_>, _>>()
+                        .map_err(Into::into)
+                },
+            )
+            .await?;
+
+        let next = match rows.last() {
+            Some((_, _, cursor)) if rows.len() == limit => Some(cursor.to_string()),
+            _ => None,
+        };
+        let mut ids: Vec<_> = rows
+            .into_iter()
+            .map(|(chat_id, msg_id, _)| (chat_id, msg_id))
+            .collect();
+        if after.is_none()
+            && !flag_archived_only
+            && !flag_no_specials
+            && get_archived_cnt(context).await? > 0
+        {
+            if ids.is_empty() && flag_add_alldone_hint {
+                ids.push((DC_CHAT_ID_ALLDONE_HINT, None));
+            }
+            ids.insert(0, (DC_CHAT_ID_ARCHIVED_LINK, None));
+        }
+        Ok(ChatlistPage {
+            chatlist: Chatlist { ids },
+            next,
+            change_token,
+        })
+    }
+
+    /// Returns true if the chatlist may have changed
+    /// since the [`ChatlistPage::change_token`] was returned.
+    ///
+    /// This is cheap and does not access the database.
+    pub fn has_changes_since(context: &Context, change_token: &str) -> bool {
+        context.chatlist_version.load(Ordering::Relaxed).to_string() != change_token
+    }
+
     /// Converts list of chat IDs to a chatlist.
     pub(crate) async fn from_chat_ids(context: &Context, chat_ids: &[ChatId]) -> Result<Self> {
         let mut ids = Vec::new();
@@ -438,6 +595,65 @@ pub fn iter(&self) -> impl Iterator<Item = &(ChatId, Option<MsgId>)> {
     }
 }
 
+/// A page of the chatlist returned by [`Chatlist::try_load_page`].
+#[derive(Debug)]
+pub struct ChatlistPage {
+    /// The chats of the page.
+    pub chatlist: Chatlist,
+
+    /// Cursor to pass to [`Chatlist::try_load_page`] to load the next page,
+    /// `None` if this is the last page.
+    pub next: Option<String>,
+
+    /// Token to pass to [`Chatlist::has_changes_since`].
+    pub change_token: String,
+}
+
+/// Position in the chatlist sort order, the last chat of a page.
+#[derive(Debug, PartialEq)]
+struct ChatlistCursor {
+    pinned: bool,
+    timestamp: i64,
+    msg_id: u32,
+    chat_id: u32,
+}
+
+impl ChatlistCursor {
+    /// Cursor sorting before all chats.
+    const FIRST: Self = Self {
+        pinned: true,
+        timestamp: i64::MAX,
+        msg_id: u32::MAX,
+        chat_id: u32::MAX,
+    };
+
+    fn parse(s: &str) -> Result<Self> {
+        let mut parts = s.split(':');
+        let mut next = || parts.next().context("chatlist cursor is too short");
+        let cursor = Self {
+            pinned: next()? == "1",
+            timestamp: next()?.parse()?,
+            msg_id: next()?.parse()?,
+            chat_id: next()?.parse()?,
+        };
+        ensure!(parts.next().is_none(), "chatlist cursor is too long");
+        Ok(cursor)
+    }
+}
+
+impl fmt::Display for ChatlistCursor {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(
+            f,
+            "{}:{}:{}:{}",
+            u8::from(self.pinned),
+            self.timestamp,
+            self.msg_id,
+            self.chat_id
+        )
+    }
+}
+
 /// Returns the number of archived chats
 pub async fn get_archived_cnt(context: &Context) -> Result<usize> {
     let count = context
@@ -562,6 +778,69 @@ async fn test_try_load() {
         assert_eq!(chats.len(), 1);
     }
 
+    #[tokio::test(flavor = "multi_thread", worker_threads = 2)]
+    async fn test_try_load_page() -> Result<()> {
+        let t = TestContext::new_bob().await;
+        for name in ["a", "b", "c", "d", "e"] {
+            let chat_id = create_group_chat(&t, ProtectionStatus::Unprotected, name).await?;
+            // Drafts have unique timestamps, so the chatlist order is well-defined.
+            let mut msg = Message::new(Viewtype::Text);
+            msg.set_text("draft".to_string());
+            chat_id.set_draft(&t, Some(&mut msg)).await?;
+        }
+        let all = Chatlist::try_load(&t, 0, None, None).await?;
+
+        let page = Chatlist::try_load_page(&t, 0, None, 2).await?;
+        assert_eq!(page.chatlist.len(), 2);
+        let mut chat_ids: Vec<_> = page.chatlist.iter().map(|(chat_id, _)| *chat_id).collect();
+        let mut next = page.next;
+        while let Some(after) = next {
+            let page = Chatlist::try_load_page(&t, 0, Some(&after), 2).await?;
+            chat_ids.extend(page.chatlist.iter().map(|(chat_id, _)| *chat_id));
+            next = page.next;
+        }
+        assert_eq!(
+            chat_ids,
+            all.iter().map(|(chat_id, _)| *chat_id).collect::<Vec<_>>()
+        );
+        assert!(!Chatlist::has_changes_since(&t, &page.change_token));
+
+        // Archiving changes the chatlist and adds the archive link to the first page only.
+        chat_ids[0]
+            .set_visibility(&t, ChatVisibility::Archived)
+            .await?;
+        assert!(Chatlist::has_changes_since(&t, &page.change_token));
+        let page = Chatlist::try_load_page(&t, 0, None, 10).await?;
+        assert_eq!(page.chatlist.len(), 5);
+        assert_eq!(page.chatlist.get_chat_id(0)?, DC_CHAT_ID_ARCHIVED_LINK);
+        assert!(page.next.is_none());
+        let page = Chatlist::try_load_page(&t, DC_GCL_ARCHIVED_ONLY, None, 10).await?;
+        assert_eq!(page.chatlist.len(), 1);
+        assert_eq!(page.chatlist.get_chat_id(0)?, chat_ids[0]);
+
+        assert!(Chatlist::try_load_page(&t, 0, Some("invalid"), 10)
+            .await
+            .is_err());
+        assert!(Chatlist::try_load_page(&t, DC_GCL_FOR_FORWARDING, None, 10)
+            .await
+            .is_err());
+        Ok(())
+    }
+
+    #[test]
+    fn test_chatlist_cursor() {
+        let cursor = ChatlistCursor {
+            pinned: true,
+            timestamp: 1700000000,
+            msg_id: 42,
+            chat_id: 12,
+        };
+        assert_eq!(cursor.to_string(), "1:1700000000:42:12");
+        assert_eq!(ChatlistCursor::parse(&cursor.to_string()).unwrap(), cursor);
+        assert!(ChatlistCursor::parse("1:2:3").is_err());
+        assert!(ChatlistCursor::parse("1:2:3:4:5").is_err());
+    }
+
     #[tokio::test(flavor = "multi_thread", worker_threads = 2)]
     async fn test_sort_self_talk_up_on_forward() {
         let t = TestContext::new().await;
@@ -1,5 +1,6 @@
 //! # Key-value configuration management.
 
+use std::collections::BTreeMap;
 use std::env;
 use std::path::Path;
 use std::str::FromStr;
@@ -13,12 +14,13 @@
 use tokio::fs;
 
 use crate::blob::BlobObject;
+use crate::bot_commands::parse_bot_commands;
 use crate::constants::{self, DC_VERSION_STR};
 use crate::context::Context;
 use crate::events::EventType;
 use crate::log::LogExt;
 use crate::mimefactory::RECOMMENDED_FILE_SIZE;
-use crate::provider::{get_provider_by_id, Provider};
+use crate::provider::{get_provider_by_id, overrides, Provider};
 use crate::sync::{self, Sync::*, SyncData};
 use crate::tools::get_abs_path;
 
@@ -103,6 +105,14 @@ pub enum Config {
     /// SOCKS5 proxy server password.
     Socks5Password,
 
+    /// Additional proxies as `socks5://`, `http://`, `https://` or Shadowsocks `ss://` URLs,
+    /// one per line.
+    ///
+    /// If [`Config::Socks5Enabled`] is set, the proxies are tried in order
+    /// after the proxy configured with [`Config::Socks5Host`] on connection failures.
+    /// Use [`Context::add_proxy_url`] and related functions to modify the list.
+    ProxyUrls,
+
     /// Own name to use in the `From:` field when sending messages.
     Displayname,
 
@@ -150,6 +160,10 @@ pub enum Config {
     #[strum(props(default = "0"))] // also change MediaQuality.default() on changes
     MediaQuality,
 
+    /// What to send in the IMAP `ID` command, see [`crate::constants::ImapId`].
+    #[strum(props(default = "0"))] // also change ImapId.default() on changes
+    ImapId,
+
     /// If set to "1", on the first time `start_io()` is called after configuring,
     /// the newest existing messages are fetched.
     /// Existing recipients are added to the contact database regardless of this setting.
@@ -184,6 +198,14 @@ pub enum Config {
     #[strum(props(default = "0"))]
     DeleteDeviceAfter,
 
+    /// Time in seconds after sending a message
+    /// during which it can be deleted for everyone,
+    /// see [`crate::message::delete_for_all`].
+    ///
+    /// Equals to one day by default, 0 means no limit.
+    #[strum(props(default = "86400"))]
+    DeleteForAllWindow,
+
     /// Move messages to the Trash folder instead of marking them "\Deleted". Overrides
     /// `ProviderOptions::delete_to_trash`.
     DeleteToTrash,
@@ -251,6 +273,10 @@ pub enum Config {
     /// ID of the configured provider from the provider database.
     ConfiguredProvider,
 
+    /// Where the server settings of the last successful configuration came from,
+    /// e.g. `provider_db`, `ispdb` or `manual`.
+    ConfiguredServerSource,
+
     /// True if account is configured.
     Configured,
 
@@ -279,6 +305,14 @@ pub enum Config {
     /// True if it is a bot account.
     Bot,
 
+    /// JSON list of commands advertised by the bot,
+    /// e.g. `[{"name":"help","description":"Shows help"}]`.
+    ///
+    /// Each command has a `name` without the leading `/`
+    /// and optional `params` and `description`.
+    /// The list is sent along with all messages if [`Config::Bot`] is set.
+    BotCommands,
+
     /// True when to skip initial start messages in groups.
     #[strum(props(default = "0"))]
     SkipStartMessages,
@@ -293,6 +327,10 @@ pub enum Config {
     /// Unset, when quota falls below minimal warning threshold again.
     QuotaExceeding,
 
+    /// Set if a warning about low disk space was shown,
+    /// unset when there is enough free disk space again.
+    LowDiskSpace,
+
     /// address to webrtc instance to use for videochats
     WebrtcInstance,
 
@@ -322,6 +360,41 @@ pub enum Config {
     #[strum(props(default = "1"))]
     SyncMsgs,
 
+    /// Store unsent messages, encrypted to self, in a folder on the server,
+    /// so they are still sent if the app is reinstalled before sending succeeded.
+    #[strum(props(default = "0"))]
+    PersistOutbox,
+
+    /// Send an abuse report to the provider when blocking a contact request,
+    /// see [`Config::SpamReportAddr`].
+    #[strum(props(default = "0"))]
+    ReportSpam,
+
+    /// Address accepting abuse reports. Overrides `ProviderOptions::abuse_report_addr`.
+    ///
+    /// If neither is set, the provider is not known to accept abuse reports
+    /// and no reports are sent.
+    SpamReportAddr,
+
+    /// Interval in seconds between automatic backups,
+    /// 0 disables automatic backups.
+    ///
+    /// Backups are exported to [`Config::AutoBackupDir`].
+    #[strum(props(default = "0"))]
+    AutoBackupInterval,
+
+    /// Directory automatic backups are exported to.
+    AutoBackupDir,
+
+    /// Number of automatic backups to keep in [`Config::AutoBackupDir`],
+    /// older backups of the account are deleted.
+    /// 0 keeps all backups.
+    #[strum(props(default = "3"))]
+    AutoBackupKeep,
+
+    /// Timestamp of the last automatic backup.
+    LastAutoBackup,
+
     /// Space-separated list of all the authserv-ids which we believe
     /// may be the one of our email server.
     ///
@@ -356,6 +429,19 @@ pub enum Config {
     #[strum(props(default = "0"))]
     VerifiedOneOnOneChats,
 
+    /// Protection against messages with a forged From address.
+    ///
+    /// Applies to messages claiming to be from a contact with an existing 1:1 chat
+    /// whose key is known, but which are not signed with this key.
+    /// Such messages are put into a separate contact request with a warning
+    /// instead of the 1:1 chat.
+    /// 0=Off.
+    /// 1=Quarantine the message if additionally our email server reported `dkim=fail`.
+    /// Messages without DKIM results or with results like `dkim=none` are not quarantined.
+    /// 2=Quarantine the message regardless of DKIM.
+    #[strum(props(default = "0"))]
+    SpoofingProtection,
+
     /// Row ID of the key in the `keypairs` table
     /// used for signatures, encryption to self and included in `Autocrypt` header.
     KeyId,
@@ -369,6 +455,41 @@ pub enum Config {
 
     /// Enable webxdc realtime features.
     WebxdcRealtimeEnabled,
+
+    /// Timeout in seconds for establishing TCP connections to IMAP, SMTP and proxy servers.
+    ///
+    /// 0 means the built-in default.
+    /// Providers may override the default using `config_defaults` in the provider database.
+    #[strum(props(default = "60"))]
+    ConnectTimeout,
+
+    /// Timeout in seconds for the TLS handshake, including STARTTLS upgrades.
+    ///
+    /// 0 means the built-in default.
+    #[strum(props(default = "60"))]
+    TlsHandshakeTimeout,
+
+    /// Read and write timeout in seconds for IMAP commands.
+    ///
+    /// Does not apply while waiting in IDLE.
+    /// 0 means the built-in default.
+    #[strum(props(default = "60"))]
+    ImapTimeout,
+
+    /// Read and write timeout in seconds for SMTP commands.
+    ///
+    /// 0 means the built-in default.
+    #[strum(props(default = "60"))]
+    SmtpTimeout,
+
+    /// Whether to look up keys of new contacts and of recipients without a known key
+    /// in the Web Key Directory of their domain.
+    #[strum(props(default = "0"))]
+    WkdLookup,
+
+    /// Provider database overrides in JSON or TOML format,
+    /// see [`crate::provider::overrides`].
+    ProviderOverrides,
 }
 
 impl Config {
@@ -396,6 +517,87 @@ pub(crate) fn is_synced(&self) -> bool {
         )
     }
 
+    /// Whether the config option holds a password.
+    pub(crate) fn is_secret(&self) -> bool {
+        matches!(
+            self,
+            Self::MailPw | Self::SendPw | Self::Socks5Password | Self::ProxyUrls
+        )
+    }
+
+    /// Whether the config option is a setting
+    /// which is exported by [`Context::export_config_json`].
+    ///
+    /// State maintained by the core, such as configured server parameters,
+    /// and file paths which are meaningless on other devices are not exported.
+    /// New keys are not exported unless they are added here.
+    pub(crate) fn is_exported(&self) -> bool {
+        matches!(
+            self,
+            Self::Addr
+                | Self::MailServer
+                | Self::MailUser
+                | Self::MailPw
+                | Self::MailPort
+                | Self::MailSecurity
+                | Self::ImapCertificateChecks
+                | Self::SendServer
+                | Self::SendUser
+                | Self::SendPw
+                | Self::SendPort
+                | Self::SendSecurity
+                | Self::SmtpCertificateChecks
+                | Self::ServerFlags
+                | Self::Socks5Enabled
+                | Self::Socks5Host
+                | Self::Socks5Port
+                | Self::Socks5User
+                | Self::Socks5Password
+                | Self::ProxyUrls
+                | Self::Displayname
+                | Self::Selfstatus
+                | Self::BccSelf
+                | Self::E2eeEnabled
+                | Self::MdnsEnabled
+                | Self::SentboxWatch
+                | Self::MvboxMove
+                | Self::OnlyFetchMvbox
+                | Self::ShowEmails
+                | Self::MediaQuality
+                | Self::ImapId
+                | Self::FetchExistingMsgs
+                | Self::KeyGenType
+                | Self::DeleteServerAfter
+                | Self::DeleteDeviceAfter
+                | Self::DeleteForAllWindow
+                | Self::DeleteToTrash
+                | Self::SaveMimeHeaders
+                | Self::IsMuted
+                | Self::Bot
+                | Self::BotCommands
+                | Self::SkipStartMessages
+                | Self::WebrtcInstance
+                | Self::ScanAllFoldersDebounceSecs
+                | Self::DisableIdle
+                | Self::DownloadLimit
+                | Self::SyncMsgs
+                | Self::PersistOutbox
+                | Self::ReportSpam
+                | Self::AutoBackupInterval
+                | Self::AutoBackupKeep
+                | Self::SignUnencrypted
+                | Self::GossipPeriod
+                | Self::VerifiedOneOnOneChats
+                | Self::SpoofingProtection
+                | Self::WebxdcRealtimeEnabled
+                | Self::ConnectTimeout
+                | Self::TlsHandshakeTimeout
+                | Self::ImapTimeout
+                | Self::SmtpTimeout
+                | Self::WkdLookup
+        )
+    }
+
     /// Whether the config option needs an IO scheduler restart to take effect.
     pub(crate) fn needs_io_restart(&self) -> bool {
         matches!(
@@ -405,6 +607,25 @@ pub(crate) fn needs_io_restart(&self) -> bool {
     }
 }
 
+/// Problem with a configuration value found by [`Context::check_config`].
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ConfigProblem {
+    /// The configuration key which has the problem.
+    pub key: Config,
+
+    /// Human-readable description of the problem.
+    pub msg: String,
+}
+
+impl ConfigProblem {
+    pub(crate) fn new(key: Config, msg: impl Into<String>) -> Self {
+        Self {
+            key,
+            msg: msg.into(),
+        }
+    }
+}
+
 impl Context {
     /// Returns true if configuration value is set for the given key.
     pub async fn config_exists(&self, key: Config) -> Result<bool> {
@@ -522,7 +743,7 @@ pub async fn get_config_delete_server_after(&self) -> Result<Option<i64>> {
     /// to the db in `param.save_to_database()`, together with all the other `configured_*` values.
     pub async fn get_configured_provider(&self) -> Result<Option<&'static Provider>> {
         if let Some(cfg) = self.get_config(Config::ConfiguredProvider).await? {
-            return Ok(get_provider_by_id(&cfg));
+            return Ok(get_provider_by_id(self, &cfg).await);
         }
         Ok(None)
     }
@@ -555,7 +776,7 @@ pub(crate) async fn sync_config(&self, key: &Config, value: &str) -> Result<()>
         }
     }
 
-    fn check_config(key: Config, value: Option<&str>) -> Result<()> {
+    fn check_config_value(key: Config, value: Option<&str>) -> Result<()> {
         match key {
             Config::Socks5Enabled
             | Config::BccSelf
@@ -578,6 +799,16 @@ fn check_config(key: Config, value: Option<&str>) -> Result<()> {
                     "Boolean value must be either 0 or 1"
                 );
             }
+            Config::BotCommands => {
+                if let Some(value) = value {
+                    parse_bot_commands(value)?;
+                }
+            }
+            Config::ProviderOverrides => {
+                if let Some(value) = value {
+                    overrides::parse_cached(value)?;
+                }
+            }
             _ => (),
         }
         Ok(())
@@ -587,7 +818,7 @@ fn check_config(key: Config, value: Option<&str>) -> Result<()> {
     /// This may restart the IO scheduler. If `None` is passed as a value the value is cleared and
     /// set to the default if there is one.
     pub async fn set_config(&self, key: Config, value: Option<&str>) -> Result<()> {
-        Self::check_config(key, value)?;
+        Self::check_config_value(key, value)?;
 
         let _pause = match key.needs_io_restart() {
             true => self.scheduler.pause(self.clone()).await?,
@@ -610,7 +841,7 @@ pub(crate) async fn set_config_ex(
         key: Config,
         mut value: Option<&str>,
     ) -> Result<()> {
-        Self::check_config(key, value)?;
+        Self::check_config_value(key, value)?;
         let sync = sync == Sync && key.is_synced() && self.is_configured().await?;
         let better_value;
 
@@ -671,7 +902,9 @@ pub(crate) async fn set_config_ex(
             }
         }
         if key.is_synced() {
-            self.emit_event(EventType::ConfigSynced { key });
+            self.emit_event(EventType::ConfigSynced {
+                key: key.to_string(),
+            });
         }
         if !sync {
             return Ok(());
@@ -708,9 +941,47 @@ pub async fn set_config_bool(&self, key: Config, value: bool) -> Result<()> {
     /// Keys must be prefixed by `ui.`
     /// and should be followed by the name of the system and maybe subsystem,
     /// eg. `ui.desktop.linux.foo`, `ui.desktop.macos.bar`, `ui.ios.foobar`.
+    ///
+    /// The core does not interpret ui-specific values.
+    /// Keys prefixed by `ui.sync.`, eg. `ui.sync.desktop.theme`,
+    /// are synced to other devices if multi-device sync is enabled,
+    /// other keys are device-specific.
+    /// [`EventType::ConfigSynced`] is emitted when a value changes,
+    /// for synced keys on all devices.
     pub async fn set_ui_config(&self, key: &str, value: Option<&str>) -> Result<()> {
+        self.set_ui_config_ex(Sync, key, value).await
+    }
+
+    pub(crate) async fn set_ui_config_ex(
+        &self,
+        sync: sync::Sync,
+        key: &str,
+        value: Option<&str>,
+    ) -> Result<()> {
         ensure!(key.starts_with("ui."), "set_ui_config(): prefix missing.");
-        self.sql.set_raw_config(key, value).await
+        if self.sql.get_raw_config(key).await?.as_deref() == value {
+            return Ok(());
+        }
+        self.sql.set_raw_config(key, value).await?;
+        self.emit_event(EventType::ConfigSynced {
+            key: key.to_string(),
+        });
+        if sync == Nosync || !key.starts_with("ui.sync.") || !self.is_configured().await? {
+            return Ok(());
+        }
+        if self
+            .add_sync_item(SyncData::UiConfig {
+                key: key.to_string(),
+                val: value.map(|v| v.to_string()),
+            })
+            .await
+            .log_err(self)
+            .is_err()
+        {
+            return Ok(());
+        }
+        self.scheduler.interrupt_smtp().await;
+        Ok(())
     }
 
     /// Gets an ui-specific value set by set_ui_config().
@@ -763,6 +1034,47 @@ pub(crate) async fn set_primary_self_addr(&self, primary_new: &str) -> Result<()
         Ok(())
     }
 
+    /// Exports the explicitly set configuration values as a JSON object.
+    ///
+    /// Internal state, such as the configured server parameters, is not exported.
+    /// Passwords are only exported if `include_secrets` is true.
+    /// The result can be imported with [`Context::import_config_json`],
+    /// e.g. to set up bots from a template or to move settings to another device.
+    pub async fn export_config_json(&self, include_secrets: bool) -> Result<String> {
+        let mut config = BTreeMap::new();
+        for key in Config::iter() {
+            if !key.is_exported() || (key.is_secret() && !include_secrets) {
+                continue;
+            }
+            if let Some(value) = self.sql.get_raw_config(key.as_ref()).await? {
+                config.insert(key.to_string(), value);
+            }
+        }
+        Ok(serde_json::to_string_pretty(&config)?)
+    }
+
+    /// Imports configuration values from a JSON object
+    /// as exported by [`Context::export_config_json`].
+    ///
+    /// `null` values unset the key.
+    /// Nothing is imported if the object contains unknown or internal keys or invalid values.
+    pub async fn import_config_json(&self, json: &str) -> Result<()> {
+        let config: BTreeMap<String, Option<String>> =
+            serde_json::from_str(json).context("Malformed configuration JSON")?;
+        let mut values = Vec::new();
+        for (key, value) in config {
+            let key = Config::from_str(&key).with_context(|| format!("Unknown key {key:?}"))?;
+            ensure!(key.is_exported(), "Cannot import internal key {key}");
+            Self::check_config_value(key, value.as_deref())
+                .with_context(|| format!("Invalid value for {key}"))?;
+            values.push((key, value));
+        }
+        for (key, value) in values {
+            self.set_config(key, value.as_deref()).await?;
+        }
+        Ok(())
+    }
+
     /// Returns all primary and secondary self addresses.
     pub(crate) async fn get_all_self_addrs(&self) -> Result<Vec<String>> {
         let primary_addrs = self.get_config(Config::ConfiguredAddr).await?.into_iter();
@@ -1117,12 +1429,7 @@ async fn test_event_config_synced() -> Result<()> {
         alice0
             .evtracker
             .get_matching(|e| {
-                matches!(
-                    e,
-                    EventType::ConfigSynced {
-                        key: Config::Displayname
-                    }
-                )
+                matches!(e, EventType::ConfigSynced { key } if key == Config::Displayname.as_ref())
             })
             .await;
         sync(&alice0, &alice1).await;
@@ -1133,12 +1440,7 @@ async fn test_event_config_synced() -> Result<()> {
         alice1
             .evtracker
             .get_matching(|e| {
-                matches!(
-                    e,
-                    EventType::ConfigSynced {
-                        key: Config::Displayname
-                    }
-                )
+                matches!(e, EventType::ConfigSynced { key } if key == Config::Displayname.as_ref())
             })
             .await;
 
@@ -1146,15 +1448,106 @@ async fn test_event_config_synced() -> Result<()> {
         alice0
             .evtracker
             .get_matching(|e| {
-                matches!(
-                    e,
-                    EventType::ConfigSynced {
-                        key: Config::Displayname
-                    }
-                )
+                matches!(e, EventType::ConfigSynced { key } if key == Config::Displayname.as_ref())
             })
             .await;
 
         Ok(())
     }
+
+    #[tokio::test(flavor = "multi_thread", worker_threads = 2)]
+    async fn test_sync_ui_config() -> Result<()> {
+        let alice0 = TestContext::new_alice().await;
+        let alice1 = TestContext::new_alice().await;
+        for a in [&alice0, &alice1] {
+            a.set_config_bool(Config::SyncMsgs, true).await?;
+        }
+
+        alice0
+            .set_ui_config("ui.sync.desktop.theme", Some("dark"))
+            .await?;
+        alice0
+            .evtracker
+            .get_matching(
+                |e| matches!(e, EventType::ConfigSynced { key } if key == "ui.sync.desktop.theme"),
+            )
+            .await;
+        sync(&alice0, &alice1).await;
+        assert_eq!(
+            alice1.get_ui_config("ui.sync.desktop.theme").await?,
+            Some("dark".to_string())
+        );
+        alice1
+            .evtracker
+            .get_matching(
+                |e| matches!(e, EventType::ConfigSynced { key } if key == "ui.sync.desktop.theme"),
+            )
+            .await;
+
+        // Setting the same value again is not synced.
+        alice0
+            .set_ui_config("ui.sync.desktop.theme", Some("dark"))
+            .await?;
+        assert_eq!(alice0.send_sync_msg().await?, None);
+
+        // Keys without the `ui.sync.` prefix are device-specific.
+        alice0
+            .set_ui_config("ui.desktop.window_width", Some("800"))
+            .await?;
+        assert_eq!(alice0.send_sync_msg().await?, None);
+        assert_eq!(alice1.get_ui_config("ui.desktop.window_width").await?, None);
+
+        // Removing the value is synced too.
+        alice0.set_ui_config("ui.sync.desktop.theme", None).await?;
+        sync(&alice0, &alice1).await;
+        assert_eq!(alice1.get_ui_config("ui.sync.desktop.theme").await?, None);
+        Ok(())
+    }
+
+    #[tokio::test(flavor = "multi_thread", worker_threads = 2)]
+    async fn test_export_import_config_json() -> Result<()> {
+        let mut tcm = TestContextManager::new();
+        let alice = &tcm.alice().await;
+        alice.set_config(Config::Displayname, Some("Alice")).await?;
+        alice.set_config_bool(Config::MdnsEnabled, false).await?;
+        alice.set_config(Config::MailPw, Some("secret")).await?;
+        alice
+            .sql
+            .set_raw_config(Config::AutoBackupDir.as_ref(), Some("/backups"))
+            .await?;
+
+        let json = alice.export_config_json(false).await?;
+        let config: BTreeMap<String, String> = serde_json::from_str(&json)?;
+        assert_eq!(config.get("displayname").unwrap(), "Alice");
+        assert_eq!(config.get("mdns_enabled").unwrap(), "0");
+        assert!(!config.contains_key("mail_pw"));
+        assert!(!config.contains_key("configured_addr"));
+        assert!(!config.contains_key("auto_backup_dir"));
+        let json_with_secrets = alice.export_config_json(true).await?;
+        let config: BTreeMap<String, String> = serde_json::from_str(&json_with_secrets)?;
+        assert_eq!(config.get("mail_pw").unwrap(), "secret");
+
+        let t = TestContext::new().await;
+        t.import_config_json(&json).await?;
+        assert_eq!(t.get_config(Config::Displayname).await?.unwrap(), "Alice");
+        assert!(!t.get_config_bool(Config::MdnsEnabled).await?);
+        assert!(t.get_config(Config::MailPw).await?.is_none());
+
+        // Internal keys are rejected and nothing is imported.
+        let t = TestContext::new().await;
+        assert!(t
+            .import_config_json(r#"{"displayname": "Bob", "configured_addr": "bob@example.net"}"#)
+            .await
+            .is_err());
+        assert!(t.get_config(Config::Displayname).await?.is_none());
+        assert!(t
+            .import_config_json(r#"{"mdns_enabled": "yes"}"#)
+            .await
+            .is_err());
+
+        // `null` unsets the key.
+        alice.import_config_json(r#"{"displayname": null}"#).await?;
+        assert!(alice.get_config(Config::Displayname).await?.is_none());
+        Ok(())
+    }
 }
@@ -13,17 +13,20 @@
 mod auto_outlook;
 mod server_params;
 
-use anyhow::{bail, ensure, Context as _, Result};
-use auto_mozilla::moz_autoconfigure;
-use auto_outlook::outlk_autodiscover;
+use std::net::IpAddr;
+
+use anyhow::{anyhow, bail, ensure, Context as _, Result};
+use auto_mozilla::{moz_autoconfigure, moz_ispdb_autoconfigure};
+use auto_outlook::{outlk_autodiscover, outlk_autodiscover_json};
 use deltachat_contact_tools::EmailAddress;
 use futures::FutureExt;
 use futures_lite::FutureExt as _;
 use percent_encoding::{utf8_percent_encode, NON_ALPHANUMERIC};
 use server_params::{expand_param_vector, ServerParams};
+use strum_macros::Display;
 use tokio::task;
 
-use crate::config::{self, Config};
+use crate::config::{self, Config, ConfigProblem};
 use crate::context::Context;
 use crate::imap::{session::Session as ImapSession, Imap};
 use crate::log::LogExt;
@@ -31,6 +34,7 @@
 use crate::message::{Message, Viewtype};
 use crate::oauth2::get_oauth2_addr;
 use crate::provider::{Protocol, Socket, UsernamePattern};
+use crate::qr::{self, Qr};
 use crate::smtp::Smtp;
 use crate::socks::Socks5Config;
 use crate::stock_str;
@@ -107,6 +111,166 @@ pub async fn configure(&self) -> Result<()> {
         res
     }
 
+    /// Configures an account from a `DCACCOUNT:` or `DCLOGIN:` QR code.
+    ///
+    /// For `DCACCOUNT:`, an account is created on the chatmail server
+    /// and the address and password returned by the server are stored
+    /// as [`Config::Addr`] and [`Config::MailPw`] before [`Context::configure`] runs.
+    /// For `DCLOGIN:`, the address, password and server parameters
+    /// contained in the QR code are stored.
+    /// Account creation is reported with the same `ConfigureProgress` events
+    /// as the configuration itself.
+    pub async fn configure_from_qr(&self, qr: &str) -> Result<()> {
+        ensure!(
+            !self.scheduler.is_running().await,
+            "cannot configure, already running"
+        );
+
+        let res = match qr::check_qr(self, qr).await {
+            Ok(Qr::Account { domain }) => {
+                info!(self, "Creating account on {domain}.");
+                progress!(self, 1);
+                qr::set_account_from_qr(self, qr).await
+            }
+            Ok(Qr::Login { .. }) => {
+                progress!(self, 1);
+                qr::set_config_from_qr(self, qr).await
+            }
+            Ok(_) => Err(anyhow!("QR code does not contain account data")),
+            Err(err) => Err(err),
+        };
+        if let Err(err) = res {
+            progress!(
+                self,
+                0,
+                Some(stock_str::configuration_failed(self, &format!("{err:#}")).await)
+            );
+            return Err(err);
+        }
+
+        self.configure().await
+    }
+
+    /// Checks the currently set parameters without configuring the account.
+    ///
+    /// The email address, password, ports and hostnames are validated first.
+    /// If they are valid, logging into the IMAP and SMTP servers is tried
+    /// using the same servers as [`Context::configure`] would.
+    /// Nothing is saved and the connections of the running account are not touched.
+    ///
+    /// Returns the list of problems found, which is empty if the parameters work.
+    pub async fn check_config(&self) -> Result<Vec<ConfigProblem>> {
+        let mut param = LoginParam::load_candidate_params_unchecked(self).await?;
+        let mut problems = Vec::new();
+
+        if param.addr.is_empty() {
+            problems.push(ConfigProblem::new(Config::Addr, "Missing email address."));
+        } else if EmailAddress::new(&param.addr).is_err() {
+            problems.push(ConfigProblem::new(
+                Config::Addr,
+                format!("Invalid email address {:?}.", param.addr),
+            ));
+        }
+        if param.imap.password.is_empty() {
+            problems.push(ConfigProblem::new(Config::MailPw, "Missing password."));
+        }
+        for key in [Config::MailPort, Config::SendPort, Config::Socks5Port] {
+            if let Some(port) = self.get_config(key).await? {
+                if !port.is_empty() && port.parse::<u16>().is_err() {
+                    problems.push(ConfigProblem::new(key, format!("Invalid port {port:?}.")));
+                }
+            }
+        }
+        for key in [Config::MailServer, Config::SendServer, Config::Socks5Host] {
+            if let Some(hostname) = self.get_config(key).await? {
+                if !hostname.is_empty() && !is_valid_hostname(&hostname) {
+                    problems.push(ConfigProblem::new(
+                        key,
+                        format!("Invalid hostname {hostname:?}."),
+                    ));
+                }
+            }
+        }
+        if let Some(socks5_config) = &param.socks5_config {
+            if socks5_config.host.is_empty() {
+                problems.push(ConfigProblem::new(
+                    Config::Socks5Host,
+                    "Missing SOCKS5 proxy hostname.",
+                ));
+            }
+        }
+        if !problems.is_empty() {
+            return Ok(problems);
+        }
+
+        let param_domain = EmailAddress::new(&param.addr)?.domain;
+        if param.smtp.password.is_empty() {
+            param.smtp.password.clone_from(&param.imap.password)
+        }
+        let (servers, _source) = get_server_params(self, &mut param, &param_domain, false).await;
+        let provider_strict_tls = param
+            .provider
+            .map_or(param.socks5_config.is_some(), |provider| {
+                provider.opt.strict_tls
+            });
+
+        let mut errors = Vec::new();
+        for server in servers.iter().filter(|s| s.protocol == Protocol::Imap) {
+            let imap_param = server_login_param(&param.imap, server);
+            match try_imap_one_param(
+                self,
+                &imap_param,
+                &param.socks5_config,
+                &param.addr,
+                provider_strict_tls,
+            )
+            .await
+            {
+                Ok(_) => {
+                    errors.clear();
+                    break;
+                }
+                Err(err) => errors.push(err),
+            }
+        }
+        if !errors.is_empty() {
+            problems.push(ConfigProblem::new(
+                Config::MailServer,
+                nicer_configuration_error(self, errors).await,
+            ));
+        }
+
+        let mut errors = Vec::new();
+        let mut smtp = Smtp::new();
+        for server in servers.iter().filter(|s| s.protocol == Protocol::Smtp) {
+            let smtp_param = server_login_param(&param.smtp, server);
+            match try_smtp_one_param(
+                self,
+                &smtp_param,
+                &param.socks5_config,
+                &param.addr,
+                provider_strict_tls,
+                &mut smtp,
+            )
+            .await
+            {
+                Ok(()) => {
+                    errors.clear();
+                    break;
+                }
+                Err(err) => errors.push(err),
+            }
+        }
+        if !errors.is_empty() {
+            problems.push(ConfigProblem::new(
+                Config::SendServer,
+                nicer_configuration_error(self, errors).await,
+            ));
+        }
+
+        Ok(problems)
+    }
+
     async fn inner_configure(&self) -> Result<()> {
         info!(self, "Configure ...");
 
@@ -120,6 +284,9 @@ async fn inner_configure(&self) -> Result<()> {
         let success = configure(self, &mut param).await;
         self.set_config_internal(Config::NotifyAboutWrongPw, None)
             .await?;
+        if success.is_ok() {
+            warn_unaligned_sending_domain(self, &param).await;
+        }
 
         on_configure_completed(self, param, old_addr).await?;
 
@@ -182,6 +349,45 @@ async fn on_configure_completed(
     Ok(())
 }
 
+/// Returns the provider of the SMTP server
+/// if it is not responsible for the domain of the configured address.
+///
+/// Such a server usually cannot DKIM-sign messages so that the signature aligns
+/// with the `From` domain, e.g. if an address on a custom domain is used
+/// with the SMTP server of a free provider.
+async fn unaligned_smtp_provider(
+    context: &Context,
+    param: &LoginParam,
+) -> Option<&'static provider::Provider> {
+    let smtp_provider = provider::get_provider_by_smtp_server(context, &param.smtp.server).await?;
+    match param.provider {
+        Some(provider) if provider.id == smtp_provider.id => None,
+        _ => Some(smtp_provider),
+    }
+}
+
+/// Adds a device message if messages will likely fail DKIM alignment checks.
+async fn warn_unaligned_sending_domain(context: &Context, param: &LoginParam) {
+    let Some(smtp_provider) = unaligned_smtp_provider(context, param).await else {
+        return;
+    };
+    warn!(
+        context,
+        "SMTP server {} of provider {} is not responsible for {}.",
+        param.smtp.server,
+        smtp_provider.id,
+        param.addr
+    );
+    let mut msg = Message::new(Viewtype::Text);
+    msg.text =
+        stock_str::sending_domain_not_aligned(context, &param.addr, &param.smtp.server).await;
+    chat::add_device_msg(context, None, Some(&mut msg))
+        .await
+        .context("Cannot add sending domain warning")
+        .log_err(context)
+        .ok();
+}
+
 async fn configure(ctx: &Context, param: &mut LoginParam) -> Result<()> {
     progress!(ctx, 1);
 
@@ -216,130 +422,11 @@ async fn configure(ctx: &Context, param: &mut LoginParam) -> Result<()> {
 
     let parsed = EmailAddress::new(&param.addr).context("Bad email-address")?;
     let param_domain = parsed.domain;
-    let param_addr_urlencoded = utf8_percent_encode(&param.addr, NON_ALPHANUMERIC).to_string();
 
     // Step 2: Autoconfig
     progress!(ctx, 200);
 
-    let param_autoconfig;
-    if param.imap.server.is_empty()
-        && param.imap.port == 0
-        && param.imap.security == Socket::Automatic
-        && param.imap.user.is_empty()
-        && param.smtp.server.is_empty()
-        && param.smtp.port == 0
-        && param.smtp.security == Socket::Automatic
-        && param.smtp.user.is_empty()
-    {
-        // no advanced parameters entered by the user: query provider-database or do Autoconfig
-
-        info!(
-            ctx,
-            "checking internal provider-info for offline autoconfig"
-        );
-
-        if let Some(provider) =
-            provider::get_provider_info(ctx, &param_domain, socks5_enabled).await
-        {
-            param.provider = Some(provider);
-            match provider.status {
-                provider::Status::Ok | provider::Status::Preparation => {
-                    if provider.server.is_empty() {
-                        info!(ctx, "offline autoconfig found, but no servers defined");
-                        param_autoconfig = None;
-                    } else {
-                        info!(ctx, "offline autoconfig found");
-                        let servers = provider
-                            .server
-                            .iter()
-                            .map(|s| ServerParams {
-                                protocol: s.protocol,
-                                socket: s.socket,
-                                hostname: s.hostname.to_string(),
-                                port: s.port,
-                                username: match s.username_pattern {
-                                    UsernamePattern::Email => param.addr.to_string(),
-                                    UsernamePattern::Emaillocalpart => {
-                                        if let Some(at) = param.addr.find('@') {
-                                            param.addr.split_at(at).0.to_string()
-                                        } else {
-                                            param.addr.to_string()
-                                        }
-                                    }
-                                },
-                                strict_tls: Some(provider.opt.strict_tls),
-                            })
-                            .collect();
-
-                        param_autoconfig = Some(servers)
-                    }
-                }
-                provider::Status::Broken => {
-                    info!(ctx, "offline autoconfig found, provider is broken");
-                    param_autoconfig = None;
-                }
-            }
-        } else {
-            // Try receiving autoconfig
-            info!(ctx, "no offline autoconfig found");
-            param_autoconfig = if socks5_enabled {
-                // Currently we can't do http requests through socks5, to not leak
-                // the ip, just don't do online autoconfig
-                info!(ctx, "socks5 enabled, skipping autoconfig");
-                None
-            } else {
-                get_autoconfig(ctx, param, &param_domain, &param_addr_urlencoded).await
-            }
-        }
-    } else {
-        param_autoconfig = None;
-    }
-
-    progress!(ctx, 500);
-
-    let mut servers = param_autoconfig.unwrap_or_default();
-    if !servers
-        .iter()
-        .any(|server| server.protocol == Protocol::Imap)
-    {
-        servers.push(ServerParams {
-            protocol: Protocol::Imap,
-            hostname: param.imap.server.clone(),
-            port: param.imap.port,
-            socket: param.imap.security,
-            username: param.imap.user.clone(),
-            strict_tls: None,
-        })
-    }
-    if !servers
-        .iter()
-        .any(|server| server.protocol == Protocol::Smtp)
-    {
-        servers.push(ServerParams {
-            protocol: Protocol::Smtp,
-            hostname: param.smtp.server.clone(),
-            port: param.smtp.port,
-            socket: param.smtp.security,
-            username: param.smtp.user.clone(),
-            strict_tls: None,
-        })
-    }
-
-    // respect certificate setting from function parameters
-    for server in &mut servers {
-        let certificate_checks = match server.protocol {
-            Protocol::Imap => param.imap.certificate_checks,
-            Protocol::Smtp => param.smtp.certificate_checks,
-        };
-        server.strict_tls = match certificate_checks {
-            CertificateChecks::AcceptInvalidCertificates
-            | CertificateChecks::AcceptInvalidCertificates2 => Some(false),
-            CertificateChecks::Strict => Some(true),
-            CertificateChecks::Automatic => server.strict_tls,
-        };
-    }
-
-    let servers = expand_param_vector(servers, &param.addr, &param_domain);
+    let (servers, server_source) = get_server_params(ctx, param, &param_domain, true).await;
 
     progress!(ctx, 550);
 
@@ -493,6 +580,11 @@ async fn configure(ctx: &Context, param: &mut LoginParam) -> Result<()> {
     param.save_as_configured_params(ctx).await?;
     ctx.set_config_internal(Config::ConfiguredTimestamp, Some(&time().to_string()))
         .await?;
+    ctx.set_config_internal(
+        Config::ConfiguredServerSource,
+        Some(&server_source.to_string()),
+    )
+    .await?;
 
     progress!(ctx, 920);
 
@@ -511,16 +603,229 @@ async fn configure(ctx: &Context, param: &mut LoginParam) -> Result<()> {
     Ok(())
 }
 
+/// Source of the server parameters tried during configuration.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq, Display)]
+#[strum(serialize_all = "snake_case")]
+enum ServerParamsSource {
+    /// Parameters entered by the user, possibly completed by guessing.
+    #[default]
+    Manual,
+
+    /// Built-in provider database.
+    ProviderDb,
+
+    /// Thunderbird autoconfiguration file published by the domain.
+    Autoconfig,
+
+    /// Outlook Autodiscover.
+    Autodiscover,
+
+    /// Mozilla ISPDB.
+    Ispdb,
+
+    /// Locally cached response of Mozilla ISPDB.
+    IspdbCache,
+}
+
+/// Returns the list of servers to try for the given login parameters.
+///
+/// If no advanced parameters are entered,
+/// the provider database or online autoconfiguration is used.
+/// Sets the provider of `param` if it is found in the provider database.
+/// `ConfigureProgress` events are only emitted if `report_progress` is set.
+///
+/// Returns the servers together with the source they were found in.
+async fn get_server_params(
+    ctx: &Context,
+    param: &mut LoginParam,
+    param_domain: &str,
+    report_progress: bool,
+) -> (Vec<ServerParams>, ServerParamsSource) {
+    let socks5_enabled = param.socks5_config.is_some();
+    let param_addr_urlencoded = utf8_percent_encode(&param.addr, NON_ALPHANUMERIC).to_string();
+
+    let param_autoconfig;
+    if param.imap.server.is_empty()
+        && param.imap.port == 0
+        && param.imap.security == Socket::Automatic
+        && param.imap.user.is_empty()
+        && param.smtp.server.is_empty()
+        && param.smtp.port == 0
+        && param.smtp.security == Socket::Automatic
+        && param.smtp.user.is_empty()
+    {
+        // no advanced parameters entered by the user: query provider-database or do Autoconfig
+
+        info!(
+            ctx,
+            "checking internal provider-info for offline autoconfig"
+        );
+
+        if let Some(provider) = provider::get_provider_info(ctx, param_domain, socks5_enabled).await
+        {
+            param.provider = Some(provider);
+            match provider.status {
+                provider::Status::Ok | provider::Status::Preparation => {
+                    if provider.server.is_empty() {
+                        info!(ctx, "offline autoconfig found, but no servers defined");
+                        param_autoconfig = None;
+                    } else {
+                        info!(ctx, "offline autoconfig found");
+                        let servers = provider
+                            .server
+                            .iter()
+                            .map(|s| ServerParams {
+                                protocol: s.protocol,
+                                socket: s.socket,
+                                hostname: s.hostname.to_string(),
+                                port: s.port,
+                                username: match s.username_pattern {
+                                    UsernamePattern::Email => param.addr.to_string(),
+                                    UsernamePattern::Emaillocalpart => {
+                                        if let Some(at) = param.addr.find('@') {
+                                            param.addr.split_at(at).0.to_string()
+                                        } else {
+                                            param.addr.to_string()
+                                        }
+                                    }
+                                },
+                                strict_tls: Some(provider.opt.strict_tls),
+                            })
+                            .collect();
+
+                        param_autoconfig = Some((servers, ServerParamsSource::ProviderDb))
+                    }
+                }
+                provider::Status::Broken => {
+                    info!(ctx, "offline autoconfig found, provider is broken");
+                    param_autoconfig = None;
+                }
+            }
+        } else {
+            // Try receiving autoconfig
+            info!(ctx, "no offline autoconfig found");
+            param_autoconfig = if socks5_enabled {
+                // Currently we can't do http requests through socks5, to not leak
+                // the ip, just don't do online autoconfig
+                info!(ctx, "socks5 enabled, skipping autoconfig");
+                None
+            } else {
+                get_autoconfig(
+                    ctx,
+                    param,
+                    param_domain,
+                    &param_addr_urlencoded,
+                    report_progress,
+                )
+                .await
+            }
+        }
+    } else {
+        param_autoconfig = None;
+    }
+
+    if report_progress {
+        progress!(ctx, 500);
+    }
+
+    let (mut servers, source) = param_autoconfig.unwrap_or_default();
+    if !servers
+        .iter()
+        .any(|server| server.protocol == Protocol::Imap)
+    {
+        servers.push(ServerParams {
+            protocol: Protocol::Imap,
+            hostname: param.imap.server.clone(),
+            port: param.imap.port,
+            socket: param.imap.security,
+            username: param.imap.user.clone(),
+            strict_tls: None,
+        })
+    }
+    if !servers
+        .iter()
+        .any(|server| server.protocol == Protocol::Smtp)
+    {
+        servers.push(ServerParams {
+            protocol: Protocol::Smtp,
+            hostname: param.smtp.server.clone(),
+            port: param.smtp.port,
+            socket: param.smtp.security,
+            username: param.smtp.user.clone(),
+            strict_tls: None,
+        })
+    }
+
+    // respect certificate setting from function parameters
+    for server in &mut servers {
+        let certificate_checks = match server.protocol {
+            Protocol::Imap => param.imap.certificate_checks,
+            Protocol::Smtp => param.smtp.certificate_checks,
+        };
+        server.strict_tls = match certificate_checks {
+            CertificateChecks::AcceptInvalidCertificates
+            | CertificateChecks::AcceptInvalidCertificates2 => Some(false),
+            CertificateChecks::Strict => Some(true),
+            CertificateChecks::Automatic => server.strict_tls,
+        };
+    }
+
+    (
+        expand_param_vector(servers, &param.addr, param_domain),
+        source,
+    )
+}
+
+/// Returns login parameters for one of the servers to try.
+fn server_login_param(param: &ServerLoginParam, server: &ServerParams) -> ServerLoginParam {
+    ServerLoginParam {
+        user: server.username.clone(),
+        server: server.hostname.clone(),
+        port: server.port,
+        security: server.socket,
+        certificate_checks: match server.strict_tls {
+            Some(true) => CertificateChecks::Strict,
+            Some(false) => CertificateChecks::AcceptInvalidCertificates,
+            None => CertificateChecks::Automatic,
+        },
+        ..param.clone()
+    }
+}
+
+/// Returns true if `hostname` is an IP address or a syntactically valid domain name.
+fn is_valid_hostname(hostname: &str) -> bool {
+    if hostname.parse::<IpAddr>().is_ok() {
+        return true;
+    }
+    let hostname = hostname.strip_suffix('.').unwrap_or(hostname);
+    !hostname.is_empty()
+        && hostname.split('.').all(|label| {
+            !label.is_empty()
+                && label.len() <= 63
+                && !label.starts_with('-')
+                && !label.ends_with('-')
+                && label
+                    .chars()
+                    .all(|c| c.is_alphanumeric() || c == '-' || c == '_')
+        })
+}
+
 /// Retrieve available autoconfigurations.
 ///
 /// A. Search configurations from the domain used in the email-address
-/// B. If we have no configuration yet, search configuration in Thunderbird's central database
+/// B. Ask Office 365 Autodiscover V2 for the Autodiscover endpoint of the domain
+///    if the MX records point to Office 365
+/// C. If we have no configuration yet, search configuration in Thunderbird's central database,
+///    which is cached locally
+///
+/// `ConfigureProgress` events are only emitted if `report_progress` is set.
 async fn get_autoconfig(
     ctx: &Context,
     param: &LoginParam,
     param_domain: &str,
     param_addr_urlencoded: &str,
-) -> Option<Vec<ServerParams>> {
+    report_progress: bool,
+) -> Option<(Vec<ServerParams>, ServerParamsSource)> {
     if let Ok(res) = moz_autoconfigure(
         ctx,
         &format!(
@@ -530,9 +835,11 @@ async fn get_autoconfig(
     )
     .await
     {
-        return Some(res);
+        return Some((res, ServerParamsSource::Autoconfig));
+    }
+    if report_progress {
+        progress!(ctx, 300);
     }
-    progress!(ctx, 300);
 
     if let Ok(res) = moz_autoconfigure(
         ctx,
@@ -545,20 +852,31 @@ async fn get_autoconfig(
     )
     .await
     {
-        return Some(res);
+        return Some((res, ServerParamsSource::Autoconfig));
+    }
+    if report_progress {
+        progress!(ctx, 310);
     }
-    progress!(ctx, 310);
 
     // Outlook uses always SSL but different domains (this comment describes the next two steps)
+    // OAuth2 tokens are not passwords and are never sent to Autodiscover.
+    let password = match param.imap.oauth2 {
+        true => "",
+        false => param.imap.password.as_str(),
+    };
     if let Ok(res) = outlk_autodiscover(
         ctx,
         format!("https://{}/autodiscover/autodiscover.xml", &param_domain),
+        &param.addr,
+        password,
     )
     .await
     {
-        return Some(res);
+        return Some((res, ServerParamsSource::Autodiscover));
+    }
+    if report_progress {
+        progress!(ctx, 320);
     }
-    progress!(ctx, 320);
 
     if let Ok(res) = outlk_autodiscover(
         ctx,
@@ -566,22 +884,48 @@ async fn get_autoconfig(
             "https://autodiscover.{}/autodiscover/autodiscover.xml",
             &param_domain
         ),
+        &param.addr,
+        password,
     )
     .await
     {
-        return Some(res);
+        return Some((res, ServerParamsSource::Autodiscover));
+    }
+    if report_progress {
+        progress!(ctx, 330);
     }
-    progress!(ctx, 330);
 
-    // always SSL for Thunderbird's database
-    if let Ok(res) = moz_autoconfigure(
-        ctx,
-        &format!("https://autoconfig.thunderbird.net/v1.1/{}", &param_domain),
-        param,
-    )
-    .await
-    {
-        return Some(res);
+    // Autodiscover V2 of Office 365 knows the POX endpoint for custom domains hosted there.
+    // Only ask Microsoft for domains hosted there to not leak other addresses.
+    let is_outlook = param
+        .provider
+        .is_some_and(|provider| provider.id == "outlook.com")
+        || provider::is_outlook_by_mx(ctx, param_domain).await;
+    if is_outlook {
+        if let Ok(res) = outlk_autodiscover_json(
+            ctx,
+            &format!(
+                "https://autodiscover-s.outlook.com/autodiscover/autodiscover.json/v1.0/{}?Protocol=AutodiscoverV1",
+                &param_addr_urlencoded
+            ),
+            &param.addr,
+            password,
+        )
+        .await
+        {
+            return Some((res, ServerParamsSource::Autodiscover));
+        }
+    }
+    if report_progress {
+        progress!(ctx, 340);
+    }
+
+    if let Ok((res, cached)) = moz_ispdb_autoconfigure(ctx, param_domain, param).await {
+        let source = match cached {
+            true => ServerParamsSource::IspdbCache,
+            false => ServerParamsSource::Ispdb,
+        };
+        return Some((res, source));
     }
 
     None
@@ -746,7 +1090,9 @@ pub enum Error {
 mod tests {
     #![allow(clippy::indexing_slicing)]
 
+    use super::*;
     use crate::config::Config;
+    use crate::events::EventType;
     use crate::test_utils::TestContext;
 
     #[tokio::test(flavor = "multi_thread", worker_threads = 2)]
@@ -758,4 +1104,78 @@ async fn test_no_panic_on_bad_credentials() {
         t.set_config(Config::MailPw, Some("123456")).await.unwrap();
         assert!(t.configure().await.is_err());
     }
+
+    #[tokio::test(flavor = "multi_thread", worker_threads = 2)]
+    async fn test_unaligned_smtp_provider() {
+        let t = TestContext::new().await;
+        let mut param = LoginParam {
+            addr: "alice@example.org".to_string(),
+            ..Default::default()
+        };
+        param.smtp.server = "smtp.example.org".to_string();
+        assert!(unaligned_smtp_provider(&t, &param).await.is_none());
+
+        // Custom domain sent via a free provider.
+        param.smtp.server = "smtp.gmail.com".to_string();
+        assert_eq!(
+            unaligned_smtp_provider(&t, &param).await.unwrap().id,
+            "gmail"
+        );
+
+        // Provider detected for the domain, e.g. via MX records.
+        param.provider = provider::get_provider_by_id(&t, "gmail").await;
+        assert!(unaligned_smtp_provider(&t, &param).await.is_none());
+    }
+
+    #[tokio::test(flavor = "multi_thread", worker_threads = 2)]
+    async fn test_configure_from_qr_rejects_other_qr() {
+        let t = TestContext::new().await;
+        assert!(t
+            .configure_from_qr("mailto:someone@example.org")
+            .await
+            .is_err());
+        t.evtracker
+            .get_matching(|e| matches!(e, EventType::ConfigureProgress { progress: 0, .. }))
+            .await;
+        assert!(!t.is_configured().await.unwrap());
+        assert!(t.get_config(Config::MailPw).await.unwrap().is_none());
+    }
+
+    #[tokio::test(flavor = "multi_thread", worker_threads = 2)]
+    async fn test_check_config_invalid_params() -> Result<()> {
+        let t = TestContext::new().await;
+        let problems = t.check_config().await?;
+        let keys: Vec<Config> = problems.iter().map(|problem| problem.key).collect();
+        assert_eq!(keys, vec![Config::Addr, Config::MailPw]);
+
+        t.set_config(Config::Addr, Some("alice@example.org"))
+            .await?;
+        t.set_config(Config::MailPw, Some("123456")).await?;
+        t.set_config(Config::MailPort, Some("100000")).await?;
+        t.set_config(Config::SendServer, Some("smtp example.org"))
+            .await?;
+        let problems = t.check_config().await?;
+        let keys: Vec<Config> = problems.iter().map(|problem| problem.key).collect();
+        assert_eq!(keys, vec![Config::MailPort, Config::SendServer]);
+
+        // Nothing is saved.
+        assert!(!t.is_configured().await?);
+        assert!(t.get_config(Config::ConfiguredAddr).await?.is_none());
+        Ok(())
+    }
+
+    #[test]
+    fn test_is_valid_hostname() {
+        assert!(is_valid_hostname("imap.example.org"));
+        assert!(is_valid_hostname("imap.example.org."));
+        assert!(is_valid_hostname("localhost"));
+        assert!(is_valid_hostname("127.0.0.1"));
+        assert!(is_valid_hostname("::1"));
+        assert!(is_valid_hostname("bücher.example"));
+        assert!(!is_valid_hostname("imap example.org"));
+        assert!(!is_valid_hostname("imap..example.org"));
+        assert!(!is_valid_hostname("-imap.example.org"));
+        assert!(!is_valid_hostname("imap.example.org:993"));
+        assert!(!is_valid_hostname(""));
+    }
 }
@@ -2,9 +2,12 @@
 //!
 //! RFC draft: <https://www.ietf.org/archive/id/draft-bucksch-autoconfig-00.html>
 //! Archived original documentation: <https://web.archive.org/web/20210624004729/https://developer.mozilla.org/en-US/docs/Mozilla/Thunderbird/Autoconfiguration>
+use std::collections::HashMap;
 use std::io::BufRead;
 use std::str::FromStr;
+use std::sync::Mutex;
 
+use once_cell::sync::Lazy;
 use quick_xml::events::{BytesStart, Event};
 
 use super::{Error, ServerParams};
@@ -12,6 +15,15 @@
 use crate::login_param::LoginParam;
 use crate::net::read_url;
 use crate::provider::{Protocol, Socket};
+use crate::tools::time;
+
+/// Time for which configurations fetched from Mozilla ISPDB are cached, 30 days.
+const ISPDB_CACHE_TTL: i64 = 30 * 24 * 60 * 60;
+
+/// Configurations fetched from Mozilla ISPDB, shared by all accounts.
+///
+/// Maps the lowercased domain to the XML and the timestamp it was fetched at.
+static ISPDB_CACHE: Lazy<Mutex<HashMap<String, (String, i64)>>> = Lazy::new(Default::default);
 
 #[derive(Debug)]
 struct Server {
@@ -272,11 +284,55 @@ pub(crate) async fn moz_autoconfigure(
     res
 }
 
+/// Looks up the configuration for `domain` in Mozilla ISPDB.
+///
+/// Fetched configurations are cached for [`ISPDB_CACHE_TTL`].
+/// Returns the server parameters and whether they were loaded from the cache.
+pub(crate) async fn moz_ispdb_autoconfigure(
+    context: &Context,
+    domain: &str,
+    param_in: &LoginParam,
+) -> Result<(Vec<ServerParams>, bool), Error> {
+    let domain = domain.to_lowercase();
+    let cached = ISPDB_CACHE
+        .lock()
+        .unwrap()
+        .get(&domain)
+        .filter(|(_, timestamp)| *timestamp > time() - ISPDB_CACHE_TTL)
+        .map(|(xml_raw, _)| xml_raw.clone());
+    if let Some(xml_raw) = cached {
+        match parse_serverparams(&param_in.addr, &xml_raw) {
+            Ok(res) => {
+                info!(context, "Using cached ISPDB configuration for {domain}.");
+                return Ok((res, true));
+            }
+            Err(err) => warn!(
+                context,
+                "Failed to parse cached ISPDB configuration: {err}."
+            ),
+        }
+    }
+
+    // always SSL for Thunderbird's database
+    let url = format!("https://autoconfig.thunderbird.net/v1.1/{domain}");
+    let xml_raw = read_url(context, &url).await?;
+    let res = parse_serverparams(&param_in.addr, &xml_raw).map_err(|err| {
+        warn!(context, "Failed to parse ISPDB configuration: {err}.");
+        err
+    })?;
+    ISPDB_CACHE
+        .lock()
+        .unwrap()
+        .insert(domain, (xml_raw, time()));
+    Ok((res, false))
+}
+
 #[cfg(test)]
 mod tests {
     #![allow(clippy::indexing_slicing)]
 
     use super::*;
+    use crate::test_utils::TestContextManager;
 
     #[test]
     fn test_parse_outlook_autoconfig() {
@@ -330,4 +386,29 @@ fn test_parse_lakenet_autoconfig() {
         assert_eq!(res.outgoing_servers[0].sockettype, Socket::Starttls);
         assert_eq!(res.outgoing_servers[0].username, "example@lakenet.ch");
     }
+
+    #[tokio::test(flavor = "multi_thread", worker_threads = 2)]
+    async fn test_ispdb_cache() -> Result<(), Error> {
+        let mut tcm = TestContextManager::new();
+        let alice = &tcm.unconfigured().await;
+        let bob = &tcm.unconfigured().await;
+        let param = LoginParam {
+            addr: "example@outlook.com".to_string(),
+            ..Default::default()
+        };
+        let xml_raw = include_str!("../../test-data/autoconfig/outlook.com.xml");
+        ISPDB_CACHE
+            .lock()
+            .unwrap()
+            .insert("outlook.com".to_string(), (xml_raw.to_string(), time()));
+
+        // The cache is shared by all accounts.
+        for t in [alice, bob] {
+            let (res, cached) = moz_ispdb_autoconfigure(t, "Outlook.com", &param).await?;
+            assert!(cached);
+            assert_eq!(res[0].protocol, Protocol::Imap);
+            assert_eq!(res[0].hostname, "outlook.office365.com");
+        }
+        Ok(())
+    }
 }
@@ -2,16 +2,36 @@
 //!
 //! This module implements autoconfiguration via POX (Plain Old XML) interface to Autodiscover
 //! Service. Newer SOAP interface, introduced in Exchange 2010, is not used.
+//!
+//! The URL of the POX endpoint can also be looked up
+//! via the JSON interface of Autodiscover V2,
+//! which is used by Office 365 for custom domains.
 
+use std::collections::BTreeSet;
 use std::io::BufRead;
 
+use anyhow::{anyhow, Context as _};
+use deltachat_contact_tools::EmailAddress;
+use quick_xml::escape::escape;
 use quick_xml::events::Event;
+use serde::Deserialize;
+use url::Url;
 
 use super::{Error, ServerParams};
 use crate::context::Context;
+use crate::net::http::get_client;
 use crate::net::read_url;
 use crate::provider::{Protocol, Socket};
 
+/// Maximum number of redirections to follow via `RedirectUrl` and `RedirectAddr`.
+const MAX_REDIRECTS: usize = 10;
+
+/// Domains of Microsoft hosting Autodiscover for their customers.
+///
+/// Besides the domain of the account and its subdomains,
+/// the password is only sent to these domains and their subdomains.
+const MICROSOFT_DOMAINS: &[&str] = &["outlook.com", "office365.com", "office.com"];
+
 /// Result of parsing a single `Protocol` tag.
 ///
 /// <https://docs.microsoft.com/en-us/exchange/client-developer/web-service-reference/protocol-pox>
@@ -36,6 +56,11 @@ struct ProtocolTag {
     ///
     /// <https://docs.microsoft.com/en-us/exchange/client-developer/web-service-reference/ssl-pox>
     pub ssl: bool,
+
+    /// Username to log in with, if it differs from the email address.
+    ///
+    /// <https://docs.microsoft.com/en-us/exchange/client-developer/web-service-reference/loginname-pox>
+    pub login_name: Option<String>,
 }
 
 enum ParsingResult {
@@ -43,6 +68,22 @@ enum ParsingResult {
 
     /// XML redirect via `RedirectUrl` tag.
     RedirectUrl(String),
+
+    /// Redirect to another email address via `RedirectAddr` tag.
+    RedirectAddr(String),
+}
+
+/// Response of the Autodiscover V2 JSON interface.
+///
+/// <https://learn.microsoft.com/en-us/exchange/client-developer/exchange-web-services/autodiscover-for-exchange>
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "PascalCase")]
+struct JsonResponse {
+    /// Requested protocol, "AutodiscoverV1" for the POX endpoint.
+    protocol: String,
+
+    /// URL of the endpoint.
+    url: String,
 }
 
 /// Parses a single Protocol section.
@@ -53,6 +94,7 @@ fn parse_protocol<B: BufRead>(
     let mut protocol_server = None;
     let mut protocol_port = None;
     let mut protocol_ssl = true;
+    let mut protocol_login_name = None;
 
     let mut buf = Vec::new();
 
@@ -85,6 +127,7 @@ fn parse_protocol<B: BufRead>(
                         "type" => protocol_type = Some(val.trim().to_string()),
                         "server" => protocol_server = Some(val.trim().to_string()),
                         "port" => protocol_port = Some(val.trim().parse().unwrap_or_default()),
+                        "loginname" => protocol_login_name = Some(val.trim().to_string()),
                         "ssl" => {
                             protocol_ssl = match val.trim() {
                                 "on" => true,
@@ -109,13 +152,14 @@ fn parse_protocol<B: BufRead>(
             server: protocol_server,
             port: protocol_port,
             ssl: protocol_ssl,
+            login_name: protocol_login_name.filter(|login_name| !login_name.is_empty()),
         }))
     } else {
         Ok(None)
     }
 }
 
-/// Parses `RedirectUrl` tag.
+/// Parses `RedirectUrl` or `RedirectAddr` tag.
 fn parse_redirecturl<B: BufRead>(
     reader: &mut quick_xml::Reader<B>,
 ) -> Result<String, quick_xml::Error> {
@@ -149,6 +193,9 @@ fn parse_xml_reader<B: BufRead>(
                 } else if tag == "redirecturl" {
                     let redirecturl = parse_redirecturl(reader)?;
                     return Ok(ParsingResult::RedirectUrl(redirecturl));
+                } else if tag == "redirectaddr" {
+                    let redirectaddr = parse_redirecturl(reader)?;
+                    return Ok(ParsingResult::RedirectAddr(redirectaddr));
                 }
             }
             Event::Eof => break,
@@ -170,7 +217,7 @@ fn parse_xml(xml_raw: &str) -> Result<ParsingResult, Error> {
     })
 }
 
-fn protocols_to_serverparams(protocols: Vec<ProtocolTag>) -> Vec<ServerParams> {
+fn protocols_to_serverparams(protocols: Vec<ProtocolTag>, username: &str) -> Vec<ServerParams> {
     protocols
         .into_iter()
         .filter_map(|protocol| {
@@ -186,34 +233,182 @@ fn protocols_to_serverparams(protocols: Vec<ProtocolTag>) -> Vec<ServerParams> {
                 },
                 hostname: protocol.server,
                 port: protocol.port,
-                username: String::new(),
+                username: protocol.login_name.unwrap_or_else(|| username.to_string()),
                 strict_tls: None,
             })
         })
         .collect()
 }
 
+/// Returns the body of a POX request for `addr`.
+///
+/// <https://learn.microsoft.com/en-us/exchange/client-developer/web-service-reference/pox-autodiscover-request-for-exchange>
+fn request_xml(addr: &str) -> String {
+    format!(
+        "<?xml version=\"1.0\" encoding=\"utf-8\"?>\n\
+         <Autodiscover xmlns=\"http://schemas.microsoft.com/exchange/autodiscover/outlook/requestschema/2006\">\n\
+         <Request>\n\
+         <EMailAddress>{}</EMailAddress>\n\
+         <AcceptableResponseSchema>http://schemas.microsoft.com/exchange/autodiscover/outlook/responseschema/2006a</AcceptableResponseSchema>\n\
+         </Request>\n\
+         </Autodiscover>",
+        escape(addr)
+    )
+}
+
+/// Returns true if the password of an account at `domain` may be sent to `url`.
+///
+/// The password is only sent over HTTPS to the domain of the account,
+/// to [`MICROSOFT_DOMAINS`] and to their subdomains.
+fn may_send_password(url: &Url, domain: &str) -> bool {
+    if url.scheme() != "https" {
+        return false;
+    }
+    let Some(host) = url.host_str() else {
+        return false;
+    };
+    let domain = domain.to_ascii_lowercase();
+    std::iter::once(domain.as_str())
+        .chain(MICROSOFT_DOMAINS.iter().copied())
+        .any(|trusted| {
+            host == trusted
+                || host
+                    .strip_suffix(trusted)
+                    .is_some_and(|subdomain| subdomain.ends_with('.'))
+        })
+}
+
+/// Sends the POX request for `addr` to `url` and returns the response body.
+///
+/// If the server requires authentication, the request is repeated
+/// with HTTP basic authentication,
+/// but only if [`may_send_password`] allows it for the account `domain`
+/// and the request was not redirected to another host.
+async fn post_request(
+    context: &Context,
+    url: &str,
+    addr: &str,
+    password: &str,
+    domain: &str,
+) -> anyhow::Result<String> {
+    let client = get_client(context, url.starts_with("https://")).await?;
+    let request = |url: &str| {
+        client
+            .post(url)
+            .header(reqwest::header::CONTENT_TYPE, "text/xml; charset=utf-8")
+            .body(request_xml(addr))
+    };
+
+    let mut response = request(url).send().await?;
+    if response.status() == reqwest::StatusCode::UNAUTHORIZED {
+        let requested_url = Url::parse(url).context("Invalid Autodiscover URL")?;
+        let url = response.url().clone();
+        if password.is_empty()
+            || url.host_str() != requested_url.host_str()
+            || !may_send_password(&url, domain)
+        {
+            return Err(anyhow!("Autodiscover at {url} requires authentication"));
+        }
+        info!(context, "Autodiscover at {url} requires authentication.");
+        response = request(url.as_str())
+            .basic_auth(addr, Some(password))
+            .send()
+            .await?;
+    }
+    let response = response
+        .error_for_status()
+        .with_context(|| format!("Autodiscover at {url} failed"))?;
+    Ok(response.text().await?)
+}
+
+/// Queries the POX Autodiscover endpoint at `url` for the settings of `addr`.
+///
+/// `password` is only sent to endpoints which require authentication
+/// and are trusted with the password of `addr`, see [`may_send_password`].
 pub(crate) async fn outlk_autodiscover(
     context: &Context,
     mut url: String,
+    addr: &str,
+    password: &str,
 ) -> Result<Vec<ServerParams>, Error> {
-    /* Follow up to 10 xml-redirects (http-redirects are followed in read_url() */
-    for _i in 0..10 {
-        let xml_raw = read_url(context, &url).await?;
+    // Redirects to other addresses don't change the domain the password belongs to.
+    let domain = EmailAddress::new(addr).context("Bad email-address")?.domain;
+    let mut addr = addr.to_string();
+    let mut username = String::new();
+    let mut visited = BTreeSet::new();
+
+    // Follow up to 10 xml-redirects (http-redirects are followed by the HTTP client).
+    for _i in 0..MAX_REDIRECTS {
+        if !visited.insert((url.clone(), addr.clone())) {
+            warn!(
+                context,
+                "Autodiscover redirection loop at {url} for {addr}."
+            );
+            return Err(Error::Redirection);
+        }
+        let xml_raw = post_request(context, &url, &addr, password, &domain).await?;
         let res = parse_xml(&xml_raw);
         if let Err(err) = &res {
             warn!(context, "{}", err);
         }
         match res? {
-            ParsingResult::RedirectUrl(redirect_url) => url = redirect_url,
+            ParsingResult::RedirectUrl(redirect_url) => {
+                if !redirect_url.starts_with("https://") {
+                    return Err(Error::Other(anyhow!(
+                        "Autodiscover redirects to non-HTTPS URL {redirect_url:?}"
+                    )));
+                }
+                info!(context, "Autodiscover redirects to {redirect_url}.");
+                url = redirect_url;
+            }
+            ParsingResult::RedirectAddr(redirect_addr) => {
+                info!(
+                    context,
+                    "Autodiscover redirects to address {redirect_addr}."
+                );
+                username.clone_from(&redirect_addr);
+                addr = redirect_addr;
+            }
             ParsingResult::Protocols(protocols) => {
-                return Ok(protocols_to_serverparams(protocols));
+                return Ok(protocols_to_serverparams(protocols, &username));
             }
         }
     }
     Err(Error::Redirection)
 }
 
+/// Looks up the POX endpoint via the Autodiscover V2 JSON interface at `url`
+/// and queries it for the settings of `addr`.
+pub(crate) async fn outlk_autodiscover_json(
+    context: &Context,
+    url: &str,
+    addr: &str,
+    password: &str,
+) -> Result<Vec<ServerParams>, Error> {
+    let json_raw = read_url(context, url).await?;
+    let response = parse_json(&json_raw)?;
+    info!(context, "Autodiscover V2 points to {}.", response.url);
+    outlk_autodiscover(context, response.url, addr, password).await
+}
+
+fn parse_json(json_raw: &str) -> Result<JsonResponse, Error> {
+    let response: JsonResponse =
+        serde_json::from_str(json_raw).context("Invalid Autodiscover V2 response")?;
+    if !response.protocol.eq_ignore_ascii_case("AutodiscoverV1") {
+        return Err(Error::Other(anyhow!(
+            "Unexpected Autodiscover V2 protocol {:?}",
+            response.protocol
+        )));
+    }
+    if !response.url.starts_with("https://") {
+        return Err(Error::Other(anyhow!(
+            "Autodiscover V2 returned non-HTTPS URL {:?}",
+            response.url
+        )));
+    }
+    Ok(response)
+}
+
 #[cfg(test)]
 mod tests {
     #![allow(clippy::indexing_slicing)]
@@ -291,4 +486,109 @@ fn test_parse_loginparam() {
             }
         }
     }
+
+    #[test]
+    fn test_parse_redirectaddr() {
+        let res = parse_xml(
+            "\
+<?xml version=\"1.0\" encoding=\"utf-8\"?>
+<Autodiscover xmlns=\"http://schemas.microsoft.com/exchange/autodiscover/responseschema/2006\">
+  <Response xmlns=\"http://schemas.microsoft.com/exchange/autodiscover/outlook/responseschema/2006a\">
+    <Account>
+      <AccountType>email</AccountType>
+      <Action>redirectAddr</Action>
+      <RedirectAddr>alice@mail.example.com</RedirectAddr>
+    </Account>
+  </Response>
+</Autodiscover>",
+        )
+        .expect("XML is not parsed successfully");
+        match res {
+            ParsingResult::RedirectAddr(addr) => assert_eq!(addr, "alice@mail.example.com"),
+            _ => panic!("RedirectAddr is not found"),
+        }
+    }
+
+    #[test]
+    fn test_parse_login_name() {
+        let res = parse_xml(
+            "\
+<?xml version=\"1.0\" encoding=\"utf-8\"?>
+<Autodiscover xmlns=\"http://schemas.microsoft.com/exchange/autodiscover/responseschema/2006\">
+  <Response xmlns=\"http://schemas.microsoft.com/exchange/autodiscover/outlook/responseschema/2006a\">
+    <Account>
+      <AccountType>email</AccountType>
+      <Action>settings</Action>
+      <Protocol>
+        <Type>IMAP</Type>
+        <Server>outlook.office365.com</Server>
+        <Port>993</Port>
+        <LoginName>alice@example.onmicrosoft.com</LoginName>
+      </Protocol>
+      <Protocol>
+        <Type>SMTP</Type>
+        <Server>smtp.office365.com</Server>
+        <Port>587</Port>
+      </Protocol>
+    </Account>
+  </Response>
+</Autodiscover>",
+        )
+        .expect("XML is not parsed successfully");
+        let ParsingResult::Protocols(protocols) = res else {
+            panic!("Protocols are not found");
+        };
+        let servers = protocols_to_serverparams(protocols, "");
+        assert_eq!(servers.len(), 2);
+        assert_eq!(servers[0].protocol, Protocol::Imap);
+        assert_eq!(servers[0].username, "alice@example.onmicrosoft.com");
+        assert_eq!(servers[1].protocol, Protocol::Smtp);
+        assert_eq!(servers[1].username, "");
+    }
+
+    #[test]
+    fn test_parse_json() {
+        let response = parse_json(
+            r#"{"Protocol":"AutodiscoverV1","Url":"https://outlook.office365.com/autodiscover/autodiscover.xml"}"#,
+        )
+        .unwrap();
+        assert_eq!(
+            response.url,
+            "https://outlook.office365.com/autodiscover/autodiscover.xml"
+        );
+
+        assert!(
+            parse_json(r#"{"Protocol":"AutodiscoverV1","Url":"http://example.com/"}"#).is_err()
+        );
+        assert!(parse_json(r#"{"ErrorCode":"InvalidUser","ErrorMessage":"No user"}"#).is_err());
+    }
+
+    #[test]
+    fn test_may_send_password() {
+        let allowed = |url: &str| may_send_password(&Url::parse(url).unwrap(), "Example.org");
+        assert!(allowed("https://example.org/autodiscover/autodiscover.xml"));
+        assert!(allowed(
+            "https://autodiscover.example.org/autodiscover/autodiscover.xml"
+        ));
+        assert!(allowed(
+            "https://outlook.office365.com/autodiscover/autodiscover.xml"
+        ));
+        assert!(!allowed("http://example.org/autodiscover/autodiscover.xml"));
+        assert!(!allowed(
+            "https://example.net/autodiscover/autodiscover.xml"
+        ));
+        assert!(!allowed(
+            "https://evilexample.org/autodiscover/autodiscover.xml"
+        ));
+        assert!(!allowed(
+            "https://example.org.example.net/autodiscover/autodiscover.xml"
+        ));
+        assert!(!allowed("https://notoutlook.com/"));
+    }
+
+    #[test]
+    fn test_request_xml() {
+        let xml = request_xml("<alice>@example.org");
+        assert!(xml.contains("<EMailAddress>&lt;alice&gt;@example.org</EMailAddress>"));
+    }
 }
@@ -54,6 +54,23 @@ pub enum MediaQuality {
     Worse = 1,
 }
 
+/// What to send in the IMAP `ID` command.
+#[derive(
+    Debug, Default, Display, Clone, Copy, PartialEq, Eq, FromPrimitive, ToPrimitive, FromSql, ToSql,
+)]
+#[repr(u8)]
+pub enum ImapId {
+    /// Send the client name only.
+    #[default] // also change Config.ImapId props(default) on changes
+    Name = 0,
+
+    /// Do not send the `ID` command at all.
+    Anonymous = 1,
+
+    /// Send the client name and version.
+    Full = 2,
+}
+
 /// Type of the key to generate.
 #[derive(
     Debug, Default, Display, Clone, Copy, PartialEq, Eq, FromPrimitive, ToPrimitive, FromSql, ToSql,
@@ -282,6 +299,15 @@ fn test_mediaquality_values() {
         assert_eq!(MediaQuality::Worse, MediaQuality::from_i32(1).unwrap());
     }
 
+    #[test]
+    fn test_imapid_values() {
+        // values may be written to disk and must not change
+        assert_eq!(ImapId::Name, ImapId::default());
+        assert_eq!(ImapId::Name, ImapId::from_i32(0).unwrap());
+        assert_eq!(ImapId::Anonymous, ImapId::from_i32(1).unwrap());
+        assert_eq!(ImapId::Full, ImapId::from_i32(2).unwrap());
+    }
+
     #[test]
     fn test_videochattype_values() {
         // values may be written to disk and must not change
@@ -12,16 +12,18 @@
 pub use deltachat_contact_tools::may_be_valid_addr;
 use deltachat_contact_tools::{
     self as contact_tools, addr_cmp, addr_normalize, sanitize_name, sanitize_name_and_addr,
-    ContactAddress, VcardContact,
+    sanitize_single_line, ContactAddress, VcardContact,
 };
 use deltachat_derive::{FromSql, ToSql};
 use rusqlite::OptionalExtension;
 use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
 use tokio::task;
 use tokio::time::{timeout, Duration};
 
 use crate::aheader::{Aheader, EncryptPreference};
 use crate::blob::BlobObject;
+use crate::bot_commands::BotCommand;
 use crate::chat::{ChatId, ChatIdBlocked, ProtectionStatus};
 use crate::color::str_to_color;
 use crate::config::Config;
@@ -31,14 +33,14 @@
 use crate::key::{load_self_public_key, DcKey, SignedPublicKey};
 use crate::log::LogExt;
 use crate::login_param::LoginParam;
-use crate::message::MessageState;
+use crate::message::{MessageState, MsgId};
 use crate::mimeparser::AvatarAction;
 use crate::param::{Param, Params};
 use crate::peerstate::Peerstate;
 use crate::sql::{self, params_iter};
-use crate::sync::{self, Sync::*};
-use crate::tools::{duration_to_str, get_abs_path, smeared_time, time, SystemTime};
-use crate::{chat, chatlist_events, stock_str};
+use crate::sync::{self, Sync::*, SyncData};
+use crate::tools::{create_id, duration_to_str, get_abs_path, smeared_time, time, SystemTime};
+use crate::{chat, chatlist_events, stock_str, wkd};
 
 /// Time during which a contact is considered as seen recently.
 const SEEN_RECENTLY_SECONDS: i64 = 600;
@@ -297,7 +299,7 @@ async fn import_vcard_contact(context: &Context, contact: &VcardContact) -> Resu
             return Ok(id);
         }
         if let Err(e) = peerstate
-            .handle_fingerprint_change(context, timestamp)
+            .handle_fingerprint_change(context, timestamp, None)
             .await
         {
             warn!(
@@ -389,6 +391,50 @@ pub struct Contact {
     is_bot: bool,
 }
 
+/// The current state of a contact.
+///
+/// Unlike [`Contact`], this has a stable serialization
+/// and can be passed to bots and bridges.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[non_exhaustive]
+pub struct ContactInfo {
+    /// The contact ID.
+    pub id: ContactId,
+
+    /// E-Mail-Address of the contact.
+    pub addr: String,
+
+    /// Name given to the contact by the user, may be empty.
+    pub name: String,
+
+    /// Name authorized by the contact, may be empty.
+    pub authname: String,
+
+    /// Name to display in the UI, see [`Contact::get_display_name`].
+    pub display_name: String,
+
+    /// Last seen message signature of the contact.
+    pub status: String,
+
+    /// Colour this contact should be represented in by the UI.
+    pub color: u32,
+
+    /// The path to the profile image, if any.
+    pub profile_image: Option<PathBuf>,
+
+    /// Whether the contact is blocked.
+    pub is_blocked: bool,
+
+    /// Whether the contact is a bot.
+    pub is_bot: bool,
+
+    /// Whether the contact is verified.
+    pub is_verified: bool,
+
+    /// Time when the contact was seen last time, Unix time in seconds.
+    pub last_seen: i64,
+}
+
 /// Possible origins of a contact.
 #[derive(
     Debug,
@@ -483,6 +529,23 @@ pub(crate) enum Modifier {
     Created,
 }
 
+/// Observed change of a contact's key.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct KeyChange {
+    /// Hex fingerprint of the previous key.
+    pub old_fingerprint: String,
+
+    /// Hex fingerprint of the new key.
+    pub new_fingerprint: String,
+
+    /// Timestamp of the message that caused the change.
+    pub timestamp: i64,
+
+    /// ID of the message that caused the change,
+    /// `None` if it is unknown or was deleted.
+    pub msg_id: Option<MsgId>,
+}
+
 impl Contact {
     /// Loads a single contact object from the database.
     ///
@@ -641,6 +704,9 @@ pub(crate) async fn create_ex(
         if blocked {
             set_blocked(context, Nosync, contact_id, false).await?;
         }
+        if sth_modified == Modifier::Created {
+            wkd::spawn_lookup(context, &addr).await?;
+        }
 
         if sync.into() && sth_modified != Modifier::None {
             chat::sync(
@@ -672,6 +738,19 @@ pub fn is_bot(&self) -> bool {
         self.is_bot
     }
 
+    /// Returns the commands advertised by the bot.
+    ///
+    /// Returns an empty list if the contact is not a bot.
+    pub fn get_bot_commands(&self) -> Vec<BotCommand> {
+        if !self.is_bot {
+            return Vec::new();
+        }
+        self.param
+            .get(Param::BotCommands)
+            .and_then(|json| serde_json::from_str(json).ok())
+            .unwrap_or_default()
+    }
+
     /// Check if an e-mail address belongs to a known and unblocked contact.
     ///
     /// Known and unblocked contacts will be returned by `get_contacts()`.
@@ -1179,6 +1258,39 @@ pub async fn get_all_blocked(context: &Context) -> Result<Vec<ContactId>> {
         Ok(list)
     }
 
+    /// Returns the observed key changes of the contact, oldest first.
+    pub async fn get_key_history(
+        context: &Context,
+        contact_id: ContactId,
+    ) -> Result<Vec<KeyChange>> {
+        let contact = Contact::get_by_id(context, contact_id).await?;
+        let history = context
+            .sql
+            .query_map(
+                "SELECT k.old_fingerprint, k.new_fingerprint, k.timestamp,
+                        (SELECT id FROM msgs WHERE rfc724_mid=k.rfc724_mid AND k.rfc724_mid!=''
+                         ORDER BY id LIMIT 1)
+                 FROM key_history k
+                 WHERE k.addr=?
+                 ORDER BY k.timestamp, k.id",
+                (&contact.addr,),
+                |row| {
+                    Ok(KeyChange {
+                        old_fingerprint: row.get(0)?,
+                        new_fingerprint: row.get(1)?,
+                        timestamp: row.get(2)?,
+                        msg_id: row.get(3)?,
+                    })
+                },
+                |rows| {
+                    rows.collect::<std::result::Result<Vec<_>, _>>()
+                        .map_err(Into::into)
+                },
+            )
+            .await?;
+        Ok(history)
+    }
+
     /// Returns a textual summary of the encryption state for the contact.
     ///
     /// This function returns a string explaining the encryption state
@@ -1241,6 +1353,52 @@ pub async fn get_encrinfo(context: &Context, contact_id: ContactId) -> Result<St
         Ok(ret)
     }
 
+    /// Returns a short numeric representation of the own and the contact's fingerprint,
+    /// similar to "safety numbers" of other messengers.
+    ///
+    /// The result consists of 8 groups of 5 digits and is the same on both sides,
+    /// so two users can compare it verbally, e.g. during a phone call,
+    /// instead of scanning a QR code.
+    /// As the result is derived from both keys, it changes if any of them changes
+    /// and a previous comparison becomes invalid.
+    ///
+    /// Returns `None` if the contact's key is not known.
+    pub async fn get_verification_words(
+        context: &Context,
+        contact_id: ContactId,
+    ) -> Result<Option<String>> {
+        ensure!(
+            !contact_id.is_special(),
+            "Can not provide verification words for special contact"
+        );
+
+        let contact = Contact::get_by_id(context, contact_id).await?;
+        let Some(peerstate) = Peerstate::from_addr(context, &contact.addr).await? else {
+            return Ok(None);
+        };
+        let Some(fingerprint_other) = peerstate.peek_key(false).map(|k| k.fingerprint()) else {
+            return Ok(None);
+        };
+        let fingerprint_self = load_self_public_key(context).await?.fingerprint();
+
+        // Sort the fingerprints so that both sides get the same result.
+        let mut fingerprints = [fingerprint_self.hex(), fingerprint_other.hex()];
+        fingerprints.sort();
+        let digest = Sha256::new()
+            .chain_update(&fingerprints[0])
+            .chain_update(&fingerprints[1])
+            .finalize();
+
+        let words: Vec<String> = digest
+            .chunks_exact(4)
+            .map(|chunk| {
+                let n = chunk.iter().fold(0u32, |n, b| n << 8 | u32::from(*b));
+                format!("{:05}", n % 100000)
+            })
+            .collect();
+        Ok(Some(words.join(" ")))
+    }
+
     /// Delete a contact so that it disappears from the corresponding lists.
     /// Depending on whether there are ongoing chats, deletion is done by physical deletion or hiding.
     /// The contact is deleted from the local device.
@@ -1265,6 +1423,10 @@ pub async fn delete(context: &Context, contact_id: ContactId) -> Result<()> {
                         (Origin::Hidden, contact_id),
                     )?;
                 }
+                transaction.execute(
+                    "DELETE FROM contact_lists_contacts WHERE contact_id=?",
+                    (contact_id,),
+                )?;
                 Ok(())
             })
             .await?;
@@ -1507,6 +1669,24 @@ pub async fn is_profile_verified(&self, context: &Context) -> Result<bool> {
         }
     }
 
+    /// Returns a struct describing the current state of the contact.
+    pub async fn get_info(&self, context: &Context) -> Result<ContactInfo> {
+        Ok(ContactInfo {
+            id: self.id,
+            addr: self.addr.clone(),
+            name: self.name.clone(),
+            authname: self.authname.clone(),
+            display_name: self.get_display_name().to_string(),
+            status: self.status.clone(),
+            color: self.get_color(),
+            profile_image: self.get_profile_image(context).await?,
+            is_blocked: self.blocked,
+            is_bot: self.is_bot,
+            is_verified: self.is_verified(context).await?,
+            last_seen: self.last_seen,
+        })
+    }
+
     /// Returns the number of real (i.e. non-special) contacts in the database.
     pub async fn get_real_cnt(context: &Context) -> Result<usize> {
         if !context.sql.is_open().await {
@@ -1537,6 +1717,213 @@ pub async fn real_exists_by_id(context: &Context, contact_id: ContactId) -> Resu
     }
 }
 
+/// A reusable set of contacts,
+/// e.g. to create groups or broadcast lists with the same members in one call.
+///
+/// Contact lists are synchronised across own devices.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ContactList {
+    /// Database ID of the contact list.
+    pub id: u32,
+
+    /// ID of the contact list used for synchronisation between own devices.
+    pub(crate) uid: String,
+
+    /// Name of the contact list.
+    pub name: String,
+
+    /// Members of the contact list.
+    pub contact_ids: Vec<ContactId>,
+}
+
+/// Creates a new contact list with the given name and members.
+///
+/// Returns the ID of the new contact list.
+pub async fn create_contact_list(
+    context: &Context,
+    name: &str,
+    members: &[ContactId],
+) -> Result<u32> {
+    let uid = create_id();
+    save_contact_list(context, Sync, &uid, name, members).await
+}
+
+/// Replaces name and members of the contact list.
+pub async fn set_contact_list(
+    context: &Context,
+    id: u32,
+    name: &str,
+    members: &[ContactId],
+) -> Result<()> {
+    let list = get_contact_list(context, id).await?;
+    save_contact_list(context, Sync, &list.uid, name, members).await?;
+    Ok(())
+}
+
+/// Deletes the contact list. Contacts and chats created from the list are not affected.
+pub async fn delete_contact_list(context: &Context, id: u32) -> Result<()> {
+    let list = get_contact_list(context, id).await?;
+    delete_contact_list_ex(context, Sync, &list.uid).await
+}
+
+/// Returns the contact list with the given ID.
+pub async fn get_contact_list(context: &Context, id: u32) -> Result<ContactList> {
+    let (uid, name) = context
+        .sql
+        .query_row_optional(
+            "SELECT uid, name FROM contact_lists WHERE id=?",
+            (id,),
+            |row| Ok((row.get::<_, String>(0)?, row.get::<_, String>(1)?)),
+        )
+        .await?
+        .with_context(|| format!("Contact list {id} does not exist"))?;
+    let contact_ids = context
+        .sql
+        .query_map(
+            "SELECT contact_id FROM contact_lists_contacts WHERE list_id=? ORDER BY contact_id",
+            (id,),
+            |row| row.get::<_, ContactId>(0),
+            |ids| {
+                ids.collect::<std::result::Result<Vec<_>, _>>()
+                    .map_err(Into::into)
+            },
+        )
+        .await?;
+    Ok(ContactList {
+        id,
+        uid,
+        name,
+        contact_ids,
+    })
+}
+
+/// Returns all contact lists, sorted by name.
+pub async fn get_contact_lists(context: &Context) -> Result<Vec<ContactList>> {
+    let ids = context
+        .sql
+        .query_map(
+            "SELECT id FROM contact_lists ORDER BY name COLLATE NOCASE, id",
+            (),
+            |row| row.get::<_, u32>(0),
+            |ids| {
+                ids.collect::<std::result::Result<Vec<_>, _>>()
+                    .map_err(Into::into)
+            },
+        )
+        .await?;
+    let mut lists = Vec::with_capacity(ids.len());
+    for id in ids {
+        lists.push(get_contact_list(context, id).await?);
+    }
+    Ok(lists)
+}
+
+/// Creates or updates the contact list identified by `uid`.
+async fn save_contact_list(
+    context: &Context,
+    sync: sync::Sync,
+    uid: &str,
+    name: &str,
+    members: &[ContactId],
+) -> Result<u32> {
+    let name = sanitize_single_line(name);
+    ensure!(!name.is_empty(), "Contact list name must not be empty");
+    for contact_id in members {
+        ensure!(
+            !contact_id.is_special(),
+            "Special contact {contact_id} cannot be added to a contact list"
+        );
+    }
+    let id = context
+        .sql
+        .transaction(|transaction| {
+            let id: u32 = transaction.query_row(
+                "INSERT INTO contact_lists (uid, name) VALUES (?, ?)
+                 ON CONFLICT (uid) DO UPDATE SET name=excluded.name
+                 RETURNING id",
+                (uid, &name),
+                |row| row.get(0),
+            )?;
+            transaction.execute("DELETE FROM contact_lists_contacts WHERE list_id=?", (id,))?;
+            let mut stmt = transaction.prepare(
+                "INSERT OR IGNORE INTO contact_lists_contacts (list_id, contact_id) VALUES (?, ?)",
+            )?;
+            for contact_id in members {
+                stmt.execute((id, contact_id))?;
+            }
+            Ok(id)
+        })
+        .await?;
+    context.emit_event(EventType::ContactsChanged(None));
+
+    if sync.into() {
+        let mut addrs = Vec::with_capacity(members.len());
+        for contact_id in members {
+            addrs.push(Contact::get_by_id(context, *contact_id).await?.addr);
+        }
+        context
+            .add_sync_item(SyncData::ContactList {
+                uid: uid.to_string(),
+                name,
+                addrs,
+            })
+            .await?;
+        context.scheduler.interrupt_smtp().await;
+    }
+    Ok(id)
+}
+
+async fn delete_contact_list_ex(context: &Context, sync: sync::Sync, uid: &str) -> Result<()> {
+    context
+        .sql
+        .transaction(|transaction| {
+            transaction.execute(
+                "DELETE FROM contact_lists_contacts
+                 WHERE list_id IN (SELECT id FROM contact_lists WHERE uid=?)",
+                (uid,),
+            )?;
+            transaction.execute("DELETE FROM contact_lists WHERE uid=?", (uid,))?;
+            Ok(())
+        })
+        .await?;
+    context.emit_event(EventType::ContactsChanged(None));
+
+    if sync.into() {
+        context
+            .add_sync_item(SyncData::DeleteContactList {
+                uid: uid.to_string(),
+            })
+            .await?;
+        context.scheduler.interrupt_smtp().await;
+    }
+    Ok(())
+}
+
+impl Context {
+    /// Executes [`SyncData::ContactList`] item sent by other device.
+    pub(crate) async fn sync_contact_list(
+        &self,
+        uid: &str,
+        name: &str,
+        addrs: &[String],
+    ) -> Result<()> {
+        let mut members = Vec::with_capacity(addrs.len());
+        for addr in addrs {
+            let contact_addr = ContactAddress::new(addr)?;
+            let (contact_id, _) =
+                Contact::add_or_lookup(self, "", &contact_addr, Origin::Hidden).await?;
+            members.push(contact_id);
+        }
+        save_contact_list(self, Nosync, uid, name, &members).await?;
+        Ok(())
+    }
+
+    /// Executes [`SyncData::DeleteContactList`] item sent by other device.
+    pub(crate) async fn sync_delete_contact_list(&self, uid: &str) -> Result<()> {
+        delete_contact_list_ex(self, Nosync, uid).await
+    }
+}
+
 pub(crate) async fn set_blocked(
     context: &Context,
     sync: sync::Sync,
@@ -2694,6 +3081,50 @@ async fn test_contact_get_encrinfo() -> Result<()> {
         Ok(())
     }
 
+    #[tokio::test(flavor = "multi_thread", worker_threads = 2)]
+    async fn test_get_verification_words() -> Result<()> {
+        let mut tcm = TestContextManager::new();
+        let alice = tcm.alice().await;
+        let bob = tcm.bob().await;
+
+        assert!(Contact::get_verification_words(&alice, ContactId::SELF)
+            .await
+            .is_err());
+
+        let alice_bob_id = Contact::create(&alice, "", "bob@example.net").await?;
+        assert_eq!(
+            Contact::get_verification_words(&alice, alice_bob_id).await?,
+            None
+        );
+
+        tcm.send_recv_accept(&bob, &alice, "Hi").await;
+        tcm.send_recv(&alice, &bob, "Hello").await;
+        let bob_alice_id = Contact::create(&bob, "", "alice@example.org").await?;
+
+        let words = Contact::get_verification_words(&alice, alice_bob_id)
+            .await?
+            .unwrap();
+        assert_eq!(words.split(' ').count(), 8);
+        assert!(words.split(' ').all(|w| w.len() == 5));
+        assert_eq!(
+            Contact::get_verification_words(&bob, bob_alice_id).await?,
+            Some(words.clone())
+        );
+
+        // If Bob's key changes, the words change as well.
+        let mut peerstate = Peerstate::from_addr(&alice, "bob@example.net")
+            .await?
+            .unwrap();
+        peerstate.public_key = Some(test_utils::alice_keypair().public);
+        peerstate.save_to_db(&alice.sql).await?;
+        assert_ne!(
+            Contact::get_verification_words(&alice, alice_bob_id).await?,
+            Some(words)
+        );
+
+        Ok(())
+    }
+
     /// Tests that status is synchronized when sending encrypted BCC-self messages and not
     /// synchronized when the message is not encrypted.
     #[tokio::test(flavor = "multi_thread", worker_threads = 2)]
@@ -2975,6 +3406,76 @@ async fn test_sync_create() -> Result<()> {
         Ok(())
     }
 
+    #[tokio::test(flavor = "multi_thread", worker_threads = 2)]
+    async fn test_contact_list() -> Result<()> {
+        let alice0 = &TestContext::new_alice().await;
+        let alice1 = &TestContext::new_alice().await;
+        for a in [alice0, alice1] {
+            a.set_config_bool(Config::SyncMsgs, true).await?;
+        }
+
+        let bob_id = Contact::create(alice0, "Bob", "bob@example.net").await?;
+        let fiona_id = Contact::create(alice0, "Fiona", "fiona@example.net").await?;
+        assert!(create_contact_list(alice0, "Team", &[ContactId::SELF])
+            .await
+            .is_err());
+        let list_id = create_contact_list(alice0, "Team", &[bob_id, fiona_id]).await?;
+        let list = get_contact_list(alice0, list_id).await?;
+        assert_eq!(list.name, "Team");
+        assert_eq!(list.contact_ids, vec![bob_id, fiona_id]);
+
+        let chat_id = chat::create_group_chat_from_contact_list(
+            alice0,
+            ProtectionStatus::Unprotected,
+            list_id,
+        )
+        .await?;
+        let chat = Chat::load_from_db(alice0, chat_id).await?;
+        assert_eq!(chat.get_name(), "Team");
+        let contacts = get_chat_contacts(alice0, chat_id).await?;
+        assert_eq!(contacts.len(), 3);
+        assert!(contacts.contains(&bob_id));
+        assert!(contacts.contains(&fiona_id));
+
+        let broadcast_id = chat::create_broadcast_list_from_contact_list(alice0, list_id).await?;
+        let broadcast = Chat::load_from_db(alice0, broadcast_id).await?;
+        assert_eq!(broadcast.get_type(), Chattype::Broadcast);
+        assert_eq!(broadcast.get_name(), "Team");
+        assert_eq!(get_chat_contacts(alice0, broadcast_id).await?.len(), 2);
+
+        test_utils::sync(alice0, alice1).await;
+        let lists = get_contact_lists(alice1).await?;
+        assert_eq!(lists.len(), 1);
+        assert_eq!(lists[0].name, "Team");
+        assert_eq!(lists[0].uid, list.uid);
+        let mut addrs = Vec::new();
+        for contact_id in &lists[0].contact_ids {
+            addrs.push(Contact::get_by_id(alice1, *contact_id).await?.addr);
+        }
+        addrs.sort();
+        assert_eq!(addrs, vec!["bob@example.net", "fiona@example.net"]);
+
+        set_contact_list(alice0, list_id, "Small Team", &[bob_id]).await?;
+        test_utils::sync(alice0, alice1).await;
+        let lists = get_contact_lists(alice1).await?;
+        assert_eq!(lists[0].name, "Small Team");
+        assert_eq!(lists[0].contact_ids.len(), 1);
+
+        // Deleted contacts are removed from contact lists.
+        Contact::delete(alice0, bob_id).await?;
+        assert!(get_contact_list(alice0, list_id)
+            .await?
+            .contact_ids
+            .is_empty());
+
+        delete_contact_list(alice0, list_id).await?;
+        assert!(get_contact_list(alice0, list_id).await.is_err());
+        test_utils::sync(alice0, alice1).await;
+        assert!(get_contact_lists(alice1).await?.is_empty());
+
+        Ok(())
+    }
+
     #[tokio::test(flavor = "multi_thread", worker_threads = 2)]
     async fn test_make_n_import_vcard() -> Result<()> {
         let alice = &TestContext::new_alice().await;
@@ -3115,6 +3616,10 @@ async fn test_import_vcard_updates_only_key() -> Result<()> {
             msg.get_text(),
             stock_str::contact_setup_changed(alice, bob_addr).await
         );
+        let history = Contact::get_key_history(alice, alice_bob_id).await?;
+        assert_eq!(history.len(), 1);
+        assert_ne!(history[0].old_fingerprint, history[0].new_fingerprint);
+        assert_eq!(history[0].msg_id, None);
         let sent_msg = alice.send_text(chat_id, "moin").await;
         let msg = bob.recv_msg(&sent_msg).await;
         assert!(msg.get_showpadlock());
@@ -3127,4 +3632,32 @@ async fn test_import_vcard_updates_only_key() -> Result<()> {
 
         Ok(())
     }
+
+    #[tokio::test(flavor = "multi_thread", worker_threads = 2)]
+    async fn test_contact_info_json() -> Result<()> {
+        let t = TestContext::new_alice().await;
+        let contact_id = Contact::create(&t, "Bob", "bob@example.net").await?;
+        let contact = Contact::get_by_id(&t, contact_id).await?;
+        let info = contact.get_info(&t).await?;
+        let value = serde_json::to_value(&info)?;
+        assert_eq!(
+            value,
+            serde_json::json!({
+                "id": contact_id,
+                "addr": "bob@example.net",
+                "name": "Bob",
+                "authname": "",
+                "display_name": "Bob",
+                "status": "",
+                "color": contact.get_color(),
+                "profile_image": null,
+                "is_blocked": false,
+                "is_bot": false,
+                "is_verified": false,
+                "last_seen": 0,
+            })
+        );
+        assert_eq!(serde_json::from_value::<ContactInfo>(value)?, info);
+        Ok(())
+    }
 }
@@ -4,7 +4,7 @@
 use std::ffi::OsString;
 use std::ops::Deref;
 use std::path::{Path, PathBuf};
-use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::atomic::{AtomicBool, AtomicU64, AtomicUsize, Ordering};
 use std::sync::Arc;
 use std::time::Duration;
 
@@ -26,7 +26,8 @@
 use crate::download::DownloadState;
 use crate::events::{Event, EventEmitter, EventType, Events};
 use crate::imap::{FolderMeaning, Imap, ServerMetadata};
-use crate::key::{load_self_public_key, load_self_secret_key, DcKey as _};
+use crate::key::{load_self_public_key, load_self_secret_key, DcKey as _, SigningBackend};
+use crate::log::{dedups_errors, RepeatedError};
 use crate::login_param::LoginParam;
 use crate::message::{self, Message, MessageState, MsgId, Viewtype};
 use crate::param::{Param, Params};
@@ -34,7 +35,8 @@
 use crate::peerstate::Peerstate;
 use crate::push::PushSubscriber;
 use crate::quota::QuotaInfo;
-use crate::scheduler::{convert_folder_meaning, SchedulerState};
+use crate::scheduler::{convert_folder_meaning, IoPausedGuard, SchedulerState};
+use crate::search;
 use crate::sql::Sql;
 use crate::stock_str::StockStrings;
 use crate::timesmearing::SmearedTimestamp;
@@ -236,6 +238,9 @@ pub struct InnerContext {
     pub(crate) oauth2_mutex: Mutex<()>,
     /// Mutex to prevent a race condition when a "your pw is wrong" warning is sent, resulting in multiple messages being sent.
     pub(crate) wrong_pw_warning_mutex: Mutex<()>,
+    /// Mutex to prepare messages sent with [`crate::chat::send_msg_deferred`] one at a time
+    /// and in the order they were sent.
+    pub(crate) deferred_msgs_mutex: Mutex<()>,
     pub(crate) translated_stockstrings: StockStrings,
     pub(crate) events: Events,
 
@@ -277,6 +282,12 @@ pub struct InnerContext {
     /// `last_error` should be used to avoid races with the event thread.
     pub(crate) last_error: std::sync::RwLock<String>,
 
+    /// Errors emitted by background tasks by message, used to deduplicate repeated errors.
+    pub(crate) repeated_errors: std::sync::Mutex<HashMap<String, RepeatedError>>,
+
+    /// Index of the active proxy in the list of proxies to try.
+    pub(crate) proxy_index: AtomicUsize,
+
     /// If debug logging is enabled, this contains all necessary information
     ///
     /// Standard RwLock instead of [`tokio::sync::RwLock`] is used
@@ -292,6 +303,69 @@ pub struct InnerContext {
 
     /// Iroh for realtime peer channels.
     pub(crate) iroh: OnceCell<Iroh>,
+
+    /// True if SMTP loop is going to be interrupted
+    /// to flush the batch of queued webxdc status updates.
+    pub(crate) webxdc_flush_scheduled: AtomicBool,
+
+    /// Timestamps of the last Web Key Directory lookups, keyed by lowercased address.
+    ///
+    /// Used to avoid querying the same domain with every message
+    /// sent to a contact that does not publish a key.
+    pub(crate) wkd_lookups: Mutex<HashMap<String, i64>>,
+
+    /// Backend signing outgoing messages instead of the secret key stored in the database.
+    ///
+    /// Standard RwLock is used because the lock is never held across await points.
+    pub(crate) signing_backend: std::sync::RwLock<Option<Arc<dyn SigningBackend>>>,
+
+    /// Incremented on each chatlist change, see [`crate::chatlist::Chatlist::has_changes_since`].
+    ///
+    /// Starts at a random value so that tokens of previous runs are outdated.
+    pub(crate) chatlist_version: AtomicU64,
+
+    /// Incremented on each event possibly changing the [`FreshCounts`].
+    fresh_counts_version: AtomicU64,
+
+    /// Cached result of [`Context::get_fresh_counts`].
+    ///
+    /// Standard Mutex is used because the lock is never held across await points.
+    fresh_counts: std::sync::Mutex<Option<FreshCountsCache>>,
+
+    /// Battery level in percent as last reported by the UI,
+    /// see [`crate::location::set_battery_level`].
+    ///
+    /// Standard Mutex is used because the lock is never held across await points.
+    pub(crate) battery_level: std::sync::Mutex<Option<u32>>,
+
+    /// Guard keeping IO paused while the database is closed for maintenance.
+    ///
+    /// `None` if the context is not in maintenance mode.
+    maintenance: Mutex<Option<IoPausedGuard>>,
+}
+
+/// Numbers of fresh messages in unblocked chats, see [`Context::get_fresh_counts`].
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub struct FreshCounts {
+    /// Number of all fresh messages, including muted chats.
+    pub total: usize,
+
+    /// Number of fresh messages in unmuted chats.
+    pub unmuted: usize,
+
+    /// Number of fresh messages mentioning us, including muted chats.
+    pub mentions: usize,
+}
+
+#[derive(Debug)]
+struct FreshCountsCache {
+    /// Value of [`InnerContext::fresh_counts_version`] before the counts were computed.
+    version: u64,
+
+    /// Timestamp when the earliest mute of a chat with fresh messages expires.
+    muted_until: Option<i64>,
+
+    counts: FreshCounts,
 }
 
 /// The state of ongoing process.
@@ -347,6 +421,30 @@ pub async fn new(
         Ok(context)
     }
 
+    /// Creates new context and opens the database encrypted with `passphrase`.
+    ///
+    /// A new database is created encrypted.
+    /// Fails if an existing database cannot be decrypted with `passphrase`.
+    ///
+    /// Only the database is encrypted.
+    /// Files in the blob directory, i.e. attachments and avatars, are stored unencrypted
+    /// because UIs access them directly by their path, see [`Context::get_blobdir`].
+    pub async fn open_encrypted(
+        dbfile: &Path,
+        id: u32,
+        events: Events,
+        stock_strings: StockStrings,
+        passphrase: String,
+    ) -> Result<Context> {
+        ensure!(!passphrase.is_empty(), "Passphrase must not be empty");
+        let context =
+            Self::new_closed(dbfile, id, events, stock_strings, Default::default()).await?;
+        if !context.open(passphrase).await? {
+            bail!("database could not be decrypted, incorrect passphrase");
+        }
+        Ok(context)
+    }
+
     /// Creates new context without opening the database.
     pub async fn new_closed(
         dbfile: &Path,
@@ -386,12 +484,72 @@ pub async fn open(&self, passphrase: String) -> Result<bool> {
         }
     }
 
+    /// Closes the database.
+    ///
+    /// The database can be opened again with [`Context::open`].
+    pub(crate) async fn close(&self) {
+        self.sql.close().await;
+    }
+
+    /// Enters maintenance mode so that external tools
+    /// such as backup scripts or the `sqlite3` shell can safely access the database files.
+    ///
+    /// IO is paused, the write-ahead log is checkpointed into the database file
+    /// and the database is closed, releasing all file locks.
+    /// Until [`Context::exit_maintenance`] is called, all database operations fail.
+    pub async fn enter_maintenance(&self) -> Result<()> {
+        let mut maintenance = self.maintenance.lock().await;
+        ensure!(maintenance.is_none(), "Already in maintenance mode");
+        ensure!(self.sql.is_open().await, "Database is not opened");
+
+        let pause_guard = self.scheduler.pause(self.clone()).await?;
+        self.sql.wal_checkpoint().await?;
+        self.sql.close().await;
+        *maintenance = Some(pause_guard);
+        info!(self, "Entered maintenance mode.");
+        Ok(())
+    }
+
+    /// Leaves maintenance mode, reopening the database with the given passphrase
+    /// and resuming IO if it was running before [`Context::enter_maintenance`].
+    ///
+    /// The passphrase must be the empty string for unencrypted databases.
+    /// Returns false and stays in maintenance mode if the passphrase is wrong.
+    pub async fn exit_maintenance(&self, passphrase: String) -> Result<bool> {
+        let mut maintenance = self.maintenance.lock().await;
+        ensure!(maintenance.is_some(), "Not in maintenance mode");
+
+        if !self.open(passphrase).await? {
+            return Ok(false);
+        }
+        // Dropping the guard resumes IO.
+        maintenance.take();
+        info!(self, "Left maintenance mode.");
+        Ok(true)
+    }
+
+    /// Returns true if the context is in maintenance mode.
+    pub async fn is_in_maintenance(&self) -> bool {
+        self.maintenance.lock().await.is_some()
+    }
+
     /// Changes encrypted database passphrase.
     pub async fn change_passphrase(&self, passphrase: String) -> Result<()> {
         self.sql.change_passphrase(passphrase).await?;
         Ok(())
     }
 
+    /// Returns true if the database is encrypted.
+    ///
+    /// Can be called on a closed context to find out
+    /// whether a passphrase is needed to open it.
+    pub async fn is_encrypted(&self) -> Result<bool> {
+        if let Some(is_encrypted) = self.sql.is_encrypted().await {
+            return Ok(is_encrypted);
+        }
+        Ok(!self.check_passphrase("".to_string()).await?)
+    }
+
     /// Returns true if database is open.
     pub async fn is_open(&self) -> bool {
         self.sql.is_open().await
@@ -434,6 +592,7 @@ pub(crate) fn with_blobdir(
             generating_key_mutex: Mutex::new(()),
             oauth2_mutex: Mutex::new(()),
             wrong_pw_warning_mutex: Mutex::new(()),
+            deferred_msgs_mutex: Mutex::new(()),
             translated_stockstrings: stockstrings,
             events,
             scheduler: SchedulerState::new(),
@@ -446,10 +605,20 @@ pub(crate) fn with_blobdir(
             creation_time: tools::Time::now(),
             last_full_folder_scan: Mutex::new(None),
             last_error: std::sync::RwLock::new("".to_string()),
+            repeated_errors: std::sync::Mutex::new(HashMap::new()),
+            proxy_index: AtomicUsize::new(0),
             debug_logging: std::sync::RwLock::new(None),
             push_subscriber,
             push_subscribed: AtomicBool::new(false),
+            webxdc_flush_scheduled: AtomicBool::new(false),
             iroh: OnceCell::new(),
+            wkd_lookups: Mutex::new(HashMap::new()),
+            signing_backend: std::sync::RwLock::new(None),
+            chatlist_version: AtomicU64::new(u64::from(rand::random::<u32>()) << 32),
+            fresh_counts_version: AtomicU64::new(0),
+            fresh_counts: std::sync::Mutex::new(None),
+            battery_level: std::sync::Mutex::new(None),
+            maintenance: Mutex::new(None),
         };
 
         let ctx = Context {
@@ -559,6 +728,79 @@ pub async fn background_fetch(&self) -> Result<()> {
         Ok(())
     }
 
+    /// Destroys the account irrecoverably.
+    ///
+    /// Stops IO and any ongoing process,
+    /// then overwrites and deletes the database and all blob files.
+    /// If `delete_server_messages` is set, all messages on the server are deleted
+    /// and all folders are unsubscribed first.
+    /// This is only supported for chatmail accounts
+    /// because other mailboxes may contain messages not handled by Delta Chat.
+    ///
+    /// Emits [`EventType::AccountDestroyProgress`] while destroying the account,
+    /// with 1000 on success and 0 on failure.
+    /// If destroying fails while the database is still open,
+    /// nothing local is deleted and the account can still be used.
+    /// Otherwise the context must not be used afterwards, even if destroying failed.
+    pub async fn destroy_account(&self, delete_server_messages: bool) -> Result<()> {
+        let res = self.destroy_account_inner(delete_server_messages).await;
+        match &res {
+            Ok(()) => info!(self, "Account destroyed."),
+            Err(err) => error!(self, "Failed to destroy account: {err:#}."),
+        }
+        self.emit_event(EventType::AccountDestroyProgress(if res.is_ok() {
+            1000
+        } else {
+            0
+        }));
+        res
+    }
+
+    async fn destroy_account_inner(&self, delete_server_messages: bool) -> Result<()> {
+        if delete_server_messages {
+            ensure!(
+                self.is_chatmail().await?,
+                "Deleting server messages is only supported for chatmail accounts"
+            );
+        }
+        self.stop_io().await;
+        self.stop_ongoing().await;
+        self.emit_event(EventType::AccountDestroyProgress(100));
+
+        if delete_server_messages {
+            let mut connection = Imap::new_configured(self, channel::bounded(1).1).await?;
+            let mut session = connection.connect(self).await?;
+            session.delete_all_messages(self).await?;
+            session.logout().await.ok();
+        }
+        self.emit_event(EventType::AccountDestroyProgress(500));
+
+        self.sql.close().await;
+        let dbfile = self.get_dbfile();
+        for path in [
+            dbfile.to_path_buf(),
+            Self::derive_walfile(dbfile),
+            Self::derive_shmfile(dbfile),
+        ] {
+            tools::wipe_file(&path).await?;
+        }
+        self.emit_event(EventType::AccountDestroyProgress(700));
+
+        let blobdir = self.get_blobdir();
+        if blobdir.exists() {
+            let mut dir = tokio::fs::read_dir(blobdir).await?;
+            while let Some(entry) = dir.next_entry().await? {
+                if entry.file_type().await?.is_file() {
+                    tools::wipe_file(&entry.path()).await?;
+                }
+            }
+            tokio::fs::remove_dir_all(blobdir)
+                .await
+                .context("Failed to remove blobdir")?;
+        }
+        Ok(())
+    }
+
     pub(crate) async fn schedule_resync(&self) -> Result<()> {
         self.resync_request.store(true, Ordering::Relaxed);
         self.scheduler.interrupt_inbox().await;
@@ -579,12 +821,53 @@ pub fn get_dbfile(&self) -> &Path {
     }
 
     /// Returns blob directory path.
+    ///
+    /// Files in the blob directory are not encrypted,
+    /// even if the database is, see [`Context::open_encrypted`].
     pub fn get_blobdir(&self) -> &Path {
         self.blobdir.as_path()
     }
 
     /// Emits a single event.
+    ///
+    /// Identical consecutive errors are deduplicated, see [`EventType::ErrorRepeated`].
     pub fn emit_event(&self, event: EventType) {
+        if matches!(
+            event,
+            EventType::ChatlistChanged | EventType::ChatlistItemChanged { .. }
+        ) {
+            self.chatlist_version.fetch_add(1, Ordering::Relaxed);
+        }
+        if matches!(
+            event,
+            EventType::MsgsChanged { .. }
+                | EventType::IncomingMsg { .. }
+                | EventType::IncomingMsgBunch
+                | EventType::MsgsNoticed(_)
+                | EventType::MsgDeleted { .. }
+                | EventType::ChatModified(_)
+                | EventType::ContactsChanged(_)
+                | EventType::ChatlistChanged
+                | EventType::ChatlistItemChanged { .. }
+        ) {
+            self.fresh_counts_version.fetch_add(1, Ordering::Relaxed);
+        }
+        match event {
+            EventType::Error(msg) if dedups_errors() => {
+                for event in self.dedup_error(msg) {
+                    self.emit_event_inner(event);
+                }
+            }
+            event => {
+                for repeated_error in self.flush_repeated_errors() {
+                    self.emit_event_inner(repeated_error);
+                }
+                self.emit_event_inner(event);
+            }
+        }
+    }
+
+    fn emit_event_inner(&self, event: EventType) {
         {
             let lock = self.debug_logging.read().expect("RwLock is poisoned");
             if let Some(debug_logging) = &*lock {
@@ -806,8 +1089,18 @@ pub async fn get_info(&self) -> Result<BTreeMap<&'static str, String>> {
         );
         res.insert("is_configured", is_configured.to_string());
         res.insert("socks5_enabled", socks5_enabled.to_string());
+        res.insert(
+            "proxy_count",
+            self.get_proxy_urls().await?.len().to_string(),
+        );
         res.insert("entered_account_settings", l.to_string());
         res.insert("used_account_settings", l2.to_string());
+        res.insert(
+            "configured_server_source",
+            self.get_config(Config::ConfiguredServerSource)
+                .await?
+                .unwrap_or_else(|| unset.into()),
+        );
 
         if let Some(server_id) = &*self.server_id.read().await {
             res.insert("imap_server_id", format!("{server_id:?}"));
@@ -891,6 +1184,10 @@ pub async fn get_info(&self) -> Result<BTreeMap<&'static str, String>> {
             "media_quality",
             self.get_config_int(Config::MediaQuality).await?.to_string(),
         );
+        res.insert(
+            "imap_id",
+            self.get_config_int(Config::ImapId).await?.to_string(),
+        );
         res.insert(
             "delete_device_after",
             self.get_config_int(Config::DeleteDeviceAfter)
@@ -903,6 +1200,12 @@ pub async fn get_info(&self) -> Result<BTreeMap<&'static str, String>> {
                 .await?
                 .to_string(),
         );
+        res.insert(
+            "delete_for_all_window",
+            self.get_config_int(Config::DeleteForAllWindow)
+                .await?
+                .to_string(),
+        );
         res.insert(
             "delete_to_trash",
             self.get_config(Config::DeleteToTrash)
@@ -933,6 +1236,45 @@ pub async fn get_info(&self) -> Result<BTreeMap<&'static str, String>> {
                 .await?
                 .to_string(),
         );
+        res.insert(
+            "report_spam",
+            self.get_config_bool(Config::ReportSpam).await?.to_string(),
+        );
+        res.insert(
+            "persist_outbox",
+            self.get_config_bool(Config::PersistOutbox)
+                .await?
+                .to_string(),
+        );
+        res.insert(
+            "auto_backup_interval",
+            self.get_config_int(Config::AutoBackupInterval)
+                .await?
+                .to_string(),
+        );
+        res.insert(
+            "auto_backup_keep",
+            self.get_config_int(Config::AutoBackupKeep)
+                .await?
+                .to_string(),
+        );
+        res.insert(
+            "last_auto_backup",
+            self.get_config_int(Config::LastAutoBackup)
+                .await?
+                .to_string(),
+        );
+        res.insert(
+            "low_disk_space",
+            self.get_config_bool(Config::LowDiskSpace)
+                .await?
+                .to_string(),
+        );
+        res.insert(
+            "disk_space_available",
+            self.available_disk_space()
+                .map_or_else(|| "unknown".to_string(), |bytes| bytes.to_string()),
+        );
         res.insert(
             "authserv_id_candidates",
             self.get_config(Config::AuthservIdCandidates)
@@ -1141,8 +1483,67 @@ pub async fn draft_self_report(&self) -> Result<ChatId> {
         Ok(chat_id)
     }
 
+    /// Returns the numbers of fresh messages in unblocked chats,
+    /// e.g. to show a badge counter on the app icon.
+    ///
+    /// The counts are computed by a single query
+    /// and cached until an event indicates that they may have changed.
+    pub async fn get_fresh_counts(&self) -> Result<FreshCounts> {
+        let version = self.fresh_counts_version.load(Ordering::Relaxed);
+        let now = time();
+        if let Some(cache) = &*self.fresh_counts.lock().expect("Mutex is poisoned") {
+            let mute_expired = cache
+                .muted_until
+                .is_some_and(|muted_until| muted_until <= now);
+            if cache.version == version && !mute_expired {
+                return Ok(cache.counts);
+            }
+        }
+
+        let (total, unmuted, mentions, muted_until) = self
+            .sql
+            .query_row(
+                "SELECT COUNT(*),
+                        IFNULL(SUM(NOT(c.muted_until=-1 OR c.muted_until>?2)), 0),
+                        IFNULL(SUM(m.mentions_self=1), 0),
+                        MIN(CASE WHEN c.muted_until>?2 THEN c.muted_until END)
+                 FROM msgs m
+                 LEFT JOIN contacts ct
+                        ON m.from_id=ct.id
+                 LEFT JOIN chats c
+                        ON m.chat_id=c.id
+                 WHERE m.state=?1
+                   AND m.hidden=0
+                   AND m.chat_id>9
+                   AND ct.blocked=0
+                   AND c.blocked=0",
+                (MessageState::InFresh, now),
+                |row| {
+                    let total: usize = row.get(0)?;
+                    let unmuted: usize = row.get(1)?;
+                    let mentions: usize = row.get(2)?;
+                    let muted_until: Option<i64> = row.get(3)?;
+                    Ok((total, unmuted, mentions, muted_until))
+                },
+            )
+            .await?;
+        let counts = FreshCounts {
+            total,
+            unmuted,
+            mentions,
+        };
+        *self.fresh_counts.lock().expect("Mutex is poisoned") = Some(FreshCountsCache {
+            version,
+            muted_until,
+            counts,
+        });
+        Ok(counts)
+    }
+
     /// Get a list of fresh, unmuted messages in unblocked chats.
     ///
+    /// Messages mentioning us are returned even if the chat is muted.
+    ///
     /// The list starts with the most recent message
     /// and is typically used to show notifications.
     /// Moreover, the number of returned messages
@@ -1163,7 +1564,7 @@ pub async fn get_fresh_msgs(&self) -> Result<Vec<MsgId>> {
                     "   AND m.chat_id>9",
                     "   AND ct.blocked=0",
                     "   AND c.blocked=0",
-                    "   AND NOT(c.muted_until=-1 OR c.muted_until>?)",
+                    "   AND (NOT(c.muted_until=-1 OR c.muted_until>?) OR m.mentions_self=1)",
                     " ORDER BY m.timestamp DESC,m.id DESC;"
                 ),
                 (MessageState::InFresh, time()),
@@ -1255,15 +1656,21 @@ pub async fn wait_next_msgs(&self) -> Result<Vec<MsgId>> {
         Ok(list)
     }
 
-    /// Searches for messages containing the query string case-insensitively.
+    /// Searches for messages containing the query string case-insensitively
+    /// in the message text or the attachment filename.
     ///
     /// If `chat_id` is provided this searches only for messages in this chat, if `chat_id`
     /// is `None` this searches messages from all chats.
+    /// Results of the global search are sorted by relevance if the full-text index can be used,
+    /// see [`Context::get_msg_search_snippet`].
     pub async fn search_msgs(&self, chat_id: Option<ChatId>, query: &str) -> Result<Vec<MsgId>> {
         let real_query = query.trim().to_lowercase();
         if real_query.is_empty() {
             return Ok(Vec::new());
         }
+        if let Some(list) = search::search_msgs(self, chat_id, &real_query).await? {
+            return Ok(list);
+        }
         let str_like_in_text = format!("%{real_query}%");
 
         let list = if let Some(chat_id) = chat_id {
@@ -1364,6 +1771,19 @@ pub(crate) async fn should_delete_to_trash(&self) -> Result<bool> {
         Ok(false)
     }
 
+    /// Returns the address accepting abuse reports, if the provider is known to accept them.
+    pub(crate) async fn get_spam_report_addr(&self) -> Result<Option<String>> {
+        if let Some(addr) = self.get_config(Config::SpamReportAddr).await? {
+            if !addr.is_empty() {
+                return Ok(Some(addr));
+            }
+        }
+        if let Some(provider) = self.get_configured_provider().await? {
+            return Ok(provider.opt.abuse_report_addr.map(str::to_string));
+        }
+        Ok(None)
+    }
+
     /// Returns `target` for deleted messages as per `imap` table. Empty string means "delete w/o
     /// moving to trash".
     pub(crate) async fn get_delete_msgs_target(&self) -> Result<String> {
@@ -1388,6 +1808,13 @@ pub(crate) fn derive_walfile(dbfile: &Path) -> PathBuf {
         wal_fname.push("-wal");
         dbfile.with_file_name(wal_fname)
     }
+
+    pub(crate) fn derive_shmfile(dbfile: &Path) -> PathBuf {
+        let mut shm_fname = OsString::new();
+        shm_fname.push(dbfile.file_name().unwrap_or_default());
+        shm_fname.push("-shm");
+        dbfile.with_file_name(shm_fname)
+    }
 }
 
 /// Returns core version as a string.
@@ -1689,6 +2116,7 @@ async fn test_get_info_completeness() {
             "socks5_port",
             "socks5_user",
             "socks5_password",
+            "proxy_urls",
             "key_id",
             "webxdc_integration",
         ];
@@ -1743,7 +2171,7 @@ async fn test_search_msgs() -> Result<()> {
         let res = alice.search_msgs(None, "bar").await?;
         assert_eq!(res.len(), 2);
 
-        // Message added later is returned first.
+        // With equal relevance, message added later is returned first.
         assert_eq!(res.first(), Some(&msg2.id));
         assert_eq!(res.get(1), Some(&msg1.id));
 
@@ -1885,6 +2313,45 @@ async fn test_check_passphrase() -> Result<()> {
         Ok(())
     }
 
+    #[tokio::test(flavor = "multi_thread", worker_threads = 2)]
+    async fn test_open_encrypted() -> Result<()> {
+        let dir = tempdir()?;
+        let dbfile = dir.path().join("db.sqlite");
+
+        let context = Context::open_encrypted(
+            &dbfile,
+            1,
+            Events::new(),
+            StockStrings::new(),
+            "foo".to_string(),
+        )
+        .await?;
+        assert!(context.is_open().await);
+        assert!(context.is_encrypted().await?);
+        drop(context);
+
+        let context = Context::new(&dbfile, 2, Events::new(), StockStrings::new()).await?;
+        assert!(!context.is_open().await);
+        assert!(context.is_encrypted().await?);
+        drop(context);
+
+        assert!(Context::open_encrypted(
+            &dbfile,
+            3,
+            Events::new(),
+            StockStrings::new(),
+            "bar".to_string()
+        )
+        .await
+        .is_err());
+
+        let dbfile = dir.path().join("plain.sqlite");
+        let context = Context::new(&dbfile, 4, Events::new(), StockStrings::new()).await?;
+        assert!(!context.is_encrypted().await?);
+
+        Ok(())
+    }
+
     #[tokio::test(flavor = "multi_thread", worker_threads = 2)]
     async fn test_context_change_passphrase() -> Result<()> {
         let dir = tempdir()?;
@@ -1915,6 +2382,36 @@ async fn test_context_change_passphrase() -> Result<()> {
         Ok(())
     }
 
+    #[tokio::test(flavor = "multi_thread", worker_threads = 2)]
+    async fn test_maintenance() -> Result<()> {
+        let t = TestContext::new_alice().await;
+        assert!(t.exit_maintenance(String::new()).await.is_err());
+
+        t.enter_maintenance().await?;
+        assert!(t.is_in_maintenance().await);
+        assert!(!t.is_open().await);
+        assert!(t.get_config(Config::Addr).await.is_err());
+        assert!(t.enter_maintenance().await.is_err());
+
+        // The database file can be copied and opened by another context.
+        let dir = tempdir()?;
+        let copy = dir.path().join("copy.sqlite");
+        tokio::fs::copy(t.get_dbfile(), &copy).await?;
+        let copied = Context::new(&copy, 2, Events::new(), StockStrings::new()).await?;
+        assert_eq!(
+            copied.get_config(Config::Addr).await?.as_deref(),
+            Some("alice@example.org")
+        );
+
+        assert!(t.exit_maintenance(String::new()).await?);
+        assert!(!t.is_in_maintenance().await);
+        assert_eq!(
+            t.get_config(Config::Addr).await?.as_deref(),
+            Some("alice@example.org")
+        );
+        Ok(())
+    }
+
     #[tokio::test(flavor = "multi_thread", worker_threads = 2)]
     async fn test_ongoing() -> Result<()> {
         let context = TestContext::new().await;
@@ -38,11 +38,15 @@ pub fn try_decrypt(
     )
 }
 
+/// Prepares decryption of `mail`, applying its Autocrypt header to the sender's peerstate.
+///
+/// If `imported` is set, the Autocrypt header is not applied and the peerstate is only loaded.
 pub(crate) async fn prepare_decryption(
     context: &Context,
     mail: &ParsedMail<'_>,
     from: &str,
     message_time: i64,
+    imported: bool,
 ) -> Result<DecryptionInfo> {
     if mail.headers.get_header(HeaderDef::ListPost).is_some() {
         if mail.headers.get_header(HeaderDef::Autocrypt).is_some() {
@@ -57,7 +61,11 @@ pub(crate) async fn prepare_decryption(
             autocrypt_header: None,
             peerstate: None,
             message_time,
-            dkim_results: DkimResults { dkim_passed: false },
+            dkim_results: DkimResults {
+                dkim_passed: false,
+                dkim_failed: false,
+                auth_results: Default::default(),
+            },
         });
     }
 
@@ -84,14 +92,19 @@ pub(crate) async fn prepare_decryption(
 
     let dkim_results = handle_authres(context, mail, from).await?;
     let allow_aeap = get_encrypted_mime(mail).is_some();
-    let peerstate = get_autocrypt_peerstate(
-        context,
-        from,
-        autocrypt_header.as_ref(),
-        message_time,
-        allow_aeap,
-    )
-    .await?;
+    let peerstate = match imported {
+        true => Peerstate::from_addr(context, from).await?,
+        false => {
+            get_autocrypt_peerstate(
+                context,
+                from,
+                autocrypt_header.as_ref(),
+                message_time,
+                allow_aeap,
+            )
+            .await?
+        }
+    };
 
     Ok(DecryptionInfo {
         from: from.to_string(),
@@ -119,7 +132,7 @@ pub struct DecryptionInfo {
 }
 
 /// Returns a reference to the encrypted payload of a message.
-fn get_encrypted_mime<'a, 'b>(mail: &'a ParsedMail<'b>) -> Option<&'a ParsedMail<'b>> {
+pub(crate) fn get_encrypted_mime<'a, 'b>(mail: &'a ParsedMail<'b>) -> Option<&'a ParsedMail<'b>> {
     get_autocrypt_mime(mail)
         .or_else(|| get_mixed_up_mime(mail))
         .or_else(|| get_attachment_mime(mail))
@@ -0,0 +1,143 @@
+//! # Guard against running out of disk space.
+//!
+//! Running the database into `ENOSPC` risks corrupting it,
+//! so large blobs and backups are only written
+//! if enough disk space is left afterwards.
+//! If the disk space gets low, downloading attachments is paused.
+
+use std::path::Path;
+
+use anyhow::Result;
+
+use crate::chat::add_device_msg_with_importance;
+use crate::config::Config;
+use crate::context::Context;
+use crate::message::{Message, Viewtype};
+use crate::stock_str;
+
+/// Free disk space that is always kept available for the database.
+pub const MIN_FREE_DISK_SPACE: u64 = 50 * 1024 * 1024;
+
+/// Below this free disk space, attachments are not downloaded automatically
+/// and the user is warned.
+pub const LOW_DISK_SPACE: u64 = 200 * 1024 * 1024;
+
+/// Error returned if writing a blob or backup would leave
+/// less than [`MIN_FREE_DISK_SPACE`] on the disk.
+#[derive(Debug, thiserror::Error)]
+#[error("Not enough disk space: {required} bytes required, {available} bytes available")]
+pub struct InsufficientDiskSpace {
+    /// Number of bytes that were about to be written.
+    pub required: u64,
+
+    /// Number of bytes available on the disk.
+    pub available: u64,
+}
+
+/// Returns the number of bytes available to unprivileged users
+/// on the filesystem containing `path`,
+/// or `None` if this cannot be determined.
+#[cfg(unix)]
+fn available_space(path: &Path) -> Option<u64> {
+    let stat = rustix::fs::statvfs(path).ok()?;
+    Some(stat.f_bavail.saturating_mul(stat.f_frsize))
+}
+
+#[cfg(not(unix))]
+fn available_space(_path: &Path) -> Option<u64> {
+    None
+}
+
+impl Context {
+    /// Returns the free disk space in the blob directory,
+    /// `None` if it cannot be determined on this platform.
+    pub(crate) fn available_disk_space(&self) -> Option<u64> {
+        available_space(self.get_blobdir())
+    }
+
+    /// Returns true if attachments should not be downloaded
+    /// because the disk is almost full.
+    pub(crate) async fn is_low_disk_space(&self) -> Result<bool> {
+        let Some(available) = self.available_disk_space() else {
+            return Ok(false);
+        };
+        if available >= LOW_DISK_SPACE {
+            if self.get_config_bool(Config::LowDiskSpace).await? {
+                info!(self, "Enough disk space again, resuming downloads.");
+                self.set_config_internal(Config::LowDiskSpace, None).await?;
+            }
+            return Ok(false);
+        }
+        self.warn_low_disk_space(available).await?;
+        Ok(true)
+    }
+
+    /// Fails with [`InsufficientDiskSpace`] if writing `required` bytes to the blob directory
+    /// would leave less than [`MIN_FREE_DISK_SPACE`] on the disk.
+    pub(crate) async fn ensure_disk_space(&self, required: u64) -> Result<()> {
+        self.ensure_disk_space_in(self.get_blobdir(), required)
+            .await
+    }
+
+    /// Like [`Context::ensure_disk_space`], but for writing to `dir`,
+    /// e.g. a backup directory on a different filesystem.
+    pub(crate) async fn ensure_disk_space_in(&self, dir: &Path, required: u64) -> Result<()> {
+        let Some(available) = available_space(dir) else {
+            return Ok(());
+        };
+        if available < required.saturating_add(MIN_FREE_DISK_SPACE) {
+            warn!(
+                self,
+                "Refusing to write {required} bytes, only {available} bytes of disk space left."
+            );
+            self.warn_low_disk_space(available).await?;
+            return Err(InsufficientDiskSpace {
+                required,
+                available,
+            }
+            .into());
+        }
+        Ok(())
+    }
+
+    /// Adds a device message about low disk space
+    /// unless the user was already warned.
+    async fn warn_low_disk_space(&self, available: u64) -> Result<()> {
+        if self.get_config_bool(Config::LowDiskSpace).await? {
+            return Ok(());
+        }
+        self.set_config_internal(Config::LowDiskSpace, Some("1"))
+            .await?;
+        let mut msg = Message::new(Viewtype::Text);
+        msg.text = stock_str::low_disk_space(self, available).await;
+        add_device_msg_with_importance(self, None, Some(&mut msg), true).await?;
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_utils::TestContext;
+
+    #[tokio::test(flavor = "multi_thread", worker_threads = 2)]
+    async fn test_ensure_disk_space() -> Result<()> {
+        let t = TestContext::new_alice().await;
+        let Some(available) = t.available_disk_space() else {
+            return Ok(());
+        };
+
+        t.ensure_disk_space(0).await?;
+        let err = t.ensure_disk_space(available).await.unwrap_err();
+        let err = err.downcast_ref::<InsufficientDiskSpace>().unwrap();
+        assert_eq!(err.required, available);
+        assert!(t.get_config_bool(Config::LowDiskSpace).await?);
+
+        // The user is warned only once.
+        let device_msg = t.get_last_msg().await;
+        assert!(device_msg.text.contains("running out of storage"));
+        t.ensure_disk_space(available).await.unwrap_err();
+        assert_eq!(t.get_last_msg().await.id, device_msg.id);
+        Ok(())
+    }
+}
@@ -67,6 +67,10 @@ pub enum DownloadState {
 impl Context {
     // Returns validated download limit or `None` for "no limit".
     pub(crate) async fn download_limit(&self) -> Result<Option<u32>> {
+        if self.is_low_disk_space().await? {
+            // Only download small messages, attachments can be downloaded later.
+            return Ok(Some(MIN_DOWNLOAD_LIMIT));
+        }
         let download_limit = self.get_config_int(Config::DownloadLimit).await?;
         if download_limit <= 0 {
             Ok(None)
@@ -191,7 +195,7 @@ async fn fetch_single_msg(
 
         let mut uid_message_ids: BTreeMap<u32, String> = BTreeMap::new();
         uid_message_ids.insert(uid, rfc724_mid);
-        let (last_uid, _received) = self
+        let (last_uid, _received, _uid_not_received) = self
             .fetch_many_msgs(
                 context,
                 folder,
@@ -6,7 +6,7 @@
 use crate::aheader::{Aheader, EncryptPreference};
 use crate::config::Config;
 use crate::context::Context;
-use crate::key::{load_self_public_key, load_self_secret_key, SignedPublicKey};
+use crate::key::{load_self_public_key, load_self_signing_key, SignedPublicKey};
 use crate::peerstate::Peerstate;
 use crate::pgp;
 
@@ -47,7 +47,8 @@ pub fn get_aheader(&self) -> Aheader {
     /// `e2ee_guaranteed` should be set to true for replies to encrypted messages (as required by
     /// Autocrypt Level 1, version 1.1) and for messages sent in protected groups.
     ///
-    /// Returns an error if `e2ee_guaranteed` is true, but one or more keys are missing.
+    /// Returns an error if `e2ee_guaranteed` is true, but one or more keys are missing
+    /// or cannot be used for encryption.
     pub fn should_encrypt(
         &self,
         context: &Context,
@@ -61,6 +62,20 @@ pub fn should_encrypt(
         };
         for (peerstate, addr) in peerstates {
             match peerstate {
+                Some(peerstate)
+                    if ![peerstate.peek_key(false), peerstate.peek_key(true)]
+                        .into_iter()
+                        .flatten()
+                        .any(pgp::has_encryption_key) =>
+                {
+                    let msg = format!("Key of {addr:?} has no encryption subkey, cannot encrypt");
+                    if e2ee_guaranteed {
+                        return Err(format_err!("{msg}"));
+                    } else {
+                        info!(context, "{msg}.");
+                        return Ok(false);
+                    }
+                }
                 Some(peerstate) => {
                     let prefer_encrypt = peerstate.prefer_encrypt;
                     info!(context, "Peerstate for {addr:?} is {prefer_encrypt}.");
@@ -132,11 +147,13 @@ pub async fn encrypt(
             }
         }
 
-        let sign_key = load_self_secret_key(context).await?;
+        let sign_key = load_self_signing_key(context).await?;
 
         let raw_message = mail_to_encrypt.build().as_string().into_bytes();
 
         let ctext = pgp::pk_encrypt(&raw_message, keyring, Some(sign_key), compress).await?;
+        #[cfg(feature = "pq-hybrid")]
+        let ctext = crate::pq::maybe_wrap(context, ctext, &peerstates).await?;
 
         Ok(ctext)
     }
@@ -148,7 +165,7 @@ pub async fn sign(
         context: &Context,
         mail: lettre_email::PartBuilder,
     ) -> Result<(lettre_email::MimeMessage, String)> {
-        let sign_key = load_self_secret_key(context).await?;
+        let sign_key = load_self_signing_key(context).await?;
         let mime_message = mail.build();
         let signature = pgp::pk_calc_signature(mime_message.as_string().as_bytes(), &sign_key)?;
         Ok((mime_message, signature))
@@ -315,6 +332,7 @@ fn new_peerstates(prefer_encrypt: EncryptPreference) -> Vec<(Option<Peerstate>,
             secondary_verifier: None,
             backward_verified_key_id: None,
             fingerprint_changed: false,
+            old_fingerprint: None,
         };
         vec![(Some(peerstate), addr.to_string())]
     }
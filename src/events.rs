@@ -1,6 +1,7 @@
 //! # Events specification.
 
 use anyhow::Result;
+use serde::{Deserialize, Serialize};
 use tokio::sync::Mutex;
 
 pub(crate) mod chatlist_events;
@@ -116,7 +117,7 @@ pub fn try_recv(&self) -> Result<Event> {
 /// documented in `deltachat.h`.
 ///
 /// [`Context`]: crate::context::Context
-#[derive(Debug, Clone, PartialEq, Eq)]
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
 pub struct Event {
     /// The ID of the [`Context`] which emitted this event.
     ///
@@ -130,3 +131,52 @@ pub struct Event {
     /// These are documented in `deltachat.h` as the `DC_EVENT_*` constants.
     pub typ: EventType,
 }
+
+#[cfg(test)]
+mod tests {
+    use serde_json::json;
+
+    use super::*;
+    use crate::chat::ChatId;
+    use crate::message::MsgId;
+
+    /// Tests that the JSON representation of events does not change.
+    ///
+    /// Bridges and bots rely on the serialization of events
+    /// and of the `*Info` types of contacts, chats and messages,
+    /// so each of them has a test like this one comparing against a fixed JSON value.
+    #[test]
+    fn test_event_json_schema() -> Result<()> {
+        let event = Event {
+            id: 1,
+            typ: EventType::IncomingMsg {
+                chat_id: ChatId::new(10),
+                msg_id: MsgId::new(11),
+            },
+        };
+        let value = serde_json::to_value(&event)?;
+        assert_eq!(
+            value,
+            json!({"id": 1, "typ": {"IncomingMsg": {"chat_id": 10, "msg_id": 11}}})
+        );
+        assert_eq!(serde_json::from_value::<Event>(value)?, event);
+
+        let event = Event {
+            id: 2,
+            typ: EventType::Info("Hello".to_string()),
+        };
+        let value = serde_json::to_value(&event)?;
+        assert_eq!(value, json!({"id": 2, "typ": {"Info": "Hello"}}));
+        assert_eq!(serde_json::from_value::<Event>(value)?, event);
+
+        let event = Event {
+            id: 3,
+            typ: EventType::IncomingMsgBunch,
+        };
+        assert_eq!(
+            serde_json::to_value(&event)?,
+            json!({"id": 3, "typ": "IncomingMsgBunch"})
+        );
+        Ok(())
+    }
+}
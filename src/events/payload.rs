@@ -3,11 +3,11 @@
 use serde::{Deserialize, Serialize};
 use std::path::PathBuf;
 
-use crate::chat::ChatId;
-use crate::config::Config;
+use crate::chat::{ChatId, ProtectionStatus};
 use crate::contact::ContactId;
 use crate::ephemeral::Timer as EphemeralTimer;
 use crate::message::MsgId;
+use crate::securejoin::SecurejoinFailure;
 use crate::webxdc::StatusUpdateSerial;
 
 /// Event payload.
@@ -61,6 +61,25 @@ pub enum EventType {
     /// in a messasge box then.
     Error(String),
 
+    /// An error occurred repeatedly.
+    ///
+    /// Identical errors of background tasks are emitted as [`EventType::Error`] only once per minute,
+    /// the repetitions are summarized by this event.
+    /// It should be handled like [`EventType::Error`].
+    ErrorRepeated {
+        /// Error message.
+        msg: String,
+
+        /// Number of occurrences since the error was last reported.
+        count: usize,
+
+        /// Timestamp of the first summarized occurrence.
+        first_timestamp: i64,
+
+        /// Timestamp of the last summarized occurrence.
+        last_timestamp: i64,
+    },
+
     /// An action cannot be performed because the user is not in the group.
     /// Reported eg. after a call to
     /// dc_set_chat_name(), dc_set_chat_profile_image(),
@@ -182,11 +201,34 @@ pub enum EventType {
         timer: EphemeralTimer,
     },
 
+    /// Chat protection status changed,
+    /// e.g. a 1:1 chat became protected after a successful Secure-Join
+    /// or the protection was broken by a message from an unverified key.
+    ///
+    /// [`EventType::ChatModified`] is emitted as well.
+    ChatProtectionChanged {
+        /// Chat ID.
+        chat_id: ChatId,
+
+        /// New protection status.
+        protection: ProtectionStatus,
+    },
+
     /// Contact(s) created, renamed, blocked, deleted or changed their "recently seen" status.
     ///
     /// @param data1 (int) If set, this is the contact_id of an added contact that should be selected.
     ContactsChanged(Option<ContactId>),
 
+    /// A key of a contact was discovered outside of Autocrypt headers,
+    /// e.g. in the Web Key Directory of the contact's domain.
+    ContactKeyDiscovered {
+        /// ID of the contact the key belongs to.
+        contact_id: ContactId,
+
+        /// Where the key was discovered, e.g. the URL of the Web Key Directory.
+        source: String,
+    },
+
     /// Location of one or more contact has changed.
     ///
     /// @param data1 (u32) contact_id of the contact for which the location has changed.
@@ -211,6 +253,15 @@ pub enum EventType {
     /// @param data2 0
     ImexProgress(usize),
 
+    /// Inform about the progress of destroying the account
+    /// started by [`Context::destroy_account`].
+    ///
+    /// @param data1 (usize) 0=error, 1-999=progress in permille, 1000=success and done
+    /// @param data2 0
+    ///
+    /// [`Context::destroy_account`]: crate::context::Context::destroy_account
+    AccountDestroyProgress(usize),
+
     /// A file has been exported. A file has been written by imex().
     /// This event may be sent multiple times by a single call to imex().
     ///
@@ -220,6 +271,18 @@ pub enum EventType {
     /// @param data2 0
     ImexFileWritten(PathBuf),
 
+    /// Inform about the progress of a key rotation started by [`Context::rotate_key`].
+    ///
+    /// [`Context::rotate_key`]: crate::context::Context::rotate_key
+    KeyRotationProgress {
+        /// 0=error, 1-999=progress in permille, 1000=success and done.
+        progress: usize,
+
+        /// Number of contacts still using the old key.
+        /// Only set when rotation is done, 0 otherwise.
+        contacts_with_old_key: usize,
+    },
+
     /// Progress information of a secure-join handshake from the view of the inviter
     /// (Alice, the person who shows the QR code).
     ///
@@ -230,6 +293,7 @@ pub enum EventType {
         contact_id: ContactId,
 
         /// Progress as:
+        /// 0=Error, see [`EventType::SecurejoinFailed`] for the reason.
         /// 300=vg-/vc-request received, typically shown as "bob@addr joins".
         /// 600=vg-/vc-request-with-auth received, vg-member-added/vc-contact-confirm sent, typically shown as "bob@addr verified".
         /// 800=contact added to chat, shown as "bob@addr securely joined GROUP". Only for the verified-group-protocol.
@@ -246,18 +310,42 @@ pub enum EventType {
         contact_id: ContactId,
 
         /// Progress as:
+        /// 0=Error, see [`EventType::SecurejoinFailed`] for the reason.
         /// 400=vg-/vc-request-with-auth sent, typically shown as "alice@addr verified, introducing myself."
         /// (Bob has verified alice and waits until Alice does the same for him)
         /// 1000=vg-member-added/vc-contact-confirm received
         progress: usize,
     },
 
+    /// A secure-join handshake failed, either on the inviter or on the joiner side.
+    ///
+    /// Except for [`SecurejoinFailure::Timeout`], this follows
+    /// [`EventType::SecurejoinInviterProgress`] or [`EventType::SecurejoinJoinerProgress`]
+    /// with progress 0.
+    SecurejoinFailed {
+        /// ID of the contact on the other side of the handshake.
+        contact_id: ContactId,
+
+        /// Reason of the failure.
+        reason: SecurejoinFailure,
+    },
+
     /// The connectivity to the server changed.
     /// This means that you should refresh the connectivity view
     /// and possibly the connectivtiy HTML; see dc_get_connectivity() and
     /// dc_get_connectivity_html() for details.
     ConnectivityChanged,
 
+    /// Connecting through the active proxy failed
+    /// and the next proxy of the list is used now.
+    ProxyChanged {
+        /// Hostname of the new proxy.
+        host: String,
+
+        /// Port of the new proxy.
+        port: u16,
+    },
+
     /// The user's avatar changed.
     /// Deprecated by `ConfigSynced`.
     SelfavatarChanged,
@@ -265,9 +353,16 @@ pub enum EventType {
     /// A multi-device synced config value changed. Maybe the app needs to refresh smth. For
     /// uniformity this is emitted on the source device too. The value isn't here, otherwise it
     /// would be logged which might not be good for privacy.
+    ///
+    /// This is also emitted when an ui-specific value set by [`Context::set_ui_config`] changes.
+    ///
+    /// [`Context::set_ui_config`]: crate::context::Context::set_ui_config
     ConfigSynced {
-        /// Configuration key.
-        key: Config,
+        /// Configuration key, the string representation of a [`Config`]
+        /// or an ui-specific key starting with `ui.`.
+        ///
+        /// [`Config`]: crate::config::Config
+        key: String,
     },
 
     /// Webxdc status update received.
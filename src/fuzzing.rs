@@ -10,3 +10,43 @@
 pub fn simplify(input: String, is_chat_message: bool) {
     crate::simplify::simplify(input, is_chat_message);
 }
+
+/// Fuzzing target for the QR code parser.
+///
+/// Parses the QR code without resolving contacts,
+/// so no account is needed.
+#[cfg(fuzzing)]
+pub fn parse_qr(input: &str) {
+    crate::qr::parse_qr(input).ok();
+}
+
+/// Fuzzing target for the Autocrypt header parser.
+#[cfg(fuzzing)]
+pub fn parse_aheader(input: &str) {
+    input.parse::<crate::aheader::Aheader>().ok();
+}
+
+/// Fuzzing target for the parts of the MIME parser which do not need an account.
+///
+/// Parses the address headers, Message-ID lists, `Received` headers
+/// and disposition and delivery status reports.
+#[cfg(fuzzing)]
+pub fn parse_mime(input: &[u8]) {
+    use mailparse::MailHeaderMap;
+
+    use crate::headerdef::HeaderDef;
+    use crate::mimeparser;
+
+    let Ok(mail) = mailparse::parse_mail(input) else {
+        return;
+    };
+    mimeparser::get_from(&mail.headers);
+    mimeparser::get_recipients(&mail.headers);
+    mimeparser::get_list_post(&mail.headers);
+    if let Some(references) = mail.headers.get_header_value(HeaderDef::References) {
+        mimeparser::parse_message_ids(&references);
+    }
+    crate::tools::parse_receive_headers(&mail.get_headers());
+    mimeparser::parse_mdn_report(&mail).ok();
+    mimeparser::parse_delivery_status(&mail).ok();
+}
@@ -52,6 +52,7 @@ pub enum HeaderDef {
 
     ContentType,
     ContentId,
+    ContentDescription,
     ChatVersion,
     ChatGroupId,
     ChatGroupName,
@@ -70,6 +71,45 @@ pub enum HeaderDef {
     ChatDispositionNotificationTo,
     ChatWebrtcRoom,
 
+    /// Base64-encoded JSON list of commands advertised by a bot.
+    ChatBotCommands,
+
+    /// Base64-encoded JSON description of a poll.
+    ChatPoll,
+
+    /// Comma-separated indices of the poll options voted for.
+    ChatPollVote,
+
+    /// Closes the poll the message replies to.
+    ChatPollClose,
+
+    /// Reopens the closed poll the message replies to.
+    ChatPollReopen,
+
+    /// Message-ID of the message whose text is replaced by the text of this message.
+    ChatEdit,
+
+    /// Space-separated Message-IDs of the messages to delete for everyone.
+    ChatDelete,
+
+    /// Contacts mentioned in the message, see [crate::mention].
+    ChatMentions,
+
+    /// The attachment is shown only once, see [crate::message::Message::set_view_once].
+    ChatViewOnce,
+
+    /// Message-ID of the message pinned or unpinned by the message.
+    ChatPinnedMessage,
+
+    /// Message-ID of the root message of the topic thread.
+    ChatThreadRoot,
+
+    /// Base64-encoded ML-KEM encapsulation key for post-quantum hybrid encryption.
+    ChatPqKey,
+
+    /// Message-ID of the unsent message stored in an outbox draft.
+    ChatOutboxId,
+
     /// [Autocrypt](https://autocrypt.org/) header.
     Autocrypt,
     AutocryptSetupMessage,
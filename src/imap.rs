@@ -27,9 +27,10 @@
 use crate::chat::{self, ChatId, ChatIdBlocked};
 use crate::chatlist_events;
 use crate::config::Config;
-use crate::constants::{self, Blocked, Chattype, ShowEmails};
+use crate::constants::{self, Blocked, Chattype, ImapId, ShowEmails};
 use crate::contact::{Contact, ContactId, Modifier, Origin};
 use crate::context::Context;
+use crate::disk_space::InsufficientDiskSpace;
 use crate::events::EventType;
 use crate::headerdef::{HeaderDef, HeaderDefMap};
 use crate::login_param::{CertificateChecks, LoginParam, ServerLoginParam};
@@ -40,7 +41,7 @@
     from_field_to_contact_id, get_prefetch_parent_message, receive_imf_inner, ReceivedMsg,
 };
 use crate::scheduler::connectivity::ConnectivityStore;
-use crate::socks::Socks5Config;
+use crate::socks::{is_proxy_error, Socks5Config};
 use crate::sql;
 use crate::stock_str;
 use crate::tools::{self, create_id, duration_to_str};
@@ -48,6 +49,7 @@
 pub(crate) mod capabilities;
 mod client;
 mod idle;
+pub(crate) mod outbox;
 pub mod scan_folders;
 pub mod select_folder;
 pub(crate) mod session;
@@ -351,13 +353,28 @@ pub(crate) async fn connect(&mut self, context: &Context) -> Result<Session> {
         )
         .await;
 
-        let client = connection_res?;
+        let client = match connection_res {
+            Ok(client) => client,
+            Err(err) => {
+                if let Some(socks5_config) = &self.socks5_config {
+                    if is_proxy_error(&err) {
+                        if let Err(err) = context.switch_proxy_after_failure(socks5_config).await {
+                            warn!(context, "Failed to switch proxy: {err:#}.");
+                        }
+                        self.socks5_config = Socks5Config::from_database(context).await?;
+                    }
+                }
+                return Err(err);
+            }
+        };
         self.conn_backoff_ms = BACKOFF_MIN_MS;
         self.ratelimit.send();
 
         let imap_user: &str = self.lp.user.as_ref();
         let imap_pw: &str = self.lp.password.as_ref();
         let oauth2 = self.lp.oauth2;
+        let imap_id =
+            ImapId::from_i32(context.get_config_int(Config::ImapId).await?).unwrap_or_default();
 
         let login_res = if oauth2 {
             info!(context, "Logging into IMAP server with OAuth 2");
@@ -370,17 +387,19 @@ pub(crate) async fn connect(&mut self, context: &Context) -> Result<Session> {
                 user: imap_user.into(),
                 access_token: token,
             };
-            client.authenticate("XOAUTH2", auth).await
+            client.authenticate("XOAUTH2", auth, imap_id).await
         } else {
             info!(context, "Logging into IMAP server with LOGIN");
-            client.login(imap_user, imap_pw).await
+            client.login(imap_user, imap_pw, imap_id).await
         };
 
         match login_res {
             Ok(session) => {
                 // Store server ID in the context to display in account info.
-                let mut lock = context.server_id.write().await;
-                lock.clone_from(&session.capabilities.server_id);
+                if *context.server_id.read().await != session.capabilities.server_id {
+                    let mut lock = context.server_id.write().await;
+                    lock.clone_from(&session.capabilities.server_id);
+                }
 
                 self.login_failed_once = false;
                 context.emit_event(EventType::ImapConnected(format!(
@@ -531,6 +550,19 @@ pub(crate) async fn fetch_new_messages(
         };
         let read_cnt = msgs.len();
 
+        // With OBJECTID, messages are identified by their `EMAILID`
+        // which stays the same when the message is moved to another folder.
+        let mut objectids = BTreeMap::new();
+        if session.can_objectid() && !msgs.is_empty() {
+            let uids: Vec<u32> = msgs.iter().map(|(uid, _)| *uid).collect();
+            for (_, uid_set) in build_sequence_sets(&uids)? {
+                match session.fetch_objectids(&uid_set).await {
+                    Ok(res) => objectids.extend(res),
+                    Err(err) => warn!(context, "Failed to fetch OBJECTIDs: {err:#}."),
+                }
+            }
+        }
+
         let download_limit = context.download_limit().await?;
         let mut uids_fetch = Vec::<(_, bool /* partially? */)>::with_capacity(msgs.len() + 1);
         let mut uid_message_ids = BTreeMap::new();
@@ -547,7 +579,23 @@ pub(crate) async fn fetch_new_messages(
                 }
             };
 
-            let message_id = prefetch_get_message_id(&headers);
+            let ids = objectids.get(&uid);
+            let emailid = ids.map(|ids| ids.emailid.as_str()).unwrap_or_default();
+            let threadid = ids.map(|ids| ids.threadid.as_str()).unwrap_or_default();
+            let message_id = match prefetch_get_message_id(&headers) {
+                Some(message_id) => Some(message_id),
+                // Reuse the Message-ID assigned when the message was seen in another folder.
+                None if !emailid.is_empty() => {
+                    context
+                        .sql
+                        .query_get_value(
+                            "SELECT rfc724_mid FROM imap WHERE emailid=? LIMIT 1",
+                            (emailid,),
+                        )
+                        .await?
+                }
+                None => None,
+            };
 
             // Determine the target folder where the message should be moved to.
             //
@@ -587,8 +635,8 @@ pub(crate) async fn fetch_new_messages(
                 } else if context
                     .sql
                     .exists(
-                        "SELECT COUNT (*) FROM imap WHERE rfc724_mid=?",
-                        (message_id,),
+                        "SELECT COUNT (*) FROM imap WHERE rfc724_mid=? OR (emailid!='' AND emailid=?)",
+                        (message_id, emailid),
                     )
                     .await?
                 {
@@ -619,12 +667,22 @@ pub(crate) async fn fetch_new_messages(
             context
                 .sql
                 .execute(
-                    "INSERT INTO imap (rfc724_mid, folder, uid, uidvalidity, target)
-                       VALUES         (?1,         ?2,     ?3,  ?4,          ?5)
+                    "INSERT INTO imap (rfc724_mid, folder, uid, uidvalidity, target, emailid, threadid)
+                       VALUES         (?1,         ?2,     ?3,  ?4,          ?5,     ?6,      ?7)
                        ON CONFLICT(folder, uid, uidvalidity)
                        DO UPDATE SET rfc724_mid=excluded.rfc724_mid,
-                                     target=excluded.target",
-                    (&message_id, &folder, uid, uid_validity, target),
+                                     target=excluded.target,
+                                     emailid=excluded.emailid,
+                                     threadid=excluded.threadid",
+                    (
+                        &message_id,
+                        &folder,
+                        uid,
+                        uid_validity,
+                        target,
+                        emailid,
+                        threadid,
+                    ),
                 )
                 .await?;
 
@@ -670,26 +728,34 @@ pub(crate) async fn fetch_new_messages(
         let mut received_msgs = Vec::with_capacity(uids_fetch.len());
         let mut uids_fetch_in_batch = Vec::with_capacity(max(uids_fetch.len(), 1));
         let mut fetch_partially = false;
+        let mut uid_out_of_disk_space = None;
         uids_fetch.push((0, !uids_fetch.last().unwrap_or(&(0, false)).1));
         for (uid, fp) in uids_fetch {
             if fp != fetch_partially {
-                let (largest_uid_fetched_in_batch, received_msgs_in_batch) = session
-                    .fetch_many_msgs(
-                        context,
-                        folder,
-                        uid_validity,
-                        uids_fetch_in_batch.split_off(0),
-                        &uid_message_ids,
-                        fetch_partially,
-                        fetch_existing_msgs,
-                    )
-                    .await
-                    .context("fetch_many_msgs")?;
+                let (largest_uid_fetched_in_batch, received_msgs_in_batch, uid_not_received) =
+                    session
+                        .fetch_many_msgs(
+                            context,
+                            folder,
+                            uid_validity,
+                            uids_fetch_in_batch.split_off(0),
+                            &uid_message_ids,
+                            fetch_partially,
+                            fetch_existing_msgs,
+                        )
+                        .await
+                        .context("fetch_many_msgs")?;
                 received_msgs.extend(received_msgs_in_batch);
                 largest_uid_fetched = max(
                     largest_uid_fetched,
                     largest_uid_fetched_in_batch.unwrap_or(0),
                 );
+                if uid_not_received.is_some() {
+                    // Don't fetch later messages,
+                    // they would not fit on the disk either.
+                    uid_out_of_disk_space = uid_not_received;
+                    break;
+                }
                 fetch_partially = fp;
             }
             uids_fetch_in_batch.push(uid);
@@ -706,10 +772,14 @@ pub(crate) async fn fetch_new_messages(
             .with_context(|| format!("Expected {folder:?} to be selected"))?
             .uid_next
             .unwrap_or_default();
-        let new_uid_next = max(
+        let mut new_uid_next = max(
             max(largest_uid_fetched, largest_uid_skipped.unwrap_or(0)) + 1,
             mailbox_uid_next,
         );
+        if let Some(uid) = uid_out_of_disk_space {
+            // Fetch the message that did not fit on the disk again next time.
+            new_uid_next = min(new_uid_next, uid);
+        }
 
         if new_uid_next > old_uid_next {
             set_uid_next(context, folder, new_uid_next).await?;
@@ -1089,19 +1159,22 @@ pub(crate) async fn store_seen_flags_on_imap(&mut self, context: &Context) -> Re
     }
 
     /// Synchronizes `\Seen` flags using `CONDSTORE` extension.
+    ///
+    /// If the server or the mailbox does not support `CONDSTORE`,
+    /// falls back to fetching the flags of the messages which are unseen locally.
     pub(crate) async fn sync_seen_flags(&mut self, context: &Context, folder: &str) -> Result<()> {
+        self.select_with_uidvalidity(context, folder)
+            .await
+            .context("failed to select folder")?;
+
         if !self.can_condstore() {
             info!(
                 context,
-                "Server does not support CONDSTORE, skipping flag synchronization."
+                "Server does not support CONDSTORE, synchronizing flags of unseen messages."
             );
-            return Ok(());
+            return self.sync_seen_flags_of_unseen(context, folder).await;
         }
 
-        self.select_with_uidvalidity(context, folder)
-            .await
-            .context("failed to select folder")?;
-
         let mailbox = self
             .selected_mailbox
             .as_ref()
@@ -1112,9 +1185,10 @@ pub(crate) async fn sync_seen_flags(&mut self, context: &Context, folder: &str)
         if mailbox.highest_modseq.is_none() {
             info!(
                 context,
-                "Mailbox {} does not support mod-sequences, skipping flag synchronization.", folder
+                "Mailbox {} does not support mod-sequences, synchronizing flags of unseen messages.",
+                folder
             );
-            return Ok(());
+            return self.sync_seen_flags_of_unseen(context, folder).await;
         }
 
         let mut updated_chat_ids = BTreeSet::new();
@@ -1164,13 +1238,55 @@ pub(crate) async fn sync_seen_flags(&mut self, context: &Context, folder: &str)
         set_modseq(context, folder, highest_modseq)
             .await
             .with_context(|| format!("failed to set MODSEQ for folder {folder}"))?;
-        if !updated_chat_ids.is_empty() {
-            context.on_archived_chats_maybe_noticed();
+        emit_msgs_noticed(context, updated_chat_ids);
+
+        Ok(())
+    }
+
+    /// Synchronizes `\Seen` flags of the messages which are unseen locally.
+    ///
+    /// Used if mod-sequences are not supported.
+    /// Only the flags of at most [`UNSEEN_FLAGS_SYNC_LIMIT`] newest fresh messages are fetched,
+    /// so the traffic does not grow with the size of the folder,
+    /// and at most once per [`UNSEEN_FLAGS_SYNC_INTERVAL`].
+    async fn sync_seen_flags_of_unseen(&mut self, context: &Context, folder: &str) -> Result<()> {
+        if !unseen_flags_sync_due(context, folder).await? {
+            return Ok(());
         }
-        for updated_chat_id in updated_chat_ids {
-            context.emit_event(EventType::MsgsNoticed(updated_chat_id));
-            chatlist_events::emit_chatlist_item_changed(context, updated_chat_id);
+        let uid_validity = get_uidvalidity(context, folder)
+            .await
+            .with_context(|| format!("failed to get UID validity for folder {folder}"))?;
+        let uids = get_unseen_uids(context, folder, uid_validity).await?;
+
+        let mut updated_chat_ids = BTreeSet::new();
+        for (_, uid_set) in build_sequence_sets(&uids)? {
+            let mut list = self
+                .uid_fetch(&uid_set, "(FLAGS)")
+                .await
+                .context("failed to fetch flags")?;
+            while let Some(fetch) = list
+                .try_next()
+                .await
+                .context("failed to get FETCH result")?
+            {
+                let Some(uid) = fetch.uid else {
+                    info!(context, "FETCH result contains no UID, skipping");
+                    continue;
+                };
+                if !fetch.flags().any(|flag| flag == Flag::Seen) {
+                    continue;
+                }
+                if let Some(chat_id) = mark_seen_by_uid(context, folder, uid_validity, uid)
+                    .await
+                    .with_context(|| {
+                        format!("failed to update seen status for msg {folder}/{uid}")
+                    })?
+                {
+                    updated_chat_ids.insert(chat_id);
+                }
+            }
         }
+        emit_msgs_noticed(context, updated_chat_ids);
 
         Ok(())
     }
@@ -1212,7 +1328,9 @@ pub async fn get_all_recipients(&mut self, context: &Context) -> Result<Vec<Sing
 
     /// Fetches a list of messages by server UID.
     ///
-    /// Returns the last UID fetched successfully and the info about each downloaded message.
+    /// Returns the last UID fetched successfully, the info about each downloaded message
+    /// and the UID of the message which could not be received because of insufficient disk space.
+    /// In the latter case, fetching stops at this message.
     /// If the message is incorrect or there is a failure to write a message to the database,
     /// it is skipped and the error is logged.
     #[allow(clippy::too_many_arguments)]
@@ -1225,12 +1343,12 @@ pub(crate) async fn fetch_many_msgs(
         uid_message_ids: &BTreeMap<u32, String>,
         fetch_partially: bool,
         fetching_existing_messages: bool,
-    ) -> Result<(Option<u32>, Vec<ReceivedMsg>)> {
+    ) -> Result<(Option<u32>, Vec<ReceivedMsg>, Option<u32>)> {
         let mut last_uid = None;
         let mut received_msgs = Vec::new();
 
         if request_uids.is_empty() {
-            return Ok((last_uid, received_msgs));
+            return Ok((last_uid, received_msgs, None));
         }
 
         for (request_uids, set) in build_sequence_sets(&request_uids)? {
@@ -1361,6 +1479,7 @@ pub(crate) async fn fetch_many_msgs(
                     is_seen,
                     partial,
                     fetching_existing_messages,
+                    false,
                 )
                 .await
                 {
@@ -1369,6 +1488,17 @@ pub(crate) async fn fetch_many_msgs(
                             received_msgs.push(m);
                         }
                     }
+                    Err(err) if err.is::<InsufficientDiskSpace>() => {
+                        // Stop without advancing the last seen UID
+                        // and let the caller keep `uid_next` at this message,
+                        // so it is fetched again when there is enough disk space.
+                        warn!(
+                            context,
+                            "Not enough disk space to receive messages: {err:#}."
+                        );
+                        while fetch_responses.next().await.is_some() {}
+                        return Ok((last_uid, received_msgs, Some(request_uid)));
+                    }
                     Err(err) => {
                         warn!(context, "receive_imf error: {:#}.", err);
                     }
@@ -1397,7 +1527,7 @@ pub(crate) async fn fetch_many_msgs(
             }
         }
 
-        Ok((last_uid, received_msgs))
+        Ok((last_uid, received_msgs, None))
     }
 
     /// Retrieves server metadata if it is supported.
@@ -1517,6 +1647,39 @@ async fn add_flag_finalized_with_set(&mut self, uid_set: &str, flag: &str) -> Re
         Ok(())
     }
 
+    /// Deletes all messages in all folders and unsubscribes from the folders.
+    ///
+    /// Used when destroying a chatmail account.
+    pub(crate) async fn delete_all_messages(&mut self, context: &Context) -> Result<()> {
+        let folders = self.list_folders().await?;
+        for folder in folders {
+            let name = folder.name();
+            if folder
+                .attributes()
+                .iter()
+                .any(|attr| matches!(attr, NameAttribute::NoSelect))
+            {
+                continue;
+            }
+            self.select_folder(context, name)
+                .await
+                .with_context(|| format!("Failed to select folder {name:?}"))?;
+            let exists = self
+                .selected_mailbox
+                .as_ref()
+                .map_or(0, |mailbox| mailbox.exists);
+            if exists > 0 {
+                info!(context, "Deleting {exists} messages in {name:?}.");
+                self.add_flag_finalized_with_set("1:*", "\\Deleted").await?;
+                self.maybe_close_folder(context).await?;
+            }
+            if let Err(err) = self.unsubscribe(name).await {
+                warn!(context, "Failed to unsubscribe from {name:?}: {err:#}.");
+            }
+        }
+        Ok(())
+    }
+
     /// Attempts to configure mvbox.
     ///
     /// Tries to find any folder in the given list of `folders`. If none is found, tries to create
@@ -2156,6 +2319,7 @@ async fn mark_seen_by_uid(
             > 0;
 
         if updated {
+            chat::update_last_read_msg(context, msg_id).await?;
             msg_id
                 .start_ephemeral_timer(context)
                 .await
@@ -2171,6 +2335,76 @@ async fn mark_seen_by_uid(
     }
 }
 
+/// Maximum number of unseen messages whose flags are synchronized
+/// if the server does not support `CONDSTORE`.
+const UNSEEN_FLAGS_SYNC_LIMIT: u32 = 1000;
+
+/// Minimum interval in seconds between synchronizations of `\Seen` flags
+/// of fresh messages in a folder without mod-sequences.
+const UNSEEN_FLAGS_SYNC_INTERVAL: i64 = 10 * 60;
+
+/// Returns whether the `\Seen` flags of fresh messages in the folder
+/// were not synchronized for [`UNSEEN_FLAGS_SYNC_INTERVAL`]
+/// and records the synchronization in this case.
+async fn unseen_flags_sync_due(context: &Context, folder: &str) -> Result<bool> {
+    let key = format!("imap.unseen_flags_synced.{folder}");
+    let last = context
+        .sql
+        .get_raw_config_int64(&key)
+        .await?
+        .unwrap_or_default();
+    let now = tools::time();
+    // The clock may have been moved backwards.
+    if last <= now && now < last.saturating_add(UNSEEN_FLAGS_SYNC_INTERVAL) {
+        return Ok(false);
+    }
+    context.sql.set_raw_config_int64(&key, now).await?;
+    Ok(true)
+}
+
+/// Returns the UIDs of the newest fresh messages in the folder, in ascending order.
+///
+/// Noticed messages are not counted as unread,
+/// so it does not matter much if another client has seen them.
+async fn get_unseen_uids(context: &Context, folder: &str, uid_validity: u32) -> Result<Vec<u32>> {
+    let mut uids = context
+        .sql
+        .query_map(
+            "SELECT imap.uid FROM imap
+             INNER JOIN msgs ON msgs.rfc724_mid=imap.rfc724_mid
+             WHERE imap.folder=?1
+             AND imap.uidvalidity=?2
+             AND msgs.id>9
+             AND msgs.state=?3
+             ORDER BY imap.uid DESC
+             LIMIT ?4",
+            (
+                folder,
+                uid_validity,
+                MessageState::InFresh,
+                UNSEEN_FLAGS_SYNC_LIMIT,
+            ),
+            |row| row.get::<_, u32>(0),
+            |rows| rows.collect::<Result<Vec<_>, _>>().map_err(Into::into),
+        )
+        .await
+        .with_context(|| format!("failed to get unseen UIDs for folder {folder}"))?;
+    uids.reverse();
+    uids.dedup();
+    Ok(uids)
+}
+
+/// Emits events for the chats which have messages marked as seen by another client.
+fn emit_msgs_noticed(context: &Context, updated_chat_ids: BTreeSet<ChatId>) {
+    if !updated_chat_ids.is_empty() {
+        context.on_archived_chats_maybe_noticed();
+    }
+    for updated_chat_id in updated_chat_ids {
+        context.emit_event(EventType::MsgsNoticed(updated_chat_id));
+        chatlist_events::emit_chatlist_item_changed(context, updated_chat_id);
+    }
+}
+
 /// Schedule marking the message as Seen on IMAP by adding all known IMAP messages corresponding to
 /// the given Message-ID to `imap_markseen` table.
 pub(crate) async fn markseen_on_imap_table(context: &Context, message_id: &str) -> Result<()> {
@@ -2425,7 +2659,9 @@ async fn add_all_recipients_as_contacts(
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::receive_imf::receive_imf;
     use crate::test_utils::TestContext;
+    use crate::tools::SystemTime;
 
     #[test]
     fn test_get_folder_meaning_by_name() {
@@ -2461,6 +2697,60 @@ async fn test_set_uid_next_validity() {
         assert_eq!(get_uidvalidity(&t.ctx, "Inbox").await.unwrap(), 6);
     }
 
+    #[tokio::test(flavor = "multi_thread", worker_threads = 2)]
+    async fn test_get_unseen_uids() -> Result<()> {
+        let t = TestContext::new_alice().await;
+        for uid in 1..=3 {
+            let rfc724_mid = format!("{uid}@example.org");
+            let raw = format!(
+                "From: bob@example.net\n\
+                 To: alice@example.org\n\
+                 Message-ID: <{rfc724_mid}>\n\
+                 Date: Sun, 22 Mar 2020 22:37:57 +0000\n\
+                 \n\
+                 Message {uid}\n"
+            );
+            receive_imf(&t, raw.as_bytes(), false).await?.unwrap();
+            t.sql
+                .execute(
+                    "INSERT INTO imap (rfc724_mid, folder, uid, uidvalidity, target)
+                     VALUES (?, 'INBOX', ?, 6, 'INBOX')",
+                    (rfc724_mid, uid),
+                )
+                .await?;
+        }
+        assert_eq!(get_unseen_uids(&t, "INBOX", 6).await?, vec![1, 2, 3]);
+        assert_eq!(get_unseen_uids(&t, "INBOX", 7).await?, Vec::<u32>::new());
+
+        // Message seen by another client.
+        assert!(mark_seen_by_uid(&t, "INBOX", 6, 2).await?.is_some());
+        assert_eq!(get_unseen_uids(&t, "INBOX", 6).await?, vec![1, 3]);
+
+        // Noticed messages are not synchronized.
+        let msg_id = t.get_last_msg().await.id;
+        t.sql
+            .execute(
+                "UPDATE msgs SET state=? WHERE id=?",
+                (MessageState::InNoticed, msg_id),
+            )
+            .await?;
+        assert_eq!(get_unseen_uids(&t, "INBOX", 6).await?, vec![1]);
+        Ok(())
+    }
+
+    #[tokio::test(flavor = "multi_thread", worker_threads = 2)]
+    async fn test_unseen_flags_sync_due() -> Result<()> {
+        let t = TestContext::new_alice().await;
+        assert!(unseen_flags_sync_due(&t, "INBOX").await?);
+        assert!(!unseen_flags_sync_due(&t, "INBOX").await?);
+        assert!(unseen_flags_sync_due(&t, "Other").await?);
+
+        SystemTime::shift(Duration::from_secs(UNSEEN_FLAGS_SYNC_INTERVAL as u64 + 1));
+        assert!(unseen_flags_sync_due(&t, "INBOX").await?);
+        assert!(!unseen_flags_sync_due(&t, "INBOX").await?);
+        Ok(())
+    }
+
     #[test]
     fn test_build_sequence_sets() {
         assert_eq!(build_sequence_sets(&[]).unwrap(), vec![]);
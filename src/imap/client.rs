@@ -7,13 +7,13 @@
 
 use super::capabilities::Capabilities;
 use super::session::Session;
+use crate::constants::{ImapId, DC_VERSION_STR};
 use crate::context::Context;
 use crate::net::session::SessionStream;
 use crate::net::tls::wrap_tls;
-use crate::net::{connect_starttls_imap, connect_tcp, connect_tls};
-use crate::provider::Socket;
+use crate::net::{connect_starttls_imap, connect_tcp, connect_tls, Timeouts};
+use crate::provider::{Protocol, Socket};
 use crate::socks::Socks5Config;
-use fast_socks5::client::Socks5Stream;
 
 #[derive(Debug)]
 pub(crate) struct Client {
@@ -36,16 +36,28 @@ fn deref_mut(&mut self) -> &mut Self::Target {
 
 /// Determine server capabilities.
 ///
-/// If server supports ID capability, send our client ID.
+/// If server supports ID capability, send our client ID as configured by `imap_id`.
 async fn determine_capabilities(
     session: &mut ImapSession<Box<dyn SessionStream>>,
+    imap_id: ImapId,
 ) -> Result<Capabilities> {
     let caps = session
         .capabilities()
         .await
         .context("CAPABILITY command error")?;
     let server_id = if caps.has_str("ID") {
-        session.id([("name", Some("Delta Chat"))]).await?
+        match imap_id {
+            ImapId::Name => session.id([("name", Some("Delta Chat"))]).await?,
+            ImapId::Anonymous => None,
+            ImapId::Full => {
+                session
+                    .id([
+                        ("name", Some("Delta Chat")),
+                        ("version", Some(DC_VERSION_STR.as_str())),
+                    ])
+                    .await?
+            }
+        }
     } else {
         None
     };
@@ -55,6 +67,7 @@ async fn determine_capabilities(
         can_check_quota: caps.has_str("QUOTA"),
         can_condstore: caps.has_str("CONDSTORE"),
         can_metadata: caps.has_str("METADATA"),
+        can_objectid: caps.has_str("OBJECTID"),
         can_push: caps.has_str("XDELTAPUSH"),
         is_chatmail: caps.has_str("XCHATMAIL"),
         server_id,
@@ -69,13 +82,18 @@ fn new(stream: Box<dyn SessionStream>) -> Self {
         }
     }
 
-    pub(crate) async fn login(self, username: &str, password: &str) -> Result<Session> {
+    pub(crate) async fn login(
+        self,
+        username: &str,
+        password: &str,
+        imap_id: ImapId,
+    ) -> Result<Session> {
         let Client { inner, .. } = self;
         let mut session = inner
             .login(username, password)
             .await
             .map_err(|(err, _client)| err)?;
-        let capabilities = determine_capabilities(&mut session).await?;
+        let capabilities = determine_capabilities(&mut session, imap_id).await?;
         Ok(Session::new(session, capabilities))
     }
 
@@ -83,13 +101,14 @@ pub(crate) async fn authenticate(
         self,
         auth_type: &str,
         authenticator: impl async_imap::Authenticator,
+        imap_id: ImapId,
     ) -> Result<Session> {
         let Client { inner, .. } = self;
         let mut session = inner
             .authenticate(auth_type, authenticator)
             .await
             .map_err(|(err, _client)| err)?;
-        let capabilities = determine_capabilities(&mut session).await?;
+        let capabilities = determine_capabilities(&mut session, imap_id).await?;
         Ok(Session::new(session, capabilities))
     }
 
@@ -101,27 +120,47 @@ pub async fn connect(
         socks5_config: Option<Socks5Config>,
         security: Socket,
     ) -> Result<Self> {
+        let timeouts = Timeouts::load(context, Protocol::Imap).await?;
         if let Some(socks5_config) = socks5_config {
             match security {
                 Socket::Automatic => bail!("IMAP port security is not configured"),
                 Socket::Ssl => {
-                    Client::connect_secure_socks5(context, host, port, strict_tls, socks5_config)
-                        .await
+                    Client::connect_secure_socks5(
+                        context,
+                        host,
+                        port,
+                        strict_tls,
+                        socks5_config,
+                        timeouts,
+                    )
+                    .await
                 }
                 Socket::Starttls => {
-                    Client::connect_starttls_socks5(context, host, port, socks5_config, strict_tls)
-                        .await
+                    Client::connect_starttls_socks5(
+                        context,
+                        host,
+                        port,
+                        socks5_config,
+                        strict_tls,
+                        timeouts,
+                    )
+                    .await
                 }
                 Socket::Plain => {
-                    Client::connect_insecure_socks5(context, host, port, socks5_config).await
+                    Client::connect_insecure_socks5(context, host, port, socks5_config, timeouts)
+                        .await
                 }
             }
         } else {
             match security {
                 Socket::Automatic => bail!("IMAP port security is not configured"),
-                Socket::Ssl => Client::connect_secure(context, host, port, strict_tls).await,
-                Socket::Starttls => Client::connect_starttls(context, host, port, strict_tls).await,
-                Socket::Plain => Client::connect_insecure(context, host, port).await,
+                Socket::Ssl => {
+                    Client::connect_secure(context, host, port, strict_tls, timeouts).await
+                }
+                Socket::Starttls => {
+                    Client::connect_starttls(context, host, port, strict_tls, timeouts).await
+                }
+                Socket::Plain => Client::connect_insecure(context, host, port, timeouts).await,
             }
         }
     }
@@ -131,8 +170,9 @@ async fn connect_secure(
         hostname: &str,
         port: u16,
         strict_tls: bool,
+        timeouts: Timeouts,
     ) -> Result<Self> {
-        let tls_stream = connect_tls(context, hostname, port, strict_tls, "imap").await?;
+        let tls_stream = connect_tls(context, hostname, port, strict_tls, "imap", timeouts).await?;
         let buffered_stream = BufWriter::new(tls_stream);
         let session_stream: Box<dyn SessionStream> = Box::new(buffered_stream);
         let mut client = Client::new(session_stream);
@@ -143,8 +183,13 @@ async fn connect_secure(
         Ok(client)
     }
 
-    async fn connect_insecure(context: &Context, hostname: &str, port: u16) -> Result<Self> {
-        let tcp_stream = connect_tcp(context, hostname, port, false).await?;
+    async fn connect_insecure(
+        context: &Context,
+        hostname: &str,
+        port: u16,
+        timeouts: Timeouts,
+    ) -> Result<Self> {
+        let tcp_stream = connect_tcp(context, hostname, port, false, timeouts).await?;
         let buffered_stream = BufWriter::new(tcp_stream);
         let session_stream: Box<dyn SessionStream> = Box::new(buffered_stream);
         let mut client = Client::new(session_stream);
@@ -160,8 +205,10 @@ async fn connect_starttls(
         hostname: &str,
         port: u16,
         strict_tls: bool,
+        timeouts: Timeouts,
     ) -> Result<Self> {
-        let tls_stream = connect_starttls_imap(context, hostname, port, strict_tls).await?;
+        let tls_stream =
+            connect_starttls_imap(context, hostname, port, strict_tls, timeouts).await?;
 
         let buffered_stream = BufWriter::new(tls_stream);
         let session_stream: Box<dyn SessionStream> = Box::new(buffered_stream);
@@ -175,11 +222,19 @@ async fn connect_secure_socks5(
         port: u16,
         strict_tls: bool,
         socks5_config: Socks5Config,
+        timeouts: Timeouts,
     ) -> Result<Self> {
         let socks5_stream = socks5_config
-            .connect(context, domain, port, strict_tls)
+            .connect(context, domain, port, strict_tls, timeouts)
             .await?;
-        let tls_stream = wrap_tls(strict_tls, domain, "imap", socks5_stream).await?;
+        let tls_stream = wrap_tls(
+            strict_tls,
+            domain,
+            "imap",
+            socks5_stream,
+            timeouts.tls_handshake,
+        )
+        .await?;
         let buffered_stream = BufWriter::new(tls_stream);
         let session_stream: Box<dyn SessionStream> = Box::new(buffered_stream);
         let mut client = Client::new(session_stream);
@@ -195,8 +250,11 @@ async fn connect_insecure_socks5(
         domain: &str,
         port: u16,
         socks5_config: Socks5Config,
+        timeouts: Timeouts,
     ) -> Result<Self> {
-        let socks5_stream = socks5_config.connect(context, domain, port, false).await?;
+        let socks5_stream = socks5_config
+            .connect(context, domain, port, false, timeouts)
+            .await?;
         let buffered_stream = BufWriter::new(socks5_stream);
         let session_stream: Box<dyn SessionStream> = Box::new(buffered_stream);
         let mut client = Client::new(session_stream);
@@ -213,9 +271,10 @@ async fn connect_starttls_socks5(
         port: u16,
         socks5_config: Socks5Config,
         strict_tls: bool,
+        timeouts: Timeouts,
     ) -> Result<Self> {
         let socks5_stream = socks5_config
-            .connect(context, hostname, port, strict_tls)
+            .connect(context, hostname, port, strict_tls, timeouts)
             .await?;
 
         // Run STARTTLS command and convert the client back into a stream.
@@ -230,11 +289,17 @@ async fn connect_starttls_socks5(
             .await
             .context("STARTTLS command failed")?;
         let buffered_socks5_stream = client.into_inner();
-        let socks5_stream: Socks5Stream<_> = buffered_socks5_stream.into_inner();
+        let socks5_stream: Box<dyn SessionStream> = buffered_socks5_stream.into_inner();
 
-        let tls_stream = wrap_tls(strict_tls, hostname, "imap", socks5_stream)
-            .await
-            .context("STARTTLS upgrade failed")?;
+        let tls_stream = wrap_tls(
+            strict_tls,
+            hostname,
+            "imap",
+            socks5_stream,
+            timeouts.tls_handshake,
+        )
+        .await
+        .context("STARTTLS upgrade failed")?;
         let buffered_stream = BufWriter::new(tls_stream);
         let session_stream: Box<dyn SessionStream> = Box::new(buffered_stream);
         let client = Client::new(session_stream);
@@ -10,7 +10,8 @@
 use super::Imap;
 use crate::context::Context;
 use crate::imap::FolderMeaning;
-use crate::net::TIMEOUT;
+use crate::net::Timeouts;
+use crate::provider::Protocol;
 use crate::tools::{self, time_elapsed};
 
 /// Timeout after which IDLE is finished
@@ -52,8 +53,8 @@ pub async fn idle(
 
         // At this point IDLE command was sent and we received a "+ idling" response. We will now
         // read from the stream without getting any data for up to `IDLE_TIMEOUT`. If we don't
-        // disable read timeout, we would get a timeout after `crate::net::TIMEOUT`, which is a lot
-        // shorter than `IDLE_TIMEOUT`.
+        // disable read timeout, we would get a timeout after the configured IMAP timeout, which is
+        // usually a lot shorter than `IDLE_TIMEOUT`.
         handle.as_mut().set_read_timeout(None);
         let (idle_wait, interrupt) = handle.wait_with_timeout(IDLE_TIMEOUT);
 
@@ -94,7 +95,8 @@ enum Event {
             .await
             .with_context(|| format!("{folder}: IMAP IDLE protocol timed out"))?
             .with_context(|| format!("{folder}: IMAP IDLE failed"))?;
-        session.as_mut().set_read_timeout(Some(TIMEOUT));
+        let timeouts = Timeouts::load(context, Protocol::Imap).await?;
+        session.as_mut().set_read_timeout(Some(timeouts.io));
         self.inner = session;
 
         // Fetch mail once we exit IDLE.
@@ -0,0 +1,587 @@
+//! # Persisting the outgoing queue on the server.
+//!
+//! If [`Config::PersistOutbox`] is enabled, each entry of the `smtp` queue
+//! is uploaded, encrypted to self and signed with the self key,
+//! as a draft to the [`OUTBOX_FOLDER`].
+//! Drafts of messages that were sent are deleted and expunged from the folder again.
+//!
+//! When the account is set up again, e.g. after reinstalling the app,
+//! drafts of messages unknown to this device are restored
+//! into the chat and the `smtp` queue, so they are not lost.
+//! Drafts which are not signed with a self key are never restored,
+//! so that whoever can write to the folder cannot send messages as the user.
+//! Drafts of messages which already arrived on the server,
+//! e.g. as a copy sent to self by another device, are considered sent and not restored.
+//!
+//! Each draft carries a claim keyword of the device which is going to send the message.
+//! The uploading device claims the draft when appending it.
+//! Another device takes over a draft only if it has no claim
+//! or the claim is older than [`CLAIM_TIMEOUT`],
+//! i.e. the uploading device is likely gone.
+//! The claim is replaced with a conditional `STORE` (`UNCHANGEDSINCE`),
+//! so if several devices try to take over the same draft, exactly one of them succeeds.
+//! A device which finds its draft claimed by another device removes the message from its queue.
+
+use std::collections::{BTreeMap, BTreeSet};
+
+use anyhow::{ensure, Context as _, Result};
+use async_imap::types::{Fetch, Flag};
+use futures::{StreamExt as _, TryStreamExt};
+use serde::{Deserialize, Serialize};
+
+use super::session::Session;
+use super::{get_uid_next, get_uidvalidity, set_uid_next, set_uidvalidity};
+use crate::config::Config;
+use crate::context::Context;
+use crate::headerdef::{HeaderDef, HeaderDefMap};
+use crate::key::{
+    load_self_public_key, load_self_public_keyring, load_self_secret_keyring, load_self_signing_key,
+};
+use crate::message::{rfc724_mid_exists, update_msg_state, MessageState};
+use crate::pgp;
+use crate::receive_imf::receive_imf;
+use crate::tools::{create_id, create_outgoing_rfc724_mid, time};
+
+/// Folder containing the drafts of unsent messages.
+pub(crate) const OUTBOX_FOLDER: &str = "DeltaChat-Outbox";
+
+/// Raw config key of the random ID with which this device claims drafts.
+///
+/// Reset when importing a backup, as the imported database belongs to another device.
+pub(crate) const DEVICE_ID_CFG: &str = "outbox_device_id";
+
+/// Prefix of the keyword with which a device claims a draft.
+const CLAIM_KEYWORD_PREFIX: &str = "$DcClaim-";
+
+/// Age of a draft in seconds after which another device may take it over.
+const CLAIM_TIMEOUT: i64 = 24 * 60 * 60;
+
+/// Unsent message as stored encrypted in the draft.
+#[derive(Debug, Serialize, Deserialize)]
+struct OutboxEntry {
+    /// Message-ID of the unsent message.
+    rfc724_mid: String,
+
+    /// Recipients separated by space.
+    recipients: String,
+
+    /// SMTP payload.
+    mime: String,
+}
+
+/// Returns the keyword with which this device claims drafts.
+async fn claim_keyword(context: &Context) -> Result<String> {
+    let device_id = match context.sql.get_raw_config(DEVICE_ID_CFG).await? {
+        Some(device_id) => device_id,
+        None => {
+            let device_id = create_id();
+            context
+                .sql
+                .set_raw_config(DEVICE_ID_CFG, Some(&device_id))
+                .await?;
+            device_id
+        }
+    };
+    Ok(format!("{CLAIM_KEYWORD_PREFIX}{device_id}"))
+}
+
+/// Returns the claim keywords of a draft.
+fn claims(fetch: &Fetch) -> Vec<String> {
+    fetch
+        .flags()
+        .filter_map(|flag| match flag {
+            Flag::Custom(keyword) if keyword.starts_with(CLAIM_KEYWORD_PREFIX) => {
+                Some(keyword.to_string())
+            }
+            _ => None,
+        })
+        .collect()
+}
+
+/// Returns whether the message arrived on the server, i.e. was sent by this or another device.
+async fn arrived_on_server(context: &Context, rfc724_mid: &str) -> Result<bool> {
+    context
+        .sql
+        .exists(
+            "SELECT COUNT(*) FROM imap WHERE rfc724_mid=? AND folder!=?",
+            (rfc724_mid, OUTBOX_FOLDER),
+        )
+        .await
+}
+
+/// Renders the draft for an `smtp` queue entry, encrypted to self and signed with the self key.
+async fn render_draft(context: &Context, entry: &OutboxEntry) -> Result<String> {
+    let addr = context.get_primary_self_addr().await?;
+    let public_key = load_self_public_key(context).await?;
+    let sign_key = load_self_signing_key(context).await?;
+    let plain = serde_json::to_vec(entry)?;
+    let ctext = pgp::pk_encrypt(&plain, vec![public_key], Some(sign_key), true).await?;
+
+    Ok(format!(
+        "From: <{addr}>\r\n\
+         To: <{addr}>\r\n\
+         Subject: Unsent message\r\n\
+         Date: {}\r\n\
+         Message-ID: <{}>\r\n\
+         {}: {}\r\n\
+         Content-Type: text/plain; charset=utf-8\r\n\
+         \r\n\
+         {}\r\n",
+        chrono::Utc::now().to_rfc2822(),
+        create_outgoing_rfc724_mid(),
+        HeaderDef::ChatOutboxId.get_headername(),
+        entry.rfc724_mid,
+        ctext.replace('\n', "\r\n"),
+    ))
+}
+
+/// Decrypts the queue entry from a draft rendered by [`render_draft`].
+///
+/// Fails if the draft is not signed with a self key.
+async fn parse_draft(context: &Context, raw: &[u8]) -> Result<OutboxEntry> {
+    let mail = mailparse::parse_mail(raw)?;
+    let ctext = mail.get_body()?;
+    let secret_keys = load_self_secret_keyring(context).await?;
+    let public_keys = load_self_public_keyring(context).await?;
+    let (plain, signatures) = pgp::pk_decrypt(ctext.into_bytes(), &secret_keys, &public_keys)?;
+    ensure!(
+        !signatures.is_empty(),
+        "Draft is not signed with a self key"
+    );
+    let entry = serde_json::from_slice(&plain).context("Malformed outbox entry")?;
+    Ok(entry)
+}
+
+/// Adds the message from the draft to its chat and to the `smtp` queue.
+///
+/// The message is shown as pending until it is sent.
+async fn restore_entry(context: &Context, entry: &OutboxEntry) -> Result<()> {
+    let msg_id = receive_imf(context, entry.mime.as_bytes(), true)
+        .await?
+        .and_then(|received| received.msg_ids.last().copied())
+        .context("Unsent message could not be restored")?;
+    update_msg_state(context, msg_id, MessageState::OutPending).await?;
+    context
+        .sql
+        .insert(
+            "INSERT INTO smtp (rfc724_mid, recipients, mime, msg_id) VALUES (?, ?, ?, ?)",
+            (&entry.rfc724_mid, &entry.recipients, &entry.mime, msg_id),
+        )
+        .await?;
+    info!(
+        context,
+        "Restored unsent message {} from the server.", entry.rfc724_mid
+    );
+    context.scheduler.interrupt_smtp().await;
+    Ok(())
+}
+
+impl Session {
+    /// Synchronizes the `smtp` queue with the drafts in the [`OUTBOX_FOLDER`].
+    ///
+    /// Uploads queue entries not on the server yet,
+    /// deletes drafts of messages that were sent
+    /// and restores drafts of messages this device does not know.
+    ///
+    /// Only the headers of drafts appended since the last call are downloaded,
+    /// flags are fetched only for drafts of messages which did not arrive on the server yet.
+    /// If the queue is empty, the folder is only selected if it contains drafts.
+    pub(crate) async fn sync_outbox(&mut self, context: &Context) -> Result<()> {
+        if !context.get_config_bool(Config::PersistOutbox).await? {
+            return Ok(());
+        }
+
+        let queue: BTreeMap<String, (String, String)> = context
+            .sql
+            .query_map(
+                "SELECT rfc724_mid, recipients, mime FROM smtp ORDER BY id",
+                (),
+                |row| {
+                    let rfc724_mid: String = row.get(0)?;
+                    let recipients: String = row.get(1)?;
+                    let mime: String = row.get(2)?;
+                    Ok((rfc724_mid, (recipients, mime)))
+                },
+                |rows| rows.collect::<Result<_, _>>().map_err(Into::into),
+            )
+            .await?;
+
+        if queue.is_empty() {
+            let has_drafts = self
+                .status(OUTBOX_FOLDER, "(MESSAGES)")
+                .await
+                .map_or(false, |mailbox| mailbox.exists > 0);
+            if !has_drafts {
+                return Ok(());
+            }
+        }
+
+        self.select_or_create_folder(context, OUTBOX_FOLDER)
+            .await
+            .context("Failed to select outbox folder")?;
+        let mailbox = self
+            .selected_mailbox
+            .as_ref()
+            .context("No mailbox selected")?;
+        let is_empty = mailbox.exists == 0;
+        let can_claim = self.can_condstore() && mailbox.highest_modseq.is_some();
+        let uid_validity = mailbox
+            .uid_validity
+            .context("Outbox folder has no UIDVALIDITY")?;
+        if get_uidvalidity(context, OUTBOX_FOLDER).await? != uid_validity {
+            set_uid_next(context, OUTBOX_FOLDER, 1).await?;
+            set_uidvalidity(context, OUTBOX_FOLDER, uid_validity).await?;
+        }
+        if is_empty {
+            // Drafts were deleted by other devices.
+            context
+                .sql
+                .execute("DELETE FROM imap WHERE folder=?", (OUTBOX_FOLDER,))
+                .await?;
+            if queue.is_empty() {
+                return Ok(());
+            }
+        } else {
+            context
+                .sql
+                .execute(
+                    "DELETE FROM imap WHERE folder=? AND uidvalidity!=?",
+                    (OUTBOX_FOLDER, uid_validity),
+                )
+                .await?;
+            self.fetch_new_drafts(context, uid_validity).await?;
+        }
+
+        let drafts: Vec<(u32, String)> = context
+            .sql
+            .query_map(
+                "SELECT uid, rfc724_mid FROM imap WHERE folder=?1 AND target=?1 AND uidvalidity=?2",
+                (OUTBOX_FOLDER, uid_validity),
+                |row| Ok((row.get(0)?, row.get(1)?)),
+                |rows| rows.collect::<Result<_, _>>().map_err(Into::into),
+            )
+            .await?;
+        let mut pending = BTreeMap::new();
+        for (uid, rfc724_mid) in drafts {
+            if arrived_on_server(context, &rfc724_mid).await? {
+                plan_draft_deletion(context, uid, uid_validity).await?;
+            } else {
+                pending.insert(uid, rfc724_mid);
+            }
+        }
+
+        let keyword = claim_keyword(context).await?;
+        let mut on_server = BTreeSet::new();
+        let mut candidates = BTreeMap::new();
+        if !pending.is_empty() {
+            let uid_set = join_uids(pending.keys());
+            let query = match can_claim {
+                true => "(UID FLAGS INTERNALDATE MODSEQ)",
+                false => "(UID FLAGS INTERNALDATE)",
+            };
+            let mut list = self
+                .uid_fetch(&uid_set, query)
+                .await
+                .context("Failed to fetch flags of outbox drafts")?;
+            let mut fetched = Vec::new();
+            while let Some(fetch) = list.try_next().await? {
+                let Some(uid) = fetch.uid else {
+                    continue;
+                };
+                let claims = claims(&fetch);
+                let is_deleted = fetch.flags().any(|flag| flag == Flag::Deleted);
+                let is_claimed_by_other = !claims.is_empty() && !claims.contains(&keyword);
+                let is_stale = fetch
+                    .internal_date()
+                    .map_or(false, |date| date.timestamp() < time() - CLAIM_TIMEOUT);
+                fetched.push((uid, is_deleted, is_claimed_by_other, is_stale, fetch.modseq));
+            }
+            drop(list);
+
+            for (uid, is_deleted, is_claimed_by_other, is_stale, modseq) in fetched {
+                let Some(rfc724_mid) = pending.remove(&uid) else {
+                    continue;
+                };
+                if is_deleted {
+                    // The draft was taken over or deleted by another device.
+                    continue;
+                }
+                if queue.contains_key(&rfc724_mid) {
+                    if is_claimed_by_other {
+                        drop_taken_over_entry(context, &rfc724_mid).await?;
+                    }
+                    on_server.insert(rfc724_mid);
+                } else if rfc724_mid_exists(context, &rfc724_mid).await?.is_some() {
+                    if !is_claimed_by_other {
+                        // The message was sent or deleted on this device.
+                        plan_draft_deletion(context, uid, uid_validity).await?;
+                    }
+                } else if !is_claimed_by_other || is_stale {
+                    if let Some(modseq) = modseq {
+                        candidates.insert(uid, modseq);
+                    } else {
+                        info!(
+                            context,
+                            "Cannot claim draft of unsent message {rfc724_mid} without CONDSTORE."
+                        );
+                    }
+                }
+            }
+
+            // Drafts which were not returned by the server were expunged by another device.
+            for uid in pending.into_keys() {
+                context
+                    .sql
+                    .execute(
+                        "DELETE FROM imap WHERE folder=? AND uid=? AND uidvalidity=?",
+                        (OUTBOX_FOLDER, uid, uid_validity),
+                    )
+                    .await?;
+            }
+        }
+
+        let mut restored = Vec::new();
+        for uid in self.claim_drafts(context, &keyword, candidates).await? {
+            // The claimed draft is flagged as deleted and replaced with a new one below.
+            context
+                .sql
+                .execute(
+                    "DELETE FROM imap WHERE folder=? AND uid=? AND uidvalidity=?",
+                    (OUTBOX_FOLDER, uid, uid_validity),
+                )
+                .await?;
+            match self.restore_draft(context, uid).await {
+                Ok(entry) => restored.push(entry),
+                Err(err) => warn!(context, "Failed to restore unsent message: {err:#}."),
+            }
+        }
+
+        let uploads = queue
+            .into_iter()
+            .filter(|(rfc724_mid, _)| !on_server.contains(rfc724_mid))
+            .map(|(rfc724_mid, (recipients, mime))| OutboxEntry {
+                rfc724_mid,
+                recipients,
+                mime,
+            })
+            .chain(restored);
+        let flags = format!("(\\Draft \\Seen {keyword})");
+        for entry in uploads {
+            let draft = render_draft(context, &entry).await?;
+            self.append(OUTBOX_FOLDER, Some(&flags), None, draft)
+                .await
+                .context("Failed to upload unsent message")?;
+            info!(context, "Uploaded unsent message {}.", entry.rfc724_mid);
+        }
+
+        // Deletes the drafts of sent messages and expunges claimed drafts.
+        self.move_delete_messages(context, OUTBOX_FOLDER).await
+    }
+
+    /// Fetches the Message-IDs of drafts appended since the last call into the `imap` table.
+    async fn fetch_new_drafts(&mut self, context: &Context, uid_validity: u32) -> Result<()> {
+        let uid_next = get_uid_next(context, OUTBOX_FOLDER).await?.max(1);
+        let mut list = self
+            .uid_fetch(
+                format!("{uid_next}:*"),
+                "(UID FLAGS BODY.PEEK[HEADER.FIELDS (CHAT-OUTBOX-ID)])",
+            )
+            .await
+            .context("Failed to fetch outbox drafts")?;
+        let mut new_uid_next = uid_next;
+        let mut drafts = Vec::new();
+        while let Some(fetch) = list.try_next().await? {
+            let (Some(uid), Some(header)) = (fetch.uid, fetch.header()) else {
+                continue;
+            };
+            // `{uid_next}:*` also matches the last draft if there are no new ones.
+            if uid < uid_next {
+                continue;
+            }
+            new_uid_next = new_uid_next.max(uid.saturating_add(1));
+            if fetch.flags().any(|flag| flag == Flag::Deleted) {
+                continue;
+            }
+            let (headers, _) = mailparse::parse_headers(header)?;
+            if let Some(rfc724_mid) = headers.get_header_value(HeaderDef::ChatOutboxId) {
+                drafts.push((uid, rfc724_mid));
+            }
+        }
+        drop(list);
+
+        context
+            .sql
+            .transaction(move |transaction| {
+                for (uid, rfc724_mid) in drafts {
+                    transaction.execute(
+                        "INSERT INTO imap (rfc724_mid, folder, uid, uidvalidity, target)
+                         VALUES           (?1,         ?2,     ?3,  ?4,          ?2)
+                         ON CONFLICT(folder, uid, uidvalidity)
+                         DO UPDATE SET rfc724_mid=excluded.rfc724_mid",
+                        (rfc724_mid, OUTBOX_FOLDER, uid, uid_validity),
+                    )?;
+                }
+                Ok(())
+            })
+            .await?;
+        set_uid_next(context, OUTBOX_FOLDER, new_uid_next).await?;
+        Ok(())
+    }
+
+    /// Claims the drafts with the given UIDs and mod-sequences.
+    ///
+    /// The claim keyword replaces the claims of other devices
+    /// and the draft is flagged as deleted at the same time,
+    /// but only if the draft was not modified since it was fetched,
+    /// so at most one device succeeds.
+    ///
+    /// Returns the UIDs of the drafts claimed by this device.
+    async fn claim_drafts(
+        &mut self,
+        context: &Context,
+        keyword: &str,
+        candidates: BTreeMap<u32, u64>,
+    ) -> Result<Vec<u32>> {
+        if candidates.is_empty() {
+            return Ok(Vec::new());
+        }
+        for (uid, modseq) in &candidates {
+            let query =
+                format!("(UNCHANGEDSINCE {modseq}) FLAGS (\\Draft \\Seen \\Deleted {keyword})");
+            let mut responses = self
+                .uid_store(uid.to_string(), &query)
+                .await
+                .with_context(|| format!("Failed to claim draft {uid}"))?;
+            while let Some(_response) = responses.next().await {
+                // Read all the responses
+            }
+        }
+        self.selected_folder_needs_expunge = true;
+
+        let mut list = self
+            .uid_fetch(join_uids(candidates.keys()), "(UID FLAGS)")
+            .await
+            .context("Failed to fetch flags of claimed drafts")?;
+        let mut claimed = Vec::new();
+        while let Some(fetch) = list.try_next().await? {
+            if let Some(uid) = fetch.uid {
+                if claims(&fetch).iter().any(|claim| claim == keyword) {
+                    claimed.push(uid);
+                } else {
+                    info!(context, "Draft {uid} was claimed by another device.");
+                }
+            }
+        }
+        Ok(claimed)
+    }
+
+    /// Downloads the draft with the given UID and restores the message from it.
+    async fn restore_draft(&mut self, context: &Context, uid: u32) -> Result<OutboxEntry> {
+        let mut list = self.uid_fetch(uid.to_string(), "BODY.PEEK[]").await?;
+        let mut raw = None;
+        while let Some(fetch) = list.try_next().await? {
+            if fetch.uid == Some(uid) {
+                raw = fetch.body().map(|body| body.to_vec());
+            }
+        }
+        drop(list);
+
+        let raw = raw.context("Draft has no body")?;
+        let entry = parse_draft(context, &raw).await?;
+        restore_entry(context, &entry).await?;
+        Ok(entry)
+    }
+}
+
+/// Plans the deletion of the draft with the given UID from the [`OUTBOX_FOLDER`].
+async fn plan_draft_deletion(context: &Context, uid: u32, uid_validity: u32) -> Result<()> {
+    context
+        .sql
+        .execute(
+            "UPDATE imap SET target='' WHERE folder=? AND uid=? AND uidvalidity=?",
+            (OUTBOX_FOLDER, uid, uid_validity),
+        )
+        .await?;
+    Ok(())
+}
+
+/// Removes the message from the `smtp` queue because another device took its draft over.
+///
+/// The message is shown as delivered as it is sent by the other device.
+async fn drop_taken_over_entry(context: &Context, rfc724_mid: &str) -> Result<()> {
+    context
+        .sql
+        .execute("DELETE FROM smtp WHERE rfc724_mid=?", (rfc724_mid,))
+        .await?;
+    if let Some((msg_id, _)) = rfc724_mid_exists(context, rfc724_mid).await? {
+        update_msg_state(context, msg_id, MessageState::OutDelivered).await?;
+    }
+    info!(
+        context,
+        "Unsent message {rfc724_mid} is sent by another device."
+    );
+    Ok(())
+}
+
+/// Joins UIDs into a UID set.
+fn join_uids<'a>(uids: impl Iterator<Item = &'a u32>) -> String {
+    uids.map(|uid| uid.to_string())
+        .collect::<Vec<_>>()
+        .join(",")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::chat;
+    use crate::test_utils::TestContext;
+
+    #[tokio::test(flavor = "multi_thread", worker_threads = 2)]
+    async fn test_restore_unsent_message() -> Result<()> {
+        let alice = TestContext::new_alice().await;
+        let bob = TestContext::new_bob().await;
+        let chat_id = alice.create_chat(&bob).await.id;
+        let msg_id = chat::send_text_msg(&alice, chat_id, "Composed offline".to_string()).await?;
+
+        let (rfc724_mid, recipients, mime) = alice
+            .sql
+            .query_row(
+                "SELECT rfc724_mid, recipients, mime FROM smtp WHERE msg_id=?",
+                (msg_id,),
+                |row| Ok((row.get(0)?, row.get(1)?, row.get(2)?)),
+            )
+            .await?;
+        let entry = OutboxEntry {
+            rfc724_mid,
+            recipients,
+            mime,
+        };
+        let draft = render_draft(&alice, &entry).await?;
+        assert!(!draft.contains("Composed offline"));
+
+        // Drafts not signed with a self key are not restored.
+        let plain = serde_json::to_vec(&entry)?;
+        let public_key = load_self_public_key(&alice).await?;
+        let bob_key = load_self_signing_key(&bob).await?;
+        for sign_key in [None, Some(bob_key)] {
+            let ctext = pgp::pk_encrypt(&plain, vec![public_key.clone()], sign_key, true).await?;
+            let forged = format!("Subject: Unsent message\r\n\r\n{ctext}\r\n");
+            assert!(parse_draft(&alice, forged.as_bytes()).await.is_err());
+        }
+
+        // The same account set up again on a new device.
+        let alice2 = TestContext::new_alice().await;
+        let entry = parse_draft(&alice2, draft.as_bytes()).await?;
+        restore_entry(&alice2, &entry).await?;
+
+        let msg = alice2.get_last_msg().await;
+        assert_eq!(msg.text, "Composed offline");
+        assert_eq!(msg.state, MessageState::OutPending);
+        let queued = alice2
+            .sql
+            .count("SELECT COUNT(*) FROM smtp WHERE msg_id=?", (msg.id,))
+            .await?;
+        assert_eq!(queued, 1);
+        Ok(())
+    }
+}
@@ -48,7 +48,11 @@ pub(super) async fn maybe_close_folder(&mut self, context: &Context) -> anyhow::
     /// Selects a folder, possibly updating uid_validity and, if needed,
     /// expunging the folder to remove delete-marked messages.
     /// Returns whether a new folder was selected.
-    async fn select_folder(&mut self, context: &Context, folder: &str) -> Result<NewlySelected> {
+    pub(super) async fn select_folder(
+        &mut self,
+        context: &Context,
+        folder: &str,
+    ) -> Result<NewlySelected> {
         // if there is a new folder and the new folder is equal to the selected one, there's nothing to do.
         // if there is _no_ new folder, we continue as we might want to expunge below.
         if let Some(selected_folder) = &self.selected_folder {
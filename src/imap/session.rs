@@ -96,6 +96,10 @@ pub fn can_metadata(&self) -> bool {
         self.capabilities.can_metadata
     }
 
+    pub fn can_objectid(&self) -> bool {
+        self.capabilities.can_objectid
+    }
+
     pub fn can_push(&self) -> bool {
         self.capabilities.can_push
     }
@@ -143,6 +147,21 @@ pub(crate) async fn prefetch(
         Ok(msgs.into_iter().map(|((_, uid), msg)| (uid, msg)).collect())
     }
 
+    /// Fetches OBJECTID `EMAILID`s and `THREADID`s of the messages in `uid_set`.
+    ///
+    /// The FETCH response is read raw
+    /// because the OBJECTID attributes are not parsed by the IMAP library.
+    pub(crate) async fn fetch_objectids(
+        &mut self,
+        uid_set: &str,
+    ) -> Result<BTreeMap<u32, ObjectIds>> {
+        let response = self
+            .run_command_and_read_response(format!("UID FETCH {uid_set} (UID EMAILID THREADID)"))
+            .await
+            .context("IMAP could not fetch OBJECTIDs")?;
+        Ok(parse_objectids(&String::from_utf8_lossy(&response)))
+    }
+
     /// Like prefetch(), but not for new messages but existing ones (the DC_FETCH_EXISTING_MSGS_COUNT newest messages)
     pub(crate) async fn prefetch_existing_msgs(
         &mut self,
@@ -172,3 +191,74 @@ pub(crate) async fn prefetch_existing_msgs(
         Ok(msgs.into_iter().map(|((_, uid), msg)| (uid, msg)).collect())
     }
 }
+
+/// OBJECTID identifiers of a message as defined in RFC 8474.
+#[derive(Debug, Default, Clone, PartialEq, Eq)]
+pub(crate) struct ObjectIds {
+    /// `EMAILID`, the same for all copies of the message in all folders.
+    pub emailid: String,
+
+    /// `THREADID`, the same for all messages of a thread.
+    /// Empty if the server does not support threads.
+    pub threadid: String,
+}
+
+/// Parses `UID`, `EMAILID` and `THREADID` out of untagged FETCH responses
+/// such as `* 5 FETCH (UID 12 EMAILID (M6d99ac3275bb4e) THREADID (T64b478a75b7ea9))`.
+///
+/// Messages without `EMAILID` are skipped.
+fn parse_objectids(response: &str) -> BTreeMap<u32, ObjectIds> {
+    let mut objectids = BTreeMap::new();
+    for line in response.lines() {
+        let tokens: Vec<&str> = line.split_whitespace().collect();
+        let mut uid = None;
+        let mut ids = ObjectIds::default();
+        for pair in tokens.windows(2) {
+            let [name, value] = pair else {
+                continue;
+            };
+            let value = value.trim_start_matches('(').trim_end_matches(')');
+            match name.trim_start_matches('(') {
+                "UID" => uid = value.parse::<u32>().ok(),
+                "EMAILID" => ids.emailid = value.to_string(),
+                "THREADID" if value != "NIL" => ids.threadid = value.to_string(),
+                _ => {}
+            }
+        }
+        if let Some(uid) = uid {
+            if !ids.emailid.is_empty() {
+                objectids.insert(uid, ids);
+            }
+        }
+    }
+    objectids
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_objectids() {
+        let response =
+            "* 5 FETCH (UID 12 EMAILID (M6d99ac3275bb4e) THREADID (T64b478a75b7ea9))\r\n\
+             * 6 FETCH (EMAILID (Mabc) THREADID NIL UID 13)\r\n\
+             * 7 FETCH (UID 14)\r\n";
+        let objectids = parse_objectids(response);
+        assert_eq!(objectids.len(), 2);
+        assert_eq!(
+            objectids.get(&12).unwrap(),
+            &ObjectIds {
+                emailid: "M6d99ac3275bb4e".to_string(),
+                threadid: "T64b478a75b7ea9".to_string(),
+            }
+        );
+        assert_eq!(
+            objectids.get(&13).unwrap(),
+            &ObjectIds {
+                emailid: "Mabc".to_string(),
+                threadid: String::new(),
+            }
+        );
+    }
+}
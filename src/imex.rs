@@ -1,41 +1,74 @@
 //! # Import/export module.
 
+use std::collections::BTreeMap;
 use std::ffi::OsStr;
 use std::path::{Path, PathBuf};
+use std::pin::Pin;
+use std::task::Poll;
 
+use ::pgp::composed::Deserializable;
 use ::pgp::types::KeyTrait;
 use anyhow::{bail, ensure, format_err, Context as _, Result};
-use deltachat_contact_tools::EmailAddress;
+use deltachat_contact_tools::{ContactAddress, EmailAddress};
 use futures::TryStreamExt;
 use futures_lite::FutureExt;
+use sha2::{Digest, Sha256};
 
 use tokio::fs::{self, File};
+use tokio::io::AsyncReadExt;
 use tokio_tar::Archive;
 
+use crate::aheader::EncryptPreference;
 use crate::blob::BlobDirContents;
 use crate::chat::{self, delete_and_reset_all_device_msgs};
+use crate::config::Config;
+use crate::contact::{Contact, Origin};
 use crate::context::Context;
 use crate::e2ee;
 use crate::events::EventType;
-use crate::key::{self, DcKey, DcSecretKey, SignedPublicKey, SignedSecretKey};
+use crate::imap::outbox;
+use crate::key::{self, DcKey, DcSecretKey, Fingerprint, SignedPublicKey, SignedSecretKey};
 use crate::log::LogExt;
 use crate::message::{Message, Viewtype};
+use crate::peerstate::Peerstate;
 use crate::pgp;
 use crate::sql;
 use crate::tools::{
     create_folder, delete_file, get_filesuffix_lc, read_file, time, write_file, TempPathGuard,
 };
 
+mod chat_export;
+mod checkpoint;
 mod key_transfer;
+mod mail_import;
+mod peerstates;
+mod recovery;
 mod transfer;
+mod verify;
 
+pub use chat_export::{export_chat, export_mbox, save_as_eml};
+use checkpoint::ImportCheckpoint;
 pub use key_transfer::{continue_key_transfer, initiate_key_transfer};
+pub use mail_import::{import_eml, import_mail_file};
+pub use peerstates::import_peerstates;
+pub use recovery::RecoveryCode;
 pub use transfer::{get_backup, BackupProvider};
+pub use verify::{verify_backup, BackupReport};
 
 // Name of the database file in the backup.
 const DBFILE_BACKUP_NAME: &str = "dc_database_backup.sqlite";
 pub(crate) const BLOBS_BACKUP_NAME: &str = "blobs_backup";
 
+/// Name of the entry in the backup listing the SHA-256 hashes of the blobs.
+///
+/// The manifest is stored as a PAX global extended header,
+/// which is not unpacked by importers, including the ones of older versions,
+/// so it does not end up in the blobdir.
+const BLOBS_MANIFEST_NAME: &str = "blobs_manifest.json";
+
+/// PAX keyword of the manifest, its value is a JSON object mapping blob names to hashes.
+const BLOBS_MANIFEST_PAX_KEY: &str = "DELTACHAT.blobs_manifest";
+
 /// Import/export command.
 #[derive(Debug, Display, Copy, Clone, PartialEq, Eq, FromPrimitive, ToPrimitive)]
 #[repr(u32)]
@@ -44,15 +77,20 @@ pub enum ImexMode {
     /// directory given as `path`. The default key is written to the files
     /// `{public,private}-key-<addr>-default-<fingerprint>.asc`, if there are more keys, they are
     /// written to files as `{public,private}-key-<addr>-<id>-<fingerprint>.asc`.
+    /// If a non-empty `passphrase` is given, secret keys are symmetrically encrypted with it.
     ExportSelfKeys = 1,
 
     /// Import private keys found in `path` if it is a directory, otherwise import a private key
     /// from `path`.
     /// The last imported key is made the default keys unless its name contains the string `legacy`.
     /// Public keys are not imported.
+    /// Passphrase-protected key files are decrypted using the given `passphrase`.
     ImportSelfKeys = 2,
 
     /// Export a backup to the directory given as `path` with the given `passphrase`.
+    /// If the passphrase is not empty, the database in the backup,
+    /// including the secret keys, is encrypted with it.
+    /// Attachments in the backup are not encrypted.
     /// The backup contains all contacts, chats, images and other data and device independent settings.
     /// The backup does not contain device dependent settings as ringtones or LED notification settings.
     /// The name of the backup is `delta-chat-backup-<day>-<number>-<addr>.tar`.
@@ -61,6 +99,7 @@ pub enum ImexMode {
     /// `path` is the file (not: directory) to import. The file is normally
     /// created by DC_IMEX_EXPORT_BACKUP and detected by imex_has_backup(). Importing a backup
     /// is only possible as long as the context is not configured or used in another way.
+    /// A missing or wrong passphrase of an encrypted backup fails with [`BackupPassphraseError`].
     ImportBackup = 12,
 }
 
@@ -199,6 +238,386 @@ async fn set_self_key(context: &Context, armored: &str, set_default: bool) -> Re
     Ok(())
 }
 
+/// Key imported by [`Context::import_key_from_armored`].
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ImportedKey {
+    /// Fingerprint of the imported key.
+    pub fingerprint: Fingerprint,
+
+    /// User IDs of the imported key.
+    pub user_ids: Vec<String>,
+
+    /// True if a secret key was imported and made the default self key.
+    pub is_secret: bool,
+}
+
+/// Outcome of importing a single key with [`import_keys`].
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum KeyImportStatus {
+    /// The key was imported.
+    Imported,
+
+    /// The key was already imported before, nothing was changed.
+    Duplicate,
+
+    /// Another key is already known for an address of the key user IDs,
+    /// the key was not imported for this address.
+    Conflict {
+        /// Address with a conflicting key.
+        addr: String,
+
+        /// Fingerprint of the already known key.
+        existing: Fingerprint,
+    },
+
+    /// No contact matches the addresses of the key user IDs.
+    NoContact,
+
+    /// The key could not be imported.
+    Failed(String),
+}
+
+/// Result of importing a single key with [`import_keys`].
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct KeyringImportResult {
+    /// Fingerprint of the key, `None` if the key could not be parsed.
+    pub fingerprint: Option<Fingerprint>,
+
+    /// User IDs of the key.
+    pub user_ids: Vec<String>,
+
+    /// True if this is a secret key.
+    pub is_secret: bool,
+
+    /// Outcome of the import.
+    pub status: KeyImportStatus,
+}
+
+impl KeyringImportResult {
+    fn failed(is_secret: bool, err: anyhow::Error) -> Self {
+        Self {
+            fingerprint: None,
+            user_ids: Vec::new(),
+            is_secret,
+            status: KeyImportStatus::Failed(format!("{err:#}")),
+        }
+    }
+}
+
+/// Imports all keys from a keyring,
+/// e.g. exported from another OpenPGP application.
+///
+/// The keyring may be binary or consist of one or more ASCII-armored blocks.
+/// Public keys are assigned to the contacts matching the addresses in their user IDs.
+/// If `existing_contacts_only` is true, no new contacts are created,
+/// otherwise contacts are created as needed.
+/// Keys conflicting with an already known key of a contact are not imported,
+/// use [`Context::import_key_from_armored`] to replace the key explicitly.
+/// Secret keys are added to the self keys used for decryption,
+/// the default self key is not changed.
+///
+/// Returns the result for each key found in the keyring.
+/// Fails if the keyring does not contain any parseable key.
+pub async fn import_keys(
+    context: &Context,
+    keyring: &[u8],
+    existing_contacts_only: bool,
+) -> Result<Vec<KeyringImportResult>> {
+    let mut results = Vec::new();
+    match std::str::from_utf8(keyring)
+        .ok()
+        .filter(|s| s.contains("-----BEGIN PGP "))
+    {
+        Some(armored) => {
+            for block in split_armored_blocks(armored) {
+                if block.contains("PRIVATE KEY BLOCK") {
+                    let (keys, _) = SignedSecretKey::from_armor_many(block.as_bytes())?;
+                    for key in keys {
+                        results.push(match key {
+                            Ok(key) => import_keyring_secret_key(context, &key).await,
+                            Err(err) => KeyringImportResult::failed(true, err.into()),
+                        });
+                    }
+                } else {
+                    let (keys, _) = SignedPublicKey::from_armor_many(block.as_bytes())?;
+                    for key in keys {
+                        results.push(match key {
+                            Ok(key) => {
+                                import_keyring_public_key(context, &key, existing_contacts_only)
+                                    .await
+                            }
+                            Err(err) => KeyringImportResult::failed(false, err.into()),
+                        });
+                    }
+                }
+            }
+        }
+        None => {
+            for key in SignedPublicKey::from_bytes_many(keyring) {
+                results.push(match key {
+                    Ok(key) => {
+                        import_keyring_public_key(context, &key, existing_contacts_only).await
+                    }
+                    Err(err) => KeyringImportResult::failed(false, err.into()),
+                });
+            }
+        }
+    }
+    ensure!(
+        results.iter().any(|result| result.fingerprint.is_some()),
+        "No keys found in keyring"
+    );
+    Ok(results)
+}
+
+async fn import_keyring_public_key(
+    context: &Context,
+    public_key: &SignedPublicKey,
+    existing_contacts_only: bool,
+) -> KeyringImportResult {
+    let user_ids = key_user_ids(&public_key.details.users);
+    let status =
+        import_keyring_public_key_ex(context, public_key, &user_ids, existing_contacts_only)
+            .await
+            .unwrap_or_else(|err| KeyImportStatus::Failed(format!("{err:#}")));
+    KeyringImportResult {
+        fingerprint: Some(DcKey::fingerprint(public_key)),
+        user_ids,
+        is_secret: false,
+        status,
+    }
+}
+
+async fn import_keyring_public_key_ex(
+    context: &Context,
+    public_key: &SignedPublicKey,
+    user_ids: &[String],
+    existing_contacts_only: bool,
+) -> Result<KeyImportStatus> {
+    public_key.verify().context("Invalid public key")?;
+    let addrs = key_addrs(user_ids);
+    ensure!(
+        !addrs.is_empty(),
+        "Public key has no user ID with a valid address"
+    );
+
+    let fingerprint = DcKey::fingerprint(public_key);
+    let now = time();
+    let mut imported = false;
+    let mut duplicate = false;
+    let mut conflict = None;
+    for addr in &addrs {
+        if context.is_self_addr(addr).await? {
+            warn!(context, "Not importing public key for self address {addr}.");
+            continue;
+        }
+        if existing_contacts_only
+            && Contact::lookup_id_by_addr(context, addr, Origin::IncomingReplyTo)
+                .await?
+                .is_none()
+        {
+            continue;
+        }
+        let peerstate = Peerstate::from_addr(context, addr).await?;
+        if let Some(existing) = peerstate
+            .as_ref()
+            .and_then(|peerstate| peerstate.peek_key(false))
+            .map(DcKey::fingerprint)
+        {
+            if existing == fingerprint {
+                duplicate = true;
+            } else if conflict.is_none() {
+                conflict = Some(KeyImportStatus::Conflict {
+                    addr: addr.to_string(),
+                    existing,
+                });
+            }
+            continue;
+        }
+
+        Contact::add_or_lookup(context, "", addr, Origin::ManuallyCreated).await?;
+        let peerstate = match peerstate {
+            Some(mut peerstate) => {
+                peerstate.public_key = Some(public_key.clone());
+                peerstate.recalc_fingerprint();
+                peerstate
+            }
+            None => {
+                Peerstate::from_public_key(addr, now, EncryptPreference::NoPreference, public_key)
+            }
+        };
+        peerstate.save_to_db(&context.sql).await?;
+        info!(context, "Imported public key for {addr}.");
+        imported = true;
+    }
+
+    let status = if imported {
+        KeyImportStatus::Imported
+    } else if let Some(conflict) = conflict {
+        conflict
+    } else if duplicate {
+        KeyImportStatus::Duplicate
+    } else {
+        KeyImportStatus::NoContact
+    };
+    Ok(status)
+}
+
+async fn import_keyring_secret_key(
+    context: &Context,
+    secret: &SignedSecretKey,
+) -> KeyringImportResult {
+    let fingerprint = DcKey::fingerprint(secret);
+    let status = async {
+        secret.verify().context("Invalid secret key")?;
+        if key::load_self_secret_keyring(context)
+            .await?
+            .iter()
+            .any(|key| DcKey::fingerprint(key) == fingerprint)
+        {
+            return Ok(KeyImportStatus::Duplicate);
+        }
+        let set_default = false;
+        set_self_key(context, &secret.to_asc(None), set_default).await?;
+        Ok(KeyImportStatus::Imported)
+    }
+    .await
+    .unwrap_or_else(|err: anyhow::Error| KeyImportStatus::Failed(format!("{err:#}")));
+    KeyringImportResult {
+        fingerprint: Some(fingerprint),
+        user_ids: key_user_ids(&secret.details.users),
+        is_secret: true,
+        status,
+    }
+}
+
+impl Context {
+    /// Imports an ASCII-armored public or secret key from a string,
+    /// e.g. pasted from the clipboard.
+    ///
+    /// A secret key becomes the default self key,
+    /// previous self keys are kept for decryption.
+    /// A public key is assigned to the contacts
+    /// matching the addresses in its user IDs.
+    /// It is merged with their current public key if it has the same fingerprint,
+    /// e.g. to add a new subkey, otherwise it replaces the current public key.
+    pub async fn import_key_from_armored(&self, armored: &str) -> Result<ImportedKey> {
+        if armored.contains("PRIVATE KEY BLOCK") {
+            let (secret, _) = SignedSecretKey::from_asc(armored)?;
+            secret.verify().context("Invalid secret key")?;
+            let user_ids = key_user_ids(&secret.details.users);
+            let fingerprint = DcKey::fingerprint(&secret);
+            let set_default = true;
+            set_self_key(self, armored, set_default).await?;
+            return Ok(ImportedKey {
+                fingerprint,
+                user_ids,
+                is_secret: true,
+            });
+        }
+
+        let (public_key, _) = SignedPublicKey::from_asc(armored)?;
+        public_key.verify().context("Invalid public key")?;
+        let user_ids = key_user_ids(&public_key.details.users);
+        let addrs = key_addrs(&user_ids);
+        ensure!(
+            !addrs.is_empty(),
+            "Public key has no user ID with a valid address"
+        );
+
+        let now = time();
+        for addr in &addrs {
+            if self.is_self_addr(addr).await? {
+                warn!(self, "Not importing public key for self address {addr}.");
+                continue;
+            }
+            Contact::add_or_lookup(self, "", addr, Origin::ManuallyCreated).await?;
+            let peerstate = match Peerstate::from_addr(self, addr).await? {
+                Some(mut peerstate) => {
+                    peerstate.public_key = Some(merge_with_known_key(
+                        peerstate.public_key.as_ref(),
+                        &public_key,
+                    )?);
+                    if let Some(verified_key) = &peerstate.verified_key {
+                        if DcKey::fingerprint(verified_key) == DcKey::fingerprint(&public_key) {
+                            peerstate.verified_key =
+                                Some(key::merge_public_keys(verified_key, &public_key)?);
+                        }
+                    }
+                    peerstate.recalc_fingerprint();
+                    peerstate
+                }
+                None => Peerstate::from_public_key(
+                    addr,
+                    now,
+                    EncryptPreference::NoPreference,
+                    &public_key,
+                ),
+            };
+            peerstate.save_to_db(&self.sql).await?;
+            peerstate.handle_fingerprint_change(self, now, None).await?;
+            info!(self, "Imported public key for {addr}.");
+        }
+
+        Ok(ImportedKey {
+            fingerprint: DcKey::fingerprint(&public_key),
+            user_ids,
+            is_secret: false,
+        })
+    }
+}
+
+/// Returns `key` merged with `known_key` if both have the same fingerprint,
+/// otherwise `key`.
+fn merge_with_known_key(
+    known_key: Option<&SignedPublicKey>,
+    key: &SignedPublicKey,
+) -> Result<SignedPublicKey> {
+    match known_key {
+        Some(known_key) if DcKey::fingerprint(known_key) == DcKey::fingerprint(key) => {
+            key::merge_public_keys(known_key, key)
+        }
+        _ => Ok(key.clone()),
+    }
+}
+
+fn key_user_ids(users: &[::pgp::types::SignedUser]) -> Vec<String> {
+    users
+        .iter()
+        .map(|user| String::from_utf8_lossy(user.id.id().as_ref()).into_owned())
+        .collect()
+}
+
+/// Extracts the addresses from key user IDs like `Name <addr@example.org>`.
+fn key_addrs(user_ids: &[String]) -> Vec<ContactAddress> {
+    user_ids
+        .iter()
+        .filter_map(|user_id| {
+            let addr = match (user_id.rfind('<'), user_id.rfind('>')) {
+                (Some(start), Some(end)) if start < end => user_id.get(start + 1..end)?,
+                _ => user_id,
+            };
+            ContactAddress::new(addr).ok()
+        })
+        .collect()
+}
+
+/// Splits concatenated ASCII-armored blocks.
+fn split_armored_blocks(s: &str) -> Vec<&str> {
+    let starts: Vec<usize> = s
+        .match_indices("-----BEGIN PGP ")
+        .map(|(start, _)| start)
+        .collect();
+    starts
+        .iter()
+        .enumerate()
+        .filter_map(|(i, &start)| {
+            let end = starts.get(i + 1).copied().unwrap_or(s.len());
+            s.get(start..end)
+        })
+        .collect()
+}
+
 async fn imex_inner(
     context: &Context,
     what: ImexMode,
@@ -227,8 +646,12 @@ async fn imex_inner(
     }
 
     match what {
-        ImexMode::ExportSelfKeys => export_self_keys(context, path).await,
-        ImexMode::ImportSelfKeys => import_self_keys(context, path).await,
+        ImexMode::ExportSelfKeys => {
+            export_self_keys(context, path, passphrase.filter(|p| !p.is_empty())).await
+        }
+        ImexMode::ImportSelfKeys => {
+            import_self_keys(context, path, passphrase.filter(|p| !p.is_empty())).await
+        }
 
         ImexMode::ExportBackup => {
             export_backup(context, path, passphrase.unwrap_or_default()).await
@@ -269,7 +692,16 @@ async fn import_backup(
         context.get_dbfile().display()
     );
 
-    import_backup_stream(context, backup_file, file_size, passphrase).await?;
+    let checkpoint = ImportCheckpoint::load(context, backup_to_import).await?;
+    import_backup_stream_inner(
+        context,
+        backup_file,
+        file_size,
+        passphrase,
+        Some(checkpoint),
+    )
+    .await
+    .0?;
     Ok(())
 }
 
@@ -292,16 +724,26 @@ pub(crate) async fn import_backup_stream<R: tokio::io::AsyncRead + Unpin>(
     file_size: u64,
     passphrase: String,
 ) -> Result<()> {
-    import_backup_stream_inner(context, backup_file, file_size, passphrase)
+    import_backup_stream_inner(context, backup_file, file_size, passphrase, None)
         .await
         .0
 }
 
+/// Imports backup from a stream.
+///
+/// If `checkpoint` is set, the import is resumable:
+/// entries already unpacked according to the checkpoint are skipped
+/// and the progress is persisted.
+/// If unpacking is interrupted by an IO error or cancellation,
+/// unpacked files and the checkpoint are kept so the import can be resumed.
+/// If the unpacked database cannot be imported, all unpacked files are removed
+/// and the progress of the checkpoint is reset.
 async fn import_backup_stream_inner<R: tokio::io::AsyncRead + Unpin>(
     context: &Context,
     backup_file: R,
     file_size: u64,
     passphrase: String,
+    mut checkpoint: Option<ImportCheckpoint>,
 ) -> (Result<()>,) {
     let mut archive = Archive::new(backup_file);
 
@@ -309,11 +751,16 @@ async fn import_backup_stream_inner<R: tokio::io::AsyncRead + Unpin>(
         Ok(entries) => entries,
         Err(e) => return (Err(e).context("Failed to get archive entries"),),
     };
+    // The unpacked database may contain the private key unencrypted,
+    // so it is never left in the blobdir, even if the import is cancelled.
+    let unpacked_database = TempPathGuard::new(context.get_blobdir().join(DBFILE_BACKUP_NAME));
     let mut blobs = Vec::new();
     // We already emitted ImexProgress(10) above
     let mut last_progress = 10;
+    const PROGRESS_DATABASE: u128 = 950;
     const PROGRESS_MIGRATIONS: u128 = 999;
     let mut total_size: u64 = 0;
+    let mut entry_index: u64 = 0;
     let mut res: Result<()> = loop {
         let mut f = match entries.try_next().await {
             Ok(Some(f)) => f,
@@ -324,12 +771,17 @@ async fn import_backup_stream_inner<R: tokio::io::AsyncRead + Unpin>(
             Ok(size) => size,
             Err(e) => break Err(e).context("Failed to get entry size"),
         };
-        let max = PROGRESS_MIGRATIONS - 1;
+        let done = checkpoint
+            .as_ref()
+            .is_some_and(|checkpoint| entry_index < checkpoint.entries_done);
+        entry_index += 1;
+        let max = PROGRESS_DATABASE - 1;
         let progress = std::cmp::min(
             max * u128::from(total_size) / std::cmp::max(u128::from(file_size), 1),
             max,
         );
-        if progress > last_progress {
+        let progressed = progress > last_progress;
+        if progressed {
             context.emit_event(EventType::ImexProgress(progress as usize));
             last_progress = progress;
         }
@@ -338,50 +790,99 @@ async fn import_backup_stream_inner<R: tokio::io::AsyncRead + Unpin>(
             Ok(path) => path.to_path_buf(),
             Err(e) => break Err(e).context("Failed to get entry path"),
         };
-        if let Err(e) = f.unpack_in(context.get_blobdir()).await {
-            break Err(e).context("Failed to unpack file");
-        }
-        if path.file_name() == Some(OsStr::new(DBFILE_BACKUP_NAME)) {
+        if f.header().entry_type().is_pax_global_extensions() {
+            // The manifest is only needed to verify the backup.
             continue;
         }
-        // async_tar unpacked to $BLOBDIR/BLOBS_BACKUP_NAME/, so we move the file afterwards.
-        let from_path = context.get_blobdir().join(&path);
-        if from_path.is_file() {
-            if let Some(name) = from_path.file_name() {
-                let to_path = context.get_blobdir().join(name);
+        let is_database = path.file_name() == Some(OsStr::new(DBFILE_BACKUP_NAME));
+        if is_database {
+            let unpacked = checkpoint
+                .as_ref()
+                .is_some_and(|checkpoint| checkpoint.database_unpacked);
+            if !(done && unpacked && unpacked_database.exists()) {
+                if let Err(e) = f.unpack_in(context.get_blobdir()).await {
+                    break Err(e).context("Failed to unpack file");
+                }
+            }
+        } else if let Some(name) = path.file_name() {
+            let to_path = context.get_blobdir().join(name);
+            if done && to_path.exists() {
+                blobs.push(to_path);
+                continue;
+            }
+            if let Err(e) = f.unpack_in(context.get_blobdir()).await {
+                break Err(e).context("Failed to unpack file");
+            }
+            // async_tar unpacked to $BLOBDIR/BLOBS_BACKUP_NAME/, so we move the file afterwards.
+            let from_path = context.get_blobdir().join(&path);
+            if from_path.is_file() {
                 if let Err(e) = fs::rename(&from_path, &to_path).await {
                     blobs.push(from_path);
                     break Err(e).context("Failed to move file to blobdir");
                 }
                 blobs.push(to_path);
-            } else {
-                warn!(context, "No file name");
+            }
+        } else {
+            warn!(context, "No file name");
+        }
+
+        if let Some(checkpoint) = &mut checkpoint {
+            checkpoint.entries_done = entry_index;
+            checkpoint.bytes_done = total_size;
+            checkpoint.database_unpacked |= is_database;
+            // Saving the checkpoint once per progress step is enough,
+            // at most a few entries are unpacked again after an interruption.
+            if progressed || is_database {
+                if let Err(e) = checkpoint.save(context).await {
+                    break Err(e);
+                }
             }
         }
     };
-    if res.is_err() {
-        for blob in blobs {
-            fs::remove_file(&blob).await.log_err(context).ok();
+    if let Err(err) = &res {
+        if let Some(checkpoint) = &checkpoint {
+            // Unpacking was interrupted, e.g. because the backup is on a removed storage.
+            // Keep the unpacked files so that the import is resumed from here.
+            warn!(
+                context,
+                "Backup import interrupted after {} entries: {err:#}.", checkpoint.entries_done
+            );
+            checkpoint.save(context).await.log_err(context).ok();
+            return (res,);
         }
-    }
-
-    let unpacked_database = context.get_blobdir().join(DBFILE_BACKUP_NAME);
-    if res.is_ok() {
+    } else {
+        context.emit_event(EventType::ImexProgress(PROGRESS_DATABASE as usize));
         res = context
             .sql
             .import(&unpacked_database, passphrase.clone())
             .await
             .context("cannot import unpacked database");
     }
-    fs::remove_file(unpacked_database)
-        .await
-        .context("cannot remove unpacked database")
-        .log_err(context)
-        .ok();
+    drop(unpacked_database);
+    if res.is_err() {
+        for blob in blobs {
+            fs::remove_file(&blob).await.log_err(context).ok();
+        }
+        if let Some(checkpoint) = &mut checkpoint {
+            checkpoint.reset();
+            checkpoint.save(context).await.log_err(context).ok();
+        }
+    }
     if res.is_ok() {
+        if checkpoint.is_some() {
+            ImportCheckpoint::remove(context).await;
+        }
         context.emit_event(EventType::ImexProgress(PROGRESS_MIGRATIONS as usize));
         res = context.sql.run_migrations(context).await;
     }
+    if res.is_ok() {
+        // The imported database belongs to another device,
+        // so drafts claimed by it must not be considered claimed by this one.
+        res = context
+            .sql
+            .set_raw_config(outbox::DEVICE_ID_CFG, None)
+            .await;
+    }
     if res.is_ok() {
         delete_and_reset_all_device_msgs(context)
             .await
@@ -428,10 +929,34 @@ fn get_next_backup_path(
     bail!("could not create backup file, disk full?");
 }
 
+/// Backup with the database exported, ready to be archived together with the blobs.
+struct PreparedBackup<'a> {
+    /// Exported database.
+    temp_db_path: TempPathGuard,
+
+    /// Path the archive is written to.
+    temp_path: TempPathGuard,
+
+    /// Path the archive is moved to when it is complete.
+    dest_path: PathBuf,
+
+    blobdir: BlobDirContents<'a>,
+}
+
 /// Exports the database to a separate file with the given passphrase.
 ///
 /// Set passphrase to empty string to export the database unencrypted.
 async fn export_backup(context: &Context, dir: &Path, passphrase: String) -> Result<()> {
+    let backup = prepare_backup(context, dir, passphrase).await?;
+    write_backup(context, backup).await
+}
+
+/// Exports the database into the backup directory `dir`, pausing IO meanwhile.
+async fn prepare_backup<'a>(
+    context: &'a Context,
+    dir: &Path,
+    passphrase: String,
+) -> Result<PreparedBackup<'a>> {
     // get a fine backup file name (the name includes the date so that multiple backup instances are possible)
     let now = time();
     let self_addr = context.get_primary_self_addr().await?;
@@ -439,10 +964,42 @@ async fn export_backup(context: &Context, dir: &Path, passphrase: String) -> Res
     let temp_db_path = TempPathGuard::new(temp_db_path);
     let temp_path = TempPathGuard::new(temp_path);
 
-    export_database(context, &temp_db_path, passphrase, now)
-        .await
-        .context("could not export database")?;
+    // The database is exported to the backup directory and then archived together with the blobs,
+    // so both are there at the same time.
+    let blobdir = BlobDirContents::new(context).await?;
+    let db_size = fs::metadata(context.get_dbfile()).await?.len();
+    let mut required = db_size.saturating_mul(2);
+    for blob in blobdir.iter() {
+        let blob_size = fs::metadata(blob.to_abs_path()).await?.len();
+        required = required.saturating_add(blob_size);
+    }
+    context.ensure_disk_space_in(dir, required).await?;
+
+    {
+        // IO only needs to be paused while the database is exported,
+        // see `auto_backup()` which does not pause IO otherwise.
+        let _guard = context.scheduler.pause(context.clone()).await?;
+        export_database(context, &temp_db_path, passphrase, now)
+            .await
+            .context("could not export database")?;
+    }
 
+    Ok(PreparedBackup {
+        temp_db_path,
+        temp_path,
+        dest_path,
+        blobdir,
+    })
+}
+
+/// Archives the exported database together with the blobs.
+async fn write_backup(context: &Context, backup: PreparedBackup<'_>) -> Result<()> {
+    let PreparedBackup {
+        temp_db_path,
+        temp_path,
+        dest_path,
+        blobdir,
+    } = backup;
     info!(
         context,
         "Backup '{}' to '{}'.",
@@ -451,7 +1008,6 @@ async fn export_backup(context: &Context, dir: &Path, passphrase: String) -> Res
     );
 
     let file = File::create(&temp_path).await?;
-    let blobdir = BlobDirContents::new(context).await?;
     export_backup_stream(context, &temp_db_path, blobdir, file)
         .await
         .context("Exporting backup to file failed")?;
@@ -477,11 +1033,19 @@ pub(crate) async fn export_backup_stream<'a, W>(
         .await?;
 
     let mut last_progress = 10;
+    let mut manifest = BTreeMap::new();
 
     for (i, blob) in blobdir.iter().enumerate() {
-        let mut file = File::open(blob.to_abs_path()).await?;
+        let file = File::open(blob.to_abs_path()).await?;
+        let mut header = tokio_tar::Header::new_gnu();
+        header.set_metadata(&file.metadata().await?);
+        // The blob is hashed while it is archived, so it is only read once.
+        let mut reader = HashingReader::new(file);
         let path_in_archive = PathBuf::from(BLOBS_BACKUP_NAME).join(blob.as_name());
-        builder.append_file(path_in_archive, &mut file).await?;
+        builder
+            .append_data(&mut header, path_in_archive, &mut reader)
+            .await?;
+        manifest.insert(blob.as_file_name().to_string(), reader.finalize());
         let progress = std::cmp::min(1000 * i / blobdir.len(), 999);
         if progress > last_progress {
             context.emit_event(EventType::ImexProgress(progress));
@@ -489,14 +1053,246 @@ pub(crate) async fn export_backup_stream<'a, W>(
         }
     }
 
+    let manifest = pax_record(BLOBS_MANIFEST_PAX_KEY, &serde_json::to_string(&manifest)?);
+    let mut header = tokio_tar::Header::new_ustar();
+    header.set_entry_type(tokio_tar::EntryType::XGlobalHeader);
+    header.set_size(manifest.len() as u64);
+    header.set_mode(0o644);
+    header.set_mtime(time().try_into().unwrap_or_default());
+    builder
+        .append_data(&mut header, BLOBS_MANIFEST_NAME, manifest.as_bytes())
+        .await?;
+
     builder.finish().await?;
     Ok(())
 }
 
+/// Returns a PAX extended header record, see
+/// <https://pubs.opengroup.org/onlinepubs/9699919799/utilities/pax.html#tag_20_92_13_03>.
+fn pax_record(key: &str, value: &str) -> String {
+    // The length includes the decimal length itself, a space, `=` and the newline.
+    let rest = key.len() + value.len() + 3;
+    let mut len = rest + 1;
+    while len != rest + len.to_string().len() {
+        len = rest + len.to_string().len();
+    }
+    format!("{len} {key}={value}\n")
+}
+
+/// Returns the value of `key` from PAX extended header records.
+fn parse_pax_record<'a>(data: &'a str, key: &str) -> Option<&'a str> {
+    let mut data = data;
+    while let Some((len, rest)) = data.split_once(' ') {
+        let len: usize = len.parse().ok()?;
+        let record = rest.get(..len.checked_sub(data.len() - rest.len())?)?;
+        let (record_key, value) = record.strip_suffix('\n')?.split_once('=')?;
+        if record_key == key {
+            return Some(value);
+        }
+        data = data.get(len..)?;
+    }
+    None
+}
+
+/// Reader computing the SHA-256 hash of the data read through it.
+struct HashingReader<R> {
+    inner: R,
+    hasher: Sha256,
+}
+
+impl<R> HashingReader<R> {
+    fn new(inner: R) -> Self {
+        Self {
+            inner,
+            hasher: Sha256::new(),
+        }
+    }
+
+    /// Returns the hex-encoded hash of the data read so far.
+    fn finalize(self) -> String {
+        hex::encode(self.hasher.finalize())
+    }
+}
+
+impl<R: tokio::io::AsyncRead + Unpin> tokio::io::AsyncRead for HashingReader<R> {
+    fn poll_read(
+        mut self: Pin<&mut Self>,
+        cx: &mut std::task::Context<'_>,
+        buf: &mut tokio::io::ReadBuf<'_>,
+    ) -> Poll<std::io::Result<()>> {
+        let filled = buf.filled().len();
+        let res = Pin::new(&mut self.inner).poll_read(cx, buf);
+        if let Poll::Ready(Ok(())) = res {
+            let data = buf.filled().get(filled..).unwrap_or_default();
+            self.hasher.update(data);
+        }
+        res
+    }
+}
+
+/// Returns the hex-encoded SHA-256 hash of the contents of `reader`.
+async fn sha256_hex<R: tokio::io::AsyncRead + Unpin>(mut reader: R) -> Result<String> {
+    let mut hasher = Sha256::new();
+    let mut buf = vec![0; 64 * 1024];
+    loop {
+        let n = reader.read(&mut buf).await?;
+        if n == 0 {
+            break;
+        }
+        hasher.update(buf.get(..n).unwrap_or_default());
+    }
+    Ok(hex::encode(hasher.finalize()))
+}
+
+/// Starts an automatic backup in the background
+/// if [`Config::AutoBackupInterval`] has passed since the last one.
+pub(crate) async fn maybe_start_auto_backup(context: &Context) -> Result<()> {
+    let interval = context.get_config_i64(Config::AutoBackupInterval).await?;
+    if interval <= 0 {
+        return Ok(());
+    }
+    let Some(dir) = context
+        .get_config(Config::AutoBackupDir)
+        .await?
+        .filter(|dir| !dir.is_empty())
+    else {
+        return Ok(());
+    };
+    let last_backup = context.get_config_i64(Config::LastAutoBackup).await?;
+    if last_backup.saturating_add(interval) > time() {
+        return Ok(());
+    }
+
+    // Setting `Config::LastAutoBackup` before exporting
+    // avoids retrying failing backups in a loop.
+    context
+        .set_config_internal(Config::LastAutoBackup, Some(&time().to_string()))
+        .await?;
+    let keep = context.get_config_int(Config::AutoBackupKeep).await?;
+    let keep = usize::try_from(keep).unwrap_or_default();
+    let context = context.clone();
+    tokio::spawn(async move {
+        auto_backup(&context, Path::new(&dir), keep)
+            .await
+            .context("Automatic backup failed")
+            .log_err(&context)
+            .ok();
+    });
+    Ok(())
+}
+
+/// Exports a backup to `dir` and deletes all but the `keep` newest backups of the account there.
+///
+/// Unlike [`imex`], IO is only paused while the database is exported,
+/// not while the blobs are archived.
+/// Likewise, the ongoing process is only allocated while the database is exported,
+/// so archiving the blobs does not block other ongoing processes
+/// and cannot be canceled by [`Context::stop_ongoing`].
+///
+/// `keep` set to 0 keeps all backups.
+async fn auto_backup(context: &Context, dir: &Path, keep: usize) -> Result<()> {
+    info!(context, "Starting automatic backup to {}.", dir.display());
+    let cancel = context.alloc_ongoing().await?;
+    let res = async {
+        e2ee::ensure_secret_key_exists(context)
+            .await
+            .context("Cannot create private key or private key not available")?;
+        create_folder(context, &dir).await?;
+        prepare_backup(context, dir, String::new()).await
+    }
+    .race(async {
+        cancel.recv().await.ok();
+        Err(format_err!("canceled"))
+    })
+    .await;
+    context.free_ongoing().await;
+    write_backup(context, res?).await?;
+    if keep > 0 {
+        delete_old_backups(context, dir, keep).await?;
+    }
+    Ok(())
+}
+
+/// Deletes all but the `keep` newest backups of the account from `dir`.
+async fn delete_old_backups(context: &Context, dir: &Path, keep: usize) -> Result<()> {
+    let self_addr = context.get_primary_self_addr().await?;
+    let suffix = format!("-{self_addr}.tar");
+    let mut backups = Vec::new();
+    let mut dir_iter = fs::read_dir(dir).await?;
+    while let Some(entry) = dir_iter.next_entry().await? {
+        let name = entry.file_name();
+        let Some(name) = name.to_str() else {
+            continue;
+        };
+        if name.starts_with("delta-chat-backup-") && name.ends_with(&suffix) {
+            let modified = entry.metadata().await?.modified()?;
+            backups.push((modified, entry.path()));
+        }
+    }
+
+    // File names are not sorted chronologically
+    // because `get_next_backup_path()` reuses the counters of deleted backups.
+    backups.sort_unstable();
+    let outdated = backups.len().saturating_sub(keep);
+    for (_, path) in backups.into_iter().take(outdated) {
+        info!(context, "Deleting outdated backup {}.", path.display());
+        fs::remove_file(&path)
+            .await
+            .with_context(|| format!("Failed to delete {}", path.display()))?;
+    }
+    Ok(())
+}
+
+/// Error returned when a backup cannot be imported
+/// because the passphrase is missing or wrong.
+#[derive(Debug, thiserror::Error)]
+pub enum BackupPassphraseError {
+    /// The backup is passphrase-protected, but no passphrase was given.
+    #[error("Backup is passphrase-protected, passphrase required")]
+    Missing,
+
+    /// The given passphrase does not decrypt the backup.
+    #[error("Wrong backup passphrase")]
+    Wrong,
+}
+
+/// Error returned when a passphrase-protected key file
+/// cannot be imported because the passphrase is missing or wrong.
+#[derive(Debug, thiserror::Error)]
+pub enum KeyPassphraseError {
+    /// The key file is passphrase-protected, but no passphrase was given.
+    #[error("Key file {0} is passphrase-protected, passphrase required")]
+    Missing(String),
+
+    /// The given passphrase does not decrypt the key file.
+    #[error("Wrong passphrase for key file {0}")]
+    Wrong(String),
+}
+
 /// Imports secret key from a file.
-async fn import_secret_key(context: &Context, path: &Path, set_default: bool) -> Result<()> {
+///
+/// If the file contains a symmetrically encrypted message
+/// as written by [`export_self_keys`] with a passphrase,
+/// it is decrypted using `passphrase` first.
+async fn import_secret_key(
+    context: &Context,
+    path: &Path,
+    set_default: bool,
+    passphrase: Option<&str>,
+) -> Result<()> {
     let buf = read_file(context, &path).await?;
     let armored = std::string::String::from_utf8_lossy(&buf);
+    let armored = if armored.contains("-----BEGIN PGP MESSAGE-----") {
+        let Some(passphrase) = passphrase else {
+            return Err(KeyPassphraseError::Missing(path.display().to_string()).into());
+        };
+        let plain = pgp::symm_decrypt(passphrase, std::io::Cursor::new(buf.clone()))
+            .await
+            .map_err(|_| KeyPassphraseError::Wrong(path.display().to_string()))?;
+        std::string::String::from_utf8(plain).context("Decrypted key file is not valid UTF-8")?
+    } else {
+        armored.into_owned()
+    };
     set_self_key(context, &armored, set_default).await?;
     Ok(())
 }
@@ -510,7 +1306,16 @@ async fn import_secret_key(context: &Context, path: &Path, set_default: bool) ->
 /// containing secret keys are imported and the last successfully
 /// imported which does not contain "legacy" in its filename
 /// is set as the default.
-async fn import_self_keys(context: &Context, path: &Path) -> Result<()> {
+///
+/// Passphrase-protected files are decrypted with `passphrase`.
+/// A missing or wrong passphrase aborts the import
+/// with [`KeyPassphraseError`] instead of skipping the file.
+async fn import_self_keys(
+    context: &Context,
+    path: &Path,
+    passphrase: Option<String>,
+) -> Result<()> {
+    let passphrase = passphrase.as_deref();
     let attr = tokio::fs::metadata(path).await?;
 
     if attr.is_file() {
@@ -520,7 +1325,7 @@ async fn import_self_keys(context: &Context, path: &Path) -> Result<()> {
             path.display()
         );
         let set_default = true;
-        import_secret_key(context, path, set_default).await?;
+        import_secret_key(context, path, set_default, passphrase).await?;
         return Ok(());
     }
 
@@ -545,7 +1350,11 @@ async fn import_self_keys(context: &Context, path: &Path) -> Result<()> {
             path_plus_name.display()
         );
 
-        if let Err(err) = import_secret_key(context, &path_plus_name, set_default).await {
+        if let Err(err) = import_secret_key(context, &path_plus_name, set_default, passphrase).await
+        {
+            if err.is::<KeyPassphraseError>() {
+                return Err(err);
+            }
             warn!(
                 context,
                 "Failed to import secret key from {}: {:#}.",
@@ -565,7 +1374,11 @@ async fn import_self_keys(context: &Context, path: &Path) -> Result<()> {
     Ok(())
 }
 
-async fn export_self_keys(context: &Context, dir: &Path) -> Result<()> {
+/// Exports all self keys to `dir`.
+///
+/// If `passphrase` is set, secret keys are written
+/// as symmetrically encrypted ASCII-armored messages.
+async fn export_self_keys(context: &Context, dir: &Path, passphrase: Option<String>) -> Result<()> {
     let mut export_errors = 0;
 
     let keys = context
@@ -594,7 +1407,8 @@ async fn export_self_keys(context: &Context, dir: &Path) -> Result<()> {
         let id = Some(id).filter(|_| is_default == 0);
 
         if let Ok(key) = public_key {
-            if let Err(err) = export_key_to_asc_file(context, dir, &self_addr, id, &key).await {
+            if let Err(err) = export_key_to_asc_file(context, dir, &self_addr, id, &key, None).await
+            {
                 error!(context, "Failed to export public key: {:#}.", err);
                 export_errors += 1;
             }
@@ -602,7 +1416,10 @@ async fn export_self_keys(context: &Context, dir: &Path) -> Result<()> {
             export_errors += 1;
         }
         if let Ok(key) = private_key {
-            if let Err(err) = export_key_to_asc_file(context, dir, &self_addr, id, &key).await {
+            if let Err(err) =
+                export_key_to_asc_file(context, dir, &self_addr, id, &key, passphrase.as_deref())
+                    .await
+            {
                 error!(context, "Failed to export private key: {:#}.", err);
                 export_errors += 1;
             }
@@ -616,12 +1433,15 @@ async fn export_self_keys(context: &Context, dir: &Path) -> Result<()> {
 }
 
 /// Returns the exported key file name inside `dir`.
+///
+/// If `passphrase` is set, the armored key is symmetrically encrypted with it.
 async fn export_key_to_asc_file<T>(
     context: &Context,
     dir: &Path,
     addr: &str,
     id: Option<i64>,
     key: &T,
+    passphrase: Option<&str>,
 ) -> Result<String>
 where
     T: DcKey,
@@ -646,7 +1466,12 @@ async fn export_key_to_asc_file<T>(
     // Delete the file if it already exists.
     delete_file(context, &path).await.ok();
 
-    let content = key.to_asc(None).into_bytes();
+    let content = match passphrase {
+        Some(passphrase) => pgp::symm_encrypt(passphrase, key.to_asc(None).as_bytes())
+            .await?
+            .into_bytes(),
+        None => key.to_asc(None).into_bytes(),
+    };
     write_file(context, &path, &content)
         .await
         .with_context(|| format!("cannot write key to {}", path.display()))?;
@@ -713,15 +1538,30 @@ mod tests {
     use tokio::task;
 
     use super::*;
-    use crate::config::Config;
     use crate::test_utils::{alice_keypair, TestContext};
 
+    #[test]
+    fn test_pax_record() {
+        assert_eq!(pax_record("k", "v"), "6 k=v\n");
+        assert_eq!(pax_record("key", "1234"), "12 key=1234\n");
+        let value = "x".repeat(95);
+        let record = pax_record("k", &value);
+        assert_eq!(record.len(), 102);
+        assert!(record.starts_with("102 k="));
+
+        let data = format!("{}{record}", pax_record("other", "a=b"));
+        assert_eq!(parse_pax_record(&data, "other"), Some("a=b"));
+        assert_eq!(parse_pax_record(&data, "k"), Some(value.as_str()));
+        assert_eq!(parse_pax_record(&data, "missing"), None);
+        assert_eq!(parse_pax_record("99 k=v\n", "k"), None);
+    }
+
     #[tokio::test(flavor = "multi_thread", worker_threads = 2)]
     async fn test_export_public_key_to_asc_file() {
         let context = TestContext::new().await;
         let key = alice_keypair().public;
         let blobdir = Path::new("$BLOBDIR");
-        let filename = export_key_to_asc_file(&context.ctx, blobdir, "a@b", None, &key)
+        let filename = export_key_to_asc_file(&context.ctx, blobdir, "a@b", None, &key, None)
             .await
             .unwrap();
         assert!(filename.starts_with("public-key-a@b-default-"));
@@ -738,7 +1578,7 @@ async fn test_import_private_key_exported_to_asc_file() {
         let context = TestContext::new().await;
         let key = alice_keypair().secret;
         let blobdir = Path::new("$BLOBDIR");
-        let filename = export_key_to_asc_file(&context.ctx, blobdir, "a@b", None, &key)
+        let filename = export_key_to_asc_file(&context.ctx, blobdir, "a@b", None, &key, None)
             .await
             .unwrap();
         let fingerprint = filename
@@ -788,6 +1628,158 @@ async fn test_export_and_import_key_from_dir() {
         }
     }
 
+    #[tokio::test(flavor = "multi_thread", worker_threads = 2)]
+    async fn test_export_and_import_key_with_passphrase() -> Result<()> {
+        let export_dir = tempfile::tempdir()?;
+        let alice = &TestContext::new_alice().await;
+        imex(
+            alice,
+            ImexMode::ExportSelfKeys,
+            export_dir.path(),
+            Some("secret".to_string()),
+        )
+        .await?;
+
+        let alice2 = &TestContext::new().await;
+        alice2.configure_addr("alice@example.org").await;
+        let err = imex(alice2, ImexMode::ImportSelfKeys, export_dir.path(), None)
+            .await
+            .unwrap_err();
+        assert!(matches!(
+            err.downcast_ref::<KeyPassphraseError>(),
+            Some(KeyPassphraseError::Missing(_))
+        ));
+        let err = imex(
+            alice2,
+            ImexMode::ImportSelfKeys,
+            export_dir.path(),
+            Some("wrong".to_string()),
+        )
+        .await
+        .unwrap_err();
+        assert!(matches!(
+            err.downcast_ref::<KeyPassphraseError>(),
+            Some(KeyPassphraseError::Wrong(_))
+        ));
+
+        imex(
+            alice2,
+            ImexMode::ImportSelfKeys,
+            export_dir.path(),
+            Some("secret".to_string()),
+        )
+        .await?;
+        assert_eq!(
+            key::load_self_secret_key(alice2).await?,
+            key::load_self_secret_key(alice).await?
+        );
+        Ok(())
+    }
+
+    #[tokio::test(flavor = "multi_thread", worker_threads = 2)]
+    async fn test_import_key_from_armored() -> Result<()> {
+        let alice = &TestContext::new_alice().await;
+        let bob_public = include_str!("../test-data/key/bob-public.asc");
+        let imported = alice.import_key_from_armored(bob_public).await?;
+        assert!(!imported.is_secret);
+        assert!(imported
+            .user_ids
+            .iter()
+            .any(|user_id| user_id.contains("bob@example.net")));
+        let peerstate = Peerstate::from_addr(alice, "bob@example.net")
+            .await?
+            .unwrap();
+        assert_eq!(peerstate.public_key_fingerprint, Some(imported.fingerprint));
+
+        let t = &TestContext::new().await;
+        t.configure_addr("alice@example.org").await;
+        let alice_secret = include_str!("../test-data/key/alice-secret.asc");
+        let imported = t.import_key_from_armored(alice_secret).await?;
+        assert!(imported.is_secret);
+        assert_eq!(
+            imported.fingerprint,
+            DcKey::fingerprint(&key::load_self_public_key(t).await?)
+        );
+
+        assert!(t.import_key_from_armored("garbage").await.is_err());
+        Ok(())
+    }
+
+    #[tokio::test(flavor = "multi_thread", worker_threads = 2)]
+    async fn test_import_keys() -> Result<()> {
+        let alice = &TestContext::new_alice().await;
+        let keyring = [
+            include_str!("../test-data/key/bob-public.asc"),
+            include_str!("../test-data/key/charlie-public.asc"),
+            include_str!("../test-data/key/dom-public.asc"),
+            include_str!("../test-data/key/fiona-secret.asc"),
+        ]
+        .concat();
+
+        // Bob already has another key.
+        let (charlie_key, _) =
+            SignedPublicKey::from_asc(include_str!("../test-data/key/charlie-public.asc"))?;
+        Contact::create(alice, "", "bob@example.net").await?;
+        Peerstate::from_public_key(
+            "bob@example.net",
+            0,
+            EncryptPreference::Mutual,
+            &charlie_key,
+        )
+        .save_to_db(&alice.sql)
+        .await?;
+        Contact::create(alice, "", "dom@example.net").await?;
+
+        let results = import_keys(alice, keyring.as_bytes(), true).await?;
+        let statuses: Vec<_> = results.iter().map(|r| r.status.clone()).collect();
+        assert_eq!(
+            statuses,
+            vec![
+                KeyImportStatus::Conflict {
+                    addr: "bob@example.net".to_string(),
+                    existing: DcKey::fingerprint(&charlie_key),
+                },
+                KeyImportStatus::NoContact,
+                KeyImportStatus::Imported,
+                KeyImportStatus::Imported,
+            ]
+        );
+        assert!(results.last().unwrap().is_secret);
+        assert_eq!(key::load_self_secret_keyring(alice).await?.len(), 2);
+        assert!(Peerstate::from_addr(alice, "dom@example.net")
+            .await?
+            .is_some());
+        assert!(Peerstate::from_addr(alice, "charlie@example.net")
+            .await?
+            .is_none());
+
+        let results = import_keys(alice, keyring.as_bytes(), false).await?;
+        let statuses: Vec<_> = results.iter().map(|r| r.status.clone()).collect();
+        assert_eq!(
+            statuses,
+            vec![
+                KeyImportStatus::Conflict {
+                    addr: "bob@example.net".to_string(),
+                    existing: DcKey::fingerprint(&charlie_key),
+                },
+                KeyImportStatus::Imported,
+                KeyImportStatus::Duplicate,
+                KeyImportStatus::Duplicate,
+            ]
+        );
+        assert!(
+            Contact::lookup_id_by_addr(alice, "charlie@example.net", Origin::ManuallyCreated)
+                .await?
+                .is_some()
+        );
+
+        assert!(import_keys(alice, b"garbage", false).await.is_err());
+        assert!(import_keys(alice, b"-----BEGIN PGP garbage", false)
+            .await
+            .is_err());
+        Ok(())
+    }
+
     #[tokio::test(flavor = "multi_thread", worker_threads = 2)]
     async fn test_import_second_key() -> Result<()> {
         let alice = &TestContext::new_alice().await;
@@ -851,14 +1843,18 @@ async fn test_export_and_import_backup() -> Result<()> {
             let backup = has_backup(&context2, backup_dir.path()).await?;
 
             // Import of unencrypted backup with incorrect "foobar" backup passphrase fails.
-            assert!(imex(
+            let err = imex(
                 &context2,
                 ImexMode::ImportBackup,
                 backup.as_ref(),
-                Some("foobar".to_string())
+                Some("foobar".to_string()),
             )
             .await
-            .is_err());
+            .unwrap_err();
+            assert!(matches!(
+                err.downcast_ref::<BackupPassphraseError>(),
+                Some(BackupPassphraseError::Wrong)
+            ));
 
             assert!(
                 imex(&context2, ImexMode::ImportBackup, backup.as_ref(), None)
@@ -891,6 +1887,156 @@ async fn test_export_and_import_backup() -> Result<()> {
         Ok(())
     }
 
+    #[tokio::test(flavor = "multi_thread", worker_threads = 2)]
+    async fn test_auto_backup_keeps_newest() -> Result<()> {
+        let alice = TestContext::new_alice().await;
+        let backup_dir = tempfile::tempdir()?;
+        let other_backup = backup_dir
+            .path()
+            .join("delta-chat-backup-2020-01-01-00-bob@example.net.tar");
+        fs::write(&other_backup, b"").await?;
+
+        async fn backup_names(dir: &Path) -> Result<Vec<String>> {
+            let mut names = Vec::new();
+            let mut dir_iter = fs::read_dir(dir).await?;
+            while let Some(entry) = dir_iter.next_entry().await? {
+                names.push(entry.file_name().to_string_lossy().to_string());
+            }
+            names.sort();
+            Ok(names)
+        }
+
+        for _ in 0..3 {
+            auto_backup(&alice, backup_dir.path(), 2).await?;
+        }
+        let names = backup_names(backup_dir.path()).await?;
+        assert_eq!(names.len(), 3);
+        assert!(names[0].ends_with("-bob@example.net.tar"));
+        assert!(names[1].ends_with("-01-alice@example.org.tar"));
+        assert!(names[2].ends_with("-02-alice@example.org.tar"));
+
+        // The next backup reuses the file name of the deleted one,
+        // but is the newest and must be kept.
+        auto_backup(&alice, backup_dir.path(), 2).await?;
+        let names = backup_names(backup_dir.path()).await?;
+        assert_eq!(names.len(), 3);
+        assert!(names[0].ends_with("-bob@example.net.tar"));
+        assert!(names[1].ends_with("-00-alice@example.org.tar"));
+        assert!(names[2].ends_with("-02-alice@example.org.tar"));
+        Ok(())
+    }
+
+    /// Reader which is exhausted and fails or never returns.
+    struct InterruptedReader {
+        fail: bool,
+    }
+
+    impl tokio::io::AsyncRead for InterruptedReader {
+        fn poll_read(
+            self: Pin<&mut Self>,
+            _cx: &mut std::task::Context<'_>,
+            _buf: &mut tokio::io::ReadBuf<'_>,
+        ) -> Poll<std::io::Result<()>> {
+            if self.fail {
+                Poll::Ready(Err(std::io::Error::other("Storage removed")))
+            } else {
+                Poll::Pending
+            }
+        }
+    }
+
+    #[tokio::test(flavor = "multi_thread", worker_threads = 2)]
+    async fn test_resume_import_backup() -> Result<()> {
+        let backup_dir = tempfile::tempdir()?;
+        let alice = TestContext::new_alice().await;
+        let chat = alice.get_self_chat().await;
+        let names = ["file0.txt", "file1.txt", "file2.txt"];
+        for (i, name) in names.iter().enumerate() {
+            let file = alice.get_blobdir().join(name);
+            fs::write(&file, vec![b'a' + i as u8; 50_000]).await?;
+            let mut msg = Message::new(Viewtype::File);
+            msg.set_file(file.to_str().unwrap(), None);
+            chat::send_msg(&alice, chat.id, &mut msg).await?;
+        }
+        imex(&alice, ImexMode::ExportBackup, backup_dir.path(), None).await?;
+        let backup = has_backup(&alice, backup_dir.path()).await?;
+        let backup = Path::new(&backup);
+        let file_size = fs::metadata(backup).await?.len();
+
+        // The import fails after unpacking the archive.
+        // Nothing unpacked is left behind.
+        let t = TestContext::new().await;
+        assert!(imex(
+            &t,
+            ImexMode::ImportBackup,
+            backup,
+            Some("foobar".to_string())
+        )
+        .await
+        .is_err());
+        let checkpoint = ImportCheckpoint::load(&t, backup).await?;
+        assert!(!checkpoint.database_unpacked);
+        assert_eq!(checkpoint.entries_done, 0);
+        for name in names {
+            assert!(!t.get_blobdir().join(name).exists());
+        }
+        assert!(!t.get_blobdir().join(DBFILE_BACKUP_NAME).exists());
+
+        // The import is cancelled while the last blob is read,
+        // the database and the other blobs are unpacked.
+        let interrupted_import = |fail| {
+            let t = &t;
+            async move {
+                let reader = File::open(backup)
+                    .await?
+                    .take(file_size - 30_000)
+                    .chain(InterruptedReader { fail });
+                let checkpoint = ImportCheckpoint::load(t, backup).await?;
+                import_backup_stream_inner(t, reader, file_size, String::new(), Some(checkpoint))
+                    .await
+                    .0
+            }
+        };
+        assert!(
+            tokio::time::timeout(Duration::from_secs(1), interrupted_import(false))
+                .await
+                .is_err()
+        );
+        let checkpoint = ImportCheckpoint::load(&t, backup).await?;
+        assert!(checkpoint.database_unpacked);
+        assert!(checkpoint.entries_done >= 3);
+        assert!(!t.get_blobdir().join(DBFILE_BACKUP_NAME).exists());
+        let mut unpacked = Vec::new();
+        for name in names {
+            let blob = t.get_blobdir().join(name);
+            if blob.exists() {
+                fs::write(&blob, b"Resumed").await?;
+                unpacked.push(blob);
+            }
+        }
+        assert_eq!(unpacked.len(), 2);
+
+        // Failing to read the backup again does not unpack the blobs again
+        // and keeps the checkpoint.
+        assert!(interrupted_import(true).await.is_err());
+        let resumed = ImportCheckpoint::load(&t, backup).await?;
+        assert!(resumed.entries_done >= checkpoint.entries_done);
+        assert!(!t.get_blobdir().join(DBFILE_BACKUP_NAME).exists());
+
+        // The import is resumed, unpacked blobs are not unpacked again.
+        imex(&t, ImexMode::ImportBackup, backup, None).await?;
+        assert!(t.is_configured().await?);
+        for blob in &unpacked {
+            assert_eq!(fs::read(blob).await?, b"Resumed");
+        }
+        for name in names {
+            assert!(t.get_blobdir().join(name).exists());
+        }
+        assert!(!t.get_blobdir().join(DBFILE_BACKUP_NAME).exists());
+        assert_eq!(ImportCheckpoint::load(&t, backup).await?.entries_done, 0);
+        Ok(())
+    }
+
     /// This is a regression test for
     /// https://github.com/deltachat/deltachat-android/issues/2263
     /// where the config cache wasn't reset properly after a backup.
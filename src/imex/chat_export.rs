@@ -0,0 +1,730 @@
+//! Export of a single chat.
+//!
+//! The chat is exported as a tar archive
+//! containing each message as an `.eml` file in the `messages/` directory
+//! and an `index.json` listing the messages in chat order,
+//! or as an mbox file for use with traditional mail tools.
+//! Single messages can be saved as `.eml` files.
+//! Info messages, e.g. about member changes, are not exported.
+
+use std::path::{Path, PathBuf};
+
+use anyhow::{bail, ensure, Context as _, Result};
+use base64::engine::general_purpose;
+use base64::Engine as _;
+use percent_encoding::{utf8_percent_encode, AsciiSet, NON_ALPHANUMERIC};
+use serde::Serialize;
+use tokio::fs::File;
+use tokio::io::{
+    AsyncBufRead, AsyncBufReadExt, AsyncRead, AsyncReadExt, AsyncWrite, AsyncWriteExt, BufReader,
+    BufWriter,
+};
+
+use crate::chat::{self, Chat, ChatId, ChatItem};
+use crate::contact::{Contact, ContactId};
+use crate::context::Context;
+use crate::events::EventType;
+use crate::message::{self, Message, MsgId};
+use crate::tools::{create_id, time, TempPathGuard};
+
+/// Name of the index file in the archive.
+const INDEX_NAME: &str = "index.json";
+
+/// Directory containing the messages in the archive.
+const MESSAGES_DIR: &str = "messages";
+
+/// Index of the exported chat.
+#[derive(Debug, Serialize)]
+struct ChatIndex {
+    /// Name of the chat.
+    name: String,
+
+    /// Exported messages in chat order.
+    messages: Vec<MessageIndexEntry>,
+}
+
+/// Index entry of an exported message.
+#[derive(Debug, Serialize)]
+struct MessageIndexEntry {
+    /// Path of the `.eml` file in the archive.
+    file: String,
+
+    /// Address of the sender.
+    from: String,
+
+    /// Timestamp of the message as sent by the sender.
+    timestamp: i64,
+
+    /// Message text.
+    text: String,
+
+    /// Name of the attached file, if any.
+    ///
+    /// The file is only contained in the `.eml` file
+    /// if media was included in the export.
+    attachment: Option<String>,
+}
+
+/// Characters which must be percent-encoded in RFC 2231 parameter values.
+const RFC2231_ESCAPE: &AsciiSet = &NON_ALPHANUMERIC
+    .remove(b'!')
+    .remove(b'#')
+    .remove(b'$')
+    .remove(b'&')
+    .remove(b'+')
+    .remove(b'-')
+    .remove(b'.')
+    .remove(b'^')
+    .remove(b'_')
+    .remove(b'`')
+    .remove(b'|')
+    .remove(b'~');
+
+/// Number of bytes encoded into a base64 line of 76 characters.
+const BASE64_LINE_BYTES: usize = 57;
+
+/// Encodes a header value if it is not plain ASCII.
+fn encode_header_value(value: &str) -> String {
+    let value = value.replace(['\r', '\n'], " ");
+    if value.is_ascii() {
+        value
+    } else {
+        encoded_words::encode(&value, None, encoded_words::EncodingFlag::Shortest, None)
+    }
+}
+
+/// Returns the value as quoted string, see RFC 5322.
+fn quote_string(value: &str) -> String {
+    format!("\"{}\"", value.replace('\\', "\\\\").replace('"', "\\\""))
+}
+
+/// Formats an address with an optional display name for use in headers.
+///
+/// ASCII names containing special characters are quoted, other names are encoded.
+fn format_mailbox(name: &str, addr: &str) -> String {
+    let name = name.replace(['\r', '\n'], " ");
+    if name.is_empty() {
+        format!("<{addr}>")
+    } else if !name.is_ascii() {
+        format!("{} <{addr}>", encode_header_value(&name))
+    } else if name.contains(|c: char| "()<>[]:;@\\,.\"".contains(c)) {
+        format!("{} <{addr}>", quote_string(&name))
+    } else {
+        format!("{name} <{addr}>")
+    }
+}
+
+/// Formats the `Content-Disposition` of an attachment.
+///
+/// Non-ASCII file names are encoded as described in RFC 2231.
+fn format_content_disposition(filename: &str) -> String {
+    let filename = filename.replace(['\r', '\n'], " ");
+    if filename.is_ascii() {
+        format!("attachment; filename={}", quote_string(&filename))
+    } else {
+        format!(
+            "attachment; filename*=utf-8''{}",
+            utf8_percent_encode(&filename, RFC2231_ESCAPE)
+        )
+    }
+}
+
+/// Writes the base64 encoding of `reader` to `writer`,
+/// wrapped into lines of 76 characters.
+///
+/// The input is read in chunks, so that large files are not loaded into memory.
+async fn write_wrapped_base64<R, W>(mut reader: R, writer: &mut W) -> Result<()>
+where
+    R: AsyncRead + Unpin,
+    W: AsyncWrite + Unpin,
+{
+    const CHUNK_SIZE: usize = BASE64_LINE_BYTES * 1024;
+    let mut chunk = Vec::with_capacity(CHUNK_SIZE);
+    let mut first_line = true;
+    loop {
+        chunk.clear();
+        (&mut reader)
+            .take(CHUNK_SIZE as u64)
+            .read_to_end(&mut chunk)
+            .await?;
+        for line in chunk.chunks(BASE64_LINE_BYTES) {
+            if !first_line {
+                writer.write_all(b"\r\n").await?;
+            }
+            first_line = false;
+            writer
+                .write_all(general_purpose::STANDARD.encode(line).as_bytes())
+                .await?;
+        }
+        if chunk.len() < CHUNK_SIZE {
+            return Ok(());
+        }
+    }
+}
+
+/// Writes the message as `.eml` file to `writer`.
+///
+/// `attachment` is the file name and path of the attached file to include.
+async fn write_eml<W>(
+    writer: &mut W,
+    msg: &Message,
+    from: &str,
+    to: &[String],
+    subject: &str,
+    attachment: Option<(&str, &Path)>,
+) -> Result<()>
+where
+    W: AsyncWrite + Unpin,
+{
+    let date = chrono::DateTime::<chrono::Utc>::from_timestamp(msg.get_timestamp(), 0)
+        .unwrap_or_default()
+        .to_rfc2822();
+    let text = msg.get_text().replace("\r\n", "\n").replace('\n', "\r\n");
+    let mut head = format!(
+        "From: {from}\r\n\
+         To: {to}\r\n\
+         Date: {date}\r\n\
+         Message-ID: <{mid}>\r\n\
+         Subject: {subject}\r\n\
+         MIME-Version: 1.0\r\n",
+        to = to.join(", "),
+        mid = msg.rfc724_mid,
+        subject = encode_header_value(subject),
+    );
+    match attachment {
+        Some((filename, path)) => {
+            let file = File::open(path)
+                .await
+                .with_context(|| format!("Failed to read {}", path.display()))?;
+            let boundary = create_id();
+            head += &format!(
+                "Content-Type: multipart/mixed; boundary=\"{boundary}\"\r\n\
+                 \r\n\
+                 --{boundary}\r\n\
+                 Content-Type: text/plain; charset=utf-8\r\n\
+                 Content-Transfer-Encoding: 8bit\r\n\
+                 \r\n\
+                 {text}\r\n\
+                 --{boundary}\r\n\
+                 Content-Type: application/octet-stream\r\n\
+                 Content-Disposition: {disposition}\r\n\
+                 Content-Transfer-Encoding: base64\r\n\
+                 \r\n",
+                disposition = format_content_disposition(filename),
+            );
+            writer.write_all(head.as_bytes()).await?;
+            write_wrapped_base64(file, writer).await?;
+            writer
+                .write_all(format!("\r\n--{boundary}--\r\n").as_bytes())
+                .await?;
+        }
+        None => {
+            head += &format!(
+                "Content-Type: text/plain; charset=utf-8\r\n\
+                 Content-Transfer-Encoding: 8bit\r\n\
+                 \r\n\
+                 {text}\r\n"
+            );
+            writer.write_all(head.as_bytes()).await?;
+        }
+    }
+    Ok(())
+}
+
+/// Renders messages of a chat as `.eml` files.
+struct EmlRenderer {
+    chat: Chat,
+
+    /// Chat members except self with their addresses.
+    members: Vec<(ContactId, String)>,
+
+    self_addr: String,
+}
+
+impl EmlRenderer {
+    async fn new(context: &Context, chat_id: ChatId) -> Result<Self> {
+        let chat = Chat::load_from_db(context, chat_id).await?;
+        let mut members = Vec::new();
+        for contact_id in chat::get_chat_contacts(context, chat_id).await? {
+            if contact_id == ContactId::SELF {
+                continue;
+            }
+            let contact = Contact::get_by_id(context, contact_id).await?;
+            if !contact.get_addr().is_empty() {
+                members.push((contact_id, contact.get_addr().to_string()));
+            }
+        }
+        let self_addr = context.get_primary_self_addr().await?;
+        Ok(Self {
+            chat,
+            members,
+            self_addr,
+        })
+    }
+
+    /// Returns the address of the sender of the message.
+    async fn get_from_addr(&self, context: &Context, msg: &Message) -> Result<String> {
+        let contact = Contact::get_by_id(context, msg.get_from_id()).await?;
+        Ok(match contact.get_addr() {
+            "" => self.self_addr.clone(),
+            addr => addr.to_string(),
+        })
+    }
+
+    /// Writes the message rendered from its contents to `writer`.
+    ///
+    /// The attached file is only included if `include_media` is set.
+    async fn write<W>(
+        &self,
+        context: &Context,
+        writer: &mut W,
+        msg: &Message,
+        include_media: bool,
+    ) -> Result<()>
+    where
+        W: AsyncWrite + Unpin,
+    {
+        let from_id = msg.get_from_id();
+        let contact = Contact::get_by_id(context, from_id).await?;
+        let from = format_mailbox(
+            contact.get_display_name(),
+            &self.get_from_addr(context, msg).await?,
+        );
+        let to: Vec<String> = self
+            .members
+            .iter()
+            .filter(|(contact_id, _)| *contact_id != from_id)
+            .map(|(_, addr)| format!("<{addr}>"))
+            .chain((from_id != ContactId::SELF).then(|| format!("<{}>", self.self_addr)))
+            .collect();
+
+        let attachment_name = msg.get_filename();
+        let attachment_path = match include_media && !msg.is_view_once() {
+            true => msg.get_file(context),
+            false => None,
+        };
+        let attachment = attachment_name.as_deref().zip(attachment_path.as_deref());
+        let subject = match msg.get_subject() {
+            "" => self.chat.get_name(),
+            subject => subject,
+        };
+        write_eml(writer, msg, &from, &to, subject, attachment).await
+    }
+
+    /// Writes the message rendered from its contents to the file at `path`,
+    /// including the attachment if `include_media` is set.
+    async fn write_to_file(
+        &self,
+        context: &Context,
+        path: &Path,
+        msg: &Message,
+        include_media: bool,
+    ) -> Result<()> {
+        let mut file = File::create(path)
+            .await
+            .with_context(|| format!("Failed to write {}", path.display()))?;
+        self.write(context, &mut file, msg, include_media).await?;
+        file.flush().await?;
+        Ok(())
+    }
+
+    /// Returns the received MIME message if it is stored,
+    /// see [`message::get_mime_headers`].
+    async fn get_original(&self, context: &Context, msg: &Message) -> Result<Option<Vec<u8>>> {
+        if msg.is_view_once() {
+            return Ok(None);
+        }
+        let mime = message::get_mime_headers(context, msg.get_id()).await?;
+        Ok((!mime.is_empty()).then_some(mime))
+    }
+}
+
+/// Writes the message read from `eml` in mboxrd format to `writer`, see
+/// <https://www.loc.gov/preservation/digital/formats/fdd/fdd000385.shtml>.
+async fn write_mbox_entry<R, W>(
+    writer: &mut W,
+    from_addr: &str,
+    timestamp: i64,
+    eml: R,
+) -> Result<()>
+where
+    R: AsyncBufRead + Unpin,
+    W: AsyncWrite + Unpin,
+{
+    let date = chrono::DateTime::<chrono::Utc>::from_timestamp(timestamp, 0)
+        .unwrap_or_default()
+        .format("%a %b %e %H:%M:%S %Y");
+    writer
+        .write_all(format!("From {from_addr} {date}\n").as_bytes())
+        .await?;
+    // Work on bytes, 8-bit messages are not necessarily valid UTF-8.
+    let mut lines = eml.split(b'\n');
+    while let Some(line) = lines.next_segment().await? {
+        let line = line.strip_suffix(b"\r").unwrap_or(line.as_slice());
+        let mut unquoted = line;
+        while let Some(rest) = unquoted.strip_prefix(b">") {
+            unquoted = rest;
+        }
+        if unquoted.starts_with(b"From ") {
+            writer.write_all(b">").await?;
+        }
+        writer.write_all(line).await?;
+        writer.write_all(b"\n").await?;
+    }
+    writer.write_all(b"\n").await?;
+    Ok(())
+}
+
+/// Returns the path of the archive to write and the temporary path to write it to.
+fn get_next_export_path(dir: &Path, chat_id: ChatId, now: i64) -> Result<(PathBuf, PathBuf)> {
+    let stem = chrono::DateTime::<chrono::Utc>::from_timestamp(now, 0)
+        .context("can't get next export path")?
+        .format("delta-chat-chat-%Y-%m-%d")
+        .to_string();
+    for i in 0..64 {
+        let dest_path = dir.join(format!("{stem}-{}-{i:02}.tar", chat_id.to_u32()));
+        let temp_path = dir.join(format!("{stem}-{}-{i:02}.tar.part", chat_id.to_u32()));
+        if !dest_path.exists() && !temp_path.exists() {
+            return Ok((dest_path, temp_path));
+        }
+    }
+    bail!("could not create export file, disk full?");
+}
+
+/// Appends a file of the given size read from `data` to the archive.
+async fn append_data<W, R>(
+    builder: &mut tokio_tar::Builder<W>,
+    path: &str,
+    size: u64,
+    data: R,
+) -> Result<()>
+where
+    W: tokio::io::AsyncWrite + Unpin + Send + 'static,
+    R: AsyncRead + Unpin,
+{
+    let mut header = tokio_tar::Header::new_gnu();
+    header.set_size(size);
+    header.set_mode(0o644);
+    header.set_mtime(time().try_into().unwrap_or_default());
+    builder.append_data(&mut header, path, data).await?;
+    Ok(())
+}
+
+/// Exports the messages of a single chat into an archive in the directory `dir`.
+///
+/// Each message is exported as an `.eml` file,
+/// `index.json` lists the messages with sender, timestamp and text.
+/// Attached files are only included if `include_media` is set.
+///
+/// Returns the path of the written archive.
+pub async fn export_chat(
+    context: &Context,
+    chat_id: ChatId,
+    dir: &Path,
+    include_media: bool,
+) -> Result<PathBuf> {
+    let renderer = EmlRenderer::new(context, chat_id).await?;
+    let (dest_path, temp_path) = get_next_export_path(dir, chat_id, time())?;
+    let temp_path = TempPathGuard::new(temp_path);
+    // Each message is rendered into this file first,
+    // as the size of an archive entry must be known before writing it.
+    let eml_path = TempPathGuard::new(temp_path.with_extension("eml"));
+
+    let file = File::create(&*temp_path).await?;
+    let mut builder = tokio_tar::Builder::new(file);
+    let mut index = ChatIndex {
+        name: renderer.chat.get_name().to_string(),
+        messages: Vec::new(),
+    };
+
+    let items = chat::get_chat_msgs(context, chat_id).await?;
+    for (i, item) in items.iter().enumerate() {
+        let ChatItem::Message { msg_id } = item else {
+            continue;
+        };
+        let msg = Message::load_from_db(context, *msg_id).await?;
+        if msg.is_info() {
+            continue;
+        }
+
+        renderer
+            .write_to_file(context, &eml_path, &msg, include_media)
+            .await?;
+        let eml = File::open(&*eml_path).await?;
+        let size = eml.metadata().await?.len();
+
+        let file = format!("{MESSAGES_DIR}/{:05}-{}.eml", i, msg_id.to_u32());
+        append_data(&mut builder, &file, size, eml).await?;
+        index.messages.push(MessageIndexEntry {
+            file,
+            from: renderer.get_from_addr(context, &msg).await?,
+            timestamp: msg.get_timestamp(),
+            text: msg.get_text(),
+            attachment: msg.get_filename(),
+        });
+    }
+
+    let index = serde_json::to_vec_pretty(&index)?;
+    append_data(
+        &mut builder,
+        INDEX_NAME,
+        index.len() as u64,
+        index.as_slice(),
+    )
+    .await?;
+    let mut file = builder.into_inner().await?;
+    file.flush().await?;
+
+    tokio::fs::rename(&*temp_path, &dest_path).await?;
+    info!(
+        context,
+        "Exported chat {chat_id} to {}.",
+        dest_path.display()
+    );
+    context.emit_event(EventType::ImexFileWritten(dest_path.clone()));
+    Ok(dest_path)
+}
+
+/// Saves the message as `.eml` file at `path`, including the attached file.
+///
+/// If `original` is set and the received MIME message is stored,
+/// it is written unchanged. The stored MIME message of an encrypted message is the decrypted one,
+/// the encrypted message is only kept on the server.
+/// Otherwise, e.g. for outgoing messages, the `.eml` file is rendered from the message contents.
+pub async fn save_as_eml(
+    context: &Context,
+    msg_id: MsgId,
+    path: &Path,
+    original: bool,
+) -> Result<()> {
+    let msg = Message::load_from_db(context, msg_id).await?;
+    ensure!(
+        !msg.is_view_once(),
+        "Cannot save view-once message {msg_id}"
+    );
+    let renderer = EmlRenderer::new(context, msg.get_chat_id()).await?;
+    let mime = match original {
+        true => renderer.get_original(context, &msg).await?,
+        false => None,
+    };
+    if let Some(mime) = mime {
+        tokio::fs::write(path, mime)
+            .await
+            .with_context(|| format!("Failed to write {}", path.display()))?;
+    } else {
+        renderer.write_to_file(context, path, &msg, true).await?;
+    }
+    context.emit_event(EventType::ImexFileWritten(path.to_path_buf()));
+    Ok(())
+}
+
+/// Exports the messages of a single chat as mbox file at `path`, including attached files.
+///
+/// See [`save_as_eml`] for the meaning of `original`.
+pub async fn export_mbox(
+    context: &Context,
+    chat_id: ChatId,
+    path: &Path,
+    original: bool,
+) -> Result<()> {
+    let renderer = EmlRenderer::new(context, chat_id).await?;
+    let mut temp_path = path.as_os_str().to_owned();
+    temp_path.push(".part");
+    let temp_path = TempPathGuard::new(temp_path.into());
+    let eml_path = TempPathGuard::new(temp_path.with_extension("eml"));
+
+    let mut file = BufWriter::new(File::create(&*temp_path).await?);
+    for item in chat::get_chat_msgs(context, chat_id).await? {
+        let ChatItem::Message { msg_id } = item else {
+            continue;
+        };
+        let msg = Message::load_from_db(context, msg_id).await?;
+        if msg.is_info() {
+            continue;
+        }
+        let from_addr = renderer.get_from_addr(context, &msg).await?;
+        let mime = match original {
+            true => renderer.get_original(context, &msg).await?,
+            false => None,
+        };
+        if let Some(mime) = mime {
+            write_mbox_entry(&mut file, &from_addr, msg.get_timestamp(), mime.as_slice()).await?;
+        } else {
+            renderer
+                .write_to_file(context, &eml_path, &msg, true)
+                .await?;
+            let eml = BufReader::new(File::open(&*eml_path).await?);
+            write_mbox_entry(&mut file, &from_addr, msg.get_timestamp(), eml).await?;
+        }
+    }
+    file.flush().await?;
+
+    tokio::fs::rename(&*temp_path, path).await?;
+    info!(context, "Exported chat {chat_id} to {}.", path.display());
+    context.emit_event(EventType::ImexFileWritten(path.to_path_buf()));
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use futures::TryStreamExt;
+
+    use super::*;
+    use crate::chat::send_msg;
+    use crate::config::Config;
+    use crate::message::Viewtype;
+    use crate::mimefactory::wrapped_base64_encode;
+    use crate::test_utils::TestContextManager;
+
+    /// Reads all files of the archive at `path`.
+    async fn read_archive(path: &Path) -> Result<Vec<(String, Vec<u8>)>> {
+        let file = File::open(path).await?;
+        let mut archive = tokio_tar::Archive::new(file);
+        let mut entries = archive.entries()?;
+        let mut files = Vec::new();
+        while let Some(mut entry) = entries.try_next().await? {
+            let name = entry.path()?.to_string_lossy().to_string();
+            let mut data = Vec::new();
+            entry.read_to_end(&mut data).await?;
+            files.push((name, data));
+        }
+        Ok(files)
+    }
+
+    #[tokio::test(flavor = "multi_thread", worker_threads = 2)]
+    async fn test_export_chat() -> Result<()> {
+        let mut tcm = TestContextManager::new();
+        let alice = &tcm.alice().await;
+        let bob = &tcm.bob().await;
+
+        let chat_id = alice.create_chat(bob).await.id;
+        alice.send_text(chat_id, "Hello Bob").await;
+        let file = alice.get_blobdir().join("notes.txt");
+        tokio::fs::write(&file, b"Secret notes").await?;
+        let mut msg = Message::new(Viewtype::File);
+        msg.set_file(file.to_str().unwrap(), None);
+        msg.set_text("See attachment".to_string());
+        send_msg(alice, chat_id, &mut msg).await?;
+        let received = tcm.send_recv(bob, alice, "Hi Alice").await;
+        assert_eq!(received.chat_id, chat_id);
+
+        let dir = tempfile::tempdir()?;
+        let path = export_chat(alice, chat_id, dir.path(), true).await?;
+        let files = read_archive(&path).await?;
+        assert_eq!(files.len(), 4);
+
+        let (name, index) = files.last().unwrap();
+        assert_eq!(name, INDEX_NAME);
+        let index: serde_json::Value = serde_json::from_slice(index)?;
+        let messages = index["messages"].as_array().unwrap();
+        assert_eq!(messages.len(), 3);
+        assert_eq!(messages[0]["text"], "Hello Bob");
+        assert_eq!(messages[0]["from"], "alice@example.org");
+        assert_eq!(messages[1]["attachment"], "notes.txt");
+        assert_eq!(messages[2]["from"], "bob@example.net");
+
+        let eml = String::from_utf8(files[1].1.clone())?;
+        assert!(eml.contains("See attachment"));
+        assert!(eml.contains(&wrapped_base64_encode(b"Secret notes")));
+        let parsed = mailparse::parse_mail(eml.as_bytes())?;
+        assert_eq!(parsed.subparts.len(), 2);
+
+        // Without media, the attachment is only listed in the index.
+        let path = export_chat(alice, chat_id, dir.path(), false).await?;
+        let files = read_archive(&path).await?;
+        let eml = String::from_utf8(files[1].1.clone())?;
+        assert!(eml.contains("See attachment"));
+        assert!(!eml.contains(&wrapped_base64_encode(b"Secret notes")));
+        Ok(())
+    }
+
+    #[tokio::test(flavor = "multi_thread", worker_threads = 2)]
+    async fn test_save_as_eml_and_export_mbox() -> Result<()> {
+        let mut tcm = TestContextManager::new();
+        let alice = &tcm.alice().await;
+        let bob = &tcm.bob().await;
+        bob.set_config_bool(Config::SaveMimeHeaders, true).await?;
+
+        let chat_id = alice.create_chat(bob).await.id;
+        let sent = alice.send_text(chat_id, "Hello Bob").await;
+        let received = bob.recv_msg(&sent).await;
+        let sent = alice.send_text(chat_id, "From now on\n>From here").await;
+        bob.recv_msg(&sent).await;
+
+        let dir = tempfile::tempdir()?;
+        let path = dir.path().join("msg.eml");
+        save_as_eml(alice, sent.sender_msg_id, &path, true).await?;
+        let eml = tokio::fs::read(&path).await?;
+        let parsed = mailparse::parse_mail(&eml)?;
+        assert_eq!(parsed.get_body()?.trim(), "From now on\r\n>From here");
+
+        // The received MIME message is only written if requested.
+        save_as_eml(bob, received.id, &path, true).await?;
+        let eml = tokio::fs::read_to_string(&path).await?;
+        assert!(eml.contains("Chat-Version: 1.0"));
+        save_as_eml(bob, received.id, &path, false).await?;
+        let eml = tokio::fs::read_to_string(&path).await?;
+        assert!(!eml.contains("Chat-Version"));
+        assert!(eml.contains("Hello Bob"));
+
+        let path = dir.path().join("chat.mbox");
+        export_mbox(bob, received.chat_id, &path, true).await?;
+        let mbox = tokio::fs::read_to_string(&path).await?;
+        assert!(mbox.starts_with("From alice@example.org "));
+        assert_eq!(mbox.matches("\nFrom alice@example.org ").count(), 1);
+        assert_eq!(mbox.matches("Chat-Version: 1.0").count(), 2);
+        assert!(!mbox.contains('\r'));
+
+        // Lines starting with "From " are escaped.
+        export_mbox(bob, received.chat_id, &path, false).await?;
+        let mbox = tokio::fs::read_to_string(&path).await?;
+        assert!(mbox.contains("\n>From now on\n>>From here\n"));
+        Ok(())
+    }
+
+    #[tokio::test(flavor = "multi_thread", worker_threads = 2)]
+    async fn test_write_mbox_entry_8bit() -> Result<()> {
+        let eml = b"Subject: Gr\xfc\xdfe\r\n\r\nFrom \xe4\r\n>From x\r\nend";
+        let mut entry = Vec::new();
+        write_mbox_entry(&mut entry, "alice@example.org", 0, &eml[..]).await?;
+        assert!(entry.starts_with(b"From alice@example.org Thu Jan  1 00:00:00 1970\n"));
+        assert!(entry.ends_with(b"\nSubject: Gr\xfc\xdfe\n\n>From \xe4\n>>From x\nend\n\n"));
+        Ok(())
+    }
+
+    #[tokio::test(flavor = "multi_thread", worker_threads = 2)]
+    async fn test_write_wrapped_base64() -> Result<()> {
+        for len in [0, 1, BASE64_LINE_BYTES, BASE64_LINE_BYTES * 1024 + 1] {
+            let data: Vec<u8> = (0..len).map(|i| i as u8).collect();
+            let mut encoded = Vec::new();
+            write_wrapped_base64(data.as_slice(), &mut encoded).await?;
+            assert_eq!(String::from_utf8(encoded)?, wrapped_base64_encode(&data));
+        }
+        Ok(())
+    }
+
+    #[test]
+    fn test_format_mailbox() {
+        assert_eq!(format_mailbox("", "a@b.c"), "<a@b.c>");
+        assert_eq!(format_mailbox("Alice", "a@b.c"), "Alice <a@b.c>");
+        assert_eq!(
+            format_mailbox("Doe, J. \"Al\"", "a@b.c"),
+            "\"Doe, J. \\\"Al\\\"\" <a@b.c>"
+        );
+        let encoded = format_mailbox("Jörg", "a@b.c");
+        assert!(encoded.starts_with("=?"));
+        assert!(encoded.ends_with("?= <a@b.c>"));
+    }
+
+    #[test]
+    fn test_format_content_disposition() {
+        assert_eq!(
+            format_content_disposition("my \"notes\".txt"),
+            "attachment; filename=\"my \\\"notes\\\".txt\""
+        );
+        assert_eq!(
+            format_content_disposition("Grüße 100%.txt"),
+            "attachment; filename*=utf-8''Gr%C3%BC%C3%9Fe%20100%25.txt"
+        );
+    }
+}
@@ -0,0 +1,157 @@
+//! Checkpoints of interrupted backup imports.
+//!
+//! While a backup file is imported,
+//! the number of archive entries already unpacked to the blobdir
+//! is persisted in a small file next to them.
+//! If the import is interrupted, e.g. because the app was killed,
+//! importing the same backup file again skips these entries.
+//!
+//! The database import itself is a single step
+//! and is repeated after an interruption.
+//!
+//! Unpacked files are only removed and the progress of the checkpoint
+//! is only reset if the unpacked database cannot be imported,
+//! e.g. because of a wrong passphrase.
+//! IO errors and cancellation keep them.
+
+use std::path::{Path, PathBuf};
+use std::time::UNIX_EPOCH;
+
+use anyhow::{Context as _, Result};
+use serde::{Deserialize, Serialize};
+use tokio::fs;
+
+use crate::context::Context;
+use crate::log::LogExt;
+
+/// Name of the checkpoint file in the blobdir.
+const CHECKPOINT_NAME: &str = "dc_backup_import_checkpoint.json";
+
+/// Progress of a backup import.
+#[derive(Debug, Default, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub(crate) struct ImportCheckpoint {
+    /// Size of the imported backup file.
+    backup_size: u64,
+
+    /// Modification time of the imported backup file in seconds since the epoch.
+    backup_mtime: u64,
+
+    /// Number of archive entries which are completely unpacked.
+    pub entries_done: u64,
+
+    /// Sum of the sizes of the completely unpacked entries.
+    pub bytes_done: u64,
+
+    /// Whether the database is completely unpacked.
+    pub database_unpacked: bool,
+}
+
+impl ImportCheckpoint {
+    /// Returns the checkpoint of a previous import of the backup file at `backup`
+    /// or a new checkpoint if there is none.
+    ///
+    /// Checkpoints of other backup files are discarded.
+    pub(crate) async fn load(context: &Context, backup: &Path) -> Result<Self> {
+        let metadata = fs::metadata(backup).await?;
+        let backup_size = metadata.len();
+        let backup_mtime = metadata
+            .modified()?
+            .duration_since(UNIX_EPOCH)
+            .map(|duration| duration.as_secs())
+            .unwrap_or_default();
+        let new = Self {
+            backup_size,
+            backup_mtime,
+            ..Default::default()
+        };
+
+        let path = checkpoint_path(context);
+        let Ok(data) = fs::read(&path).await else {
+            return Ok(new);
+        };
+        match serde_json::from_slice::<Self>(&data) {
+            Ok(checkpoint)
+                if checkpoint.backup_size == backup_size
+                    && checkpoint.backup_mtime == backup_mtime =>
+            {
+                info!(
+                    context,
+                    "Resuming backup import after {} entries ({} bytes).",
+                    checkpoint.entries_done,
+                    checkpoint.bytes_done
+                );
+                Ok(checkpoint)
+            }
+            _ => {
+                info!(context, "Discarding checkpoint of another backup import.");
+                Ok(new)
+            }
+        }
+    }
+
+    /// Resets the progress, keeping the backup file the checkpoint belongs to.
+    pub(crate) fn reset(&mut self) {
+        self.entries_done = 0;
+        self.bytes_done = 0;
+        self.database_unpacked = false;
+    }
+
+    /// Persists the checkpoint.
+    pub(crate) async fn save(&self, context: &Context) -> Result<()> {
+        let path = checkpoint_path(context);
+        let temp_path = path.with_extension("json.tmp");
+        fs::write(&temp_path, serde_json::to_vec(self)?)
+            .await
+            .context("Failed to write import checkpoint")?;
+        fs::rename(&temp_path, &path)
+            .await
+            .context("Failed to move import checkpoint")?;
+        Ok(())
+    }
+
+    /// Removes the persisted checkpoint after the import is finished.
+    pub(crate) async fn remove(context: &Context) {
+        let path = checkpoint_path(context);
+        if path.exists() {
+            fs::remove_file(&path)
+                .await
+                .context("Failed to remove import checkpoint")
+                .log_err(context)
+                .ok();
+        }
+    }
+}
+
+fn checkpoint_path(context: &Context) -> PathBuf {
+    context.get_blobdir().join(CHECKPOINT_NAME)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_utils::TestContext;
+
+    #[tokio::test(flavor = "multi_thread", worker_threads = 2)]
+    async fn test_checkpoint_bound_to_backup() -> Result<()> {
+        let t = TestContext::new().await;
+        let dir = tempfile::tempdir()?;
+        let backup = dir.path().join("backup.tar");
+        fs::write(&backup, b"backup").await?;
+
+        let mut checkpoint = ImportCheckpoint::load(&t, &backup).await?;
+        assert_eq!(checkpoint.entries_done, 0);
+        checkpoint.entries_done = 3;
+        checkpoint.bytes_done = 100;
+        checkpoint.save(&t).await?;
+        assert_eq!(ImportCheckpoint::load(&t, &backup).await?, checkpoint);
+
+        // Another backup file does not resume the checkpoint.
+        let other = dir.path().join("other.tar");
+        fs::write(&other, b"other backup").await?;
+        assert_eq!(ImportCheckpoint::load(&t, &other).await?.entries_done, 0);
+
+        ImportCheckpoint::remove(&t).await;
+        assert_eq!(ImportCheckpoint::load(&t, &backup).await?.entries_done, 0);
+        Ok(())
+    }
+}
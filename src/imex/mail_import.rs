@@ -0,0 +1,273 @@
+//! Import of messages from `.eml` and mbox files.
+//!
+//! Imported messages are processed as if they were received,
+//! so they are assigned to chats, decrypted and deduplicated
+//! by their `Message-ID` the usual way.
+//! They are marked as seen as they are not new to the user.
+//! As they may be arbitrarily old, their side effects such as Autocrypt keys,
+//! sync items and group membership changes are not applied.
+//!
+//! Mbox files are expected in the mboxrd format as written by [`super::export_mbox`],
+//! i.e. messages are separated by `From <sender> <date>` lines
+//! and lines starting with `From ` or a quoted `From ` in the messages are escaped by `>`.
+
+use std::path::Path;
+
+use anyhow::{Context as _, Result};
+use chrono::NaiveDateTime;
+use tokio::fs;
+use tokio::io::{AsyncBufReadExt, AsyncReadExt, BufReader};
+
+use crate::context::Context;
+use crate::imap;
+use crate::message::MsgId;
+use crate::receive_imf::receive_imf_imported;
+use crate::tools::get_abs_path;
+
+/// Imports a single message in Internet Message Format, e.g. the content of an `.eml` file.
+///
+/// Returns the IDs of the created messages,
+/// an empty list if the message already exists or is not shown in a chat.
+pub async fn import_eml(context: &Context, eml: &[u8]) -> Result<Vec<MsgId>> {
+    let mail = mailparse::parse_mail(eml).context("Cannot parse message")?;
+    let rfc724_mid =
+        imap::prefetch_get_message_id(&mail.headers).unwrap_or_else(imap::create_message_id);
+    let received = receive_imf_imported(context, &rfc724_mid, eml).await?;
+    Ok(received
+        .filter(|received| !received.chat_id.is_trash())
+        .map(|received| received.msg_ids)
+        .unwrap_or_default())
+}
+
+/// Imports the messages of an `.eml` or mbox file at `path`.
+///
+/// Mbox files are read line by line, so they are not loaded into memory as a whole.
+/// Messages that cannot be imported are skipped.
+/// Returns the IDs of the created messages.
+pub async fn import_mail_file(context: &Context, path: &Path) -> Result<Vec<MsgId>> {
+    let file = fs::File::open(get_abs_path(context, path))
+        .await
+        .with_context(|| format!("Cannot open {}", path.display()))?;
+    let mut reader = BufReader::new(file);
+    let mut line = Vec::new();
+    reader.read_until(b'\n', &mut line).await?;
+    if !is_mbox_separator(&line) {
+        let mut data = line;
+        reader.read_to_end(&mut data).await?;
+        return import_eml(context, &data).await;
+    }
+
+    let mut splitter = MboxSplitter::default();
+    splitter.push_line(&line);
+    let mut msg_ids = Vec::new();
+    let mut i = 0;
+    loop {
+        line.clear();
+        let eml = if reader.read_until(b'\n', &mut line).await? == 0 {
+            splitter.finish()
+        } else {
+            splitter.push_line(&line)
+        };
+        if let Some(eml) = eml {
+            match import_eml(context, &eml).await {
+                Ok(ids) => msg_ids.extend(ids),
+                Err(err) => warn!(context, "Cannot import message #{i} from mbox: {err:#}."),
+            }
+            i += 1;
+        }
+        if line.is_empty() {
+            break;
+        }
+    }
+    info!(
+        context,
+        "Imported {} messages from {}.",
+        msg_ids.len(),
+        path.display()
+    );
+    Ok(msg_ids)
+}
+
+/// Returns true if `line` is an mbox separator line, `From <sender> <date>`,
+/// where the date is in the `asctime()` format, e.g. `From a@example.org Mon Jan  1 00:00:00 2024`.
+fn is_mbox_separator(line: &[u8]) -> bool {
+    let Some(rest) = line.strip_prefix(b"From ") else {
+        return false;
+    };
+    let Ok(rest) = std::str::from_utf8(rest) else {
+        return false;
+    };
+    let mut fields = rest.split_whitespace();
+    if fields.next().is_none() {
+        return false;
+    }
+    // Some writers append a time zone or other data after the date.
+    let date = fields.take(5).collect::<Vec<_>>().join(" ");
+    NaiveDateTime::parse_from_str(&date, "%a %b %e %H:%M:%S %Y").is_ok()
+}
+
+/// Splits mboxrd data into messages line by line.
+///
+/// `From ` separator lines are removed and escaped `From ` lines are unescaped.
+#[derive(Debug, Default)]
+struct MboxSplitter {
+    /// The message being read, `None` before the first separator line.
+    eml: Option<Vec<u8>>,
+}
+
+impl MboxSplitter {
+    /// Processes a line including its line break.
+    ///
+    /// Returns the previous message if the line starts a new one.
+    fn push_line(&mut self, line: &[u8]) -> Option<Vec<u8>> {
+        if is_mbox_separator(line) {
+            return self.eml.replace(Vec::new()).map(trim_mbox_entry);
+        }
+        let eml = self.eml.as_mut()?;
+        let mut unquoted = line;
+        while let Some(rest) = unquoted.strip_prefix(b">") {
+            unquoted = rest;
+        }
+        match line.strip_prefix(b">") {
+            Some(rest) if unquoted.starts_with(b"From ") => eml.extend_from_slice(rest),
+            _ => eml.extend_from_slice(line),
+        }
+        None
+    }
+
+    /// Returns the last message.
+    fn finish(&mut self) -> Option<Vec<u8>> {
+        self.eml.take().map(trim_mbox_entry)
+    }
+}
+
+/// Removes the empty line separating the message from the next one.
+fn trim_mbox_entry(mut eml: Vec<u8>) -> Vec<u8> {
+    if eml.ends_with(b"\r\n\r\n") {
+        eml.truncate(eml.len() - 2);
+    } else if eml.ends_with(b"\n\n") {
+        eml.pop();
+    }
+    eml
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::message::{Message, MessageState};
+    use crate::peerstate::Peerstate;
+    use crate::test_utils::TestContextManager;
+
+    fn split_mbox(data: &[u8]) -> Vec<Vec<u8>> {
+        let mut splitter = MboxSplitter::default();
+        let mut emls: Vec<Vec<u8>> = data
+            .split_inclusive(|b| *b == b'\n')
+            .filter_map(|line| splitter.push_line(line))
+            .collect();
+        emls.extend(splitter.finish());
+        emls
+    }
+
+    #[test]
+    fn test_split_mbox() {
+        let mbox = b"From a@example.org Mon Jan  1 00:00:00 2024\n\
+                     Subject: one\n\
+                     \n\
+                     >From here\n\
+                     >>From there\n\
+                     > From nowhere\n\
+                     From the start, this is no separator.\n\
+                     \n\
+                     From b@example.org Mon Jan  1 00:00:00 2024 +0000\n\
+                     Subject: two\n\
+                     \n\
+                     text\n";
+        assert_eq!(
+            split_mbox(mbox),
+            vec![
+                b"Subject: one\n\nFrom here\n>From there\n> From nowhere\n\
+                  From the start, this is no separator.\n"
+                    .to_vec(),
+                b"Subject: two\n\ntext\n".to_vec(),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_is_mbox_separator() {
+        assert!(is_mbox_separator(
+            b"From a@example.org Mon Jan  1 00:00:00 2024\n"
+        ));
+        assert!(is_mbox_separator(
+            b"From MAILER-DAEMON Fri Jul  8 12:08:34 2011\r\n"
+        ));
+        assert!(!is_mbox_separator(b"From a@example.org\n"));
+        assert!(!is_mbox_separator(b"From here on, we meet on Mondays.\n"));
+        assert!(!is_mbox_separator(b"From: a@example.org\n"));
+    }
+
+    #[tokio::test(flavor = "multi_thread", worker_threads = 2)]
+    async fn test_import_mail() -> Result<()> {
+        let mut tcm = TestContextManager::new();
+        let alice = &tcm.alice().await;
+        let bob = &tcm.bob().await;
+
+        let chat_id = alice.create_chat(bob).await.id;
+        let sent = alice.send_text(chat_id, "Hello Bob").await;
+        let msg_ids = import_eml(bob, sent.payload().as_bytes()).await?;
+        assert_eq!(msg_ids.len(), 1);
+        let msg = Message::load_from_db(bob, msg_ids[0]).await?;
+        assert_eq!(msg.get_text(), "Hello Bob");
+        assert_eq!(msg.get_state(), MessageState::InSeen);
+        assert_eq!(msg.chat_id, bob.get_chat(alice).await.id);
+
+        // Messages are not imported twice.
+        assert!(import_eml(bob, sent.payload().as_bytes()).await?.is_empty());
+
+        let dir = tempfile::tempdir()?;
+        let path = dir.path().join("import.mbox");
+        let sent = alice.send_text(chat_id, "How are you?").await;
+        let mbox = format!(
+            "From alice@example.org Mon Jan  1 00:00:00 2024\n{}\n\n\
+             From alice@example.org Mon Jan  1 00:00:00 2024\n\
+             From: alice@example.org\n\
+             To: bob@example.net\n\
+             Message-ID: <plans@example.org>\n\
+             Date: Mon, 1 Jan 2024 00:00:00 +0000\n\
+             \n\
+             >From now on, we meet on Mondays.\n\n",
+            sent.payload()
+        );
+        tokio::fs::write(&path, mbox).await?;
+        let msg_ids = import_mail_file(bob, &path).await?;
+        assert_eq!(msg_ids.len(), 2);
+        let msg = Message::load_from_db(bob, msg_ids[0]).await?;
+        assert_eq!(msg.get_text(), "How are you?");
+        let msg = Message::load_from_db(bob, msg_ids[1]).await?;
+        assert_eq!(msg.get_text(), "From now on, we meet on Mondays.");
+
+        Ok(())
+    }
+
+    #[tokio::test(flavor = "multi_thread", worker_threads = 2)]
+    async fn test_import_mail_no_side_effects() -> Result<()> {
+        let mut tcm = TestContextManager::new();
+        let alice = &tcm.alice().await;
+        let fiona = &tcm.fiona().await;
+
+        let chat_id = alice
+            .create_chat_with_contact("Fiona", "fiona@example.net")
+            .await
+            .id;
+        let sent = alice.send_text(chat_id, "Hello Fiona").await;
+        assert!(sent.payload().contains("Autocrypt: "));
+        let msg_ids = import_eml(fiona, sent.payload().as_bytes()).await?;
+        assert_eq!(msg_ids.len(), 1);
+
+        // The Autocrypt header of the imported message is not applied.
+        assert!(Peerstate::from_addr(fiona, "alice@example.org")
+            .await?
+            .is_none());
+        Ok(())
+    }
+}
@@ -0,0 +1,246 @@
+//! Import of Autocrypt peer states from other clients.
+//!
+//! Users switching from another Autocrypt client
+//! can import the peer states of their correspondents
+//! so that messages to them are encrypted from the start.
+//!
+//! Peer states are read from a JSON file in the following interchange format,
+//! the field names follow the
+//! [Autocrypt Level 1 peer state](https://autocrypt.org/level1.html#peer-state-management):
+//!
+//! ```json
+//! {
+//!   "peers": [
+//!     {
+//!       "addr": "bob@example.net",
+//!       "last_seen": 1700000000,
+//!       "autocrypt_timestamp": 1700000000,
+//!       "public_key": "<base64-encoded or ASCII-armored public key>",
+//!       "prefer_encrypt": "mutual",
+//!       "gossip_timestamp": 1690000000,
+//!       "gossip_key": "<base64-encoded or ASCII-armored public key>"
+//!     }
+//!   ]
+//! }
+//! ```
+//!
+//! All fields except `addr` are optional.
+//! Unknown `prefer_encrypt` values are treated as `nopreference`.
+//! Existing peer states are only updated by newer imported keys.
+
+use std::path::Path;
+
+use anyhow::{Context as _, Result};
+use deltachat_contact_tools::ContactAddress;
+use serde::Deserialize;
+
+use crate::aheader::{Aheader, EncryptPreference};
+use crate::context::Context;
+use crate::key::{DcKey, SignedPublicKey};
+use crate::peerstate::Peerstate;
+use crate::tools::{read_file, time};
+
+/// Peer states in the interchange format.
+#[derive(Debug, Deserialize)]
+struct PeerstateExport {
+    peers: Vec<PeerEntry>,
+}
+
+/// Peer state of a single correspondent in the interchange format.
+#[derive(Debug, Deserialize)]
+struct PeerEntry {
+    addr: String,
+
+    #[serde(default)]
+    last_seen: i64,
+
+    #[serde(default)]
+    autocrypt_timestamp: i64,
+
+    public_key: Option<String>,
+
+    #[serde(default)]
+    prefer_encrypt: String,
+
+    #[serde(default)]
+    gossip_timestamp: i64,
+
+    gossip_key: Option<String>,
+}
+
+/// Parses a base64-encoded or ASCII-armored public key and verifies its signatures.
+fn parse_public_key(data: &str) -> Result<SignedPublicKey> {
+    let key = if data.trim_start().starts_with("-----BEGIN") {
+        SignedPublicKey::from_asc(data)?.0
+    } else {
+        SignedPublicKey::from_base64(data)?
+    };
+    key.verify().context("Public key cannot be verified")?;
+    Ok(key)
+}
+
+/// Imports a single peer state.
+///
+/// Returns true if the peer state was created or changed.
+async fn import_peer(context: &Context, peer: PeerEntry) -> Result<bool> {
+    let addr = ContactAddress::new(&peer.addr)?;
+    if context.is_self_addr(&addr).await? {
+        return Ok(false);
+    }
+    // Timestamps in the future would prevent updates by received messages.
+    let now = time();
+    let autocrypt_timestamp = match peer.autocrypt_timestamp {
+        0 => peer.last_seen,
+        timestamp => timestamp,
+    }
+    .min(now);
+    let gossip_timestamp = peer.gossip_timestamp.min(now);
+    let prefer_encrypt: EncryptPreference = peer.prefer_encrypt.parse().unwrap_or_default();
+
+    let old_peerstate = Peerstate::from_addr(context, &addr).await?;
+    let mut peerstate = old_peerstate.clone();
+    if let Some(public_key) = peer.public_key {
+        let public_key = parse_public_key(&public_key)?;
+        let header = Aheader::new(addr.to_string(), public_key, prefer_encrypt);
+        match &mut peerstate {
+            Some(peerstate) => peerstate.apply_header(&header, autocrypt_timestamp),
+            None => peerstate = Some(Peerstate::from_header(&header, autocrypt_timestamp)),
+        }
+    }
+    if let Some(gossip_key) = peer.gossip_key {
+        let gossip_key = parse_public_key(&gossip_key)?;
+        let header = Aheader::new(addr.to_string(), gossip_key, prefer_encrypt);
+        match &mut peerstate {
+            Some(peerstate) => peerstate.apply_gossip(&header, gossip_timestamp),
+            None => peerstate = Some(Peerstate::from_gossip(&header, gossip_timestamp)),
+        }
+    }
+
+    match peerstate {
+        Some(peerstate) if Some(&peerstate) != old_peerstate.as_ref() => {
+            peerstate.save_to_db(&context.sql).await?;
+            if let Err(err) = peerstate
+                .handle_fingerprint_change(context, now, None)
+                .await
+            {
+                warn!(
+                    context,
+                    "import_peer: handle_fingerprint_change() failed for {addr}: {err:#}."
+                );
+            }
+            Ok(true)
+        }
+        _ => Ok(false),
+    }
+}
+
+/// Imports Autocrypt peer states from the JSON file at `path`.
+///
+/// See the [module documentation](self) for the file format.
+/// Invalid entries are skipped.
+/// Returns the number of created or updated peer states.
+pub async fn import_peerstates(context: &Context, path: &Path) -> Result<usize> {
+    let data = read_file(context, path).await?;
+    let export: PeerstateExport =
+        serde_json::from_slice(&data).context("Malformed peer state file")?;
+
+    let mut imported = 0;
+    for peer in export.peers {
+        let addr = peer.addr.clone();
+        match import_peer(context, peer).await {
+            Ok(true) => imported += 1,
+            Ok(false) => {}
+            Err(err) => warn!(context, "Skipping peer state of {addr}: {err:#}."),
+        }
+    }
+    info!(
+        context,
+        "Imported {imported} peer states from {}.",
+        path.display()
+    );
+    Ok(imported)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::key::load_self_public_key;
+    use crate::stock_str;
+    use crate::test_utils::TestContextManager;
+
+    #[tokio::test(flavor = "multi_thread", worker_threads = 2)]
+    async fn test_import_peerstates() -> Result<()> {
+        let mut tcm = TestContextManager::new();
+        let alice = &tcm.alice().await;
+        let bob = &tcm.bob().await;
+        let fiona = &tcm.fiona().await;
+        let bob_key = load_self_public_key(bob).await?;
+        let fiona_key = load_self_public_key(fiona).await?;
+
+        let dir = tempfile::tempdir()?;
+        let path = dir.path().join("peers.json");
+        let export = serde_json::json!({
+            "peers": [
+                {
+                    "addr": "bob@example.net",
+                    "autocrypt_timestamp": 1700000000,
+                    "public_key": bob_key.to_base64(),
+                    "prefer_encrypt": "mutual",
+                },
+                {
+                    "addr": "fiona@example.net",
+                    "gossip_timestamp": 1700000000,
+                    "gossip_key": fiona_key.to_asc(None),
+                },
+                {
+                    "addr": "alice@example.org",
+                    "public_key": bob_key.to_base64(),
+                },
+                {
+                    "addr": "invalid@example.org",
+                    "public_key": "not a key",
+                },
+            ]
+        });
+        tokio::fs::write(&path, serde_json::to_vec(&export)?).await?;
+
+        assert_eq!(import_peerstates(alice, &path).await?, 2);
+        let bob_peerstate = Peerstate::from_addr(alice, "bob@example.net")
+            .await?
+            .unwrap();
+        assert_eq!(bob_peerstate.public_key, Some(bob_key));
+        assert_eq!(bob_peerstate.prefer_encrypt, EncryptPreference::Mutual);
+        assert_eq!(bob_peerstate.last_seen_autocrypt, 1700000000);
+        let fiona_peerstate = Peerstate::from_addr(alice, "fiona@example.net")
+            .await?
+            .unwrap();
+        assert_eq!(fiona_peerstate.gossip_key, Some(fiona_key));
+        assert!(Peerstate::from_addr(alice, "invalid@example.org")
+            .await?
+            .is_none());
+
+        // Importing the same peer states again changes nothing.
+        assert_eq!(import_peerstates(alice, &path).await?, 0);
+
+        // A changed key is announced in the chat like a key change in a received message.
+        let bob_chat = alice.create_chat(bob).await;
+        let export = serde_json::json!({
+            "peers": [
+                {
+                    "addr": "bob@example.net",
+                    "autocrypt_timestamp": 1700000001,
+                    "public_key": fiona_key.to_base64(),
+                },
+            ]
+        });
+        tokio::fs::write(&path, serde_json::to_vec(&export)?).await?;
+        assert_eq!(import_peerstates(alice, &path).await?, 1);
+        let msg = alice.get_last_msg_in(bob_chat.id).await;
+        assert!(msg.is_info());
+        assert_eq!(
+            msg.get_text(),
+            stock_str::contact_setup_changed(alice, "bob@example.net").await
+        );
+        Ok(())
+    }
+}
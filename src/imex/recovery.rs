@@ -0,0 +1,217 @@
+//! # Account recovery codes.
+//!
+//! A recovery bundle contains the secret key and the minimal configuration
+//! needed to log in to the account again,
+//! symmetrically encrypted with a recovery code of the same format
+//! as the setup code of Autocrypt Setup Message.
+//!
+//! The bundle and the code are meant to be stored offline, separately from each other,
+//! e.g. the bundle in a password manager and the code on paper.
+//! Unlike a backup, the bundle does not contain any chats or messages,
+//! but restoring it on a new device keeps the key,
+//! so that contacts can still send encrypted messages and verifications stay valid.
+
+use std::collections::BTreeMap;
+use std::io::Cursor;
+use std::str::FromStr;
+
+use anyhow::{ensure, Context as _, Result};
+use deltachat_contact_tools::EmailAddress;
+use serde::{Deserialize, Serialize};
+
+use super::key_transfer::{create_setup_code, normalize_setup_code};
+use crate::config::Config;
+use crate::context::Context;
+use crate::key::{self, load_self_secret_key, DcKey, SignedSecretKey};
+use crate::pgp;
+
+/// Version of the recovery bundle format.
+const RECOVERY_BUNDLE_VERSION: u32 = 1;
+
+/// Configuration keys saved in the recovery bundle.
+const RECOVERY_CONFIG_KEYS: &[Config] = &[
+    Config::Addr,
+    Config::MailServer,
+    Config::MailUser,
+    Config::MailPw,
+    Config::MailPort,
+    Config::MailSecurity,
+    Config::ImapCertificateChecks,
+    Config::SendServer,
+    Config::SendUser,
+    Config::SendPw,
+    Config::SendPort,
+    Config::SendSecurity,
+    Config::SmtpCertificateChecks,
+    Config::ServerFlags,
+    Config::Displayname,
+    Config::Selfstatus,
+    Config::E2eeEnabled,
+];
+
+/// Recovery code together with the bundle encrypted with it.
+#[derive(Debug, Clone)]
+pub struct RecoveryCode {
+    /// Code the bundle is encrypted with,
+    /// consisting of 9 blocks of 4 digits.
+    pub code: String,
+
+    /// ASCII-armored encrypted bundle.
+    pub bundle: String,
+}
+
+/// Contents of the recovery bundle.
+#[derive(Debug, Serialize, Deserialize)]
+struct RecoveryBundle {
+    version: u32,
+
+    /// Configuration keys and values.
+    config: BTreeMap<String, String>,
+
+    /// ASCII-armored secret key.
+    secret_key: String,
+}
+
+impl Context {
+    /// Generates a recovery code and a bundle
+    /// containing the secret key and the login configuration encrypted with the code.
+    ///
+    /// The account can be restored from the bundle on a new device
+    /// using [`Context::recover_from_code`].
+    pub async fn generate_recovery_code(&self) -> Result<RecoveryCode> {
+        ensure!(
+            self.is_configured().await?,
+            "Not configured, cannot generate recovery code"
+        );
+        let mut config = BTreeMap::new();
+        for key in RECOVERY_CONFIG_KEYS {
+            if let Some(value) = self.get_config(*key).await? {
+                config.insert(key.as_ref().to_string(), value);
+            }
+        }
+        let secret_key = load_self_secret_key(self).await?.to_asc(None);
+        let bundle = serde_json::to_string(&RecoveryBundle {
+            version: RECOVERY_BUNDLE_VERSION,
+            config,
+            secret_key,
+        })?;
+
+        let code = create_setup_code(self);
+        let bundle = pgp::symm_encrypt(&code, bundle.as_bytes()).await?;
+        Ok(RecoveryCode { code, bundle })
+    }
+
+    /// Restores the secret key and the login configuration
+    /// from a `bundle` generated by [`Context::generate_recovery_code`].
+    ///
+    /// The context must not be configured yet.
+    /// Afterwards, [`Context::configure`] should be called to log in.
+    pub async fn recover_from_code(&self, bundle: &str, code: &str) -> Result<()> {
+        ensure!(
+            !self.is_configured().await?,
+            "Cannot recover into an already configured account"
+        );
+        let code = normalize_setup_code(code);
+        let plain = pgp::symm_decrypt(&code, Cursor::new(bundle.as_bytes()))
+            .await
+            .context("Failed to decrypt recovery bundle, wrong recovery code?")?;
+        let bundle: RecoveryBundle =
+            serde_json::from_slice(&plain).context("Failed to parse recovery bundle")?;
+        ensure!(
+            bundle.version <= RECOVERY_BUNDLE_VERSION,
+            "Unsupported recovery bundle version {}",
+            bundle.version
+        );
+
+        let addr = bundle
+            .config
+            .get(Config::Addr.as_ref())
+            .context("Recovery bundle contains no address")?;
+        let addr = EmailAddress::new(addr)?;
+        let (secret, _) = SignedSecretKey::from_asc(&bundle.secret_key)?;
+        let public = secret.split_public_key()?;
+        let keypair = pgp::KeyPair {
+            addr,
+            public,
+            secret,
+        };
+        key::store_self_keypair(self, &keypair, key::KeyPairUse::Default).await?;
+
+        for (key, value) in &bundle.config {
+            match Config::from_str(key) {
+                Ok(key) if RECOVERY_CONFIG_KEYS.contains(&key) => {
+                    self.set_config(key, Some(value)).await?;
+                }
+                _ => warn!(
+                    self,
+                    "Ignoring unknown config key {key:?} in recovery bundle."
+                ),
+            }
+        }
+        info!(
+            self,
+            "Recovered key {} from recovery bundle.",
+            DcKey::fingerprint(&keypair.public)
+        );
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::key::load_self_public_key;
+    use crate::test_utils::TestContext;
+
+    #[tokio::test(flavor = "multi_thread", worker_threads = 2)]
+    async fn test_recovery_code() -> Result<()> {
+        let alice = TestContext::new_alice().await;
+        alice.set_config(Config::MailPw, Some("secret")).await?;
+        alice.set_config(Config::Displayname, Some("Alice")).await?;
+        let recovery = alice.generate_recovery_code().await?;
+        assert_eq!(recovery.code.len(), 44);
+        assert!(recovery.bundle.starts_with("-----BEGIN PGP MESSAGE-----"));
+        assert!(!recovery.bundle.contains("secret"));
+
+        let t = TestContext::new().await;
+        assert!(t
+            .recover_from_code(
+                &recovery.bundle,
+                "1234-1234-1234-1234-1234-1234-1234-1234-1234"
+            )
+            .await
+            .is_err());
+        // The code may be entered without dashes.
+        t.recover_from_code(&recovery.bundle, &recovery.code.replace('-', " "))
+            .await?;
+        assert_eq!(
+            t.get_config(Config::Addr).await?,
+            Some("alice@example.org".to_string())
+        );
+        assert_eq!(
+            t.get_config(Config::MailPw).await?,
+            Some("secret".to_string())
+        );
+        assert_eq!(
+            t.get_config(Config::Displayname).await?,
+            Some("Alice".to_string())
+        );
+        assert!(!t.is_configured().await?);
+
+        // Pretend that configuration succeeded.
+        t.set_config(Config::ConfiguredAddr, Some("alice@example.org"))
+            .await?;
+        t.set_config(Config::Configured, Some("1")).await?;
+        assert_eq!(
+            load_self_public_key(&t).await?,
+            load_self_public_key(&alice).await?
+        );
+
+        // Configured accounts cannot be overwritten.
+        assert!(t
+            .recover_from_code(&recovery.bundle, &recovery.code)
+            .await
+            .is_err());
+        Ok(())
+    }
+}
@@ -306,16 +306,48 @@ pub async fn get_legacy_backup(context: &Context, qr: Qr) -> Result<()> {
     res
 }
 
+/// Retrieves backup from a [`BackupProvider`] on the local network.
+///
+/// Like [`get_legacy_backup`], this can be cancelled with [`Context::stop_ongoing`]
+/// and reports progress with [`EventType::ImexProgress`] events.
 pub async fn get_backup2(
     context: &Context,
     node_addr: iroh_net::NodeAddr,
     auth_token: String,
+) -> Result<()> {
+    ensure!(
+        !context.is_configured().await?,
+        "Cannot import backups to accounts in use."
+    );
+    // Acquire global "ongoing" mutex.
+    let cancel_token = context.alloc_ongoing().await?;
+    let _guard = context.scheduler.pause(context.clone()).await;
+    let res = tokio::select! {
+        biased;
+        res = get_backup2_inner(context, node_addr, auth_token) => res,
+        _ = cancel_token.recv() => Err(format_err!("cancelled")),
+    };
+    if res.is_err() {
+        context.emit_event(ReceiveProgress::Failed.into());
+    }
+    context.free_ongoing().await;
+    res
+}
+
+async fn get_backup2_inner(
+    context: &Context,
+    node_addr: iroh_net::NodeAddr,
+    auth_token: String,
 ) -> Result<()> {
     let relay_mode = RelayMode::Disabled;
 
     let endpoint = Endpoint::builder().relay_mode(relay_mode).bind(0).await?;
 
-    let conn = endpoint.connect(node_addr, BACKUP_ALPN).await?;
+    let conn = endpoint
+        .connect(node_addr, BACKUP_ALPN)
+        .await
+        .context("Failed to connect to the backup provider")?;
+    context.emit_event(ReceiveProgress::Connected.into());
     let (mut send_stream, mut recv_stream) = conn.open_bi().await?;
     info!(context, "Sending backup authentication token.");
     send_stream.write_all(auth_token.as_bytes()).await?;
@@ -635,4 +667,21 @@ async fn test_drop_provider() {
             .get_matching(|ev| matches!(ev, EventType::ImexProgress(0)))
             .await;
     }
+
+    #[tokio::test(flavor = "multi_thread", worker_threads = 2)]
+    async fn test_get_backup_into_configured_account() -> Result<()> {
+        let mut tcm = TestContextManager::new();
+        let ctx0 = tcm.alice().await;
+        let provider = BackupProvider::prepare(&ctx0).await?;
+
+        let ctx1 = tcm.bob().await;
+        assert!(get_backup(&ctx1, provider.qr()).await.is_err());
+
+        // The provider keeps waiting for a device that can import the backup.
+        ctx0.stop_ongoing().await;
+        tokio::time::timeout(Duration::from_secs(30), provider)
+            .await
+            .expect("timed out")?;
+        Ok(())
+    }
 }
@@ -0,0 +1,251 @@
+//! Verification of backup files.
+//!
+//! Checks a backup without importing it,
+//! so it can be validated before the old device is wiped.
+
+use std::collections::BTreeMap;
+use std::ffi::OsStr;
+use std::path::Path;
+
+use anyhow::{Context as _, Result};
+use futures::TryStreamExt;
+use rusqlite::{Connection, OpenFlags};
+use tokio::fs::File;
+use tokio::io::AsyncReadExt;
+use tokio_tar::Archive;
+
+use super::{
+    parse_pax_record, sha256_hex, BackupPassphraseError, BLOBS_BACKUP_NAME, BLOBS_MANIFEST_PAX_KEY,
+    DBFILE_BACKUP_NAME,
+};
+use crate::context::Context;
+use crate::tools::{create_id, TempPathGuard};
+
+/// Result of a backup verification.
+#[derive(Debug, Default, Clone, PartialEq, Eq)]
+pub struct BackupReport {
+    /// Whether the backup contains a database.
+    pub has_database: bool,
+
+    /// Problems found by the database integrity check, empty if the database is consistent.
+    pub database_errors: Vec<String>,
+
+    /// Configured address of the account in the backup.
+    pub addr: Option<String>,
+
+    /// Whether the backup contains a secret key.
+    pub has_secret_key: bool,
+
+    /// Number of blobs in the backup.
+    pub blob_count: usize,
+
+    /// Whether the backup contains a manifest with the hashes of the blobs.
+    ///
+    /// Backups created by older versions have no manifest,
+    /// then the blobs are not verified.
+    pub has_manifest: bool,
+
+    /// Blobs listed in the manifest, but missing in the backup.
+    pub missing_blobs: Vec<String>,
+
+    /// Blobs whose content does not match the hash in the manifest.
+    pub corrupted_blobs: Vec<String>,
+
+    /// Entries of the archive which do not belong into a backup.
+    pub unexpected_entries: Vec<String>,
+}
+
+impl BackupReport {
+    /// Returns true if no problems were found,
+    /// i.e. the backup can be imported without losing data.
+    pub fn is_ok(&self) -> bool {
+        self.has_database
+            && self.database_errors.is_empty()
+            && self.addr.is_some()
+            && self.has_secret_key
+            && self.missing_blobs.is_empty()
+            && self.corrupted_blobs.is_empty()
+            && self.unexpected_entries.is_empty()
+    }
+}
+
+/// Checks the database at `path` and adds the result to `report`.
+fn verify_database(path: &Path, passphrase: String, report: &mut BackupReport) -> Result<()> {
+    let passphrase_error = match passphrase.is_empty() {
+        true => BackupPassphraseError::Missing,
+        false => BackupPassphraseError::Wrong,
+    };
+    // The database is a temporary copy, so it is fine to open it writable,
+    // which is needed to read databases in WAL mode.
+    let flags = OpenFlags::SQLITE_OPEN_NO_MUTEX | OpenFlags::SQLITE_OPEN_READ_WRITE;
+    let conn = Connection::open_with_flags(path, flags)?;
+    conn.pragma_update(None, "key", passphrase)?;
+    conn.query_row("SELECT count(*) FROM sqlite_master", [], |_row| Ok(()))
+        .map_err(|_| anyhow::Error::from(passphrase_error))?;
+
+    let mut stmt = conn.prepare("PRAGMA integrity_check")?;
+    report.database_errors = stmt
+        .query_map([], |row| row.get::<_, String>(0))?
+        .collect::<rusqlite::Result<Vec<_>>>()?
+        .into_iter()
+        .filter(|line| line != "ok")
+        .collect();
+
+    report.addr = conn
+        .query_row(
+            "SELECT value FROM config WHERE keyname='configured_addr'",
+            [],
+            |row| row.get::<_, String>(0),
+        )
+        .ok()
+        .filter(|addr| !addr.is_empty());
+    report.has_secret_key = conn
+        .query_row("SELECT COUNT(*) FROM keypairs", [], |row| {
+            row.get::<_, i64>(0)
+        })
+        .is_ok_and(|count| count > 0);
+    Ok(())
+}
+
+/// Verifies the backup file at `path` without importing it.
+///
+/// Checks the archive structure, the consistency of the database,
+/// the presence of the account address and secret key
+/// and the hashes of the blobs.
+///
+/// `passphrase` is the passphrase of the backup, if any.
+/// An error is returned if the backup cannot be read at all,
+/// e.g. because of a wrong passphrase,
+/// otherwise the problems found are listed in the returned [`BackupReport`].
+pub async fn verify_backup(
+    context: &Context,
+    path: &Path,
+    passphrase: Option<String>,
+) -> Result<BackupReport> {
+    let mut report = BackupReport::default();
+    let temp_db_path = TempPathGuard::new(
+        context
+            .get_blobdir()
+            .join(format!("verify-{}.db", create_id())),
+    );
+    let mut manifest: Option<BTreeMap<String, String>> = None;
+    let mut hashes = BTreeMap::new();
+
+    let file = File::open(path)
+        .await
+        .with_context(|| format!("Failed to open {}", path.display()))?;
+    let mut archive = Archive::new(file);
+    let mut entries = archive.entries().context("Failed to get archive entries")?;
+    while let Some(mut entry) = entries
+        .try_next()
+        .await
+        .context("Failed to get next entry")?
+    {
+        let entry_path = entry.path()?.to_path_buf();
+        let name = entry_path
+            .file_name()
+            .and_then(OsStr::to_str)
+            .unwrap_or_default()
+            .to_string();
+        let parent = entry_path.parent().unwrap_or(Path::new(""));
+        if entry.header().entry_type().is_pax_global_extensions() {
+            let mut data = String::new();
+            entry.read_to_string(&mut data).await?;
+            if let Some(value) = parse_pax_record(&data, BLOBS_MANIFEST_PAX_KEY) {
+                manifest = Some(serde_json::from_str(value).context("Malformed blob manifest")?);
+            }
+        } else if parent.as_os_str().is_empty() && name == DBFILE_BACKUP_NAME {
+            entry.unpack(&*temp_db_path).await?;
+            report.has_database = true;
+        } else if parent.starts_with(BLOBS_BACKUP_NAME) && !name.is_empty() {
+            // Blobs are archived as `blobs_backup/$BLOBDIR/<name>`.
+            let hash = sha256_hex(&mut entry).await?;
+            hashes.insert(name, hash);
+        } else {
+            report
+                .unexpected_entries
+                .push(entry_path.to_string_lossy().to_string());
+        }
+    }
+
+    report.blob_count = hashes.len();
+    if let Some(manifest) = manifest {
+        report.has_manifest = true;
+        for (name, expected) in manifest {
+            match hashes.get(&name) {
+                None => report.missing_blobs.push(name),
+                Some(hash) if *hash != expected => report.corrupted_blobs.push(name),
+                Some(_) => {}
+            }
+        }
+    }
+
+    if report.has_database {
+        let passphrase = passphrase.unwrap_or_default();
+        let db_path = temp_db_path.to_path_buf();
+        report = tokio::task::spawn_blocking(move || {
+            verify_database(&db_path, passphrase, &mut report).map(|_| report)
+        })
+        .await??;
+    }
+    info!(context, "Verified backup {}: {report:?}.", path.display());
+    Ok(report)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::chat::send_msg;
+    use crate::imex::{has_backup, imex, ImexMode};
+    use crate::message::{Message, Viewtype};
+    use crate::test_utils::TestContext;
+
+    #[tokio::test(flavor = "multi_thread", worker_threads = 2)]
+    async fn test_verify_backup() -> Result<()> {
+        let alice = TestContext::new_alice().await;
+        let file = alice.get_blobdir().join("hello.txt");
+        tokio::fs::write(&file, b"Hello").await?;
+        let chat = alice.get_self_chat().await;
+        let mut msg = Message::new(Viewtype::File);
+        msg.set_file(file.to_str().unwrap(), None);
+        send_msg(&alice, chat.id, &mut msg).await?;
+
+        let backup_dir = tempfile::tempdir()?;
+        imex(
+            &alice,
+            ImexMode::ExportBackup,
+            backup_dir.path(),
+            Some("secret".to_string()),
+        )
+        .await?;
+        let backup = has_backup(&alice, backup_dir.path()).await?;
+
+        let report = verify_backup(&alice, backup.as_ref(), Some("secret".to_string())).await?;
+        assert!(report.is_ok());
+        assert!(report.has_manifest);
+        assert_eq!(report.addr.as_deref(), Some("alice@example.org"));
+        assert!(report.blob_count > 0);
+
+        let err = verify_backup(&alice, backup.as_ref(), None)
+            .await
+            .unwrap_err();
+        assert!(matches!(
+            err.downcast_ref::<BackupPassphraseError>(),
+            Some(BackupPassphraseError::Missing)
+        ));
+
+        // A backup without database is reported as broken.
+        let blob = backup_dir.path().join("hello.txt");
+        tokio::fs::write(&blob, b"Hello").await?;
+        let broken = backup_dir.path().join("broken.tar");
+        let mut builder = tokio_tar::Builder::new(File::create(&broken).await?);
+        builder
+            .append_path_with_name(&blob, format!("{BLOBS_BACKUP_NAME}/hello.txt"))
+            .await?;
+        builder.into_inner().await?;
+        let report = verify_backup(&alice, &broken, None).await?;
+        assert!(!report.has_database);
+        assert!(!report.is_ok());
+        Ok(())
+    }
+}
@@ -3,6 +3,7 @@
 use std::collections::BTreeMap;
 use std::fmt;
 use std::io::Cursor;
+use std::sync::Arc;
 
 use anyhow::{bail, ensure, Context as _, Result};
 use base64::Engine as _;
@@ -10,15 +11,19 @@
 use num_traits::FromPrimitive;
 use pgp::composed::Deserializable;
 pub use pgp::composed::{SignedPublicKey, SignedSecretKey};
+pub use pgp::crypto::hash::HashAlgorithm;
 use pgp::ser::Serialize;
 use pgp::types::{KeyTrait, SecretKeyTrait};
 use tokio::runtime::Handle;
 
+use crate::chat::ProtectionStatus;
 use crate::config::Config;
-use crate::constants::KeyGenType;
+use crate::constants::{Chattype, KeyGenType};
+use crate::contact::ContactId;
 use crate::context::Context;
+use crate::events::EventType;
 use crate::log::LogExt;
-use crate::pgp::KeyPair;
+use crate::pgp::{KeyPair, SigningKey};
 use crate::tools::{self, time_elapsed};
 
 /// Convenience trait for working with keys.
@@ -165,6 +170,45 @@ pub(crate) async fn load_self_secret_key(context: &Context) -> Result<SignedSecr
     }
 }
 
+/// Backend creating signatures with the own signing key.
+///
+/// By default, messages are signed in software with the secret key stored in the database.
+/// A backend set with [`Context::set_signing_backend`] allows to keep the signing key
+/// non-exportable, e.g. on an OpenPGP card or YubiKey accessed via PC/SC or an external agent.
+/// Decryption keys stay in software in any case.
+pub trait SigningBackend: fmt::Debug + Send + Sync {
+    /// Returns the fingerprint of the signing key.
+    ///
+    /// It must match the primary key of the own public key.
+    fn fingerprint(&self) -> Fingerprint;
+
+    /// Signs `digest`, the hash of the data to sign calculated with `hash`.
+    ///
+    /// Returns the signature as big-endian multiprecision integers,
+    /// i.e. `r` and `s` for EdDSA and ECDSA signatures or a single integer for RSA signatures.
+    fn sign(&self, hash: HashAlgorithm, digest: &[u8]) -> Result<Vec<Vec<u8>>>;
+}
+
+/// Returns the key to sign outgoing messages with.
+///
+/// This is the signing backend if one is set and the own secret key otherwise.
+pub(crate) async fn load_self_signing_key(context: &Context) -> Result<SigningKey> {
+    let backend = context.signing_backend.read().unwrap().clone();
+    match backend {
+        Some(backend) => {
+            let public = load_self_public_key(context).await?;
+            ensure!(
+                backend.fingerprint() == DcKey::fingerprint(&public),
+                "Signing backend key {} does not match own key {}",
+                backend.fingerprint(),
+                DcKey::fingerprint(&public)
+            );
+            Ok(SigningKey::Backend { public, backend })
+        }
+        None => Ok(SigningKey::Secret(load_self_secret_key(context).await?)),
+    }
+}
+
 pub(crate) async fn load_self_secret_keyring(context: &Context) -> Result<Vec<SignedSecretKey>> {
     let keys = context
         .sql
@@ -238,6 +282,79 @@ fn split_public_key(&self) -> Result<SignedPublicKey> {
     }
 }
 
+/// Merges two versions of the same public key.
+///
+/// The result contains the user IDs, subkeys and signatures of both keys,
+/// so e.g. a new encryption subkey or an extended expiration is added
+/// without dropping anything known from `old`.
+pub(crate) fn merge_public_keys(
+    old: &SignedPublicKey,
+    new: &SignedPublicKey,
+) -> Result<SignedPublicKey> {
+    ensure!(
+        DcKey::fingerprint(old) == DcKey::fingerprint(new),
+        "Cannot merge keys with different fingerprints"
+    );
+    let mut merged = old.clone();
+    for signature in &new.details.revocation_signatures {
+        if !merged.details.revocation_signatures.contains(signature) {
+            merged.details.revocation_signatures.push(signature.clone());
+        }
+    }
+    for signature in &new.details.direct_signatures {
+        if !merged.details.direct_signatures.contains(signature) {
+            merged.details.direct_signatures.push(signature.clone());
+        }
+    }
+    for user in &new.details.users {
+        match merged.details.users.iter_mut().find(|u| u.id == user.id) {
+            Some(merged_user) => {
+                for signature in &user.signatures {
+                    if !merged_user.signatures.contains(signature) {
+                        merged_user.signatures.push(signature.clone());
+                    }
+                }
+            }
+            None => merged.details.users.push(user.clone()),
+        }
+    }
+    for attribute in &new.details.user_attributes {
+        match merged
+            .details
+            .user_attributes
+            .iter_mut()
+            .find(|a| a.attr == attribute.attr)
+        {
+            Some(merged_attribute) => {
+                for signature in &attribute.signatures {
+                    if !merged_attribute.signatures.contains(signature) {
+                        merged_attribute.signatures.push(signature.clone());
+                    }
+                }
+            }
+            None => merged.details.user_attributes.push(attribute.clone()),
+        }
+    }
+    for subkey in &new.public_subkeys {
+        match merged
+            .public_subkeys
+            .iter_mut()
+            .find(|s| s.key == subkey.key)
+        {
+            Some(merged_subkey) => {
+                for signature in &subkey.signatures {
+                    if !merged_subkey.signatures.contains(signature) {
+                        merged_subkey.signatures.push(signature.clone());
+                    }
+                }
+            }
+            None => merged.public_subkeys.push(subkey.clone()),
+        }
+    }
+    merged.verify().context("Merged key is invalid")?;
+    Ok(merged)
+}
+
 async fn generate_keypair(context: &Context) -> Result<KeyPair> {
     let addr = context.get_primary_self_addr().await?;
     let addr = EmailAddress::new(&addr)?;
@@ -390,6 +507,170 @@ pub async fn preconfigure_keypair(context: &Context, addr: &str, secret_data: &s
     Ok(())
 }
 
+impl Context {
+    /// Replaces the default key with a newly generated one.
+    ///
+    /// The old key is kept in the database so that messages encrypted to it can still be
+    /// decrypted, but all outgoing messages are from now on signed with and advertise the new key
+    /// in the Autocrypt header. Gossip is reset in all protected groups so that the new key
+    /// is distributed to other members with the next message.
+    ///
+    /// Progress is reported with [`EventType::KeyRotationProgress`] events.
+    ///
+    /// Returns the fingerprint of the new key.
+    pub async fn rotate_key(&self) -> Result<Fingerprint> {
+        let res = self.rotate_key_inner().await;
+        if res.is_err() {
+            self.emit_event(EventType::KeyRotationProgress {
+                progress: 0,
+                contacts_with_old_key: 0,
+            });
+        }
+        res
+    }
+
+    async fn rotate_key_inner(&self) -> Result<Fingerprint> {
+        ensure!(
+            self.is_configured().await?,
+            "Not configured, cannot rotate key"
+        );
+        let addr = self.get_primary_self_addr().await?;
+        let addr = EmailAddress::new(&addr)?;
+        let _guard = self.generating_key_mutex.lock().await;
+        self.emit_event(EventType::KeyRotationProgress {
+            progress: 10,
+            contacts_with_old_key: 0,
+        });
+
+        let start = tools::Time::now();
+        let keytype = KeyGenType::from_i32(self.get_config_int(Config::KeyGenType).await?)
+            .unwrap_or_default();
+        info!(
+            self,
+            "Rotating keypair, generating new key with type {}.", keytype
+        );
+        let keypair = Handle::current()
+            .spawn_blocking(move || crate::pgp::create_keypair(addr, keytype))
+            .await??;
+        info!(
+            self,
+            "Keypair generated in {:.3}s.",
+            time_elapsed(&start).as_secs(),
+        );
+        self.emit_event(EventType::KeyRotationProgress {
+            progress: 500,
+            contacts_with_old_key: 0,
+        });
+
+        // The old keypair stays in the `keypairs` table,
+        // so it remains part of the secret keyring used for decryption.
+        store_self_keypair(self, &keypair, KeyPairUse::Default).await?;
+        self.emit_event(EventType::KeyRotationProgress {
+            progress: 800,
+            contacts_with_old_key: 0,
+        });
+
+        // Make sure the new key is gossiped with the next message to protected groups,
+        // so that members learn it without the chat getting broken.
+        self.sql
+            .execute(
+                "UPDATE chats SET gossiped_timestamp=0 WHERE type=? AND protected=?",
+                (Chattype::Group, ProtectionStatus::Protected),
+            )
+            .await?;
+
+        let contacts_with_old_key = self.get_contacts_with_old_self_key().await?.len();
+        info!(
+            self,
+            "Key rotated, {contacts_with_old_key} contacts still use the old key."
+        );
+        self.emit_event(EventType::KeyRotationProgress {
+            progress: 1000,
+            contacts_with_old_key,
+        });
+        Ok(DcKey::fingerprint(&keypair.public))
+    }
+
+    /// Replaces the encryption subkey of the default key with a newly generated one.
+    ///
+    /// Unlike [`Context::rotate_key`], this keeps the primary key and thus the fingerprint,
+    /// so verifications by other contacts stay valid.
+    /// The old subkey is kept in the secret key for decryption,
+    /// but is not advertised in the Autocrypt header anymore.
+    /// Gossip is reset in all protected groups so that the new subkey
+    /// is distributed to other members with the next message.
+    pub async fn renew_encryption_subkey(&self) -> Result<()> {
+        ensure!(
+            self.is_configured().await?,
+            "Not configured, cannot renew encryption subkey"
+        );
+        let addr = self.get_primary_self_addr().await?;
+        let addr = EmailAddress::new(&addr)?;
+        let _guard = self.generating_key_mutex.lock().await;
+        let keypair = load_keypair(self, &addr)
+            .await?
+            .context("No default key to renew the encryption subkey of")?;
+
+        let keytype = KeyGenType::from_i32(self.get_config_int(Config::KeyGenType).await?)
+            .unwrap_or_default();
+        info!(self, "Renewing encryption subkey with type {keytype}.");
+        let keypair = Handle::current()
+            .spawn_blocking(move || crate::pgp::renew_encryption_subkey(&keypair, keytype))
+            .await??;
+
+        // Replace the keypair in place, so that its ID stays the same.
+        self.sql
+            .execute(
+                "UPDATE keypairs SET public_key=?, private_key=?
+                 WHERE id=(SELECT value FROM config WHERE keyname='key_id')",
+                (
+                    DcKey::to_bytes(&keypair.public),
+                    DcKey::to_bytes(&keypair.secret),
+                ),
+            )
+            .await?;
+        self.sql
+            .execute(
+                "UPDATE chats SET gossiped_timestamp=0 WHERE type=? AND protected=?",
+                (Chattype::Group, ProtectionStatus::Protected),
+            )
+            .await?;
+        Ok(())
+    }
+
+    /// Sets the backend used to sign outgoing messages.
+    ///
+    /// The backend must hold the primary key of the own key,
+    /// otherwise sending signed messages fails.
+    /// `None` switches back to signing with the secret key stored in the database.
+    pub fn set_signing_backend(&self, backend: Option<Arc<dyn SigningBackend>>) {
+        *self.signing_backend.write().unwrap() = backend;
+    }
+
+    /// Returns contacts that are known to still use one of our older keys.
+    ///
+    /// These are contacts that confirmed our key in the past,
+    /// but did not confirm the current default key yet, e.g. after [`Context::rotate_key`].
+    /// The list shrinks as contacts send us messages verifying the new key.
+    pub async fn get_contacts_with_old_self_key(&self) -> Result<Vec<ContactId>> {
+        let contact_ids = self
+            .sql
+            .query_map(
+                "SELECT c.id FROM contacts c
+                 INNER JOIN acpeerstates ps ON c.addr=ps.addr
+                 WHERE c.id>?
+                 AND ps.backward_verified_key_id IS NOT NULL
+                 AND ps.backward_verified_key_id!=(SELECT value FROM config WHERE keyname='key_id')
+                 ORDER BY c.id",
+                (ContactId::LAST_SPECIAL,),
+                |row| row.get::<_, ContactId>(0),
+                |ids| ids.collect::<Result<Vec<_>, _>>().map_err(Into::into),
+            )
+            .await?;
+        Ok(contact_ids)
+    }
+}
+
 /// A key fingerprint
 #[derive(Clone, Eq, PartialEq, Hash, serde::Serialize, serde::Deserialize)]
 pub struct Fingerprint(Vec<u8>);
@@ -458,7 +739,11 @@ mod tests {
     use once_cell::sync::Lazy;
 
     use super::*;
-    use crate::test_utils::{alice_keypair, TestContext};
+    use crate::chat;
+    use crate::contact::Contact;
+    use crate::peerstate::Peerstate;
+    use crate::test_utils::{alice_keypair, mark_as_verified, TestContext, TestContextManager};
+    use crate::tools::time;
 
     static KEYPAIR: Lazy<KeyPair> = Lazy::new(alice_keypair);
 
@@ -669,6 +954,147 @@ async fn test_save_self_key_twice() {
         assert_eq!(nrows().await, 1);
     }
 
+    #[tokio::test(flavor = "multi_thread", worker_threads = 2)]
+    async fn test_rotate_key() -> Result<()> {
+        let mut tcm = TestContextManager::new();
+        let alice = &tcm.alice().await;
+        let bob = &tcm.bob().await;
+        mark_as_verified(alice, bob).await;
+        let bob_id = alice.add_or_lookup_contact(bob).await.id;
+        assert!(alice.get_contacts_with_old_self_key().await?.is_empty());
+
+        let chat_id = chat::create_group_chat(alice, ProtectionStatus::Protected, "Group").await?;
+        chat_id.set_gossiped_timestamp(alice, time()).await?;
+
+        let old_fingerprint = DcKey::fingerprint(&load_self_public_key(alice).await?);
+        let new_fingerprint = alice.rotate_key().await?;
+        assert_ne!(old_fingerprint, new_fingerprint);
+        assert_eq!(
+            DcKey::fingerprint(&load_self_public_key(alice).await?),
+            new_fingerprint
+        );
+
+        // The old key is still available for decryption.
+        let keyring = load_self_secret_keyring(alice).await?;
+        assert_eq!(keyring.len(), 2);
+        assert_eq!(DcKey::fingerprint(&keyring[0]), new_fingerprint);
+        assert_eq!(DcKey::fingerprint(&keyring[1]), old_fingerprint);
+
+        assert_eq!(chat_id.get_gossiped_timestamp(alice).await?, 0);
+        assert_eq!(alice.get_contacts_with_old_self_key().await?, vec![bob_id]);
+        // Bob still knows the old key and stays verified.
+        assert!(
+            Contact::get_by_id(alice, bob_id)
+                .await?
+                .is_verified(alice)
+                .await?
+        );
+        let event = alice
+            .evtracker
+            .get_matching(|evt| {
+                matches!(evt, EventType::KeyRotationProgress { progress: 1000, .. })
+            })
+            .await;
+        assert_eq!(
+            event,
+            EventType::KeyRotationProgress {
+                progress: 1000,
+                contacts_with_old_key: 1
+            }
+        );
+        Ok(())
+    }
+
+    #[tokio::test(flavor = "multi_thread", worker_threads = 2)]
+    async fn test_renew_encryption_subkey() -> Result<()> {
+        let mut tcm = TestContextManager::new();
+        let alice = &tcm.alice().await;
+        let bob = &tcm.bob().await;
+        mark_as_verified(bob, alice).await;
+        let alice_addr = alice.get_primary_self_addr().await?;
+
+        let old_key = load_self_public_key(alice).await?;
+        alice.renew_encryption_subkey().await?;
+        let new_key = load_self_public_key(alice).await?;
+        assert_ne!(new_key, old_key);
+        assert_eq!(DcKey::fingerprint(&new_key), DcKey::fingerprint(&old_key));
+        assert_eq!(load_self_secret_keyring(alice).await?.len(), 1);
+
+        // Bob updates the verified key as the fingerprint did not change.
+        tcm.send_recv_accept(alice, bob, "Hi!").await;
+        let peerstate = Peerstate::from_addr(bob, &alice_addr).await?.unwrap();
+        assert_eq!(peerstate.public_key, Some(new_key.clone()));
+        assert_eq!(peerstate.verified_key, Some(new_key));
+
+        let msg = tcm
+            .send_recv(bob, alice, "Encrypted to the new subkey")
+            .await;
+        assert!(msg.get_showpadlock());
+        Ok(())
+    }
+
+    #[tokio::test(flavor = "multi_thread", worker_threads = 2)]
+    async fn test_merge_public_keys() -> Result<()> {
+        let alice = &TestContext::new_alice().await;
+        let old_key = load_self_public_key(alice).await?;
+        assert_eq!(merge_public_keys(&old_key, &old_key)?, old_key);
+
+        alice.renew_encryption_subkey().await?;
+        let new_key = load_self_public_key(alice).await?;
+        let merged = merge_public_keys(&old_key, &new_key)?;
+        for subkey in old_key.public_subkeys.iter().chain(&new_key.public_subkeys) {
+            assert!(merged.public_subkeys.iter().any(|s| s.key == subkey.key));
+        }
+        assert_eq!(merge_public_keys(&merged, &new_key)?, merged);
+
+        let bob_key = SignedPublicKey::from_asc(include_str!("../test-data/key/bob-public.asc"))?.0;
+        assert!(merge_public_keys(&old_key, &bob_key).is_err());
+        Ok(())
+    }
+
+    /// Signing backend holding the secret key in memory.
+    #[derive(Debug)]
+    struct SoftwareSigningBackend(SignedSecretKey);
+
+    impl SigningBackend for SoftwareSigningBackend {
+        fn fingerprint(&self) -> Fingerprint {
+            DcKey::fingerprint(&self.0)
+        }
+
+        fn sign(&self, hash: HashAlgorithm, digest: &[u8]) -> Result<Vec<Vec<u8>>> {
+            let signature = self.0.create_signature(|| "".into(), hash, digest)?;
+            Ok(signature
+                .iter()
+                .map(|mpi| mpi.as_bytes().to_vec())
+                .collect())
+        }
+    }
+
+    #[tokio::test(flavor = "multi_thread", worker_threads = 2)]
+    async fn test_signing_backend() -> Result<()> {
+        let mut tcm = TestContextManager::new();
+        let alice = &tcm.alice().await;
+        let bob = &tcm.bob().await;
+        tcm.send_recv_accept(bob, alice, "Hi!").await;
+
+        let alice_secret = load_self_secret_key(alice).await?;
+        alice.set_signing_backend(Some(Arc::new(SoftwareSigningBackend(alice_secret))));
+        let msg = tcm.send_recv(alice, bob, "Signed by the backend").await;
+        assert!(msg.get_showpadlock());
+
+        // A backend holding another key must not be used.
+        let bob_secret = load_self_secret_key(bob).await?;
+        alice.set_signing_backend(Some(Arc::new(SoftwareSigningBackend(bob_secret))));
+        assert!(load_self_signing_key(alice).await.is_err());
+
+        alice.set_signing_backend(None);
+        assert!(matches!(
+            load_self_signing_key(alice).await?,
+            SigningKey::Secret(_)
+        ));
+        Ok(())
+    }
+
     #[test]
     fn test_fingerprint_from_str() {
         let res = Fingerprint::new(vec![
@@ -75,39 +75,53 @@
 mod param;
 pub mod peerstate;
 mod pgp;
+#[cfg(feature = "pq-hybrid")]
+mod pq;
 pub mod provider;
 pub mod qr;
 pub mod qr_code_generator;
 pub mod quota;
 pub mod release;
 mod scheduler;
+pub use scheduler::connectivity::{ConnectionStatus, Connectivity};
 pub mod securejoin;
 mod simplify;
 mod smtp;
 mod socks;
+mod spam_report;
 pub mod stock_str;
 mod sync;
 mod timesmearing;
 mod token;
 mod update_helper;
 pub mod webxdc;
+mod wkd;
 #[macro_use]
 mod dehtml;
-mod authres;
+pub mod authres;
 pub mod color;
+pub mod disk_space;
 pub mod html;
 pub mod net;
 pub mod plaintext;
 mod push;
 pub mod summary;
+#[doc(hidden)]
+pub mod synthetic;
 
 mod debug_logging;
 pub mod receive_imf;
 pub mod tools;
 
 pub mod accounts;
+pub mod bot_commands;
+pub mod mention;
 pub mod peer_channels;
+pub mod poll;
+mod preview;
 pub mod reaction;
+mod search;
+pub mod sticker;
 
 /// If set IMAP/incoming and SMTP/outgoing MIME messages will be printed.
 pub const DCC_MIME_DEBUG: &str = "DCC_MIME_DEBUG";
@@ -9,7 +9,13 @@
 //! Locations are sent as KML attachments.
 //! Independent locations are sent in `message.kml` attachments
 //! and path locations are sent in `location.kml` attachments.
+//!
+//! While streaming, all path locations recorded since the last sent message
+//! are batched into the next `location.kml`.
+//! The interval between location-only messages adapts to the speed of the device
+//! and to the battery level reported by the UI, see [`set_battery_level`].
 
+use std::collections::BTreeMap;
 use std::time::Duration;
 
 use anyhow::{ensure, Context as _, Result};
@@ -19,7 +25,7 @@
 
 use crate::chat::{self, ChatId};
 use crate::constants::DC_CHAT_ID_TRASH;
-use crate::contact::ContactId;
+use crate::contact::{Contact, ContactId};
 use crate::context::Context;
 use crate::events::EventType;
 use crate::message::{Message, MsgId, Viewtype};
@@ -27,6 +33,39 @@
 use crate::tools::{duration_to_str, time};
 use crate::{chatlist_events, stock_str};
 
+/// Interval between location-only messages in seconds when moving fast.
+const FAST_SEND_INTERVAL: i64 = 30;
+
+/// Interval between location-only messages in seconds when moving slowly or if the speed is
+/// unknown.
+const DEFAULT_SEND_INTERVAL: i64 = 60;
+
+/// Interval between location-only messages in seconds when not moving.
+const STATIONARY_SEND_INTERVAL: i64 = 300;
+
+/// Speed in meters per second above which the device is considered moving fast,
+/// e.g. in a car.
+const FAST_SPEED: f64 = 8.0;
+
+/// Speed in meters per second below which the device is considered not moving.
+const STATIONARY_SPEED: f64 = 0.5;
+
+/// Battery level in percent below which the send interval is doubled.
+const LOW_BATTERY_LEVEL: u32 = 30;
+
+/// Battery level in percent below which the send interval is quadrupled.
+const CRITICAL_BATTERY_LEVEL: u32 = 15;
+
+/// Age in seconds after which the last location of a contact is considered stale.
+///
+/// This is twice the longest interval between location-only messages.
+pub const STALE_LOCATION_SECONDS: i64 = 2 * 4 * STATIONARY_SEND_INTERVAL;
+
+/// Maximum number of path locations batched into one `location.kml`.
+///
+/// If more locations were recorded since the last sent message, only the newest ones are sent.
+const MAX_KML_LOCATIONS: u32 = 100;
+
 /// Location record.
 #[derive(Debug, Clone, Default)]
 pub struct Location {
@@ -265,6 +304,21 @@ fn starttag_cb<B: std::io::BufRead>(
     }
 }
 
+/// Freshness of the last path location of a contact, see [`get_freshness`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct LocationFreshness {
+    /// Contact ID.
+    pub contact_id: ContactId,
+
+    /// Timestamp of the newest location of the contact.
+    pub timestamp: i64,
+
+    /// Whether the location is older than [`STALE_LOCATION_SECONDS`].
+    ///
+    /// UIs may gray out stale locations on the map.
+    pub is_stale: bool,
+}
+
 /// Enables location streaming in chat identified by `chat_id` for `seconds` seconds.
 pub async fn send_locations_to_chat(
     context: &Context,
@@ -390,6 +444,47 @@ pub async fn set(context: &Context, latitude: f64, longitude: f64, accuracy: f64
     Ok(continue_streaming)
 }
 
+/// Sets the battery level of the device in percent, `None` if unknown or charging.
+///
+/// Location-only messages are sent less often if the battery is low.
+pub async fn set_battery_level(context: &Context, percent: Option<u32>) {
+    *context.battery_level.lock().expect("Mutex is poisoned") = percent;
+    context.scheduler.interrupt_location().await;
+}
+
+/// Returns the freshness of the newest path location of each contact,
+/// optionally filtering by chat.
+pub async fn get_freshness(
+    context: &Context,
+    chat_id: Option<ChatId>,
+) -> Result<Vec<LocationFreshness>> {
+    let now = time();
+    let freshness = context
+        .sql
+        .query_map(
+            "SELECT from_id, MAX(timestamp) FROM locations
+             WHERE independent=0 AND (?1=0 OR chat_id=?1)
+             GROUP BY from_id
+             ORDER BY from_id",
+            (chat_id.unwrap_or_default(),),
+            |row| {
+                let contact_id: ContactId = row.get(0)?;
+                let timestamp: i64 = row.get(1)?;
+                Ok(LocationFreshness {
+                    contact_id,
+                    timestamp,
+                    is_stale: now - timestamp > STALE_LOCATION_SECONDS,
+                })
+            },
+            |rows| {
+                rows.collect::<std::result::Result<Vec<_>, _>>()
+                    .map_err(Into::into)
+            },
+        )
+        .await?;
+    Ok(freshness)
+}
+
 /// Searches for locations in the given time range, optionally filtering by chat and contact IDs.
 pub async fn get_range(
     context: &Context,
@@ -559,19 +654,22 @@ pub async fn get_kml(context: &Context, chat_id: ChatId) -> Result<Option<(Strin
         context
             .sql
             .query_map(
-                "SELECT id, latitude, longitude, accuracy, timestamp \
+                "SELECT id, latitude, longitude, accuracy, timestamp FROM (\
+             SELECT id, latitude, longitude, accuracy, timestamp \
              FROM locations  WHERE from_id=? \
              AND timestamp>=? \
              AND (timestamp>=? OR \
                   timestamp=(SELECT MAX(timestamp) FROM locations WHERE from_id=?)) \
              AND independent=0 \
              GROUP BY timestamp \
+             ORDER BY timestamp DESC LIMIT ?) \
              ORDER BY timestamp;",
              (
                     ContactId::SELF,
                     locations_send_begin,
                     locations_last_sent,
-                    ContactId::SELF
+                    ContactId::SELF,
+                    MAX_KML_LOCATIONS,
                 ),
                 |row| {
                     let location_id: i32 = row.get(0)?;
@@ -634,6 +732,130 @@ pub fn get_message_kml(timestamp: i64, latitude: f64, longitude: f64) -> String
     )
 }
 
+/// File format of exported locations, see [`export`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ExportFormat {
+    /// Keyhole Markup Language, see [`Kml`].
+    Kml,
+
+    /// GPS Exchange Format, see <https://www.topografix.com/gpx.asp>.
+    Gpx,
+}
+
+/// Exports the locations of a chat in the given time range as a KML or GPX document.
+///
+/// Path locations are exported as one track per contact
+/// and independent locations as waypoints.
+/// If `timestamp_to` is 0, all locations up to now are exported.
+pub async fn export(
+    context: &Context,
+    chat_id: ChatId,
+    timestamp_from: i64,
+    timestamp_to: i64,
+    format: ExportFormat,
+) -> Result<String> {
+    ensure!(!chat_id.is_special(), "Invalid chat id");
+    let chat = chat::Chat::load_from_db(context, chat_id).await?;
+    let chat_name = escaper::encode_minimal(chat.get_name());
+
+    let mut waypoints = Vec::new();
+    let mut tracks: BTreeMap<ContactId, Vec<Location>> = BTreeMap::new();
+    let locations = get_range(context, Some(chat_id), None, timestamp_from, timestamp_to).await?;
+    // `get_range()` returns the newest locations first
+    // and independent locations regardless of the time range.
+    for location in locations.into_iter().rev() {
+        if location.independent == 0 {
+            tracks
+                .entry(location.contact_id)
+                .or_default()
+                .push(location);
+        } else if location.timestamp >= timestamp_from
+            && (timestamp_to == 0 || location.timestamp <= timestamp_to)
+        {
+            waypoints.push(location);
+        }
+    }
+
+    let mut ret = match format {
+        ExportFormat::Kml => format!(
+            "<?xml version=\"1.0\" encoding=\"UTF-8\"?>\n\
+             <kml xmlns=\"http://www.opengis.net/kml/2.2\">\n\
+             <Document>\n<name>{chat_name}</name>\n"
+        ),
+        ExportFormat::Gpx => format!(
+            "<?xml version=\"1.0\" encoding=\"UTF-8\"?>\n\
+             <gpx version=\"1.1\" creator=\"Delta Chat\" xmlns=\"http://www.topografix.com/GPX/1/1\">\n\
+             <metadata><name>{chat_name}</name></metadata>\n"
+        ),
+    };
+
+    for location in &waypoints {
+        let Location {
+            latitude,
+            longitude,
+            timestamp,
+            ..
+        } = location;
+        let timestamp = get_kml_timestamp(*timestamp);
+        let name = location
+            .marker
+            .as_deref()
+            .map(|marker| format!("<name>{}</name>", escaper::encode_minimal(marker)))
+            .unwrap_or_default();
+        ret += &match format {
+            ExportFormat::Kml => format!(
+                "<Placemark>{name}\
+                 <TimeStamp><when>{timestamp}</when></TimeStamp>\
+                 <Point><coordinates>{longitude},{latitude}</coordinates></Point>\
+                 </Placemark>\n"
+            ),
+            ExportFormat::Gpx => format!(
+                "<wpt lat=\"{latitude}\" lon=\"{longitude}\"><time>{timestamp}</time>{name}</wpt>\n"
+            ),
+        };
+    }
+
+    for (contact_id, track) in &tracks {
+        let contact = Contact::get_by_id(context, *contact_id).await?;
+        let name = escaper::encode_minimal(&contact.get_name_n_addr());
+        ret += &match format {
+            ExportFormat::Kml => format!("<Folder><name>{name}</name>\n"),
+            ExportFormat::Gpx => format!("<trk><name>{name}</name><trkseg>\n"),
+        };
+        for location in track {
+            let Location {
+                latitude,
+                longitude,
+                accuracy,
+                timestamp,
+                ..
+            } = location;
+            let timestamp = get_kml_timestamp(*timestamp);
+            ret += &match format {
+                ExportFormat::Kml => format!(
+                    "<Placemark>\
+                     <TimeStamp><when>{timestamp}</when></TimeStamp>\
+                     <Point><coordinates accuracy=\"{accuracy}\">{longitude},{latitude}</coordinates></Point>\
+                     </Placemark>\n"
+                ),
+                ExportFormat::Gpx => format!(
+                    "<trkpt lat=\"{latitude}\" lon=\"{longitude}\"><time>{timestamp}</time></trkpt>\n"
+                ),
+            };
+        }
+        ret += match format {
+            ExportFormat::Kml => "</Folder>\n",
+            ExportFormat::Gpx => "</trkseg></trk>\n",
+        };
+    }
+
+    ret += match format {
+        ExportFormat::Kml => "</Document>\n</kml>",
+        ExportFormat::Gpx => "</gpx>",
+    };
+    Ok(ret)
+}
+
 /// Sets the timestamp of the last time location was sent in the chat.
 pub async fn set_kml_sent_timestamp(
     context: &Context,
@@ -767,12 +989,69 @@ pub(crate) async fn location_loop(context: &Context, interrupt_receiver: Receive
     }
 }
 
+/// Returns the distance in meters between two points on Earth.
+fn distance(latitude1: f64, longitude1: f64, latitude2: f64, longitude2: f64) -> f64 {
+    const EARTH_RADIUS: f64 = 6_371_000.0;
+    let (lat1, lat2) = (latitude1.to_radians(), latitude2.to_radians());
+    let dlat = lat2 - lat1;
+    let dlon = (longitude2 - longitude1).to_radians();
+    let a = (dlat / 2.0).sin().powi(2) + lat1.cos() * lat2.cos() * (dlon / 2.0).sin().powi(2);
+    2.0 * EARTH_RADIUS * a.sqrt().asin()
+}
+
+/// Returns the current speed of the device in meters per second,
+/// estimated from the two newest path locations.
+///
+/// Returns `None` if there are no two locations recorded recently.
+async fn get_speed(context: &Context, now: i64) -> Result<Option<f64>> {
+    let locations = context
+        .sql
+        .query_map(
+            "SELECT latitude, longitude, timestamp FROM locations
+             WHERE from_id=? AND independent=0 AND timestamp>=?
+             GROUP BY timestamp
+             ORDER BY timestamp DESC LIMIT 2",
+            (ContactId::SELF, now - STATIONARY_SEND_INTERVAL),
+            |row| {
+                let latitude: f64 = row.get(0)?;
+                let longitude: f64 = row.get(1)?;
+                let timestamp: i64 = row.get(2)?;
+                Ok((latitude, longitude, timestamp))
+            },
+            |rows| {
+                rows.collect::<std::result::Result<Vec<_>, _>>()
+                    .map_err(Into::into)
+            },
+        )
+        .await?;
+    let [(lat2, lon2, ts2), (lat1, lon1, ts1)] = locations[..] else {
+        return Ok(None);
+    };
+    Ok(Some(distance(lat1, lon1, lat2, lon2) / (ts2 - ts1) as f64))
+}
+
+/// Returns the minimum number of seconds between two location-only messages.
+fn send_interval(speed: Option<f64>, battery_level: Option<u32>) -> i64 {
+    let interval = match speed {
+        Some(speed) if speed >= FAST_SPEED => FAST_SEND_INTERVAL,
+        Some(speed) if speed < STATIONARY_SPEED => STATIONARY_SEND_INTERVAL,
+        _ => DEFAULT_SEND_INTERVAL,
+    };
+    match battery_level {
+        Some(level) if level < CRITICAL_BATTERY_LEVEL => 4 * interval,
+        Some(level) if level < LOW_BATTERY_LEVEL => 2 * interval,
+        _ => interval,
+    }
+}
+
 /// Returns number of seconds until the next time location streaming for some chat ends
 /// automatically.
 async fn maybe_send_locations(context: &Context) -> Result<Option<u64>> {
     let mut next_event: Option<u64> = None;
 
     let now = time();
+    let battery_level = *context.battery_level.lock().expect("Mutex is poisoned");
+    let interval = send_interval(get_speed(context, now).await?, battery_level);
     let rows = context
         .sql
         .query_map(
@@ -802,7 +1081,7 @@ async fn maybe_send_locations(context: &Context) -> Result<Option<u64>> {
 
     for (chat_id, locations_send_begin, locations_send_until, locations_last_sent) in rows {
         if locations_send_begin > 0 && locations_send_until > now {
-            let can_send = now > locations_last_sent + 60;
+            let can_send = now > locations_last_sent + interval;
             let has_locations = context
                 .sql
                 .exists(
@@ -842,7 +1121,7 @@ async fn maybe_send_locations(context: &Context) -> Result<Option<u64>> {
                     );
                     next_event = next_event
                         .into_iter()
-                        .chain(u64::try_from(locations_last_sent + 61 - now))
+                        .chain(u64::try_from(locations_last_sent + interval + 1 - now))
                         .min();
                 }
             } else {
@@ -1104,6 +1383,122 @@ async fn test_send_locations_to_chat() -> Result<()> {
         Ok(())
     }
 
+    #[test]
+    fn test_send_interval() {
+        assert!((distance(10.0, 20.0, 11.0, 20.0) - 111_195.0).abs() < 1.0);
+        assert_eq!(distance(10.0, 20.0, 10.0, 20.0), 0.0);
+
+        assert_eq!(send_interval(None, None), DEFAULT_SEND_INTERVAL);
+        assert_eq!(send_interval(Some(0.0), None), STATIONARY_SEND_INTERVAL);
+        assert_eq!(send_interval(Some(1.5), Some(50)), DEFAULT_SEND_INTERVAL);
+        assert_eq!(send_interval(Some(20.0), None), FAST_SEND_INTERVAL);
+        assert_eq!(send_interval(Some(20.0), Some(20)), 2 * FAST_SEND_INTERVAL);
+        assert_eq!(send_interval(None, Some(5)), 4 * DEFAULT_SEND_INTERVAL);
+    }
+
+    #[tokio::test(flavor = "multi_thread", worker_threads = 2)]
+    async fn test_adaptive_streaming() -> Result<()> {
+        let mut tcm = TestContextManager::new();
+        let alice = &tcm.alice().await;
+        let bob = &tcm.bob().await;
+        let alice_chat = alice.create_chat(bob).await;
+        send_locations_to_chat(alice, alice_chat.id, 10000).await?;
+        bob.recv_msg(&alice.pop_sent_msg().await).await;
+
+        assert_eq!(set(alice, 10.0, 20.0, 1.0).await?, true);
+        maybe_send_locations(alice).await?;
+        bob.recv_msg_opt(&alice.pop_sent_msg().await).await;
+        let last_sent: i64 = alice
+            .sql
+            .query_get_value(
+                "SELECT locations_last_sent FROM chats WHERE id=?",
+                (alice_chat.id,),
+            )
+            .await?
+            .unwrap();
+        let wait = |interval: i64| Some(u64::try_from(last_sent + interval + 1 - time()).unwrap());
+
+        // Alice does not move, so the next location is sent after five minutes.
+        SystemTime::shift(Duration::from_secs(10));
+        assert_eq!(set(alice, 10.0, 20.0, 1.0).await?, true);
+        assert_eq!(maybe_send_locations(alice).await?, wait(300));
+        assert!(alice.pop_sent_msg_opt(Duration::ZERO).await.is_none());
+
+        set_battery_level(alice, Some(10)).await;
+        assert_eq!(maybe_send_locations(alice).await?, wait(1200));
+
+        // Alice drives at 40 km/h, so the locations are sent every 30 seconds.
+        set_battery_level(alice, None).await;
+        SystemTime::shift(Duration::from_secs(10));
+        let timestamp = time();
+        assert_eq!(set(alice, 10.001, 20.0, 1.0).await?, true);
+        let next_event = maybe_send_locations(alice).await?;
+        assert_eq!(next_event, wait(30));
+        assert!(alice.pop_sent_msg_opt(Duration::ZERO).await.is_none());
+
+        // Both pending locations are sent in one message.
+        SystemTime::shift(Duration::from_secs(next_event.unwrap()));
+        maybe_send_locations(alice).await?;
+        bob.recv_msg_opt(&alice.pop_sent_msg().await).await;
+        assert_eq!(get_range(bob, None, None, 0, 0).await?.len(), 3);
+
+        let alice_id = bob.add_or_lookup_contact(alice).await.id;
+        let freshness = get_freshness(bob, None).await?;
+        assert_eq!(freshness.len(), 1);
+        assert_eq!(freshness[0].contact_id, alice_id);
+        assert_eq!(freshness[0].timestamp, timestamp);
+        assert!(!freshness[0].is_stale);
+
+        SystemTime::shift(Duration::from_secs(STALE_LOCATION_SECONDS as u64));
+        let freshness = get_freshness(bob, None).await?;
+        assert!(freshness[0].is_stale);
+        Ok(())
+    }
+
+    #[tokio::test(flavor = "multi_thread", worker_threads = 2)]
+    async fn test_export() -> Result<()> {
+        let mut tcm = TestContextManager::new();
+        let alice = &tcm.alice().await;
+        let bob = &tcm.bob().await;
+        let chat_id = alice.create_chat(bob).await.id;
+        send_locations_to_chat(alice, chat_id, 1000).await?;
+        alice.pop_sent_msg().await;
+
+        assert_eq!(set(alice, 10.0, 20.0, 1.0).await?, true);
+        SystemTime::shift(Duration::from_secs(10));
+        let timestamp = time();
+        assert_eq!(set(alice, 10.5, 20.5, 1.0).await?, true);
+        let mut msg = Message::new(Viewtype::Text);
+        msg.set_text("🏠".to_string());
+        msg.set_location(52.5, 13.25);
+        alice.send_msg(chat_id, &mut msg).await;
+
+        let kml = export(alice, chat_id, 0, 0, ExportFormat::Kml).await?;
+        assert!(kml.contains("<name>🏠</name>"));
+        let locations = Kml::parse(kml.as_bytes())?.locations;
+        let coordinates: Vec<_> = locations
+            .iter()
+            .map(|l| (l.latitude, l.longitude))
+            .collect();
+        assert_eq!(coordinates, [(52.5, 13.25), (10.0, 20.0), (10.5, 20.5)]);
+
+        let kml = export(alice, chat_id, timestamp, 0, ExportFormat::Kml).await?;
+        assert_eq!(Kml::parse(kml.as_bytes())?.locations.len(), 2);
+        let kml = export(alice, chat_id, 0, timestamp - 1, ExportFormat::Kml).await?;
+        assert_eq!(Kml::parse(kml.as_bytes())?.locations.len(), 1);
+
+        let gpx = export(alice, chat_id, 0, 0, ExportFormat::Gpx).await?;
+        assert!(gpx.contains("<wpt lat=\"52.5\" lon=\"13.25\">"));
+        assert!(gpx.contains("<name>🏠</name></wpt>"));
+        assert_eq!(gpx.matches("<trkpt ").count(), 2);
+        assert!(gpx.ends_with("</trkseg></trk>\n</gpx>"));
+
+        assert!(export(alice, DC_CHAT_ID_TRASH, 0, 0, ExportFormat::Gpx)
+            .await
+            .is_err());
+        Ok(())
+    }
+
     #[tokio::test(flavor = "multi_thread", worker_threads = 2)]
     async fn test_delete_expired_locations() -> Result<()> {
         let mut tcm = TestContextManager::new();
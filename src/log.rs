@@ -2,7 +2,60 @@
 
 #![allow(missing_docs)]
 
+use std::future::Future;
+
 use crate::context::Context;
+use crate::events::EventType;
+use crate::tools::time;
+
+/// Interval in seconds in which identical errors are reported only once.
+const REPEATED_ERROR_INTERVAL: i64 = 60;
+
+tokio::task_local! {
+    /// Set for background tasks whose repeated errors are deduplicated.
+    static DEDUP_ERRORS: ();
+}
+
+/// Runs a background task, deduplicating the errors it emits, see [`Context::dedup_error`].
+///
+/// Errors of other tasks, e.g. caused by user actions, are always reported.
+pub(crate) async fn dedup_errors<F: Future>(future: F) -> F::Output {
+    DEDUP_ERRORS.scope((), future).await
+}
+
+/// Returns true if the errors of the current task are deduplicated.
+pub(crate) fn dedups_errors() -> bool {
+    DEDUP_ERRORS.try_with(|_| ()).is_ok()
+}
+
+/// An error reported by a background task.
+#[derive(Debug)]
+pub(crate) struct RepeatedError {
+    msg: String,
+
+    /// Timestamp of the last report of the error.
+    reported: i64,
+
+    /// Number of times the error occurred since the last report.
+    count: usize,
+
+    /// Timestamp of the first occurrence since the last report.
+    first: i64,
+
+    /// Timestamp of the last occurrence.
+    last: i64,
+}
+
+impl RepeatedError {
+    fn to_event(&self) -> EventType {
+        EventType::ErrorRepeated {
+            msg: self.msg.clone(),
+            count: self.count,
+            first_timestamp: self.first,
+            last_timestamp: self.last,
+        }
+    }
+}
 
 #[macro_export]
 macro_rules! info {
@@ -59,6 +112,66 @@ pub fn get_last_error(&self) -> String {
         let last_error = &*self.last_error.read().unwrap();
         last_error.clone()
     }
+
+    /// Deduplicates identical errors.
+    ///
+    /// An error is reported at most once per [`REPEATED_ERROR_INTERVAL`],
+    /// further occurrences are summarized as [`EventType::ErrorRepeated`].
+    /// Different errors are deduplicated independently of each other.
+    /// Returns the events to emit instead of the error.
+    pub(crate) fn dedup_error(&self, msg: String) -> Vec<EventType> {
+        let now = time();
+        let mut repeated_errors = self.repeated_errors.lock().unwrap();
+        // Forget errors which were reported long enough ago and did not repeat since then.
+        repeated_errors.retain(|_, error| {
+            error.count > 0 || now.saturating_sub(error.reported) < REPEATED_ERROR_INTERVAL
+        });
+        let Some(error) = repeated_errors.get_mut(&msg) else {
+            repeated_errors.insert(
+                msg.clone(),
+                RepeatedError {
+                    msg: msg.clone(),
+                    reported: now,
+                    count: 0,
+                    first: now,
+                    last: now,
+                },
+            );
+            return vec![EventType::Error(msg)];
+        };
+        if error.count == 0 {
+            error.first = now;
+        }
+        error.count += 1;
+        error.last = now;
+        if now.saturating_sub(error.reported) < REPEATED_ERROR_INTERVAL {
+            return Vec::new();
+        }
+        let event = error.to_event();
+        error.reported = now;
+        error.count = 0;
+        vec![event]
+    }
+
+    /// Returns the summaries of pending error repetitions
+    /// which were not reported for [`REPEATED_ERROR_INTERVAL`].
+    ///
+    /// Called for all other events so that the repetitions are reported
+    /// even if the error does not occur anymore.
+    pub(crate) fn flush_repeated_errors(&self) -> Vec<EventType> {
+        let now = time();
+        let mut repeated_errors = self.repeated_errors.lock().unwrap();
+        let mut events = Vec::new();
+        for error in repeated_errors.values_mut() {
+            if error.count == 0 || now.saturating_sub(error.reported) < REPEATED_ERROR_INTERVAL {
+                continue;
+            }
+            events.push(error.to_event());
+            error.reported = now;
+            error.count = 0;
+        }
+        events
+    }
 }
 
 pub trait LogExt<T, E>
@@ -102,9 +215,13 @@ fn log_err(self, context: &Context) -> Result<T, E> {
 
 #[cfg(test)]
 mod tests {
+    use std::time::Duration;
+
     use anyhow::Result;
 
+    use super::*;
     use crate::test_utils::TestContext;
+    use crate::tools::SystemTime;
 
     #[tokio::test(flavor = "multi_thread", worker_threads = 2)]
     async fn test_get_last_error() -> Result<()> {
@@ -127,4 +244,94 @@ async fn test_get_last_error() -> Result<()> {
 
         Ok(())
     }
+
+    #[tokio::test(flavor = "multi_thread", worker_threads = 2)]
+    async fn test_dedup_error() -> Result<()> {
+        let t = TestContext::new().await;
+        let error = "Connection failed".to_string();
+        let start = time();
+
+        assert_eq!(
+            t.dedup_error(error.clone()),
+            vec![EventType::Error(error.clone())]
+        );
+        assert!(t.dedup_error(error.clone()).is_empty());
+        assert!(t.dedup_error(error.clone()).is_empty());
+
+        // After the interval, the repetitions are summarized.
+        SystemTime::shift(Duration::from_secs(REPEATED_ERROR_INTERVAL as u64));
+        let events = t.dedup_error(error.clone());
+        let [EventType::ErrorRepeated {
+            msg,
+            count,
+            first_timestamp,
+            last_timestamp,
+        }] = &events[..]
+        else {
+            panic!("Unexpected events {events:?}");
+        };
+        assert_eq!(msg, &error);
+        assert_eq!(*count, 3);
+        assert!(*first_timestamp >= start);
+        assert!(*last_timestamp >= *first_timestamp + REPEATED_ERROR_INTERVAL);
+
+        // Different errors are deduplicated independently.
+        assert!(t.dedup_error(error.clone()).is_empty());
+        assert_eq!(
+            t.dedup_error("Other error".to_string()),
+            vec![EventType::Error("Other error".to_string())]
+        );
+        assert!(t.dedup_error(error.clone()).is_empty());
+        assert!(t.dedup_error("Other error".to_string()).is_empty());
+
+        // Pending repetitions are reported after the interval
+        // even if the errors do not occur anymore.
+        assert_eq!(t.flush_repeated_errors(), vec![]);
+        SystemTime::shift(Duration::from_secs(REPEATED_ERROR_INTERVAL as u64));
+        let events = t.flush_repeated_errors();
+        assert_eq!(events.len(), 2);
+        assert!(events
+            .iter()
+            .all(|event| matches!(event, EventType::ErrorRepeated { count: 2 | 1, .. })));
+        assert_eq!(t.flush_repeated_errors(), vec![]);
+
+        // Errors which did not repeat are reported again after the interval.
+        SystemTime::shift(Duration::from_secs(REPEATED_ERROR_INTERVAL as u64));
+        assert_eq!(
+            t.dedup_error(error.clone()),
+            vec![EventType::Error(error.clone())]
+        );
+        Ok(())
+    }
+
+    #[tokio::test(flavor = "multi_thread", worker_threads = 2)]
+    async fn test_dedup_errors_of_background_tasks_only() -> Result<()> {
+        let t = TestContext::new().await;
+        let error = "Failed to do something".to_string();
+
+        // Errors caused by user actions are never suppressed.
+        for _ in 0..2 {
+            error!(t, "{error}");
+            t.evtracker
+                .get_matching(|evt| matches!(evt, EventType::Error(msg) if msg == &error))
+                .await;
+        }
+
+        dedup_errors(async {
+            error!(t, "{error}");
+            error!(t, "{error}");
+        })
+        .await;
+        info!(t, "Done");
+        let is_error_or_done = |evt: &EventType| match evt {
+            EventType::Error(_) => true,
+            EventType::Info(msg) => msg.ends_with("Done"),
+            _ => false,
+        };
+        let event = t.evtracker.get_matching(is_error_or_done).await;
+        assert_eq!(event, EventType::Error(error));
+        let event = t.evtracker.get_matching(is_error_or_done).await;
+        assert!(matches!(event, EventType::Info(_)));
+        Ok(())
+    }
 }
@@ -170,12 +170,12 @@ async fn from_database(context: &Context, prefix: &str) -> Result<Self> {
         let oauth2 = matches!(server_flags & DC_LP_AUTH_FLAGS, DC_LP_AUTH_OAUTH2);
 
         let key = &format!("{prefix}provider");
-        let provider = sql
-            .get_raw_config(key)
-            .await?
-            .and_then(|provider_id| get_provider_by_id(&provider_id));
+        let provider = match sql.get_raw_config(key).await? {
+            Some(provider_id) => get_provider_by_id(context, &provider_id).await,
+            None => None,
+        };
 
-        let socks5_config = Socks5Config::from_database(&context.sql).await?;
+        let socks5_config = Socks5Config::from_database(context).await?;
 
         Ok(LoginParam {
             addr,
@@ -358,7 +358,7 @@ async fn test_save_load_login_param() -> Result<()> {
                 oauth2: false,
                 certificate_checks: CertificateChecks::AcceptInvalidCertificates,
             },
-            provider: get_provider_by_id("example.com"),
+            provider: get_provider_by_id(&t, "example.com").await,
             // socks5_config is not saved by `save_to_database`, using default value
             socks5_config: None,
         };
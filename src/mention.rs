@@ -0,0 +1,317 @@
+//! # Mentions.
+//!
+//! A message can mention chat members, usually written as `@Name` in the text.
+//! The mentioned contacts are sent in the `Chat-Mentions` header
+//! as space-separated list of `addr:start-end` items,
+//! where `start-end` is the optional range of the mention in the message text
+//! counted in Unicode scalar values.
+//!
+//! Received messages mentioning us are still notified about
+//! if the chat is muted, see [`Context::get_fresh_msgs`].
+
+use std::ops::Range;
+
+use anyhow::{ensure, Result};
+
+use crate::chat::ChatId;
+use crate::contact::{Contact, ContactId, Origin};
+use crate::context::Context;
+use crate::message::{Message, MessageState, MsgId};
+use crate::param::Param;
+
+/// Maximum number of received mentions per message.
+const MENTIONS_MAX: usize = 100;
+
+/// A contact mentioned in a message.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Mention {
+    /// The mentioned contact.
+    pub contact_id: ContactId,
+
+    /// Range of the mention in the message text, counted in Unicode scalar values.
+    ///
+    /// `None` if the mention is not visible in the text.
+    pub range: Option<Range<usize>>,
+}
+
+impl Message {
+    /// Sets the contacts mentioned in the message.
+    ///
+    /// Must be called after setting the text.
+    /// The first `@Name` occurrence for each contact, using the display name of the contact,
+    /// is used as the range of the mention in the text.
+    /// Passing an empty list removes all mentions.
+    pub async fn set_mentions(
+        &mut self,
+        context: &Context,
+        contact_ids: &[ContactId],
+    ) -> Result<()> {
+        let mut mentions = Vec::new();
+        for contact_id in contact_ids {
+            ensure!(!contact_id.is_special(), "Cannot mention special contacts");
+            let contact = Contact::get_by_id(context, *contact_id).await?;
+            let range = find_mention(&self.text, contact.get_display_name());
+            mentions.push(Mention {
+                contact_id: *contact_id,
+                range,
+            });
+        }
+        if mentions.is_empty() {
+            self.param.remove(Param::Mentions);
+        } else {
+            self.param.set(Param::Mentions, render_mentions(&mentions));
+        }
+        Ok(())
+    }
+
+    /// Returns the contacts mentioned in the message.
+    pub fn get_mentions(&self) -> Vec<Mention> {
+        let text_len = self.text.chars().count();
+        self.param
+            .get(Param::Mentions)
+            .map(parse_mentions)
+            .unwrap_or_default()
+            .into_iter()
+            .filter_map(|(contact_id, range)| {
+                Some(Mention {
+                    contact_id: contact_id.parse().ok().map(ContactId::new)?,
+                    range: range.filter(|range| range.end <= text_len),
+                })
+            })
+            .collect()
+    }
+
+    /// Returns true if the message mentions us.
+    pub fn is_mentioning_self(&self) -> bool {
+        self.get_mentions()
+            .iter()
+            .any(|mention| mention.contact_id == ContactId::SELF)
+    }
+}
+
+impl ChatId {
+    /// Returns the number of fresh messages in the chat mentioning us.
+    ///
+    /// Can be used to highlight muted chats
+    /// where the user is addressed directly.
+    pub async fn get_fresh_mention_cnt(self, context: &Context) -> Result<usize> {
+        context
+            .sql
+            .count(
+                "SELECT COUNT(*)
+                   FROM msgs
+                  WHERE state=?
+                    AND hidden=0
+                    AND chat_id=?
+                    AND mentions_self=1",
+                (MessageState::InFresh, self),
+            )
+            .await
+    }
+}
+
+/// Returns the value of the `Chat-Mentions` header to send, if the message has mentions.
+pub(crate) async fn render_mentions_header(
+    context: &Context,
+    msg: &Message,
+) -> Result<Option<String>> {
+    let mut items = Vec::new();
+    for mention in msg.get_mentions() {
+        let Some(contact) = Contact::get_by_id_optional(context, mention.contact_id).await? else {
+            continue;
+        };
+        items.push(match mention.range {
+            Some(range) => format!("{}:{}-{}", contact.get_addr(), range.start, range.end),
+            None => contact.get_addr().to_string(),
+        });
+    }
+    Ok(Some(items.join(" ")).filter(|header| !header.is_empty()))
+}
+
+/// Parses the `Chat-Mentions` header.
+///
+/// Mentions of unknown contacts are dropped.
+pub(crate) async fn parse_mentions_header(
+    context: &Context,
+    header_value: &str,
+) -> Result<Vec<Mention>> {
+    let mut mentions = Vec::new();
+    for (addr, range) in parse_mentions(header_value).into_iter().take(MENTIONS_MAX) {
+        if let Some(contact_id) =
+            Contact::lookup_id_by_addr(context, &addr, Origin::Unknown).await?
+        {
+            mentions.push(Mention { contact_id, range });
+        }
+    }
+    Ok(mentions)
+}
+
+/// Marks the received message `msg_id` as mentioning us.
+pub(crate) async fn set_mentions_self(context: &Context, msg_id: MsgId) -> Result<()> {
+    context
+        .sql
+        .execute("UPDATE msgs SET mentions_self=1 WHERE id=?", (msg_id,))
+        .await?;
+    Ok(())
+}
+
+fn find_mention(text: &str, name: &str) -> Option<Range<usize>> {
+    let pattern = format!("@{name}");
+    let start = text.find(&pattern)?;
+    let start = text.get(..start)?.chars().count();
+    Some(start..start + pattern.chars().count())
+}
+
+/// Returns the value of [`Param::Mentions`] to store.
+pub(crate) fn render_mentions(mentions: &[Mention]) -> String {
+    mentions
+        .iter()
+        .map(|mention| match &mention.range {
+            Some(range) => format!(
+                "{}:{}-{}",
+                mention.contact_id.to_u32(),
+                range.start,
+                range.end
+            ),
+            None => mention.contact_id.to_u32().to_string(),
+        })
+        .collect::<Vec<_>>()
+        .join(" ")
+}
+
+/// Parses space-separated `id:start-end` items, the range being optional.
+fn parse_mentions(mentions: &str) -> Vec<(String, Option<Range<usize>>)> {
+    mentions
+        .split_ascii_whitespace()
+        .map(|item| {
+            if let Some((id, range)) = item.rsplit_once(':') {
+                if let Some(range) = parse_range(range) {
+                    return (id.to_string(), Some(range));
+                }
+            }
+            (item.to_string(), None)
+        })
+        .collect()
+}
+
+fn parse_range(range: &str) -> Option<Range<usize>> {
+    let (start, end) = range.split_once('-')?;
+    let range = start.parse().ok()?..end.parse().ok()?;
+    Some(range).filter(|range| range.start < range.end)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::chat::{marknoticed_chat, send_msg, set_muted, MuteDuration, ProtectionStatus};
+    use crate::context::FreshCounts;
+    use crate::message::Viewtype;
+    use crate::receive_imf::receive_imf;
+    use crate::test_utils::{TestContext, TestContextManager};
+
+    #[test]
+    fn test_parse_mentions() {
+        assert_eq!(
+            parse_mentions("bob@example.net:0-4 10 x:y 7:5-3"),
+            vec![
+                ("bob@example.net".to_string(), Some(0..4)),
+                ("10".to_string(), None),
+                ("x:y".to_string(), None),
+                ("7:5-3".to_string(), None),
+            ]
+        );
+        assert_eq!(find_mention("Hi @Bob!", "Bob"), Some(3..7));
+        assert_eq!(find_mention("Hi 🙂 @Bob!", "Bob"), Some(5..9));
+        assert_eq!(find_mention("Hi Bob!", "Bob"), None);
+    }
+
+    #[tokio::test(flavor = "multi_thread", worker_threads = 2)]
+    async fn test_mentions() -> Result<()> {
+        let mut tcm = TestContextManager::new();
+        let alice = &tcm.alice().await;
+        let bob = &tcm.bob().await;
+        let fiona = &tcm.fiona().await;
+
+        let alice_chat_id = alice
+            .create_group_with_members(ProtectionStatus::Unprotected, "Group", &[bob, fiona])
+            .await;
+        let sent = alice.send_text(alice_chat_id, "Hi!").await;
+        let bob_chat_id = bob.recv_msg(&sent).await.chat_id;
+        bob_chat_id.accept(bob).await?;
+        set_muted(bob, bob_chat_id, MuteDuration::Forever).await?;
+        let fiona_chat_id = fiona.recv_msg(&sent).await.chat_id;
+
+        let bob_contact = alice.add_or_lookup_contact(bob).await;
+        let mention_len = bob_contact.get_display_name().chars().count() + 1;
+        let mut msg = Message::new(Viewtype::Text);
+        msg.set_text(format!(
+            "@{} please have a look",
+            bob_contact.get_display_name()
+        ));
+        msg.set_mentions(alice, &[bob_contact.id]).await?;
+        assert_eq!(
+            msg.get_mentions(),
+            vec![Mention {
+                contact_id: bob_contact.id,
+                range: Some(0..mention_len)
+            }]
+        );
+        send_msg(alice, alice_chat_id, &mut msg).await?;
+        let sent = alice.pop_sent_msg().await;
+
+        let bob_msg = bob.recv_msg(&sent).await;
+        assert!(bob_msg.is_mentioning_self());
+        assert_eq!(bob_msg.get_mentions()[0].range, Some(0..mention_len));
+        assert_eq!(bob.get_fresh_msgs().await?, vec![bob_msg.id]);
+        assert_eq!(bob_chat_id.get_fresh_mention_cnt(bob).await?, 1);
+
+        let fiona_msg = fiona.recv_msg(&sent).await;
+        assert!(!fiona_msg.is_mentioning_self());
+        assert_eq!(fiona_chat_id.get_fresh_mention_cnt(fiona).await?, 0);
+
+        // Without mention, no notification in the muted chat.
+        let sent = alice.send_text(alice_chat_id, "Thanks").await;
+        bob.recv_msg(&sent).await;
+        assert_eq!(bob.get_fresh_msgs().await?, vec![bob_msg.id]);
+        assert_eq!(bob_chat_id.get_fresh_msg_cnt(bob).await?, 3);
+        assert_eq!(
+            bob.get_fresh_counts().await?,
+            FreshCounts {
+                total: 3,
+                unmuted: 0,
+                mentions: 1
+            }
+        );
+
+        // Cached counts are updated on changes.
+        set_muted(bob, bob_chat_id, MuteDuration::NotMuted).await?;
+        assert_eq!(bob.get_fresh_counts().await?.unmuted, 3);
+        marknoticed_chat(bob, bob_chat_id).await?;
+        assert_eq!(bob.get_fresh_counts().await?, FreshCounts::default());
+
+        Ok(())
+    }
+
+    #[tokio::test(flavor = "multi_thread", worker_threads = 2)]
+    async fn test_unencrypted_mentions_ignored() -> Result<()> {
+        let t = TestContext::new_bob().await;
+        receive_imf(
+            &t,
+            b"From: alice@example.org\n\
+              To: bob@example.net\n\
+              Subject: Hi\n\
+              Message-ID: <unencrypted-mention@example.org>\n\
+              Chat-Version: 1.0\n\
+              Chat-Mentions: bob@example.net:0-4\n\
+              Date: Sun, 22 Mar 2020 22:37:57 +0000\n\
+              \n\
+              @Bob hello\n",
+            false,
+        )
+        .await?;
+        let msg = t.get_last_msg().await;
+        assert!(!msg.is_mentioning_self());
+        assert!(msg.get_mentions().is_empty());
+        Ok(())
+    }
+}
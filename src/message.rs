@@ -1,6 +1,6 @@
 //! # Messages and their identifiers.
 
-use std::collections::BTreeSet;
+use std::collections::{BTreeMap, BTreeSet};
 use std::path::{Path, PathBuf};
 use std::str;
 
@@ -10,8 +10,9 @@
 use serde::{Deserialize, Serialize};
 use tokio::{fs, io};
 
+use crate::authres::AuthenticationResults;
 use crate::blob::BlobObject;
-use crate::chat::{Chat, ChatId, ChatIdBlocked, ChatVisibility};
+use crate::chat::{self, Chat, ChatId, ChatIdBlocked, ChatVisibility};
 use crate::chatlist_events;
 use crate::config::Config;
 use crate::constants::{
@@ -24,18 +25,29 @@
 use crate::ephemeral::{start_ephemeral_timers_msgids, Timer as EphemeralTimer};
 use crate::events::EventType;
 use crate::imap::markseen_on_imap_table;
+use crate::key::{load_self_secret_keyring, DcKey, Fingerprint};
 use crate::location::delete_poi_location;
-use crate::mimeparser::{parse_message_id, SystemMessage};
+use crate::mimeparser::{parse_message_id, MimeMessage, SystemMessage};
 use crate::param::{Param, Params};
 use crate::pgp::split_armored_data;
 use crate::reaction::get_msg_reactions;
 use crate::sql;
 use crate::summary::Summary;
 use crate::tools::{
-    buf_compress, buf_decompress, get_filebytes, get_filemeta, gm2local_offset, read_file, time,
-    timestamp_to_str, truncate,
+    buf_compress, buf_decompress, delete_file, get_filebytes, get_filemeta, gm2local_offset,
+    read_file, time, timestamp_to_str, truncate,
 };
 
+/// Previous text of an edited message, see [`MsgId::get_edit_history`].
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct MsgEdit {
+    /// The text before it was replaced.
+    pub text: String,
+
+    /// Time the text was replaced, in seconds since the epoch.
+    pub timestamp: i64,
+}
+
 /// Message ID, including reserved IDs.
 ///
 /// Some message IDs are reserved to identify special message types.
@@ -97,6 +109,34 @@ pub(crate) async fn get_param(self, context: &Context) -> Result<Params> {
             .unwrap_or_default())
     }
 
+    /// Returns why the message was assigned to its chat.
+    ///
+    /// Messages received before this was recorded
+    /// and messages sent from this device return [ChatAssignment::Unknown].
+    pub async fn explain_assignment(self, context: &Context) -> Result<ChatAssignment> {
+        let (chat_id, param) = context
+            .sql
+            .query_row_optional(
+                "SELECT chat_id, param FROM msgs WHERE id=?",
+                (self,),
+                |row| {
+                    let chat_id: ChatId = row.get(0)?;
+                    let param: String = row.get(1)?;
+                    Ok((chat_id, param))
+                },
+            )
+            .await?
+            .with_context(|| format!("Message {self} does not exist"))?;
+        if chat_id.is_trash() {
+            return Ok(ChatAssignment::Trash);
+        }
+        let param: Params = param.parse().unwrap_or_default();
+        Ok(param
+            .get_int(Param::ChatAssignment)
+            .and_then(ChatAssignment::from_i32)
+            .unwrap_or_default())
+    }
+
     /// Put message into trash chat and delete message text.
     ///
     /// It means the message is deleted locally, but not on the server.
@@ -122,7 +162,7 @@ pub async fn trash(self, context: &Context, on_server: bool) -> Result<()> {
                 &format!(
                     "UPDATE msgs SET \
                      chat_id=?, txt='', txt_normalized=NULL, \
-                     subject='', txt_raw='', \
+                     subject='', txt_raw='', txt_raw_compressed=0, \
                      mime_headers='', \
                      from_id=0, to_id=0, \
                      param=''{deleted_subst} \
@@ -131,6 +171,10 @@ pub async fn trash(self, context: &Context, on_server: bool) -> Result<()> {
                 (chat_id, self),
             )
             .await?;
+        context
+            .sql
+            .execute("DELETE FROM msgs_edits WHERE msg_id=?", (self,))
+            .await?;
 
         Ok(())
     }
@@ -142,7 +186,10 @@ pub(crate) async fn delete_from_db(self, context: &Context) -> Result<()> {
             .transaction(move |transaction| {
                 transaction.execute("DELETE FROM smtp WHERE msg_id=?", (self,))?;
                 transaction.execute("DELETE FROM msgs_mdns WHERE msg_id=?", (self,))?;
+                transaction.execute("DELETE FROM msgs_deliveries WHERE msg_id=?", (self,))?;
                 transaction.execute("DELETE FROM msgs_status_updates WHERE msg_id=?", (self,))?;
+                transaction.execute("DELETE FROM msgs_security WHERE msg_id=?", (self,))?;
+                transaction.execute("DELETE FROM msgs_edits WHERE msg_id=?", (self,))?;
                 transaction.execute("DELETE FROM msgs WHERE id=?", (self,))?;
                 Ok(())
             })
@@ -175,11 +222,72 @@ pub fn to_u32(self) -> u32 {
 
     /// Returns raw text of a message, used for message info
     pub async fn rawtext(self, context: &Context) -> Result<String> {
-        Ok(context
+        let (txt_raw, compressed) = context
             .sql
-            .query_get_value("SELECT txt_raw FROM msgs WHERE id=?", (self,))
+            .query_row_optional(
+                "SELECT txt_raw, txt_raw_compressed FROM msgs WHERE id=?",
+                (self,),
+                |row| {
+                    let txt_raw = sql::row_get_vec(row, 0)?;
+                    let compressed: bool = row.get(1)?;
+                    Ok((txt_raw, compressed))
+                },
+            )
             .await?
-            .unwrap_or_default())
+            .unwrap_or_default();
+        let txt_raw = if compressed {
+            buf_decompress(&txt_raw)?
+        } else {
+            txt_raw
+        };
+        Ok(String::from_utf8_lossy(&txt_raw).into_owned())
+    }
+
+    /// Returns the previous texts of an edited message, oldest first,
+    /// see [`crate::chat::send_edit`].
+    ///
+    /// The first entry holds the original text.
+    pub async fn get_edit_history(self, context: &Context) -> Result<Vec<MsgEdit>> {
+        context
+            .sql
+            .query_map(
+                "SELECT text, timestamp FROM msgs_edits WHERE msg_id=? ORDER BY timestamp, id",
+                (self,),
+                |row| {
+                    Ok(MsgEdit {
+                        text: row.get(0)?,
+                        timestamp: row.get(1)?,
+                    })
+                },
+                |rows| rows.collect::<Result<Vec<_>, _>>().map_err(Into::into),
+            )
+            .await
+    }
+
+    /// Deletes the attachment of a received view-once message after it was displayed,
+    /// see [`Message::set_view_once`].
+    ///
+    /// The message is kept as a placeholder without file.
+    /// The copies of the message on the server are deleted,
+    /// so that other devices cannot download the attachment anymore.
+    pub async fn mark_view_once_viewed(self, context: &Context) -> Result<()> {
+        let mut msg = Message::load_from_db(context, self).await?;
+        ensure!(
+            msg.is_view_once(),
+            "Message {self} is not a view-once message"
+        );
+        msg.remove_view_once_file(context).await?;
+        context
+            .sql
+            .execute(
+                "UPDATE imap SET target='' WHERE rfc724_mid=?",
+                (&msg.rfc724_mid,),
+            )
+            .await?;
+        context.emit_msgs_changed(msg.chat_id, self);
+        // Interrupt Inbox loop to start message deletion.
+        context.scheduler.interrupt_inbox().await;
+        Ok(())
     }
 
     /// Returns server foldernames and UIDs of a message, used for message info
@@ -286,6 +394,11 @@ pub async fn get_info(self, context: &Context) -> Result<String> {
             }
         }
 
+        let assignment = self.explain_assignment(context).await?;
+        if assignment != ChatAssignment::Unknown {
+            ret += &format!("Chat assignment: {assignment}\n");
+        }
+
         ret += &format!("State: {}", msg.state);
 
         if msg.has_location() {
@@ -465,6 +578,10 @@ pub struct Message {
 
     /// `In-Reply-To` header value.
     pub(crate) in_reply_to: Option<String>,
+
+    /// Message-ID of the root message of the topic thread
+    /// this message belongs to.
+    pub(crate) thread_root: Option<String>,
     pub(crate) is_dc_message: MessengerMessage,
     pub(crate) mime_modified: bool,
     pub(crate) chat_blocked: Blocked,
@@ -473,6 +590,112 @@ pub struct Message {
     pub(crate) param: Params,
 }
 
+/// The current state of a message.
+///
+/// Unlike [`Message`], this has a stable serialization
+/// and can be passed to bots and bridges.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[non_exhaustive]
+pub struct MessageInfo {
+    /// Message ID.
+    pub id: MsgId,
+
+    /// ID of the chat the message belongs to.
+    pub chat_id: ChatId,
+
+    /// `From:` contact ID.
+    pub from_id: ContactId,
+
+    /// Type of the message.
+    pub viewtype: Viewtype,
+
+    /// State of the message.
+    pub state: MessageState,
+
+    /// Message text.
+    pub text: String,
+
+    /// Message subject.
+    pub subject: String,
+
+    /// Time the message was sent, Unix time in seconds.
+    pub timestamp: i64,
+
+    /// Time the message was received, Unix time in seconds, 0 for outgoing messages.
+    pub received_timestamp: i64,
+
+    /// Time used to sort the message in the chat, Unix time in seconds.
+    pub sort_timestamp: i64,
+
+    /// Whether the message is an informational message.
+    pub is_info: bool,
+
+    /// Whether the message is forwarded.
+    pub is_forwarded: bool,
+
+    /// Whether the message is end-to-end encrypted.
+    pub is_encrypted: bool,
+
+    /// Name of the attached file, if any.
+    pub file_name: Option<String>,
+
+    /// MIME type of the attached file, if any.
+    pub file_mime: Option<String>,
+
+    /// Error message if sending or receiving the message failed.
+    pub error: Option<String>,
+}
+
+impl From<&Message> for MessageInfo {
+    fn from(msg: &Message) -> Self {
+        Self {
+            id: msg.id,
+            chat_id: msg.chat_id,
+            from_id: msg.from_id,
+            viewtype: msg.viewtype,
+            state: msg.state,
+            text: msg.text.clone(),
+            subject: msg.subject.clone(),
+            timestamp: msg.get_timestamp(),
+            received_timestamp: msg.timestamp_rcvd,
+            sort_timestamp: msg.timestamp_sort,
+            is_info: msg.is_info(),
+            is_forwarded: msg.is_forwarded(),
+            is_encrypted: msg.get_showpadlock(),
+            file_name: msg.get_filename(),
+            file_mime: msg.get_filemime(),
+            error: msg.error.clone(),
+        }
+    }
+}
+
+/// Rendering hints for a single message bubble.
+///
+/// Bundles the flags assistive-technology frontends need
+/// to describe a message, see [`Message::get_render_hints`].
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct RenderHints {
+    /// True if the message is forwarded.
+    pub is_forwarded: bool,
+
+    /// True if the message is an informational message.
+    pub is_info: bool,
+
+    /// Kind of the informational message,
+    /// [`SystemMessage::Unknown`] for normal messages.
+    pub info_type: SystemMessage,
+
+    /// True if the message has an ephemeral timer set.
+    pub has_ephemeral_timer: bool,
+
+    /// True if the text was edited by the sender, see [`Message::is_edited`].
+    pub is_edited: bool,
+
+    /// Reactions to the message as emoji and count,
+    /// sorted by descending frequency.
+    pub reactions: Vec<(String, usize)>,
+}
+
 impl Message {
     /// Creates a new message with given view type.
     pub fn new(viewtype: Viewtype) -> Self {
@@ -509,6 +732,7 @@ pub async fn load_from_db_optional(context: &Context, id: MsgId) -> Result<Optio
                     "    m.id AS id,",
                     "    rfc724_mid AS rfc724mid,",
                     "    m.mime_in_reply_to AS mime_in_reply_to,",
+                    "    m.thread_root AS thread_root,",
                     "    m.chat_id AS chat_id,",
                     "    m.from_id AS from_id,",
                     "    m.to_id AS to_id,",
@@ -559,6 +783,9 @@ pub async fn load_from_db_optional(context: &Context, id: MsgId) -> Result<Optio
                         in_reply_to: row
                             .get::<_, Option<String>>("mime_in_reply_to")?
                             .and_then(|in_reply_to| parse_message_id(&in_reply_to).ok()),
+                        thread_root: row
+                            .get::<_, Option<String>>("thread_root")?
+                            .filter(|thread_root| !thread_root.is_empty()),
                         chat_id: row.get("chat_id")?,
                         from_id: row.get("from_id")?,
                         to_id: row.get("to_id")?,
@@ -617,6 +844,16 @@ pub fn get_file(&self, context: &Context) -> Option<PathBuf> {
         self.param.get_path(Param::File, context).unwrap_or(None)
     }
 
+    /// Returns the path to a small JPEG preview of the attached video or PDF file.
+    ///
+    /// Previews are only available if the file contains an embedded image
+    /// as cover art or page thumbnail.
+    pub fn get_preview_blob(&self, context: &Context) -> Option<PathBuf> {
+        self.param
+            .get_path(Param::PreviewFile, context)
+            .unwrap_or(None)
+    }
+
     /// Returns vector of vcards if the file has a vCard attachment.
     pub async fn vcard_contacts(&self, context: &Context) -> Result<Vec<VcardContact>> {
         if self.viewtype != Viewtype::Vcard {
@@ -819,11 +1056,67 @@ pub fn get_duration(&self) -> i32 {
         self.param.get_int(Param::Duration).unwrap_or_default()
     }
 
+    /// Returns the number of frames of an animated image, 0 for still images or if unknown.
+    ///
+    /// UIs may use this to decide whether to autoplay `Viewtype::Gif` messages.
+    pub fn get_frame_count(&self) -> i32 {
+        self.param.get_int(Param::Frames).unwrap_or_default()
+    }
+
+    /// Returns true if the message was deleted for everyone by its sender,
+    /// see [`delete_for_all`].
+    ///
+    /// The text and the attachment of such messages are removed,
+    /// UIs should show a placeholder instead.
+    pub fn is_retracted(&self) -> bool {
+        self.param.get_bool(Param::IsRetracted).unwrap_or_default()
+    }
+
+    /// Returns true if the text was edited by the sender,
+    /// see [`MsgId::get_edit_history`] for the previous texts.
+    pub fn is_edited(&self) -> bool {
+        self.param.get_bool(Param::IsEdited).unwrap_or_default()
+    }
+
     /// Returns true if padlock indicating message encryption should be displayed in the UI.
     pub fn get_showpadlock(&self) -> bool {
         self.param.get_int(Param::GuaranteeE2ee).unwrap_or_default() != 0
     }
 
+    /// Returns true if the message is unencrypted,
+    /// but signed with a valid PGP/MIME signature of the sender.
+    ///
+    /// UI may display this similar to the padlock,
+    /// but must not claim that the message is encrypted.
+    pub fn is_clearsigned(&self) -> bool {
+        self.param.get_bool(Param::Clearsigned).unwrap_or_default()
+    }
+
+    /// Sends the message unencrypted, but with a PGP/MIME signature,
+    /// so it is readable in any mail client while still being authenticatable.
+    ///
+    /// Has no effect in protected chats.
+    pub fn set_clearsigned(&mut self, clearsigned: bool) {
+        if clearsigned {
+            self.param.set_int(Param::Clearsigned, 1);
+        } else {
+            self.param.remove(Param::Clearsigned);
+        }
+    }
+
+    /// Returns the DKIM, SPF and DMARC results our email server reported for an incoming message.
+    ///
+    /// All results are unknown for outgoing messages
+    /// and if the server does not add Authentication-Results headers.
+    /// If `dkim_warning` is set, the sender is a known contact, but DKIM failed;
+    /// UI should warn that the message may be spoofed.
+    pub fn get_authentication_results(&self) -> AuthenticationResults {
+        self.param
+            .get(Param::AuthenticationResults)
+            .and_then(|s| s.parse().ok())
+            .unwrap_or_default()
+    }
+
     /// Returns true if message is auto-generated.
     pub fn is_bot(&self) -> bool {
         self.param.get_bool(Param::Bot).unwrap_or_default()
@@ -909,6 +1202,52 @@ pub fn is_forwarded(&self) -> bool {
         0 != self.param.get_int(Param::Forwarded).unwrap_or_default()
     }
 
+    /// Returns true if the attachment is shown only once, see [`Message::set_view_once`].
+    pub fn is_view_once(&self) -> bool {
+        self.param.get_bool(Param::ViewOnce).unwrap_or_default()
+    }
+
+    /// Makes the attached image, video or audio file viewable only once.
+    ///
+    /// The receiver deletes the file by calling [`MsgId::mark_view_once_viewed`]
+    /// after displaying it, the sender's copy of the file is deleted once the message is sent.
+    /// View-once messages cannot be forwarded or saved.
+    pub fn set_view_once(&mut self, view_once: bool) {
+        if view_once {
+            self.param.set_int(Param::ViewOnce, 1);
+        } else {
+            self.param.remove(Param::ViewOnce);
+        }
+    }
+
+    /// Removes the attachment of a view-once message, keeping the message as a placeholder.
+    ///
+    /// The blob is deleted unless it is used by another message.
+    pub(crate) async fn remove_view_once_file(&mut self, context: &Context) -> Result<()> {
+        let Some(file) = self.param.get(Param::File).map(ToString::to_string) else {
+            return Ok(());
+        };
+        for key in [
+            Param::File,
+            Param::PreviewFile,
+            Param::Width,
+            Param::Height,
+            Param::Duration,
+        ] {
+            self.param.remove(key);
+        }
+        self.update_param(context).await?;
+        // The stored MIME message contains the attachment as well.
+        context
+            .sql
+            .execute(
+                "UPDATE msgs SET mime_headers='', mime_compressed=0 WHERE id=?",
+                (self.id,),
+            )
+            .await?;
+        delete_unused_blob(context, &file).await
+    }
+
     /// Returns true if the message is an informational message.
     pub fn is_info(&self) -> bool {
         let cmd = self.param.get_cmd();
@@ -922,6 +1261,19 @@ pub fn get_info_type(&self) -> SystemMessage {
         self.param.get_cmd()
     }
 
+    /// Returns all rendering hints for the message at once.
+    pub async fn get_render_hints(&self, context: &Context) -> Result<RenderHints> {
+        let reactions = get_msg_reactions(context, self.id).await?;
+        Ok(RenderHints {
+            is_forwarded: self.is_forwarded(),
+            is_info: self.is_info(),
+            info_type: self.get_info_type(),
+            has_ephemeral_timer: self.ephemeral_timer != EphemeralTimer::Disabled,
+            is_edited: self.is_edited(),
+            reactions: reactions.emoji_sorted_by_frequency(),
+        })
+    }
+
     /// Returns true if the message is a system message.
     pub fn is_system_message(&self) -> bool {
         let cmd = self.param.get_cmd();
@@ -1121,6 +1473,21 @@ pub fn set_override_sender_name(&mut self, name: Option<String>) {
             .set_optional(Param::OverrideSenderDisplayname, name);
     }
 
+    /// Sets the alternative text describing the attached file,
+    /// e.g. the content of an image for screen readers.
+    pub fn set_alt_text(&mut self, text: Option<String>) {
+        self.param.set_optional(
+            Param::AltText,
+            text.map(|text| normalize_alt_text(&text))
+                .filter(|text| !text.is_empty()),
+        );
+    }
+
+    /// Returns the alternative text describing the attached file, if any.
+    pub fn get_alt_text(&self) -> Option<String> {
+        self.param.get(Param::AltText).map(|text| text.to_string())
+    }
+
     /// Sets the dimensions of associated image or video file.
     pub fn set_dimension(&mut self, width: i32, height: i32) {
         self.param.set_int(Param::Width, width);
@@ -1247,6 +1614,45 @@ pub async fn parent(&self, context: &Context) -> Result<Option<Message>> {
         Ok(None)
     }
 
+    /// Returns the IDs of the messages quoting this message, see [`get_replies`].
+    pub async fn get_replies(&self, context: &Context) -> Result<Vec<MsgId>> {
+        get_replies(context, self.id).await
+    }
+
+    /// Puts the message into the topic thread of `root`.
+    ///
+    /// If `root` itself is a reply in a thread,
+    /// the message is put into the same thread.
+    /// Passing `None` removes the message from any thread.
+    pub fn set_thread_root(&mut self, root: Option<&Message>) -> Result<()> {
+        if let Some(root) = root {
+            ensure!(
+                !root.rfc724_mid.is_empty(),
+                "Message without Message-Id cannot be a thread root"
+            );
+            self.thread_root = Some(
+                root.thread_root
+                    .clone()
+                    .unwrap_or_else(|| root.rfc724_mid.clone()),
+            );
+        } else {
+            self.thread_root = None;
+        }
+        Ok(())
+    }
+
+    /// Returns the ID of the root message of the topic thread
+    /// the message belongs to,
+    /// if the message is a thread reply and the root exists in the database.
+    pub async fn get_thread_root(&self, context: &Context) -> Result<Option<MsgId>> {
+        if let Some(thread_root) = &self.thread_root {
+            if let Some((msg_id, _ts_sent)) = rfc724_mid_exists(context, thread_root).await? {
+                return Ok(Some(msg_id));
+            }
+        }
+        Ok(None)
+    }
+
     /// Force the message to be sent in plain text.
     pub fn force_plaintext(&mut self) {
         self.param.set_int(Param::ForcePlaintext, 1);
@@ -1378,6 +1784,66 @@ fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
     }
 }
 
+/// Reason why a received message was assigned to its chat.
+///
+/// Returned by [MsgId::explain_assignment], mainly useful for debugging misfiled messages.
+#[derive(
+    Debug, Default, Clone, Copy, PartialEq, Eq, FromPrimitive, ToPrimitive, Serialize, Deserialize,
+)]
+#[repr(u32)]
+pub enum ChatAssignment {
+    /// Unknown, e.g. the message was sent from this device
+    /// or received before the reason was recorded.
+    #[default]
+    Unknown = 0,
+
+    /// The message was trashed, e.g. because it is an MDN, a reaction
+    /// or a classical email while classical emails are not shown.
+    Trash = 1,
+
+    /// The `Chat-Group-ID` header matched a group or a new group was created for it.
+    GroupId = 2,
+
+    /// `In-Reply-To` or `References` point to a message in the chat.
+    Reply = 3,
+
+    /// A new ad-hoc group was created for the message recipients.
+    AdHocGroup = 4,
+
+    /// The `List-Id` or `List-Post` header matched a mailing list or broadcast list.
+    MailingList = 5,
+
+    /// The message looks forged and was quarantined
+    /// instead of being put into the 1:1 chat with the sender.
+    SpoofingQuarantine = 6,
+
+    /// Fallback to the 1:1 chat with the sender or, for outgoing messages, the recipient.
+    OneOneChat = 7,
+
+    /// The message was sent by ourselves to ourselves.
+    SelfChat = 8,
+}
+
+impl std::fmt::Display for ChatAssignment {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        write!(
+            f,
+            "{}",
+            match self {
+                Self::Unknown => "Unknown",
+                Self::Trash => "Trashed",
+                Self::GroupId => "Chat-Group-ID header",
+                Self::Reply => "Reply to a message in the chat (In-Reply-To/References)",
+                Self::AdHocGroup => "New ad-hoc group for the recipients",
+                Self::MailingList => "Mailing list ID",
+                Self::SpoofingQuarantine => "Possibly forged sender, quarantined",
+                Self::OneOneChat => "Fallback to 1:1 chat",
+                Self::SelfChat => "Sent to self",
+            }
+        )
+    }
+}
+
 impl MessageState {
     /// Returns true if the message can transition to `OutFailed` state from the current state.
     pub fn can_fail(self) -> bool {
@@ -1418,6 +1884,154 @@ pub async fn get_msg_read_receipts(
         .await
 }
 
+/// Delivery and read state of a message for a single recipient,
+/// as returned by [`get_read_receipts`].
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ReadReceipt {
+    /// The recipient.
+    pub contact_id: ContactId,
+
+    /// Time when the SMTP server accepted the message for the recipient.
+    pub delivered_timestamp: Option<i64>,
+
+    /// Time when the recipient sent a read receipt.
+    pub read_timestamp: Option<i64>,
+}
+
+/// Returns the recipients of an outgoing group message
+/// the message was delivered to or that sent read receipts, in the order of these events.
+///
+/// Unlike the message state that only reflects the first read receipt,
+/// this allows to show the state for each member.
+pub async fn get_read_receipts(context: &Context, msg_id: MsgId) -> Result<Vec<ReadReceipt>> {
+    context
+        .sql
+        .query_map(
+            "SELECT contact_id, MAX(delivered), MAX(read) FROM (
+               SELECT contact_id, timestamp AS delivered, NULL AS read
+               FROM msgs_deliveries WHERE msg_id=?1
+               UNION ALL
+               SELECT contact_id, NULL, timestamp_sent
+               FROM msgs_mdns WHERE msg_id=?1)
+             GROUP BY contact_id
+             ORDER BY MIN(IFNULL(delivered, read)), contact_id",
+            (msg_id,),
+            |row| {
+                Ok(ReadReceipt {
+                    contact_id: row.get(0)?,
+                    delivered_timestamp: row.get(1)?,
+                    read_timestamp: row.get(2)?,
+                })
+            },
+            |rows| rows.collect::<Result<Vec<_>, _>>().map_err(Into::into),
+        )
+        .await
+}
+
+/// Records that the group message was delivered to the recipients `addrs` over SMTP.
+///
+/// Addresses not belonging to group members and messages in other chats are ignored.
+pub(crate) async fn save_delivery(
+    context: &Context,
+    msg_id: MsgId,
+    addrs: Vec<String>,
+) -> Result<()> {
+    let now = time();
+    context
+        .sql
+        .transaction(move |transaction| {
+            let mut stmt = transaction.prepare(
+                "INSERT OR IGNORE INTO msgs_deliveries (msg_id, contact_id, timestamp)
+                 SELECT ?1, ct.id, ?2 FROM contacts ct
+                 INNER JOIN msgs m ON m.id=?1
+                 INNER JOIN chats c ON c.id=m.chat_id
+                 WHERE ct.addr=?3 COLLATE NOCASE AND ct.id>?4 AND c.type=?5
+                   AND ct.id IN (SELECT contact_id FROM chats_contacts WHERE chat_id=c.id)",
+            )?;
+            for addr in addrs {
+                stmt.execute((msg_id, now, addr, ContactId::LAST_SPECIAL, Chattype::Group))?;
+            }
+            Ok(())
+        })
+        .await
+}
+
+/// Cryptographic protection of a message, as returned by [`get_security_info`].
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct SecurityInfo {
+    /// The message is end-to-end encrypted.
+    pub encrypted: bool,
+
+    /// Fingerprint of the key that made a valid signature of the message, if any.
+    ///
+    /// Set for encrypted messages as well as for unencrypted signed messages.
+    pub signing_fingerprint: Option<Fingerprint>,
+
+    /// The signing key was verified, e.g. by scanning a QR code,
+    /// when the message was sent or received.
+    pub verified: bool,
+
+    /// The message was encrypted with post-quantum hybrid encryption.
+    pub pq_hybrid: bool,
+
+    /// The message contained keys of other recipients in `Autocrypt-Gossip` headers.
+    pub gossiped: bool,
+}
+
+/// Returns the cryptographic details of a message,
+/// e.g. to show a padlock detail screen.
+pub async fn get_security_info(context: &Context, msg_id: MsgId) -> Result<SecurityInfo> {
+    let msg = Message::load_from_db(context, msg_id).await?;
+    let mut info = context
+        .sql
+        .query_row_optional(
+            "SELECT signing_fingerprint, verified, pq_hybrid, gossiped
+             FROM msgs_security WHERE msg_id=?",
+            (msg_id,),
+            |row| {
+                let signing_fingerprint: String = row.get(0)?;
+                Ok(SecurityInfo {
+                    encrypted: false,
+                    signing_fingerprint: signing_fingerprint.parse().ok(),
+                    verified: row.get(1)?,
+                    pq_hybrid: row.get(2)?,
+                    gossiped: row.get(3)?,
+                })
+            },
+        )
+        .await?
+        .unwrap_or_default();
+    info.encrypted = msg.get_showpadlock();
+    Ok(info)
+}
+
+/// Saves cryptographic details of the message for [`get_security_info`].
+pub(crate) async fn save_security_info(
+    context: &Context,
+    msg_id: MsgId,
+    info: &SecurityInfo,
+) -> Result<()> {
+    context
+        .sql
+        .execute(
+            "INSERT OR REPLACE INTO msgs_security
+             (msg_id, signing_fingerprint, verified, pq_hybrid, gossiped)
+             VALUES (?, ?, ?, ?, ?)",
+            (
+                msg_id,
+                info.signing_fingerprint
+                    .as_ref()
+                    .map(|fp| fp.hex())
+                    .unwrap_or_default(),
+                info.verified,
+                info.pq_hybrid,
+                info.gossiped,
+            ),
+        )
+        .await?;
+    Ok(())
+}
+
 pub(crate) fn guess_msgtype_from_suffix(path: &Path) -> Option<(Viewtype, &str)> {
     let extension: &str = &path.extension()?.to_str()?.to_lowercase();
     let info = match extension {
@@ -1504,22 +2118,95 @@ pub(crate) fn guess_msgtype_from_suffix(path: &Path) -> Option<(Viewtype, &str)>
     Some(info)
 }
 
-/// Get the raw mime-headers of the given message.
-/// Raw headers are saved for incoming messages
-/// only if `set_config(context, "save_mime_headers", "1")`
-/// was called before.
+/// Minimum size in bytes of raw texts and MIME headers to store them compressed.
 ///
-/// Returns an empty vector if there are no headers saved for the given message,
-/// e.g. because of save_mime_headers is not set
-/// or the message is not incoming.
-pub async fn get_mime_headers(context: &Context, msg_id: MsgId) -> Result<Vec<u8>> {
-    let (headers, compressed) = context
-        .sql
-        .query_row(
-            "SELECT mime_headers, mime_compressed FROM msgs WHERE id=?",
-            (msg_id,),
-            |row| {
-                let headers = sql::row_get_vec(row, 0)?;
+/// Compressing small texts would save little space.
+const TXT_RAW_COMPRESSION_MIN: usize = 4096;
+
+/// Returns the value to store in `msgs.txt_raw` and whether it is compressed.
+///
+/// Texts shorter than [`TXT_RAW_COMPRESSION_MIN`] are stored as is.
+pub(crate) fn txt_raw_to_sql(txt_raw: String) -> Result<(rusqlite::types::Value, bool)> {
+    if txt_raw.len() < TXT_RAW_COMPRESSION_MIN {
+        return Ok((rusqlite::types::Value::Text(txt_raw), false));
+    }
+    let compressed = buf_compress(txt_raw.as_bytes())?;
+    Ok((rusqlite::types::Value::Blob(compressed), true))
+}
+
+/// Compresses large raw texts and MIME headers stored uncompressed by previous versions.
+///
+/// Called during housekeeping.
+pub(crate) async fn compress_stored_texts(context: &Context) -> Result<()> {
+    compress_column(
+        context,
+        "SELECT id, txt_raw FROM msgs
+         WHERE txt_raw_compressed=0 AND length(CAST(txt_raw AS BLOB))>=?
+         LIMIT 100",
+        "UPDATE msgs SET txt_raw=?, txt_raw_compressed=1 WHERE id=? AND txt_raw_compressed=0",
+    )
+    .await?;
+    compress_column(
+        context,
+        "SELECT id, mime_headers FROM msgs
+         WHERE mime_compressed=0 AND length(CAST(mime_headers AS BLOB))>=?
+         LIMIT 100",
+        "UPDATE msgs SET mime_headers=?, mime_compressed=1 WHERE id=? AND mime_compressed=0",
+    )
+    .await
+}
+
+/// Compresses the column values returned by `select` in batches and stores them with `update`.
+async fn compress_column(context: &Context, select: &str, update: &str) -> Result<()> {
+    let mut count = 0;
+    loop {
+        let rows = context
+            .sql
+            .query_map(
+                select,
+                (TXT_RAW_COMPRESSION_MIN,),
+                |row| {
+                    let msg_id: MsgId = row.get(0)?;
+                    let buf = sql::row_get_vec(row, 1)?;
+                    Ok((msg_id, buf))
+                },
+                |rows| {
+                    rows.collect::<std::result::Result<Vec<_>, _>>()
+                        .map_err(Into::into)
+                },
+            )
+            .await?;
+        if rows.is_empty() {
+            break;
+        }
+        for (msg_id, buf) in rows {
+            let compressed = tokio::task::block_in_place(move || buf_compress(&buf))?;
+            context.sql.execute(update, (compressed, msg_id)).await?;
+            count += 1;
+        }
+    }
+    if count > 0 {
+        info!(context, "Compressed {count} stored texts.");
+    }
+    Ok(())
+}
+
+/// Get the raw mime-headers of the given message.
+/// Raw headers are saved for incoming messages
+/// only if `set_config(context, "save_mime_headers", "1")`
+/// was called before.
+///
+/// Returns an empty vector if there are no headers saved for the given message,
+/// e.g. because of save_mime_headers is not set
+/// or the message is not incoming.
+pub async fn get_mime_headers(context: &Context, msg_id: MsgId) -> Result<Vec<u8>> {
+    let (headers, compressed) = context
+        .sql
+        .query_row(
+            "SELECT mime_headers, mime_compressed FROM msgs WHERE id=?",
+            (msg_id,),
+            |row| {
+                let headers = sql::row_get_vec(row, 0)?;
                 let compressed: bool = row.get(1)?;
                 Ok((headers, compressed))
             },
@@ -1562,6 +2249,94 @@ pub async fn get_mime_headers(context: &Context, msg_id: MsgId) -> Result<Vec<u8
     Ok(headers)
 }
 
+/// Returns the decrypted MIME of the given message.
+///
+/// For unencrypted messages, this is the same as [`get_mime_headers`].
+/// As with [`get_mime_headers`], an empty vector is returned if no MIME was saved.
+pub async fn get_decrypted_mime(context: &Context, msg_id: MsgId) -> Result<Vec<u8>> {
+    let raw = get_mime_headers(context, msg_id).await?;
+    if raw.is_empty() {
+        return Ok(raw);
+    }
+    let mail = mailparse::parse_mail(&raw)?;
+    let private_keyring = load_self_secret_keyring(context).await?;
+    let (decrypted, _pq_hybrid) = MimeMessage::decrypt(context, &mail, &private_keyring, &[]).await;
+    match decrypted? {
+        Some((decrypted, _signatures)) => Ok(decrypted),
+        None => Ok(raw),
+    }
+}
+
+/// Runs the security checks of the MIME parser on the saved MIME of the given message
+/// and returns a human-readable report.
+///
+/// This is meant for debugging interoperability problems with other MUAs.
+/// Unlike receiving the message, the checks do not modify any peerstates.
+/// The MIME is only available if `save_mime_headers` was set when the message was received.
+pub async fn verify_mime(context: &Context, msg_id: MsgId) -> Result<String> {
+    let msg = Message::load_from_db(context, msg_id).await?;
+    let raw = get_mime_headers(context, msg_id).await?;
+    ensure!(
+        !raw.is_empty(),
+        "No MIME saved for {msg_id}, set save_mime_headers before receiving messages"
+    );
+    let padlock = if msg.get_showpadlock() { "yes" } else { "no" };
+
+    // Parse the message as if it was imported, so that no keys are changed.
+    let mime = match MimeMessage::from_bytes_ex(context, &raw, None, true).await {
+        Ok(mime) => mime,
+        Err(err) => {
+            return Ok(format!(
+                "Shown with padlock: {padlock}\nRejected: {err:#}\n"
+            ))
+        }
+    };
+    let mut ret = format!("From: {}\n", mime.from.addr);
+    ret += &format!("Shown with padlock: {padlock}\n");
+
+    match &mime.decryption_info.autocrypt_header {
+        Some(aheader) => {
+            ret += &format!(
+                "Autocrypt header: key {}\n",
+                DcKey::fingerprint(&aheader.public_key)
+            );
+        }
+        None => ret += "Autocrypt header: none\n",
+    }
+
+    if mime.was_encrypted() {
+        ret += "Encrypted: yes, decrypted successfully\n";
+    } else if mime.decrypting_failed {
+        ret += "Encrypted: yes, decryption failed\n";
+        return Ok(ret);
+    } else {
+        ret += "Encrypted: no\n";
+    }
+
+    let signatures: Vec<_> = match mime.was_encrypted() {
+        true => mime.signatures.iter().collect(),
+        false => mime.signing_fingerprint.iter().collect(),
+    };
+    if signatures.is_empty() {
+        ret += "Valid signatures: none\n";
+    } else {
+        for fingerprint in signatures {
+            ret += &format!("Valid signature: {fingerprint}\n");
+        }
+    }
+
+    if mime.was_encrypted() {
+        let from_is_signed = if mime.from_is_signed {
+            "signed"
+        } else {
+            "none"
+        };
+        ret += &format!("Protected From header: {from_is_signed}\n");
+    }
+
+    Ok(ret)
+}
+
 /// Deletes requested messages
 /// by moving them to the trash chat
 /// and scheduling for deletion on IMAP.
@@ -1640,6 +2415,151 @@ pub async fn delete_msgs(context: &Context, msg_ids: &[MsgId]) -> Result<()> {
     Ok(())
 }
 
+/// Deletes our messages for everyone.
+///
+/// A request to delete the messages is sent to their chats
+/// and the messages are replaced by tombstones, see [`Message::is_retracted`].
+/// Receivers not supporting the request keep the messages.
+///
+/// Messages can only be deleted for everyone
+/// during [`Config::DeleteForAllWindow`] after sending them.
+pub async fn delete_for_all(context: &Context, msg_ids: &[MsgId]) -> Result<()> {
+    let window = context.get_config_i64(Config::DeleteForAllWindow).await?;
+    let now = time();
+    let mut msgs_by_chat: BTreeMap<ChatId, Vec<Message>> = BTreeMap::new();
+    for &msg_id in msg_ids {
+        let msg = Message::load_from_db(context, msg_id).await?;
+        ensure!(
+            msg.from_id == ContactId::SELF,
+            "Can delete only own messages for everyone"
+        );
+        ensure!(
+            !msg.is_info() && !msg.hidden && !msg.chat_id.is_special(),
+            "Message {msg_id} cannot be deleted for everyone"
+        );
+        ensure!(
+            window <= 0 || now.saturating_sub(msg.timestamp_sort) <= window,
+            "Message {msg_id} is too old to be deleted for everyone"
+        );
+        msgs_by_chat.entry(msg.chat_id).or_default().push(msg);
+    }
+
+    for (chat_id, msgs) in msgs_by_chat {
+        let mut request = Message::new(Viewtype::Text);
+        request.text = "🚮".to_string();
+        request.param.set(
+            Param::DeleteRequestFor,
+            msgs.iter()
+                .map(|msg| msg.rfc724_mid.as_str())
+                .collect::<Vec<_>>()
+                .join(" "),
+        );
+        request.hidden = true;
+        chat::send_msg(context, chat_id, &mut request).await?;
+        for msg in msgs {
+            retract_msg(context, msg).await?;
+        }
+    }
+    Ok(())
+}
+
+/// Deletes the messages with the given Message-IDs for everyone
+/// on request of `from_id` received in the `Chat-Delete` header.
+///
+/// Only messages sent by `from_id` are deleted.
+pub(crate) async fn receive_delete_request(
+    context: &Context,
+    rfc724_mids: &[String],
+    from_id: ContactId,
+    encrypted: bool,
+) -> Result<()> {
+    for rfc724_mid in rfc724_mids {
+        let Some((msg_id, _)) = rfc724_mid_exists(context, rfc724_mid).await? else {
+            info!(
+                context,
+                "Ignoring delete request for unknown message {rfc724_mid}."
+            );
+            continue;
+        };
+        let msg = Message::load_from_db(context, msg_id).await?;
+        if msg.from_id != from_id || msg.is_info() {
+            warn!(
+                context,
+                "Ignoring delete request for message {msg_id} not sent by {from_id}."
+            );
+            continue;
+        }
+        if !encrypted && msg.get_showpadlock() {
+            warn!(
+                context,
+                "Ignoring unencrypted delete request for encrypted message {msg_id}."
+            );
+            continue;
+        }
+        retract_msg(context, msg).await?;
+    }
+    Ok(())
+}
+
+/// Deletes the blob `file` unless it is still used by a message.
+async fn delete_unused_blob(context: &Context, file: &str) -> Result<()> {
+    let used = context
+        .sql
+        .exists(
+            "SELECT COUNT(*) FROM msgs WHERE instr(char(10)||param||char(10), ?)>0",
+            (format!("={file}\n"),),
+        )
+        .await?;
+    if !used {
+        if let Err(err) = delete_file(context, Path::new(file)).await {
+            warn!(context, "Cannot delete blob {file}: {err:#}.");
+        }
+    }
+    Ok(())
+}
+
+/// Replaces the message by a tombstone,
+/// removing its text, attachment and everything related to it.
+async fn retract_msg(context: &Context, msg: Message) -> Result<()> {
+    if msg.is_retracted() {
+        return Ok(());
+    }
+    if msg.location_id > 0 {
+        delete_poi_location(context, msg.location_id).await?;
+    }
+    let msg_id = msg.id;
+    let mut param = Params::new();
+    param.set_int(Param::IsRetracted, 1);
+    let param = param.to_string();
+    context
+        .sql
+        .transaction(move |transaction| {
+            transaction.execute(
+                "UPDATE msgs SET type=?, txt='', txt_normalized=NULL, subject='', txt_raw='', \
+                 txt_raw_compressed=0, mime_headers='', param=? WHERE id=?",
+                (Viewtype::Text, param, msg_id),
+            )?;
+            transaction.execute("DELETE FROM msgs_edits WHERE msg_id=?", (msg_id,))?;
+            transaction.execute("DELETE FROM reactions WHERE msg_id=?", (msg_id,))?;
+            transaction.execute("DELETE FROM msgs_status_updates WHERE msg_id=?", (msg_id,))?;
+            transaction.execute("DELETE FROM poll_votes WHERE msg_id=?", (msg_id,))?;
+            Ok(())
+        })
+        .await?;
+    info!(context, "Message {msg_id} was deleted for everyone.");
+
+    if msg.viewtype == Viewtype::Webxdc {
+        context.emit_event(EventType::WebxdcInstanceDeleted { msg_id });
+    }
+    context.emit_msgs_changed(msg.chat_id, msg_id);
+    for key in [Param::File, Param::PreviewFile] {
+        if let Some(file) = msg.param.get(key) {
+            delete_unused_blob(context, file).await?;
+        }
+    }
+    Ok(())
+}
+
 /// Marks requested messages as seen.
 pub async fn markseen_msgs(context: &Context, msg_ids: Vec<MsgId>) -> Result<()> {
     if msg_ids.is_empty() {
@@ -1726,6 +2646,7 @@ pub async fn markseen_msgs(context: &Context, msg_ids: Vec<MsgId>) -> Result<()>
     {
         if curr_state == MessageState::InFresh || curr_state == MessageState::InNoticed {
             update_msg_state(context, id, MessageState::InSeen).await?;
+            chat::update_last_read_msg(context, id).await?;
             info!(context, "Seen message {}.", id);
 
             markseen_on_imap_table(context, &curr_rfc724_mid).await?;
@@ -1933,6 +2854,85 @@ pub async fn estimate_deletion_cnt(
     Ok(cnt)
 }
 
+/// Returns the IDs of the messages quoting `msg_id`, sorted by timestamp.
+///
+/// Unlike [`crate::chat::get_thread_msgs`], only direct replies are returned,
+/// i.e. messages created with [`Message::set_quote`] or received with a quote of `msg_id`.
+pub async fn get_replies(context: &Context, msg_id: MsgId) -> Result<Vec<MsgId>> {
+    let list = context
+        .sql
+        .query_map(
+            "SELECT id
+               FROM msgs
+              WHERE quoted_msg_id=?
+                AND chat_id!=?
+                AND hidden=0
+              ORDER BY timestamp, id;",
+            (msg_id, DC_CHAT_ID_TRASH),
+            |row| row.get::<_, MsgId>(0),
+            |ids| Ok(ids.flatten().collect()),
+        )
+        .await?;
+    Ok(list)
+}
+
+/// Links the message `msg_id` quoting the message with `in_reply_to` Message-ID
+/// to the quoted message if it exists in the database.
+pub(crate) async fn set_quoted_msg_id(
+    context: &Context,
+    msg_id: MsgId,
+    in_reply_to: &str,
+) -> Result<()> {
+    if in_reply_to.is_empty() {
+        return Ok(());
+    }
+    let Some((quoted_msg_id, _)) = rfc724_mid_exists(context, in_reply_to).await? else {
+        return Ok(());
+    };
+    context
+        .sql
+        .execute(
+            "UPDATE msgs SET quoted_msg_id=? WHERE id=?",
+            (quoted_msg_id, msg_id),
+        )
+        .await?;
+    Ok(())
+}
+
+/// Links the messages quoting the message with `rfc724_mid` Message-ID
+/// which were received before it to the quoted message.
+pub(crate) async fn link_early_replies(context: &Context, rfc724_mid: &str) -> Result<()> {
+    let Some((quoted_msg_id, _)) = rfc724_mid_exists(context, rfc724_mid).await? else {
+        return Ok(());
+    };
+    // Received messages store the `In-Reply-To` header as is, i.e. with angle brackets.
+    let reply_ids = context
+        .sql
+        .query_map(
+            "SELECT id FROM msgs
+             WHERE mime_in_reply_to IN (?, ?) AND quoted_msg_id=0 AND id!=?",
+            (rfc724_mid, format!("<{rfc724_mid}>"), quoted_msg_id),
+            |row| row.get::<_, MsgId>(0),
+            |ids| ids.collect::<Result<Vec<_>, _>>().map_err(Into::into),
+        )
+        .await?;
+    for reply_id in reply_ids {
+        let Some(reply) = Message::load_from_db_optional(context, reply_id).await? else {
+            continue;
+        };
+        if reply.param.exists(Param::Quote) && !reply.is_forwarded() {
+            context
+                .sql
+                .execute(
+                    "UPDATE msgs SET quoted_msg_id=? WHERE id=?",
+                    (quoted_msg_id, reply_id),
+                )
+                .await?;
+        }
+    }
+    Ok(())
+}
+
 /// See [`rfc724_mid_exists_ex()`].
 pub(crate) async fn rfc724_mid_exists(
     context: &Context,
@@ -2050,7 +3050,8 @@ pub enum Viewtype {
     /// and retrieved via dc_msg_set_file(), dc_msg_set_dimension().
     Image = 20,
 
-    /// Animated GIF message.
+    /// Animated GIF or WebP message.
+    /// Animated images are detected and sent as is, without recoding.
     /// File, width and height are set via dc_msg_set_file(), dc_msg_set_dimension()
     /// and retrieved via dc_msg_get_file(), dc_msg_get_width(), dc_msg_get_height().
     Gif = 21,
@@ -2125,8 +3126,24 @@ pub(crate) fn normalize_text(text: &str) -> Option<String> {
     Some(text.to_lowercase()).filter(|t| t != text)
 }
 
+/// Maximum length of the alternative text in characters.
+const ALT_TEXT_MAX_CHARS: usize = 1000;
+
+/// Collapses whitespace in the alternative text into single spaces
+/// so it fits into a header and truncates it to [`ALT_TEXT_MAX_CHARS`].
+pub(crate) fn normalize_alt_text(text: &str) -> String {
+    text.split_whitespace()
+        .collect::<Vec<_>>()
+        .join(" ")
+        .chars()
+        .take(ALT_TEXT_MAX_CHARS)
+        .collect()
+}
+
 #[cfg(test)]
 mod tests {
+    use std::time::Duration;
+
     use num_traits::FromPrimitive;
 
     use super::*;
@@ -2135,10 +3152,55 @@ mod tests {
     };
     use crate::chatlist::Chatlist;
     use crate::config::Config;
+    use crate::key::load_self_public_key;
     use crate::reaction::send_reaction;
     use crate::receive_imf::receive_imf;
+    use crate::stock_str;
     use crate::test_utils as test;
     use crate::test_utils::{TestContext, TestContextManager};
+    use crate::tools::SystemTime;
+
+    #[tokio::test(flavor = "multi_thread", worker_threads = 2)]
+    async fn test_verify_mime() -> Result<()> {
+        let mut tcm = TestContextManager::new();
+        let alice = &tcm.alice().await;
+        let bob = &tcm.bob().await;
+        alice.set_config_bool(Config::SaveMimeHeaders, true).await?;
+        bob.set_config_bool(Config::SaveMimeHeaders, true).await?;
+
+        let chat_alice = alice.create_chat(bob).await;
+        let sent = alice.send_text(chat_alice.id, "hi!").await;
+        let msg = bob.recv_msg(&sent).await;
+        let report = verify_mime(bob, msg.id).await?;
+        assert!(report.contains("Encrypted: no\n"));
+        assert!(report.contains("Valid signatures: none\n"));
+        assert!(report.contains(&format!("From: {}\n", alice.get_primary_self_addr().await?)));
+
+        let chat_bob = bob.create_chat(alice).await;
+        let sent = bob.send_text(chat_bob.id, "ho!").await;
+        let msg = alice.recv_msg(&sent).await;
+        assert!(msg.get_showpadlock());
+        let report = verify_mime(alice, msg.id).await?;
+        assert!(report.contains("Shown with padlock: yes\n"));
+        assert!(report.contains("Encrypted: yes, decrypted successfully\n"));
+        assert!(report.contains(&format!(
+            "Valid signature: {}\n",
+            DcKey::fingerprint(&load_self_public_key(bob).await?)
+        )));
+        assert!(report.contains("Protected From header: signed\n"));
+
+        let raw = String::from_utf8(get_mime_headers(alice, msg.id).await?)?;
+        assert!(!raw.contains("ho!"));
+        let decrypted = String::from_utf8(get_decrypted_mime(alice, msg.id).await?)?;
+        assert!(decrypted.contains("ho!"));
+
+        // Without saved MIME, there is nothing to verify.
+        bob.set_config_bool(Config::SaveMimeHeaders, false).await?;
+        let sent = alice.send_text(chat_alice.id, "no mime").await;
+        let msg = bob.recv_msg(&sent).await;
+        assert!(verify_mime(bob, msg.id).await.is_err());
+        Ok(())
+    }
 
     #[test]
     fn test_guess_msgtype_from_suffix() {
@@ -2328,6 +3390,45 @@ async fn test_quote() {
         assert_eq!(quoted_msg.get_text(), msg2.quoted_text().unwrap());
     }
 
+    #[tokio::test(flavor = "multi_thread", worker_threads = 2)]
+    async fn test_get_replies() -> Result<()> {
+        let mut tcm = TestContextManager::new();
+        let alice = &tcm.alice().await;
+        let bob = &tcm.bob().await;
+
+        let alice_chat = alice.create_chat(bob).await;
+        let sent_question = alice.send_text(alice_chat.id, "Question?").await;
+        let question = Message::load_from_db(alice, sent_question.sender_msg_id).await?;
+        let bob_question = bob.recv_msg(&sent_question).await;
+        bob_question.chat_id.accept(bob).await?;
+
+        // Not quoting messages are no replies even though they have `In-Reply-To`.
+        let sent = alice.send_text(alice_chat.id, "Anyone?").await;
+        bob.recv_msg(&sent).await;
+
+        let mut reply = Message::new(Viewtype::Text);
+        reply.set_text("Answer!".to_string());
+        reply.set_quote(bob, Some(&bob_question)).await?;
+        let reply_id = chat::send_msg(bob, bob_question.chat_id, &mut reply).await?;
+        assert_eq!(bob_question.get_replies(bob).await?, vec![reply_id]);
+
+        let sent_reply = bob.pop_sent_msg().await;
+        let alice_reply = alice.recv_msg(&sent_reply).await;
+        assert_eq!(get_replies(alice, question.id).await?, vec![alice_reply.id]);
+        assert!(get_replies(alice, alice_reply.id).await?.is_empty());
+
+        // A reply arriving before the quoted message is linked once the quoted message arrives.
+        let alice2 = &tcm.alice().await;
+        let alice2_reply = alice2.recv_msg(&sent_reply).await;
+        let alice2_question = alice2.recv_msg(&sent_question).await;
+        assert_eq!(
+            get_replies(alice2, alice2_question.id).await?,
+            vec![alice2_reply.id]
+        );
+
+        Ok(())
+    }
+
     #[tokio::test(flavor = "multi_thread", worker_threads = 2)]
     async fn test_unencrypted_quote_encrypted_message() -> Result<()> {
         let mut tcm = TestContextManager::new();
@@ -2399,6 +3500,105 @@ async fn test_get_chat_id() {
         assert_eq!(msg.get_text(), "hello".to_string());
     }
 
+    #[tokio::test(flavor = "multi_thread", worker_threads = 2)]
+    async fn test_view_once() -> Result<()> {
+        let mut tcm = TestContextManager::new();
+        let alice = &tcm.alice().await;
+        let bob = &tcm.bob().await;
+        let chat = alice.create_chat(bob).await;
+
+        let mut msg = Message::new(Viewtype::Text);
+        msg.set_text("Hi".to_string());
+        msg.set_view_once(true);
+        assert!(chat::send_msg(alice, chat.id, &mut msg).await.is_err());
+
+        let file = alice.get_blobdir().join("photo.png");
+        tokio::fs::write(&file, include_bytes!("../test-data/image/logo.png")).await?;
+        let mut msg = Message::new(Viewtype::Image);
+        msg.set_file(file.to_str().unwrap(), None);
+        msg.set_view_once(true);
+        let sent = alice.send_msg(chat.id, &mut msg).await;
+        assert!(sent.payload().contains("Chat-View-Once: 1"));
+
+        // The sender keeps a placeholder only.
+        let alice_msg = Message::load_from_db(alice, sent.sender_msg_id).await?;
+        assert!(alice_msg.is_view_once());
+        assert_eq!(alice_msg.get_viewtype(), Viewtype::Image);
+        assert_eq!(alice_msg.get_file(alice), None);
+
+        let msg = bob.recv_msg(&sent).await;
+        assert!(msg.is_view_once());
+        let path = msg.get_file(bob).unwrap();
+        assert!(path.exists());
+        let self_chat = bob.get_self_chat().await;
+        assert!(chat::forward_msgs(bob, &[msg.id], self_chat.id)
+            .await
+            .is_err());
+        let eml = bob.get_blobdir().join("saved.eml");
+        assert!(crate::imex::save_as_eml(bob, msg.id, &eml, true)
+            .await
+            .is_err());
+
+        bob.sql
+            .execute(
+                "INSERT INTO imap (rfc724_mid, folder, uid, uidvalidity, target)
+                 VALUES (?, 'INBOX', 1, 1, 'INBOX')",
+                (&msg.rfc724_mid,),
+            )
+            .await?;
+        msg.id.mark_view_once_viewed(bob).await?;
+        let msg = Message::load_from_db(bob, msg.id).await?;
+        assert!(msg.is_view_once());
+        assert_eq!(msg.get_file(bob), None);
+        assert!(!path.exists());
+        assert!(get_mime_headers(bob, msg.id).await?.is_empty());
+        // The server copy is deleted.
+        let target: String = bob
+            .sql
+            .query_get_value(
+                "SELECT target FROM imap WHERE rfc724_mid=?",
+                (&msg.rfc724_mid,),
+            )
+            .await?
+            .unwrap();
+        assert_eq!(target, "");
+
+        Ok(())
+    }
+
+    #[tokio::test(flavor = "multi_thread", worker_threads = 2)]
+    async fn test_alt_text() -> Result<()> {
+        let mut tcm = TestContextManager::new();
+        let alice = &tcm.alice().await;
+        let bob = &tcm.bob().await;
+        let chat = alice.create_chat(bob).await;
+
+        let file = alice.get_blobdir().join("IMG_20240101_my_secret_trip.png");
+        tokio::fs::write(&file, include_bytes!("../test-data/image/logo.png")).await?;
+        let mut msg = Message::new(Viewtype::Image);
+        msg.set_file(file.to_str().unwrap(), None);
+        msg.set_alt_text(Some("  Delta Chat logo,\n  a rotated square  ".to_string()));
+        assert_eq!(
+            msg.get_alt_text(),
+            Some("Delta Chat logo, a rotated square".to_string())
+        );
+        let sent = alice.send_msg(chat.id, &mut msg).await;
+        assert!(!sent.payload().contains("my_secret_trip"));
+
+        let msg = bob.recv_msg(&sent).await;
+        assert_eq!(msg.get_viewtype(), Viewtype::Image);
+        assert!(msg.get_filename().unwrap().starts_with("image_"));
+        assert_eq!(
+            msg.get_alt_text(),
+            Some("Delta Chat logo, a rotated square".to_string())
+        );
+
+        let mut msg = Message::new(Viewtype::Text);
+        msg.set_alt_text(Some(" \n ".to_string()));
+        assert_eq!(msg.get_alt_text(), None);
+        Ok(())
+    }
+
     #[tokio::test(flavor = "multi_thread", worker_threads = 2)]
     async fn test_set_override_sender_name() {
         // send message with overridden sender name
@@ -2631,6 +3831,82 @@ async fn test_is_bot() -> Result<()> {
         Ok(())
     }
 
+    #[tokio::test(flavor = "multi_thread", worker_threads = 2)]
+    async fn test_compressed_rawtext() -> Result<()> {
+        let alice = TestContext::new_alice().await;
+        let text = "Lorem ipsum dolor sit amet.\n".repeat(500);
+        let raw = format!(
+            "From: Bob <bob@example.com>\n\
+             To: alice@example.org\n\
+             Subject: Long\n\
+             Message-ID: <123@example.com>\n\
+             Date: Fri, 29 Jan 2021 21:37:55 +0000\n\
+             \n\
+             {text}"
+        );
+        receive_imf(&alice, raw.as_bytes(), false).await?;
+        let msg = alice.get_last_msg().await;
+        let rawtext = msg.id.rawtext(&alice).await?;
+        assert!(rawtext.starts_with("Long\n\nLorem ipsum dolor sit amet.\n"));
+        assert!(rawtext.len() > text.len() - 10);
+        let compressed_len: usize = alice
+            .sql
+            .query_get_value(
+                "SELECT length(txt_raw) FROM msgs WHERE id=? AND txt_raw_compressed=1",
+                (msg.id,),
+            )
+            .await?
+            .unwrap();
+        assert!(compressed_len < rawtext.len() / 10);
+
+        // Texts stored uncompressed by previous versions are compressed during housekeeping.
+        alice
+            .sql
+            .execute(
+                "UPDATE msgs SET txt_raw=?, txt_raw_compressed=0 WHERE id=?",
+                (&rawtext, msg.id),
+            )
+            .await?;
+        assert_eq!(msg.id.rawtext(&alice).await?, rawtext);
+        compress_stored_texts(&alice).await?;
+        assert!(
+            alice
+                .sql
+                .exists(
+                    "SELECT COUNT(*) FROM msgs WHERE id=? AND txt_raw_compressed=1",
+                    (msg.id,),
+                )
+                .await?
+        );
+        assert_eq!(msg.id.rawtext(&alice).await?, rawtext);
+
+        // Short texts are not compressed.
+        receive_imf(
+            &alice,
+            b"From: Bob <bob@example.com>\n\
+                    To: alice@example.org\n\
+                    Subject: Short\n\
+                    Message-ID: <456@example.com>\n\
+                    Date: Fri, 29 Jan 2021 21:37:55 +0000\n\
+                    \n\
+                    hello\n",
+            false,
+        )
+        .await?;
+        let msg = alice.get_last_msg().await;
+        assert!(msg.id.rawtext(&alice).await?.starts_with("Short\n\nhello"));
+        assert!(
+            alice
+                .sql
+                .exists(
+                    "SELECT COUNT(*) FROM msgs WHERE id=? AND txt_raw_compressed=0",
+                    (msg.id,),
+                )
+                .await?
+        );
+        Ok(())
+    }
+
     #[test]
     fn test_viewtype_derive_display_works_as_expected() {
         assert_eq!(format!("{}", Viewtype::Audio), "Audio");
@@ -2746,4 +4022,193 @@ async fn test_delete_msgs_offline() -> Result<()> {
 
         Ok(())
     }
+
+    #[tokio::test(flavor = "multi_thread", worker_threads = 2)]
+    async fn test_get_security_info() -> Result<()> {
+        let mut tcm = TestContextManager::new();
+        let alice = &tcm.alice().await;
+        let bob = &tcm.bob().await;
+        let alice_fingerprint = DcKey::fingerprint(&load_self_public_key(alice).await?);
+
+        // Unencrypted message, Alice has no key of Bob yet.
+        let bob_chat_id = bob.create_chat(alice).await.id;
+        let sent = bob.send_text(bob_chat_id, "Hi").await;
+        let msg = alice.recv_msg(&sent).await;
+        assert_eq!(
+            get_security_info(alice, msg.id).await?,
+            SecurityInfo::default()
+        );
+        msg.chat_id.accept(alice).await?;
+
+        let sent = alice.send_text(msg.chat_id, "Encrypted").await;
+        let info = get_security_info(alice, sent.sender_msg_id).await?;
+        assert!(info.encrypted);
+        assert_eq!(info.signing_fingerprint, Some(alice_fingerprint.clone()));
+        // The chat is not protected, so the key is not verified.
+        assert!(!info.verified);
+
+        let msg = bob.recv_msg(&sent).await;
+        let info = get_security_info(bob, msg.id).await?;
+        assert!(info.encrypted);
+        assert_eq!(info.signing_fingerprint, Some(alice_fingerprint));
+        assert!(!info.verified);
+        assert!(!info.gossiped);
+
+        Ok(())
+    }
+
+    #[tokio::test(flavor = "multi_thread", worker_threads = 2)]
+    async fn test_get_read_receipts() -> Result<()> {
+        let mut tcm = TestContextManager::new();
+        let alice = &tcm.alice().await;
+        let bob = &tcm.bob().await;
+        let fiona = &tcm.fiona().await;
+
+        let chat_id = alice
+            .create_group_with_members(ProtectionStatus::Unprotected, "Group", &[bob, fiona])
+            .await;
+        let msg_id = alice.send_text(chat_id, "Hi").await.sender_msg_id;
+        let bob_id = alice.add_or_lookup_contact(bob).await.id;
+        let fiona_id = alice.add_or_lookup_contact(fiona).await.id;
+        assert!(get_read_receipts(alice, msg_id).await?.is_empty());
+
+        // Self and non-members are ignored.
+        let addrs = [
+            "bob@example.net",
+            "alice@example.org",
+            "unknown@example.com",
+        ];
+        save_delivery(alice, msg_id, addrs.map(ToString::to_string).to_vec()).await?;
+        alice
+            .sql
+            .execute(
+                "INSERT INTO msgs_mdns (msg_id, contact_id, timestamp_sent) VALUES (?, ?, ?)",
+                (msg_id, bob_id, time() + 10),
+            )
+            .await?;
+        save_delivery(alice, msg_id, vec!["fiona@example.net".to_string()]).await?;
+
+        let receipts = get_read_receipts(alice, msg_id).await?;
+        assert_eq!(receipts.len(), 2);
+        assert_eq!(receipts[0].contact_id, bob_id);
+        assert!(receipts[0].delivered_timestamp.is_some());
+        assert!(receipts[0].read_timestamp > receipts[0].delivered_timestamp);
+        assert_eq!(receipts[1].contact_id, fiona_id);
+        assert!(receipts[1].delivered_timestamp.is_some());
+        assert_eq!(receipts[1].read_timestamp, None);
+
+        // Deliveries are not recorded for 1:1 chats.
+        let chat_id = alice.create_chat(bob).await.id;
+        let msg_id = alice.send_text(chat_id, "Hi").await.sender_msg_id;
+        save_delivery(alice, msg_id, vec!["bob@example.net".to_string()]).await?;
+        assert!(get_read_receipts(alice, msg_id).await?.is_empty());
+
+        Ok(())
+    }
+
+    #[tokio::test(flavor = "multi_thread", worker_threads = 2)]
+    async fn test_get_render_hints() -> Result<()> {
+        let alice = TestContext::new_alice().await;
+        let bob = TestContext::new_bob().await;
+        let chat = alice.create_chat(&bob).await;
+        let sent = alice.send_text(chat.id, "hi").await;
+        let msg = bob.recv_msg(&sent).await;
+
+        let hints = msg.get_render_hints(&bob).await?;
+        assert_eq!(hints, RenderHints::default());
+
+        crate::reaction::send_reaction(&bob, msg.id, "👍").await?;
+        let hints = msg.get_render_hints(&bob).await?;
+        assert_eq!(hints.reactions, vec![("👍".to_string(), 1)]);
+        assert!(!hints.is_info);
+
+        Ok(())
+    }
+
+    #[tokio::test(flavor = "multi_thread", worker_threads = 2)]
+    async fn test_delete_for_all() -> Result<()> {
+        let mut tcm = TestContextManager::new();
+        let alice = &tcm.alice().await;
+        let bob = &tcm.bob().await;
+
+        let alice_chat = alice.create_chat(bob).await;
+        let sent = alice.send_text(alice_chat.id, "Oops").await;
+        let bob_msg = bob.recv_msg(&sent).await;
+
+        // Only own messages can be deleted for everyone.
+        assert!(delete_for_all(bob, &[bob_msg.id]).await.is_err());
+
+        delete_for_all(alice, &[sent.sender_msg_id]).await?;
+        let alice_msg = Message::load_from_db(alice, sent.sender_msg_id).await?;
+        assert!(alice_msg.is_retracted());
+        assert_eq!(alice_msg.text, "");
+
+        bob.recv_msg_trash(&alice.pop_sent_msg().await).await;
+        let bob_msg = Message::load_from_db(bob, bob_msg.id).await?;
+        assert!(bob_msg.is_retracted());
+        assert_eq!(bob_msg.text, "");
+        assert_eq!(
+            bob_msg.get_summary(bob, None).await?.text,
+            stock_str::msg_retracted(bob).await
+        );
+
+        // Attachments are deleted.
+        let mut msg = Message::new(Viewtype::File);
+        msg.set_file_from_bytes(alice, "file.txt", b"Secret", None)
+            .await?;
+        let sent = alice.send_msg(alice_chat.id, &mut msg).await;
+        let alice_file = msg.get_file(alice).unwrap();
+        let bob_msg = bob.recv_msg(&sent).await;
+        let bob_file = bob_msg.get_file(bob).unwrap();
+        delete_for_all(alice, &[sent.sender_msg_id]).await?;
+        assert!(!alice_file.exists());
+        bob.recv_msg_trash(&alice.pop_sent_msg().await).await;
+        assert!(!bob_file.exists());
+
+        // Old messages cannot be deleted for everyone.
+        let sent = alice.send_text(alice_chat.id, "Old news").await;
+        SystemTime::shift(Duration::from_secs(2 * 86400));
+        assert!(delete_for_all(alice, &[sent.sender_msg_id]).await.is_err());
+        alice
+            .set_config(Config::DeleteForAllWindow, Some("0"))
+            .await?;
+        delete_for_all(alice, &[sent.sender_msg_id]).await?;
+        Ok(())
+    }
+
+    #[tokio::test(flavor = "multi_thread", worker_threads = 2)]
+    async fn test_message_info_json() -> Result<()> {
+        let mut tcm = TestContextManager::new();
+        let alice = &tcm.alice().await;
+        let bob = &tcm.bob().await;
+        let chat_id = alice.create_chat(bob).await.id;
+        let sent = alice.send_text(chat_id, "Hello").await;
+        let msg = bob.recv_msg(&sent).await;
+
+        let info = MessageInfo::from(&msg);
+        let value = serde_json::to_value(&info)?;
+        assert_eq!(
+            value,
+            serde_json::json!({
+                "id": msg.id,
+                "chat_id": msg.chat_id,
+                "from_id": msg.from_id,
+                "viewtype": "Text",
+                "state": "InFresh",
+                "text": "Hello",
+                "subject": msg.subject,
+                "timestamp": msg.get_timestamp(),
+                "received_timestamp": msg.get_received_timestamp(),
+                "sort_timestamp": msg.get_sort_timestamp(),
+                "is_info": false,
+                "is_forwarded": false,
+                "is_encrypted": true,
+                "file_name": null,
+                "file_mime": null,
+                "error": null,
+            })
+        );
+        assert_eq!(serde_json::from_value::<MessageInfo>(value)?, info);
+        Ok(())
+    }
 }
@@ -11,7 +11,8 @@
 use tokio::fs;
 
 use crate::blob::BlobObject;
-use crate::chat::{self, Chat};
+use crate::bot_commands::render_bot_commands_header;
+use crate::chat::{self, Chat, EncryptionPolicy};
 use crate::config::Config;
 use crate::constants::{Chattype, DC_FROM_HANDSHAKE};
 use crate::contact::{Contact, ContactId, Origin};
@@ -20,11 +21,13 @@
 use crate::ephemeral::Timer as EphemeralTimer;
 use crate::headerdef::HeaderDef;
 use crate::html::new_html_mimepart;
+use crate::mention::render_mentions_header;
 use crate::message::{self, Message, MsgId, Viewtype};
 use crate::mimeparser::SystemMessage;
 use crate::param::Param;
 use crate::peer_channels::create_iroh_header;
 use crate::peerstate::Peerstate;
+use crate::poll::render_poll_header;
 use crate::simplify::escape_message_footer_marks;
 use crate::stock_str;
 use crate::tools::IsNoneOrEmpty;
@@ -32,7 +35,7 @@
     create_outgoing_rfc724_mid, create_smeared_timestamp, remove_subject_prefix, time,
 };
 use crate::webxdc::StatusUpdateSerial;
-use crate::{location, peer_channels};
+use crate::{location, peer_channels, wkd};
 
 // attachments of 25 mb brutto should work on the majority of providers
 // (brutto examples: web.de=50, 1&1=40, t-online.de=32, gmail=25, posteo=50, yahoo=25, all-inkl=100).
@@ -101,6 +104,12 @@ pub struct RenderedEmail {
     pub message: String,
     // pub envelope: Envelope,
     pub is_encrypted: bool,
+
+    /// True if the message is not encrypted, but has a PGP/MIME signature.
+    pub is_clearsigned: bool,
+
+    /// True if the message is post-quantum hybrid-encrypted.
+    pub is_pq_hybrid: bool,
     pub is_gossiped: bool,
     pub last_added_location_id: Option<u32>,
 
@@ -278,7 +287,11 @@ async fn peerstates_for_recipients(
             .iter()
             .filter(|(_, addr)| addr != &self_addr)
         {
-            res.push((Peerstate::from_addr(context, addr).await?, addr.clone()));
+            let peerstate = Peerstate::from_addr(context, addr).await?;
+            if peerstate.is_none() {
+                wkd::spawn_lookup(context, addr).await?;
+            }
+            res.push((peerstate, addr.clone()));
         }
 
         Ok(res)
@@ -291,6 +304,7 @@ fn is_e2ee_guaranteed(&self) -> bool {
                     .get_bool(Param::ForcePlaintext)
                     .unwrap_or_default()
                     && (chat.is_protected()
+                        || chat.get_encryption_policy() == EncryptionPolicy::Required
                         || msg.param.get_bool(Param::GuaranteeE2ee).unwrap_or_default())
             }
             Loaded::Mdn { .. } => false,
@@ -318,6 +332,22 @@ fn should_force_plaintext(&self) -> bool {
                     .get_bool(Param::ForcePlaintext)
                     .unwrap_or_default()
                     || chat.typ == Chattype::Broadcast
+                    || (chat.get_encryption_policy() == EncryptionPolicy::Disabled
+                        && !chat.is_protected())
+                    || self.should_clearsign()
+            }
+            Loaded::Mdn { .. } => false,
+        }
+    }
+
+    /// Returns true if the message should be sent unencrypted,
+    /// but with a PGP/MIME signature, regardless of `Config::SignUnencrypted`.
+    fn should_clearsign(&self) -> bool {
+        match &self.loaded {
+            Loaded::Message { chat, msg } => {
+                !chat.is_protected()
+                    && (msg.param.get_bool(Param::Clearsigned).unwrap_or_default()
+                        || chat.get_encryption_policy() == EncryptionPolicy::Clearsigned)
             }
             Loaded::Mdn { .. } => false,
         }
@@ -581,6 +611,12 @@ pub async fn render(mut self, context: &Context) -> Result<RenderedEmail> {
             ));
         }
 
+        if let Loaded::Message { .. } = self.loaded {
+            if let Some(bot_commands) = render_bot_commands_header(context).await? {
+                headers.push(Header::new("Chat-Bot-Commands".into(), bot_commands));
+            }
+        }
+
         if let Loaded::Message { chat, .. } = &self.loaded {
             if chat.typ == Chattype::Broadcast {
                 let encoded_chat_name = encode_words(&chat.name);
@@ -630,10 +666,36 @@ pub async fn render(mut self, context: &Context) -> Result<RenderedEmail> {
         }
 
         let mut is_gossiped = false;
+        let mut is_clearsigned = false;
+        #[cfg_attr(not(feature = "pq-hybrid"), allow(unused_mut))]
+        let mut is_pq_hybrid = false;
 
         let peerstates = self.peerstates_for_recipients(context).await?;
-        let is_encrypted = !self.should_force_plaintext()
-            && encrypt_helper.should_encrypt(context, e2ee_guaranteed, &peerstates)?;
+        let encryption_required = match &self.loaded {
+            Loaded::Message { chat, .. } => {
+                chat.get_encryption_policy() == EncryptionPolicy::Required
+            }
+            Loaded::Mdn { .. } => false,
+        };
+        let is_encrypted = !self.should_force_plaintext() && {
+            let should_encrypt =
+                encrypt_helper.should_encrypt(context, e2ee_guaranteed, &peerstates);
+            if encryption_required {
+                should_encrypt
+                    .context("Chat requires encryption, but not all recipients' keys are known")?
+            } else {
+                should_encrypt?
+            }
+        };
+        #[cfg(feature = "pq-hybrid")]
+        if is_encrypted {
+            if let Loaded::Message { .. } = self.loaded {
+                headers.push(Header::new(
+                    "Chat-Pq-Key".into(),
+                    crate::pq::render_pq_key_header(context).await?,
+                ));
+            }
+        }
         let is_securejoin_message = if let Loaded::Message { msg, .. } = &self.loaded {
             msg.param.get_cmd() == SystemMessage::SecurejoinMessage
         } else {
@@ -721,7 +783,10 @@ pub async fn render(mut self, context: &Context) -> Result<RenderedEmail> {
             if header_name == "message-id" {
                 unprotected_headers.push(header.clone());
                 hidden_headers.push(header);
-            } else if header_name == "chat-user-avatar" {
+            } else if header_name == "chat-user-avatar"
+                || header_name == "chat-bot-commands"
+                || header_name == "chat-pq-key"
+            {
                 hidden_headers.push(header);
             } else if header_name == "autocrypt" {
                 unprotected_headers.push(header.clone());
@@ -857,6 +922,10 @@ pub async fn render(mut self, context: &Context) -> Result<RenderedEmail> {
             let encrypted = encrypt_helper
                 .encrypt(context, verified, message, peerstates, compress)
                 .await?;
+            #[cfg(feature = "pq-hybrid")]
+            {
+                is_pq_hybrid = crate::pq::is_wrapped(&encrypted);
+            }
 
             outer_message
                 .child(
@@ -901,7 +970,10 @@ pub async fn render(mut self, context: &Context) -> Result<RenderedEmail> {
                 .iter()
                 .fold(message, |message, header| message.header(header.clone()));
 
-            if skip_autocrypt || !context.get_config_bool(Config::SignUnencrypted).await? {
+            if skip_autocrypt
+                || !(self.should_clearsign()
+                    || context.get_config_bool(Config::SignUnencrypted).await?)
+            {
                 let protected: HashSet<Header> = HashSet::from_iter(protected_headers.into_iter());
                 for h in unprotected_headers.split_off(0) {
                     if !protected.contains(&h) {
@@ -912,6 +984,7 @@ pub async fn render(mut self, context: &Context) -> Result<RenderedEmail> {
             } else {
                 let message = message.header(get_content_type_directives_header());
                 let (payload, signature) = encrypt_helper.sign(context, message).await?;
+                is_clearsigned = true;
                 PartBuilder::new()
                     .header((
                         "Content-Type",
@@ -955,6 +1028,8 @@ pub async fn render(mut self, context: &Context) -> Result<RenderedEmail> {
             message: outer_message.build().as_string(),
             // envelope: Envelope::new,
             is_encrypted,
+            is_clearsigned,
+            is_pq_hybrid,
             is_gossiped,
             last_added_location_id,
             sync_ids_to_delete: self.sync_ids_to_delete,
@@ -1207,6 +1282,18 @@ async fn render_message(
                     "protection-disabled".to_string(),
                 ));
             }
+            SystemMessage::MessagePinned | SystemMessage::MessageUnpinned => {
+                let content = if command == SystemMessage::MessagePinned {
+                    "message-pinned"
+                } else {
+                    "message-unpinned"
+                };
+                headers.push(Header::new("Chat-Content".into(), content.into()));
+                headers.push(Header::new(
+                    "Chat-Pinned-Message".into(),
+                    render_rfc724_mid(msg.param.get(Param::Arg).unwrap_or_default()),
+                ));
+            }
             SystemMessage::IrohNodeAddr => {
                 headers.push(Header::new(
                     HeaderDef::IrohNodeAddr.get_headername().to_string(),
@@ -1246,6 +1333,42 @@ async fn render_message(
             ));
         }
 
+        if let Some(poll) = render_poll_header(&msg) {
+            headers.push(Header::new("Chat-Poll".into(), poll));
+        } else if let Some(closed) = msg.param.get_bool(Param::PollClosed) {
+            let name = if closed {
+                "Chat-Poll-Close"
+            } else {
+                "Chat-Poll-Reopen"
+            };
+            headers.push(Header::new(name.into(), "1".into()));
+        }
+        if let Some(vote) = msg.param.get(Param::PollVote) {
+            headers.push(Header::new("Chat-Poll-Vote".into(), vote.into()));
+        }
+        if let Some(edit_for) = msg.param.get(Param::TextEditFor) {
+            headers.push(Header::new("Chat-Edit".into(), render_rfc724_mid(edit_for)));
+        }
+        if let Some(delete_for) = msg.param.get(Param::DeleteRequestFor) {
+            headers.push(Header::new(
+                "Chat-Delete".into(),
+                render_rfc724_mid_list(delete_for),
+            ));
+        }
+        if let Some(mentions) = render_mentions_header(context, &msg).await? {
+            headers.push(Header::new("Chat-Mentions".into(), mentions));
+        }
+        if msg.is_view_once() {
+            headers.push(Header::new("Chat-View-Once".into(), "1".into()));
+        }
+
+        if let Some(thread_root) = &msg.thread_root {
+            headers.push(Header::new(
+                "Chat-Thread-Root".into(),
+                render_rfc724_mid(thread_root),
+            ));
+        }
+
         if msg.viewtype == Viewtype::Voice
             || msg.viewtype == Viewtype::Audio
             || msg.viewtype == Viewtype::Video
@@ -1570,11 +1693,14 @@ async fn build_body_file(
     let body = fs::read(blob.to_abs_path()).await?;
     let encoded_body = wrapped_base64_encode(&body);
 
-    let mail = PartBuilder::new()
+    let mut mail = PartBuilder::new()
         .content_type(&mimetype)
         .header(("Content-Disposition", cd_value))
-        .header(("Content-Transfer-Encoding", "base64"))
-        .body(encoded_body);
+        .header(("Content-Transfer-Encoding", "base64"));
+    if let Some(alt_text) = msg.get_alt_text() {
+        mail = mail.header(("Content-Description", maybe_encode_words(&alt_text)));
+    }
+    let mail = mail.body(encoded_body);
 
     Ok((mail, filename_to_send))
 }
@@ -2324,6 +2450,62 @@ async fn test_selfavatar_unencrypted() -> anyhow::Result<()> {
         Ok(())
     }
 
+    #[tokio::test(flavor = "multi_thread", worker_threads = 2)]
+    async fn test_clearsigned() -> Result<()> {
+        let mut tcm = TestContextManager::new();
+        let alice = tcm.alice().await;
+        let bob = tcm.bob().await;
+        let chat_id = tcm.send_recv_accept(&bob, &alice, "hi").await.chat_id;
+
+        // Clearsigned message even though Bob's key is known.
+        let mut msg = Message::new(Viewtype::Text);
+        msg.set_text("signed only".to_string());
+        msg.set_clearsigned(true);
+        let sent = alice.send_msg(chat_id, &mut msg).await;
+        assert!(sent.payload().contains("multipart/signed"));
+        assert!(sent.payload().contains("signed only"));
+        assert!(!sent.payload().contains("-----BEGIN PGP MESSAGE-----"));
+        assert!(alice.get_last_msg().await.is_clearsigned());
+
+        // Bob does not know Alice's key yet. The key attached to the message could be anyone's,
+        // so the signature does not count.
+        let msg = bob.recv_msg(&sent).await;
+        assert_eq!(msg.get_text(), "signed only");
+        assert!(!msg.is_clearsigned());
+        assert!(!msg.get_showpadlock());
+
+        let mut msg = Message::new(Viewtype::Text);
+        msg.set_text("signed only".to_string());
+        msg.set_clearsigned(true);
+        let sent = alice.send_msg(chat_id, &mut msg).await;
+        let msg = bob.recv_msg(&sent).await;
+        assert_eq!(msg.get_text(), "signed only");
+        assert!(msg.is_clearsigned());
+        assert!(!msg.get_showpadlock());
+
+        // Clearsigned chat.
+        chat_id
+            .set_encryption_policy(&alice, EncryptionPolicy::Clearsigned)
+            .await?;
+        let sent = alice.send_text(chat_id, "bot output").await;
+        assert!(sent.payload().contains("multipart/signed"));
+        let msg = bob.recv_msg(&sent).await;
+        assert!(msg.is_clearsigned());
+        assert!(!msg.get_showpadlock());
+
+        // Unsigned messages are not shown as clearsigned.
+        chat_id
+            .set_encryption_policy(&alice, EncryptionPolicy::Disabled)
+            .await?;
+        let sent = alice.send_text(chat_id, "plain").await;
+        assert!(!sent.payload().contains("multipart/signed"));
+        let msg = bob.recv_msg(&sent).await;
+        assert!(!msg.is_clearsigned());
+        assert!(!msg.get_showpadlock());
+
+        Ok(())
+    }
+
     #[tokio::test(flavor = "multi_thread", worker_threads = 2)]
     async fn test_selfavatar_unencrypted_signed() {
         // create chat with bob, set selfavatar
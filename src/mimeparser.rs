@@ -25,22 +25,25 @@
     DecryptionInfo,
 };
 use crate::dehtml::dehtml;
+use crate::disk_space::InsufficientDiskSpace;
 use crate::events::EventType;
 use crate::headerdef::{HeaderDef, HeaderDefMap};
-use crate::key::{self, load_self_secret_keyring, DcKey, Fingerprint, SignedPublicKey};
+use crate::key::{
+    self, load_self_secret_keyring, DcKey, Fingerprint, SignedPublicKey, SignedSecretKey,
+};
 use crate::message::{
-    self, get_vcard_summary, set_msg_failed, update_msg_state, Message, MessageState, MsgId,
-    Viewtype,
+    self, get_vcard_summary, normalize_alt_text, set_msg_failed, update_msg_state, Message,
+    MessageState, MsgId, Viewtype,
 };
 use crate::param::{Param, Params};
 use crate::peerstate::Peerstate;
 use crate::simplify::{simplify, SimplifiedText};
 use crate::sync::SyncItems;
 use crate::tools::{
-    create_smeared_timestamp, get_filemeta, parse_receive_headers, smeared_time, truncate_by_lines,
-    validate_id,
+    create_smeared_timestamp, get_animation_frames, get_filemeta, parse_receive_headers,
+    smeared_time, truncate_by_lines, validate_id,
 };
-use crate::{chatlist_events, location, stock_str, tools};
+use crate::{chatlist_events, location, preview, stock_str, tools};
 
 /// A parsed MIME message.
 ///
@@ -77,12 +80,28 @@ pub(crate) struct MimeMessage {
     pub decryption_info: DecryptionInfo,
     pub decrypting_failed: bool,
 
+    /// True if the message is imported from a file rather than received,
+    /// its side effects such as key updates and group changes are not applied then.
+    pub(crate) imported: bool,
+
     /// Set of valid signature fingerprints if a message is an
     /// Autocrypt encrypted and signed message.
     ///
     /// If a message is not encrypted or the signature is not valid,
     /// this set is empty.
     pub signatures: HashSet<Fingerprint>,
+
+    /// True if the message is not encrypted,
+    /// but has a valid PGP/MIME signature of the sender.
+    pub(crate) signed_cleartext: bool,
+
+    /// Fingerprint of the key which made a valid signature,
+    /// for both encrypted and cleartext messages.
+    pub(crate) signing_fingerprint: Option<Fingerprint>,
+
+    /// True if the message was post-quantum hybrid-encrypted.
+    pub(crate) pq_hybrid: bool,
+
     /// The mail recipient addresses for which gossip headers were applied
     /// and their respective gossiped keys,
     /// regardless of whether they modified any peerstates.
@@ -204,6 +223,12 @@ pub enum SystemMessage {
 
     /// This message contains a users iroh node address.
     IrohNodeAddr = 40,
+
+    /// A message was pinned in the chat.
+    MessagePinned = 50,
+
+    /// A message was unpinned in the chat.
+    MessageUnpinned = 51,
 }
 
 const MIME_AC_SETUP_FILE: &str = "application/autocrypt-setup";
@@ -217,6 +242,44 @@ pub(crate) async fn from_bytes(
         context: &Context,
         body: &[u8],
         partial: Option<u32>,
+    ) -> Result<Self> {
+        Self::from_bytes_ex(context, body, partial, false).await
+    }
+
+    /// Decrypts `mail` and validates its signatures against `public_keyring`.
+    ///
+    /// Returns the decrypted MIME and the valid signatures, `None` if the message is not encrypted,
+    /// and whether post-quantum hybrid encryption was used.
+    #[cfg_attr(not(feature = "pq-hybrid"), allow(unused_variables))]
+    pub(crate) async fn decrypt(
+        context: &Context,
+        mail: &mailparse::ParsedMail<'_>,
+        private_keyring: &[SignedSecretKey],
+        public_keyring: &[SignedPublicKey],
+    ) -> (Result<Option<(Vec<u8>, HashSet<Fingerprint>)>>, bool) {
+        #[cfg(feature = "pq-hybrid")]
+        match crate::pq::try_decrypt(context, mail, private_keyring, public_keyring).await {
+            Ok(None) => {}
+            res => {
+                let pq_hybrid = matches!(res, Ok(Some(_)));
+                return (res, pq_hybrid);
+            }
+        }
+        (
+            tokio::task::block_in_place(|| try_decrypt(mail, private_keyring, public_keyring)),
+            false,
+        )
+    }
+
+    /// Parse a mime message, see [`Self::from_bytes`].
+    ///
+    /// If `imported` is set, the message is imported from a file
+    /// and its Autocrypt and gossip headers and attached keys are not applied.
+    pub(crate) async fn from_bytes_ex(
+        context: &Context,
+        body: &[u8],
+        partial: Option<u32>,
+        imported: bool,
     ) -> Result<Self> {
         let mail = mailparse::parse_mail(body)?;
 
@@ -275,9 +338,11 @@ pub(crate) async fn from_bytes(
                 for field in &part.headers {
                     let key = field.get_key().to_lowercase();
 
-                    // For now only avatar headers can be hidden.
+                    // For now only avatar and bot command headers can be hidden.
                     if !headers.contains_key(&key)
-                        && (key == "chat-user-avatar" || key == "chat-group-avatar")
+                        && (key == "chat-user-avatar"
+                            || key == "chat-group-avatar"
+                            || key == "chat-bot-commands")
                     {
                         headers.insert(key.to_string(), field.get_value());
                     }
@@ -304,8 +369,10 @@ pub(crate) async fn from_bytes(
         let mut from = from.context("No from in message")?;
         let private_keyring = load_self_secret_keyring(context).await?;
 
+        // Peerstate before it is updated from the Autocrypt header of this message.
+        let known_peerstate = Peerstate::from_addr(context, &from.addr).await?;
         let mut decryption_info =
-            prepare_decryption(context, &mail, &from.addr, timestamp_sent).await?;
+            prepare_decryption(context, &mail, &from.addr, timestamp_sent, imported).await?;
 
         // Memory location for a possible decrypted message.
         let mut mail_raw = Vec::new();
@@ -319,9 +386,9 @@ pub(crate) async fn from_bytes(
             true => key::load_self_public_keyring(context).await?,
             false => keyring_from_peerstate(decryption_info.peerstate.as_ref()),
         };
-        let (mail, mut signatures, encrypted) = match tokio::task::block_in_place(|| {
-            try_decrypt(&mail, &private_keyring, &public_keyring)
-        }) {
+        let (decrypted, pq_hybrid) =
+            Self::decrypt(context, &mail, &private_keyring, &public_keyring).await;
+        let (mail, mut signatures, encrypted) = match decrypted {
             Ok(Some((raw, signatures))) => {
                 mail_raw = raw;
                 let decrypted_mail = mailparse::parse_mail(&mail_raw)?;
@@ -340,9 +407,20 @@ pub(crate) async fn from_bytes(
                 (Err(err), HashSet::new(), false)
             }
         };
+        // Signatures of unencrypted messages are only checked against the keys known
+        // before this message, otherwise anyone could attach their own key
+        // to the Autocrypt header and sign the message with it.
+        let cleartext_keyring = match known_peerstate.is_none() && !incoming {
+            true => key::load_self_public_keyring(context).await?,
+            false => keyring_from_peerstate(known_peerstate.as_ref()),
+        };
         let mail = mail.as_ref().map(|mail| {
-            let (content, signatures_detached) = validate_detached_signature(mail, &public_keyring)
-                .unwrap_or((mail, Default::default()));
+            let keyring = match encrypted {
+                true => &public_keyring,
+                false => &cleartext_keyring,
+            };
+            let (content, signatures_detached) =
+                validate_detached_signature(mail, keyring).unwrap_or((mail, Default::default()));
             signatures.extend(signatures_detached);
             content
         });
@@ -354,10 +432,19 @@ pub(crate) async fn from_bytes(
                 // "3.6 Key Gossip" of <https://autocrypt.org/autocrypt-spec-1.1.0.pdf>
                 // but only if the mail was correctly signed. Probably it's ok to not require
                 // encryption here, but let's follow the standard.
-                let gossip_headers = mail.headers.get_all_values("Autocrypt-Gossip");
+                // Gossip of imported messages is outdated and not applied.
+                let gossip_headers = match imported {
+                    true => Vec::new(),
+                    false => mail.headers.get_all_values("Autocrypt-Gossip"),
+                };
+                let rfc724_mid = mail
+                    .headers
+                    .get_header_value(HeaderDef::MessageId)
+                    .and_then(|msgid| parse_message_id(&msgid).ok());
                 gossiped_keys = update_gossip_peerstates(
                     context,
                     timestamp_sent,
+                    rfc724_mid.as_deref(),
                     &from.addr,
                     &recipients,
                     gossip_headers,
@@ -433,10 +520,12 @@ pub(crate) async fn from_bytes(
                 }
             }
         }
+        let signed_cleartext = !encrypted && !signatures.is_empty();
+        let signing_fingerprint = signatures.iter().next().cloned();
         if !encrypted {
             signatures.clear();
         }
-        if let Some(peerstate) = &mut decryption_info.peerstate {
+        if let Some(peerstate) = decryption_info.peerstate.as_mut().filter(|_| !imported) {
             if peerstate.prefer_encrypt != EncryptPreference::Mutual && !signatures.is_empty() {
                 peerstate.prefer_encrypt = EncryptPreference::Mutual;
                 peerstate.save_to_db(&context.sql).await?;
@@ -454,9 +543,13 @@ pub(crate) async fn from_bytes(
             chat_disposition_notification_to,
             decryption_info,
             decrypting_failed: mail.is_err(),
+            imported,
 
             // only non-empty if it was a valid autocrypt message
             signatures,
+            signed_cleartext,
+            signing_fingerprint,
+            pq_hybrid,
             gossiped_keys,
             is_forwarded: false,
             mdn_reports: Vec::new(),
@@ -560,6 +653,10 @@ fn parse_system_message_headers(&mut self, context: &Context) {
                 self.is_system_message = SystemMessage::ChatProtectionDisabled;
             } else if value == "group-avatar-changed" {
                 self.is_system_message = SystemMessage::GroupImageChanged;
+            } else if value == "message-pinned" {
+                self.is_system_message = SystemMessage::MessagePinned;
+            } else if value == "message-unpinned" {
+                self.is_system_message = SystemMessage::MessageUnpinned;
             }
         } else if self.get_header(HeaderDef::ChatGroupMemberRemoved).is_some() {
             self.is_system_message = SystemMessage::MemberRemovedFromGroup;
@@ -985,8 +1082,10 @@ async fn handle_multiple(
                 if mail.subparts.len() >= 2 {
                     match mail.ctype.params.get("report-type").map(|s| s as &str) {
                         Some("disposition-notification") => {
-                            if let Some(report) = self.process_report(context, mail)? {
+                            if let Some(report) = parse_mdn_report(mail)? {
                                 self.mdn_reports.push(report);
+                            } else {
+                                warn!(context, "Ignoring unknown disposition-notification.");
                             }
 
                             // Add MDN part so we can track it, avoid
@@ -1003,8 +1102,10 @@ async fn handle_multiple(
                         }
                         // Some providers, e.g. Tiscali, forget to set the report-type. So, if it's None, assume that it might be delivery-status
                         Some("delivery-status") | None => {
-                            if let Some(report) = self.process_delivery_status(context, mail)? {
+                            if let Some(report) = parse_delivery_status(mail)? {
                                 self.delivery_report = Some(report);
+                            } else {
+                                info!(context, "Ignoring unknown delivery status notification.");
                             }
 
                             // Add all parts (we need another part, preferably text/plain, to show as an error message)
@@ -1085,6 +1186,17 @@ async fn add_single_part_if_known(
                     is_related,
                 )
                 .await?;
+                if self.parts.len() > old_part_count {
+                    if let (Some(part), Some(alt_text)) = (
+                        self.parts.last_mut(),
+                        mail.headers.get_header_value(HeaderDef::ContentDescription),
+                    ) {
+                        let alt_text = normalize_alt_text(&alt_text);
+                        if !alt_text.is_empty() {
+                            part.param.set(Param::AltText, alt_text);
+                        }
+                    }
+                }
             }
             None => {
                 match mime_type.type_() {
@@ -1235,7 +1347,7 @@ async fn add_single_part_if_known(
     async fn do_add_single_file_part(
         &mut self,
         context: &Context,
-        msg_type: Viewtype,
+        mut msg_type: Viewtype,
         mime_type: Mime,
         raw_mime: &str,
         decoded_data: &[u8],
@@ -1245,7 +1357,12 @@ async fn do_add_single_file_part(
         if decoded_data.is_empty() {
             return Ok(());
         }
-        if let Some(peerstate) = &mut self.decryption_info.peerstate {
+        if let Some(peerstate) = self
+            .decryption_info
+            .peerstate
+            .as_mut()
+            .filter(|_| !self.imported)
+        {
             if peerstate.prefer_encrypt != EncryptPreference::Mutual
                 && mime_type.type_() == mime::APPLICATION
                 && mime_type.subtype().as_str() == "pgp-keys"
@@ -1314,6 +1431,9 @@ async fn do_add_single_file_part(
 
         let blob = match BlobObject::create(context, filename, decoded_data).await {
             Ok(blob) => blob,
+            // Fail early instead of silently dropping the attachment,
+            // so that the message can be fetched again later.
+            Err(err) if err.is::<InsufficientDiskSpace>() => return Err(err),
             Err(err) => {
                 error!(
                     context,
@@ -1329,6 +1449,18 @@ async fn do_add_single_file_part(
                 part.param.set_int(Param::Width, width as i32);
                 part.param.set_int(Param::Height, height as i32);
             }
+            if let Some(frames) = get_animation_frames(decoded_data) {
+                if msg_type == Viewtype::Image {
+                    msg_type = Viewtype::Gif;
+                }
+                part.param.set_int(Param::Frames, frames as i32);
+            }
+        }
+
+        if let Some(preview) =
+            preview::create_preview(context, msg_type, Some(raw_mime), decoded_data).await
+        {
+            part.param.set(Param::PreviewFile, preview);
         }
 
         part.typ = msg_type;
@@ -1406,6 +1538,8 @@ async fn try_set_peer_key_from_file_part(
     fn do_add_single_part(&mut self, mut part: Part) {
         if self.was_encrypted() {
             part.param.set_int(Param::GuaranteeE2ee, 1);
+        } else if self.signed_cleartext {
+            part.param.set_int(Param::Clearsigned, 1);
         }
         self.parts.push(part);
     }
@@ -1513,132 +1647,6 @@ fn merge_headers(
         }
     }
 
-    fn process_report(
-        &self,
-        context: &Context,
-        report: &mailparse::ParsedMail<'_>,
-    ) -> Result<Option<Report>> {
-        // parse as mailheaders
-        let report_body = if let Some(subpart) = report.subparts.get(1) {
-            subpart.get_body_raw()?
-        } else {
-            bail!("Report does not have second MIME part");
-        };
-        let (report_fields, _) = mailparse::parse_headers(&report_body)?;
-
-        // must be present
-        if report_fields
-            .get_header_value(HeaderDef::Disposition)
-            .is_none()
-        {
-            warn!(
-                context,
-                "Ignoring unknown disposition-notification, Message-Id: {:?}.",
-                report_fields.get_header_value(HeaderDef::MessageId)
-            );
-            return Ok(None);
-        };
-
-        let original_message_id = report_fields
-            .get_header_value(HeaderDef::OriginalMessageId)
-            // MS Exchange doesn't add an Original-Message-Id header. Instead, they put
-            // the original message id into the In-Reply-To header:
-            .or_else(|| report.headers.get_header_value(HeaderDef::InReplyTo))
-            .and_then(|v| parse_message_id(&v).ok());
-        let additional_message_ids = report_fields
-            .get_header_value(HeaderDef::AdditionalMessageIds)
-            .map_or_else(Vec::new, |v| {
-                v.split(' ')
-                    .filter_map(|s| parse_message_id(s).ok())
-                    .collect()
-            });
-
-        Ok(Some(Report {
-            original_message_id,
-            additional_message_ids,
-        }))
-    }
-
-    fn process_delivery_status(
-        &self,
-        context: &Context,
-        report: &mailparse::ParsedMail<'_>,
-    ) -> Result<Option<DeliveryReport>> {
-        // Assume failure.
-        let mut failure = true;
-
-        if let Some(status_part) = report.subparts.get(1) {
-            // RFC 3464 defines `message/delivery-status`
-            // RFC 6533 defines `message/global-delivery-status`
-            if status_part.ctype.mimetype != "message/delivery-status"
-                && status_part.ctype.mimetype != "message/global-delivery-status"
-            {
-                warn!(context, "Second part of Delivery Status Notification is not message/delivery-status or message/global-delivery-status, ignoring");
-                return Ok(None);
-            }
-
-            let status_body = status_part.get_body_raw()?;
-
-            // Skip per-message fields.
-            let (_, sz) = mailparse::parse_headers(&status_body)?;
-
-            // Parse first set of per-recipient fields
-            if let Some(status_body) = status_body.get(sz..) {
-                let (status_fields, _) = mailparse::parse_headers(status_body)?;
-                if let Some(action) = status_fields.get_first_value("action") {
-                    if action != "failed" {
-                        info!(context, "DSN with {:?} action", action);
-                        failure = false;
-                    }
-                } else {
-                    warn!(context, "DSN without action");
-                }
-            } else {
-                warn!(context, "DSN without per-recipient fields");
-            }
-        } else {
-            // No message/delivery-status part.
-            return Ok(None);
-        }
-
-        // parse as mailheaders
-        if let Some(original_msg) = report.subparts.get(2).filter(|p| {
-            p.ctype.mimetype.contains("rfc822")
-                || p.ctype.mimetype == "message/global"
-                || p.ctype.mimetype == "message/global-headers"
-        }) {
-            let report_body = original_msg.get_body_raw()?;
-            let (report_fields, _) = mailparse::parse_headers(&report_body)?;
-
-            if let Some(original_message_id) = report_fields
-                .get_header_value(HeaderDef::MessageId)
-                .and_then(|v| parse_message_id(&v).ok())
-            {
-                let mut to_list =
-                    get_all_addresses_from_header(&report.headers, "x-failed-recipients");
-                let to = if to_list.len() == 1 {
-                    Some(to_list.pop().unwrap())
-                } else {
-                    None // We do not know which recipient failed
-                };
-
-                return Ok(Some(DeliveryReport {
-                    rfc724_mid: original_message_id,
-                    failed_recipient: to.map(|s| s.addr),
-                    failure,
-                }));
-            }
-
-            warn!(
-                context,
-                "ignoring unknown ndn-notification, Message-Id: {:?}",
-                report_fields.get_header_value(HeaderDef::MessageId)
-            );
-        }
-
-        Ok(None)
-    }
-
     fn maybe_remove_bad_parts(&mut self) {
         let good_parts = self.parts.iter().filter(|p| !p.dehtml_failed).count();
         if good_parts == 0 {
@@ -1785,11 +1793,13 @@ pub async fn get_parent_timestamp(&self, context: &Context) -> Result<Option<i64
 /// Parses `Autocrypt-Gossip` headers from the email and applies them to peerstates.
 /// Params:
 /// from: The address which sent the message currently being parsed
+/// rfc724_mid: The Message-ID of the message currently being parsed
 ///
 /// Returns the set of mail recipient addresses for which valid gossip headers were found.
 async fn update_gossip_peerstates(
     context: &Context,
     message_time: i64,
+    rfc724_mid: Option<&str>,
     from: &str,
     recipients: &[SingleInfo],
     gossip_headers: Vec<String>,
@@ -1836,7 +1846,7 @@ async fn update_gossip_peerstates(
             peerstate = p;
         };
         peerstate
-            .handle_fingerprint_change(context, message_time)
+            .handle_fingerprint_change(context, message_time, rfc724_mid)
             .await?;
 
         gossiped_keys.insert(header.addr.to_lowercase(), header.public_key);
@@ -1865,6 +1875,115 @@ pub(crate) struct DeliveryReport {
     pub failure: bool,
 }
 
+/// Parses a `multipart/report` with `report-type=disposition-notification`.
+///
+/// Returns `None` if the disposition is unknown.
+pub(crate) fn parse_mdn_report(report: &mailparse::ParsedMail<'_>) -> Result<Option<Report>> {
+    // parse as mailheaders
+    let report_body = if let Some(subpart) = report.subparts.get(1) {
+        subpart.get_body_raw()?
+    } else {
+        bail!("Report does not have second MIME part");
+    };
+    let (report_fields, _) = mailparse::parse_headers(&report_body)?;
+
+    // must be present
+    if report_fields
+        .get_header_value(HeaderDef::Disposition)
+        .is_none()
+    {
+        return Ok(None);
+    };
+
+    let original_message_id = report_fields
+        .get_header_value(HeaderDef::OriginalMessageId)
+        // MS Exchange doesn't add an Original-Message-Id header. Instead, they put
+        // the original message id into the In-Reply-To header:
+        .or_else(|| report.headers.get_header_value(HeaderDef::InReplyTo))
+        .and_then(|v| parse_message_id(&v).ok());
+    let additional_message_ids = report_fields
+        .get_header_value(HeaderDef::AdditionalMessageIds)
+        .map_or_else(Vec::new, |v| {
+            v.split(' ')
+                .filter_map(|s| parse_message_id(s).ok())
+                .collect()
+        });
+
+    Ok(Some(Report {
+        original_message_id,
+        additional_message_ids,
+    }))
+}
+
+/// Parses a `multipart/report` with `report-type=delivery-status`.
+///
+/// Returns `None` if the report is not a delivery status notification
+/// or the original message cannot be determined.
+pub(crate) fn parse_delivery_status(
+    report: &mailparse::ParsedMail<'_>,
+) -> Result<Option<DeliveryReport>> {
+    // Assume failure.
+    let mut failure = true;
+
+    if let Some(status_part) = report.subparts.get(1) {
+        // RFC 3464 defines `message/delivery-status`
+        // RFC 6533 defines `message/global-delivery-status`
+        if status_part.ctype.mimetype != "message/delivery-status"
+            && status_part.ctype.mimetype != "message/global-delivery-status"
+        {
+            return Ok(None);
+        }
+
+        let status_body = status_part.get_body_raw()?;
+
+        // Skip per-message fields.
+        let (_, sz) = mailparse::parse_headers(&status_body)?;
+
+        // Parse first set of per-recipient fields
+        if let Some(status_body) = status_body.get(sz..) {
+            let (status_fields, _) = mailparse::parse_headers(status_body)?;
+            if let Some(action) = status_fields.get_first_value("action") {
+                if action != "failed" {
+                    failure = false;
+                }
+            }
+        }
+    } else {
+        // No message/delivery-status part.
+        return Ok(None);
+    }
+
+    // parse as mailheaders
+    if let Some(original_msg) = report.subparts.get(2).filter(|p| {
+        p.ctype.mimetype.contains("rfc822")
+            || p.ctype.mimetype == "message/global"
+            || p.ctype.mimetype == "message/global-headers"
+    }) {
+        let report_body = original_msg.get_body_raw()?;
+        let (report_fields, _) = mailparse::parse_headers(&report_body)?;
+
+        if let Some(original_message_id) = report_fields
+            .get_header_value(HeaderDef::MessageId)
+            .and_then(|v| parse_message_id(&v).ok())
+        {
+            let mut to_list = get_all_addresses_from_header(&report.headers, "x-failed-recipients");
+            let to = if to_list.len() == 1 {
+                Some(to_list.pop().unwrap())
+            } else {
+                None // We do not know which recipient failed
+            };
+
+            return Ok(Some(DeliveryReport {
+                rfc724_mid: original_message_id,
+                failed_recipient: to.map(|s| s.addr),
+                failure,
+            }));
+        }
+    }
+
+    Ok(None)
+}
+
 #[allow(clippy::indexing_slicing)]
 pub(crate) fn parse_message_ids(ids: &str) -> Vec<String> {
     // take care with mailparse::msgidparse() that is pretty untolerant eg. wrt missing `<` or `>`
@@ -11,7 +11,9 @@
 use tokio::time::timeout;
 use tokio_io_timeout::TimeoutStream;
 
+use crate::config::Config;
 use crate::context::Context;
+use crate::provider::Protocol;
 
 pub(crate) mod dns;
 pub(crate) mod http;
@@ -25,15 +27,68 @@
 /// Connection, write and read timeout.
 ///
 /// This constant should be more than the largest expected RTT.
+/// It is used as a default if no timeout is configured.
 pub(crate) const TIMEOUT: Duration = Duration::from_secs(60);
 
+/// Timeouts used for a single IMAP or SMTP connection.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(crate) struct Timeouts {
+    /// TCP connection establishment timeout.
+    pub connect: Duration,
+
+    /// TLS handshake timeout.
+    pub tls_handshake: Duration,
+
+    /// Read and write timeout for protocol commands.
+    pub io: Duration,
+}
+
+impl Default for Timeouts {
+    fn default() -> Self {
+        Self {
+            connect: TIMEOUT,
+            tls_handshake: TIMEOUT,
+            io: TIMEOUT,
+        }
+    }
+}
+
+impl Timeouts {
+    /// Loads configured timeouts for connections using the given protocol.
+    pub(crate) async fn load(context: &Context, protocol: Protocol) -> Result<Self> {
+        let io_key = match protocol {
+            Protocol::Imap => Config::ImapTimeout,
+            Protocol::Smtp => Config::SmtpTimeout,
+        };
+        Ok(Self {
+            connect: get_timeout(context, Config::ConnectTimeout).await?,
+            tls_handshake: get_timeout(context, Config::TlsHandshakeTimeout).await?,
+            io: get_timeout(context, io_key).await?,
+        })
+    }
+}
+
+/// Returns the timeout configured in seconds for the given key,
+/// falling back to [`TIMEOUT`] if it is set to 0.
+async fn get_timeout(context: &Context, key: Config) -> Result<Duration> {
+    let secs = context.get_config_u64(key).await?;
+    if secs == 0 {
+        Ok(TIMEOUT)
+    } else {
+        Ok(Duration::from_secs(secs))
+    }
+}
+
 /// Returns a TCP connection stream with read/write timeouts set
 /// and Nagle's algorithm disabled with `TCP_NODELAY`.
 ///
 /// `TCP_NODELAY` ensures writing to the stream always results in immediate sending of the packet
 /// to the network, which is important to reduce the latency of interactive protocols such as IMAP.
-async fn connect_tcp_inner(addr: SocketAddr) -> Result<Pin<Box<TimeoutStream<TcpStream>>>> {
-    let tcp_stream = timeout(TIMEOUT, TcpStream::connect(addr))
+async fn connect_tcp_inner(
+    addr: SocketAddr,
+    timeouts: Timeouts,
+) -> Result<Pin<Box<TimeoutStream<TcpStream>>>> {
+    let tcp_stream = timeout(timeouts.connect, TcpStream::connect(addr))
         .await
         .context("connection timeout")?
         .context("connection failure")?;
@@ -42,8 +97,8 @@ async fn connect_tcp_inner(addr: SocketAddr) -> Result<Pin<Box<TimeoutStream<Tcp
     tcp_stream.set_nodelay(true)?;
 
     let mut timeout_stream = TimeoutStream::new(tcp_stream);
-    timeout_stream.set_write_timeout(Some(TIMEOUT));
-    timeout_stream.set_read_timeout(Some(TIMEOUT));
+    timeout_stream.set_write_timeout(Some(timeouts.io));
+    timeout_stream.set_read_timeout(Some(timeouts.io));
 
     Ok(Box::pin(timeout_stream))
 }
@@ -55,9 +110,10 @@ async fn connect_tls_inner(
     host: &str,
     strict_tls: bool,
     alpn: &str,
+    timeouts: Timeouts,
 ) -> Result<TlsStream<Pin<Box<TimeoutStream<TcpStream>>>>> {
-    let tcp_stream = connect_tcp_inner(addr).await?;
-    let tls_stream = wrap_tls(strict_tls, host, alpn, tcp_stream).await?;
+    let tcp_stream = connect_tcp_inner(addr, timeouts).await?;
+    let tls_stream = wrap_tls(strict_tls, host, alpn, tcp_stream, timeouts.tls_handshake).await?;
     Ok(tls_stream)
 }
 
@@ -72,11 +128,12 @@ pub(crate) async fn connect_tcp(
     host: &str,
     port: u16,
     load_cache: bool,
+    timeouts: Timeouts,
 ) -> Result<Pin<Box<TimeoutStream<TcpStream>>>> {
     let mut first_error = None;
 
     for resolved_addr in lookup_host_with_cache(context, host, port, load_cache).await? {
-        match connect_tcp_inner(resolved_addr).await {
+        match connect_tcp_inner(resolved_addr, timeouts).await {
             Ok(stream) => {
                 return Ok(stream);
             }
@@ -99,11 +156,12 @@ pub(crate) async fn connect_tls(
     port: u16,
     strict_tls: bool,
     alpn: &str,
+    timeouts: Timeouts,
 ) -> Result<TlsStream<Pin<Box<TimeoutStream<TcpStream>>>>> {
     let mut first_error = None;
 
     for resolved_addr in lookup_host_with_cache(context, host, port, strict_tls).await? {
-        match connect_tls_inner(resolved_addr, host, strict_tls, alpn).await {
+        match connect_tls_inner(resolved_addr, host, strict_tls, alpn, timeouts).await {
             Ok(tls_stream) => {
                 if strict_tls {
                     dns::update_connect_timestamp(context, host, &resolved_addr.ip().to_string())
@@ -125,8 +183,9 @@ async fn connect_starttls_imap_inner(
     addr: SocketAddr,
     host: &str,
     strict_tls: bool,
+    timeouts: Timeouts,
 ) -> Result<TlsStream<Pin<Box<TimeoutStream<TcpStream>>>>> {
-    let tcp_stream = connect_tcp_inner(addr).await?;
+    let tcp_stream = connect_tcp_inner(addr, timeouts).await?;
 
     // Run STARTTLS command and convert the client back into a stream.
     let buffered_tcp_stream = BufWriter::new(tcp_stream);
@@ -142,7 +201,7 @@ async fn connect_starttls_imap_inner(
     let buffered_tcp_stream = client.into_inner();
     let tcp_stream = buffered_tcp_stream.into_inner();
 
-    let tls_stream = wrap_tls(strict_tls, host, "imap", tcp_stream)
+    let tls_stream = wrap_tls(strict_tls, host, "imap", tcp_stream, timeouts.tls_handshake)
         .await
         .context("STARTTLS upgrade failed")?;
 
@@ -154,11 +213,12 @@ pub(crate) async fn connect_starttls_imap(
     host: &str,
     port: u16,
     strict_tls: bool,
+    timeouts: Timeouts,
 ) -> Result<TlsStream<Pin<Box<TimeoutStream<TcpStream>>>>> {
     let mut first_error = None;
 
     for resolved_addr in lookup_host_with_cache(context, host, port, strict_tls).await? {
-        match connect_starttls_imap_inner(resolved_addr, host, strict_tls).await {
+        match connect_starttls_imap_inner(resolved_addr, host, strict_tls, timeouts).await {
             Ok(tls_stream) => {
                 if strict_tls {
                     dns::update_connect_timestamp(context, host, &resolved_addr.ip().to_string())
@@ -180,14 +240,15 @@ async fn connect_starttls_smtp_inner(
     addr: SocketAddr,
     host: &str,
     strict_tls: bool,
+    timeouts: Timeouts,
 ) -> Result<TlsStream<Pin<Box<TimeoutStream<TcpStream>>>>> {
-    let tcp_stream = connect_tcp_inner(addr).await?;
+    let tcp_stream = connect_tcp_inner(addr, timeouts).await?;
 
     // Run STARTTLS command and convert the client back into a stream.
     let client = async_smtp::SmtpClient::new().smtp_utf8(true);
     let transport = async_smtp::SmtpTransport::new(client, BufStream::new(tcp_stream)).await?;
     let tcp_stream = transport.starttls().await?.into_inner();
-    let tls_stream = wrap_tls(strict_tls, host, "smtp", tcp_stream)
+    let tls_stream = wrap_tls(strict_tls, host, "smtp", tcp_stream, timeouts.tls_handshake)
         .await
         .context("STARTTLS upgrade failed")?;
     Ok(tls_stream)
@@ -198,11 +259,12 @@ pub(crate) async fn connect_starttls_smtp(
     host: &str,
     port: u16,
     strict_tls: bool,
+    timeouts: Timeouts,
 ) -> Result<TlsStream<Pin<Box<TimeoutStream<TcpStream>>>>> {
     let mut first_error = None;
 
     for resolved_addr in lookup_host_with_cache(context, host, port, strict_tls).await? {
-        match connect_starttls_smtp_inner(resolved_addr, host, strict_tls).await {
+        match connect_starttls_smtp_inner(resolved_addr, host, strict_tls, timeouts).await {
             Ok(tls_stream) => {
                 if strict_tls {
                     dns::update_connect_timestamp(context, host, &resolved_addr.ip().to_string())
@@ -219,3 +281,34 @@ pub(crate) async fn connect_starttls_smtp(
 
     Err(first_error.unwrap_or_else(|| format_err!("no DNS resolution results for {host}")))
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_utils::TestContext;
+
+    #[tokio::test(flavor = "multi_thread", worker_threads = 2)]
+    async fn test_timeouts_load() -> Result<()> {
+        let t = TestContext::new().await;
+        assert_eq!(
+            Timeouts::load(&t, Protocol::Imap).await?,
+            Timeouts::default()
+        );
+
+        t.set_config(Config::ConnectTimeout, Some("5")).await?;
+        t.set_config(Config::TlsHandshakeTimeout, Some("10"))
+            .await?;
+        t.set_config(Config::ImapTimeout, Some("120")).await?;
+        t.set_config(Config::SmtpTimeout, Some("0")).await?;
+
+        let imap = Timeouts::load(&t, Protocol::Imap).await?;
+        assert_eq!(imap.connect, Duration::from_secs(5));
+        assert_eq!(imap.tls_handshake, Duration::from_secs(10));
+        assert_eq!(imap.io, Duration::from_secs(120));
+
+        let smtp = Timeouts::load(&t, Protocol::Smtp).await?;
+        assert_eq!(smtp.connect, Duration::from_secs(5));
+        assert_eq!(smtp.io, TIMEOUT);
+        Ok(())
+    }
+}
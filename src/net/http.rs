@@ -2,13 +2,13 @@
 
 use std::sync::Arc;
 
-use anyhow::{anyhow, Result};
+use anyhow::{anyhow, ensure, Result};
 use mime::Mime;
 use once_cell::sync::Lazy;
 
 use crate::context::Context;
 use crate::net::lookup_host_with_cache;
-use crate::socks::Socks5Config;
+use crate::socks::{ProxyProtocol, Socks5Config};
 
 static LETSENCRYPT_ROOT: Lazy<reqwest::tls::Certificate> = Lazy::new(|| {
     reqwest::tls::Certificate::from_der(include_bytes!(
@@ -133,7 +133,7 @@ fn resolve(&self, hostname: reqwest::dns::Name) -> reqwest::dns::Resolving {
 }
 
 pub(crate) async fn get_client(context: &Context, load_cache: bool) -> Result<reqwest::Client> {
-    let socks5_config = Socks5Config::from_database(&context.sql).await?;
+    let socks5_config = Socks5Config::from_database(context).await?;
     let resolver = Arc::new(CustomResolver::new(context.clone(), load_cache));
 
     let builder = reqwest::ClientBuilder::new()
@@ -142,6 +142,11 @@ pub(crate) async fn get_client(context: &Context, load_cache: bool) -> Result<re
         .dns_resolver(resolver);
 
     let builder = if let Some(socks5_config) = socks5_config {
+        // Never bypass the proxy, requests fail instead.
+        ensure!(
+            !matches!(socks5_config.protocol, ProxyProtocol::Shadowsocks(_)),
+            "HTTP requests through Shadowsocks proxies are not supported"
+        );
         let proxy = reqwest::Proxy::all(socks5_config.to_url())?;
         builder.proxy(proxy)
     } else {
@@ -1,9 +1,12 @@
 //! TLS support.
 
-use anyhow::Result;
+use std::time::Duration;
+
+use anyhow::{Context as _, Result};
 use async_native_tls::{Certificate, Protocol, TlsConnector, TlsStream};
 use once_cell::sync::Lazy;
 use tokio::io::{AsyncRead, AsyncWrite};
+use tokio::time::timeout;
 
 // this certificate is missing on older android devices (eg. lg with android6 from 2017)
 // certificate downloaded from https://letsencrypt.org/certificates/
@@ -29,14 +32,20 @@ pub fn build_tls(strict_tls: bool, alpns: &[&str]) -> TlsConnector {
     }
 }
 
+/// Performs TLS handshake over the stream.
+///
+/// Fails if the handshake does not complete within `handshake_timeout`.
 pub async fn wrap_tls<T: AsyncRead + AsyncWrite + Unpin>(
     strict_tls: bool,
     hostname: &str,
     alpn: &str,
     stream: T,
+    handshake_timeout: Duration,
 ) -> Result<TlsStream<T>> {
     let tls = build_tls(strict_tls, &[alpn]);
-    let tls_stream = tls.connect(hostname, stream).await?;
+    let tls_stream = timeout(handshake_timeout, tls.connect(hostname, stream))
+        .await
+        .context("TLS handshake timeout")??;
     Ok(tls_stream)
 }
 
@@ -203,6 +203,80 @@ pub enum Param {
 
     /// For messages: Whether [crate::message::Viewtype::Sticker] should be forced.
     ForceSticker = b'X',
+
+    /// For Contacts: JSON list of commands advertised by the bot.
+    BotCommands = b'z',
+
+    /// For Messages: JSON description of the poll, see [crate::poll::Poll].
+    Poll = b'I',
+
+    /// For Messages: comma-separated indices of the options voted for in a poll.
+    PollVote = b'5',
+
+    /// For Messages: the poll is closed.
+    /// For hidden messages: the message closes (`1`) or reopens (`0`) the poll it replies to.
+    PollClosed = b'6',
+
+    /// For hidden messages: Message-ID of the message whose text is replaced
+    /// by the text of this message, see [crate::chat::send_edit].
+    TextEditFor = b'$',
+
+    /// For Messages: the text was edited by the sender.
+    IsEdited = b'%',
+
+    /// For hidden messages: space-separated Message-IDs of the messages
+    /// to delete for everyone, see [crate::message::delete_for_all].
+    DeleteRequestFor = b'&',
+
+    /// For Messages: the message was deleted for everyone by its sender.
+    IsRetracted = b'*',
+
+    /// For Messages: contacts mentioned in the message, see [crate::mention::Mention].
+    Mentions = b'@',
+
+    /// For Messages: the attachment is shown only once,
+    /// see [crate::message::Message::set_view_once].
+    ViewOnce = b'^',
+
+    /// For Messages: preview image of a video or PDF attachment,
+    /// see [crate::message::Message::get_preview_blob].
+    PreviewFile = b'+',
+
+    /// For Messages: number of frames of an animated image.
+    Frames = b'~',
+
+    /// For Chats: the [crate::chat::EncryptionPolicy], opportunistic if unset.
+    EncryptionPolicy = b'7',
+
+    /// For Messages: message is not encrypted, but has a valid PGP/MIME signature.
+    /// For outgoing messages: send unencrypted, but signed.
+    Clearsigned = b'8',
+
+    /// For Messages: normalized DKIM/SPF/DMARC results of our email server,
+    /// see [crate::authres::AuthenticationResults].
+    AuthenticationResults = b'9',
+
+    /// For Chats: ID of the contact whose possibly forged messages are quarantined
+    /// in this ad-hoc group instead of the 1:1 chat, see [crate::config::Config::SpoofingProtection].
+    SpoofingQuarantine = b'!',
+
+    /// For Messages: why the message was assigned to its chat,
+    /// see [crate::message::ChatAssignment].
+    ChatAssignment = b'#',
+
+    /// For messages: Alternative text describing the attachment,
+    /// sent in the `Content-Description` header of the attachment part.
+    AltText = b'M',
+
+    /// For Contacts: Base64-encoded ML-KEM encapsulation key
+    /// advertised for post-quantum hybrid encryption.
+    #[cfg(feature = "pq-hybrid")]
+    PqKey = b'Z',
+
+    /// For Contacts: Fingerprint of the OpenPGP key
+    /// that signed the message advertising [`Param::PqKey`].
+    #[cfg(feature = "pq-hybrid")]
+    PqKeyFingerprint = b'?',
     // 'L' was defined as ProtectionSettingsTimestamp for Chats, however, never used in production.
 }
 
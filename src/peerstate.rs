@@ -9,7 +9,6 @@
 use crate::aheader::{Aheader, EncryptPreference};
 use crate::chat::{self, Chat};
 use crate::chatlist::Chatlist;
-use crate::config::Config;
 use crate::constants::Chattype;
 use crate::contact::{Contact, Origin};
 use crate::context::Context;
@@ -95,6 +94,10 @@ pub struct Peerstate {
     /// that the fingerprint of the key used in chats with
     /// opportunistic encryption was changed after Peerstate creation.
     pub fingerprint_changed: bool,
+
+    /// Fingerprint of the key used before the change
+    /// indicated by `fingerprint_changed`.
+    pub old_fingerprint: Option<Fingerprint>,
 }
 
 impl Peerstate {
@@ -133,6 +136,7 @@ pub fn from_public_key(
             secondary_verifier: None,
             backward_verified_key_id: None,
             fingerprint_changed: false,
+            old_fingerprint: None,
         }
     }
 
@@ -163,6 +167,7 @@ pub fn from_gossip(gossip_header: &Aheader, message_time: i64) -> Self {
             secondary_verifier: None,
             backward_verified_key_id: None,
             fingerprint_changed: false,
+            old_fingerprint: None,
         }
     }
 
@@ -293,6 +298,7 @@ async fn from_stmt(
                     },
                     backward_verified_key_id: row.get("backward_verified_key_id")?,
                     fingerprint_changed: false,
+                    old_fingerprint: None,
                 };
 
                 Ok(res)
@@ -314,6 +320,7 @@ pub fn recalc_fingerprint(&mut self) {
                 && old_public_fingerprint != self.public_key_fingerprint
             {
                 self.fingerprint_changed = true;
+                self.old_fingerprint = old_public_fingerprint;
             }
         }
 
@@ -329,11 +336,26 @@ pub fn recalc_fingerprint(&mut self) {
                 // Autocrypt header, which overrides gossip key.
                 if old_gossip_fingerprint.is_some() && self.public_key_fingerprint.is_none() {
                     self.fingerprint_changed = true;
+                    self.old_fingerprint = old_gossip_fingerprint;
                 }
             }
         }
     }
 
+    /// Replaces verified keys having the same fingerprint as `key` with `key`.
+    ///
+    /// This happens if the peer renewed the encryption subkey,
+    /// which does not change the fingerprint and thus does not affect verification.
+    fn update_verified_keys(&mut self, key: &SignedPublicKey) {
+        let fingerprint = key.fingerprint();
+        if self.verified_key_fingerprint.as_ref() == Some(&fingerprint) {
+            self.verified_key = Some(key.clone());
+        }
+        if self.secondary_verified_key_fingerprint.as_ref() == Some(&fingerprint) {
+            self.secondary_verified_key = Some(key.clone());
+        }
+    }
+
     /// Reset Autocrypt peerstate.
     ///
     /// Used when it is detected that the contact no longer uses Autocrypt.
@@ -361,6 +383,7 @@ pub fn apply_header(&mut self, header: &Aheader, message_time: i64) {
             if self.public_key.as_ref() != Some(&header.public_key) {
                 self.public_key = Some(header.public_key.clone());
                 self.recalc_fingerprint();
+                self.update_verified_keys(&header.public_key);
             }
         }
     }
@@ -376,6 +399,7 @@ pub fn apply_gossip(&mut self, gossip_header: &Aheader, message_time: i64) {
             if self.gossip_key.as_ref() != Some(&gossip_header.public_key) {
                 self.gossip_key = Some(gossip_header.public_key.clone());
                 self.recalc_fingerprint();
+                self.update_verified_keys(&gossip_header.public_key);
             }
 
             // This is non-standard.
@@ -477,9 +501,15 @@ pub(crate) async fn is_backward_verified(&self, context: &Context) -> Result<boo
             return Ok(false);
         };
 
-        let self_key_id = context.get_config_i64(Config::KeyId).await?;
-
-        let backward_verified = backward_verified_key_id == self_key_id;
+        // After a key rotation, the contact still knows our previous key
+        // until it receives a message with the new one, so any of our keys is fine.
+        let backward_verified = context
+            .sql
+            .exists(
+                "SELECT COUNT(*) FROM keypairs WHERE id=?",
+                (backward_verified_key_id,),
+            )
+            .await?;
         Ok(backward_verified)
     }
 
@@ -737,13 +767,39 @@ async fn handle_setup_change(
         Ok(())
     }
 
-    /// Adds a warning to all the chats corresponding to peerstate if fingerprint has changed.
+    /// Adds a warning to all the chats corresponding to peerstate if fingerprint has changed
+    /// and records the change in the key history.
+    ///
+    /// `rfc724_mid` is the Message-ID of the message that caused the change, if any.
     pub(crate) async fn handle_fingerprint_change(
         &self,
         context: &Context,
         timestamp: i64,
+        rfc724_mid: Option<&str>,
     ) -> Result<()> {
         if self.fingerprint_changed {
+            if let (Some(old_fingerprint), Some(new_fingerprint)) = (
+                &self.old_fingerprint,
+                self.public_key_fingerprint
+                    .as_ref()
+                    .or(self.gossip_key_fingerprint.as_ref()),
+            ) {
+                context
+                    .sql
+                    .execute(
+                        "INSERT INTO key_history
+                         (addr, old_fingerprint, new_fingerprint, timestamp, rfc724_mid)
+                         VALUES (?, ?, ?, ?, ?)",
+                        (
+                            &self.addr,
+                            old_fingerprint.hex(),
+                            new_fingerprint.hex(),
+                            timestamp,
+                            rfc724_mid.unwrap_or_default(),
+                        ),
+                    )
+                    .await?;
+            }
             self.handle_setup_change(context, timestamp, PeerstateChange::FingerprintChange)
                 .await?;
         }
@@ -853,6 +909,7 @@ async fn test_peerstate_save_to_db() {
             secondary_verifier: None,
             backward_verified_key_id: None,
             fingerprint_changed: false,
+            old_fingerprint: None,
         };
 
         assert!(
@@ -897,6 +954,7 @@ async fn test_peerstate_double_create() {
             secondary_verifier: None,
             backward_verified_key_id: None,
             fingerprint_changed: false,
+            old_fingerprint: None,
         };
 
         assert!(
@@ -934,6 +992,7 @@ async fn test_peerstate_with_empty_gossip_key_save_to_db() {
             secondary_verifier: None,
             backward_verified_key_id: None,
             fingerprint_changed: false,
+            old_fingerprint: None,
         };
 
         assert!(
@@ -1001,6 +1060,7 @@ async fn test_peerstate_degrade_reordering() {
             secondary_verifier: None,
             backward_verified_key_id: None,
             fingerprint_changed: false,
+            old_fingerprint: None,
         };
 
         peerstate.apply_header(&header, 100);
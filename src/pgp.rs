@@ -3,17 +3,20 @@
 use std::collections::{BTreeMap, HashSet};
 use std::io;
 use std::io::Cursor;
+use std::sync::Arc;
 
 use anyhow::{bail, Context as _, Result};
+use chrono::{DateTime, Utc};
 use deltachat_contact_tools::EmailAddress;
 use pgp::armor::BlockType;
 use pgp::composed::{
-    Deserializable, KeyType as PgpKeyType, Message, SecretKeyParamsBuilder, SignedPublicKey,
-    SignedPublicSubKey, SignedSecretKey, StandaloneSignature, SubkeyParamsBuilder,
+    Deserializable, KeyType as PgpKeyType, Message, SecretKeyParamsBuilder, SecretSubkey,
+    SignedPublicKey, SignedPublicSubKey, SignedSecretKey, StandaloneSignature, SubkeyParamsBuilder,
 };
 use pgp::crypto::ecc_curve::ECCCurve;
 use pgp::crypto::hash::HashAlgorithm;
 use pgp::crypto::sym::SymmetricKeyAlgorithm;
+use pgp::packet::KeyFlags;
 use pgp::types::{
     CompressionAlgorithm, KeyTrait, Mpi, PublicKeyTrait, SecretKeyTrait, StringToKey,
 };
@@ -21,7 +24,7 @@
 use tokio::runtime::Handle;
 
 use crate::constants::KeyGenType;
-use crate::key::{DcKey, Fingerprint};
+use crate::key::{DcKey, Fingerprint, SigningBackend};
 
 #[allow(missing_docs)]
 #[cfg(test)]
@@ -98,6 +101,124 @@ fn to_writer_old(&self, writer: &mut impl io::Write) -> pgp::errors::Result<()>
     }
 }
 
+/// Key used to sign outgoing messages.
+#[derive(Debug, Clone)]
+pub(crate) enum SigningKey {
+    /// Secret key stored in the database.
+    Secret(SignedSecretKey),
+
+    /// Key held by a [`SigningBackend`], e.g. on an OpenPGP card.
+    Backend {
+        /// Public key whose primary key is held by the backend.
+        public: SignedPublicKey,
+
+        /// Backend creating the signatures.
+        backend: Arc<dyn SigningBackend>,
+    },
+}
+
+impl From<SignedSecretKey> for SigningKey {
+    fn from(key: SignedSecretKey) -> Self {
+        Self::Secret(key)
+    }
+}
+
+/// Signing key whose signatures are created by a [`SigningBackend`].
+///
+/// Implements rPGP key traits so it can be used
+/// in place of a secret key for signing messages.
+#[derive(Debug, Clone)]
+struct BackendSigningKey<'a> {
+    public: &'a SignedPublicKey,
+    backend: &'a dyn SigningBackend,
+}
+
+impl<'a> KeyTrait for BackendSigningKey<'a> {
+    fn fingerprint(&self) -> Vec<u8> {
+        self.public.fingerprint()
+    }
+
+    fn key_id(&self) -> pgp::types::KeyId {
+        self.public.key_id()
+    }
+
+    fn algorithm(&self) -> pgp::crypto::public_key::PublicKeyAlgorithm {
+        self.public.algorithm()
+    }
+}
+
+impl<'a> PublicKeyTrait for BackendSigningKey<'a> {
+    fn verify_signature(
+        &self,
+        hash: HashAlgorithm,
+        data: &[u8],
+        sig: &[Mpi],
+    ) -> pgp::errors::Result<()> {
+        self.public.verify_signature(hash, data, sig)
+    }
+
+    fn encrypt<R: Rng + CryptoRng>(
+        &self,
+        rng: &mut R,
+        plain: &[u8],
+    ) -> pgp::errors::Result<Vec<Mpi>> {
+        self.public.encrypt(rng, plain)
+    }
+
+    fn to_writer_old(&self, writer: &mut impl io::Write) -> pgp::errors::Result<()> {
+        self.public.to_writer_old(writer)
+    }
+}
+
+impl<'a> SecretKeyTrait for BackendSigningKey<'a> {
+    type PublicKey = SignedPublicKey;
+    type Unlocked = Self;
+
+    fn unlock<F, G, T>(&self, _pw: F, work: G) -> pgp::errors::Result<T>
+    where
+        F: FnOnce() -> String,
+        G: FnOnce(&Self::Unlocked) -> pgp::errors::Result<T>,
+    {
+        // The backend is responsible for unlocking the key, e.g. asking for the card PIN.
+        work(self)
+    }
+
+    fn create_signature<F>(
+        &self,
+        _key_pw: F,
+        hash: HashAlgorithm,
+        data: &[u8],
+    ) -> pgp::errors::Result<Vec<Mpi>>
+    where
+        F: FnOnce() -> String,
+    {
+        let signature = self
+            .backend
+            .sign(hash, data)
+            .map_err(|err| pgp::errors::Error::Message(format!("Signing backend: {err:#}")))?;
+        Ok(signature.into_iter().map(Mpi::from_raw).collect())
+    }
+
+    fn public_key(&self) -> Self::PublicKey {
+        self.public.clone()
+    }
+}
+
+/// Signs `msg` with `signing_key`.
+fn sign_message(msg: Message, signing_key: &SigningKey) -> Result<Message> {
+    let signed_msg = match signing_key {
+        SigningKey::Secret(skey) => msg.sign(skey, || "".into(), HASH_ALGORITHM)?,
+        SigningKey::Backend { public, backend } => {
+            let skey = BackendSigningKey {
+                public,
+                backend: backend.as_ref(),
+            };
+            msg.sign(&skey, || "".into(), HASH_ALGORITHM)?
+        }
+    };
+    Ok(signed_msg)
+}
+
 /// Split data from PGP Armored Data as defined in <https://tools.ietf.org/html/rfc4880#section-6.2>.
 ///
 /// Returns (type, headers, base64 encoded body).
@@ -216,17 +337,87 @@ pub(crate) fn create_keypair(addr: EmailAddress, keygen_type: KeyGenType) -> Res
     })
 }
 
-/// Select public key or subkey to use for encryption.
+/// Generates a new encryption subkey for `keypair` and binds it to the primary key.
 ///
-/// First, tries to use subkeys. If none of the subkeys are suitable
-/// for encryption, tries to use primary key. Returns `None` if the public
-/// key cannot be used for encryption.
+/// The primary key and thus the fingerprint do not change.
+/// The public key of the returned keypair contains only the new subkey,
+/// while the secret key keeps the old subkeys
+/// so that messages encrypted to them can still be decrypted.
+pub(crate) fn renew_encryption_subkey(
+    keypair: &KeyPair,
+    keygen_type: KeyGenType,
+) -> Result<KeyPair> {
+    // Generate a key of the requested type only to take its encryption subkey.
+    let generated = create_keypair(keypair.addr.clone(), keygen_type)?;
+    let subkey = generated
+        .secret
+        .secret_subkeys
+        .into_iter()
+        .next()
+        .context("generated key has no subkey")?;
+    let mut keyflags = KeyFlags::default();
+    keyflags.set_encrypt_comms(true);
+    keyflags.set_encrypt_storage(true);
+    let subkey = SecretSubkey::new(subkey.key, keyflags)
+        .sign(&keypair.secret, || "".into())
+        .context("failed to bind subkey")?;
+
+    let mut secret_key = keypair.secret.clone();
+    secret_key.secret_subkeys.insert(0, subkey);
+    secret_key
+        .verify()
+        .context("invalid secret key generated")?;
+
+    let mut public_key = secret_key
+        .public_key()
+        .sign(&secret_key, || "".into())
+        .context("failed to sign public key")?;
+    public_key.public_subkeys.truncate(1);
+    public_key
+        .verify()
+        .context("invalid public key generated")?;
+
+    Ok(KeyPair {
+        addr: keypair.addr.clone(),
+        public: public_key,
+        secret: secret_key,
+    })
+}
+
+/// Returns true if the binding signature of `subkey` says that it has expired at `now`.
+fn is_subkey_expired(subkey: &SignedPublicSubKey, now: DateTime<Utc>) -> bool {
+    let Some(expiration) = subkey
+        .signatures
+        .iter()
+        .max_by_key(|sig| sig.created().copied())
+        .and_then(|sig| sig.key_expiration_time())
+    else {
+        return false;
+    };
+    // rPGP returns key expiration time as a timestamp,
+    // but it is the number of seconds after the key creation time.
+    let lifetime = expiration.timestamp();
+    lifetime > 0 && subkey.key.created_at().timestamp().saturating_add(lifetime) <= now.timestamp()
+}
+
+/// Returns true if `key` has a subkey or primary key usable for encryption.
+pub(crate) fn has_encryption_key(key: &SignedPublicKey) -> bool {
+    select_pk_for_encryption(key).is_some()
+}
+
+/// Select public key or subkey to use for encryption.
 ///
-/// TODO: take key flags and expiration dates into account
+/// First, tries to use subkeys, preferring the newest one that has not expired.
+/// If all encryption subkeys have expired, the newest one is used anyway,
+/// because the peer likely still has the secret key and can decrypt the message.
+/// If none of the subkeys are suitable for encryption, tries to use primary key.
+/// Returns `None` if the public key cannot be used for encryption.
 fn select_pk_for_encryption(key: &SignedPublicKey) -> Option<SignedPublicKeyOrSubkey> {
+    let now = Utc::now();
     key.public_subkeys
         .iter()
-        .find(|subkey| subkey.is_encryption_key())
+        .filter(|subkey| subkey.is_encryption_key())
+        .max_by_key(|subkey| (!is_subkey_expired(subkey, now), *subkey.key.created_at()))
         .map_or_else(
             || {
                 // No usable subkey found, try primary key
@@ -242,10 +433,10 @@ fn select_pk_for_encryption(key: &SignedPublicKey) -> Option<SignedPublicKeyOrSu
 
 /// Encrypts `plain` text using `public_keys_for_encryption`
 /// and signs it using `private_key_for_signing`.
-pub async fn pk_encrypt(
+pub(crate) async fn pk_encrypt(
     plain: &[u8],
     public_keys_for_encryption: Vec<SignedPublicKey>,
-    private_key_for_signing: Option<SignedSecretKey>,
+    private_key_for_signing: Option<SigningKey>,
     compress: bool,
 ) -> Result<String> {
     let lit_msg = Message::new_literal_bytes("", plain);
@@ -261,7 +452,7 @@ pub async fn pk_encrypt(
             let mut rng = thread_rng();
 
             let encrypted_msg = if let Some(ref skey) = private_key_for_signing {
-                let signed_msg = lit_msg.sign(skey, || "".into(), HASH_ALGORITHM)?;
+                let signed_msg = sign_message(lit_msg, skey)?;
                 let compressed_msg = if compress {
                     signed_msg.compress(CompressionAlgorithm::ZLIB)?
                 } else {
@@ -280,14 +471,13 @@ pub async fn pk_encrypt(
 }
 
 /// Signs `plain` text using `private_key_for_signing`.
-pub fn pk_calc_signature(
+pub(crate) fn pk_calc_signature(
     plain: &[u8],
-    private_key_for_signing: &SignedSecretKey,
+    private_key_for_signing: &SigningKey,
 ) -> Result<String> {
-    let msg = Message::new_literal_bytes("", plain).sign(
+    let msg = sign_message(
+        Message::new_literal_bytes("", plain),
         private_key_for_signing,
-        || "".into(),
-        HASH_ALGORITHM,
     )?;
     let signature = msg.into_signature().to_armored_string(Default::default())?;
     Ok(signature)
@@ -449,6 +639,34 @@ fn test_create_keypair() {
         assert_ne!(keypair0.public, keypair1.public);
     }
 
+    #[tokio::test(flavor = "multi_thread", worker_threads = 2)]
+    async fn test_renew_encryption_subkey() -> Result<()> {
+        let alice = alice_keypair();
+        let renewed = renew_encryption_subkey(&alice, KeyGenType::Default)?;
+        assert_eq!(
+            DcKey::fingerprint(&renewed.public),
+            DcKey::fingerprint(&alice.public)
+        );
+        assert_eq!(renewed.public.public_subkeys.len(), 1);
+        assert_eq!(renewed.secret.secret_subkeys.len(), 2);
+        assert!(has_encryption_key(&renewed.public));
+        let old_subkey = select_pk_for_encryption(&alice.public).unwrap().key_id();
+        let new_subkey = select_pk_for_encryption(&renewed.public).unwrap().key_id();
+        assert_ne!(old_subkey, new_subkey);
+
+        // Messages encrypted to the old and the new subkey can be decrypted.
+        for public in [alice.public, renewed.public.clone()] {
+            let ctext = pk_encrypt(CLEARTEXT, vec![public], None, true).await?;
+            let (plain, _) = pk_decrypt(
+                ctext.into_bytes(),
+                &[renewed.secret.clone()],
+                &[renewed.public.clone()],
+            )?;
+            assert_eq!(plain, CLEARTEXT);
+        }
+        Ok(())
+    }
+
     /// [SignedSecretKey] and [SignedPublicKey] objects
     /// to use in tests.
     struct TestKeys {
@@ -490,7 +708,7 @@ async fn ctext_signed() -> &'static String {
                 pk_encrypt(
                     CLEARTEXT,
                     keyring,
-                    Some(KEYS.alice_secret.clone()),
+                    Some(KEYS.alice_secret.clone().into()),
                     compress,
                 )
                 .await
@@ -0,0 +1,430 @@
+//! # Polls.
+//!
+//! A poll is a text message carrying a question and a list of options
+//! in the `Chat-Poll` header as base64-encoded JSON.
+//! The text part lists the question and the options
+//! so the poll is readable in other email clients.
+//!
+//! Votes are hidden messages replying to the poll
+//! with the `Chat-Poll-Vote` header containing the comma-separated indices
+//! of the selected options.
+//! Like reactions, a vote overrides all previous votes of the same contact,
+//! and an empty vote retracts it.
+//!
+//! The creator of the poll can close it with a hidden message
+//! carrying the `Chat-Poll-Close` header
+//! and reopen it later with the `Chat-Poll-Reopen` header.
+//! Votes arriving while the poll is closed are ignored.
+
+use std::collections::BTreeMap;
+
+use anyhow::{ensure, Context as _, Result};
+use base64::Engine as _;
+use serde::{Deserialize, Serialize};
+
+use crate::chat::{self, send_msg, ChatId};
+use crate::contact::ContactId;
+use crate::context::Context;
+use crate::message::{rfc724_mid_exists, Message, MsgId, Viewtype};
+use crate::mimefactory::wrapped_base64_encode;
+use crate::param::Param;
+
+/// Maximum number of options of a poll.
+const POLL_OPTIONS_MAX: usize = 32;
+
+/// Maximum size of the received JSON poll description.
+const POLL_SIZE_MAX: usize = 16 << 10;
+
+/// Question with the options to vote for.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Poll {
+    /// The question.
+    pub question: String,
+
+    /// The options to vote for.
+    pub options: Vec<String>,
+
+    /// True if more than one option can be selected.
+    #[serde(default)]
+    pub multiple_choice: bool,
+}
+
+impl Poll {
+    fn validate(&self) -> Result<()> {
+        ensure!(!self.question.is_empty(), "Poll question is empty");
+        ensure!(
+            (2..=POLL_OPTIONS_MAX).contains(&self.options.len()),
+            "Poll must have between 2 and {POLL_OPTIONS_MAX} options"
+        );
+        ensure!(
+            self.options.iter().all(|option| !option.is_empty()),
+            "Poll options must not be empty"
+        );
+        Ok(())
+    }
+
+    /// Returns the text shown by clients not supporting polls.
+    fn to_text(&self) -> String {
+        let mut text = format!("📊 {}\n", self.question);
+        for (i, option) in self.options.iter().enumerate() {
+            text += &format!("\n{}. {option}", i + 1);
+        }
+        text
+    }
+}
+
+/// Tally of a poll, see [`Message::get_poll_results`].
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct PollResults {
+    /// The poll.
+    pub poll: Poll,
+
+    /// Number of votes for each option, in the order of [`Poll::options`].
+    pub counts: Vec<usize>,
+
+    /// Options selected by each voter.
+    pub votes: BTreeMap<ContactId, Vec<usize>>,
+
+    /// True if the poll was closed by its creator.
+    pub closed: bool,
+}
+
+impl Message {
+    /// Returns the poll if the message is a poll.
+    pub fn get_poll(&self) -> Option<Poll> {
+        let json = self.param.get(Param::Poll)?;
+        serde_json::from_str(json).ok()
+    }
+
+    /// Returns the tally of the poll,
+    /// `None` if the message is not a poll.
+    pub async fn get_poll_results(&self, context: &Context) -> Result<Option<PollResults>> {
+        let Some(poll) = self.get_poll() else {
+            return Ok(None);
+        };
+        let votes: BTreeMap<ContactId, Vec<usize>> = context
+            .sql
+            .query_map(
+                "SELECT contact_id, options FROM poll_votes WHERE msg_id=?",
+                (self.id,),
+                |row| {
+                    let contact_id: ContactId = row.get(0)?;
+                    let options: String = row.get(1)?;
+                    Ok((contact_id, options))
+                },
+                |rows| {
+                    let mut votes = BTreeMap::new();
+                    for row in rows {
+                        let (contact_id, options) = row?;
+                        votes.insert(contact_id, parse_vote(&options)?);
+                    }
+                    Ok(votes)
+                },
+            )
+            .await?;
+        let mut counts = vec![0; poll.options.len()];
+        for option in votes.values().flatten() {
+            if let Some(count) = counts.get_mut(*option) {
+                *count += 1;
+            }
+        }
+        Ok(Some(PollResults {
+            poll,
+            counts,
+            votes,
+            closed: self.param.get_bool(Param::PollClosed).unwrap_or_default(),
+        }))
+    }
+}
+
+/// Sends a poll to the chat.
+pub async fn send_poll(context: &Context, chat_id: ChatId, poll: &Poll) -> Result<MsgId> {
+    poll.validate()?;
+    let mut msg = Message::new(Viewtype::Text);
+    msg.text = poll.to_text();
+    msg.param.set(Param::Poll, serde_json::to_string(poll)?);
+    send_msg(context, chat_id, &mut msg).await
+}
+
+/// Votes for the options with the given indices in the poll `msg_id`,
+/// overriding the previous vote.
+///
+/// Use an empty list of options to retract the vote.
+pub async fn send_poll_vote(context: &Context, msg_id: MsgId, options: &[usize]) -> Result<MsgId> {
+    let msg = Message::load_from_db(context, msg_id).await?;
+    let poll = msg.get_poll().context("Message is not a poll")?;
+    ensure!(
+        !msg.param.get_bool(Param::PollClosed).unwrap_or_default(),
+        "Poll is closed"
+    );
+    check_vote(&poll, options)?;
+    let vote = render_vote(options);
+
+    let mut vote_msg = Message::new(Viewtype::Text);
+    vote_msg.text = options
+        .iter()
+        .filter_map(|i| poll.options.get(*i))
+        .cloned()
+        .collect::<Vec<_>>()
+        .join(", ");
+    vote_msg.param.set(Param::PollVote, &vote);
+    vote_msg.in_reply_to = Some(msg.rfc724_mid.clone());
+    vote_msg.hidden = true;
+
+    let vote_msg_id = send_msg(context, msg.chat_id, &mut vote_msg).await?;
+    set_vote(context, &msg, ContactId::SELF, &vote).await?;
+    Ok(vote_msg_id)
+}
+
+/// Closes the poll `msg_id` created by us, no more votes are accepted afterwards.
+pub async fn close_poll(context: &Context, msg_id: MsgId) -> Result<MsgId> {
+    send_poll_closed(context, msg_id, true).await
+}
+
+/// Reopens the poll `msg_id` created by us and closed before,
+/// votes are accepted again.
+pub async fn reopen_poll(context: &Context, msg_id: MsgId) -> Result<MsgId> {
+    send_poll_closed(context, msg_id, false).await
+}
+
+async fn send_poll_closed(context: &Context, msg_id: MsgId, closed: bool) -> Result<MsgId> {
+    let mut msg = Message::load_from_db(context, msg_id).await?;
+    ensure!(msg.get_poll().is_some(), "Message is not a poll");
+    ensure!(
+        msg.from_id == ContactId::SELF,
+        "Only the creator can close or reopen the poll"
+    );
+    ensure!(
+        msg.param.get_bool(Param::PollClosed).unwrap_or_default() != closed,
+        "Poll is already {}",
+        if closed { "closed" } else { "open" }
+    );
+
+    let mut close_msg = Message::new(Viewtype::Text);
+    close_msg.param.set_int(Param::PollClosed, closed.into());
+    close_msg.in_reply_to = Some(msg.rfc724_mid.clone());
+    close_msg.hidden = true;
+    let close_msg_id = send_msg(context, msg.chat_id, &mut close_msg).await?;
+
+    set_closed(context, &mut msg, closed).await?;
+    Ok(close_msg_id)
+}
+
+/// Returns the value of the `Chat-Poll` header to send, if the message is a poll.
+pub(crate) fn render_poll_header(msg: &Message) -> Option<String> {
+    let json = msg.param.get(Param::Poll)?;
+    Some(wrapped_base64_encode(json.as_bytes()))
+}
+
+/// Decodes the `Chat-Poll` header into the JSON poll description to store.
+pub(crate) fn parse_poll_header(header_value: &str) -> Result<String> {
+    let json = base64::engine::general_purpose::STANDARD
+        .decode(header_value.split_ascii_whitespace().collect::<String>())
+        .context("Failed to decode poll")?;
+    ensure!(json.len() <= POLL_SIZE_MAX, "Poll is too large");
+    let poll: Poll = serde_json::from_slice(&json).context("Failed to parse poll")?;
+    poll.validate()?;
+    Ok(serde_json::to_string(&poll)?)
+}
+
+/// Stores the vote of `contact_id` received in the `Chat-Poll-Vote` header
+/// for the poll with `in_reply_to` Message-ID.
+pub(crate) async fn receive_poll_vote(
+    context: &Context,
+    in_reply_to: &str,
+    contact_id: ContactId,
+    header_value: &str,
+) -> Result<()> {
+    let Some(msg) = load_poll(context, in_reply_to).await? else {
+        info!(context, "Ignoring vote for unknown poll {in_reply_to}.");
+        return Ok(());
+    };
+    if msg.param.get_bool(Param::PollClosed).unwrap_or_default() {
+        info!(context, "Ignoring vote for closed poll {}.", msg.id);
+        return Ok(());
+    }
+    ensure!(
+        contact_id == ContactId::SELF
+            || chat::is_contact_in_chat(context, msg.chat_id, contact_id).await?,
+        "Voter is not a member of the poll chat"
+    );
+    let poll = msg.get_poll().context("Message is not a poll")?;
+    let options = parse_vote(header_value)?;
+    check_vote(&poll, &options)?;
+    set_vote(context, &msg, contact_id, &render_vote(&options)).await
+}
+
+/// Closes or reopens the poll with `in_reply_to` Message-ID
+/// if the `Chat-Poll-Close` or `Chat-Poll-Reopen` header was sent by its creator.
+pub(crate) async fn receive_poll_close(
+    context: &Context,
+    in_reply_to: &str,
+    contact_id: ContactId,
+    closed: bool,
+) -> Result<()> {
+    let Some(mut msg) = load_poll(context, in_reply_to).await? else {
+        info!(context, "Ignoring close of unknown poll {in_reply_to}.");
+        return Ok(());
+    };
+    ensure!(
+        msg.from_id == contact_id,
+        "Poll can only be closed or reopened by its creator"
+    );
+    set_closed(context, &mut msg, closed).await
+}
+
+async fn load_poll(context: &Context, rfc724_mid: &str) -> Result<Option<Message>> {
+    let Some((msg_id, _)) = rfc724_mid_exists(context, rfc724_mid).await? else {
+        return Ok(None);
+    };
+    let msg = Message::load_from_db(context, msg_id).await?;
+    Ok(Some(msg).filter(|msg| msg.param.exists(Param::Poll)))
+}
+
+fn check_vote(poll: &Poll, options: &[usize]) -> Result<()> {
+    ensure!(
+        options.iter().all(|i| *i < poll.options.len()),
+        "Invalid poll option"
+    );
+    ensure!(
+        poll.multiple_choice || options.len() <= 1,
+        "Only one option can be selected"
+    );
+    Ok(())
+}
+
+fn render_vote(options: &[usize]) -> String {
+    let mut options = options.to_vec();
+    options.sort_unstable();
+    options.dedup();
+    options
+        .iter()
+        .map(|i| i.to_string())
+        .collect::<Vec<_>>()
+        .join(",")
+}
+
+fn parse_vote(vote: &str) -> Result<Vec<usize>> {
+    vote.split(',')
+        .map(str::trim)
+        .filter(|option| !option.is_empty())
+        .map(|option| option.parse().context("Invalid poll vote"))
+        .collect()
+}
+
+async fn set_vote(
+    context: &Context,
+    msg: &Message,
+    contact_id: ContactId,
+    vote: &str,
+) -> Result<()> {
+    if vote.is_empty() {
+        context
+            .sql
+            .execute(
+                "DELETE FROM poll_votes WHERE msg_id=? AND contact_id=?",
+                (msg.id, contact_id),
+            )
+            .await?;
+    } else {
+        context
+            .sql
+            .execute(
+                "INSERT INTO poll_votes (msg_id, contact_id, options)
+                 VALUES (?1, ?2, ?3)
+                 ON CONFLICT(msg_id, contact_id)
+                 DO UPDATE SET options=excluded.options",
+                (msg.id, contact_id, vote),
+            )
+            .await?;
+    }
+    context.emit_msgs_changed(msg.chat_id, msg.id);
+    Ok(())
+}
+
+async fn set_closed(context: &Context, msg: &mut Message, closed: bool) -> Result<()> {
+    if closed {
+        msg.param.set_int(Param::PollClosed, 1);
+    } else {
+        msg.param.remove(Param::PollClosed);
+    }
+    msg.update_param(context).await?;
+    context.emit_msgs_changed(msg.chat_id, msg.id);
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_utils::TestContextManager;
+
+    #[test]
+    fn test_parse_vote() -> Result<()> {
+        assert_eq!(parse_vote("")?, Vec::<usize>::new());
+        assert_eq!(parse_vote("0, 2")?, vec![0, 2]);
+        assert!(parse_vote("a").is_err());
+        assert_eq!(render_vote(&[2, 0, 2]), "0,2");
+        Ok(())
+    }
+
+    #[tokio::test(flavor = "multi_thread", worker_threads = 2)]
+    async fn test_poll() -> Result<()> {
+        let mut tcm = TestContextManager::new();
+        let alice = &tcm.alice().await;
+        let bob = &tcm.bob().await;
+
+        let poll = Poll {
+            question: "Lunch?".to_string(),
+            options: vec!["Pizza".to_string(), "Sushi".to_string()],
+            multiple_choice: false,
+        };
+        let alice_chat = alice.create_chat(bob).await;
+        let poll_msg_id = send_poll(alice, alice_chat.id, &poll).await?;
+        let bob_msg = bob.recv_msg(&alice.pop_sent_msg().await).await;
+        assert_eq!(bob_msg.get_poll(), Some(poll.clone()));
+        bob_msg.chat_id.accept(bob).await?;
+
+        assert!(send_poll_vote(bob, bob_msg.id, &[0, 1]).await.is_err());
+        send_poll_vote(bob, bob_msg.id, &[1]).await?;
+        alice.recv_msg_trash(&bob.pop_sent_msg().await).await;
+
+        let alice_msg = Message::load_from_db(alice, poll_msg_id).await?;
+        let results = alice_msg.get_poll_results(alice).await?.unwrap();
+        assert_eq!(results.counts, vec![0, 1]);
+        assert!(!results.closed);
+
+        close_poll(alice, poll_msg_id).await?;
+        bob.recv_msg_trash(&alice.pop_sent_msg().await).await;
+        let bob_msg = Message::load_from_db(bob, bob_msg.id).await?;
+        let results = bob_msg.get_poll_results(bob).await?.unwrap();
+        assert!(results.closed);
+        assert_eq!(results.counts, vec![0, 1]);
+        assert!(send_poll_vote(bob, bob_msg.id, &[0]).await.is_err());
+        assert!(close_poll(alice, poll_msg_id).await.is_err());
+
+        reopen_poll(alice, poll_msg_id).await?;
+        bob.recv_msg_trash(&alice.pop_sent_msg().await).await;
+        let bob_msg = Message::load_from_db(bob, bob_msg.id).await?;
+        assert!(!bob_msg.get_poll_results(bob).await?.unwrap().closed);
+        send_poll_vote(bob, bob_msg.id, &[0]).await?;
+        alice.recv_msg_trash(&bob.pop_sent_msg().await).await;
+        let alice_msg = Message::load_from_db(alice, poll_msg_id).await?;
+        let results = alice_msg.get_poll_results(alice).await?.unwrap();
+        assert_eq!(results.counts, vec![1, 0]);
+
+        // Votes are deleted together with the poll.
+        crate::message::delete_for_all(alice, &[poll_msg_id]).await?;
+        assert_eq!(
+            alice
+                .sql
+                .count(
+                    "SELECT COUNT(*) FROM poll_votes WHERE msg_id=?",
+                    (poll_msg_id,)
+                )
+                .await?,
+            0
+        );
+
+        Ok(())
+    }
+}
@@ -0,0 +1,455 @@
+//! # Experimental post-quantum hybrid encryption.
+//!
+//! Enabled with the `pq-hybrid` cargo feature.
+//!
+//! Each device has an ML-KEM-768 key pair.
+//! The encapsulation key is advertised in the `Chat-Pq-Key` header
+//! inside the encrypted part of outgoing messages
+//! and is only accepted from encrypted and signed messages.
+//!
+//! If all recipients of a message have advertised an encapsulation key,
+//! the classic OpenPGP message is additionally encrypted symmetrically with a random message key.
+//! The message key is encapsulated for each recipient
+//! and transmitted in `Pq-Encapsulation-<n>-<m>` armor headers,
+//! so reading the message requires breaking both the X25519 and the ML-KEM encryption.
+//! Otherwise the message is sent as a classic OpenPGP message.
+//!
+//! The decapsulation key is stored in the database,
+//! so it is only shared with other devices set up from a backup.
+//!
+//! The format is experimental and may change incompatibly.
+//! It is not interoperable with the PQC extension of OpenPGP
+//! (<https://datatracker.ietf.org/doc/draft-ietf-openpgp-pqc/>),
+//! which should replace it once supported by rPGP.
+//!
+//! ## Format
+//!
+//! - ML-KEM-768 is used as specified in FIPS 203
+//!   (<https://nvlpubs.nist.gov/nistpubs/FIPS/NIST.FIPS.203.pdf>).
+//! - The outer message is an OpenPGP SKESK/SEIPD message as produced by
+//!   [`pgp::symm_encrypt`] with the lowercase hex encoding of the 32-byte message key as passphrase.
+//! - For each recipient, `ct || (message_key XOR SHA-256(label || ss || ct))` is transmitted,
+//!   where `ct` is the 1088-byte ML-KEM ciphertext, `ss` the 32-byte shared secret
+//!   and `label` the ASCII string `Delta Chat PQ hybrid v1`.
+//! - The base64 encoding of the `n`-th recipient's data is split into chunks of 76 characters,
+//!   the `m`-th chunk is sent in the `Pq-Encapsulation-<n>-<m>` armor header.
+
+use std::collections::{BTreeMap, HashSet};
+use std::io::Cursor;
+
+use anyhow::{anyhow, bail, ensure, Context as _, Result};
+use base64::Engine as _;
+use mailparse::ParsedMail;
+use ml_kem::kem::{Decapsulate, Encapsulate};
+use ml_kem::{Ciphertext, Encoded, EncodedSizeUser, KemCore, MlKem768};
+use rand::{thread_rng, Rng};
+use sha2::{Digest, Sha256};
+
+use crate::contact::{Contact, ContactId, Origin};
+use crate::context::Context;
+use crate::decrypt::get_encrypted_mime;
+use crate::key::{Fingerprint, SignedPublicKey, SignedSecretKey};
+use crate::mimefactory::wrapped_base64_encode;
+use crate::param::Param;
+use crate::peerstate::Peerstate;
+use crate::pgp;
+
+type DecapsulationKey = <MlKem768 as KemCore>::DecapsulationKey;
+type EncapsulationKey = <MlKem768 as KemCore>::EncapsulationKey;
+
+/// Raw config key of the base64-encoded decapsulation key of this device.
+const DECAPSULATION_KEY_CONFIG: &str = "pq_decapsulation_key";
+
+/// Prefix of the armor headers containing the encapsulated message key.
+const ENCAPSULATION_HEADER_PREFIX: &str = "Pq-Encapsulation-";
+
+/// Length of the base64 chunks the encapsulations are split into
+/// to keep armor header lines short.
+const ENCAPSULATION_CHUNK_LEN: usize = 76;
+
+/// Length of the symmetric message key.
+const MESSAGE_KEY_LEN: usize = 32;
+
+/// Domain separation label of the key derivation.
+const KDF_LABEL: &[u8] = b"Delta Chat PQ hybrid v1";
+
+fn decode_base64(data: &str) -> Result<Vec<u8>> {
+    Ok(base64::engine::general_purpose::STANDARD
+        .decode(data.split_ascii_whitespace().collect::<String>())?)
+}
+
+fn parse_decapsulation_key(data: &[u8]) -> Result<DecapsulationKey> {
+    let encoded = Encoded::<DecapsulationKey>::try_from(data)
+        .map_err(|_| anyhow!("Invalid ML-KEM decapsulation key length {}", data.len()))?;
+    Ok(DecapsulationKey::from_bytes(&encoded))
+}
+
+fn parse_encapsulation_key(data: &[u8]) -> Result<EncapsulationKey> {
+    let encoded = Encoded::<EncapsulationKey>::try_from(data)
+        .map_err(|_| anyhow!("Invalid ML-KEM encapsulation key length {}", data.len()))?;
+    Ok(EncapsulationKey::from_bytes(&encoded))
+}
+
+/// Loads the decapsulation key of this device, if any.
+async fn load_decapsulation_key(context: &Context) -> Result<Option<DecapsulationKey>> {
+    match context.sql.get_raw_config(DECAPSULATION_KEY_CONFIG).await? {
+        Some(key) => Ok(Some(parse_decapsulation_key(&decode_base64(&key)?)?)),
+        None => Ok(None),
+    }
+}
+
+/// Loads the decapsulation key of this device, generating it if needed.
+async fn load_or_generate_decapsulation_key(context: &Context) -> Result<DecapsulationKey> {
+    let _guard = context.generating_key_mutex.lock().await;
+    if let Some(key) = load_decapsulation_key(context).await? {
+        return Ok(key);
+    }
+    let (key, _) = MlKem768::generate(&mut thread_rng());
+    context
+        .sql
+        .set_raw_config(
+            DECAPSULATION_KEY_CONFIG,
+            Some(&base64::engine::general_purpose::STANDARD.encode(key.as_bytes())),
+        )
+        .await?;
+    info!(
+        context,
+        "Generated ML-KEM key for post-quantum hybrid encryption."
+    );
+    Ok(key)
+}
+
+/// Returns the value of the `Chat-Pq-Key` header to send.
+pub(crate) async fn render_pq_key_header(context: &Context) -> Result<String> {
+    let key = load_or_generate_decapsulation_key(context).await?;
+    Ok(wrapped_base64_encode(&key.encapsulation_key().as_bytes()))
+}
+
+/// Stores the encapsulation key received in the `Chat-Pq-Key` header from `contact_id`
+/// in a message signed with the OpenPGP key `signer`.
+///
+/// The PQ key is only used as long as `signer` is the key of the contact,
+/// so it is dropped together with the OpenPGP key if the contact's key changes.
+pub(crate) async fn receive_pq_key(
+    context: &Context,
+    contact_id: ContactId,
+    header_value: &str,
+    signer: &Fingerprint,
+) -> Result<()> {
+    let key = decode_base64(header_value).context("Failed to decode PQ key")?;
+    parse_encapsulation_key(&key)?;
+    let key = base64::engine::general_purpose::STANDARD.encode(key);
+    let signer = signer.hex();
+
+    let mut contact = Contact::get_by_id(context, contact_id).await?;
+    if contact.param.get(Param::PqKey) != Some(key.as_str())
+        || contact.param.get(Param::PqKeyFingerprint) != Some(signer.as_str())
+    {
+        contact.param.set(Param::PqKey, key);
+        contact.param.set(Param::PqKeyFingerprint, signer);
+        contact.update_param(context).await?;
+        info!(context, "Received new PQ key of {contact_id}.");
+    }
+    Ok(())
+}
+
+/// Returns the encapsulation key of the recipient with the `peerstate`,
+/// if the key was advertised in a message signed with the current OpenPGP key of the recipient.
+fn get_encapsulation_key(
+    contact: &Contact,
+    peerstate: &Peerstate,
+) -> Result<Option<EncapsulationKey>> {
+    let (Some(key), Some(signer)) = (
+        contact.param.get(Param::PqKey),
+        contact.param.get(Param::PqKeyFingerprint),
+    ) else {
+        return Ok(None);
+    };
+    let signer_is_current = [
+        &peerstate.public_key_fingerprint,
+        &peerstate.verified_key_fingerprint,
+    ]
+    .into_iter()
+    .flatten()
+    .any(|fingerprint| fingerprint.hex() == signer);
+    if !signer_is_current {
+        return Ok(None);
+    }
+    Ok(Some(parse_encapsulation_key(&decode_base64(key)?)?))
+}
+
+/// Derives the key encrypting the message key from the ML-KEM shared secret.
+fn kdf(shared_secret: &[u8], ciphertext: &[u8]) -> [u8; MESSAGE_KEY_LEN] {
+    Sha256::new()
+        .chain_update(KDF_LABEL)
+        .chain_update(shared_secret)
+        .chain_update(ciphertext)
+        .finalize()
+        .into()
+}
+
+fn xor(a: &[u8; MESSAGE_KEY_LEN], b: &[u8; MESSAGE_KEY_LEN]) -> [u8; MESSAGE_KEY_LEN] {
+    let mut res = *a;
+    for (x, y) in res.iter_mut().zip(b) {
+        *x ^= y;
+    }
+    res
+}
+
+/// Encapsulates `message_key` for the owner of `key`.
+///
+/// Returns the ML-KEM ciphertext followed by the encrypted message key.
+fn encapsulate(key: &EncapsulationKey, message_key: &[u8; MESSAGE_KEY_LEN]) -> Result<Vec<u8>> {
+    let (ciphertext, shared_secret) = key
+        .encapsulate(&mut thread_rng())
+        .map_err(|_| anyhow!("ML-KEM encapsulation failed"))?;
+    let mut res = ciphertext.to_vec();
+    res.extend_from_slice(&xor(message_key, &kdf(&shared_secret, &ciphertext)));
+    Ok(res)
+}
+
+/// Recovers the message key from an encapsulation created by [`encapsulate`].
+///
+/// Because of implicit rejection in ML-KEM,
+/// a wrong key results in a random message key rather than an error.
+fn decapsulate(key: &DecapsulationKey, data: &[u8]) -> Result<[u8; MESSAGE_KEY_LEN]> {
+    ensure!(
+        data.len() > MESSAGE_KEY_LEN,
+        "PQ encapsulation is too short"
+    );
+    let (ciphertext, encrypted_key) = data.split_at(data.len() - MESSAGE_KEY_LEN);
+    let ciphertext = Ciphertext::<MlKem768>::try_from(ciphertext)
+        .map_err(|_| anyhow!("Invalid ML-KEM ciphertext length"))?;
+    let shared_secret = key
+        .decapsulate(&ciphertext)
+        .map_err(|_| anyhow!("ML-KEM decapsulation failed"))?;
+    let encrypted_key: &[u8; MESSAGE_KEY_LEN] = encrypted_key.try_into()?;
+    Ok(xor(encrypted_key, &kdf(&shared_secret, &ciphertext)))
+}
+
+/// Encrypts the armored OpenPGP message `ctext` with a random message key
+/// encapsulated for each of `keys`.
+async fn wrap(ctext: &str, keys: &[EncapsulationKey]) -> Result<String> {
+    let mut message_key = [0u8; MESSAGE_KEY_LEN];
+    thread_rng().fill(&mut message_key);
+    let outer = pgp::symm_encrypt(&hex::encode(message_key), ctext.as_bytes()).await?;
+
+    let mut replacement = "-----BEGIN PGP MESSAGE-----".to_string();
+    for (i, key) in keys.iter().enumerate() {
+        let encapsulation =
+            base64::engine::general_purpose::STANDARD.encode(encapsulate(key, &message_key)?);
+        for (j, chunk) in encapsulation
+            .as_bytes()
+            .chunks(ENCAPSULATION_CHUNK_LEN)
+            .enumerate()
+        {
+            let chunk = std::str::from_utf8(chunk)?;
+            replacement += &format!("\r\n{ENCAPSULATION_HEADER_PREFIX}{i}-{j}: {chunk}");
+        }
+    }
+    Ok(outer.replacen("-----BEGIN PGP MESSAGE-----", &replacement, 1))
+}
+
+/// Collects the encapsulations from the armor headers of a hybrid-encrypted message.
+fn parse_encapsulations(headers: &BTreeMap<String, String>) -> Result<Vec<Vec<u8>>> {
+    let mut chunks: BTreeMap<(usize, usize), &str> = BTreeMap::new();
+    for (name, value) in headers {
+        let Some(index) = name
+            .get(..ENCAPSULATION_HEADER_PREFIX.len())
+            .filter(|prefix| prefix.eq_ignore_ascii_case(ENCAPSULATION_HEADER_PREFIX))
+            .and_then(|_| name.get(ENCAPSULATION_HEADER_PREFIX.len()..))
+        else {
+            continue;
+        };
+        let (i, j) = index
+            .split_once('-')
+            .with_context(|| format!("Invalid PQ encapsulation header {name:?}"))?;
+        chunks.insert((i.parse()?, j.parse()?), value);
+    }
+
+    let mut encapsulations: BTreeMap<usize, String> = BTreeMap::new();
+    for ((i, _), chunk) in chunks {
+        encapsulations.entry(i).or_default().push_str(chunk);
+    }
+    encapsulations
+        .values()
+        .map(|encapsulation| decode_base64(encapsulation))
+        .collect()
+}
+
+/// Encrypts `ctext` additionally with ML-KEM
+/// if all recipients of the message have advertised an encapsulation key.
+///
+/// Otherwise returns `ctext` unchanged.
+pub(crate) async fn maybe_wrap(
+    context: &Context,
+    ctext: String,
+    peerstates: &[(Option<Peerstate>, String)],
+) -> Result<String> {
+    let mut keys = Vec::new();
+    for (peerstate, addr) in peerstates {
+        if context.is_self_addr(addr).await? {
+            continue;
+        }
+        let Some(peerstate) = peerstate else {
+            return Ok(ctext);
+        };
+        let Some(contact_id) = Contact::lookup_id_by_addr(context, addr, Origin::Unknown).await?
+        else {
+            return Ok(ctext);
+        };
+        let contact = Contact::get_by_id(context, contact_id).await?;
+        let Some(key) = get_encapsulation_key(&contact, peerstate)? else {
+            return Ok(ctext);
+        };
+        keys.push(key);
+    }
+    let own_key = load_or_generate_decapsulation_key(context).await?;
+    keys.push(own_key.encapsulation_key().clone());
+    wrap(&ctext, &keys).await
+}
+
+/// Returns true if the armored message `ctext` is hybrid-encrypted.
+pub(crate) fn is_wrapped(ctext: &str) -> bool {
+    ctext.contains(ENCAPSULATION_HEADER_PREFIX)
+}
+
+/// Tries to decrypt a hybrid-encrypted message.
+///
+/// Returns `None` if the message is not hybrid-encrypted,
+/// so it should be decrypted with [`crate::decrypt::try_decrypt`].
+pub(crate) async fn try_decrypt(
+    context: &Context,
+    mail: &ParsedMail<'_>,
+    private_keyring: &[SignedSecretKey],
+    public_keyring_for_validate: &[SignedPublicKey],
+) -> Result<Option<(Vec<u8>, HashSet<Fingerprint>)>> {
+    let Some(encrypted_data_part) = get_encrypted_mime(mail) else {
+        return Ok(None);
+    };
+    let data = encrypted_data_part.get_body_raw()?;
+    let Ok((_, headers, _)) = pgp::split_armored_data(&data) else {
+        return Ok(None);
+    };
+    let encapsulations = parse_encapsulations(&headers)?;
+    if encapsulations.is_empty() {
+        return Ok(None);
+    }
+
+    let key = load_decapsulation_key(context)
+        .await?
+        .context("Message is PQ hybrid-encrypted, but there is no PQ key")?;
+    for encapsulation in encapsulations {
+        let Ok(message_key) = decapsulate(&key, &encapsulation) else {
+            continue;
+        };
+        let Ok(inner) =
+            pgp::symm_decrypt(&hex::encode(message_key), Cursor::new(data.clone())).await
+        else {
+            continue;
+        };
+        let (plain, signatures) = tokio::task::block_in_place(|| {
+            pgp::pk_decrypt(inner, private_keyring, public_keyring_for_validate)
+        })?;
+        return Ok(Some((plain, signatures)));
+    }
+    bail!("Message is PQ hybrid-encrypted, but not for this device")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_utils::TestContextManager;
+
+    #[test]
+    fn test_encapsulate() -> Result<()> {
+        let (dk, ek) = MlKem768::generate(&mut thread_rng());
+        let (other_dk, _) = MlKem768::generate(&mut thread_rng());
+        let message_key = [42u8; MESSAGE_KEY_LEN];
+
+        let encapsulation = encapsulate(&ek, &message_key)?;
+        assert_eq!(decapsulate(&dk, &encapsulation)?, message_key);
+        assert_ne!(decapsulate(&other_dk, &encapsulation)?, message_key);
+        assert!(decapsulate(&dk, &encapsulation[..MESSAGE_KEY_LEN]).is_err());
+
+        let dk = parse_decapsulation_key(&dk.as_bytes())?;
+        assert_eq!(decapsulate(&dk, &encapsulation)?, message_key);
+        Ok(())
+    }
+
+    #[test]
+    fn test_kdf_vector() {
+        let shared_secret = [1u8; 32];
+        let ciphertext = [2u8; 1088];
+        assert_eq!(
+            hex::encode(kdf(&shared_secret, &ciphertext)),
+            "6d887023d02b08cd6634f06b1f41a15065391053c71d4f5a4ad6782154d93f2b"
+        );
+        assert_eq!(
+            hex::encode(xor(
+                &[42u8; MESSAGE_KEY_LEN],
+                &kdf(&shared_secret, &ciphertext)
+            )),
+            "47a25a09fa0122e74c1eda41356b8b7a4f133a79ed37657060fc520b7ef31501"
+        );
+    }
+
+    #[test]
+    fn test_parse_encapsulations() -> Result<()> {
+        let mut headers = BTreeMap::new();
+        headers.insert("Pq-Encapsulation-1-0".to_string(), "BQY=".to_string());
+        headers.insert("Pq-Encapsulation-0-1".to_string(), "BA==".to_string());
+        headers.insert("pq-encapsulation-0-0".to_string(), "AQID".to_string());
+        headers.insert("Version".to_string(), "1".to_string());
+        assert_eq!(
+            parse_encapsulations(&headers)?,
+            vec![vec![1, 2, 3, 4], vec![5, 6]]
+        );
+        Ok(())
+    }
+
+    #[tokio::test(flavor = "multi_thread", worker_threads = 2)]
+    async fn test_pq_hybrid_encryption() -> Result<()> {
+        let mut tcm = TestContextManager::new();
+        let alice = &tcm.alice().await;
+        let bob = &tcm.bob().await;
+
+        // The first message is not encrypted, so no PQ key is sent.
+        tcm.send_recv_accept(alice, bob, "Hi!").await;
+        let bob_contact = alice.add_or_lookup_contact(bob).await;
+        assert!(!bob_contact.param.exists(Param::PqKey));
+
+        // Bob's reply is encrypted and advertises his PQ key,
+        // but is not hybrid-encrypted because Alice's PQ key is unknown.
+        let bob_chat_id = bob.create_chat(alice).await.id;
+        let sent = bob.send_text(bob_chat_id, "Hello!").await;
+        assert!(!sent.payload().contains(ENCAPSULATION_HEADER_PREFIX));
+        alice.recv_msg(&sent).await;
+        let bob_contact = alice.add_or_lookup_contact(bob).await;
+        assert!(bob_contact.param.exists(Param::PqKey));
+
+        let alice_chat_id = alice.create_chat(bob).await.id;
+        let sent = alice.send_text(alice_chat_id, "Hybrid").await;
+        assert!(sent.payload().contains("Pq-Encapsulation-0-0: "));
+        assert!(sent.payload().contains("Pq-Encapsulation-1-0: "));
+        let msg = bob.recv_msg(&sent).await;
+        assert_eq!(msg.get_text(), "Hybrid");
+        assert!(msg.get_showpadlock());
+
+        let sent = bob.send_text(bob_chat_id, "Hybrid too").await;
+        assert!(sent.payload().contains("Pq-Encapsulation-0-0: "));
+        let msg = alice.recv_msg(&sent).await;
+        assert_eq!(msg.get_text(), "Hybrid too");
+        assert!(msg.get_showpadlock());
+
+        // The PQ key is not used with another OpenPGP key than the one that signed it.
+        let mut bob_contact = alice.add_or_lookup_contact(bob).await;
+        bob_contact
+            .param
+            .set(Param::PqKeyFingerprint, Fingerprint::new(vec![0; 20]).hex());
+        bob_contact.update_param(alice).await?;
+        let sent = alice.send_text(alice_chat_id, "Classic").await;
+        assert!(!sent.payload().contains(ENCAPSULATION_HEADER_PREFIX));
+        Ok(())
+    }
+}
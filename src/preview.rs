@@ -0,0 +1,467 @@
+//! # Preview images of videos and PDF files.
+//!
+//! Previews are small JPEG images stored as separate blobs next to the attachment,
+//! see [`crate::message::Message::get_preview_blob`],
+//! so that UIs do not need to decode the full file to show the message in the chat.
+//!
+//! The core does not decode videos or render PDF pages.
+//! Instead, images embedded in the files are used:
+//! the cover art of MP4/MOV videos and the page thumbnail of PDF files.
+//! If there is no embedded image, no preview is created,
+//! this is the case for most videos and PDF files.
+
+use std::io::{Cursor, SeekFrom};
+use std::path::Path;
+
+use anyhow::Result;
+use image::codecs::jpeg::JpegEncoder;
+use image::{ImageReader, Limits};
+use tokio::io::{AsyncReadExt, AsyncSeekExt};
+
+use crate::blob::BlobObject;
+use crate::context::Context;
+use crate::log::LogExt;
+use crate::message::Viewtype;
+
+/// Maximum width and height of previews.
+const PREVIEW_SIZE: u32 = 320;
+
+/// JPEG quality of previews.
+const PREVIEW_QUALITY: u8 = 75;
+
+/// Maximum width and height of embedded images to decode.
+const EMBEDDED_IMAGE_SIZE_MAX: u32 = 4096;
+
+/// Maximum memory to allocate for decoding an embedded image.
+const EMBEDDED_IMAGE_ALLOC_MAX: u64 = 128 * 1024 * 1024;
+
+/// Maximum size of the `moov` box read from video files.
+const MOOV_SIZE_MAX: u64 = 32 * 1024 * 1024;
+
+/// Maximum size of the thumbnail object read from PDF files.
+const PDF_THUMB_SIZE_MAX: u64 = 4 * 1024 * 1024;
+
+/// Size of the chunks in which PDF files are searched.
+const PDF_CHUNK_SIZE: u64 = 64 * 1024;
+
+/// Returns true if a preview may be created for an attachment of this type.
+pub(crate) fn wants_preview(viewtype: Viewtype, mime: Option<&str>) -> bool {
+    viewtype == Viewtype::Video || mime == Some("application/pdf")
+}
+
+/// Creates a preview of the video or PDF file `data` and returns the blob name of the preview.
+///
+/// Returns `None` if there is no embedded image or the preview cannot be created.
+pub(crate) async fn create_preview(
+    context: &Context,
+    viewtype: Viewtype,
+    mime: Option<&str>,
+    data: &[u8],
+) -> Option<String> {
+    if !wants_preview(viewtype, mime) {
+        return None;
+    }
+    let image = if viewtype == Viewtype::Video {
+        mp4_cover(data)
+    } else {
+        pdf_thumbnail(data)
+    }?;
+    store_preview(context, image).await
+}
+
+/// Creates a preview of the video or PDF file at `path`, see [`create_preview`].
+///
+/// Of videos, only the `moov` box containing the metadata is read,
+/// PDF files are searched in chunks and only the thumbnail is kept in memory.
+pub(crate) async fn create_preview_from_file(
+    context: &Context,
+    viewtype: Viewtype,
+    mime: Option<&str>,
+    path: &Path,
+) -> Option<String> {
+    if !wants_preview(viewtype, mime) {
+        return None;
+    }
+    if viewtype == Viewtype::Video {
+        let moov = read_moov(path).await.log_err(context).ok()??;
+        store_preview(context, moov_cover(&moov)?).await
+    } else {
+        let thumbnail = read_pdf_thumbnail(path).await.log_err(context).ok()??;
+        store_preview(context, &thumbnail).await
+    }
+}
+
+/// Renders the embedded `image` as preview and returns the blob name of the preview.
+async fn store_preview(context: &Context, image: &[u8]) -> Option<String> {
+    let preview = tokio::task::block_in_place(|| render_preview(image))
+        .log_err(context)
+        .ok()?;
+    let blob = BlobObject::create(context, "preview.jpg", &preview)
+        .await
+        .log_err(context)
+        .ok()?;
+    Some(blob.as_name().to_string())
+}
+
+/// Scales down the image and encodes it as JPEG.
+///
+/// The image comes from an untrusted file, so decoding is limited in size and memory.
+fn render_preview(image: &[u8]) -> Result<Vec<u8>> {
+    let mut limits = Limits::default();
+    limits.max_image_width = Some(EMBEDDED_IMAGE_SIZE_MAX);
+    limits.max_image_height = Some(EMBEDDED_IMAGE_SIZE_MAX);
+    limits.max_alloc = Some(EMBEDDED_IMAGE_ALLOC_MAX);
+    let mut reader = ImageReader::new(Cursor::new(image)).with_guessed_format()?;
+    reader.limits(limits);
+    let mut img = reader.decode()?;
+    if img.width() > PREVIEW_SIZE || img.height() > PREVIEW_SIZE {
+        img = img.thumbnail(PREVIEW_SIZE, PREVIEW_SIZE);
+    }
+    let mut preview = Vec::new();
+    let encoder = JpegEncoder::new_with_quality(&mut preview, PREVIEW_QUALITY);
+    img.into_rgb8().write_with_encoder(encoder)?;
+    Ok(preview)
+}
+
+/// Returns the position of `needle` in `haystack`.
+fn find(haystack: &[u8], needle: &[u8]) -> Option<usize> {
+    haystack
+        .windows(needle.len())
+        .position(|window| window == needle)
+}
+
+/// Returns the payload of the first ISO-BMFF box of type `typ` in `data`.
+fn find_box<'a>(data: &'a [u8], typ: &[u8; 4]) -> Option<&'a [u8]> {
+    let mut pos = 0;
+    while let Some(header) = data.get(pos..pos + 8) {
+        let (size_bytes, box_type) = header.split_at(4);
+        let (size, header_len) = match u32::from_be_bytes(size_bytes.try_into().ok()?) {
+            0 => (data.len() - pos, 8),
+            1 => {
+                let size = data.get(pos + 8..pos + 16)?;
+                (
+                    usize::try_from(u64::from_be_bytes(size.try_into().ok()?)).ok()?,
+                    16,
+                )
+            }
+            size => (usize::try_from(size).ok()?, 8),
+        };
+        let end = pos.checked_add(size)?;
+        if size < header_len || end > data.len() {
+            return None;
+        }
+        if box_type == typ {
+            return data.get(pos + header_len..end);
+        }
+        pos = end;
+    }
+    None
+}
+
+/// Reads the payload of the top-level `moov` box of the MP4 or MOV video at `path`.
+///
+/// Other boxes, especially the media data, are skipped without reading them.
+async fn read_moov(path: &Path) -> Result<Option<Vec<u8>>> {
+    let mut file = tokio::fs::File::open(path).await?;
+    let len = file.metadata().await?.len();
+    let mut pos = 0;
+    while pos + 8 <= len {
+        file.seek(SeekFrom::Start(pos)).await?;
+        let mut size = [0; 4];
+        let mut box_type = [0; 4];
+        file.read_exact(&mut size).await?;
+        file.read_exact(&mut box_type).await?;
+        let (size, header_len) = match u32::from_be_bytes(size) {
+            0 => (len - pos, 8),
+            1 => {
+                let mut size = [0; 8];
+                file.read_exact(&mut size).await?;
+                (u64::from_be_bytes(size), 16)
+            }
+            size => (u64::from(size), 8),
+        };
+        if size < header_len || size > len - pos {
+            return Ok(None);
+        }
+        if &box_type == b"moov" {
+            if size > MOOV_SIZE_MAX {
+                return Ok(None);
+            }
+            let mut moov = vec![0; usize::try_from(size - header_len)?];
+            file.read_exact(&mut moov).await?;
+            return Ok(Some(moov));
+        }
+        pos += size;
+    }
+    Ok(None)
+}
+
+/// Returns the cover art stored in the iTunes-style metadata of an MP4 or MOV video.
+fn mp4_cover(data: &[u8]) -> Option<&[u8]> {
+    moov_cover(find_box(data, b"moov")?)
+}
+
+/// Returns the cover art stored in the payload of a `moov` box.
+fn moov_cover(moov: &[u8]) -> Option<&[u8]> {
+    let udta = find_box(moov, b"udta")?;
+    let meta = find_box(udta, b"meta")?;
+    // In MP4 files, `meta` is a full box starting with version and flags,
+    // in QuickTime files it is not.
+    let meta = match meta.get(4..8) {
+        Some(b"hdlr") => meta,
+        _ => meta.get(4..)?,
+    };
+    let covr = find_box(find_box(meta, b"ilst")?, b"covr")?;
+    // The `data` box starts with the type and locale indicators.
+    find_box(covr, b"data")?.get(8..)
+}
+
+/// Returns the JPEG-encoded thumbnail of the first page of a PDF file that has one.
+fn pdf_thumbnail(data: &[u8]) -> Option<&[u8]> {
+    let start = find(data, b"/Thumb")? + b"/Thumb".len();
+    let header = pdf_thumb_header(data.get(start..data.len().min(start + 32))?)?;
+    let mut pos = 0;
+    let obj = loop {
+        let start = pos + find(data.get(pos..)?, header.as_bytes())?;
+        let preceding = start.checked_sub(1).and_then(|i| data.get(i));
+        if !preceding.is_some_and(u8::is_ascii_digit) {
+            break data.get(start..)?;
+        }
+        pos = start + header.len();
+    };
+    pdf_thumb_stream(obj)
+}
+
+/// Returns the header of the object referenced after `/Thumb`, e.g. `12 0 obj`.
+fn pdf_thumb_header(reference: &[u8]) -> Option<String> {
+    let reference = String::from_utf8_lossy(reference);
+    let mut tokens = reference.split_whitespace();
+    let num: u32 = tokens.next()?.parse().ok()?;
+    let generation: u32 = tokens.next()?.parse().ok()?;
+    if !tokens.next()?.starts_with('R') {
+        return None;
+    }
+    Some(format!("{num} {generation} obj"))
+}
+
+/// Returns the JPEG stream of the thumbnail object `obj` starting at its header.
+fn pdf_thumb_stream(obj: &[u8]) -> Option<&[u8]> {
+    let obj = obj.get(..find(obj, b"endobj")?)?;
+    let stream_start = find(obj, b"stream")?;
+    if find(obj.get(..stream_start)?, b"/DCTDecode").is_none() {
+        return None;
+    }
+    let stream = obj.get(stream_start + b"stream".len()..)?;
+    let stream = stream
+        .strip_prefix(b"\r\n")
+        .or_else(|| stream.strip_prefix(b"\n"))?;
+    stream.get(..find(stream, b"endstream")?)
+}
+
+/// Reads the thumbnail of the PDF file at `path`, see [`pdf_thumbnail`].
+///
+/// The file is searched in chunks, only the thumbnail object is read completely.
+async fn read_pdf_thumbnail(path: &Path) -> Result<Option<Vec<u8>>> {
+    let mut file = tokio::fs::File::open(path).await?;
+    let Some(start) = find_in_file(&mut file, b"/Thumb", 0).await? else {
+        return Ok(None);
+    };
+    let reference = read_at(&mut file, start + b"/Thumb".len() as u64, 32).await?;
+    let Some(header) = pdf_thumb_header(&reference) else {
+        return Ok(None);
+    };
+    let mut pos = 0;
+    let start = loop {
+        let Some(start) = find_in_file(&mut file, header.as_bytes(), pos).await? else {
+            return Ok(None);
+        };
+        let preceding = match start.checked_sub(1) {
+            Some(i) => read_at(&mut file, i, 1).await?,
+            None => Vec::new(),
+        };
+        if !preceding.first().is_some_and(u8::is_ascii_digit) {
+            break start;
+        }
+        pos = start + header.len() as u64;
+    };
+    let obj = read_at(&mut file, start, PDF_THUMB_SIZE_MAX).await?;
+    Ok(pdf_thumb_stream(&obj).map(|stream| stream.to_vec()))
+}
+
+/// Reads up to `len` bytes of `file` starting at `pos`.
+async fn read_at(file: &mut tokio::fs::File, pos: u64, len: u64) -> Result<Vec<u8>> {
+    file.seek(SeekFrom::Start(pos)).await?;
+    let mut buf = Vec::new();
+    file.take(len).read_to_end(&mut buf).await?;
+    Ok(buf)
+}
+
+/// Returns the position of `needle` in `file`, searching from `pos` on.
+async fn find_in_file(
+    file: &mut tokio::fs::File,
+    needle: &[u8],
+    mut pos: u64,
+) -> Result<Option<u64>> {
+    loop {
+        let chunk = read_at(file, pos, PDF_CHUNK_SIZE).await?;
+        if let Some(i) = find(&chunk, needle) {
+            return Ok(Some(pos + i as u64));
+        }
+        if (chunk.len() as u64) < PDF_CHUNK_SIZE {
+            return Ok(None);
+        }
+        // Chunks overlap so that needles crossing chunk boundaries are found.
+        pos += PDF_CHUNK_SIZE - needle.len() as u64 + 1;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::chat::send_msg;
+    use crate::message::Message;
+    use crate::test_utils::TestContextManager;
+
+    /// Returns an ISO-BMFF box.
+    fn mp4_box(typ: &[u8; 4], payload: &[u8]) -> Vec<u8> {
+        let mut data = u32::try_from(payload.len() + 8)
+            .unwrap()
+            .to_be_bytes()
+            .to_vec();
+        data.extend_from_slice(typ);
+        data.extend_from_slice(payload);
+        data
+    }
+
+    /// Returns a minimal MP4 file with the cover art `image`.
+    fn mp4_with_cover(image: &[u8]) -> Vec<u8> {
+        let mut cover = vec![0, 0, 0, 13, 0, 0, 0, 0];
+        cover.extend_from_slice(image);
+        let ilst = mp4_box(b"ilst", &mp4_box(b"covr", &mp4_box(b"data", &cover)));
+        let mut meta = vec![0; 4];
+        meta.extend(ilst);
+        let moov = mp4_box(
+            b"moov",
+            &[
+                mp4_box(b"mvhd", &[0; 100]),
+                mp4_box(b"udta", &mp4_box(b"meta", &meta)),
+            ]
+            .concat(),
+        );
+        [
+            mp4_box(b"ftyp", b"isom\0\0\0\0"),
+            moov,
+            mp4_box(b"mdat", &[0; 64]),
+        ]
+        .concat()
+    }
+
+    #[test]
+    fn test_mp4_cover() {
+        let image = b"\xff\xd8cover";
+        assert_eq!(mp4_cover(&mp4_with_cover(image)), Some(&image[..]));
+        assert_eq!(mp4_cover(&mp4_box(b"moov", &[0; 16])), None);
+        assert_eq!(mp4_cover(b"\0\0\0\xffmoov"), None);
+    }
+
+    #[tokio::test(flavor = "multi_thread", worker_threads = 2)]
+    async fn test_read_moov() -> Result<()> {
+        let dir = tempfile::tempdir()?;
+        let path = dir.path().join("clip.mp4");
+        let image = b"\xff\xd8cover";
+        tokio::fs::write(&path, mp4_with_cover(image)).await?;
+        let moov = read_moov(&path).await?.unwrap();
+        assert_eq!(moov_cover(&moov), Some(&image[..]));
+
+        tokio::fs::write(&path, mp4_box(b"ftyp", b"isom\0\0\0\0")).await?;
+        assert_eq!(read_moov(&path).await?, None);
+        tokio::fs::write(&path, b"\0\0\0\xffmoov").await?;
+        assert_eq!(read_moov(&path).await?, None);
+        Ok(())
+    }
+
+    #[test]
+    fn test_render_preview_limits() {
+        // A PNG header claiming a huge image is rejected before allocating it.
+        let mut png = b"\x89PNG\r\n\x1a\n\0\0\0\x0dIHDR".to_vec();
+        png.extend_from_slice(&100_000u32.to_be_bytes());
+        png.extend_from_slice(&100_000u32.to_be_bytes());
+        png.extend_from_slice(b"\x08\x02\0\0\0\x27\x30\x9c\x9f");
+        let err = render_preview(&png).unwrap_err();
+        assert!(matches!(
+            err.downcast_ref::<image::ImageError>(),
+            Some(image::ImageError::Limits(_))
+        ));
+    }
+
+    #[test]
+    fn test_pdf_thumbnail() {
+        let pdf = b"%PDF-1.4\n\
+                    3 0 obj\n<< /Type /Page /Thumb 12 0 R >>\nendobj\n\
+                    112 0 obj\n<< /Length 3 >>\nstream\nbad\nendstream\nendobj\n\
+                    12 0 obj\n<< /Filter /DCTDecode /Length 5 >>\nstream\r\n\xff\xd8jpg\nendstream\nendobj\n";
+        assert_eq!(pdf_thumbnail(pdf), Some(&b"\xff\xd8jpg\n"[..]));
+        assert_eq!(pdf_thumbnail(b"%PDF-1.4\n/Thumb 12 0"), None);
+        assert_eq!(pdf_thumbnail(b"%PDF-1.4\n"), None);
+    }
+
+    #[tokio::test(flavor = "multi_thread", worker_threads = 2)]
+    async fn test_read_pdf_thumbnail() -> Result<()> {
+        let dir = tempfile::tempdir()?;
+        let path = dir.path().join("doc.pdf");
+
+        // `/Thumb` crosses the boundary of the first chunk.
+        let padding = PDF_CHUNK_SIZE as usize - 26;
+        let mut pdf = b"%PDF-1.4\n".to_vec();
+        pdf.resize(padding, b' ');
+        pdf.extend_from_slice(b"3 0 obj\n<< /Type /Page /Thumb 12 0 R >>\nendobj\n");
+        pdf.resize(padding + PDF_CHUNK_SIZE as usize, b' ');
+        pdf.extend_from_slice(
+            b"12 0 obj\n<< /Filter /DCTDecode /Length 5 >>\nstream\n\xff\xd8jpg\nendstream\nendobj\n",
+        );
+        tokio::fs::write(&path, &pdf).await?;
+        assert_eq!(
+            read_pdf_thumbnail(&path).await?,
+            Some(b"\xff\xd8jpg\n".to_vec())
+        );
+
+        tokio::fs::write(&path, b"%PDF-1.4\n/Thumb 12 0 R\n").await?;
+        assert_eq!(read_pdf_thumbnail(&path).await?, None);
+        tokio::fs::write(&path, b"%PDF-1.4\n").await?;
+        assert_eq!(read_pdf_thumbnail(&path).await?, None);
+        Ok(())
+    }
+
+    #[tokio::test(flavor = "multi_thread", worker_threads = 2)]
+    async fn test_video_preview() -> Result<()> {
+        let mut tcm = TestContextManager::new();
+        let alice = &tcm.alice().await;
+        let bob = &tcm.bob().await;
+        let chat_id = alice.create_chat(bob).await.id;
+
+        let file = alice.get_blobdir().join("clip.mp4");
+        let cover = include_bytes!("../test-data/image/avatar1000x1000.jpg");
+        tokio::fs::write(&file, mp4_with_cover(cover)).await?;
+        let mut msg = Message::new(Viewtype::Video);
+        msg.set_file(file.to_str().unwrap(), None);
+        send_msg(alice, chat_id, &mut msg).await?;
+        let preview = msg.get_preview_blob(alice).unwrap();
+        let img = image::open(preview)?;
+        assert_eq!((img.width(), img.height()), (PREVIEW_SIZE, PREVIEW_SIZE));
+
+        let sent = alice.pop_sent_msg().await;
+        let msg = bob.recv_msg(&sent).await;
+        assert_eq!(msg.get_viewtype(), Viewtype::Video);
+        assert!(msg.get_preview_blob(bob).unwrap().exists());
+
+        // Without embedded image, there is no preview.
+        let file = alice.get_blobdir().join("plain.mp4");
+        tokio::fs::write(&file, mp4_box(b"ftyp", b"isom\0\0\0\0")).await?;
+        let mut msg = Message::new(Viewtype::Video);
+        msg.set_file(file.to_str().unwrap(), None);
+        send_msg(alice, chat_id, &mut msg).await?;
+        assert_eq!(msg.get_preview_blob(alice), None);
+
+        Ok(())
+    }
+}
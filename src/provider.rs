@@ -1,6 +1,7 @@
 //! [Provider database](https://providers.delta.chat/) module.
 
 mod data;
+pub mod overrides;
 
 use anyhow::Result;
 use deltachat_contact_tools::EmailAddress;
@@ -66,7 +67,7 @@ pub enum UsernamePattern {
 }
 
 /// Type of OAuth 2 authorization.
-#[derive(Debug, PartialEq, Eq)]
+#[derive(Debug, PartialEq, Eq, Clone, Copy)]
 #[repr(u8)]
 pub enum Oauth2Authorizer {
     /// Yandex.
@@ -148,6 +149,9 @@ pub struct ProviderOptions {
 
     /// Move messages to the Trash folder instead of marking them "\Deleted".
     pub delete_to_trash: bool,
+
+    /// Address accepting abuse reports, which may be sent unencrypted.
+    pub abuse_report_addr: Option<&'static str>,
 }
 
 impl ProviderOptions {
@@ -156,6 +160,7 @@ const fn new() -> Self {
             strict_tls: true,
             max_smtp_rcpt_to: None,
             delete_to_trash: false,
+            abuse_report_addr: None,
         }
     }
 }
@@ -200,7 +205,7 @@ pub async fn get_provider_info(
     domain: &str,
     skip_mx: bool,
 ) -> Option<&'static Provider> {
-    if let Some(provider) = get_provider_by_domain(domain) {
+    if let Some(provider) = get_provider_by_domain(context, domain).await {
         return Some(provider);
     }
 
@@ -214,18 +219,25 @@ pub async fn get_provider_info(
 }
 
 /// Finds a provider in offline database based on domain.
-pub fn get_provider_by_domain(domain: &str) -> Option<&'static Provider> {
+///
+/// [Overrides](overrides) of the context take precedence over the compiled-in database.
+pub async fn get_provider_by_domain(context: &Context, domain: &str) -> Option<&'static Provider> {
     let domain = domain.to_lowercase();
+    let overrides = overrides::load(context).await;
+    let overrides = overrides.as_deref();
+    if let Some(provider) = overrides.and_then(|overrides| overrides.get_by_domain(&domain)) {
+        return Some(provider);
+    }
     for (pattern, provider) in PROVIDER_DATA {
         if let Some(suffix) = pattern.strip_prefix('*') {
             // Wildcard domain pattern.
             //
             // For example, `suffix` is ".hermes.radio" for "*.hermes.radio" pattern.
             if domain.ends_with(suffix) {
-                return Some(provider);
+                return Some(overrides::apply(overrides, provider));
             }
         } else if pattern == domain {
-            return Some(provider);
+            return Some(overrides::apply(overrides, provider));
         }
     }
 
@@ -236,6 +248,22 @@ pub fn get_provider_by_domain(domain: &str) -> Option<&'static Provider> {
 ///
 /// For security reasons, only Gmail can be configured this way.
 pub async fn get_provider_by_mx(context: &Context, domain: &str) -> Option<&'static Provider> {
+    get_provider_by_mx_among(context, domain, &["gmail"]).await
+}
+
+/// Returns true if the MX records of the given domain point to Outlook or Office 365.
+pub(crate) async fn is_outlook_by_mx(context: &Context, domain: &str) -> bool {
+    get_provider_by_mx_among(context, domain, &["outlook.com"])
+        .await
+        .is_some()
+}
+
+/// Finds a provider with one of the given IDs based on MX record for the given domain.
+async fn get_provider_by_mx_among(
+    context: &Context,
+    domain: &str,
+    provider_ids: &[&str],
+) -> Option<&'static Provider> {
     let Ok(resolver) = get_resolver() else {
         warn!(context, "Cannot get a resolver to check MX records.");
         return None;
@@ -252,8 +280,7 @@ pub async fn get_provider_by_mx(context: &Context, domain: &str) -> Option<&'sta
     };
 
     for (provider_domain_pattern, provider) in PROVIDER_DATA {
-        if provider.id != "gmail" {
-            // MX lookup is limited to Gmail for security reasons
+        if !provider_ids.contains(&provider.id) {
             continue;
         }
 
@@ -269,7 +296,8 @@ pub async fn get_provider_by_mx(context: &Context, domain: &str) -> Option<&'sta
             let mx_domain = mx_domain.exchange().to_lowercase().to_utf8();
 
             if mx_domain == provider_fqdn || mx_domain.ends_with(&provider_fqdn_dot) {
-                return Some(provider);
+                let overrides = overrides::load(context).await;
+                return Some(overrides::apply(overrides.as_deref(), provider));
             }
         }
     }
@@ -277,9 +305,28 @@ pub async fn get_provider_by_mx(context: &Context, domain: &str) -> Option<&'sta
     None
 }
 
+/// Finds a provider in offline database by the hostname of its SMTP server.
+pub(crate) async fn get_provider_by_smtp_server(
+    context: &Context,
+    hostname: &str,
+) -> Option<&'static Provider> {
+    let overrides = overrides::load(context).await;
+    PROVIDER_DATA
+        .iter()
+        .map(|(_, provider)| overrides::apply(overrides.as_deref(), provider))
+        .find(|provider| {
+            provider.server.iter().any(|server| {
+                server.protocol == Protocol::Smtp && server.hostname.eq_ignore_ascii_case(hostname)
+            })
+        })
+}
+
 /// Returns a provider with the given ID from the database.
-pub fn get_provider_by_id(id: &str) -> Option<&'static Provider> {
-    if let Some(provider) = PROVIDER_IDS.get(id) {
+pub async fn get_provider_by_id(context: &Context, id: &str) -> Option<&'static Provider> {
+    let overrides = overrides::load(context).await;
+    if let Some(provider) = overrides.and_then(|overrides| overrides.get_by_id(id)) {
+        Some(provider)
+    } else if let Some(provider) = PROVIDER_IDS.get(id) {
         Some(provider)
     } else {
         None
@@ -293,22 +340,40 @@ mod tests {
     use super::*;
     use crate::test_utils::TestContext;
 
-    #[test]
-    fn test_get_provider_by_domain_unexistant() {
-        let provider = get_provider_by_domain("unexistant.org");
+    #[tokio::test(flavor = "multi_thread", worker_threads = 2)]
+    async fn test_get_provider_by_domain_unexistant() {
+        let t = TestContext::new().await;
+        let provider = get_provider_by_domain(&t, "unexistant.org").await;
         assert!(provider.is_none());
     }
 
-    #[test]
-    fn test_get_provider_by_domain_mixed_case() {
-        let provider = get_provider_by_domain("nAUta.Cu").unwrap();
+    #[tokio::test(flavor = "multi_thread", worker_threads = 2)]
+    async fn test_get_provider_by_smtp_server() {
+        let t = TestContext::new().await;
+        let provider = get_provider_by_smtp_server(&t, "SMTP.gmail.com")
+            .await
+            .unwrap();
+        assert_eq!(provider.id, "gmail");
+        assert!(get_provider_by_smtp_server(&t, "imap.gmail.com")
+            .await
+            .is_none());
+        assert!(get_provider_by_smtp_server(&t, "smtp.example.org")
+            .await
+            .is_none());
+    }
+
+    #[tokio::test(flavor = "multi_thread", worker_threads = 2)]
+    async fn test_get_provider_by_domain_mixed_case() {
+        let t = TestContext::new().await;
+        let provider = get_provider_by_domain(&t, "nAUta.Cu").await.unwrap();
         assert!(provider.status == Status::Ok);
     }
 
-    #[test]
-    fn test_get_provider_by_domain() {
+    #[tokio::test(flavor = "multi_thread", worker_threads = 2)]
+    async fn test_get_provider_by_domain() {
+        let t = TestContext::new().await;
         let addr = "nauta.cu";
-        let provider = get_provider_by_domain(addr).unwrap();
+        let provider = get_provider_by_domain(&t, addr).await.unwrap();
         assert!(provider.status == Status::Ok);
         let server = &provider.server[0];
         assert_eq!(server.protocol, Protocol::Imap);
@@ -323,18 +388,19 @@ fn test_get_provider_by_domain() {
         assert_eq!(server.port, 25);
         assert_eq!(server.username_pattern, UsernamePattern::Email);
 
-        let provider = get_provider_by_domain("gmail.com").unwrap();
+        let provider = get_provider_by_domain(&t, "gmail.com").await.unwrap();
         assert!(provider.status == Status::Preparation);
         assert!(!provider.before_login_hint.is_empty());
         assert!(!provider.overview_page.is_empty());
 
-        let provider = get_provider_by_domain("googlemail.com").unwrap();
+        let provider = get_provider_by_domain(&t, "googlemail.com").await.unwrap();
         assert!(provider.status == Status::Preparation);
     }
 
-    #[test]
-    fn test_get_provider_by_id() {
-        let provider = get_provider_by_id("gmail").unwrap();
+    #[tokio::test(flavor = "multi_thread", worker_threads = 2)]
+    async fn test_get_provider_by_id() {
+        let t = TestContext::new().await;
+        let provider = get_provider_by_id(&t, "gmail").await.unwrap();
         assert!(provider.id == "gmail");
     }
 
@@ -0,0 +1,415 @@
+//! # Provider database overrides.
+//!
+//! Operators can load additional provider database entries at runtime
+//! to fix broken provider hints or servers without waiting for a release.
+//! Overrides take precedence over the compiled-in database.
+//! They are stored in [`Config::ProviderOverrides`] of each context
+//! and can be updated at any time.
+//!
+//! Overrides are given in JSON or TOML format.
+//! The JSON format looks like this:
+//!
+//! ```json
+//! {
+//!   "providers": [
+//!     {
+//!       "id": "example.net",
+//!       "domains": ["example.net", "example.org"],
+//!       "status": "preparation",
+//!       "before_login_hint": "Enable IMAP in the web interface first.",
+//!       "server": [
+//!         { "protocol": "imap", "socket": "ssl", "hostname": "imap.example.net", "port": 993 },
+//!         { "protocol": "smtp", "socket": "starttls", "hostname": "smtp.example.net", "port": 587 }
+//!       ],
+//!       "config_defaults": { "delete_server_after": "0" }
+//!     }
+//!   ]
+//! }
+//! ```
+//!
+//! Only `id` is required.
+//! If `id` refers to a compiled-in provider,
+//! fields which are not set are taken from it
+//! and the provider is also used for its compiled-in domains.
+
+use std::collections::{BTreeMap, HashMap};
+use std::path::Path;
+use std::sync::{Arc, Mutex};
+
+use anyhow::{bail, ensure, Context as _, Result};
+use once_cell::sync::Lazy;
+use serde::Deserialize;
+
+use super::data::PROVIDER_IDS;
+use super::{
+    ConfigDefault, Protocol, Provider, ProviderOptions, Server, Socket, Status, UsernamePattern,
+};
+use crate::config::Config;
+use crate::context::Context;
+use crate::log::LogExt;
+
+/// Parsed provider overrides.
+#[derive(Debug, Default)]
+pub(super) struct Overrides {
+    /// Overriding providers by domain, lowercased.
+    by_domain: HashMap<String, &'static Provider>,
+
+    /// Overriding providers by ID.
+    by_id: HashMap<String, &'static Provider>,
+}
+
+/// Parsed overrides by their source, shared by all contexts of the process.
+///
+/// Providers are referenced as `&'static Provider` everywhere,
+/// so parsed overrides are never freed.
+/// Parsing each source only once bounds the memory
+/// by the size of the distinct overrides used while the process runs.
+static PARSED: Lazy<Mutex<HashMap<String, Arc<Overrides>>>> = Lazy::new(Default::default);
+
+/// Provider overrides file.
+#[derive(Debug, Deserialize)]
+struct OverridesFile {
+    providers: Vec<ProviderEntry>,
+}
+
+/// Single provider in the overrides file.
+#[derive(Debug, Default, Deserialize)]
+#[serde(default)]
+struct ProviderEntry {
+    id: String,
+    domains: Vec<String>,
+    status: Option<String>,
+    before_login_hint: Option<String>,
+    after_login_hint: Option<String>,
+    overview_page: Option<String>,
+    server: Option<Vec<ServerEntry>>,
+    config_defaults: Option<BTreeMap<String, String>>,
+    strict_tls: Option<bool>,
+    max_smtp_rcpt_to: Option<u16>,
+    delete_to_trash: Option<bool>,
+    abuse_report_addr: Option<String>,
+}
+
+/// Single server of a provider in the overrides file.
+#[derive(Debug, Deserialize)]
+struct ServerEntry {
+    protocol: String,
+    socket: String,
+    hostname: String,
+    port: u16,
+    username_pattern: Option<String>,
+}
+
+/// Converts the string to a `&'static str`.
+///
+/// See [`PARSED`] for why this does not leak memory repeatedly.
+fn leak_str(s: String) -> &'static str {
+    Box::leak(s.into_boxed_str())
+}
+
+fn parse_status(status: &str) -> Result<Status> {
+    match status.to_lowercase().as_str() {
+        "ok" => Ok(Status::Ok),
+        "preparation" => Ok(Status::Preparation),
+        "broken" => Ok(Status::Broken),
+        _ => bail!("Unknown provider status {status:?}"),
+    }
+}
+
+fn parse_server(server: ServerEntry) -> Result<Server> {
+    let protocol = match server.protocol.to_lowercase().as_str() {
+        "imap" => Protocol::Imap,
+        "smtp" => Protocol::Smtp,
+        _ => bail!("Unknown protocol {:?}", server.protocol),
+    };
+    let socket = match server.socket.to_lowercase().as_str() {
+        "automatic" => Socket::Automatic,
+        "ssl" => Socket::Ssl,
+        "starttls" => Socket::Starttls,
+        "plain" => Socket::Plain,
+        _ => bail!("Unknown socket security {:?}", server.socket),
+    };
+    let username_pattern = match server.username_pattern.as_deref().map(str::to_lowercase) {
+        None => UsernamePattern::Email,
+        Some(pattern) if pattern == "email" => UsernamePattern::Email,
+        Some(pattern) if pattern == "emaillocalpart" => UsernamePattern::Emaillocalpart,
+        Some(pattern) => bail!("Unknown username pattern {pattern:?}"),
+    };
+    ensure!(!server.hostname.is_empty(), "Missing server hostname");
+    Ok(Server {
+        protocol,
+        socket,
+        hostname: leak_str(server.hostname),
+        port: server.port,
+        username_pattern,
+    })
+}
+
+/// Builds a provider from the entry, filling unset fields from `base`.
+fn build_provider(entry: ProviderEntry, base: Option<&'static Provider>) -> Result<Provider> {
+    let status = match &entry.status {
+        Some(status) => parse_status(status)?,
+        None => base.map_or(Status::Ok, |base| base.status),
+    };
+    let server: &'static [Server] = match entry.server {
+        Some(servers) => Box::leak(
+            servers
+                .into_iter()
+                .map(parse_server)
+                .collect::<Result<Vec<_>>>()?
+                .into_boxed_slice(),
+        ),
+        None => base.map_or(&[], |base| base.server),
+    };
+    let config_defaults = match entry.config_defaults {
+        Some(config_defaults) => {
+            let config_defaults = config_defaults
+                .into_iter()
+                .map(|(key, value)| {
+                    Ok(ConfigDefault {
+                        key: key
+                            .parse()
+                            .with_context(|| format!("Unknown config key {key:?}"))?,
+                        value: leak_str(value),
+                    })
+                })
+                .collect::<Result<Vec<_>>>()?;
+            Some(&*Box::leak(config_defaults.into_boxed_slice()))
+        }
+        None => base.and_then(|base| base.config_defaults),
+    };
+    let default_opt = ProviderOptions::new();
+    let base_opt = base.map_or(&default_opt, |base| &base.opt);
+    Ok(Provider {
+        id: leak_str(entry.id),
+        status,
+        before_login_hint: entry
+            .before_login_hint
+            .map_or(base.map_or("", |base| base.before_login_hint), leak_str),
+        after_login_hint: entry
+            .after_login_hint
+            .map_or(base.map_or("", |base| base.after_login_hint), leak_str),
+        overview_page: entry
+            .overview_page
+            .map_or(base.map_or("", |base| base.overview_page), leak_str),
+        server,
+        config_defaults,
+        oauth2_authorizer: base.and_then(|base| base.oauth2_authorizer),
+        opt: ProviderOptions {
+            strict_tls: entry.strict_tls.unwrap_or(base_opt.strict_tls),
+            max_smtp_rcpt_to: entry.max_smtp_rcpt_to.or(base_opt.max_smtp_rcpt_to),
+            delete_to_trash: entry.delete_to_trash.unwrap_or(base_opt.delete_to_trash),
+            abuse_report_addr: entry
+                .abuse_report_addr
+                .map(leak_str)
+                .or(base_opt.abuse_report_addr),
+        },
+    })
+}
+
+/// Parses the overrides in JSON or TOML format.
+fn parse_overrides(data: &str) -> Result<Overrides> {
+    let file: OverridesFile = if data.trim_start().starts_with('{') {
+        serde_json::from_str(data).context("Malformed provider overrides")?
+    } else {
+        toml::from_str(data).context("Malformed provider overrides")?
+    };
+
+    let mut overrides = Overrides::default();
+    for entry in file.providers {
+        ensure!(!entry.id.is_empty(), "Provider without ID");
+        let base = PROVIDER_IDS.get(entry.id.as_str()).copied();
+        let domains: Vec<String> = entry.domains.iter().map(|d| d.to_lowercase()).collect();
+        let id = entry.id.clone();
+        let provider =
+            build_provider(entry, base).with_context(|| format!("Invalid provider {id:?}"))?;
+        let provider: &'static Provider = Box::leak(Box::new(provider));
+        for domain in domains {
+            overrides.by_domain.insert(domain, provider);
+        }
+        overrides.by_id.insert(id, provider);
+    }
+    Ok(overrides)
+}
+
+/// Returns the parsed overrides, parsing them only once per process.
+pub(crate) fn parse_cached(data: &str) -> Result<Arc<Overrides>> {
+    let mut parsed = PARSED.lock().unwrap();
+    if let Some(overrides) = parsed.get(data) {
+        return Ok(Arc::clone(overrides));
+    }
+    let overrides = Arc::new(parse_overrides(data)?);
+    parsed.insert(data.to_string(), Arc::clone(&overrides));
+    Ok(overrides)
+}
+
+/// Returns the provider overrides of the context, if any.
+pub(super) async fn load(context: &Context) -> Option<Arc<Overrides>> {
+    let data = context
+        .get_config(Config::ProviderOverrides)
+        .await
+        .log_err(context)
+        .ok()??;
+    parse_cached(&data).log_err(context).ok()
+}
+
+impl Overrides {
+    /// Returns the overriding provider for the lowercased domain.
+    pub(super) fn get_by_domain(&self, domain: &str) -> Option<&'static Provider> {
+        self.by_domain.get(domain).copied()
+    }
+
+    /// Returns the overriding provider with the given ID.
+    pub(super) fn get_by_id(&self, id: &str) -> Option<&'static Provider> {
+        self.by_id.get(id).copied()
+    }
+}
+
+/// Returns the override of a compiled-in provider or the provider itself.
+pub(super) fn apply(
+    overrides: Option<&Overrides>,
+    provider: &'static Provider,
+) -> &'static Provider {
+    overrides
+        .and_then(|overrides| overrides.get_by_id(provider.id))
+        .unwrap_or(provider)
+}
+
+impl Context {
+    /// Loads provider database overrides from the file at `path`
+    /// into [`Config::ProviderOverrides`].
+    ///
+    /// See the [module documentation](crate::provider::overrides) for the file format.
+    /// Loading another file replaces the overrides.
+    /// If the file is invalid, the overrides are not changed.
+    ///
+    /// Returns the number of loaded providers.
+    pub async fn set_provider_overrides(&self, path: &Path) -> Result<usize> {
+        let data = tokio::fs::read_to_string(path)
+            .await
+            .with_context(|| format!("Cannot read {}", path.display()))?;
+        let count = parse_cached(&data)?.by_id.len();
+        self.set_config(Config::ProviderOverrides, Some(&data))
+            .await?;
+        info!(
+            self,
+            "Loaded {count} provider overrides from {}.",
+            path.display()
+        );
+        Ok(count)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::provider::get_provider_by_domain;
+    use crate::test_utils::TestContextManager;
+
+    #[test]
+    fn test_parse_overrides() -> Result<()> {
+        let json = r#"{
+            "providers": [
+                {
+                    "id": "overrides.example",
+                    "domains": ["Overrides.Example"],
+                    "status": "preparation",
+                    "before_login_hint": "Hint",
+                    "server": [
+                        {"protocol": "imap", "socket": "ssl", "hostname": "imap.overrides.example", "port": 993},
+                        {"protocol": "smtp", "socket": "starttls", "hostname": "smtp.overrides.example", "port": 587, "username_pattern": "emaillocalpart"}
+                    ],
+                    "config_defaults": {"bcc_self": "1"}
+                }
+            ]
+        }"#;
+        let overrides = parse_overrides(json)?;
+        assert_eq!(overrides.by_id.len(), 1);
+        let provider = *overrides.by_domain.get("overrides.example").unwrap();
+        assert_eq!(provider.id, "overrides.example");
+        assert_eq!(provider.status, Status::Preparation);
+        assert_eq!(provider.before_login_hint, "Hint");
+        assert_eq!(provider.server.len(), 2);
+        assert_eq!(provider.server[1].socket, Socket::Starttls);
+        assert_eq!(
+            provider.server[1].username_pattern,
+            UsernamePattern::Emaillocalpart
+        );
+        assert_eq!(provider.config_defaults.unwrap().len(), 1);
+        assert_eq!(
+            overrides.by_id.get("overrides.example").copied(),
+            Some(provider)
+        );
+
+        let json = r#"{"providers": [{"id": "broken.example", "domains": ["broken.example"], "status": "unknown"}]}"#;
+        assert!(parse_overrides(json).is_err());
+        Ok(())
+    }
+
+    #[test]
+    fn test_override_compiled_in_provider() -> Result<()> {
+        let toml = r#"
+            [[providers]]
+            id = "aol"
+            before_login_hint = "Updated hint"
+        "#;
+        let overrides = parse_overrides(toml)?;
+        let provider = *overrides.by_id.get("aol").unwrap();
+        assert_eq!(provider.before_login_hint, "Updated hint");
+        // Unset fields are taken from the compiled-in provider.
+        assert_eq!(provider.server, PROVIDER_IDS.get("aol").unwrap().server);
+        Ok(())
+    }
+
+    #[tokio::test(flavor = "multi_thread", worker_threads = 2)]
+    async fn test_overrides_per_context() -> Result<()> {
+        let mut tcm = TestContextManager::new();
+        let alice = &tcm.alice().await;
+        let bob = &tcm.bob().await;
+        let json = r#"{"providers": [{"id": "context.example", "domains": ["context.example"], "before_login_hint": "First"}]}"#;
+        alice
+            .set_config(Config::ProviderOverrides, Some(json))
+            .await?;
+        let provider = get_provider_by_domain(alice, "context.example")
+            .await
+            .unwrap();
+        assert_eq!(provider.before_login_hint, "First");
+        assert!(get_provider_by_domain(bob, "context.example")
+            .await
+            .is_none());
+
+        // The same overrides are parsed only once.
+        assert!(Arc::ptr_eq(&parse_cached(json)?, &parse_cached(json)?));
+
+        // Overrides can be updated.
+        let toml = r#"
+            [[providers]]
+            id = "context.example"
+            domains = ["context.example"]
+            before_login_hint = "Second"
+        "#;
+        alice
+            .set_config(Config::ProviderOverrides, Some(toml))
+            .await?;
+        let provider = get_provider_by_domain(alice, "context.example")
+            .await
+            .unwrap();
+        assert_eq!(provider.before_login_hint, "Second");
+
+        // Invalid overrides are rejected and the previous ones are kept.
+        assert!(alice
+            .set_config(Config::ProviderOverrides, Some("{"))
+            .await
+            .is_err());
+        let provider = get_provider_by_domain(alice, "context.example")
+            .await
+            .unwrap();
+        assert_eq!(provider.before_login_hint, "Second");
+
+        alice.set_config(Config::ProviderOverrides, None).await?;
+        assert!(get_provider_by_domain(alice, "context.example")
+            .await
+            .is_none());
+        Ok(())
+    }
+}
@@ -5,7 +5,7 @@
 
 use anyhow::{anyhow, bail, ensure, Context as _, Result};
 pub use dclogin_scheme::LoginOptions;
-use deltachat_contact_tools::{addr_normalize, may_be_valid_addr, ContactAddress};
+use deltachat_contact_tools::{addr_normalize, may_be_valid_addr, parse_vcard, ContactAddress};
 use once_cell::sync::Lazy;
 use percent_encoding::percent_decode_str;
 use serde::Deserialize;
@@ -14,14 +14,14 @@
 use crate::chat::{get_chat_id_by_grpid, ChatIdBlocked};
 use crate::config::Config;
 use crate::constants::Blocked;
-use crate::contact::{Contact, ContactId, Origin};
+use crate::contact::{self, Contact, ContactId, Origin};
 use crate::context::Context;
 use crate::events::EventType;
 use crate::key::Fingerprint;
 use crate::message::Message;
 use crate::peerstate::Peerstate;
-use crate::token;
 use crate::tools::validate_id;
+use crate::{securejoin, token};
 use iroh_old as iroh;
 
 const OPENPGP4FPR_SCHEME: &str = "OPENPGP4FPR:"; // yes: uppercase
@@ -154,6 +154,19 @@ pub enum Qr {
         draft: Option<String>,
     },
 
+    /// vCard with several contacts or with a key scanned.
+    ///
+    /// Ask the user if they want to import the contacts;
+    /// if so, pass this QR code to [`set_config_from_qr`].
+    /// vCards with a single contact and no key result in [`Qr::Addr`].
+    Vcard {
+        /// The scanned vCard.
+        vcard: String,
+
+        /// Addresses of the contacts in the vCard.
+        addrs: Vec<String>,
+    },
+
     /// URL scanned.
     ///
     /// Ask the user if they want to open a browser or copy the URL to clipboard.
@@ -263,42 +276,107 @@ fn starts_with_ignore_case(string: &str, pattern: &str) -> bool {
 /// The function should be called after a QR code is scanned.
 /// The function takes the raw text scanned and checks what can be done with it.
 pub async fn check_qr(context: &Context, qr: &str) -> Result<Qr> {
-    let qrcode = if starts_with_ignore_case(qr, OPENPGP4FPR_SCHEME) {
-        decode_openpgp(context, qr)
+    match parse_qr(qr)? {
+        ParsedQr::Openpgp(openpgp) => openpgp
+            .resolve(context)
             .await
-            .context("failed to decode OPENPGP4FPR QR code")?
+            .context("failed to resolve OPENPGP4FPR QR code"),
+        ParsedQr::Address { name, addr, draft } => {
+            Qr::from_address(context, &name, &addr, draft).await
+        }
+        ParsedQr::Resolved(qrcode) => Ok(qrcode),
+    }
+}
+
+/// QR code parsed without looking up contacts, keys or tokens of the account.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub(crate) enum ParsedQr {
+    /// `OPENPGP4FPR:` QR code, possibly with a Securejoin invitation.
+    Openpgp(OpenpgpQr),
+
+    /// QR code containing a contact address, e.g. `mailto:`.
+    Address {
+        /// Name of the contact, may be empty.
+        name: String,
+
+        /// Normalized address of the contact.
+        addr: String,
+
+        /// Draft message to the contact.
+        draft: Option<String>,
+    },
+
+    /// QR code which does not depend on the account.
+    Resolved(Qr),
+}
+
+/// Contents of an `OPENPGP4FPR:` QR code.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub(crate) struct OpenpgpQr {
+    /// Fingerprint of the contact key.
+    pub fingerprint: Fingerprint,
+
+    /// Normalized address of the contact.
+    pub addr: Option<String>,
+
+    /// Name of the contact, may be empty.
+    pub name: String,
+
+    /// Securejoin invite number.
+    pub invitenumber: Option<String>,
+
+    /// Securejoin authentication code.
+    pub authcode: Option<String>,
+
+    /// ID of the group to join.
+    pub grpid: Option<String>,
+
+    /// Name of the group to join.
+    pub grpname: Option<String>,
+}
+
+/// Parses a QR code without access to the account.
+///
+/// QR codes referring to contacts or keys are resolved by [`check_qr`].
+pub(crate) fn parse_qr(qr: &str) -> Result<ParsedQr> {
+    let parsed = if starts_with_ignore_case(qr, OPENPGP4FPR_SCHEME) {
+        ParsedQr::Openpgp(parse_openpgp(qr).context("failed to decode OPENPGP4FPR QR code")?)
     } else if qr.starts_with(IDELTACHAT_SCHEME) {
-        decode_ideltachat(context, IDELTACHAT_SCHEME, qr).await?
+        ParsedQr::Openpgp(parse_ideltachat(IDELTACHAT_SCHEME, qr)?)
     } else if qr.starts_with(IDELTACHAT_NOSLASH_SCHEME) {
-        decode_ideltachat(context, IDELTACHAT_NOSLASH_SCHEME, qr).await?
+        ParsedQr::Openpgp(parse_ideltachat(IDELTACHAT_NOSLASH_SCHEME, qr)?)
     } else if starts_with_ignore_case(qr, DCACCOUNT_SCHEME) {
-        decode_account(qr)?
+        ParsedQr::Resolved(decode_account(qr)?)
     } else if starts_with_ignore_case(qr, DCLOGIN_SCHEME) {
-        dclogin_scheme::decode_login(qr)?
+        ParsedQr::Resolved(dclogin_scheme::decode_login(qr)?)
     } else if starts_with_ignore_case(qr, DCWEBRTC_SCHEME) {
-        decode_webrtc_instance(context, qr)?
+        ParsedQr::Resolved(decode_webrtc_instance(qr)?)
     } else if starts_with_ignore_case(qr, DCBACKUP_SCHEME) {
-        decode_backup(qr)?
+        ParsedQr::Resolved(decode_backup(qr)?)
     } else if starts_with_ignore_case(qr, DCBACKUP2_SCHEME) {
-        decode_backup2(qr)?
-    } else if qr.starts_with(MAILTO_SCHEME) {
-        decode_mailto(context, qr).await?
-    } else if qr.starts_with(SMTP_SCHEME) {
-        decode_smtp(context, qr).await?
-    } else if qr.starts_with(MATMSG_SCHEME) {
-        decode_matmsg(context, qr).await?
-    } else if qr.starts_with(VCARD_SCHEME) {
-        decode_vcard(context, qr).await?
+        ParsedQr::Resolved(decode_backup2(qr)?)
+    } else if starts_with_ignore_case(qr, MAILTO_SCHEME) {
+        decode_mailto(qr)?
+    } else if starts_with_ignore_case(qr, SMTP_SCHEME) {
+        decode_smtp(qr)?
+    } else if starts_with_ignore_case(qr, MATMSG_SCHEME) {
+        decode_matmsg(qr)?
+    } else if starts_with_ignore_case(qr, VCARD_SCHEME) {
+        decode_vcard(qr)?
     } else if qr.starts_with(HTTP_SCHEME) || qr.starts_with(HTTPS_SCHEME) {
-        Qr::Url {
+        ParsedQr::Resolved(Qr::Url {
             url: qr.to_string(),
-        }
+        })
+    } else if let Some(openpgp4fpr) = plain_fingerprint_to_openpgp4fpr(qr) {
+        ParsedQr::Openpgp(
+            parse_openpgp(&openpgp4fpr).context("failed to decode fingerprint QR code")?,
+        )
     } else {
-        Qr::Text {
+        ParsedQr::Resolved(Qr::Text {
             text: qr.to_string(),
-        }
+        })
     };
-    Ok(qrcode)
+    Ok(parsed)
 }
 
 /// Formats the text of the [`Qr::Backup`] variant.
@@ -321,11 +399,32 @@ pub fn format_backup(qr: &Qr) -> Result<String> {
     }
 }
 
+/// Converts a plain OpenPGP fingerprint, optionally followed by parameters,
+/// to the `OPENPGP4FPR:` scheme.
+///
+/// The fingerprint may be grouped by spaces as commonly printed,
+/// e.g. `1234 5678 ... #a=ADDR`.
+fn plain_fingerprint_to_openpgp4fpr(qr: &str) -> Option<String> {
+    let (fingerprint, fragment) = match qr.split_once(['#', '?']) {
+        Some((fingerprint, fragment)) => (fingerprint, Some(fragment)),
+        None => (qr, None),
+    };
+    let fingerprint: String = fingerprint.chars().filter(|c| !c.is_whitespace()).collect();
+    if fingerprint.len() != 40 || !fingerprint.chars().all(|c| c.is_ascii_hexdigit()) {
+        return None;
+    }
+    Some(match fragment {
+        Some(fragment) => format!("{OPENPGP4FPR_SCHEME}{fingerprint}#{fragment}"),
+        None => format!("{OPENPGP4FPR_SCHEME}{fingerprint}"),
+    })
+}
+
 /// scheme: `OPENPGP4FPR:FINGERPRINT#a=ADDR&n=NAME&i=INVITENUMBER&s=AUTH`
 ///     or: `OPENPGP4FPR:FINGERPRINT#a=ADDR&g=GROUPNAME&x=GROUPID&i=INVITENUMBER&s=AUTH`
 ///     or: `OPENPGP4FPR:FINGERPRINT#a=ADDR`
 #[allow(clippy::indexing_slicing)]
-async fn decode_openpgp(context: &Context, qr: &str) -> Result<Qr> {
+/// Parses an `OPENPGP4FPR:` QR code.
+fn parse_openpgp(qr: &str) -> Result<OpenpgpQr> {
     let payload = &qr[OPENPGP4FPR_SCHEME.len()..];
 
     // macOS and iOS sometimes replace the # with %23 (uri encode it), we should be able to parse this wrong format too.
@@ -395,26 +494,72 @@ async fn decode_openpgp(context: &Context, qr: &str) -> Result<Qr> {
         None
     };
 
-    // retrieve known state for this fingerprint
-    let peerstate = Peerstate::from_fingerprint(context, &fingerprint)
-        .await
-        .context("Can't load peerstate")?;
+    Ok(OpenpgpQr {
+        fingerprint,
+        addr,
+        name,
+        invitenumber,
+        authcode,
+        grpid,
+        grpname,
+    })
+}
 
-    if let (Some(addr), Some(invitenumber), Some(authcode)) = (&addr, invitenumber, authcode) {
-        let addr = ContactAddress::new(addr)?;
-        let (contact_id, _) =
-            Contact::add_or_lookup(context, &name, &addr, Origin::UnhandledQrScan)
-                .await
-                .with_context(|| format!("failed to add or lookup contact for address {addr:?}"))?;
+impl OpenpgpQr {
+    /// Looks up the contact and Securejoin state of the QR code.
+    async fn resolve(self, context: &Context) -> Result<Qr> {
+        let OpenpgpQr {
+            fingerprint,
+            addr,
+            name,
+            invitenumber,
+            authcode,
+            grpid,
+            grpname,
+        } = self;
 
-        if let (Some(grpid), Some(grpname)) = (grpid, grpname) {
-            if context
-                .is_self_addr(&addr)
-                .await
-                .with_context(|| format!("can't check if address {addr:?} is our address"))?
-            {
-                if token::exists(context, token::Namespace::InviteNumber, &invitenumber).await? {
-                    Ok(Qr::WithdrawVerifyGroup {
+        // retrieve known state for this fingerprint
+        let peerstate = Peerstate::from_fingerprint(context, &fingerprint)
+            .await
+            .context("Can't load peerstate")?;
+
+        if let (Some(addr), Some(invitenumber), Some(authcode)) = (&addr, invitenumber, authcode) {
+            let addr = ContactAddress::new(addr)?;
+            let (contact_id, _) =
+                Contact::add_or_lookup(context, &name, &addr, Origin::UnhandledQrScan)
+                    .await
+                    .with_context(|| {
+                        format!("failed to add or lookup contact for address {addr:?}")
+                    })?;
+
+            if let (Some(grpid), Some(grpname)) = (grpid, grpname) {
+                if context
+                    .is_self_addr(&addr)
+                    .await
+                    .with_context(|| format!("can't check if address {addr:?} is our address"))?
+                {
+                    if token::exists(context, token::Namespace::InviteNumber, &invitenumber).await?
+                    {
+                        Ok(Qr::WithdrawVerifyGroup {
+                            grpname,
+                            grpid,
+                            contact_id,
+                            fingerprint,
+                            invitenumber,
+                            authcode,
+                        })
+                    } else {
+                        Ok(Qr::ReviveVerifyGroup {
+                            grpname,
+                            grpid,
+                            contact_id,
+                            fingerprint,
+                            invitenumber,
+                            authcode,
+                        })
+                    }
+                } else {
+                    Ok(Qr::AskVerifyGroup {
                         grpname,
                         grpid,
                         contact_id,
@@ -422,10 +567,17 @@ async fn decode_openpgp(context: &Context, qr: &str) -> Result<Qr> {
                         invitenumber,
                         authcode,
                     })
+                }
+            } else if context.is_self_addr(&addr).await? {
+                if token::exists(context, token::Namespace::InviteNumber, &invitenumber).await? {
+                    Ok(Qr::WithdrawVerifyContact {
+                        contact_id,
+                        fingerprint,
+                        invitenumber,
+                        authcode,
+                    })
                 } else {
-                    Ok(Qr::ReviveVerifyGroup {
-                        grpname,
-                        grpid,
+                    Ok(Qr::ReviveVerifyContact {
                         contact_id,
                         fingerprint,
                         invitenumber,
@@ -433,70 +585,47 @@ async fn decode_openpgp(context: &Context, qr: &str) -> Result<Qr> {
                     })
                 }
             } else {
-                Ok(Qr::AskVerifyGroup {
-                    grpname,
-                    grpid,
+                Ok(Qr::AskVerifyContact {
                     contact_id,
                     fingerprint,
                     invitenumber,
                     authcode,
                 })
             }
-        } else if context.is_self_addr(&addr).await? {
-            if token::exists(context, token::Namespace::InviteNumber, &invitenumber).await? {
-                Ok(Qr::WithdrawVerifyContact {
-                    contact_id,
-                    fingerprint,
-                    invitenumber,
-                    authcode,
-                })
+        } else if let Some(addr) = addr {
+            if let Some(peerstate) = peerstate {
+                let peerstate_addr = ContactAddress::new(&peerstate.addr)?;
+                let (contact_id, _) = Contact::add_or_lookup(
+                    context,
+                    &name,
+                    &peerstate_addr,
+                    Origin::UnhandledQrScan,
+                )
+                .await
+                .context("add_or_lookup")?;
+                ChatIdBlocked::get_for_contact(context, contact_id, Blocked::Request)
+                    .await
+                    .context("Failed to create (new) chat for contact")?;
+                Ok(Qr::FprOk { contact_id })
             } else {
-                Ok(Qr::ReviveVerifyContact {
-                    contact_id,
-                    fingerprint,
-                    invitenumber,
-                    authcode,
-                })
+                let contact_id = Contact::lookup_id_by_addr(context, &addr, Origin::Unknown)
+                    .await
+                    .with_context(|| format!("Error looking up contact {addr:?}"))?;
+                Ok(Qr::FprMismatch { contact_id })
             }
         } else {
-            Ok(Qr::AskVerifyContact {
-                contact_id,
-                fingerprint,
-                invitenumber,
-                authcode,
+            Ok(Qr::FprWithoutAddr {
+                fingerprint: fingerprint.to_string(),
             })
         }
-    } else if let Some(addr) = addr {
-        if let Some(peerstate) = peerstate {
-            let peerstate_addr = ContactAddress::new(&peerstate.addr)?;
-            let (contact_id, _) =
-                Contact::add_or_lookup(context, &name, &peerstate_addr, Origin::UnhandledQrScan)
-                    .await
-                    .context("add_or_lookup")?;
-            ChatIdBlocked::get_for_contact(context, contact_id, Blocked::Request)
-                .await
-                .context("Failed to create (new) chat for contact")?;
-            Ok(Qr::FprOk { contact_id })
-        } else {
-            let contact_id = Contact::lookup_id_by_addr(context, &addr, Origin::Unknown)
-                .await
-                .with_context(|| format!("Error looking up contact {addr:?}"))?;
-            Ok(Qr::FprMismatch { contact_id })
-        }
-    } else {
-        Ok(Qr::FprWithoutAddr {
-            fingerprint: fingerprint.to_string(),
-        })
     }
 }
 
 /// scheme: `https://i.delta.chat[/]#FINGERPRINT&a=ADDR[&OPTIONAL_PARAMS]`
-async fn decode_ideltachat(context: &Context, prefix: &str, qr: &str) -> Result<Qr> {
+fn parse_ideltachat(prefix: &str, qr: &str) -> Result<OpenpgpQr> {
     let qr = qr.replacen(prefix, OPENPGP4FPR_SCHEME, 1);
     let qr = qr.replacen('&', "#", 1);
-    decode_openpgp(context, &qr)
-        .await
-        .context("failed to decode {prefix} QR code")
+    parse_openpgp(&qr).with_context(|| format!("failed to decode {prefix} QR code"))
 }
 
 /// scheme: `DCACCOUNT:https://example.org/new_email?t=1w_7wDjgjelxeX884x96v3`
@@ -518,7 +647,7 @@ fn decode_account(qr: &str) -> Result<Qr> {
 }
 
 /// scheme: `DCWEBRTC:https://meet.jit.si/$ROOM`
-fn decode_webrtc_instance(_context: &Context, qr: &str) -> Result<Qr> {
+fn decode_webrtc_instance(qr: &str) -> Result<Qr> {
     let payload = qr
         .get(DCWEBRTC_SCHEME.len()..)
         .context("invalid DCWEBRTC payload")?;
@@ -587,7 +716,7 @@ struct CreateAccountErrorResponse {
 /// download additional information from the contained url and set the parameters.
 /// on success, a configure::configure() should be able to log in to the account
 #[allow(clippy::indexing_slicing)]
-async fn set_account_from_qr(context: &Context, qr: &str) -> Result<()> {
+pub(crate) async fn set_account_from_qr(context: &Context, qr: &str) -> Result<()> {
     let url_str = &qr[DCACCOUNT_SCHEME.len()..];
 
     if !url_str.starts_with(HTTPS_SCHEME) {
@@ -656,6 +785,7 @@ pub async fn set_config_from_qr(context: &Context, qr: &str) -> Result<()> {
         } => {
             token::delete(context, token::Namespace::InviteNumber, &invitenumber).await?;
             token::delete(context, token::Namespace::Auth, &authcode).await?;
+            securejoin::set_invite_withdrawn(context, &invitenumber, true).await?;
             context
                 .sync_qr_code_token_deletion(invitenumber, authcode)
                 .await?;
@@ -667,6 +797,7 @@ pub async fn set_config_from_qr(context: &Context, qr: &str) -> Result<()> {
         } => {
             token::delete(context, token::Namespace::InviteNumber, &invitenumber).await?;
             token::delete(context, token::Namespace::Auth, &authcode).await?;
+            securejoin::set_invite_withdrawn(context, &invitenumber, true).await?;
             context
                 .sync_qr_code_token_deletion(invitenumber, authcode)
                 .await?;
@@ -678,6 +809,7 @@ pub async fn set_config_from_qr(context: &Context, qr: &str) -> Result<()> {
         } => {
             token::save(context, token::Namespace::InviteNumber, None, &invitenumber).await?;
             token::save(context, token::Namespace::Auth, None, &authcode).await?;
+            securejoin::record_invite(context, None, &invitenumber, &authcode, 0).await?;
             context.sync_qr_code_tokens(None).await?;
             context.scheduler.interrupt_smtp().await;
         }
@@ -698,12 +830,16 @@ pub async fn set_config_from_qr(context: &Context, qr: &str) -> Result<()> {
             )
             .await?;
             token::save(context, token::Namespace::Auth, chat_id, &authcode).await?;
+            securejoin::record_invite(context, chat_id, &invitenumber, &authcode, 0).await?;
             context.sync_qr_code_tokens(chat_id).await?;
             context.scheduler.interrupt_smtp().await;
         }
         Qr::Login { address, options } => {
             configure_from_login_qr(context, &address, options).await?
         }
+        Qr::Vcard { vcard, .. } => {
+            contact::import_vcard(context, &vcard).await?;
+        }
         _ => bail!("QR code does not contain config"),
     }
 
@@ -713,8 +849,9 @@ pub async fn set_config_from_qr(context: &Context, qr: &str) -> Result<()> {
 /// Extract address for the mailto scheme.
 ///
 /// Scheme: `mailto:addr...?subject=...&body=..`
+///     or: `mailto:?to=addr...&subject=...&body=..`
 #[allow(clippy::indexing_slicing)]
-async fn decode_mailto(context: &Context, qr: &str) -> Result<Qr> {
+fn decode_mailto(qr: &str) -> Result<ParsedQr> {
     let payload = &qr[MAILTO_SCHEME.len()..];
 
     let (addr, query) = if let Some(query_index) = payload.find('?') {
@@ -723,17 +860,25 @@ async fn decode_mailto(context: &Context, qr: &str) -> Result<Qr> {
         (payload, "")
     };
 
-    let param: BTreeMap<&str, &str> = query
+    // Header names in `mailto:` URIs are case-insensitive.
+    let param: BTreeMap<String, &str> = query
         .split('&')
         .filter_map(|s| {
             if let [key, value] = s.splitn(2, '=').collect::<Vec<_>>()[..] {
-                Some((key, value))
+                Some((key.to_lowercase(), value))
             } else {
                 None
             }
         })
         .collect();
 
+    // Only the first address is used if there are several recipients.
+    let addr = match addr {
+        "" => param.get("to").copied().unwrap_or_default(),
+        addr => addr,
+    };
+    let addr = addr.split(',').next().unwrap_or_default();
+
     let subject = if let Some(subject) = param.get("subject") {
         subject.to_string()
     } else {
@@ -755,21 +900,18 @@ async fn decode_mailto(context: &Context, qr: &str) -> Result<Qr> {
     };
 
     let addr = normalize_address(addr)?;
-    let name = "";
-    Qr::from_address(
-        context,
-        name,
-        &addr,
-        if draft.is_empty() { None } else { Some(draft) },
-    )
-    .await
+    Ok(ParsedQr::Address {
+        name: String::new(),
+        addr,
+        draft: if draft.is_empty() { None } else { Some(draft) },
+    })
 }
 
 /// Extract address for the smtp scheme.
 ///
 /// Scheme: `SMTP:addr...:subject...:body...`
 #[allow(clippy::indexing_slicing)]
-async fn decode_smtp(context: &Context, qr: &str) -> Result<Qr> {
+fn decode_smtp(qr: &str) -> Result<ParsedQr> {
     let payload = &qr[SMTP_SCHEME.len()..];
 
     let addr = if let Some(query_index) = payload.find(':') {
@@ -779,8 +921,11 @@ async fn decode_smtp(context: &Context, qr: &str) -> Result<Qr> {
     };
 
     let addr = normalize_address(addr)?;
-    let name = "";
-    Qr::from_address(context, name, &addr, None).await
+    Ok(ParsedQr::Address {
+        name: String::new(),
+        addr,
+        draft: None,
+    })
 }
 
 /// Extract address for the matmsg scheme.
@@ -789,7 +934,7 @@ async fn decode_smtp(context: &Context, qr: &str) -> Result<Qr> {
 ///
 /// There may or may not be linebreaks after the fields.
 #[allow(clippy::indexing_slicing)]
-async fn decode_matmsg(context: &Context, qr: &str) -> Result<Qr> {
+fn decode_matmsg(qr: &str) -> Result<ParsedQr> {
     // Does not work when the text `TO:` is used in subject/body _and_ TO: is not the first field.
     // we ignore this case.
     let addr = if let Some(to_index) = qr.find("TO:") {
@@ -804,8 +949,11 @@ async fn decode_matmsg(context: &Context, qr: &str) -> Result<Qr> {
     };
 
     let addr = normalize_address(addr)?;
-    let name = "";
-    Qr::from_address(context, name, &addr, None).await
+    Ok(ParsedQr::Address {
+        name: String::new(),
+        addr,
+        draft: None,
+    })
 }
 
 static VCARD_NAME_RE: Lazy<regex::Regex> =
@@ -817,7 +965,15 @@ async fn decode_matmsg(context: &Context, qr: &str) -> Result<Qr> {
 ///
 /// Scheme: `VCARD:BEGIN\nN:last name;first name;...;\nEMAIL;<type>:addr...;`
 #[allow(clippy::indexing_slicing)]
-async fn decode_vcard(context: &Context, qr: &str) -> Result<Qr> {
+fn decode_vcard(qr: &str) -> Result<ParsedQr> {
+    let contacts = parse_vcard(qr);
+    if contacts.len() > 1 || contacts.iter().any(|c| c.key.is_some()) {
+        return Ok(ParsedQr::Resolved(Qr::Vcard {
+            vcard: qr.to_string(),
+            addrs: contacts.into_iter().map(|c| c.addr).collect(),
+        }));
+    }
+
     let name = VCARD_NAME_RE
         .captures(qr)
         .and_then(|caps| {
@@ -834,7 +990,11 @@ async fn decode_vcard(context: &Context, qr: &str) -> Result<Qr> {
         bail!("Bad e-mail address");
     };
 
-    Qr::from_address(context, &name, &addr, None).await
+    Ok(ParsedQr::Address {
+        name,
+        addr,
+        draft: None,
+    })
 }
 
 impl Qr {
@@ -874,6 +1034,31 @@ mod tests {
     use crate::securejoin::get_securejoin_qr;
     use crate::test_utils::{alice_keypair, TestContext};
 
+    #[test]
+    fn test_parse_qr_without_context() -> Result<()> {
+        let ParsedQr::Address { name, addr, draft } =
+            parse_qr("mailto:stress@test.local?subject=hello&body=beautiful+world")?
+        else {
+            bail!("Wrong QR code type");
+        };
+        assert_eq!(name, "");
+        assert_eq!(addr, "stress@test.local");
+        assert_eq!(draft.as_deref(), Some("hello\nbeautiful world"));
+
+        let ParsedQr::Openpgp(openpgp) = parse_qr(
+            "OPENPGP4FPR:79252762C34C5096AF57958F4FC3D21A81B0F0A7#a=cli%40deltachat.de&n=J%C3%B6rn%20P.+P.",
+        )?
+        else {
+            bail!("Wrong QR code type");
+        };
+        assert_eq!(openpgp.addr.as_deref(), Some("cli@deltachat.de"));
+        assert_eq!(openpgp.name, "Jörn P. P.");
+        assert!(openpgp.invitenumber.is_none());
+
+        assert!(parse_qr("OPENPGP4FPR:12345678").is_err());
+        Ok(())
+    }
+
     #[tokio::test(flavor = "multi_thread", worker_threads = 2)]
     async fn test_decode_http() -> Result<()> {
         let ctx = TestContext::new().await;
@@ -992,6 +1177,52 @@ async fn test_decode_mailto() -> Result<()> {
         let res = check_qr(&ctx.ctx, "mailto:no-addr").await;
         assert!(res.is_err());
 
+        let res = check_qr(
+            &ctx.ctx,
+            "MAILTO:?To=to-param@example.org,other@example.org&Subject=hi",
+        )
+        .await?;
+        if let Qr::Addr { contact_id, draft } = res {
+            let contact = Contact::get_by_id(&ctx.ctx, contact_id).await?;
+            assert_eq!(contact.get_addr(), "to-param@example.org");
+            assert_eq!(draft.unwrap(), "hi");
+        } else {
+            bail!("Wrong QR code type");
+        }
+
+        Ok(())
+    }
+
+    #[tokio::test(flavor = "multi_thread", worker_threads = 2)]
+    async fn test_decode_vcard_several_contacts() -> Result<()> {
+        let ctx = TestContext::new().await;
+        let vcard = "BEGIN:VCARD\nVERSION:4.0\nEMAIL:bob@example.net\nFN:Bob\nEND:VCARD\n\
+                     BEGIN:VCARD\nVERSION:4.0\nEMAIL:claire@example.org\nFN:Claire\nEND:VCARD\n";
+
+        let qr = check_qr(&ctx, vcard).await?;
+        assert_eq!(
+            qr,
+            Qr::Vcard {
+                vcard: vcard.to_string(),
+                addrs: vec![
+                    "bob@example.net".to_string(),
+                    "claire@example.org".to_string()
+                ],
+            }
+        );
+        assert!(
+            Contact::lookup_id_by_addr(&ctx, "claire@example.org", Origin::Unknown)
+                .await?
+                .is_none()
+        );
+
+        set_config_from_qr(&ctx, vcard).await?;
+        let contact_id = Contact::lookup_id_by_addr(&ctx, "claire@example.org", Origin::Unknown)
+            .await?
+            .unwrap();
+        let contact = Contact::get_by_id(&ctx, contact_id).await?;
+        assert_eq!(contact.get_authname(), "Claire");
+
         Ok(())
     }
 
@@ -1177,6 +1408,7 @@ async fn test_decode_openpgp_fingerprint() -> Result<()> {
             secondary_verifier: None,
             backward_verified_key_id: None,
             fingerprint_changed: false,
+            old_fingerprint: None,
         };
         assert!(
             peerstate.save_to_db(&ctx.ctx.sql).await.is_ok(),
@@ -1253,6 +1485,32 @@ async fn test_decode_openpgp_without_addr() -> Result<()> {
         Ok(())
     }
 
+    #[tokio::test(flavor = "multi_thread", worker_threads = 2)]
+    async fn test_decode_plain_fingerprint() -> Result<()> {
+        let ctx = TestContext::new().await;
+
+        let qr = check_qr(&ctx, "1234 5678 9012 3456 7890  1234 5678 9012 3456 7890").await?;
+        assert_eq!(
+            qr,
+            Qr::FprWithoutAddr {
+                fingerprint: "1234 5678 9012 3456 7890\n1234 5678 9012 3456 7890".to_string()
+            }
+        );
+
+        let qr = check_qr(
+            &ctx,
+            "1234567890123456789012345678901234567890?a=bob@example.net",
+        )
+        .await?;
+        assert_eq!(qr, Qr::FprMismatch { contact_id: None });
+
+        // Not a fingerprint, too short.
+        let qr = check_qr(&ctx, "1234 5678 9012 3456 7890").await?;
+        assert!(matches!(qr, Qr::Text { .. }));
+
+        Ok(())
+    }
+
     #[tokio::test(flavor = "multi_thread", worker_threads = 2)]
     async fn test_withdraw_verifycontact() -> Result<()> {
         let alice = TestContext::new_alice().await;
@@ -4,6 +4,7 @@
 
 use deltachat_contact_tools::may_be_valid_addr;
 use num_traits::cast::ToPrimitive;
+use percent_encoding::percent_decode_str;
 
 use super::{Qr, DCLOGIN_SCHEME};
 use crate::config::Config;
@@ -78,6 +79,10 @@ pub(super) fn decode_login(qr: &str) -> Result<Qr> {
         .split(|c| c == '?' || c == '/')
         .next()
         .context("invalid DCLOGIN payload E3")?;
+    let addr = percent_decode_str(addr)
+        .decode_utf8()
+        .context("invalid DCLOGIN payload: address is not UTF-8")?;
+    let addr = addr.as_ref();
 
     if url.scheme().eq_ignore_ascii_case("dclogin") {
         let options = url.query_pairs();
@@ -185,80 +190,67 @@ pub(crate) async fn configure_from_login_qr(
             smtp_security,
             smtp_certificate_checks,
         } => {
-            context
-                .set_config_internal(Config::MailPw, Some(&mail_pw))
-                .await?;
-            if let Some(value) = imap_host {
-                context
-                    .set_config_internal(Config::MailServer, Some(&value))
-                    .await?;
-            }
-            if let Some(value) = imap_port {
-                context
-                    .set_config_internal(Config::MailPort, Some(&value.to_string()))
-                    .await?;
-            }
-            if let Some(value) = imap_username {
-                context
-                    .set_config_internal(Config::MailUser, Some(&value))
-                    .await?;
-            }
-            if let Some(value) = imap_password {
-                context
-                    .set_config_internal(Config::MailPw, Some(&value))
-                    .await?;
-            }
-            if let Some(value) = imap_security {
-                let code = value
-                    .to_u8()
-                    .context("could not convert imap security value to number")?;
-                context
-                    .set_config_internal(Config::MailSecurity, Some(&code.to_string()))
-                    .await?;
-            }
-            if let Some(value) = imap_certificate_checks {
-                let code = value
-                    .to_u32()
-                    .context("could not convert imap certificate checks value to number")?;
-                context
-                    .set_config_internal(Config::ImapCertificateChecks, Some(&code.to_string()))
-                    .await?;
-            }
-            if let Some(value) = smtp_host {
-                context
-                    .set_config_internal(Config::SendServer, Some(&value))
-                    .await?;
-            }
-            if let Some(value) = smtp_port {
-                context
-                    .set_config_internal(Config::SendPort, Some(&value.to_string()))
-                    .await?;
-            }
-            if let Some(value) = smtp_username {
-                context
-                    .set_config_internal(Config::SendUser, Some(&value))
-                    .await?;
-            }
-            if let Some(value) = smtp_password {
-                context
-                    .set_config_internal(Config::SendPw, Some(&value))
-                    .await?;
-            }
-            if let Some(value) = smtp_security {
-                let code = value
-                    .to_u8()
-                    .context("could not convert smtp security value to number")?;
-                context
-                    .set_config_internal(Config::SendSecurity, Some(&code.to_string()))
-                    .await?;
-            }
-            if let Some(value) = smtp_certificate_checks {
-                let code = value
-                    .to_u32()
-                    .context("could not convert smtp certificate checks value to number")?;
-                context
-                    .set_config_internal(Config::SmtpCertificateChecks, Some(&code.to_string()))
-                    .await?;
+            // Parameters missing in the QR code are reset,
+            // so that settings left over from an earlier attempt
+            // do not get mixed into the provisioned configuration.
+            let imap_security = imap_security
+                .map(|value| {
+                    value
+                        .to_u8()
+                        .context("could not convert imap security value to number")
+                })
+                .transpose()?;
+            let imap_certificate_checks = imap_certificate_checks
+                .map(|value| {
+                    value
+                        .to_u32()
+                        .context("could not convert imap certificate checks value to number")
+                })
+                .transpose()?;
+            let smtp_security = smtp_security
+                .map(|value| {
+                    value
+                        .to_u8()
+                        .context("could not convert smtp security value to number")
+                })
+                .transpose()?;
+            let smtp_certificate_checks = smtp_certificate_checks
+                .map(|value| {
+                    value
+                        .to_u32()
+                        .context("could not convert smtp certificate checks value to number")
+                })
+                .transpose()?;
+
+            let mail_pw = imap_password.unwrap_or(mail_pw);
+            let params = [
+                (Config::MailPw, Some(mail_pw)),
+                (Config::MailServer, imap_host),
+                (Config::MailPort, imap_port.map(|port| port.to_string())),
+                (Config::MailUser, imap_username),
+                (
+                    Config::MailSecurity,
+                    imap_security.map(|code| code.to_string()),
+                ),
+                (
+                    Config::ImapCertificateChecks,
+                    imap_certificate_checks.map(|code| code.to_string()),
+                ),
+                (Config::SendServer, smtp_host),
+                (Config::SendPort, smtp_port.map(|port| port.to_string())),
+                (Config::SendUser, smtp_username),
+                (Config::SendPw, smtp_password),
+                (
+                    Config::SendSecurity,
+                    smtp_security.map(|code| code.to_string()),
+                ),
+                (
+                    Config::SmtpCertificateChecks,
+                    smtp_certificate_checks.map(|code| code.to_string()),
+                ),
+            ];
+            for (key, value) in params {
+                context.set_config_internal(key, value.as_deref()).await?;
             }
             Ok(())
         }
@@ -272,7 +264,9 @@ pub(crate) async fn configure_from_login_qr(
 mod test {
     use anyhow::bail;
 
-    use super::{decode_login, LoginOptions};
+    use super::{configure_from_login_qr, decode_login, LoginOptions};
+    use crate::config::Config;
+    use crate::test_utils::TestContext;
     use crate::{login_param::CertificateChecks, provider::Socket, qr::Qr};
 
     macro_rules! login_options_just_pw {
@@ -435,4 +429,50 @@ fn email_with_plus_extension() -> anyhow::Result<()> {
         }
         Ok(())
     }
+
+    #[test]
+    fn percent_encoded_address() -> anyhow::Result<()> {
+        let result = decode_login("dclogin:user%2Bext%40host.tld?p=1234&v=1")?;
+        if let Qr::Login { address, .. } = result {
+            assert_eq!(address, "user+ext@host.tld".to_owned());
+        } else {
+            bail!("wrong type")
+        }
+        Ok(())
+    }
+
+    #[tokio::test(flavor = "multi_thread", worker_threads = 2)]
+    async fn test_configure_from_login_qr_resets_params() -> anyhow::Result<()> {
+        let t = TestContext::new().await;
+        t.set_config(Config::MailServer, Some("stale.example.org"))
+            .await?;
+        t.set_config(Config::SendPw, Some("stale")).await?;
+
+        let Qr::Login { address, options } =
+            decode_login("dclogin:alice@host.tld?p=secret&v=1&sh=smtp.host.tld&sp=465")?
+        else {
+            bail!("wrong type")
+        };
+        configure_from_login_qr(&t, &address, options).await?;
+
+        assert_eq!(
+            t.get_config(Config::Addr).await?.as_deref(),
+            Some("alice@host.tld")
+        );
+        assert_eq!(
+            t.get_config(Config::MailPw).await?.as_deref(),
+            Some("secret")
+        );
+        assert_eq!(t.get_config(Config::MailServer).await?, None);
+        assert_eq!(t.get_config(Config::SendPw).await?, None);
+        assert_eq!(
+            t.get_config(Config::SendServer).await?.as_deref(),
+            Some("smtp.host.tld")
+        );
+        assert_eq!(
+            t.get_config(Config::SendPort).await?.as_deref(),
+            Some("465")
+        );
+        Ok(())
+    }
 }
@@ -1,7 +1,11 @@
 //! # QR code generation module.
 
-use anyhow::Result;
+use std::io::Cursor;
+
+use anyhow::{Context as _, Result};
 use base64::Engine as _;
+use image::imageops::FilterType;
+use image::{DynamicImage, ImageFormat, Rgb, RgbImage};
 use qrcodegen::{QrCode, QrCodeEcc};
 
 use crate::blob::BlobObject;
@@ -10,6 +14,7 @@
 use crate::config::Config;
 use crate::contact::{Contact, ContactId};
 use crate::context::Context;
+use crate::log::LogExt;
 use crate::qr::{self, Qr};
 use crate::securejoin;
 use crate::stock_str::{self, backup_transfer_qr};
@@ -26,6 +31,66 @@ pub async fn get_securejoin_qr_svg(context: &Context, chat_id: Option<ChatId>) -
     }
 }
 
+/// Returns PNG of the QR code to join the group or verify contact.
+///
+/// Like [`get_securejoin_qr_svg`], the image contains the chat or profile avatar
+/// in the middle, or a dot in the chat or profile color if there is no avatar.
+/// Unlike the SVG, it contains no description and footer logo,
+/// so it can be shown by UIs that cannot render SVG.
+/// If `chat_id` is `None`, returns verification QR code.
+pub async fn get_securejoin_qr_png(context: &Context, chat_id: Option<ChatId>) -> Result<Vec<u8>> {
+    let (color, avatar_path) = if let Some(chat_id) = chat_id {
+        let chat = Chat::load_from_db(context, chat_id).await?;
+        (
+            chat.get_color(context).await?,
+            chat.get_profile_image(context).await?,
+        )
+    } else {
+        let contact = Contact::get_by_id(context, ContactId::SELF).await?;
+        (
+            contact.get_color(),
+            contact.get_profile_image(context).await?,
+        )
+    };
+    let avatar = match avatar_path {
+        Some(path) => {
+            let avatar_blob = BlobObject::from_path(context, &path)?;
+            let avatar = tokio::fs::read(avatar_blob.to_abs_path()).await?;
+            image::load_from_memory(&avatar)
+                .context("Failed to decode avatar for QR code")
+                .log_err(context)
+                .ok()
+        }
+        None => None,
+    };
+    let content = securejoin::get_securejoin_qr(context, chat_id).await?;
+    inner_generate_qr_png(&content, color, avatar.as_ref())
+}
+
+/// Renders `content` as a QR code made of Unicode half blocks,
+/// suitable for showing in a terminal.
+pub fn generate_qr_text(content: &str) -> Result<String> {
+    let qr = QrCode::encode_text(content, QrCodeEcc::Medium)?;
+    let border = 2;
+    let is_dark = |x: i32, y: i32| qr.get_module(x, y);
+
+    let mut text = String::new();
+    for y in (-border..qr.size() + border).step_by(2) {
+        for x in -border..qr.size() + border {
+            // Dark modules are printed as spaces so that the code
+            // is readable on terminals with dark background.
+            text.push(match (is_dark(x, y), is_dark(x, y + 1)) {
+                (false, false) => '\u{2588}',
+                (false, true) => '\u{2580}',
+                (true, false) => '\u{2584}',
+                (true, true) => ' ',
+            });
+        }
+        text.push('\n');
+    }
+    Ok(text)
+}
+
 async fn generate_join_group_qr_code(context: &Context, chat_id: ChatId) -> Result<String> {
     let chat = Chat::load_from_db(context, chat_id).await?;
 
@@ -94,6 +159,60 @@ async fn self_info(context: &Context) -> Result<(Option<Vec<u8>>, String, String
     Ok((avatar, displayname, addr, color))
 }
 
+fn inner_generate_qr_png(
+    content: &str,
+    color: u32,
+    avatar: Option<&DynamicImage>,
+) -> Result<Vec<u8>> {
+    const MODULE_SIZE: u32 = 10;
+    const BORDER: u32 = 4;
+    let qr = QrCode::encode_text(content, QrCodeEcc::Medium)?;
+    let qr_size = u32::try_from(qr.size())?;
+    let image_size = (qr_size + 2 * BORDER) * MODULE_SIZE;
+
+    // Same proportions as the avatar in the middle of the SVG code.
+    let center = image_size as f32 / 2.0;
+    let dot_radius = (qr_size * MODULE_SIZE) as f32 * 47.2 / 400.0;
+    let dot_border_radius = (qr_size * MODULE_SIZE) as f32 * 56.2 / 400.0;
+    let dot_color = Rgb([(color >> 16) as u8, (color >> 8) as u8, color as u8]);
+    let dot_origin = center - dot_radius;
+    let avatar = avatar.map(|avatar| {
+        let diameter = (2.0 * dot_radius).ceil() as u32;
+        avatar
+            .resize_to_fill(diameter, diameter, FilterType::Triangle)
+            .to_rgb8()
+    });
+
+    let img = RgbImage::from_fn(image_size, image_size, |x, y| {
+        let dx = x as f32 + 0.5 - center;
+        let dy = y as f32 + 0.5 - center;
+        let distance = (dx * dx + dy * dy).sqrt();
+        if distance <= dot_radius {
+            let avatar_x = (x as f32 - dot_origin).max(0.0) as u32;
+            let avatar_y = (y as f32 - dot_origin).max(0.0) as u32;
+            return avatar
+                .as_ref()
+                .and_then(|avatar| avatar.get_pixel_checked(avatar_x, avatar_y))
+                .copied()
+                .unwrap_or(dot_color);
+        }
+        if distance <= dot_border_radius {
+            return Rgb([0xff, 0xff, 0xff]);
+        }
+        let module_x = (x / MODULE_SIZE) as i32 - BORDER as i32;
+        let module_y = (y / MODULE_SIZE) as i32 - BORDER as i32;
+        if qr.get_module(module_x, module_y) {
+            Rgb([0, 0, 0])
+        } else {
+            Rgb([0xff, 0xff, 0xff])
+        }
+    });
+
+    let mut buf = Cursor::new(Vec::new());
+    img.write_to(&mut buf, ImageFormat::Png)?;
+    Ok(buf.into_inner())
+}
+
 fn inner_generate_secure_join_qr_code(
     qrcode_description: &str,
     qrcode_content: &str,
@@ -317,6 +436,42 @@ async fn test_svg_escaping() {
         assert!(svg.contains("descr123 &quot; &lt; &gt; &amp;"))
     }
 
+    #[tokio::test(flavor = "multi_thread", worker_threads = 2)]
+    async fn test_securejoin_qr_png() -> Result<()> {
+        let mut tcm = TestContextManager::new();
+        let alice = tcm.alice().await;
+        let png = get_securejoin_qr_png(&alice, None).await?;
+        let img = image::load_from_memory_with_format(&png, ImageFormat::Png)?;
+        assert_eq!(img.width(), img.height());
+        // The quiet zone is white.
+        assert_eq!(img.to_rgb8().get_pixel(0, 0), &Rgb([0xff, 0xff, 0xff]));
+        // Without avatar, there is a dot in the profile color in the middle.
+        let color = Contact::get_by_id(&alice, ContactId::SELF)
+            .await?
+            .get_color();
+        let center = img.width() / 2;
+        assert_eq!(
+            img.to_rgb8().get_pixel(center, center),
+            &Rgb([(color >> 16) as u8, (color >> 8) as u8, color as u8])
+        );
+
+        // The avatar is shown in the middle like in the SVG.
+        let avatar_file = alice.get_blobdir().join("avatar.png");
+        RgbImage::from_pixel(100, 100, Rgb([0xff, 0, 0])).save(&avatar_file)?;
+        alice
+            .set_config(Config::Selfavatar, Some(avatar_file.to_str().unwrap()))
+            .await?;
+        let png = get_securejoin_qr_png(&alice, None).await?;
+        let img = image::load_from_memory_with_format(&png, ImageFormat::Png)?;
+        let Rgb([r, g, b]) = *img.to_rgb8().get_pixel(center, center);
+        assert!(r > 0xe0 && g < 0x20 && b < 0x20);
+
+        let text = generate_qr_text("https://i.delta.chat/")?;
+        assert!(text.lines().count() > 10);
+        assert!(text.contains('\u{2588}'));
+        Ok(())
+    }
+
     #[tokio::test(flavor = "multi_thread", worker_threads = 2)]
     async fn test_generate_backup_qr() {
         let dir = testdir!();
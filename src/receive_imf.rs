@@ -12,26 +12,33 @@
 use regex::Regex;
 
 use crate::aheader::EncryptPreference;
+use crate::authres::AuthenticationResults;
+use crate::bot_commands::receive_bot_commands;
 use crate::chat::{self, Chat, ChatId, ChatIdBlocked, ProtectionStatus};
 use crate::config::Config;
 use crate::constants::{self, Blocked, Chattype, ShowEmails, DC_CHAT_ID_TRASH};
 use crate::contact::{Contact, ContactId, Origin};
 use crate::context::Context;
 use crate::debug_logging::maybe_set_logging_xdc_inner;
+use crate::disk_space::InsufficientDiskSpace;
 use crate::download::DownloadState;
 use crate::ephemeral::{stock_ephemeral_timer_changed, Timer as EphemeralTimer};
 use crate::events::EventType;
 use crate::headerdef::{HeaderDef, HeaderDefMap};
 use crate::imap::{markseen_on_imap_table, GENERATED_PREFIX};
 use crate::log::LogExt;
+use crate::mention;
 use crate::message::{
-    self, rfc724_mid_exists, rfc724_mid_exists_ex, Message, MessageState, MessengerMessage, MsgId,
-    Viewtype,
+    self, rfc724_mid_exists, rfc724_mid_exists_ex, ChatAssignment, Message, MessageState,
+    MessengerMessage, MsgId, Viewtype,
+};
+use crate::mimeparser::{
+    parse_message_id, parse_message_ids, AvatarAction, MimeMessage, SystemMessage,
 };
-use crate::mimeparser::{parse_message_ids, AvatarAction, MimeMessage, SystemMessage};
 use crate::param::{Param, Params};
 use crate::peer_channels::{get_iroh_topic_for_msg, insert_topic_stub, iroh_add_peer_for_topic};
 use crate::peerstate::Peerstate;
+use crate::poll;
 use crate::reaction::{set_msg_reaction, Reaction};
 use crate::securejoin::{self, handle_securejoin_handshake, observe_securejoin_on_other_device};
 use crate::simplify;
@@ -124,6 +131,23 @@ pub(crate) async fn receive_imf_from_inbox(
         seen,
         is_partial_download,
         fetching_existing_messages,
+        false,
+    )
+    .await
+}
+
+/// Adds a message imported from a file, e.g. an old archived message.
+///
+/// The message is assigned to a chat and marked as seen as if it was received,
+/// but Autocrypt and gossip headers, sync items, Secure-Join handshakes
+/// and group membership changes are not applied.
+pub(crate) async fn receive_imf_imported(
+    context: &Context,
+    rfc724_mid: &str,
+    imf_raw: &[u8],
+) -> Result<Option<ReceivedMsg>> {
+    receive_imf_inner(
+        context, "INBOX", 0, 0, rfc724_mid, imf_raw, true, None, false, true,
     )
     .await
 }
@@ -157,6 +181,8 @@ async fn insert_tombstone(context: &Context, rfc724_mid: &str) -> Result<MsgId>
 /// If `is_partial_download` is set, it contains the full message size in bytes.
 /// Do not confuse that with `replace_msg_id` that will be set when the full message is loaded
 /// later.
+///
+/// If `imported` is set, the message is imported from a file, see [`receive_imf_imported`].
 #[allow(clippy::too_many_arguments)]
 pub(crate) async fn receive_imf_inner(
     context: &Context,
@@ -168,6 +194,7 @@ pub(crate) async fn receive_imf_inner(
     seen: bool,
     is_partial_download: Option<u32>,
     fetching_existing_messages: bool,
+    imported: bool,
 ) -> Result<Option<ReceivedMsg>> {
     if std::env::var(crate::DCC_MIME_DEBUG).is_ok() {
         info!(
@@ -177,8 +204,10 @@ pub(crate) async fn receive_imf_inner(
         );
     }
 
-    let mut mime_parser = match MimeMessage::from_bytes(context, imf_raw, is_partial_download).await
-    {
+    let parsed = MimeMessage::from_bytes_ex(context, imf_raw, is_partial_download, imported).await;
+    let mut mime_parser = match parsed {
+        // Do not add a tombstone, the message should be received again later.
+        Err(err) if err.is::<InsufficientDiskSpace>() => return Err(err),
         Err(err) => {
             warn!(context, "receive_imf: can't parse MIME: {err:#}.");
             if rfc724_mid.starts_with(GENERATED_PREFIX) {
@@ -201,10 +230,21 @@ pub(crate) async fn receive_imf_inner(
         Ok(mime_parser) => mime_parser,
     };
 
-    crate::peerstate::maybe_do_aeap_transition(context, &mut mime_parser).await?;
-    if let Some(peerstate) = &mime_parser.decryption_info.peerstate {
+    if !imported {
+        crate::peerstate::maybe_do_aeap_transition(context, &mut mime_parser).await?;
+    }
+    if let Some(peerstate) = mime_parser
+        .decryption_info
+        .peerstate
+        .as_ref()
+        .filter(|_| !imported)
+    {
         peerstate
-            .handle_fingerprint_change(context, mime_parser.timestamp_sent)
+            .handle_fingerprint_change(
+                context,
+                mime_parser.timestamp_sent,
+                mime_parser.get_rfc724_mid().as_deref(),
+            )
             .await?;
         // When peerstate is set to Mutual, it's saved immediately to not lose that fact in case
         // of an error. Otherwise we don't save peerstate until get here to reduce the number of
@@ -332,6 +372,24 @@ pub(crate) async fn receive_imf_inner(
             }
         };
 
+    if mime_parser.incoming {
+        let mut auth_results = mime_parser.decryption_info.dkim_results.auth_results;
+        if auth_results != AuthenticationResults::default() {
+            // A contact we are already talking to is a likely target of From forgery.
+            auth_results.dkim_warning = auth_results.dkim_failed() && incoming_origin.is_known();
+            if auth_results.dkim_warning {
+                warn!(
+                    context,
+                    "receive_imf: DKIM failed for known contact {from_id}: {auth_results}."
+                );
+            }
+            for part in &mut mime_parser.parts {
+                part.param
+                    .set(Param::AuthenticationResults, auth_results.to_string());
+            }
+        }
+    }
+
     let to_ids = add_or_lookup_contacts_by_address_list(
         context,
         &mime_parser.recipients,
@@ -348,7 +406,19 @@ pub(crate) async fn receive_imf_inner(
     update_verified_keys(context, &mut mime_parser, from_id).await?;
 
     let received_msg;
-    if mime_parser.get_header(HeaderDef::SecureJoin).is_some() {
+    if imported && mime_parser.get_header(HeaderDef::SecureJoin).is_some() {
+        info!(context, "Not importing Secure-Join message (TRASH).");
+        let msg_ids = vec![insert_tombstone(context, rfc724_mid).await?];
+        return Ok(Some(ReceivedMsg {
+            chat_id: DC_CHAT_ID_TRASH,
+            state: MessageState::Undefined,
+            sort_timestamp: 0,
+            msg_ids,
+            needs_delete_job: false,
+            #[cfg(test)]
+            from_is_signed: false,
+        }));
+    } else if mime_parser.get_header(HeaderDef::SecureJoin).is_some() {
         let res;
         if mime_parser.incoming {
             res = handle_securejoin_handshake(context, &mime_parser, from_id)
@@ -465,7 +535,9 @@ pub(crate) async fn receive_imf_inner(
     save_locations(context, &mime_parser, chat_id, from_id, insert_msg_id).await?;
 
     if let Some(ref sync_items) = mime_parser.sync_items {
-        if from_id == ContactId::SELF {
+        if imported {
+            info!(context, "Not executing sync items of imported message.");
+        } else if from_id == ContactId::SELF {
             if mime_parser.was_encrypted() {
                 context.execute_sync_items(sync_items).await;
             } else {
@@ -622,6 +694,27 @@ pub(crate) async fn receive_imf_inner(
 
     if let Some(is_bot) = mime_parser.is_bot {
         from_id.mark_bot(context, is_bot).await?;
+        if is_bot && from_id != ContactId::SELF {
+            if let Some(bot_commands) = mime_parser.get_header(HeaderDef::ChatBotCommands) {
+                receive_bot_commands(context, from_id, bot_commands)
+                    .await
+                    .log_err(context)
+                    .ok();
+            }
+        }
+    }
+
+    #[cfg(feature = "pq-hybrid")]
+    if from_id != ContactId::SELF {
+        if let (Some(pq_key), Some(signer)) = (
+            mime_parser.get_header(HeaderDef::ChatPqKey),
+            mime_parser.signatures.iter().next(),
+        ) {
+            crate::pq::receive_pq_key(context, from_id, pq_key, signer)
+                .await
+                .log_err(context)
+                .ok();
+        }
     }
 
     Ok(Some(received_msg))
@@ -703,6 +796,7 @@ async fn add_parts(
 
     let mut chat_id = None;
     let mut chat_id_blocked = Blocked::Not;
+    let mut chat_assignment = ChatAssignment::Unknown;
 
     let mut better_msg = None;
     let mut group_changes_msgs = (Vec::new(), None);
@@ -823,6 +917,7 @@ async fn add_parts(
                 {
                     chat_id = Some(id);
                     chat_id_blocked = blocked;
+                    chat_assignment = ChatAssignment::GroupId;
                 } else if allow_creation || test_normal_chat.is_some() {
                     if let Some((new_chat_id, new_chat_id_blocked)) = create_group(
                         context,
@@ -838,26 +933,29 @@ async fn add_parts(
                     {
                         chat_id = Some(new_chat_id);
                         chat_id_blocked = new_chat_id_blocked;
+                        chat_assignment = ChatAssignment::GroupId;
                     }
                 }
             }
         }
 
         if chat_id.is_none() {
-            if let Some((new_chat_id, new_chat_id_blocked)) = lookup_chat_or_create_adhoc_group(
-                context,
-                mime_parser,
-                &parent,
-                to_ids,
-                from_id,
-                allow_creation || test_normal_chat.is_some(),
-                create_blocked,
-                is_partial_download.is_some(),
-            )
-            .await?
+            if let Some((new_chat_id, new_chat_id_blocked, new_chat_assignment)) =
+                lookup_chat_or_create_adhoc_group(
+                    context,
+                    mime_parser,
+                    &parent,
+                    to_ids,
+                    from_id,
+                    allow_creation || test_normal_chat.is_some(),
+                    create_blocked,
+                    is_partial_download.is_some(),
+                )
+                .await?
             {
                 chat_id = Some(new_chat_id);
                 chat_id_blocked = new_chat_id_blocked;
+                chat_assignment = new_chat_assignment;
             }
         }
 
@@ -920,6 +1018,7 @@ async fn add_parts(
                 {
                     chat_id = Some(new_chat_id);
                     chat_id_blocked = new_chat_id_blocked;
+                    chat_assignment = ChatAssignment::MailingList;
                 }
             }
         }
@@ -928,6 +1027,22 @@ async fn add_parts(
             apply_mailinglist_changes(context, mime_parser, chat_id).await?;
         }
 
+        if chat_id.is_none()
+            && test_normal_chat.is_some()
+            && is_spoofed(context, mime_parser).await?
+        {
+            // Do not let a forged message appear in the existing 1:1 chat.
+            let (new_chat_id, new_chat_id_blocked) =
+                lookup_or_create_spoofing_quarantine(context, mime_parser, from_id).await?;
+            chat_id = Some(new_chat_id);
+            chat_id_blocked = new_chat_id_blocked;
+            chat_assignment = ChatAssignment::SpoofingQuarantine;
+            let s = stock_str::spoofed_msg(context, &mime_parser.from.addr).await;
+            for part in &mut mime_parser.parts {
+                part.error = Some(s.clone());
+            }
+        }
+
         if chat_id.is_none() {
             // try to create a normal chat
             let create_blocked = if from_id == ContactId::SELF {
@@ -944,6 +1059,7 @@ async fn add_parts(
             if let Some(chat) = test_normal_chat {
                 chat_id = Some(chat.id);
                 chat_id_blocked = chat.blocked;
+                chat_assignment = ChatAssignment::OneOneChat;
             } else if allow_creation {
                 if let Ok(chat) = ChatIdBlocked::get_for_contact(context, from_id, create_blocked)
                     .await
@@ -952,6 +1068,7 @@ async fn add_parts(
                 {
                     chat_id = Some(chat.id);
                     chat_id_blocked = chat.blocked;
+                    chat_assignment = ChatAssignment::OneOneChat;
                 }
             }
 
@@ -1060,6 +1177,7 @@ async fn add_parts(
                 {
                     chat_id = Some(id);
                     chat_id_blocked = blocked;
+                    chat_assignment = ChatAssignment::GroupId;
                 } else if allow_creation {
                     if let Some((new_chat_id, new_chat_id_blocked)) = create_group(
                         context,
@@ -1075,6 +1193,7 @@ async fn add_parts(
                     {
                         chat_id = Some(new_chat_id);
                         chat_id_blocked = new_chat_id_blocked;
+                        chat_assignment = ChatAssignment::GroupId;
                     }
                 }
             }
@@ -1112,20 +1231,22 @@ async fn add_parts(
         }
 
         if chat_id.is_none() {
-            if let Some((new_chat_id, new_chat_id_blocked)) = lookup_chat_or_create_adhoc_group(
-                context,
-                mime_parser,
-                &parent,
-                to_ids,
-                from_id,
-                allow_creation,
-                Blocked::Not,
-                is_partial_download.is_some(),
-            )
-            .await?
+            if let Some((new_chat_id, new_chat_id_blocked, new_chat_assignment)) =
+                lookup_chat_or_create_adhoc_group(
+                    context,
+                    mime_parser,
+                    &parent,
+                    to_ids,
+                    from_id,
+                    allow_creation,
+                    Blocked::Not,
+                    is_partial_download.is_some(),
+                )
+                .await?
             {
                 chat_id = Some(new_chat_id);
                 chat_id_blocked = new_chat_id_blocked;
+                chat_assignment = new_chat_assignment;
             }
         }
 
@@ -1138,18 +1259,25 @@ async fn add_parts(
                     {
                         chat_id = Some(id);
                         chat_id_blocked = blocked;
+                        chat_assignment = ChatAssignment::MailingList;
                     }
                 } else if let Ok(chat) =
                     ChatIdBlocked::get_for_contact(context, to_id, Blocked::Not).await
                 {
                     chat_id = Some(chat.id);
                     chat_id_blocked = chat.blocked;
+                    chat_assignment = if to_id == ContactId::SELF {
+                        ChatAssignment::SelfChat
+                    } else {
+                        ChatAssignment::OneOneChat
+                    };
                 }
             }
             if chat_id.is_none() && is_dc_message == MessengerMessage::Yes {
                 if let Some(chat) = ChatIdBlocked::lookup_by_contact(context, to_id).await? {
                     chat_id = Some(chat.id);
                     chat_id_blocked = chat.blocked;
+                    chat_assignment = ChatAssignment::OneOneChat;
                 }
             }
 
@@ -1185,6 +1313,7 @@ async fn add_parts(
             {
                 chat_id = Some(chat.id);
                 chat_id_blocked = chat.blocked;
+                chat_assignment = ChatAssignment::SelfChat;
             }
 
             if let Some(chat_id) = chat_id {
@@ -1208,6 +1337,7 @@ async fn add_parts(
                         chat::create_broadcast_list_ex(context, Nosync, listid, name).await?
                     },
                 );
+                chat_assignment = ChatAssignment::MailingList;
             }
         }
     }
@@ -1342,6 +1472,32 @@ async fn add_parts(
         ephemeral_timer = EphemeralTimer::Disabled;
     }
 
+    if let SystemMessage::MessagePinned | SystemMessage::MessageUnpinned =
+        mime_parser.is_system_message
+    {
+        let pinned = mime_parser.is_system_message == SystemMessage::MessagePinned;
+        better_msg = Some(if pinned {
+            stock_str::msg_pinned(context, from_id).await
+        } else {
+            stock_str::msg_unpinned(context, from_id).await
+        });
+        let pinned_msg = mime_parser
+            .get_header(HeaderDef::ChatPinnedMessage)
+            .and_then(|value| parse_message_id(value).ok());
+        if let (Some(pinned_msg), false) = (pinned_msg, chat_id.is_special()) {
+            chat::receive_pin(
+                context,
+                chat_id,
+                &pinned_msg,
+                pinned,
+                mime_parser.timestamp_sent,
+            )
+            .await
+            .log_err(context)
+            .ok();
+        }
+    }
+
     // if a chat is protected and the message is fully downloaded, check additional properties
     if !chat_id.is_special() && is_partial_download.is_none() {
         let chat = Chat::load_from_db(context, chat_id).await?;
@@ -1387,6 +1543,17 @@ async fn add_parts(
         .get_header(HeaderDef::References)
         .unwrap_or_default();
 
+    // Classic emails have no explicit thread root,
+    // the first Message-ID in `References` is the root of the email thread.
+    let thread_root = if let Some(thread_root) = mime_parser.get_header(HeaderDef::ChatThreadRoot) {
+        parse_message_id(thread_root).ok()
+    } else if !mime_parser.has_chat_version() {
+        parse_message_ids(mime_references).into_iter().next()
+    } else {
+        None
+    };
+    let thread_root = thread_root.filter(|thread_root| thread_root != rfc724_mid_orig);
+
     // fine, so far.  now, split the message into simple parts usable as "short messages"
     // and add them to the database (mails sent by other messenger clients should result
     // into only one message; mails sent by other clients may result in several messages
@@ -1465,6 +1632,74 @@ async fn add_parts(
         }
     }
 
+    if let Some(vote) = mime_parser.get_header(HeaderDef::ChatPollVote) {
+        poll::receive_poll_vote(context, mime_in_reply_to, from_id, vote)
+            .await
+            .log_err(context)
+            .ok();
+        chat_id = DC_CHAT_ID_TRASH;
+    } else if mime_parser.get_header(HeaderDef::ChatPollClose).is_some() {
+        poll::receive_poll_close(context, mime_in_reply_to, from_id, true)
+            .await
+            .log_err(context)
+            .ok();
+        chat_id = DC_CHAT_ID_TRASH;
+    } else if mime_parser.get_header(HeaderDef::ChatPollReopen).is_some() {
+        poll::receive_poll_close(context, mime_in_reply_to, from_id, false)
+            .await
+            .log_err(context)
+            .ok();
+        chat_id = DC_CHAT_ID_TRASH;
+    }
+    if let Some(edit_for) = mime_parser.get_header(HeaderDef::ChatEdit) {
+        let new_text = mime_parser
+            .parts
+            .iter()
+            .find(|part| part.typ == Viewtype::Text)
+            .map_or("", |part| part.msg.as_str());
+        if let Ok(edit_for) = parse_message_id(edit_for) {
+            chat::receive_edit(
+                context,
+                &edit_for,
+                from_id,
+                new_text,
+                mime_parser.timestamp_sent,
+                mime_parser.was_encrypted(),
+            )
+            .await
+            .log_err(context)
+            .ok();
+        }
+        chat_id = DC_CHAT_ID_TRASH;
+    }
+    if let Some(delete_for) = mime_parser.get_header(HeaderDef::ChatDelete) {
+        message::receive_delete_request(
+            context,
+            &parse_message_ids(delete_for),
+            from_id,
+            mime_parser.was_encrypted(),
+        )
+        .await
+        .log_err(context)
+        .ok();
+        chat_id = DC_CHAT_ID_TRASH;
+    }
+    let poll_json = match mime_parser.get_header(HeaderDef::ChatPoll) {
+        Some(header_value) => poll::parse_poll_header(header_value).log_err(context).ok(),
+        None => None,
+    };
+    let view_once = mime_parser.get_header(HeaderDef::ChatViewOnce).is_some();
+    // Mentions bypass muting, so only authenticated ones are honoured.
+    let mentions = match mime_parser.get_header(HeaderDef::ChatMentions) {
+        Some(header_value) if mime_parser.was_encrypted() && !mime_parser.signatures.is_empty() => {
+            mention::parse_mentions_header(context, header_value)
+                .await
+                .log_err(context)
+                .unwrap_or_default()
+        }
+        _ => Vec::new(),
+    };
+
     for part in &mime_parser.parts {
         if part.is_reaction {
             let reaction_str = simplify::remove_footers(part.msg.as_str());
@@ -1480,9 +1715,25 @@ async fn add_parts(
         }
 
         let mut param = part.param.clone();
+        if !chat_id.is_trash() {
+            param.set_int(Param::ChatAssignment, chat_assignment as i32);
+        }
         if is_system_message != SystemMessage::Unknown {
             param.set_int(Param::Cmd, is_system_message as i32);
         }
+        if let Some(poll_json) = &poll_json {
+            param.set(Param::Poll, poll_json);
+        }
+        if !mentions.is_empty() {
+            param.set(Param::Mentions, mention::render_mentions(&mentions));
+        }
+        if view_once && param.exists(Param::File) {
+            param.set_int(Param::ViewOnce, 1);
+            if from_id == ContactId::SELF {
+                // Our own copy is a placeholder, the unused blob is removed by housekeeping.
+                param.remove(Param::File);
+            }
+        }
 
         if let Some(replace_msg_id) = replace_msg_id {
             let placeholder = Message::load_from_db(context, replace_msg_id).await?;
@@ -1533,6 +1784,11 @@ async fn add_parts(
         // also change `MsgId::trash()` and `delete_expired_messages()`
         let trash =
             chat_id.is_trash() || (is_location_kml && msg.is_empty() && typ == Viewtype::Text);
+        let (txt_raw, txt_raw_compressed) = if trash {
+            (rusqlite::types::Value::Text(String::new()), false)
+        } else {
+            tokio::task::block_in_place(move || message::txt_raw_to_sql(txt_raw))?
+        };
 
         let row_id = context
             .sql
@@ -1548,7 +1804,8 @@ async fn add_parts(
     txt, txt_normalized, subject, txt_raw, param, hidden,
     bytes, mime_headers, mime_compressed, mime_in_reply_to,
     mime_references, mime_modified, error, ephemeral_timer,
-    ephemeral_timestamp, download_state, hop_info
+    ephemeral_timestamp, download_state, hop_info, thread_root,
+    txt_raw_compressed
   )
   VALUES (
     ?,
@@ -1557,7 +1814,8 @@ async fn add_parts(
     ?, ?, ?, ?, ?,
     ?, ?, ?, ?, ?, 1,
     ?, ?, ?, ?,
-    ?, ?, ?, ?
+    ?, ?, ?, ?, ?,
+    ?
   )
 ON CONFLICT (id) DO UPDATE
 SET rfc724_mid=excluded.rfc724_mid, chat_id=excluded.chat_id,
@@ -1568,7 +1826,8 @@ async fn add_parts(
     hidden=excluded.hidden,bytes=excluded.bytes, mime_headers=excluded.mime_headers,
     mime_compressed=excluded.mime_compressed, mime_in_reply_to=excluded.mime_in_reply_to,
     mime_references=excluded.mime_references, mime_modified=excluded.mime_modified, error=excluded.error, ephemeral_timer=excluded.ephemeral_timer,
-    ephemeral_timestamp=excluded.ephemeral_timestamp, download_state=excluded.download_state, hop_info=excluded.hop_info
+    ephemeral_timestamp=excluded.ephemeral_timestamp, download_state=excluded.download_state, hop_info=excluded.hop_info,
+    thread_root=excluded.thread_root, txt_raw_compressed=excluded.txt_raw_compressed
 RETURNING id
 "#)?;
                 let row_id: MsgId = stmt.query_row(params![
@@ -1587,7 +1846,7 @@ async fn add_parts(
                     if trash { None } else { message::normalize_text(msg) },
                     if trash { "" } else { &subject },
                     // txt_raw might contain invalid utf8
-                    if trash { "" } else { &txt_raw },
+                    txt_raw,
                     if trash {
                         "".to_string()
                     } else {
@@ -1613,7 +1872,9 @@ async fn add_parts(
                     } else {
                         DownloadState::Done
                     },
-                    mime_parser.hop_info
+                    mime_parser.hop_info,
+                    if trash { "" } else { thread_root.as_deref().unwrap_or_default() },
+                    txt_raw_compressed,
                 ],
                 |row| {
                     let msg_id: MsgId = row.get(0)?;
@@ -1632,6 +1893,50 @@ async fn add_parts(
         created_db_entries.push(row_id);
     }
 
+    if let (Some(signing_fingerprint), false) =
+        (&mime_parser.signing_fingerprint, chat_id.is_trash())
+    {
+        let verified = match &mime_parser.decryption_info.peerstate {
+            Some(peerstate) => {
+                peerstate.verified_key_fingerprint.as_ref() == Some(signing_fingerprint)
+            }
+            None => from_id == ContactId::SELF,
+        };
+        let security_info = message::SecurityInfo {
+            encrypted: mime_parser.was_encrypted(),
+            signing_fingerprint: Some(signing_fingerprint.clone()),
+            verified,
+            pq_hybrid: mime_parser.pq_hybrid,
+            gossiped: !mime_parser.gossiped_keys.is_empty(),
+        };
+        for msg_id in &created_db_entries {
+            message::save_security_info(context, *msg_id, &security_info).await?;
+        }
+    }
+
+    // link quotes to the quoted message so they are listed as its replies
+    for (part, msg_id) in mime_parser.parts.iter().zip(&created_db_entries) {
+        if part.param.exists(Param::Quote) && !part.param.exists(Param::Forwarded) {
+            message::set_quoted_msg_id(context, *msg_id, mime_in_reply_to).await?;
+        }
+    }
+
+    // replies may arrive before the quoted message
+    if !chat_id.is_trash() && !created_db_entries.is_empty() {
+        message::link_early_replies(context, rfc724_mid_orig).await?;
+    }
+
+    // flag messages mentioning us so they are notified in muted chats
+    if from_id != ContactId::SELF
+        && mentions
+            .iter()
+            .any(|mention| mention.contact_id == ContactId::SELF)
+    {
+        for msg_id in &created_db_entries {
+            mention::set_mentions_self(context, *msg_id).await?;
+        }
+    }
+
     // check all parts whether they contain a new logging webxdc
     for (part, msg_id) in mime_parser.parts.iter().zip(&created_db_entries) {
         // check if any part contains a webxdc topic id
@@ -1682,6 +1987,13 @@ async fn add_parts(
         chat::marknoticed_chat_if_older_than(context, chat_id, sort_timestamp).await?;
     }
 
+    // incoming message seen by another client before it was fetched was read there.
+    if seen && mime_parser.incoming && !chat_id.is_special() {
+        if let Some(msg_id) = created_db_entries.last() {
+            chat::update_last_read_msg(context, *msg_id).await?;
+        }
+    }
+
     if !is_mdn {
         let mut chat = Chat::load_from_db(context, chat_id).await?;
 
@@ -1822,15 +2134,19 @@ async fn lookup_chat_or_create_adhoc_group(
     allow_creation: bool,
     create_blocked: Blocked,
     is_partial_download: bool,
-) -> Result<Option<(ChatId, Blocked)>> {
+) -> Result<Option<(ChatId, Blocked, ChatAssignment)>> {
     if let Some((new_chat_id, new_chat_id_blocked)) =
         // Try to assign to a chat based on In-Reply-To/References.
         lookup_chat_by_reply(context, mime_parser, parent, to_ids, from_id).await?
     {
-        Ok(Some((new_chat_id, new_chat_id_blocked)))
+        Ok(Some((
+            new_chat_id,
+            new_chat_id_blocked,
+            ChatAssignment::Reply,
+        )))
     } else if allow_creation {
         // Try to create an ad hoc group.
-        create_adhoc_group(
+        Ok(create_adhoc_group(
             context,
             mime_parser,
             create_blocked,
@@ -1839,7 +2155,8 @@ async fn lookup_chat_or_create_adhoc_group(
             is_partial_download,
         )
         .await
-        .context("Could not create ad hoc group")
+        .context("Could not create ad hoc group")?
+        .map(|(chat_id, blocked)| (chat_id, blocked, ChatAssignment::AdHocGroup)))
     } else {
         Ok(None)
     }
@@ -2018,6 +2335,10 @@ async fn apply_group_changes(
         // Do not apply group changes to the trash chat.
         return Ok((Vec::new(), None));
     }
+    if mime_parser.imported {
+        // Imported messages may be arbitrarily old, their changes are outdated.
+        return Ok((Vec::new(), None));
+    }
     let mut chat = Chat::load_from_db(context, chat_id).await?;
     if chat.typ != Chattype::Group {
         return Ok((Vec::new(), None));
@@ -2444,6 +2765,9 @@ async fn apply_mailinglist_changes(
     mime_parser: &MimeMessage,
     chat_id: ChatId,
 ) -> Result<()> {
+    if mime_parser.imported {
+        return Ok(());
+    }
     let Some(mailinglist_header) = mime_parser.get_mailinglist_header() else {
         return Ok(());
     };
@@ -2596,6 +2920,84 @@ async fn create_adhoc_group(
     Ok(Some((new_chat_id, create_blocked)))
 }
 
+/// Returns true if the message claims to be from a contact whose key is known,
+/// but is not signed with this key, see [`Config::SpoofingProtection`].
+async fn is_spoofed(context: &Context, mime_parser: &MimeMessage) -> Result<bool> {
+    let strictness = context.get_config_int(Config::SpoofingProtection).await?;
+    if strictness == 0 || mime_parser.get_header(HeaderDef::SecureJoin).is_some() {
+        return Ok(false);
+    }
+    let Some(peerstate) = &mime_parser.decryption_info.peerstate else {
+        return Ok(false);
+    };
+    if peerstate.peek_key(false).is_none() {
+        return Ok(false);
+    }
+    // If the message changed the key, the signature was made by an unknown key.
+    let signed = !mime_parser.signatures.is_empty() || mime_parser.signed_cleartext;
+    if signed && !peerstate.fingerprint_changed {
+        return Ok(false);
+    }
+    Ok(strictness >= 2 || mime_parser.decryption_info.dkim_results.dkim_failed)
+}
+
+/// Returns the ad-hoc group collecting possibly forged messages from `from_id`,
+/// creating it as a contact request if needed.
+async fn lookup_or_create_spoofing_quarantine(
+    context: &Context,
+    mime_parser: &MimeMessage,
+    from_id: ContactId,
+) -> Result<(ChatId, Blocked)> {
+    let chats = context
+        .sql
+        .query_map(
+            "SELECT c.id, c.blocked, c.param
+             FROM chats c INNER JOIN chats_contacts cc ON c.id=cc.chat_id
+             WHERE c.type=? AND c.grpid='' AND cc.contact_id=?",
+            (Chattype::Group, from_id),
+            |row| {
+                let id: ChatId = row.get(0)?;
+                let blocked: Blocked = row.get(1)?;
+                let param: String = row.get(2)?;
+                Ok((id, blocked, param))
+            },
+            |rows| rows.collect::<Result<Vec<_>, _>>().map_err(Into::into),
+        )
+        .await?;
+    for (id, blocked, param) in chats {
+        let param: Params = param.parse().unwrap_or_default();
+        if param.get_int(Param::SpoofingQuarantine) == Some(from_id.to_u32() as i32) {
+            return Ok((id, blocked));
+        }
+    }
+
+    let mut param = Params::new();
+    param.set_int(Param::SpoofingQuarantine, from_id.to_u32() as i32);
+    let name = stock_str::spoofing_quarantine(context, &mime_parser.from.addr).await;
+    let chat_id = ChatId::create_multiuser_record(
+        context,
+        Chattype::Group,
+        "", // Ad hoc groups have no ID.
+        &name,
+        Blocked::Request,
+        ProtectionStatus::Unprotected,
+        Some(param.to_string()),
+        mime_parser.timestamp_sent,
+    )
+    .await?;
+    info!(
+        context,
+        "Created chat {chat_id} for possibly forged messages from {from_id}."
+    );
+    chat::add_to_chat_contacts_table(context, chat_id, &[ContactId::SELF, from_id]).await?;
+
+    context.emit_event(EventType::ChatModified(chat_id));
+    chatlist_events::emit_chatlist_changed(context);
+    chatlist_events::emit_chatlist_item_changed(context, chat_id);
+
+    Ok((chat_id, Blocked::Request))
+}
+
 #[derive(Debug, PartialEq, Eq)]
 enum VerifiedEncryption {
     Verified,
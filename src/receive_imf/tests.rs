@@ -5128,3 +5128,187 @@ async fn test_prefer_chat_group_id_over_references() -> Result<()> {
     assert_ne!(chat1.id, chat2.id);
     Ok(())
 }
+
+#[tokio::test(flavor = "multi_thread", worker_threads = 2)]
+async fn test_explain_assignment() -> Result<()> {
+    let t = &TestContext::new_alice().await;
+    t.set_config(Config::ShowEmails, Some("2")).await?;
+
+    async fn recv_assignment(t: &TestContext, raw: &[u8]) -> Result<ChatAssignment> {
+        let received = receive_imf(t, raw, false).await?.unwrap();
+        let msg_id = *received.msg_ids.last().unwrap();
+        msg_id.explain_assignment(t).await
+    }
+
+    let raw = b"From: bob@example.net\n\
+                To: alice@example.org\n\
+                Subject: Hi\n\
+                Chat-Version: 1.0\n\
+                Message-ID: <oneoneone@localhost>\n\
+                \n\
+                Hello!";
+    assert_eq!(recv_assignment(t, raw).await?, ChatAssignment::OneOneChat);
+
+    let raw = b"From: bob@example.net\n\
+                To: alice@example.org, claire@example.com\n\
+                Subject: Group\n\
+                Chat-Version: 1.0\n\
+                Chat-Group-Name: Group\n\
+                Chat-Group-ID: GePFDkwEj2K\n\
+                Message-ID: <group@localhost>\n\
+                \n\
+                Hello group!";
+    assert_eq!(recv_assignment(t, raw).await?, ChatAssignment::GroupId);
+    let msg = t.get_last_msg().await;
+    assert!(msg
+        .id
+        .get_info(t)
+        .await?
+        .contains("Chat assignment: Chat-Group-ID header"));
+
+    // A classical email reply is assigned by its references.
+    let raw = b"From: claire@example.com\n\
+                To: alice@example.org, bob@example.net\n\
+                Subject: Re: Group\n\
+                Message-ID: <reply@localhost>\n\
+                In-Reply-To: <group@localhost>\n\
+                References: <group@localhost>\n\
+                \n\
+                Reply from a classical MUA";
+    assert_eq!(recv_assignment(t, raw).await?, ChatAssignment::Reply);
+
+    let raw = b"From: claire@example.com\n\
+                To: alice@example.org, bob@example.net\n\
+                Subject: Lunch\n\
+                Message-ID: <adhoc@localhost>\n\
+                \n\
+                Lunch?";
+    assert_eq!(recv_assignment(t, raw).await?, ChatAssignment::AdHocGroup);
+
+    let raw = b"From: news@example.com\n\
+                To: alice@example.org\n\
+                Subject: News\n\
+                List-Id: News <news.example.com>\n\
+                Message-ID: <list@localhost>\n\
+                \n\
+                News of the day";
+    assert_eq!(recv_assignment(t, raw).await?, ChatAssignment::MailingList);
+
+    let raw = b"From: alice@example.org\n\
+                To: alice@example.org\n\
+                Subject: Note\n\
+                Chat-Version: 1.0\n\
+                Message-ID: <self@localhost>\n\
+                \n\
+                Note to self";
+    assert_eq!(recv_assignment(t, raw).await?, ChatAssignment::SelfChat);
+
+    // Messages sent from this device have no recorded reason.
+    let sent = t.send_text(msg.chat_id, "Hi").await;
+    assert_eq!(
+        sent.sender_msg_id.explain_assignment(t).await?,
+        ChatAssignment::Unknown
+    );
+
+    Ok(())
+}
+
+#[tokio::test(flavor = "multi_thread", worker_threads = 2)]
+async fn test_spoofing_protection() -> Result<()> {
+    async fn recv_forged(t: &TestContext, raw: &str) -> Result<Message> {
+        let received = receive_imf(t, raw.as_bytes(), false).await?.unwrap();
+        Message::load_from_db(t, *received.msg_ids.last().unwrap()).await
+    }
+
+    let mut tcm = TestContextManager::new();
+    let alice = &tcm.alice().await;
+    let bob = &tcm.bob().await;
+    bob.set_config(Config::AuthservIdCandidates, Some("example.net"))
+        .await?;
+    bob.set_config(Config::SpoofingProtection, Some("1"))
+        .await?;
+
+    let bob_alice_chat_id = tcm.send_recv_accept(alice, bob, "Hi").await.chat_id;
+    tcm.send_recv(bob, alice, "Hello").await;
+
+    let forged = |id: &str, authres: &str| {
+        format!(
+            "{authres}\
+             From: alice@example.org\n\
+             To: bob@example.net\n\
+             Subject: Hi\n\
+             Chat-Version: 1.0\n\
+             Message-ID: <{id}@example.org>\n\
+             Date: Sun, 22 Mar 2020 22:37:57 +0000\n\
+             \n\
+             Please send me your password.\n"
+        )
+    };
+    let dkim_fail = "Authentication-Results: example.net; dkim=fail header.d=example.org\n";
+
+    // Unsigned message with failed DKIM is quarantined.
+    let msg = recv_forged(bob, &forged("forged1", dkim_fail)).await?;
+    assert_ne!(msg.chat_id, bob_alice_chat_id);
+    assert!(msg.error.is_some());
+    let quarantine = Chat::load_from_db(bob, msg.chat_id).await?;
+    assert_eq!(quarantine.typ, Chattype::Group);
+    assert!(quarantine.is_contact_request());
+
+    assert_eq!(
+        recv_forged(bob, &forged("forged2", dkim_fail))
+            .await?
+            .chat_id,
+        quarantine.id
+    );
+
+    // Signed messages are not affected by failing DKIM.
+    let mut sent = alice
+        .send_text(alice.create_chat(bob).await.id, "Real")
+        .await;
+    sent.payload.insert_str(0, dkim_fail);
+    let msg = bob.recv_msg(&sent).await;
+    assert_eq!(msg.chat_id, bob_alice_chat_id);
+    assert!(msg.error.is_none());
+
+    // Without DKIM failure, unsigned messages are only quarantined in strict mode.
+    assert_eq!(
+        recv_forged(bob, &forged("unsigned1", "")).await?.chat_id,
+        bob_alice_chat_id
+    );
+    let no_dkim = "Authentication-Results: example.net; spf=pass smtp.mailfrom=example.org\n";
+    assert_eq!(
+        recv_forged(bob, &forged("unsigned3", no_dkim))
+            .await?
+            .chat_id,
+        bob_alice_chat_id
+    );
+    for (id, result) in [
+        ("unsigned4", "none"),
+        ("unsigned5", "neutral"),
+        ("unsigned6", "temperror"),
+    ] {
+        let authres =
+            format!("Authentication-Results: example.net; dkim={result} header.d=example.org\n");
+        assert_eq!(
+            recv_forged(bob, &forged(id, &authres)).await?.chat_id,
+            bob_alice_chat_id
+        );
+    }
+    bob.set_config(Config::SpoofingProtection, Some("2"))
+        .await?;
+    assert_eq!(
+        recv_forged(bob, &forged("unsigned2", "")).await?.chat_id,
+        quarantine.id
+    );
+
+    bob.set_config(Config::SpoofingProtection, Some("0"))
+        .await?;
+    assert_eq!(
+        recv_forged(bob, &forged("forged3", dkim_fail))
+            .await?
+            .chat_id,
+        bob_alice_chat_id
+    );
+
+    Ok(())
+}
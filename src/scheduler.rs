@@ -19,8 +19,9 @@
 use crate::ephemeral::{self, delete_expired_imap_messages};
 use crate::events::EventType;
 use crate::imap::{session::Session, FolderMeaning, Imap};
+use crate::imex;
 use crate::location;
-use crate::log::LogExt;
+use crate::log::{dedup_errors, LogExt};
 use crate::message::MsgId;
 use crate::smtp::{send_smtp_messages, Smtp};
 use crate::sql;
@@ -338,6 +339,11 @@ pub(crate) struct Scheduler {
 }
 
 async fn download_msgs(context: &Context, session: &mut Session) -> Result<()> {
+    if context.is_low_disk_space().await? {
+        info!(context, "Low disk space, not downloading messages.");
+        return Ok(());
+    }
+
     let msg_ids = context
         .sql
         .query_map(
@@ -500,6 +506,10 @@ async fn inbox_fetch_idle(ctx: &Context, imap: &mut Imap, mut session: Session)
         }
     };
 
+    if let Err(err) = imex::maybe_start_auto_backup(ctx).await {
+        warn!(ctx, "Failed to start automatic backup: {err:#}.");
+    }
+
     match ctx.get_config_bool(Config::FetchedExistingMsgs).await {
         Ok(fetched_existing_msgs) => {
             if !fetched_existing_msgs {
@@ -527,6 +537,9 @@ async fn inbox_fetch_idle(ctx: &Context, imap: &mut Imap, mut session: Session)
     download_msgs(ctx, &mut session)
         .await
         .context("Failed to download messages")?;
+    if let Err(err) = session.sync_outbox(ctx).await {
+        warn!(ctx, "Failed to sync outbox with the server: {err:#}.");
+    }
     session
         .fetch_metadata(ctx)
         .await
@@ -837,7 +850,11 @@ pub async fn start(ctx: &Context) -> Result<Self> {
         let (inbox_start_send, inbox_start_recv) = oneshot::channel();
         let handle = {
             let ctx = ctx.clone();
-            task::spawn(inbox_loop(ctx, inbox_start_send, inbox_handlers))
+            task::spawn(dedup_errors(inbox_loop(
+                ctx,
+                inbox_start_send,
+                inbox_handlers,
+            )))
         };
         let inbox = SchedBox {
             meaning: FolderMeaning::Inbox,
@@ -854,7 +871,9 @@ pub async fn start(ctx: &Context) -> Result<Self> {
                 let (conn_state, handlers) = ImapConnectionState::new(ctx).await?;
                 let (start_send, start_recv) = oneshot::channel();
                 let ctx = ctx.clone();
-                let handle = task::spawn(simple_imap_loop(ctx, start_send, handlers, meaning));
+                let handle = task::spawn(dedup_errors(simple_imap_loop(
+                    ctx, start_send, handlers, meaning,
+                )));
                 oboxes.push(SchedBox {
                     meaning,
                     conn_state,
@@ -866,22 +885,22 @@ pub async fn start(ctx: &Context) -> Result<Self> {
 
         let smtp_handle = {
             let ctx = ctx.clone();
-            task::spawn(smtp_loop(ctx, smtp_start_send, smtp_handlers))
+            task::spawn(dedup_errors(smtp_loop(ctx, smtp_start_send, smtp_handlers)))
         };
         start_recvs.push(smtp_start_recv);
 
         let ephemeral_handle = {
             let ctx = ctx.clone();
-            task::spawn(async move {
+            task::spawn(dedup_errors(async move {
                 ephemeral::ephemeral_loop(&ctx, ephemeral_interrupt_recv).await;
-            })
+            }))
         };
 
         let location_handle = {
             let ctx = ctx.clone();
-            task::spawn(async move {
+            task::spawn(dedup_errors(async move {
                 location::location_loop(&ctx, location_interrupt_recv).await;
-            })
+            }))
         };
 
         let recently_seen_loop = RecentlySeenLoop::new(ctx.clone());
@@ -10,16 +10,21 @@
 use crate::imap::{scan_folders::get_watched_folder_configs, FolderMeaning};
 use crate::quota::{QUOTA_ERROR_THRESHOLD_PERCENTAGE, QUOTA_WARN_THRESHOLD_PERCENTAGE};
 use crate::stock_str;
+use crate::tools::time;
 use crate::{context::Context, log::LogExt};
 
 use super::InnerSchedulerState;
 
+/// Basic connectivity, see [`Context::get_connectivity()`].
 #[derive(Debug, Clone, Copy, PartialEq, Eq, EnumProperty, PartialOrd, Ord)]
 pub enum Connectivity {
+    /// Not connected, e.g. because of a network error.
     NotConnected = 1000,
+    /// Connection is being established.
     Connecting = 2000,
     /// Fetching or sending messages
     Working = 3000,
+    /// Connected and idle.
     Connected = 4000,
 }
 
@@ -127,13 +132,38 @@ fn all_work_done(&self) -> bool {
     }
 }
 
+/// Connectivity of a single connection together with its recent history.
+#[derive(Debug, Default, Clone)]
+struct ConnectivityState {
+    detailed: DetailedConnectivity,
+
+    /// Timestamp of the last state change, 0 if the state never changed.
+    changed_timestamp: i64,
+
+    /// Last error and its timestamp.
+    ///
+    /// Unlike [`DetailedConnectivity::Error`], this is kept after the connection recovers.
+    last_error: Option<(String, i64)>,
+}
+
+impl ConnectivityState {
+    fn update(&mut self, v: DetailedConnectivity) {
+        let now = time();
+        if let DetailedConnectivity::Error(e) = &v {
+            self.last_error = Some((e.clone(), now));
+        }
+        self.detailed = v;
+        self.changed_timestamp = now;
+    }
+}
+
 #[derive(Clone, Default)]
-pub(crate) struct ConnectivityStore(Arc<Mutex<DetailedConnectivity>>);
+pub(crate) struct ConnectivityStore(Arc<Mutex<ConnectivityState>>);
 
 impl ConnectivityStore {
     async fn set(&self, context: &Context, v: DetailedConnectivity) {
         {
-            *self.0.lock().await = v;
+            self.0.lock().await.update(v);
         }
         context.emit_event(EventType::ConnectivityChanged);
     }
@@ -159,16 +189,44 @@ pub(crate) async fn set_idle(&self, context: &Context) {
     }
 
     async fn get_detailed(&self) -> DetailedConnectivity {
+        self.0.lock().await.detailed.clone()
+    }
+    async fn get_state(&self) -> ConnectivityState {
         self.0.lock().await.deref().clone()
     }
     async fn get_basic(&self) -> Option<Connectivity> {
-        self.0.lock().await.to_basic()
+        self.0.lock().await.detailed.to_basic()
     }
     async fn get_all_work_done(&self) -> bool {
-        self.0.lock().await.all_work_done()
+        self.0.lock().await.detailed.all_work_done()
     }
 }
 
+/// Connectivity of a single IMAP or SMTP connection,
+/// as returned by [`Context::get_connectivity_details()`].
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ConnectionStatus {
+    /// Name of the connection, e.g. "Inbox", "Mvbox" or "SMTP".
+    pub name: String,
+
+    /// Basic connectivity of the connection.
+    ///
+    /// `None` if the connection is not configured, e.g. because the folder is not watched.
+    pub connectivity: Option<Connectivity>,
+
+    /// Localized description of the current state.
+    pub state: String,
+
+    /// Last error that happened on the connection, even if it has recovered since.
+    pub last_error: Option<String>,
+
+    /// Timestamp of the last error, 0 if there was no error.
+    pub last_error_timestamp: i64,
+
+    /// Timestamp of the last state change, 0 if the connection was not started yet.
+    pub state_timestamp: i64,
+}
+
 /// Set all folder states to InterruptingIdle in case they were `Connected` before.
 /// Called during `dc_maybe_network()` to make sure that `dc_all_work_done()`
 /// returns false immediately after `dc_maybe_network()`.
@@ -179,20 +237,20 @@ pub(crate) async fn idle_interrupted(inbox: ConnectivityStore, oboxes: Vec<Conne
     // returns Connected. But after dc_maybe_network(), dc_get_connectivity() must not
     // return Connected until DC is completely done with fetching folders; this also
     // includes scan_folders() which happens on the inbox thread.
-    if *connectivity_lock == DetailedConnectivity::Connected
-        || *connectivity_lock == DetailedConnectivity::Idle
-        || *connectivity_lock == DetailedConnectivity::NotConfigured
+    if connectivity_lock.detailed == DetailedConnectivity::Connected
+        || connectivity_lock.detailed == DetailedConnectivity::Idle
+        || connectivity_lock.detailed == DetailedConnectivity::NotConfigured
     {
-        *connectivity_lock = DetailedConnectivity::InterruptingIdle;
+        connectivity_lock.detailed = DetailedConnectivity::InterruptingIdle;
     }
     drop(connectivity_lock);
 
     for state in oboxes {
         let mut connectivity_lock = state.0.lock().await;
-        if *connectivity_lock == DetailedConnectivity::Connected
-            || *connectivity_lock == DetailedConnectivity::Idle
+        if connectivity_lock.detailed == DetailedConnectivity::Connected
+            || connectivity_lock.detailed == DetailedConnectivity::Idle
         {
-            *connectivity_lock = DetailedConnectivity::InterruptingIdle;
+            connectivity_lock.detailed = DetailedConnectivity::InterruptingIdle;
         }
     }
     // No need to send ConnectivityChanged, the user-facing connectivity doesn't change because
@@ -206,12 +264,12 @@ pub(crate) async fn maybe_network_lost(context: &Context, stores: Vec<Connectivi
     for store in &stores {
         let mut connectivity_lock = store.0.lock().await;
         if !matches!(
-            *connectivity_lock,
+            connectivity_lock.detailed,
             DetailedConnectivity::Uninitialized
                 | DetailedConnectivity::Error(_)
                 | DetailedConnectivity::NotConfigured,
         ) {
-            *connectivity_lock = DetailedConnectivity::Error("Connection lost".to_string());
+            connectivity_lock.update(DetailedConnectivity::Error("Connection lost".to_string()));
         }
         drop(connectivity_lock);
     }
@@ -221,7 +279,7 @@ pub(crate) async fn maybe_network_lost(context: &Context, stores: Vec<Connectivi
 impl fmt::Debug for ConnectivityStore {
     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
         if let Ok(guard) = self.0.try_lock() {
-            write!(f, "ConnectivityStore {:?}", &*guard)
+            write!(f, "ConnectivityStore {:?}", &guard.detailed)
         } else {
             write!(f, "ConnectivityStore [LOCKED]")
         }
@@ -266,6 +324,52 @@ pub async fn get_connectivity(&self) -> Connectivity {
             .unwrap_or(Connectivity::Connected)
     }
 
+    /// Returns the connectivity of each IMAP and SMTP connection separately,
+    /// including the last error and the time of the last state change.
+    ///
+    /// This is a structured variant of [`Context::get_connectivity_html()`]
+    /// for UIs that want to render the connection states themselves.
+    /// Returns an empty list if IO is not started.
+    ///
+    /// If the states change, a DC_EVENT_CONNECTIVITY_CHANGED will be emitted.
+    pub async fn get_connectivity_details(&self) -> Vec<ConnectionStatus> {
+        let lock = self.scheduler.inner.read().await;
+        let (folders_states, smtp) = match *lock {
+            InnerSchedulerState::Started(ref sched) => (
+                sched
+                    .boxes()
+                    .map(|b| (b.meaning, b.conn_state.state.connectivity.clone()))
+                    .collect::<Vec<_>>(),
+                sched.smtp.state.connectivity.clone(),
+            ),
+            _ => return Vec::new(),
+        };
+        drop(lock);
+
+        let mut res = Vec::new();
+        for (meaning, store) in folders_states {
+            let state = store.get_state().await;
+            res.push(ConnectionStatus {
+                name: meaning.to_string(),
+                connectivity: state.detailed.to_basic(),
+                state: state.detailed.to_string_imap(self).await,
+                last_error: state.last_error.as_ref().map(|(e, _)| e.clone()),
+                last_error_timestamp: state.last_error.map_or(0, |(_, ts)| ts),
+                state_timestamp: state.changed_timestamp,
+            });
+        }
+        let state = smtp.get_state().await;
+        res.push(ConnectionStatus {
+            name: "SMTP".to_string(),
+            connectivity: state.detailed.to_basic(),
+            state: state.detailed.to_string_smtp(self).await,
+            last_error: state.last_error.as_ref().map(|(e, _)| e.clone()),
+            last_error_timestamp: state.last_error.map_or(0, |(_, ts)| ts),
+            state_timestamp: state.changed_timestamp,
+        });
+        res
+    }
+
     /// Get an overview of the current connectivity, and possibly more statistics.
     /// Meant to give the user more insight about the current status than
     /// the basic connectivity info returned by dc_get_connectivity(); show this
@@ -529,3 +633,31 @@ pub async fn all_work_done(&self) -> bool {
         true
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_connectivity_state_keeps_last_error() {
+        let mut state = ConnectivityState::default();
+        assert_eq!(state.changed_timestamp, 0);
+        assert!(state.last_error.is_none());
+
+        state.update(DetailedConnectivity::Error(
+            "Connection refused".to_string(),
+        ));
+        assert_eq!(state.detailed.to_basic(), Some(Connectivity::NotConnected));
+        let (err, err_timestamp) = state.last_error.clone().unwrap();
+        assert_eq!(err, "Connection refused");
+        assert!(err_timestamp > 0);
+
+        state.update(DetailedConnectivity::Idle);
+        assert_eq!(state.detailed.to_basic(), Some(Connectivity::Connected));
+        assert!(state.changed_timestamp >= err_timestamp);
+        assert_eq!(
+            state.last_error.map(|(e, _)| e).as_deref(),
+            Some("Connection refused")
+        );
+    }
+}
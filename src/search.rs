@@ -0,0 +1,409 @@
+//! # Full-text message search.
+//!
+//! Message texts and attachment filenames are indexed in the `msgs_fts` FTS5 table
+//! using the trigram tokenizer, so that the query matches substrings case-insensitively
+//! as the former `LIKE` search did.
+//! The index is maintained by SQL triggers on the `msgs` table.
+//!
+//! Messages existing before the index was introduced are indexed in the background
+//! by [`backfill`]. Until this is done, and for queries shorter than
+//! [`MIN_QUERY_CHARS`] that the trigram index cannot answer,
+//! the `LIKE` search is used.
+//!
+//! If SQLite is built without FTS5 or the trigram tokenizer,
+//! the index is not maintained and the `LIKE` search is always used,
+//! see [`update_index_state`].
+
+use std::time::Duration;
+
+use anyhow::Result;
+
+use crate::chat::ChatId;
+use crate::context::Context;
+use crate::message::MsgId;
+use crate::sql::Sql;
+
+/// Config key holding the highest message ID not yet indexed by [`backfill`].
+///
+/// Unset if the index is complete.
+pub(crate) const FTS_BACKFILL_CFG: &str = "fts_backfill_id";
+
+/// Minimum number of characters in the query to use the index.
+const MIN_QUERY_CHARS: usize = 3;
+
+/// Number of messages indexed by [`backfill`] per transaction.
+const BACKFILL_BATCH: u32 = 1000;
+
+/// Number of tokens, i.e. characters for the trigram tokenizer, in search snippets.
+const SNIPPET_TOKENS: u32 = 64;
+
+/// Returns SQL expression extracting the [`crate::param::Param::Filename`] value
+/// from the `param`-like `column`.
+pub(crate) fn filename_sql(column: &str) -> String {
+    let params = format!("(char(10)||{column}||char(10))");
+    let start = format!("(instr({params}, char(10)||'v=')+3)");
+    format!(
+        "CASE WHEN instr({params}, char(10)||'v=')>0 \
+         THEN substr({params}, {start}, instr(substr({params}, {start}), char(10))-1) END"
+    )
+}
+
+/// Returns SQL creating the full-text index and the triggers maintaining it.
+///
+/// Existing messages are left to [`backfill`].
+pub(crate) fn create_index_sql() -> String {
+    let filename_new = filename_sql("new.param");
+    let filename_old = filename_sql("old.param");
+    format!(
+        "CREATE VIRTUAL TABLE IF NOT EXISTS msgs_fts USING fts5(txt, filename, tokenize='trigram');
+         DELETE FROM msgs_fts;
+         CREATE TRIGGER msgs_fts_insert AFTER INSERT ON msgs BEGIN
+           DELETE FROM msgs_fts WHERE rowid=new.id;
+           INSERT INTO msgs_fts (rowid, txt, filename)
+           VALUES (new.id, new.txt, {filename_new});
+         END;
+         CREATE TRIGGER msgs_fts_update AFTER UPDATE OF txt, param ON msgs
+         WHEN old.txt IS NOT new.txt OR {filename_old} IS NOT {filename_new} BEGIN
+           DELETE FROM msgs_fts WHERE rowid=old.id;
+           INSERT INTO msgs_fts (rowid, txt, filename)
+           VALUES (new.id, new.txt, {filename_new});
+         END;
+         CREATE TRIGGER msgs_fts_delete AFTER DELETE ON msgs BEGIN
+           DELETE FROM msgs_fts WHERE rowid=old.id;
+         END;
+         DELETE FROM config WHERE keyname='{FTS_BACKFILL_CFG}';
+         INSERT INTO config (keyname, value)
+         SELECT '{FTS_BACKFILL_CFG}', value FROM (SELECT MAX(id) AS value FROM msgs WHERE id>9)
+         WHERE value IS NOT NULL;"
+    )
+}
+
+/// Returns true if SQLite supports FTS5 with the trigram tokenizer.
+pub(crate) async fn is_fts5_available(sql: &Sql) -> bool {
+    sql.call_write(|conn| {
+        conn.execute_batch(
+            "CREATE VIRTUAL TABLE temp.fts5_probe USING fts5(txt, tokenize='trigram');
+             DROP TABLE temp.fts5_probe;",
+        )?;
+        Ok(())
+    })
+    .await
+    .is_ok()
+}
+
+/// Returns true if the index is maintained by the triggers.
+async fn is_index_enabled(sql: &Sql) -> Result<bool> {
+    sql.exists(
+        "SELECT COUNT(*) FROM sqlite_master WHERE type='trigger' AND name='msgs_fts_insert'",
+        (),
+    )
+    .await
+}
+
+/// Creates or disables the index depending on whether SQLite supports FTS5.
+///
+/// The database may be moved to a device with a different SQLite build, e.g. by a backup.
+/// Without FTS5, the triggers maintaining the index would make all changes to messages fail,
+/// so they are dropped and the index is rebuilt once FTS5 is available again.
+pub(crate) async fn update_index_state(context: &Context) -> Result<()> {
+    let available = is_fts5_available(&context.sql).await;
+    let enabled = is_index_enabled(&context.sql).await?;
+    if available && !enabled {
+        info!(context, "Creating full-text search index.");
+        let sql = create_index_sql();
+        context
+            .sql
+            .call_write(move |conn| {
+                conn.execute_batch(&sql)?;
+                Ok(())
+            })
+            .await?;
+        context
+            .sql
+            .config_cache()
+            .write()
+            .await
+            .remove(FTS_BACKFILL_CFG);
+    } else if !available && enabled {
+        warn!(
+            context,
+            "FTS5 is not available, disabling full-text search index."
+        );
+        context
+            .sql
+            .call_write(|conn| {
+                conn.execute_batch(
+                    "DROP TRIGGER msgs_fts_insert;
+                     DROP TRIGGER msgs_fts_update;
+                     DROP TRIGGER msgs_fts_delete;",
+                )?;
+                Ok(())
+            })
+            .await?;
+        context.sql.set_raw_config(FTS_BACKFILL_CFG, None).await?;
+    }
+    Ok(())
+}
+
+/// Returns FTS5 query matching `query` as a substring,
+/// `None` if the query is too short for the trigram index.
+fn fts_query(query: &str) -> Option<String> {
+    if query.chars().count() < MIN_QUERY_CHARS {
+        return None;
+    }
+    Some(format!("\"{}\"", query.replace('"', "\"\"")))
+}
+
+/// Returns true if the index is maintained and all messages are indexed.
+async fn is_index_complete(context: &Context) -> Result<bool> {
+    Ok(context
+        .sql
+        .get_raw_config_int(FTS_BACKFILL_CFG)
+        .await?
+        .is_none()
+        && is_index_enabled(&context.sql).await?)
+}
+
+/// Searches for messages using the full-text index.
+///
+/// Returns `None` if the index cannot be used for the query.
+/// Results of the chat search are sorted by timestamp,
+/// results of the global search by relevance and limited to 1000 messages.
+pub(crate) async fn search_msgs(
+    context: &Context,
+    chat_id: Option<ChatId>,
+    query: &str,
+) -> Result<Option<Vec<MsgId>>> {
+    let Some(fts_query) = fts_query(query) else {
+        return Ok(None);
+    };
+    if !is_index_complete(context).await? {
+        return Ok(None);
+    }
+
+    let list = if let Some(chat_id) = chat_id {
+        context
+            .sql
+            .query_map(
+                "SELECT m.id AS id
+                 FROM msgs_fts f
+                 INNER JOIN msgs m
+                         ON m.id=f.rowid
+                 LEFT JOIN contacts ct
+                        ON m.from_id=ct.id
+                 WHERE msgs_fts MATCH ?
+                   AND m.chat_id=?
+                   AND m.hidden=0
+                   AND ct.blocked=0
+                 ORDER BY m.timestamp,m.id",
+                (fts_query, chat_id),
+                |row| row.get::<_, MsgId>("id"),
+                |rows| {
+                    rows.collect::<std::result::Result<Vec<_>, _>>()
+                        .map_err(Into::into)
+                },
+            )
+            .await?
+    } else {
+        context
+            .sql
+            .query_map(
+                "SELECT m.id AS id
+                 FROM msgs_fts f
+                 INNER JOIN msgs m
+                         ON m.id=f.rowid
+                 LEFT JOIN contacts ct
+                        ON m.from_id=ct.id
+                 LEFT JOIN chats c
+                        ON m.chat_id=c.id
+                 WHERE msgs_fts MATCH ?
+                   AND m.chat_id>9
+                   AND m.hidden=0
+                   AND c.blocked!=1
+                   AND ct.blocked=0
+                 ORDER BY f.rank, m.id DESC LIMIT 1000",
+                (fts_query,),
+                |row| row.get::<_, MsgId>("id"),
+                |rows| {
+                    rows.collect::<std::result::Result<Vec<_>, _>>()
+                        .map_err(Into::into)
+                },
+            )
+            .await?
+    };
+    Ok(Some(list))
+}
+
+impl Context {
+    /// Returns an excerpt of the message text or attachment filename around the match of
+    /// `query`, as returned by [`Context::search_msgs`].
+    ///
+    /// Omitted parts are marked with an ellipsis.
+    /// Returns `None` if the message doesn't match
+    /// or the query cannot be answered using the full-text index yet.
+    pub async fn get_msg_search_snippet(
+        &self,
+        msg_id: MsgId,
+        query: &str,
+    ) -> Result<Option<String>> {
+        let Some(fts_query) = fts_query(query.trim()) else {
+            return Ok(None);
+        };
+        if !is_index_complete(self).await? {
+            return Ok(None);
+        }
+        self.sql
+            .query_row_optional(
+                "SELECT snippet(msgs_fts, -1, '', '', '…', ?)
+                 FROM msgs_fts
+                 WHERE msgs_fts MATCH ? AND rowid=?",
+                (SNIPPET_TOKENS, fts_query, msg_id),
+                |row| row.get(0),
+            )
+            .await
+    }
+}
+
+/// Indexes messages existing before the full-text index was introduced.
+///
+/// Works in small batches from the newest to the oldest message
+/// so that the database isn't locked for long.
+pub(crate) async fn backfill(context: &Context) -> Result<()> {
+    let filename = filename_sql("param");
+    while let Some(end) = context.sql.get_raw_config_int(FTS_BACKFILL_CFG).await? {
+        let end = u32::try_from(end).unwrap_or_default();
+        let start = end.saturating_sub(BACKFILL_BATCH);
+        context
+            .sql
+            .execute(
+                &format!(
+                    "INSERT INTO msgs_fts (rowid, txt, filename)
+                     SELECT id, txt, {filename} FROM msgs
+                     WHERE id>?1 AND id<=?2
+                       AND id NOT IN (SELECT rowid FROM msgs_fts WHERE rowid>?1 AND rowid<=?2)"
+                ),
+                (start, end),
+            )
+            .await?;
+        if start == 0 {
+            context.sql.set_raw_config(FTS_BACKFILL_CFG, None).await?;
+            info!(context, "Full-text search index is complete.");
+        } else {
+            context
+                .sql
+                .set_raw_config(FTS_BACKFILL_CFG, Some(&start.to_string()))
+                .await?;
+            tokio::time::sleep(Duration::from_millis(10)).await;
+        }
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::chat::send_msg;
+    use crate::message::{Message, Viewtype};
+    use crate::test_utils::TestContext;
+
+    #[test]
+    fn test_fts_query() {
+        assert_eq!(fts_query("ab"), None);
+        assert_eq!(fts_query("foo bar"), Some("\"foo bar\"".to_string()));
+        assert_eq!(
+            fts_query("say \"hi\""),
+            Some("\"say \"\"hi\"\"\"".to_string())
+        );
+    }
+
+    #[tokio::test(flavor = "multi_thread", worker_threads = 2)]
+    async fn test_search_fts() -> Result<()> {
+        let t = TestContext::new_alice().await;
+        let chat = t.create_chat_with_contact("Bob", "bob@example.net").await;
+        let msg1 = t
+            .send_text(chat.id, "Let's meet at the Bahnhof")
+            .await
+            .sender_msg_id;
+        let msg2 = t
+            .send_text(chat.id, "The BAHNHOF, not the airport")
+            .await
+            .sender_msg_id;
+
+        let file = t.get_blobdir().join("Fahrplan.pdf");
+        tokio::fs::write(&file, b"timetable").await?;
+        let mut msg = Message::new(Viewtype::File);
+        msg.set_file(file.to_str().unwrap(), None);
+        let msg3 = send_msg(&t, chat.id, &mut msg).await?;
+
+        assert_eq!(
+            search_msgs(&t, Some(chat.id), "bahnhof").await?,
+            Some(vec![msg1, msg2])
+        );
+        assert_eq!(t.search_msgs(None, "fahrplan").await?, vec![msg3]);
+        assert_eq!(search_msgs(&t, None, "ab").await?, None);
+
+        let snippet = t.get_msg_search_snippet(msg2, "airport").await?.unwrap();
+        assert!(snippet.contains("airport"));
+        assert_eq!(t.get_msg_search_snippet(msg1, "airport").await?, None);
+
+        // Text changes are indexed.
+        t.sql
+            .execute(
+                "UPDATE msgs SET txt='Moved to the airport' WHERE id=?",
+                (msg1,),
+            )
+            .await?;
+        assert_eq!(t.search_msgs(Some(chat.id), "bahnhof").await?, vec![msg2]);
+
+        // Messages not indexed yet are found after backfilling.
+        t.sql.execute("DELETE FROM msgs_fts", ()).await?;
+        t.sql
+            .set_raw_config(FTS_BACKFILL_CFG, Some(&msg3.to_u32().to_string()))
+            .await?;
+        assert_eq!(search_msgs(&t, None, "airport").await?, None);
+        assert_eq!(t.search_msgs(None, "airport").await?.len(), 2);
+        backfill(&t).await?;
+        assert!(is_index_complete(&t).await?);
+        assert_eq!(
+            search_msgs(&t, Some(chat.id), "airport").await?,
+            Some(vec![msg1, msg2])
+        );
+
+        Ok(())
+    }
+
+    #[tokio::test(flavor = "multi_thread", worker_threads = 2)]
+    async fn test_update_index_state() -> Result<()> {
+        let t = TestContext::new_alice().await;
+        assert!(is_fts5_available(&t.sql).await);
+        let chat = t.create_chat_with_contact("Bob", "bob@example.net").await;
+        let msg_id = t.send_text(chat.id, "Hello world").await.sender_msg_id;
+
+        // A database created without FTS5 has no triggers, the `LIKE` search is used.
+        t.sql
+            .call_write(|conn| {
+                conn.execute_batch(
+                    "DROP TRIGGER msgs_fts_insert;
+                     DROP TRIGGER msgs_fts_update;
+                     DROP TRIGGER msgs_fts_delete;
+                     DROP TABLE msgs_fts;",
+                )?;
+                Ok(())
+            })
+            .await?;
+        assert_eq!(search_msgs(&t, None, "world").await?, None);
+        assert_eq!(t.search_msgs(None, "world").await?, vec![msg_id]);
+
+        // Once FTS5 is available, the index is created and filled.
+        update_index_state(&t).await?;
+        assert!(is_index_enabled(&t.sql).await?);
+        assert_eq!(search_msgs(&t, None, "world").await?, None);
+        backfill(&t).await?;
+        assert_eq!(search_msgs(&t, None, "world").await?, Some(vec![msg_id]));
+
+        // Nothing changes if the index is enabled already.
+        update_index_state(&t).await?;
+        assert!(is_index_complete(&t).await?);
+        Ok(())
+    }
+}
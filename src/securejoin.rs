@@ -1,7 +1,10 @@
 //! Implementation of [SecureJoin protocols](https://securejoin.delta.chat/).
 
-use anyhow::{bail, Context as _, Error, Result};
+use std::time::Duration;
+
+use anyhow::{bail, ensure, Context as _, Error, Result};
 use percent_encoding::{utf8_percent_encode, AsciiSet, NON_ALPHANUMERIC};
+use serde::{Deserialize, Serialize};
 
 use crate::aheader::EncryptPreference;
 use crate::chat::{self, Chat, ChatId, ChatIdBlocked, ProtectionStatus};
@@ -23,7 +26,7 @@
 use crate::stock_str;
 use crate::sync::Sync::*;
 use crate::token;
-use crate::tools::time;
+use crate::tools::{create_id, time};
 
 mod bob;
 mod bobstate;
@@ -37,6 +40,44 @@
 /// Set of characters to percent-encode in email addresses and names.
 pub const NON_ALPHANUMERIC_WITHOUT_DOT: &AsciiSet = &NON_ALPHANUMERIC.remove(b'.');
 
+/// Reason of a failed Secure-Join handshake,
+/// reported by [`EventType::SecurejoinFailed`].
+///
+/// Use [`SecurejoinFailure::get_text`] to show the reason to the user.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, FromPrimitive, ToPrimitive, Serialize, Deserialize)]
+#[repr(u32)]
+pub enum SecurejoinFailure {
+    /// Other error.
+    Other = 0,
+
+    /// The inviter did not reply in time.
+    ///
+    /// The handshake is not aborted and may still complete later.
+    Timeout = 1,
+
+    /// The key of the peer does not match the fingerprint from the QR code.
+    FingerprintMismatch = 2,
+
+    /// The scanned invite was withdrawn by the inviter.
+    InviteWithdrawn = 3,
+
+    /// A handshake message was not encrypted and signed as required.
+    NotEncrypted = 4,
+
+    /// The auth code is missing or not valid.
+    AuthInvalid = 5,
+
+    /// The handshake was aborted because another QR code was scanned.
+    Aborted = 6,
+}
+
+impl SecurejoinFailure {
+    /// Returns the translated description of the failure reason.
+    pub async fn get_text(self, context: &Context) -> String {
+        stock_str::securejoin_failure(context, self).await
+    }
+}
+
 fn inviter_progress(context: &Context, contact_id: ContactId, progress: usize) {
     debug_assert!(
         progress <= 1000,
@@ -48,6 +89,12 @@ fn inviter_progress(context: &Context, contact_id: ContactId, progress: usize) {
     });
 }
 
+/// Reports a failed handshake on the inviter side.
+fn inviter_failed(context: &Context, contact_id: ContactId, reason: SecurejoinFailure) {
+    inviter_progress(context, contact_id, 0);
+    context.emit_event(EventType::SecurejoinFailed { contact_id, reason });
+}
+
 /// Generates a Secure Join QR code.
 ///
 /// With `group` set to `None` this generates a setup-contact QR code, with `group` set to a
@@ -67,6 +114,71 @@ pub async fn get_securejoin_qr(context: &Context, group: Option<ChatId>) -> Resu
         .is_none();
     let invitenumber = token::lookup_or_new(context, Namespace::InviteNumber, group).await?;
     let auth = token::lookup_or_new(context, Namespace::Auth, group).await?;
+    let qr = securejoin_qr(context, group, &invitenumber, &auth).await?;
+    record_invite(context, group, &invitenumber, &auth, 0).await?;
+    if sync_token {
+        context.sync_qr_code_tokens(group).await?;
+        context.scheduler.interrupt_smtp().await;
+    }
+
+    info!(context, "Generated QR code.");
+    Ok(qr)
+}
+
+/// Generates a Secure Join invite link which can be shared
+/// e.g. via email or websites and opened by clicking it.
+///
+/// The link contains the same payload as [`get_securejoin_qr`]
+/// and is accepted by [`check_qr`] and [`join_securejoin`].
+///
+/// If `validity` is `None`, the link uses the same tokens as the QR code
+/// and can be withdrawn together with it.
+/// Otherwise, new tokens are created which expire after `validity`;
+/// such a link stays valid if the QR code is withdrawn and vice versa.
+/// An expiring link can be withdrawn before it expires by passing it to [`check_qr`]
+/// and [`crate::qr::set_config_from_qr`] like a QR code.
+pub async fn get_securejoin_invite_link(
+    context: &Context,
+    group: Option<ChatId>,
+    validity: Option<Duration>,
+) -> Result<String> {
+    let qr = match validity {
+        None => get_securejoin_qr(context, group).await?,
+        Some(validity) => {
+            ensure_secret_key_exists(context).await.ok();
+            let expires = time().saturating_add(i64::try_from(validity.as_secs())?);
+            let invitenumber = create_id();
+            let auth = create_id();
+            let qr = securejoin_qr(context, group, &invitenumber, &auth).await?;
+            token::save_with_expiry(
+                context,
+                Namespace::InviteNumber,
+                group,
+                &invitenumber,
+                expires,
+            )
+            .await?;
+            token::save_with_expiry(context, Namespace::Auth, group, &auth, expires).await?;
+            record_invite(context, group, &invitenumber, &auth, expires).await?;
+            context
+                .sync_expiring_qr_code_tokens(group, invitenumber, auth, expires)
+                .await?;
+            qr
+        }
+    };
+    let payload = qr
+        .strip_prefix("OPENPGP4FPR:")
+        .context("Unexpected QR code format")?
+        .replacen('#', "&", 1);
+    Ok(format!("https://i.delta.chat/#{payload}"))
+}
+
+async fn securejoin_qr(
+    context: &Context,
+    group: Option<ChatId>,
+    invitenumber: &str,
+    auth: &str,
+) -> Result<String> {
     let self_addr = context.get_primary_self_addr().await?;
     let self_name = context
         .get_config(Config::Displayname)
@@ -96,39 +208,217 @@ pub async fn get_securejoin_qr(context: &Context, group: Option<ChatId>) -> Resu
         }
         let group_name = chat.get_name();
         let group_name_urlencoded = utf8_percent_encode(group_name, NON_ALPHANUMERIC).to_string();
-        if sync_token {
-            context.sync_qr_code_tokens(Some(chat.id)).await?;
-            context.scheduler.interrupt_smtp().await;
-        }
         format!(
             "OPENPGP4FPR:{}#a={}&g={}&x={}&i={}&s={}",
             fingerprint.hex(),
             self_addr_urlencoded,
             &group_name_urlencoded,
             &chat.grpid,
-            &invitenumber,
-            &auth,
+            invitenumber,
+            auth,
         )
     } else {
         // parameters used: a=n=i=s=
-        if sync_token {
-            context.sync_qr_code_tokens(None).await?;
-            context.scheduler.interrupt_smtp().await;
-        }
         format!(
             "OPENPGP4FPR:{}#a={}&n={}&i={}&s={}",
             fingerprint.hex(),
             self_addr_urlencoded,
             self_name_urlencoded,
-            &invitenumber,
-            &auth,
+            invitenumber,
+            auth,
         )
     };
 
-    info!(context, "Generated QR code.");
     Ok(qr)
 }
 
+/// Secure-Join invite issued by [`get_securejoin_qr`] or [`get_securejoin_invite_link`].
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct IssuedInvite {
+    /// Invite ID, used to withdraw and re-issue the invite.
+    pub id: u32,
+
+    /// Group the invite is for, `None` for setup-contact invites.
+    pub chat_id: Option<ChatId>,
+
+    /// Timestamp of issuing the invite.
+    pub timestamp: i64,
+
+    /// Expiry timestamp, 0 if the invite never expires.
+    pub expires: i64,
+
+    /// Whether the invite is withdrawn.
+    ///
+    /// Withdrawn invites are rejected when scanned until they are re-issued.
+    pub withdrawn: bool,
+
+    invitenumber: String,
+    auth: String,
+}
+
+/// Returns all issued invites which are not expired, most recent first.
+///
+/// Invites issued before the invite list was introduced
+/// are only listed after their QR code was generated again.
+pub async fn get_issued_invites(context: &Context) -> Result<Vec<IssuedInvite>> {
+    context
+        .sql
+        .query_map(
+            "SELECT id, chat_id, timestamp, expires, withdrawn, invitenumber, auth
+             FROM securejoin_invites
+             WHERE expires=0 OR expires>?
+             ORDER BY timestamp DESC, id DESC",
+            (time(),),
+            issued_invite_from_row,
+            |rows| {
+                rows.collect::<std::result::Result<Vec<_>, _>>()
+                    .map_err(Into::into)
+            },
+        )
+        .await
+}
+
+/// Withdraws an issued invite.
+///
+/// Joiners scanning the invite afterwards are told that it was withdrawn.
+pub async fn withdraw_invite(context: &Context, id: u32) -> Result<()> {
+    let invite = load_issued_invite(context, id).await?;
+    token::delete(context, Namespace::InviteNumber, &invite.invitenumber).await?;
+    token::delete(context, Namespace::Auth, &invite.auth).await?;
+    set_invite_withdrawn(context, &invite.invitenumber, true).await?;
+    context
+        .sync_qr_code_token_deletion(invite.invitenumber, invite.auth)
+        .await?;
+    Ok(())
+}
+
+/// Re-issues a withdrawn invite, so that the same QR code is accepted again.
+///
+/// Returns the QR code text of the invite.
+/// Expired invites cannot be re-issued.
+pub async fn reissue_invite(context: &Context, id: u32) -> Result<String> {
+    let invite = load_issued_invite(context, id).await?;
+    ensure!(
+        invite.expires == 0 || invite.expires > time(),
+        "Invite {id} is expired"
+    );
+    if invite.withdrawn {
+        token::save_with_expiry(
+            context,
+            Namespace::InviteNumber,
+            invite.chat_id,
+            &invite.invitenumber,
+            invite.expires,
+        )
+        .await?;
+        token::save_with_expiry(
+            context,
+            Namespace::Auth,
+            invite.chat_id,
+            &invite.auth,
+            invite.expires,
+        )
+        .await?;
+        set_invite_withdrawn(context, &invite.invitenumber, false).await?;
+        if invite.expires == 0 {
+            context.sync_qr_code_tokens(invite.chat_id).await?;
+            context.scheduler.interrupt_smtp().await;
+        } else {
+            context
+                .sync_expiring_qr_code_tokens(
+                    invite.chat_id,
+                    invite.invitenumber.clone(),
+                    invite.auth.clone(),
+                    invite.expires,
+                )
+                .await?;
+        }
+    }
+    securejoin_qr(context, invite.chat_id, &invite.invitenumber, &invite.auth).await
+}
+
+async fn load_issued_invite(context: &Context, id: u32) -> Result<IssuedInvite> {
+    context
+        .sql
+        .query_row_optional(
+            "SELECT id, chat_id, timestamp, expires, withdrawn, invitenumber, auth
+             FROM securejoin_invites WHERE id=?",
+            (id,),
+            issued_invite_from_row,
+        )
+        .await?
+        .with_context(|| format!("No invite with ID {id}"))
+}
+
+fn issued_invite_from_row(row: &rusqlite::Row) -> rusqlite::Result<IssuedInvite> {
+    let chat_id: ChatId = row.get(1)?;
+    Ok(IssuedInvite {
+        id: row.get(0)?,
+        chat_id: Some(chat_id).filter(|chat_id| !chat_id.is_unset()),
+        timestamp: row.get(2)?,
+        expires: row.get(3)?,
+        withdrawn: row.get(4)?,
+        invitenumber: row.get(5)?,
+        auth: row.get(6)?,
+    })
+}
+
+/// Adds an invite to the list of issued invites.
+///
+/// If the invite is already in the list, it is marked as not withdrawn.
+pub(crate) async fn record_invite(
+    context: &Context,
+    group: Option<ChatId>,
+    invitenumber: &str,
+    auth: &str,
+    expires: i64,
+) -> Result<()> {
+    context
+        .sql
+        .execute(
+            "INSERT INTO securejoin_invites (chat_id, invitenumber, auth, timestamp, expires)
+             VALUES (?, ?, ?, ?, ?)
+             ON CONFLICT(invitenumber) DO UPDATE SET withdrawn=0",
+            (
+                group.unwrap_or_default(),
+                invitenumber,
+                auth,
+                time(),
+                expires,
+            ),
+        )
+        .await?;
+    Ok(())
+}
+
+/// Marks an issued invite as withdrawn or not withdrawn.
+pub(crate) async fn set_invite_withdrawn(
+    context: &Context,
+    invitenumber: &str,
+    withdrawn: bool,
+) -> Result<()> {
+    context
+        .sql
+        .execute(
+            "UPDATE securejoin_invites SET withdrawn=? WHERE invitenumber=?",
+            (withdrawn, invitenumber),
+        )
+        .await?;
+    Ok(())
+}
+
+/// Returns true if the invitenumber belongs to a withdrawn invite which is not expired.
+async fn is_invite_withdrawn(context: &Context, invitenumber: &str) -> Result<bool> {
+    context
+        .sql
+        .exists(
+            "SELECT COUNT(*) FROM securejoin_invites
+             WHERE invitenumber=? AND withdrawn=1 AND (expires=0 OR expires>?)",
+            (invitenumber, time()),
+        )
+        .await
+}
+
 async fn get_self_fingerprint(context: &Context) -> Option<Fingerprint> {
     match load_self_public_key(context).await {
         Ok(key) => Some(key.fingerprint()),
@@ -332,6 +622,19 @@ pub(crate) async fn handle_securejoin_handshake(
                 }
             };
             if !token::exists(context, token::Namespace::InviteNumber, invitenumber).await? {
+                if is_invite_withdrawn(context, invitenumber).await? {
+                    warn!(context, "Secure-join denied (withdrawn invitenumber).");
+                    // Alice -> Bob
+                    send_alice_handshake_msg(
+                        context,
+                        contact_id,
+                        &format!("{}-invite-withdrawn", &step[..2]),
+                    )
+                    .await
+                    .context("failed sending invite-withdrawn handshake message")?;
+                    inviter_failed(context, contact_id, SecurejoinFailure::InviteWithdrawn);
+                    return Ok(HandshakeMessage::Done);
+                }
                 warn!(context, "Secure-join denied (bad invitenumber).");
                 return Ok(HandshakeMessage::Ignore);
             }
@@ -361,6 +664,13 @@ pub(crate) async fn handle_securejoin_handshake(
             ========================================================*/
             bob::handle_auth_required(context, mime_message).await
         }
+        "vg-invite-withdrawn" | "vc-invite-withdrawn" => {
+            /*========================================================
+            ====             Bob - the joiner's side             =====
+            ====     The invite was withdrawn by the inviter     =====
+            ========================================================*/
+            bob::handle_invite_withdrawn(context, mime_message).await
+        }
         "vg-request-with-auth" | "vc-request-with-auth" => {
             /*==========================================================
             ====              Alice - the inviter side              ====
@@ -380,6 +690,7 @@ pub(crate) async fn handle_securejoin_handshake(
                             "Fingerprint not provided.",
                         )
                         .await?;
+                        inviter_failed(context, contact_id, SecurejoinFailure::Other);
                         return Ok(HandshakeMessage::Ignore);
                     }
                 };
@@ -391,6 +702,7 @@ pub(crate) async fn handle_securejoin_handshake(
                     "Auth not encrypted.",
                 )
                 .await?;
+                inviter_failed(context, contact_id, SecurejoinFailure::NotEncrypted);
                 return Ok(HandshakeMessage::Ignore);
             }
             if !verify_sender_by_fingerprint(context, &fingerprint, contact_id).await? {
@@ -401,6 +713,7 @@ pub(crate) async fn handle_securejoin_handshake(
                     "Fingerprint mismatch on inviter-side.",
                 )
                 .await?;
+                inviter_failed(context, contact_id, SecurejoinFailure::FingerprintMismatch);
                 return Ok(HandshakeMessage::Ignore);
             }
             info!(context, "Fingerprint verified.",);
@@ -413,6 +726,7 @@ pub(crate) async fn handle_securejoin_handshake(
                     "Auth not provided.",
                 )
                 .await?;
+                inviter_failed(context, contact_id, SecurejoinFailure::AuthInvalid);
                 return Ok(HandshakeMessage::Ignore);
             };
             let Some(group_chat_id) = token::auth_chat_id(context, auth).await? else {
@@ -423,6 +737,7 @@ pub(crate) async fn handle_securejoin_handshake(
                     "Auth invalid.",
                 )
                 .await?;
+                inviter_failed(context, contact_id, SecurejoinFailure::AuthInvalid);
                 return Ok(HandshakeMessage::Ignore);
             };
 
@@ -446,6 +761,7 @@ pub(crate) async fn handle_securejoin_handshake(
                     "Fingerprint mismatch on inviter-side.",
                 )
                 .await?;
+                inviter_failed(context, contact_id, SecurejoinFailure::FingerprintMismatch);
                 return Ok(HandshakeMessage::Ignore);
             }
             contact_id.regossip_keys(context).await?;
@@ -763,14 +1079,187 @@ mod tests {
     use crate::chatlist::Chatlist;
     use crate::constants::{self, Chattype};
     use crate::imex::{imex, ImexMode};
+    use crate::qr::Qr;
     use crate::receive_imf::receive_imf;
     use crate::stock_str::{self, chat_protection_enabled};
     use crate::test_utils::get_chat_msg;
-    use crate::test_utils::{TestContext, TestContextManager};
+    use crate::test_utils::{sync, TestContext, TestContextManager};
     use crate::tools::SystemTime;
     use std::collections::HashSet;
     use std::time::Duration;
 
+    #[tokio::test(flavor = "multi_thread", worker_threads = 2)]
+    async fn test_invite_link() -> Result<()> {
+        let mut tcm = TestContextManager::new();
+        let alice = tcm.alice().await;
+        let bob = tcm.bob().await;
+
+        // Without validity, the link is just another form of the QR code.
+        let qr = get_securejoin_qr(&alice, None).await?;
+        let link = get_securejoin_invite_link(&alice, None, None).await?;
+        assert!(link.starts_with("https://i.delta.chat/#"));
+        assert_eq!(check_qr(&bob, &link).await?, check_qr(&bob, &qr).await?);
+
+        let link =
+            get_securejoin_invite_link(&alice, None, Some(Duration::from_secs(3600))).await?;
+        assert!(matches!(
+            check_qr(&alice, &link).await?,
+            Qr::WithdrawVerifyContact { .. }
+        ));
+        // The QR code is not affected by expiring links.
+        assert_eq!(get_securejoin_qr(&alice, None).await?, qr);
+
+        tcm.section("Bob clicks the link");
+        join_securejoin(&bob, &link).await?;
+        loop {
+            if let Some(sent) = bob.pop_sent_msg_opt(Duration::ZERO).await {
+                alice.recv_msg_opt(&sent).await;
+            } else if let Some(sent) = alice.pop_sent_msg_opt(Duration::ZERO).await {
+                bob.recv_msg_opt(&sent).await;
+            } else {
+                break;
+            }
+        }
+        let alice_bob_contact = alice.add_or_lookup_contact(&bob).await;
+        assert!(alice_bob_contact.is_verified(&alice).await?);
+
+        tcm.section("The link expires");
+        SystemTime::shift(Duration::from_secs(7200));
+        assert!(matches!(
+            check_qr(&alice, &link).await?,
+            Qr::ReviveVerifyContact { .. }
+        ));
+        assert!(matches!(
+            check_qr(&alice, &qr).await?,
+            Qr::WithdrawVerifyContact { .. }
+        ));
+
+        Ok(())
+    }
+
+    #[tokio::test(flavor = "multi_thread", worker_threads = 2)]
+    async fn test_withdraw_and_reissue_invite() -> Result<()> {
+        let mut tcm = TestContextManager::new();
+        let alice = tcm.alice().await;
+        let bob = tcm.bob().await;
+
+        let qr = get_securejoin_qr(&alice, None).await?;
+        let invites = get_issued_invites(&alice).await?;
+        assert_eq!(invites.len(), 1);
+        let invite = invites.first().unwrap();
+        assert_eq!(invite.chat_id, None);
+        assert!(!invite.withdrawn);
+
+        // Showing the QR code again does not issue a new invite.
+        get_securejoin_qr(&alice, None).await?;
+        assert_eq!(get_issued_invites(&alice).await?.len(), 1);
+
+        withdraw_invite(&alice, invite.id).await?;
+        assert!(get_issued_invites(&alice).await?.first().unwrap().withdrawn);
+        assert!(matches!(
+            check_qr(&alice, &qr).await?,
+            Qr::ReviveVerifyContact { .. }
+        ));
+
+        tcm.section("Bob scans the withdrawn QR code");
+        let bob_chat_id = join_securejoin(&bob, &qr).await?;
+        let sent = bob.pop_sent_msg().await;
+        alice.recv_msg_trash(&sent).await;
+        let sent = alice.pop_sent_msg().await;
+        assert_eq!(
+            sent.load_from_db().await.param.get(Param::Arg),
+            Some("vc-invite-withdrawn")
+        );
+        bob.recv_msg_trash(&sent).await;
+        let msg = bob.get_last_msg_in(bob_chat_id).await;
+        assert!(msg.is_info());
+        assert!(msg.get_text().contains("withdrawn"));
+        assert_eq!(
+            SecurejoinFailure::InviteWithdrawn.get_text(&bob).await,
+            "The invitation was withdrawn."
+        );
+        for t in [&alice, &bob] {
+            let event = t
+                .evtracker
+                .get_matching(|evt| matches!(evt, EventType::SecurejoinFailed { .. }))
+                .await;
+            assert!(matches!(
+                event,
+                EventType::SecurejoinFailed {
+                    reason: SecurejoinFailure::InviteWithdrawn,
+                    ..
+                }
+            ));
+        }
+        assert!(BobState::from_db(&bob.sql).await?.is_none());
+
+        tcm.section("Alice re-issues the invite");
+        assert_eq!(reissue_invite(&alice, invite.id).await?, qr);
+        assert!(!get_issued_invites(&alice).await?.first().unwrap().withdrawn);
+        assert_eq!(get_securejoin_qr(&alice, None).await?, qr);
+        join_securejoin(&bob, &qr).await?;
+        loop {
+            if let Some(sent) = bob.pop_sent_msg_opt(Duration::ZERO).await {
+                alice.recv_msg_opt(&sent).await;
+            } else if let Some(sent) = alice.pop_sent_msg_opt(Duration::ZERO).await {
+                bob.recv_msg_opt(&sent).await;
+            } else {
+                break;
+            }
+        }
+        let alice_bob_contact = alice.add_or_lookup_contact(&bob).await;
+        assert!(alice_bob_contact.is_verified(&alice).await?);
+
+        Ok(())
+    }
+
+    #[tokio::test(flavor = "multi_thread", worker_threads = 2)]
+    async fn test_sync_invite_link() -> Result<()> {
+        let alice0 = &TestContext::new_alice().await;
+        let alice1 = &TestContext::new_alice().await;
+        for a in [alice0, alice1] {
+            a.set_config_bool(Config::SyncMsgs, true).await?;
+        }
+
+        let link =
+            get_securejoin_invite_link(alice0, None, Some(Duration::from_secs(3600))).await?;
+        sync(alice0, alice1).await;
+        assert!(matches!(
+            check_qr(alice1, &link).await?,
+            Qr::WithdrawVerifyContact { .. }
+        ));
+        let invites = get_issued_invites(alice1).await?;
+        assert_eq!(invites.len(), 1);
+        assert_eq!(
+            invites[0].expires,
+            get_issued_invites(alice0).await?[0].expires
+        );
+
+        // Withdrawing and re-issuing the link is synced as well.
+        let id = get_issued_invites(alice0).await?[0].id;
+        withdraw_invite(alice0, id).await?;
+        sync(alice0, alice1).await;
+        assert!(get_issued_invites(alice1).await?[0].withdrawn);
+        assert!(matches!(
+            check_qr(alice1, &link).await?,
+            Qr::ReviveVerifyContact { .. }
+        ));
+
+        reissue_invite(alice0, id).await?;
+        sync(alice0, alice1).await;
+        assert!(!get_issued_invites(alice1).await?[0].withdrawn);
+        assert!(matches!(
+            check_qr(alice1, &link).await?,
+            Qr::WithdrawVerifyContact { .. }
+        ));
+
+        // The link expires on the other device too.
+        SystemTime::shift(Duration::from_secs(7200));
+        assert!(get_issued_invites(alice1).await?.is_empty());
+
+        Ok(())
+    }
+
     #[derive(PartialEq)]
     enum SetupContactCase {
         Normal,
@@ -1095,6 +1584,7 @@ async fn test_setup_contact_bob_knows_alice() -> Result<()> {
             secondary_verifier: None,
             backward_verified_key_id: None,
             fingerprint_changed: false,
+            old_fingerprint: None,
         };
         peerstate.save_to_db(&bob.ctx.sql).await?;
 
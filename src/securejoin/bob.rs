@@ -7,7 +7,7 @@
 
 use super::bobstate::{BobHandshakeStage, BobState};
 use super::qrinvite::QrInvite;
-use super::HandshakeMessage;
+use super::{HandshakeMessage, SecurejoinFailure};
 use crate::chat::{is_contact_in_chat, ChatId, ProtectionStatus};
 use crate::constants::{self, Blocked, Chattype};
 use crate::contact::Contact;
@@ -47,8 +47,10 @@ pub(super) async fn start_protocol(context: &Context, invite: QrInvite) -> Resul
         BobState::start_protocol(context, invite.clone(), chat_id).await?;
     for state in aborted_states {
         error!(context, "Aborting previously unfinished QR Join process.");
-        state.notify_aborted(context, "new QR scanned").await?;
-        state.emit_progress(context, JoinerProgress::Error);
+        state
+            .notify_aborted(context, SecurejoinFailure::Aborted)
+            .await?;
+        state.emit_failed(context, SecurejoinFailure::Aborted);
     }
     if matches!(stage, BobHandshakeStage::RequestWithAuthSent) {
         state.emit_progress(context, JoinerProgress::RequestWithAuthSent);
@@ -111,6 +113,7 @@ pub(super) async fn handle_auth_required(
     match bobstate.handle_auth_required(context, message).await? {
         Some(BobHandshakeStage::Terminated(why)) => {
             bobstate.notify_aborted(context, why).await?;
+            bobstate.emit_failed(context, why);
             Ok(HandshakeMessage::Done)
         }
         Some(_stage) => {
@@ -132,6 +135,32 @@ pub(super) async fn handle_auth_required(
     }
 }
 
+/// Handles `vc-invite-withdrawn` and `vg-invite-withdrawn` handshake messages.
+///
+/// # Bob - the joiner's side
+/// The inviter replied to our request that the scanned invite was withdrawn.
+pub(super) async fn handle_invite_withdrawn(
+    context: &Context,
+    message: &MimeMessage,
+) -> Result<HandshakeMessage> {
+    let Some(mut bobstate) = BobState::from_db(&context.sql).await? else {
+        return Ok(HandshakeMessage::Ignore);
+    };
+
+    match bobstate.handle_invite_withdrawn(context, message).await? {
+        Some(BobHandshakeStage::Terminated(why)) => {
+            let contact = Contact::get_by_id(context, bobstate.invite().contact_id()).await?;
+            let msg = stock_str::securejoin_invite_withdrawn(context, &contact).await;
+            let chat_id = bobstate.joining_chat_id(context).await?;
+            chat::add_info_msg(context, chat_id, &msg, time()).await?;
+            info!(context, "Secure-join aborted: {why:?}.");
+            bobstate.emit_failed(context, why);
+            Ok(HandshakeMessage::Done)
+        }
+        Some(_) | None => Ok(HandshakeMessage::Ignore),
+    }
+}
+
 /// Private implementations for user interactions about this [`BobState`].
 impl BobState {
     fn is_join_group(&self) -> bool {
@@ -149,6 +178,15 @@ pub(crate) fn emit_progress(&self, context: &Context, progress: JoinerProgress)
         });
     }
 
+    /// Reports a failed handshake.
+    pub(crate) fn emit_failed(&self, context: &Context, reason: SecurejoinFailure) {
+        self.emit_progress(context, JoinerProgress::Error);
+        context.emit_event(EventType::SecurejoinFailed {
+            contact_id: self.invite().contact_id(),
+            reason,
+        });
+    }
+
     /// Returns the [`ChatId`] of the chat being joined.
     ///
     /// This is the chat in which you want to notify the user as well.
@@ -191,15 +229,19 @@ async fn joining_chat_id(&self, context: &Context) -> Result<ChatId> {
 
     /// Notifies the user that the SecureJoin was aborted.
     ///
-    /// This creates an info message in the chat being joined.
-    async fn notify_aborted(&self, context: &Context, why: &str) -> Result<()> {
+    /// This creates an info message with the reason in the chat being joined.
+    async fn notify_aborted(&self, context: &Context, why: SecurejoinFailure) -> Result<()> {
         let contact = Contact::get_by_id(context, self.invite().contact_id()).await?;
-        let msg = stock_str::contact_not_verified(context, &contact).await;
+        let msg = format!(
+            "{}\n{}",
+            stock_str::contact_not_verified(context, &contact).await,
+            why.get_text(context).await
+        );
         let chat_id = self.joining_chat_id(context).await?;
         chat::add_info_msg(context, chat_id, &msg, time()).await?;
         warn!(
             context,
-            "StockMessage::ContactNotVerified posted to joining chat ({})", why
+            "StockMessage::ContactNotVerified posted to joining chat ({why:?})"
         );
         Ok(())
     }
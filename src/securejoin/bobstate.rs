@@ -11,7 +11,7 @@
 use rusqlite::Connection;
 
 use super::qrinvite::QrInvite;
-use super::{encrypted_and_signed, verify_sender_by_fingerprint};
+use super::{encrypted_and_signed, verify_sender_by_fingerprint, SecurejoinFailure};
 use crate::chat::{self, ChatId};
 use crate::config::Config;
 use crate::contact::{ContactId, Origin};
@@ -37,7 +37,7 @@ pub enum BobHandshakeStage {
     /// Step 4 completed: (vc|vg)-request-with-auth message sent.
     RequestWithAuthSent,
     /// The protocol prematurely terminated with given reason.
-    Terminated(&'static str),
+    Terminated(SecurejoinFailure),
 }
 
 /// The securejoin state kept while Bob is joining.
@@ -271,14 +271,16 @@ pub(crate) async fn handle_auth_required(
             "Bob Step 4 - handling {{vc,vg}}-auth-required message."
         );
         if !encrypted_and_signed(context, mime_message, Some(self.invite.fingerprint())) {
-            let reason = if mime_message.was_encrypted() {
-                "Valid signature missing"
+            if mime_message.was_encrypted() {
+                warn!(context, "Valid signature missing.");
             } else {
-                "Required encryption missing"
-            };
+                warn!(context, "Required encryption missing.");
+            }
             self.update_next(&context.sql, SecureJoinStep::Terminated)
                 .await?;
-            return Ok(Some(BobHandshakeStage::Terminated(reason)));
+            return Ok(Some(BobHandshakeStage::Terminated(
+                SecurejoinFailure::NotEncrypted,
+            )));
         }
         if !verify_sender_by_fingerprint(
             context,
@@ -289,7 +291,9 @@ pub(crate) async fn handle_auth_required(
         {
             self.update_next(&context.sql, SecureJoinStep::Terminated)
                 .await?;
-            return Ok(Some(BobHandshakeStage::Terminated("Fingerprint mismatch")));
+            return Ok(Some(BobHandshakeStage::Terminated(
+                SecurejoinFailure::FingerprintMismatch,
+            )));
         }
         info!(context, "Fingerprint verified.",);
 
@@ -300,6 +304,38 @@ pub(crate) async fn handle_auth_required(
         Ok(Some(BobHandshakeStage::RequestWithAuthSent))
     }
 
+    /// Handles {vc,vg}-invite-withdrawn message of the securejoin handshake for Bob.
+    ///
+    /// If the message was not used for this handshake `None` is returned, otherwise the
+    /// handshake is terminated and [`BobHandshakeStage::Terminated`] is returned.
+    pub(crate) async fn handle_invite_withdrawn(
+        &mut self,
+        context: &Context,
+        mime_message: &MimeMessage,
+    ) -> Result<Option<BobHandshakeStage>> {
+        let Some(step) = mime_message.get_header(HeaderDef::SecureJoin) else {
+            return Ok(None);
+        };
+        if !self.is_msg_expected(context, step) {
+            info!(context, "{} message out of sync for BobState", step);
+            return Ok(None);
+        }
+        // Only the inviter can tell that the invite was withdrawn,
+        // otherwise anyone could abort the handshake.
+        if !encrypted_and_signed(context, mime_message, Some(self.invite.fingerprint())) {
+            warn!(
+                context,
+                "Ignoring {step} message not signed by the inviter."
+            );
+            return Ok(None);
+        }
+        self.update_next(&context.sql, SecureJoinStep::Terminated)
+            .await?;
+        Ok(Some(BobHandshakeStage::Terminated(
+            SecurejoinFailure::InviteWithdrawn,
+        )))
+    }
+
     /// Returns `true` if the message is expected according to the protocol.
     pub(crate) fn is_msg_expected(&self, context: &Context, step: &str) -> bool {
         let variant_matches = match self.invite {
@@ -453,6 +489,7 @@ pub enum SecureJoinStep {
     /// Expecting the auth-required message.
     ///
     /// This corresponds to the `vc-auth-required` or `vg-auth-required` message of step 3d.
+    /// The inviter may also reply with `vc-invite-withdrawn` or `vg-invite-withdrawn` instead.
     AuthRequired,
     /// Expecting the contact-confirm message.
     ///
@@ -476,7 +513,13 @@ impl SecureJoinStep {
     /// Compares the legacy string representation of a step to a [`SecureJoinStep`] variant.
     fn matches(&self, context: &Context, step: &str) -> bool {
         match self {
-            Self::AuthRequired => step == "vc-auth-required" || step == "vg-auth-required",
+            Self::AuthRequired => matches!(
+                step,
+                "vc-auth-required"
+                    | "vg-auth-required"
+                    | "vc-invite-withdrawn"
+                    | "vg-invite-withdrawn"
+            ),
             Self::ContactConfirm => step == "vc-contact-confirm" || step == "vg-member-added",
             SecureJoinStep::Terminated => {
                 warn!(context, "Terminated state for next securejoin step");
@@ -8,7 +8,7 @@
 use async_smtp::{self as smtp, EmailAddress, SmtpTransport};
 use tokio::task;
 
-use crate::chat::{add_info_msg_with_cmd, ChatId};
+use crate::chat::{self, add_info_msg_with_cmd, ChatId};
 use crate::config::Config;
 use crate::contact::{Contact, ContactId};
 use crate::context::Context;
@@ -20,7 +20,8 @@
 use crate::net::session::SessionBufStream;
 use crate::oauth2::get_oauth2_access_token;
 use crate::scheduler::connectivity::ConnectivityStore;
-use crate::socks::Socks5Config;
+use crate::socks::{is_proxy_error, Socks5Config};
+use crate::spam_report;
 use crate::sql;
 use crate::stock_str::unencrypted_email;
 use crate::tools::{self, time_elapsed};
@@ -134,7 +135,7 @@ pub async fn connect(
             | CertificateChecks::AcceptInvalidCertificates2 => false,
         };
 
-        let session_stream = connect::connect_stream(
+        let session_stream = match connect::connect_stream(
             context,
             domain,
             port,
@@ -142,7 +143,20 @@ pub async fn connect(
             socks5_config.clone(),
             lp.security,
         )
-        .await?;
+        .await
+        {
+            Ok(session_stream) => session_stream,
+            Err(err) => {
+                if let Some(socks5_config) = socks5_config {
+                    if is_proxy_error(&err) {
+                        if let Err(err) = context.switch_proxy_after_failure(socks5_config).await {
+                            warn!(context, "Failed to switch proxy: {err:#}.");
+                        }
+                    }
+                }
+                return Err(err);
+            }
+        };
         let client = smtp::SmtpClient::new().smtp_utf8(true).without_greeting();
         let mut transport = SmtpTransport::new(client, session_stream).await?;
 
@@ -435,6 +449,13 @@ pub(crate) async fn send_msg_to_smtp(
                 .sql
                 .execute("DELETE FROM smtp WHERE id=?", (rowid,))
                 .await?;
+            let addrs = recipients.split(' ').map(ToString::to_string).collect();
+            if let Err(err) = message::save_delivery(context, msg_id, addrs).await {
+                warn!(
+                    context,
+                    "Cannot save delivery of message {msg_id}: {err:#}."
+                );
+            }
         }
         SendResult::Failure(ref err) => {
             if err.to_string().contains("Invalid unencrypted mail") {
@@ -520,6 +541,16 @@ pub(crate) async fn send_smtp_messages(context: &Context, connection: &mut Smtp)
         true
     };
 
+    // Messages left unprepared e.g. because the app was terminated.
+    if context.deferred_msgs_mutex.try_lock().is_ok()
+        && context
+            .sql
+            .exists("SELECT COUNT(*) FROM smtp_prepare", ())
+            .await?
+    {
+        chat::spawn_prepare_deferred_msgs(context);
+    }
+
     let rowids = context
         .sql
         .query_map(
@@ -551,6 +582,9 @@ pub(crate) async fn send_smtp_messages(context: &Context, connection: &mut Smtp)
         send_mdns(context, connection)
             .await
             .context("Failed to send MDNs")?;
+        spam_report::send_reports(context, connection)
+            .await
+            .context("Failed to send abuse reports")?;
     }
     Ok(())
 }
@@ -7,8 +7,8 @@
 use crate::context::Context;
 use crate::net::session::SessionBufStream;
 use crate::net::tls::wrap_tls;
-use crate::net::{connect_starttls_smtp, connect_tcp, connect_tls};
-use crate::provider::Socket;
+use crate::net::{connect_starttls_smtp, connect_tcp, connect_tls, Timeouts};
+use crate::provider::{Protocol, Socket};
 use crate::socks::Socks5Config;
 
 /// Returns TLS, STARTTLS or plaintext connection
@@ -27,27 +27,45 @@ pub(crate) async fn connect_stream(
     socks5_config: Option<Socks5Config>,
     security: Socket,
 ) -> Result<Box<dyn SessionBufStream>> {
+    let timeouts = Timeouts::load(context, Protocol::Smtp).await?;
     let stream = if let Some(socks5_config) = socks5_config {
         match security {
             Socket::Automatic => bail!("SMTP port security is not configured"),
             Socket::Ssl => {
-                connect_secure_socks5(context, domain, port, strict_tls, socks5_config.clone())
-                    .await?
+                connect_secure_socks5(
+                    context,
+                    domain,
+                    port,
+                    strict_tls,
+                    socks5_config.clone(),
+                    timeouts,
+                )
+                .await?
             }
             Socket::Starttls => {
-                connect_starttls_socks5(context, domain, port, strict_tls, socks5_config.clone())
-                    .await?
+                connect_starttls_socks5(
+                    context,
+                    domain,
+                    port,
+                    strict_tls,
+                    socks5_config.clone(),
+                    timeouts,
+                )
+                .await?
             }
             Socket::Plain => {
-                connect_insecure_socks5(context, domain, port, socks5_config.clone()).await?
+                connect_insecure_socks5(context, domain, port, socks5_config.clone(), timeouts)
+                    .await?
             }
         }
     } else {
         match security {
             Socket::Automatic => bail!("SMTP port security is not configured"),
-            Socket::Ssl => connect_secure(context, domain, port, strict_tls).await?,
-            Socket::Starttls => connect_starttls(context, domain, port, strict_tls).await?,
-            Socket::Plain => connect_insecure(context, domain, port).await?,
+            Socket::Ssl => connect_secure(context, domain, port, strict_tls, timeouts).await?,
+            Socket::Starttls => {
+                connect_starttls(context, domain, port, strict_tls, timeouts).await?
+            }
+            Socket::Plain => connect_insecure(context, domain, port, timeouts).await?,
         }
     };
     Ok(stream)
@@ -82,11 +100,19 @@ async fn connect_secure_socks5(
     port: u16,
     strict_tls: bool,
     socks5_config: Socks5Config,
+    timeouts: Timeouts,
 ) -> Result<Box<dyn SessionBufStream>> {
     let socks5_stream = socks5_config
-        .connect(context, hostname, port, strict_tls)
+        .connect(context, hostname, port, strict_tls, timeouts)
         .await?;
-    let tls_stream = wrap_tls(strict_tls, hostname, "smtp", socks5_stream).await?;
+    let tls_stream = wrap_tls(
+        strict_tls,
+        hostname,
+        "smtp",
+        socks5_stream,
+        timeouts.tls_handshake,
+    )
+    .await?;
     let mut buffered_stream = BufStream::new(tls_stream);
     skip_smtp_greeting(&mut buffered_stream).await?;
     let session_stream: Box<dyn SessionBufStream> = Box::new(buffered_stream);
@@ -99,18 +125,25 @@ async fn connect_starttls_socks5(
     port: u16,
     strict_tls: bool,
     socks5_config: Socks5Config,
+    timeouts: Timeouts,
 ) -> Result<Box<dyn SessionBufStream>> {
     let socks5_stream = socks5_config
-        .connect(context, hostname, port, strict_tls)
+        .connect(context, hostname, port, strict_tls, timeouts)
         .await?;
 
     // Run STARTTLS command and convert the client back into a stream.
     let client = SmtpClient::new().smtp_utf8(true);
     let transport = SmtpTransport::new(client, BufStream::new(socks5_stream)).await?;
     let tcp_stream = transport.starttls().await?.into_inner();
-    let tls_stream = wrap_tls(strict_tls, hostname, "smtp", tcp_stream)
-        .await
-        .context("STARTTLS upgrade failed")?;
+    let tls_stream = wrap_tls(
+        strict_tls,
+        hostname,
+        "smtp",
+        tcp_stream,
+        timeouts.tls_handshake,
+    )
+    .await
+    .context("STARTTLS upgrade failed")?;
     let buffered_stream = BufStream::new(tls_stream);
     let session_stream: Box<dyn SessionBufStream> = Box::new(buffered_stream);
     Ok(session_stream)
@@ -121,9 +154,10 @@ async fn connect_insecure_socks5(
     hostname: &str,
     port: u16,
     socks5_config: Socks5Config,
+    timeouts: Timeouts,
 ) -> Result<Box<dyn SessionBufStream>> {
     let socks5_stream = socks5_config
-        .connect(context, hostname, port, false)
+        .connect(context, hostname, port, false, timeouts)
         .await?;
     let mut buffered_stream = BufStream::new(socks5_stream);
     skip_smtp_greeting(&mut buffered_stream).await?;
@@ -136,8 +170,9 @@ async fn connect_secure(
     hostname: &str,
     port: u16,
     strict_tls: bool,
+    timeouts: Timeouts,
 ) -> Result<Box<dyn SessionBufStream>> {
-    let tls_stream = connect_tls(context, hostname, port, strict_tls, "smtp").await?;
+    let tls_stream = connect_tls(context, hostname, port, strict_tls, "smtp", timeouts).await?;
     let mut buffered_stream = BufStream::new(tls_stream);
     skip_smtp_greeting(&mut buffered_stream).await?;
     let session_stream: Box<dyn SessionBufStream> = Box::new(buffered_stream);
@@ -149,8 +184,9 @@ async fn connect_starttls(
     hostname: &str,
     port: u16,
     strict_tls: bool,
+    timeouts: Timeouts,
 ) -> Result<Box<dyn SessionBufStream>> {
-    let tls_stream = connect_starttls_smtp(context, hostname, port, strict_tls).await?;
+    let tls_stream = connect_starttls_smtp(context, hostname, port, strict_tls, timeouts).await?;
 
     let buffered_stream = BufStream::new(tls_stream);
     let session_stream: Box<dyn SessionBufStream> = Box::new(buffered_stream);
@@ -161,8 +197,9 @@ async fn connect_insecure(
     context: &Context,
     hostname: &str,
     port: u16,
+    timeouts: Timeouts,
 ) -> Result<Box<dyn SessionBufStream>> {
-    let tcp_stream = connect_tcp(context, hostname, port, false).await?;
+    let tcp_stream = connect_tcp(context, hostname, port, false, timeouts).await?;
     let mut buffered_stream = BufStream::new(tcp_stream);
     skip_smtp_greeting(&mut buffered_stream).await?;
     let session_stream: Box<dyn SessionBufStream> = Box::new(buffered_stream);
@@ -0,0 +1,287 @@
+//! # Reporting blocked contact requests to the provider.
+//!
+//! If [`Config::ReportSpam`] is enabled,
+//! blocking a contact request queues an abuse report
+//! in the Abuse Reporting Format ([RFC 5965])
+//! to the abuse report address of the provider,
+//! so that the provider can improve its spam filtering.
+//! The address is taken from [`Config::SpamReportAddr`] or the provider database,
+//! as providers only accept reports at addresses they explicitly allow,
+//! e.g. chatmail servers reject unencrypted messages to other addresses.
+//! If the address is unknown, the report is not sent and an error is shown.
+//!
+//! The report contains only the headers of the last message of the contact request,
+//! not its content.
+//! As the report is sent unencrypted,
+//! the subject of an encrypted message, which was protected by the encryption,
+//! is replaced with `...` like in the unencrypted headers of the message.
+//!
+//! [RFC 5965]: https://www.rfc-editor.org/rfc/rfc5965
+
+use anyhow::Result;
+
+use crate::chat::ChatId;
+use crate::config::Config;
+use crate::constants::DC_VERSION_STR;
+use crate::contact::{Contact, ContactId};
+use crate::context::Context;
+use crate::param::{Param, Params};
+use crate::smtp::{smtp_send, SendResult, Smtp};
+use crate::tools::{create_id, create_outgoing_rfc724_mid};
+
+/// Headers of the reported message.
+#[derive(Debug)]
+struct ReportedMessage {
+    /// Message-ID.
+    rfc724_mid: String,
+
+    /// Address of the sender.
+    from: String,
+
+    /// Timestamp of the message as sent by the sender.
+    timestamp: i64,
+
+    /// Subject of the message, `...` if the message was encrypted.
+    subject: String,
+}
+
+/// Formats a timestamp for use in headers.
+fn format_date(timestamp: i64) -> String {
+    chrono::DateTime::<chrono::Utc>::from_timestamp(timestamp, 0)
+        .unwrap_or_default()
+        .to_rfc2822()
+}
+
+/// Renders the abuse report about `msg` from `from` to `to`.
+fn render_report(from: &str, to: &str, msg: &ReportedMessage) -> String {
+    let boundary = create_id();
+    let subject = msg.subject.replace(['\r', '\n'], " ");
+    format!(
+        "From: <{from}>\r\n\
+         To: <{to}>\r\n\
+         Subject: Abuse report for {reported}\r\n\
+         Date: {date}\r\n\
+         Message-ID: <{mid}>\r\n\
+         MIME-Version: 1.0\r\n\
+         Content-Type: multipart/report; report-type=feedback-report; boundary=\"{boundary}\"\r\n\
+         \r\n\
+         --{boundary}\r\n\
+         Content-Type: text/plain; charset=utf-8\r\n\
+         \r\n\
+         The user blocked a contact request from {reported}.\r\n\
+         \r\n\
+         --{boundary}\r\n\
+         Content-Type: message/feedback-report\r\n\
+         \r\n\
+         Feedback-Type: abuse\r\n\
+         User-Agent: Delta Chat/{version}\r\n\
+         Version: 1\r\n\
+         Original-Mail-From: <{reported}>\r\n\
+         Original-Rcpt-To: <{from}>\r\n\
+         Arrival-Date: {sent}\r\n\
+         \r\n\
+         --{boundary}\r\n\
+         Content-Type: text/rfc822-headers\r\n\
+         \r\n\
+         From: <{reported}>\r\n\
+         To: <{from}>\r\n\
+         Date: {sent}\r\n\
+         Message-ID: <{reported_mid}>\r\n\
+         Subject: {subject}\r\n\
+         \r\n\
+         --{boundary}--\r\n",
+        reported = msg.from,
+        date = format_date(crate::tools::time()),
+        mid = create_outgoing_rfc724_mid(),
+        version = DC_VERSION_STR.as_str(),
+        sent = format_date(msg.timestamp),
+        reported_mid = msg.rfc724_mid,
+    )
+}
+
+/// Queues an abuse report about the contact request `chat_id` if reporting is enabled.
+pub(crate) async fn queue_report(context: &Context, chat_id: ChatId) -> Result<()> {
+    if !context.get_config_bool(Config::ReportSpam).await? {
+        return Ok(());
+    }
+
+    let Some((from_id, rfc724_mid, timestamp, subject, param)) = context
+        .sql
+        .query_row_optional(
+            "SELECT from_id, rfc724_mid, timestamp_sent, subject, param FROM msgs
+             WHERE chat_id=? AND from_id!=? AND hidden=0
+             ORDER BY timestamp DESC, id DESC LIMIT 1",
+            (chat_id, ContactId::INFO),
+            |row| {
+                let from_id: ContactId = row.get(0)?;
+                let rfc724_mid: String = row.get(1)?;
+                let timestamp: i64 = row.get(2)?;
+                let subject: String = row.get(3)?;
+                let param: String = row.get(4)?;
+                Ok((from_id, rfc724_mid, timestamp, subject, param))
+            },
+        )
+        .await?
+    else {
+        return Ok(());
+    };
+    if from_id == ContactId::SELF {
+        return Ok(());
+    }
+
+    let Some(recipient) = context.get_spam_report_addr().await? else {
+        error!(
+            context,
+            "Cannot report spam, the provider does not accept abuse reports."
+        );
+        return Ok(());
+    };
+    let self_addr = context.get_primary_self_addr().await?;
+    let contact = Contact::get_by_id(context, from_id).await?;
+    let param: Params = param.parse().unwrap_or_default();
+    let subject = if param.get_bool(Param::GuaranteeE2ee).unwrap_or_default() {
+        // Don't leak the protected subject.
+        "...".to_string()
+    } else {
+        subject
+    };
+    let msg = ReportedMessage {
+        rfc724_mid,
+        from: contact.get_addr().to_string(),
+        timestamp,
+        subject,
+    };
+    let mime = render_report(&self_addr, &recipient, &msg);
+    context
+        .sql
+        .insert(
+            "INSERT INTO smtp_spam_reports (recipient, mime) VALUES (?, ?)",
+            (&recipient, &mime),
+        )
+        .await?;
+    info!(
+        context,
+        "Queued abuse report about {} to {recipient}.", msg.from
+    );
+    context.scheduler.interrupt_smtp().await;
+    Ok(())
+}
+
+/// Sends the queued abuse reports.
+///
+/// Reports failing permanently are dropped,
+/// an error is returned on temporary failure so that sending is retried later.
+pub(crate) async fn send_reports(context: &Context, smtp: &mut Smtp) -> Result<()> {
+    let reports = context
+        .sql
+        .query_map(
+            "SELECT id, recipient, mime FROM smtp_spam_reports ORDER BY id",
+            (),
+            |row| {
+                let id: i64 = row.get(0)?;
+                let recipient: String = row.get(1)?;
+                let mime: String = row.get(2)?;
+                Ok((id, recipient, mime))
+            },
+            |rows| rows.collect::<Result<Vec<_>, _>>().map_err(Into::into),
+        )
+        .await?;
+
+    for (id, recipient, mime) in reports {
+        let recipients = match async_smtp::EmailAddress::new(recipient.clone()) {
+            Ok(addr) => vec![addr],
+            Err(err) => {
+                warn!(
+                    context,
+                    "Invalid abuse report recipient {recipient}: {err:?}."
+                );
+                Vec::new()
+            }
+        };
+        if !recipients.is_empty() {
+            match smtp_send(context, &recipients, &mime, smtp, None).await {
+                SendResult::Retry => anyhow::bail!("Temporary error while sending abuse report"),
+                SendResult::Success => info!(context, "Sent abuse report to {recipient}."),
+                SendResult::Failure(err) => {
+                    error!(
+                        context,
+                        "Failed to send abuse report to {recipient}: {err:#}."
+                    )
+                }
+            }
+        }
+        context
+            .sql
+            .execute("DELETE FROM smtp_spam_reports WHERE id=?", (id,))
+            .await?;
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_utils::TestContextManager;
+
+    #[tokio::test(flavor = "multi_thread", worker_threads = 2)]
+    async fn test_report_blocked_contact_request() -> Result<()> {
+        let mut tcm = TestContextManager::new();
+        let alice = &tcm.alice().await;
+        let bob = &tcm.bob().await;
+        alice.set_config_bool(Config::ReportSpam, true).await?;
+
+        let received = tcm.send_recv(bob, alice, "Buy now!").await;
+
+        // Nothing is reported if the provider does not accept abuse reports.
+        queue_report(alice, received.chat_id).await?;
+        assert_eq!(
+            alice
+                .sql
+                .count("SELECT COUNT(*) FROM smtp_spam_reports", ())
+                .await?,
+            0
+        );
+
+        alice
+            .set_config(Config::SpamReportAddr, Some("spam@example.org"))
+            .await?;
+        // Pretend that the message was encrypted and had a protected subject.
+        let mut param = received.param.clone();
+        param.set_int(Param::GuaranteeE2ee, 1);
+        alice
+            .sql
+            .execute(
+                "UPDATE msgs SET subject='Protected subject', param=? WHERE id=?",
+                (param.to_string(), received.id),
+            )
+            .await?;
+        received.chat_id.block(alice).await?;
+
+        let (recipient, mime): (String, String) = alice
+            .sql
+            .query_row("SELECT recipient, mime FROM smtp_spam_reports", (), |row| {
+                Ok((row.get(0)?, row.get(1)?))
+            })
+            .await?;
+        assert_eq!(recipient, "spam@example.org");
+        assert!(mime.contains("report-type=feedback-report"));
+        assert!(mime.contains("Original-Mail-From: <bob@example.net>"));
+        assert!(mime.contains(&format!("Message-ID: <{}>", received.rfc724_mid)));
+        assert!(!mime.contains("Buy now!"));
+        assert!(!mime.contains("Protected subject"));
+        assert!(mime.contains("Subject: ...\r\n"));
+
+        // Blocking accepted chats is not reported.
+        let fiona = &tcm.fiona().await;
+        let received = tcm.send_recv_accept(fiona, alice, "Hi").await;
+        received.chat_id.block(alice).await?;
+        assert_eq!(
+            alice
+                .sql
+                .count("SELECT COUNT(*) FROM smtp_spam_reports", ())
+                .await?,
+            1
+        );
+        Ok(())
+    }
+}
@@ -14,12 +14,13 @@
 use crate::context::Context;
 use crate::debug_logging::set_debug_logging_xdc;
 use crate::ephemeral::start_ephemeral_timers;
-use crate::imex::BLOBS_BACKUP_NAME;
+use crate::imex::{BackupPassphraseError, BLOBS_BACKUP_NAME};
 use crate::location::delete_orphaned_poi_locations;
 use crate::log::LogExt;
-use crate::message::{Message, MsgId, Viewtype};
+use crate::message::{self, Message, MsgId, Viewtype};
 use crate::param::{Param, Params};
 use crate::peerstate::Peerstate;
+use crate::search;
 use crate::stock_str;
 use crate::tools::{delete_file, time, SystemTime};
 
@@ -126,11 +127,26 @@ pub(crate) async fn is_encrypted(&self) -> Option<bool> {
     }
 
     /// Closes all underlying Sqlite connections.
-    async fn close(&self) {
+    pub(crate) async fn close(&self) {
         let _ = self.pool.write().await.take();
         // drop closes the connection
     }
 
+    /// Copies all pages from the write-ahead log into the database file
+    /// and truncates the log, so that the database file alone is complete.
+    pub(crate) async fn wal_checkpoint(&self) -> Result<()> {
+        self.call_write(|conn| {
+            let busy: i64 =
+                conn.query_row("PRAGMA wal_checkpoint(TRUNCATE)", (), |row| row.get(0))?;
+            ensure!(
+                busy == 0,
+                "WAL checkpoint could not complete, database is busy"
+            );
+            Ok(())
+        })
+        .await
+    }
+
     /// Imports the database from a separate file with the given passphrase.
     pub(crate) async fn import(&self, path: &Path, passphrase: String) -> Result<()> {
         let path_str = path
@@ -143,13 +159,23 @@ pub(crate) async fn import(&self, path: &Path, passphrase: String) -> Result<()>
         let mut config_cache = self.config_cache.write().await;
         config_cache.clear();
 
+        let passphrase_error = match passphrase.is_empty() {
+            true => BackupPassphraseError::Missing,
+            false => BackupPassphraseError::Wrong,
+        };
         self.call_write(move |conn| {
             // Check that backup passphrase is correct before resetting our database.
             conn.execute("ATTACH DATABASE ? AS backup KEY ?", (path_str, passphrase))
                 .context("failed to attach backup database")?;
             let res = conn
                 .query_row("SELECT count(*) FROM sqlite_master", [], |_row| Ok(()))
-                .context("backup passphrase is not correct");
+                .map_err(|err| match err.sqlite_error_code() {
+                    // SQLCipher reports a wrong key as a file which is not a database.
+                    Some(rusqlite::ErrorCode::NotADatabase) => {
+                        anyhow::Error::from(passphrase_error)
+                    }
+                    _ => anyhow::Error::from(err).context("failed to read backup database"),
+                });
 
             // Reset the database without reopening it. We don't want to reopen the database because we
             // don't have main database passphrase at this point.
@@ -276,6 +302,18 @@ pub async fn run_migrations(&self, context: &Context) -> Result<()> {
             }
         }
 
+        search::update_index_state(context).await?;
+        if self
+            .get_raw_config_int(search::FTS_BACKFILL_CFG)
+            .await?
+            .is_some()
+        {
+            let context = context.clone();
+            tokio::spawn(async move {
+                search::backfill(&context).await.log_err(&context).ok();
+            });
+        }
+
         Ok(())
     }
 
@@ -736,6 +774,13 @@ pub async fn housekeeping(context: &Context) -> Result<()> {
         );
     }
 
+    if let Err(err) = message::compress_stored_texts(context).await {
+        warn!(
+            context,
+            "Housekeeping: Cannot compress stored texts: {:#}.", err
+        );
+    }
+
     if let Err(err) = prune_tombstones(&context.sql).await {
         warn!(
             context,
@@ -787,6 +832,76 @@ pub async fn housekeeping(context: &Context) -> Result<()> {
         .log_err(context)
         .ok();
 
+    context
+        .sql
+        .execute(
+            "DELETE FROM msgs_edits WHERE msg_id NOT IN \
+            (SELECT id FROM msgs WHERE chat_id!=?)",
+            (DC_CHAT_ID_TRASH,),
+        )
+        .await
+        .context("failed to remove old message edits")
+        .log_err(context)
+        .ok();
+
+    context
+        .sql
+        .execute(
+            "DELETE FROM pinned_msgs WHERE msg_id NOT IN \
+            (SELECT id FROM msgs WHERE chat_id!=?)",
+            (DC_CHAT_ID_TRASH,),
+        )
+        .await
+        .context("failed to remove old pinned messages")
+        .log_err(context)
+        .ok();
+
+    context
+        .sql
+        .execute(
+            "DELETE FROM msgs_deliveries WHERE msg_id NOT IN \
+            (SELECT id FROM msgs WHERE chat_id!=?)",
+            (DC_CHAT_ID_TRASH,),
+        )
+        .await
+        .context("failed to remove old delivery info")
+        .log_err(context)
+        .ok();
+
+    context
+        .sql
+        .execute(
+            "DELETE FROM msgs_security WHERE msg_id NOT IN \
+            (SELECT id FROM msgs WHERE chat_id!=?)",
+            (DC_CHAT_ID_TRASH,),
+        )
+        .await
+        .context("failed to remove old message security info")
+        .log_err(context)
+        .ok();
+
+    context
+        .sql
+        .execute(
+            "DELETE FROM tokens WHERE expires>0 AND expires<?",
+            (time(),),
+        )
+        .await
+        .context("failed to remove expired tokens")
+        .log_err(context)
+        .ok();
+
+    context
+        .sql
+        .execute(
+            "DELETE FROM securejoin_invites WHERE expires>0 AND expires<?",
+            (time(),),
+        )
+        .await
+        .context("failed to remove expired Secure-Join invites")
+        .log_err(context)
+        .ok();
+
     // Delete POI locations
     // which don't have corresponding message.
     delete_orphaned_poi_locations(context)
@@ -822,6 +937,13 @@ pub async fn remove_unused_files(context: &Context) -> Result<()> {
         Param::File,
     )
     .await?;
+    maybe_add_from_param(
+        &context.sql,
+        &mut files_in_use,
+        "SELECT param FROM msgs  WHERE chat_id!=3   AND type!=10;",
+        Param::PreviewFile,
+    )
+    .await?;
     maybe_add_from_param(
         &context.sql,
         &mut files_in_use,
@@ -837,6 +959,22 @@ pub async fn remove_unused_files(context: &Context) -> Result<()> {
     )
     .await?;
 
+    context
+        .sql
+        .query_map(
+            "SELECT file FROM stickers;",
+            (),
+            |row| row.get::<_, String>(0),
+            |rows| {
+                for row in rows {
+                    maybe_add_file(&mut files_in_use, &row?);
+                }
+                Ok(())
+            },
+        )
+        .await
+        .context("housekeeping: failed to SELECT file FROM stickers")?;
+
     context
         .sql
         .query_map(
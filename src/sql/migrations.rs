@@ -8,8 +8,9 @@
 use crate::constants::ShowEmails;
 use crate::context::Context;
 use crate::imap;
-use crate::message::MsgId;
+use crate::message::{MessageState, MsgId};
 use crate::provider::get_provider_by_domain;
+use crate::search;
 use crate::sql::Sql;
 use crate::tools::inc_and_check;
 
@@ -368,10 +369,11 @@ pub async fn run(context: &Context, sql: &Sql) -> Result<(bool, bool, bool, bool
     if dbversion < 71 {
         if let Ok(addr) = context.get_primary_self_addr().await {
             if let Ok(domain) = EmailAddress::new(&addr).map(|email| email.domain) {
+                let provider = get_provider_by_domain(context, &domain).await;
                 context
                     .set_config_internal(
                         Config::ConfiguredProvider,
-                        get_provider_by_domain(&domain).map(|provider| provider.id),
+                        provider.map(|provider| provider.id),
                     )
                     .await?;
             } else {
@@ -955,6 +957,323 @@ pub async fn run(context: &Context, sql: &Sql) -> Result<(bool, bool, bool, bool
         .await?;
     }
 
+    inc_and_check(&mut migration_version, 117)?;
+    if dbversion < migration_version {
+        // OBJECTID `EMAILID` of the message as defined in RFC 8474,
+        // stable across folder moves. Empty if the server does not support OBJECTID.
+        sql.execute_migration(
+            "ALTER TABLE imap ADD COLUMN emailid TEXT NOT NULL DEFAULT '';
+            CREATE INDEX imap_emailid ON imap(emailid);",
+            migration_version,
+        )
+        .await?;
+    }
+
+    inc_and_check(&mut migration_version, 118)?;
+    if dbversion < migration_version {
+        // Observed changes of the contacts' keys.
+        // `rfc724_mid` is the Message-ID of the message that caused the change.
+        sql.execute_migration(
+            "CREATE TABLE key_history (
+               id INTEGER PRIMARY KEY AUTOINCREMENT,
+               addr TEXT NOT NULL COLLATE NOCASE,
+               old_fingerprint TEXT NOT NULL,
+               new_fingerprint TEXT NOT NULL,
+               timestamp INTEGER NOT NULL,
+               rfc724_mid TEXT NOT NULL DEFAULT ''
+            );
+            CREATE INDEX key_history_addr ON key_history(addr);",
+            migration_version,
+        )
+        .await?;
+    }
+
+    inc_and_check(&mut migration_version, 119)?;
+    if dbversion < migration_version {
+        // Votes in polls, `options` are the comma-separated indices of the selected options.
+        sql.execute_migration(
+            "CREATE TABLE poll_votes (
+               msg_id INTEGER NOT NULL,
+               contact_id INTEGER NOT NULL,
+               options TEXT NOT NULL,
+               PRIMARY KEY(msg_id, contact_id)
+            );",
+            migration_version,
+        )
+        .await?;
+    }
+
+    inc_and_check(&mut migration_version, 120)?;
+    if dbversion < migration_version {
+        // Message-ID of the thread root for messages replying in a topic thread.
+        sql.execute_migration(
+            "ALTER TABLE msgs ADD COLUMN thread_root TEXT DEFAULT '';
+             CREATE INDEX msgs_index9 ON msgs (thread_root);",
+            migration_version,
+        )
+        .await?;
+    }
+
+    inc_and_check(&mut migration_version, 121)?;
+    if dbversion < migration_version {
+        // Reusable contact sets, `uid` identifies the list across own devices.
+        sql.execute_migration(
+            "CREATE TABLE contact_lists (
+               id INTEGER PRIMARY KEY AUTOINCREMENT,
+               uid TEXT NOT NULL UNIQUE,
+               name TEXT NOT NULL
+             );
+             CREATE TABLE contact_lists_contacts (
+               list_id INTEGER NOT NULL,
+               contact_id INTEGER NOT NULL,
+               PRIMARY KEY(list_id, contact_id)
+             );",
+            migration_version,
+        )
+        .await?;
+    }
+
+    inc_and_check(&mut migration_version, 122)?;
+    if dbversion < migration_version {
+        // Cryptographic details of encrypted or signed messages.
+        sql.execute_migration(
+            "CREATE TABLE msgs_security (
+               msg_id INTEGER PRIMARY KEY,
+               signing_fingerprint TEXT NOT NULL DEFAULT '',
+               verified INTEGER NOT NULL DEFAULT 0,
+               pq_hybrid INTEGER NOT NULL DEFAULT 0,
+               gossiped INTEGER NOT NULL DEFAULT 0
+             );",
+            migration_version,
+        )
+        .await?;
+    }
+
+    inc_and_check(&mut migration_version, 123)?;
+    if dbversion < migration_version {
+        // Expiry timestamp of tokens, 0 means that the token never expires.
+        sql.execute_migration(
+            "ALTER TABLE tokens ADD COLUMN expires INTEGER NOT NULL DEFAULT 0;",
+            migration_version,
+        )
+        .await?;
+    }
+
+    inc_and_check(&mut migration_version, 124)?;
+    if dbversion < migration_version {
+        // Issued Secure-Join invites, kept after withdrawal so that they can be re-issued
+        // and joiners scanning a withdrawn invite can be told about it.
+        sql.execute_migration(
+            "CREATE TABLE securejoin_invites (
+              id INTEGER PRIMARY KEY AUTOINCREMENT,
+              chat_id INTEGER NOT NULL DEFAULT 0,
+              invitenumber TEXT NOT NULL UNIQUE,
+              auth TEXT NOT NULL,
+              timestamp INTEGER NOT NULL DEFAULT 0,
+              expires INTEGER NOT NULL DEFAULT 0,
+              withdrawn INTEGER NOT NULL DEFAULT 0
+            );",
+            migration_version,
+        )
+        .await?;
+    }
+
+    inc_and_check(&mut migration_version, 125)?;
+    if dbversion < migration_version {
+        // Abuse reports about blocked contact requests, waiting to be sent.
+        sql.execute_migration(
+            "CREATE TABLE smtp_spam_reports (
+              id INTEGER PRIMARY KEY AUTOINCREMENT,
+              recipient TEXT NOT NULL,
+              mime TEXT NOT NULL
+            );",
+            migration_version,
+        )
+        .await?;
+    }
+
+    inc_and_check(&mut migration_version, 126)?;
+    if dbversion < migration_version {
+        // Large raw texts are stored compressed, see `message::compress_stored_texts()`.
+        sql.execute_migration(
+            "ALTER TABLE msgs ADD COLUMN txt_raw_compressed INTEGER NOT NULL DEFAULT 0;",
+            migration_version,
+        )
+        .await?;
+    }
+
+    inc_and_check(&mut migration_version, 127)?;
+    if dbversion < migration_version {
+        // Messages sent without waiting for preparation, to be prepared by the SMTP loop.
+        sql.execute_migration(
+            "CREATE TABLE smtp_prepare (
+              id INTEGER PRIMARY KEY AUTOINCREMENT,
+              msg_id INTEGER NOT NULL
+            );",
+            migration_version,
+        )
+        .await?;
+    }
+
+    inc_and_check(&mut migration_version, 128)?;
+    if dbversion < migration_version {
+        // Used to create a per-account cache of Mozilla ISPDB configurations,
+        // the cache is now shared by all accounts of the process.
+        sql.set_db_version(migration_version).await?;
+    }
+
+    inc_and_check(&mut migration_version, 129)?;
+    if dbversion < migration_version {
+        // Previous texts of edited messages.
+        sql.execute_migration(
+            "CREATE TABLE msgs_edits (
+              id INTEGER PRIMARY KEY AUTOINCREMENT,
+              msg_id INTEGER NOT NULL,
+              text TEXT NOT NULL,
+              timestamp INTEGER NOT NULL
+            ) STRICT;
+            CREATE INDEX msgs_edits_index1 ON msgs_edits (msg_id);",
+            migration_version,
+        )
+        .await?;
+    }
+
+    inc_and_check(&mut migration_version, 130)?;
+    if dbversion < migration_version {
+        // ID of the message quoted by the message, 0 if there is no quote
+        // or the quoted message is not received yet.
+        sql.execute_migration(
+            "ALTER TABLE msgs ADD COLUMN quoted_msg_id INTEGER NOT NULL DEFAULT 0;
+             CREATE INDEX msgs_index10 ON msgs (quoted_msg_id);
+             CREATE INDEX msgs_index11 ON msgs (mime_in_reply_to) WHERE quoted_msg_id=0;",
+            migration_version,
+        )
+        .await?;
+    }
+
+    inc_and_check(&mut migration_version, 131)?;
+    if dbversion < migration_version {
+        // Whether a received message mentions us, notified even in muted chats.
+        sql.execute_migration(
+            "ALTER TABLE msgs ADD COLUMN mentions_self INTEGER NOT NULL DEFAULT 0;",
+            migration_version,
+        )
+        .await?;
+    }
+
+    inc_and_check(&mut migration_version, 132)?;
+    if dbversion < migration_version {
+        // Messages pinned in chats.
+        sql.execute_migration(
+            "CREATE TABLE pinned_msgs (
+              msg_id INTEGER PRIMARY KEY,
+              chat_id INTEGER NOT NULL,
+              timestamp INTEGER NOT NULL
+            ) STRICT;
+            CREATE INDEX pinned_msgs_index1 ON pinned_msgs (chat_id);",
+            migration_version,
+        )
+        .await?;
+    }
+
+    inc_and_check(&mut migration_version, 133)?;
+    if dbversion < migration_version {
+        // User-defined chat labels, `uid` identifies the label across own devices.
+        sql.execute_migration(
+            "CREATE TABLE chat_labels (
+               id INTEGER PRIMARY KEY AUTOINCREMENT,
+               uid TEXT NOT NULL UNIQUE,
+               name TEXT NOT NULL
+             ) STRICT;
+             CREATE TABLE chats_labels (
+               chat_id INTEGER NOT NULL,
+               label_id INTEGER NOT NULL,
+               PRIMARY KEY(chat_id, label_id)
+             ) STRICT;
+             CREATE INDEX chats_labels_index1 ON chats_labels (label_id);",
+            migration_version,
+        )
+        .await?;
+    }
+
+    inc_and_check(&mut migration_version, 134)?;
+    if dbversion < migration_version {
+        // Full-text search index, see `search` module.
+        // Existing messages are indexed in the background starting from the highest ID stored
+        // in `FTS_BACKFILL_CFG`.
+        // Without FTS5, the index is created by `search::update_index_state()` later
+        // if FTS5 becomes available.
+        if search::is_fts5_available(sql).await {
+            sql.execute_migration(&search::create_index_sql(), migration_version)
+                .await?;
+        } else {
+            sql.set_db_version(migration_version).await?;
+        }
+    }
+
+    inc_and_check(&mut migration_version, 135)?;
+    if dbversion < migration_version {
+        // Last read message of the chat, see `chat::get_first_unread()`.
+        // Initialized with the newest seen message.
+        sql.execute_migration(
+            &format!(
+                "ALTER TABLE chats ADD COLUMN last_read_msg_id INTEGER NOT NULL DEFAULT 0;
+                 UPDATE chats SET last_read_msg_id=IFNULL(
+                   (SELECT id FROM msgs WHERE chat_id=chats.id AND state={}
+                    ORDER BY timestamp DESC, id DESC LIMIT 1), 0);",
+                MessageState::InSeen as u32
+            ),
+            migration_version,
+        )
+        .await?;
+    }
+
+    inc_and_check(&mut migration_version, 136)?;
+    if dbversion < migration_version {
+        // Group members the message was delivered to over SMTP, see `message::get_read_receipts()`.
+        sql.execute_migration(
+            "CREATE TABLE msgs_deliveries (
+               msg_id INTEGER NOT NULL,
+               contact_id INTEGER NOT NULL,
+               timestamp INTEGER NOT NULL,
+               PRIMARY KEY(msg_id, contact_id)
+             ) STRICT;",
+            migration_version,
+        )
+        .await?;
+    }
+
+    inc_and_check(&mut migration_version, 137)?;
+    if dbversion < migration_version {
+        // Local sticker packs, see `sticker::get_packs()`.
+        sql.execute_migration(
+            "CREATE TABLE sticker_packs (
+               id INTEGER PRIMARY KEY AUTOINCREMENT,
+               name TEXT NOT NULL,
+               timestamp INTEGER NOT NULL
+             ) STRICT;
+             CREATE TABLE stickers (
+               id INTEGER PRIMARY KEY AUTOINCREMENT,
+               pack_id INTEGER NOT NULL,
+               file TEXT NOT NULL
+             ) STRICT;
+             CREATE INDEX stickers_index1 ON stickers (pack_id);",
+            migration_version,
+        )
+        .await?;
+    }
+
+    inc_and_check(&mut migration_version, 138)?;
+    if dbversion < migration_version {
+        // OBJECTID `THREADID` of the message as defined in RFC 8474,
+        // shared by all messages of a thread. Empty if unknown.
+        sql.execute_migration(
+            "ALTER TABLE imap ADD COLUMN threadid TEXT NOT NULL DEFAULT '';",
+            migration_version,
+        )
+        .await?;
+    }
+
     let new_version = sql
         .get_raw_config_int(VERSION_CFG)
         .await?
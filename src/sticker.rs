@@ -0,0 +1,361 @@
+//! # Sticker packs.
+//!
+//! Stickers are sent as messages of [`Viewtype::Sticker`].
+//! To offer a sticker picker, UIs can keep a local store of sticker packs,
+//! imported from a folder with [`import_pack_from_dir`]
+//! or from a received ZIP archive of stickers with [`import_pack_from_msg`].
+//! Stickers from the store are sent as is, without recoding, using [`send_sticker`].
+
+use std::path::{Path, PathBuf};
+
+use anyhow::{ensure, Context as _, Result};
+use tokio::io::AsyncReadExt;
+
+use crate::blob::BlobObject;
+use crate::chat::{self, ChatId};
+use crate::context::Context;
+use crate::message::{Message, MsgId, Viewtype};
+use crate::param::Param;
+use crate::tools::{get_abs_path, get_filesuffix_lc, time};
+
+/// Maximum number of stickers in a pack.
+const STICKERS_PER_PACK_MAX: usize = 200;
+
+/// Maximum size of a single sticker in bytes.
+const STICKER_SIZE_MAX: u64 = 1 << 20;
+
+/// Sticker pack in the local store.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct StickerPack {
+    /// ID of the pack.
+    pub id: u32,
+
+    /// Name of the pack, derived from the imported folder or archive name.
+    pub name: String,
+
+    /// Timestamp of the import.
+    pub timestamp: i64,
+
+    /// Number of stickers in the pack.
+    pub count: usize,
+}
+
+/// Sticker in the local store.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Sticker {
+    /// ID of the sticker.
+    pub id: u32,
+
+    /// ID of the pack containing the sticker.
+    pub pack_id: u32,
+
+    /// Blob file of the sticker.
+    file: String,
+}
+
+impl Sticker {
+    /// Returns the path to the image file of the sticker.
+    pub fn get_file(&self, context: &Context) -> PathBuf {
+        get_abs_path(context, Path::new(&self.file))
+    }
+}
+
+/// Returns true if `name` has the file extension of a sticker image.
+fn is_sticker_file(name: &str) -> bool {
+    matches!(
+        get_filesuffix_lc(name).as_deref(),
+        Some("png" | "webp" | "gif")
+    )
+}
+
+/// Imports all PNG, WebP and GIF images from the folder `dir` as a new sticker pack.
+///
+/// The pack is named after the folder. Returns the ID of the new pack.
+pub async fn import_pack_from_dir(context: &Context, dir: &Path) -> Result<u32> {
+    let name = dir
+        .file_name()
+        .and_then(|name| name.to_str())
+        .context("Invalid sticker pack folder name")?;
+    let mut dir_handle = tokio::fs::read_dir(dir)
+        .await
+        .with_context(|| format!("Failed to read sticker pack folder {}", dir.display()))?;
+    let mut paths = Vec::new();
+    while let Some(entry) = dir_handle.next_entry().await? {
+        let path = entry.path();
+        if path.to_str().is_some_and(is_sticker_file) && entry.file_type().await?.is_file() {
+            paths.push(path);
+        }
+    }
+    paths.sort();
+
+    let mut stickers = Vec::new();
+    for path in paths.into_iter().take(STICKERS_PER_PACK_MAX) {
+        if tokio::fs::metadata(&path).await?.len() > STICKER_SIZE_MAX {
+            warn!(context, "Sticker {} is too large.", path.display());
+            continue;
+        }
+        let file_name = path.file_name().and_then(|name| name.to_str());
+        let data = tokio::fs::read(&path).await?;
+        stickers.push((file_name.unwrap_or("sticker").to_string(), data));
+    }
+    create_pack(context, name, stickers).await
+}
+
+/// Imports the stickers of a received ZIP archive as a new sticker pack.
+///
+/// The archive must contain PNG, WebP or GIF images,
+/// other files are ignored. The pack is named after the archive.
+/// Returns the ID of the new pack.
+pub async fn import_pack_from_msg(context: &Context, msg_id: MsgId) -> Result<u32> {
+    let msg = Message::load_from_db(context, msg_id).await?;
+    let path = msg.get_file(context).context("Message has no attachment")?;
+    let filename = msg.get_filename().unwrap_or_default();
+    ensure!(
+        get_filesuffix_lc(&filename).as_deref() == Some("zip"),
+        "Sticker pack must be a ZIP archive"
+    );
+    let name = filename.strip_suffix(".zip").unwrap_or(&filename);
+
+    let archive = async_zip::read::fs::ZipFileReader::new(path)
+        .await
+        .context("Failed to read sticker pack archive")?;
+    let mut stickers = Vec::new();
+    for (i, ent) in archive.file().entries().iter().enumerate() {
+        let entry = ent.entry();
+        let Some(file_name) = Path::new(entry.filename())
+            .file_name()
+            .and_then(|name| name.to_str())
+        else {
+            continue;
+        };
+        if entry.dir() || !is_sticker_file(file_name) {
+            continue;
+        }
+        if entry.uncompressed_size() > STICKER_SIZE_MAX {
+            warn!(context, "Sticker {file_name} is too large.");
+            continue;
+        }
+        // The uncompressed size in the header is not trusted, read at most one byte too many.
+        let reader = archive.entry(i).await?;
+        let mut data = Vec::new();
+        reader
+            .take(STICKER_SIZE_MAX + 1)
+            .read_to_end(&mut data)
+            .await?;
+        if data.len() as u64 > STICKER_SIZE_MAX {
+            warn!(context, "Sticker {file_name} is too large.");
+            continue;
+        }
+        stickers.push((file_name.to_string(), data));
+        if stickers.len() == STICKERS_PER_PACK_MAX {
+            break;
+        }
+    }
+    create_pack(context, name, stickers).await
+}
+
+/// Stores the sticker images `stickers` as a new pack.
+async fn create_pack(
+    context: &Context,
+    name: &str,
+    stickers: Vec<(String, Vec<u8>)>,
+) -> Result<u32> {
+    ensure!(
+        !stickers.is_empty(),
+        "Sticker pack {name:?} contains no stickers"
+    );
+    let mut files = Vec::with_capacity(stickers.len());
+    for (file_name, data) in stickers {
+        let blob = BlobObject::create(context, &file_name, &data).await?;
+        files.push(blob.as_name().to_string());
+    }
+
+    let name = name.to_string();
+    let pack_id = context
+        .sql
+        .transaction(move |transaction| {
+            transaction.execute(
+                "INSERT INTO sticker_packs (name, timestamp) VALUES (?, ?)",
+                (name, time()),
+            )?;
+            let pack_id = transaction.last_insert_rowid();
+            for file in files {
+                transaction.execute(
+                    "INSERT INTO stickers (pack_id, file) VALUES (?, ?)",
+                    (pack_id, file),
+                )?;
+            }
+            Ok(pack_id)
+        })
+        .await?;
+    Ok(u32::try_from(pack_id)?)
+}
+
+/// Returns all sticker packs, the most recently imported first.
+pub async fn get_packs(context: &Context) -> Result<Vec<StickerPack>> {
+    context
+        .sql
+        .query_map(
+            "SELECT p.id, p.name, p.timestamp, COUNT(s.id)
+             FROM sticker_packs p LEFT JOIN stickers s ON s.pack_id=p.id
+             GROUP BY p.id
+             ORDER BY p.timestamp DESC, p.id DESC",
+            (),
+            |row| {
+                Ok(StickerPack {
+                    id: row.get(0)?,
+                    name: row.get(1)?,
+                    timestamp: row.get(2)?,
+                    count: row.get(3)?,
+                })
+            },
+            |rows| {
+                rows.collect::<std::result::Result<Vec<_>, _>>()
+                    .map_err(Into::into)
+            },
+        )
+        .await
+}
+
+/// Returns the stickers of the pack in import order.
+pub async fn get_stickers(context: &Context, pack_id: u32) -> Result<Vec<Sticker>> {
+    context
+        .sql
+        .query_map(
+            "SELECT id, pack_id, file FROM stickers WHERE pack_id=? ORDER BY id",
+            (pack_id,),
+            |row| {
+                Ok(Sticker {
+                    id: row.get(0)?,
+                    pack_id: row.get(1)?,
+                    file: row.get(2)?,
+                })
+            },
+            |rows| {
+                rows.collect::<std::result::Result<Vec<_>, _>>()
+                    .map_err(Into::into)
+            },
+        )
+        .await
+}
+
+/// Deletes the sticker pack from the local store.
+///
+/// Sticker files are removed by the housekeeping
+/// unless they are still used by messages.
+pub async fn delete_pack(context: &Context, pack_id: u32) -> Result<()> {
+    context
+        .sql
+        .transaction(move |transaction| {
+            transaction.execute("DELETE FROM stickers WHERE pack_id=?", (pack_id,))?;
+            transaction.execute("DELETE FROM sticker_packs WHERE id=?", (pack_id,))?;
+            Ok(())
+        })
+        .await
+}
+
+/// Sends a sticker from the local store to the chat.
+pub async fn send_sticker(context: &Context, chat_id: ChatId, sticker_id: u32) -> Result<MsgId> {
+    let file: String = context
+        .sql
+        .query_get_value("SELECT file FROM stickers WHERE id=?", (sticker_id,))
+        .await?
+        .with_context(|| format!("Sticker {sticker_id} not found"))?;
+    let mut msg = Message::new(Viewtype::Sticker);
+    msg.param.set(Param::File, file);
+    // Stickers from packs are sent as is.
+    msg.force_sticker();
+    chat::send_msg(context, chat_id, &mut msg).await
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_utils::TestContextManager;
+
+    #[tokio::test(flavor = "multi_thread", worker_threads = 2)]
+    async fn test_import_pack_from_dir() -> Result<()> {
+        let mut tcm = TestContextManager::new();
+        let alice = &tcm.alice().await;
+        let bob = &tcm.bob().await;
+
+        let dir = tempfile::tempdir()?;
+        let pack_dir = dir.path().join("Cats");
+        tokio::fs::create_dir(&pack_dir).await?;
+        let logo = include_bytes!("../test-data/image/logo.png");
+        tokio::fs::write(pack_dir.join("2.png"), logo).await?;
+        tokio::fs::write(
+            pack_dir.join("1.gif"),
+            include_bytes!("../test-data/image/logo.gif"),
+        )
+        .await?;
+        tokio::fs::write(pack_dir.join("readme.txt"), b"meow").await?;
+
+        let pack_id = import_pack_from_dir(alice, &pack_dir).await?;
+        let packs = get_packs(alice).await?;
+        assert_eq!(packs.len(), 1);
+        assert_eq!(packs[0].id, pack_id);
+        assert_eq!(packs[0].name, "Cats");
+        assert_eq!(packs[0].count, 2);
+        let stickers = get_stickers(alice, pack_id).await?;
+        assert_eq!(stickers.len(), 2);
+        assert!(stickers[0]
+            .get_file(alice)
+            .to_str()
+            .unwrap()
+            .ends_with(".gif"));
+        assert_eq!(tokio::fs::read(stickers[1].get_file(alice)).await?, logo);
+
+        // Sticker files are not removed by the housekeeping.
+        crate::sql::remove_unused_files(alice).await?;
+        assert!(stickers[1].get_file(alice).exists());
+
+        let chat_id = alice.create_chat(bob).await.id;
+        send_sticker(alice, chat_id, stickers[1].id).await?;
+        let msg = bob.recv_msg(&alice.pop_sent_msg().await).await;
+        assert_eq!(msg.get_viewtype(), Viewtype::Sticker);
+        assert_eq!(tokio::fs::read(msg.get_file(bob).unwrap()).await?, logo);
+
+        let empty_dir = dir.path().join("Empty");
+        tokio::fs::create_dir(&empty_dir).await?;
+        assert!(import_pack_from_dir(alice, &empty_dir).await.is_err());
+
+        delete_pack(alice, pack_id).await?;
+        assert!(get_packs(alice).await?.is_empty());
+        assert!(get_stickers(alice, pack_id).await?.is_empty());
+        assert!(send_sticker(alice, chat_id, stickers[1].id).await.is_err());
+        Ok(())
+    }
+
+    #[tokio::test(flavor = "multi_thread", worker_threads = 2)]
+    async fn test_import_pack_from_msg() -> Result<()> {
+        let mut tcm = TestContextManager::new();
+        let alice = &tcm.alice().await;
+        let bob = &tcm.bob().await;
+
+        // The archive contains a PNG image and an HTML file.
+        let file = alice.get_blobdir().join("Icons.zip");
+        tokio::fs::write(
+            &file,
+            include_bytes!("../test-data/webxdc/with-png-icon.xdc"),
+        )
+        .await?;
+        let mut msg = Message::new(Viewtype::File);
+        msg.set_file(file.to_str().unwrap(), None);
+        let chat_id = alice.create_chat(bob).await.id;
+        alice.send_msg(chat_id, &mut msg).await;
+        let msg = bob.recv_msg(&alice.pop_sent_msg().await).await;
+
+        let pack_id = import_pack_from_msg(bob, msg.id).await?;
+        let packs = get_packs(bob).await?;
+        assert_eq!(packs[0].name, "Icons");
+        assert_eq!(packs[0].count, 1);
+        let stickers = get_stickers(bob, pack_id).await?;
+        assert!(stickers[0].get_file(bob).exists());
+
+        // Other attachments can't be imported.
+        let msg = tcm.send_recv(alice, bob, "Hello!").await;
+        assert!(import_pack_from_msg(bob, msg.id).await.is_err());
+        Ok(())
+    }
+}
@@ -17,6 +17,7 @@
 use crate::context::Context;
 use crate::message::{Message, Viewtype};
 use crate::param::Param;
+use crate::securejoin::SecurejoinFailure;
 use crate::tools::timestamp_to_str;
 
 /// Storage for string translations.
@@ -437,6 +438,63 @@ pub enum StockMessage {
         fallback = "Could not yet establish guaranteed end-to-end encryption, but you may already send a message."
     ))]
     SecurejoinWaitTimeout = 191,
+
+    #[strum(props(fallback = "⚠️ Unauthenticated messages from %1$s"))]
+    SpoofingQuarantine = 192,
+
+    #[strum(props(
+        fallback = "⚠️ This message claims to be from %1$s, but it is not signed with their key and may be forged."
+    ))]
+    SpoofedMsg = 193,
+
+    #[strum(props(fallback = "The invitation of %1$s was withdrawn, ask them for a new one."))]
+    SecurejoinInviteWithdrawn = 194,
+
+    #[strum(props(
+        fallback = "⚠️ Your device is running out of storage, only %1$s left. Downloading attachments is paused until you free up some space."
+    ))]
+    LowDiskSpace = 195,
+
+    #[strum(props(
+        fallback = "⚠️ Messages from %1$s are sent via the servers of %2$s, which usually cannot sign them for this domain. Recipients' providers may reject them or sort them into spam. If your messages do not arrive, ask your email provider how to send from this address."
+    ))]
+    SendingDomainNotAligned = 196,
+
+    #[strum(props(fallback = "🚮 This message was deleted."))]
+    MsgRetracted = 197,
+
+    #[strum(props(fallback = "You pinned a message."))]
+    MsgYouPinnedMsg = 198,
+
+    #[strum(props(fallback = "Message pinned by %1$s."))]
+    MsgPinnedBy = 199,
+
+    #[strum(props(fallback = "You unpinned a message."))]
+    MsgYouUnpinnedMsg = 201,
+
+    #[strum(props(fallback = "Message unpinned by %1$s."))]
+    MsgUnpinnedBy = 202,
+
+    #[strum(props(fallback = "An unexpected error occurred."))]
+    SecurejoinFailureOther = 203,
+
+    #[strum(props(fallback = "The other side did not reply in time."))]
+    SecurejoinFailureTimeout = 204,
+
+    #[strum(props(fallback = "The key of the other side does not match the QR code."))]
+    SecurejoinFailureFingerprintMismatch = 205,
+
+    #[strum(props(fallback = "The invitation was withdrawn."))]
+    SecurejoinFailureInviteWithdrawn = 206,
+
+    #[strum(props(fallback = "A message of the other side was not encrypted and signed."))]
+    SecurejoinFailureNotEncrypted = 207,
+
+    #[strum(props(fallback = "The invitation is not valid."))]
+    SecurejoinFailureAuthInvalid = 208,
+
+    #[strum(props(fallback = "Another QR code was scanned."))]
+    SecurejoinFailureAborted = 209,
 }
 
 impl StockMessage {
@@ -837,6 +895,73 @@ pub(crate) async fn securejoin_wait_timeout(context: &Context) -> String {
     translated(context, StockMessage::SecurejoinWaitTimeout).await
 }
 
+/// Stock string: `⚠️ Unauthenticated messages from %1$s`.
+pub(crate) async fn spoofing_quarantine(context: &Context, addr: &str) -> String {
+    translated(context, StockMessage::SpoofingQuarantine)
+        .await
+        .replace1(addr)
+}
+
+/// Stock string: `⚠️ This message claims to be from %1$s, but it is not signed with their key and may be forged.`.
+pub(crate) async fn spoofed_msg(context: &Context, addr: &str) -> String {
+    translated(context, StockMessage::SpoofedMsg)
+        .await
+        .replace1(addr)
+}
+
+/// Stock string: `The invitation of %1$s was withdrawn, ask them for a new one.`.
+pub(crate) async fn securejoin_invite_withdrawn(context: &Context, contact: &Contact) -> String {
+    translated(context, StockMessage::SecurejoinInviteWithdrawn)
+        .await
+        .replace1(&contact.get_name_n_addr())
+}
+
+/// Stock string: `⚠️ Your device is running out of storage...`.
+pub(crate) async fn low_disk_space(context: &Context, available: u64) -> String {
+    translated(context, StockMessage::LowDiskSpace)
+        .await
+        .replace1(&format_size(available, BINARY))
+}
+
+/// Stock string: `⚠️ Messages from %1$s are sent via the servers of %2$s...`.
+pub(crate) async fn sending_domain_not_aligned(
+    context: &Context,
+    addr: &str,
+    provider: &str,
+) -> String {
+    translated(context, StockMessage::SendingDomainNotAligned)
+        .await
+        .replace1(addr)
+        .replace2(provider)
+}
+
+/// Stock string: `🚮 This message was deleted.`.
+pub(crate) async fn msg_retracted(context: &Context) -> String {
+    translated(context, StockMessage::MsgRetracted).await
+}
+
+/// Stock string: `You pinned a message.` or `Message pinned by %1$s.`.
+pub(crate) async fn msg_pinned(context: &Context, by_contact: ContactId) -> String {
+    if by_contact == ContactId::SELF {
+        translated(context, StockMessage::MsgYouPinnedMsg).await
+    } else {
+        translated(context, StockMessage::MsgPinnedBy)
+            .await
+            .replace1(&by_contact.get_stock_name_n_addr(context).await)
+    }
+}
+
+/// Stock string: `You unpinned a message.` or `Message unpinned by %1$s.`.
+pub(crate) async fn msg_unpinned(context: &Context, by_contact: ContactId) -> String {
+    if by_contact == ContactId::SELF {
+        translated(context, StockMessage::MsgYouUnpinnedMsg).await
+    } else {
+        translated(context, StockMessage::MsgUnpinnedBy)
+            .await
+            .replace1(&by_contact.get_stock_name_n_addr(context).await)
+    }
+}
+
 /// Stock string: `Scan to chat with %1$s`.
 pub(crate) async fn setup_contact_qr_description(
     context: &Context,
@@ -869,6 +994,23 @@ pub(crate) async fn contact_verified(context: &Context, contact: &Contact) -> St
         .replace1(addr)
 }
 
+/// Stock string describing the reason of a failed Secure-Join handshake,
+/// e.g. `The invitation was withdrawn.`.
+pub(crate) async fn securejoin_failure(context: &Context, reason: SecurejoinFailure) -> String {
+    let stock_message = match reason {
+        SecurejoinFailure::Other => StockMessage::SecurejoinFailureOther,
+        SecurejoinFailure::Timeout => StockMessage::SecurejoinFailureTimeout,
+        SecurejoinFailure::FingerprintMismatch => {
+            StockMessage::SecurejoinFailureFingerprintMismatch
+        }
+        SecurejoinFailure::InviteWithdrawn => StockMessage::SecurejoinFailureInviteWithdrawn,
+        SecurejoinFailure::NotEncrypted => StockMessage::SecurejoinFailureNotEncrypted,
+        SecurejoinFailure::AuthInvalid => StockMessage::SecurejoinFailureAuthInvalid,
+        SecurejoinFailure::Aborted => StockMessage::SecurejoinFailureAborted,
+    };
+    translated(context, stock_message).await
+}
+
 /// Stock string: `Cannot establish guaranteed end-to-end encryption with %1$s`.
 pub(crate) async fn contact_not_verified(context: &Context, contact: &Contact) -> String {
     let addr = &contact.get_name_n_addr();
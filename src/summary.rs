@@ -15,9 +15,10 @@
 use crate::stock_str::msg_reacted;
 use crate::tools::truncate;
 use anyhow::Result;
+use serde::{Deserialize, Serialize};
 
 /// Prefix displayed before message and separated by ":" in the chatlist.
-#[derive(Debug)]
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
 pub enum SummaryPrefix {
     /// Username.
     Username(String),
@@ -40,7 +41,7 @@ fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
 }
 
 /// Message summary.
-#[derive(Debug, Default)]
+#[derive(Debug, Default, Clone, PartialEq, Eq, Serialize, Deserialize)]
 pub struct Summary {
     /// Part displayed before ":", such as an username or a string "Draft".
     pub prefix: Option<SummaryPrefix>,
@@ -164,6 +165,9 @@ pub(crate) async fn get_summary_text(&self, context: &Context) -> String {
 
     /// Returns a summary text without "Forwarded:" prefix.
     async fn get_summary_text_without_prefix(&self, context: &Context) -> String {
+        if self.is_retracted() {
+            return stock_str::msg_retracted(context).await;
+        }
         let (emoji, type_name, type_file, append_text);
         match self.viewtype {
             Viewtype::Image => {
@@ -440,4 +444,28 @@ async fn test_get_summary_text() {
         msg.param.set_cmd(SystemMessage::AutocryptSetupMessage);
         assert_summary_texts(&msg, ctx, "Autocrypt Setup Message").await; // file name is not added for autocrypt setup messages
     }
+
+    #[test]
+    fn test_summary_json_schema() -> Result<()> {
+        let summary = Summary {
+            prefix: Some(SummaryPrefix::Me("Me".to_string())),
+            text: "Hello".to_string(),
+            timestamp: 1700000000,
+            state: MessageState::OutDelivered,
+            thumbnail_path: None,
+        };
+        let value = serde_json::to_value(&summary)?;
+        assert_eq!(
+            value,
+            serde_json::json!({
+                "prefix": {"Me": "Me"},
+                "text": "Hello",
+                "timestamp": 1700000000,
+                "state": "OutDelivered",
+                "thumbnail_path": null,
+            })
+        );
+        assert_eq!(serde_json::from_value::<Summary>(value)?, summary);
+        Ok(())
+    }
 }
@@ -1,6 +1,6 @@
 //! # Synchronize items between devices.
 
-use anyhow::Result;
+use anyhow::{ensure, Result};
 use lettre_email::PartBuilder;
 use serde::{Deserialize, Serialize};
 
@@ -16,7 +16,7 @@
 use crate::sync::SyncData::{AddQrToken, AlterChat, DeleteQrToken};
 use crate::token::Namespace;
 use crate::tools::time;
-use crate::{stock_str, token};
+use crate::{securejoin, stock_str, token};
 
 /// Whether to send device sync messages. Aimed for usage in the internal API.
 #[derive(Debug, PartialEq)]
@@ -54,6 +54,14 @@ pub(crate) struct QrTokenData {
 pub(crate) enum SyncData {
     AddQrToken(QrTokenData),
     DeleteQrToken(QrTokenData),
+    /// Token of an invite link which is valid until the `expires` timestamp.
+    ///
+    /// A separate item so that devices not knowing about expiry ignore it
+    /// instead of saving a token that never expires.
+    AddExpiringQrToken {
+        token: QrTokenData,
+        expires: i64,
+    },
     AlterChat {
         id: chat::SyncId,
         action: chat::SyncAction,
@@ -62,6 +70,26 @@ pub(crate) enum SyncData {
         key: Config,
         val: String,
     },
+    UiConfig {
+        key: String,
+        /// `None` removes the value.
+        val: Option<String>,
+    },
+    ContactList {
+        uid: String,
+        name: String,
+        addrs: Vec<String>,
+    },
+    DeleteContactList {
+        uid: String,
+    },
+    ChatLabel {
+        uid: String,
+        name: String,
+    },
+    DeleteChatLabel {
+        uid: String,
+    },
 }
 
 #[derive(Debug, Serialize, Deserialize)]
@@ -153,6 +181,46 @@ pub(crate) async fn sync_qr_code_tokens(&self, chat_id: Option<ChatId>) -> Resul
         Ok(())
     }
 
+    /// Adds expiring qr-code tokens, e.g. of an invite link, to the list of items to be synced.
+    /// Like [`Self::sync_qr_code_tokens()`], does nothing if the chat is unpromoted.
+    /// This interrupts SMTP on its own.
+    pub(crate) async fn sync_expiring_qr_code_tokens(
+        &self,
+        chat_id: Option<ChatId>,
+        invitenumber: String,
+        auth: String,
+        expires: i64,
+    ) -> Result<()> {
+        if !self.should_send_sync_msgs().await? {
+            return Ok(());
+        }
+
+        let grpid = if let Some(chat_id) = chat_id {
+            let chat = Chat::load_from_db(self, chat_id).await?;
+            if !chat.is_promoted() {
+                info!(
+                    self,
+                    "group '{}' not yet promoted, do not sync tokens yet.", chat.grpid
+                );
+                return Ok(());
+            }
+            Some(chat.grpid)
+        } else {
+            None
+        };
+        self.add_sync_item(SyncData::AddExpiringQrToken {
+            token: QrTokenData {
+                invitenumber,
+                auth,
+                grpid,
+            },
+            expires,
+        })
+        .await?;
+        self.scheduler.interrupt_smtp().await;
+        Ok(())
+    }
+
     /// Adds deleted qr-code token to the list of items to be synced
     /// so that the token also gets deleted on the other devices.
     /// This interrupts SMTP on its own.
@@ -280,10 +348,26 @@ pub(crate) async fn execute_sync_items(&self, items: &SyncItems) {
         for item in &items.items {
             match &item.data {
                 SyncDataOrUnknown::SyncData(data) => match data {
-                    AddQrToken(token) => self.add_qr_token(token).await,
+                    AddQrToken(token) => self.add_qr_token(token, 0).await,
+                    SyncData::AddExpiringQrToken { token, expires } => {
+                        self.add_qr_token(token, *expires).await
+                    }
                     DeleteQrToken(token) => self.delete_qr_token(token).await,
                     AlterChat { id, action } => self.sync_alter_chat(id, action).await,
                     SyncData::Config { key, val } => self.sync_config(key, val).await,
+                    SyncData::UiConfig { key, val } => {
+                        ensure!(key.starts_with("ui.sync."), "Not a synced ui config key");
+                        self.set_ui_config_ex(Sync::Nosync, key, val.as_deref())
+                            .await
+                    }
+                    SyncData::ContactList { uid, name, addrs } => {
+                        self.sync_contact_list(uid, name, addrs).await
+                    }
+                    SyncData::DeleteContactList { uid } => {
+                        self.sync_delete_contact_list(uid).await
+                    }
+                    SyncData::ChatLabel { uid, name } => self.sync_chat_label(uid, name).await,
+                    SyncData::DeleteChatLabel { uid } => self.sync_delete_chat_label(uid).await,
                 },
                 SyncDataOrUnknown::Unknown(data) => {
                     warn!(self, "Ignored unknown sync item: {data}.");
@@ -295,7 +379,8 @@ pub(crate) async fn execute_sync_items(&self, items: &SyncItems) {
         }
     }
 
-    async fn add_qr_token(&self, token: &QrTokenData) -> Result<()> {
+    /// Saves synced qr-code tokens, `expires` set to 0 means that they never expire.
+    async fn add_qr_token(&self, token: &QrTokenData, expires: i64) -> Result<()> {
         let chat_id = if let Some(grpid) = &token.grpid {
             if let Some((chat_id, _, _)) = chat::get_chat_id_by_grpid(self, grpid).await? {
                 Some(chat_id)
@@ -309,14 +394,24 @@ async fn add_qr_token(&self, token: &QrTokenData) -> Result<()> {
         } else {
             None
         };
-        token::save(self, Namespace::InviteNumber, chat_id, &token.invitenumber).await?;
-        token::save(self, Namespace::Auth, chat_id, &token.auth).await?;
+        token::save_with_expiry(
+            self,
+            Namespace::InviteNumber,
+            chat_id,
+            &token.invitenumber,
+            expires,
+        )
+        .await?;
+        token::save_with_expiry(self, Namespace::Auth, chat_id, &token.auth, expires).await?;
+        securejoin::record_invite(self, chat_id, &token.invitenumber, &token.auth, expires)
+            .await?;
         Ok(())
     }
 
     async fn delete_qr_token(&self, token: &QrTokenData) -> Result<()> {
         token::delete(self, Namespace::InviteNumber, &token.invitenumber).await?;
         token::delete(self, Namespace::Auth, &token.auth).await?;
+        securejoin::set_invite_withdrawn(self, &token.invitenumber, true).await?;
         Ok(())
     }
 }
@@ -0,0 +1,244 @@
+//! # Synthetic accounts for benchmarks.
+//!
+//! Fills an account with a reproducible set of chats and messages
+//! so that performance-sensitive code can be measured
+//! independently of a personal database.
+//!
+//! This is not part of the stable API.
+
+use anyhow::{Context as _, Result};
+
+use crate::chat::ChatId;
+use crate::config::Config;
+use crate::context::Context;
+use crate::mimefactory::wrapped_base64_encode;
+use crate::receive_imf::receive_imf;
+
+/// Timestamp of the first synthetic message.
+const START_TIMESTAMP: i64 = 1_700_000_000;
+
+/// Number of other members in synthetic groups.
+const GROUP_MEMBERS: usize = 3;
+
+/// Shape of a synthetic account.
+#[derive(Debug, Clone)]
+pub struct SyntheticAccount {
+    /// Number of chats.
+    pub chats: usize,
+
+    /// Number of messages per chat.
+    pub msgs_per_chat: usize,
+
+    /// Every `group_interval`-th chat is a group, 0 disables groups.
+    pub group_interval: usize,
+
+    /// Every `blob_interval`-th message has an attachment, 0 disables attachments.
+    pub blob_interval: usize,
+
+    /// Size of attachments in bytes.
+    pub blob_size: usize,
+}
+
+impl Default for SyntheticAccount {
+    fn default() -> Self {
+        Self {
+            chats: 100,
+            msgs_per_chat: 100,
+            group_interval: 5,
+            blob_interval: 10,
+            blob_size: 64 * 1024,
+        }
+    }
+}
+
+/// Returns `len` pseudo-random bytes determined by `seed`.
+///
+/// The bytes are not compressible, like most media.
+fn blob_data(seed: u64, len: usize) -> Vec<u8> {
+    let mut state = seed.wrapping_mul(6364136223846793005).wrapping_add(1);
+    (0..len)
+        .map(|_| {
+            state = state
+                .wrapping_mul(6364136223846793005)
+                .wrapping_add(1442695040888963407);
+            (state >> 56) as u8
+        })
+        .collect()
+}
+
+impl SyntheticAccount {
+    /// Renders message `msg_index` of chat `chat_index`.
+    fn render_msg(&self, self_addr: &str, chat_index: usize, msg_index: usize) -> String {
+        let contact = format!("contact{chat_index}@example.org");
+        let is_group = self.group_interval > 0 && chat_index % self.group_interval == 0;
+        let outgoing = msg_index % 3 == 2;
+        let timestamp =
+            START_TIMESTAMP + ((msg_index * self.chats + chat_index) as i64).saturating_mul(60);
+        let date = chrono::DateTime::<chrono::Utc>::from_timestamp(timestamp, 0)
+            .unwrap_or_default()
+            .to_rfc2822();
+
+        let (from, mut to) = if outgoing {
+            (self_addr.to_string(), vec![contact])
+        } else {
+            (contact, vec![self_addr.to_string()])
+        };
+        let mut headers = String::new();
+        if is_group {
+            to.extend(
+                (1..=GROUP_MEMBERS)
+                    .map(|member| format!("member{chat_index}.{member}@example.org")),
+            );
+            headers += &format!(
+                "Chat-Group-ID: synthetic{chat_index:08}\r\n\
+                 Chat-Group-Name: Group {chat_index}\r\n"
+            );
+        }
+        if msg_index > 0 {
+            headers += &format!(
+                "In-Reply-To: <synthetic.{chat_index}.{}@example.org>\r\n",
+                msg_index - 1
+            );
+        }
+
+        let text = format!("Message {msg_index} in chat {chat_index}.");
+        let body = if self.blob_interval > 0 && msg_index % self.blob_interval == 0 {
+            let data = blob_data(
+                (chat_index * self.msgs_per_chat + msg_index) as u64,
+                self.blob_size,
+            );
+            format!(
+                "Content-Type: multipart/mixed; boundary=\"synthetic\"\r\n\
+                 \r\n\
+                 --synthetic\r\n\
+                 Content-Type: text/plain; charset=utf-8\r\n\
+                 \r\n\
+                 {text}\r\n\
+                 --synthetic\r\n\
+                 Content-Type: application/octet-stream\r\n\
+                 Content-Disposition: attachment; filename=\"file{msg_index}.bin\"\r\n\
+                 Content-Transfer-Encoding: base64\r\n\
+                 \r\n\
+                 {}\r\n\
+                 --synthetic--\r\n",
+                wrapped_base64_encode(&data)
+            )
+        } else {
+            format!(
+                "Content-Type: text/plain; charset=utf-8\r\n\
+                 \r\n\
+                 {text}\r\n"
+            )
+        };
+
+        format!(
+            "From: <{from}>\r\n\
+             To: {to}\r\n\
+             Subject: Synthetic message\r\n\
+             Date: {date}\r\n\
+             Message-ID: <synthetic.{chat_index}.{msg_index}@example.org>\r\n\
+             Chat-Version: 1.0\r\n\
+             {headers}\
+             MIME-Version: 1.0\r\n\
+             {body}",
+            to = to
+                .iter()
+                .map(|addr| format!("<{addr}>"))
+                .collect::<Vec<_>>()
+                .join(", "),
+        )
+    }
+
+    /// Fills the account with the synthetic chats and messages.
+    ///
+    /// The account is configured with a placeholder address if it is not configured yet.
+    /// All chats are accepted.
+    /// Returns the IDs of the created chats.
+    pub async fn populate(&self, context: &Context) -> Result<Vec<ChatId>> {
+        if !context.is_configured().await? {
+            let addr = "alice@example.org";
+            context.set_config(Config::Addr, Some(addr)).await?;
+            context
+                .set_config(Config::ConfiguredAddr, Some(addr))
+                .await?;
+            context.set_config(Config::Configured, Some("1")).await?;
+        }
+        let self_addr = context.get_primary_self_addr().await?;
+
+        let mut chat_ids = Vec::with_capacity(self.chats);
+        for chat_index in 0..self.chats {
+            let mut chat_id = None;
+            for msg_index in 0..self.msgs_per_chat {
+                let raw = self.render_msg(&self_addr, chat_index, msg_index);
+                let received = receive_imf(context, raw.as_bytes(), true)
+                    .await?
+                    .context("Synthetic message was not received")?;
+                if chat_id.is_none() {
+                    received.chat_id.accept(context).await?;
+                    chat_id = Some(received.chat_id);
+                }
+            }
+            chat_ids.extend(chat_id);
+        }
+        Ok(chat_ids)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::chat::{self, Chat};
+    use crate::constants::Chattype;
+    use crate::message::{Message, Viewtype};
+    use crate::test_utils::TestContext;
+
+    #[tokio::test(flavor = "multi_thread", worker_threads = 2)]
+    async fn test_populate() -> Result<()> {
+        let t = TestContext::new().await;
+        let account = SyntheticAccount {
+            chats: 4,
+            msgs_per_chat: 5,
+            group_interval: 2,
+            blob_interval: 4,
+            blob_size: 1000,
+        };
+        let chat_ids = account.populate(&t).await?;
+        assert_eq!(chat_ids.len(), 4);
+
+        for (chat_index, chat_id) in chat_ids.iter().enumerate() {
+            let chat = Chat::load_from_db(&t, *chat_id).await?;
+            assert!(!chat.is_contact_request());
+            let expected_type = match chat_index % 2 {
+                0 => Chattype::Group,
+                _ => Chattype::Single,
+            };
+            assert_eq!(chat.get_type(), expected_type);
+
+            let msgs = chat::get_chat_msgs(&t, *chat_id).await?;
+            let mut files = 0;
+            let mut texts = 0;
+            for item in msgs {
+                let chat::ChatItem::Message { msg_id } = item else {
+                    continue;
+                };
+                let msg = Message::load_from_db(&t, msg_id).await?;
+                if msg.is_info() {
+                    continue;
+                }
+                texts += 1;
+                if msg.get_viewtype() == Viewtype::File {
+                    files += 1;
+                }
+            }
+            assert_eq!(texts, 5);
+            assert_eq!(files, 2);
+        }
+        Ok(())
+    }
+
+    #[test]
+    fn test_blob_data_reproducible() {
+        assert_eq!(blob_data(1, 100), blob_data(1, 100));
+        assert_ne!(blob_data(1, 100), blob_data(2, 100));
+    }
+}
@@ -1125,6 +1125,9 @@ fn print_event(event: &Event) {
         EventType::SmtpMessageSent(msg) => format!("[SMTP_MESSAGE_SENT] {msg}"),
         EventType::Warning(msg) => format!("WARN: {}", yellow.paint(msg)),
         EventType::Error(msg) => format!("ERROR: {}", red.paint(msg)),
+        EventType::ErrorRepeated { msg, count, .. } => {
+            format!("ERROR: {} (repeated {count} times)", red.paint(msg))
+        }
         EventType::ErrorSelfNotInGroup(msg) => {
             format!("{}", red.paint(format!("[SELF_NOT_IN_GROUP] {msg}")))
         }
@@ -1157,6 +1160,10 @@ fn print_event(event: &Event) {
                 )
             }
         }
+        EventType::AccountDestroyProgress(progress) => format!(
+            "{}",
+            green.paint(format!("Received ACCOUNT_DESTROY_PROGRESS({progress} ‰)"))
+        ),
         EventType::ImexProgress(progress) => format!(
             "{}",
             green.paint(format!("Received IMEX_PROGRESS({progress} ‰)"))
@@ -1165,6 +1172,10 @@ fn print_event(event: &Event) {
             "{}",
             green.paint(format!("Received IMEX_FILE_WRITTEN({})", file.display()))
         ),
+        EventType::ProxyChanged { host, port } => format!(
+            "{}",
+            green.paint(format!("Received PROXY_CHANGED({host}:{port})"))
+        ),
         EventType::ChatModified(chat) => {
             format!("{}", green.paint(format!("Received CHAT_MODIFIED({chat})")))
         }
@@ -6,6 +6,7 @@
 use crate::config::Config;
 use crate::constants::{Chattype, DC_GCL_FOR_FORWARDING};
 use crate::contact::{Contact, ContactId, Origin};
+use crate::events::EventType;
 use crate::message::{Message, Viewtype};
 use crate::mimefactory::MimeFactory;
 use crate::mimeparser::SystemMessage;
@@ -909,6 +910,70 @@ async fn test_no_unencrypted_name_if_verified() -> Result<()> {
     Ok(())
 }
 
+#[tokio::test(flavor = "multi_thread", worker_threads = 2)]
+async fn test_protection_changed_event() -> Result<()> {
+    let mut tcm = TestContextManager::new();
+    let alice = tcm.alice().await;
+    let bob = tcm.bob().await;
+    enable_verified_oneonone_chats(&[&alice, &bob]).await;
+
+    alice.evtracker.clear_events();
+    tcm.execute_securejoin(&alice, &bob).await;
+    let alice_bob_chat_id = alice.get_chat(&bob).await.id;
+    let event = alice
+        .evtracker
+        .get_matching(|evt| matches!(evt, EventType::ChatProtectionChanged { .. }))
+        .await;
+    assert_eq!(
+        event,
+        EventType::ChatProtectionChanged {
+            chat_id: alice_bob_chat_id,
+            protection: ProtectionStatus::Protected
+        }
+    );
+
+    tcm.section("Bob uses a classical MUA to send a message to Alice");
+    receive_imf(
+        &alice,
+        b"Subject: Re: Message from alice\r\n\
+          From: <bob@example.net>\r\n\
+          To: <alice@example.org>\r\n\
+          Date: Mon, 12 Dec 3000 14:33:39 +0000\r\n\
+          Message-ID: <abcd@example.net>\r\n\
+          \r\n\
+          Heyho!\r\n",
+        false,
+    )
+    .await?
+    .unwrap();
+    let event = alice
+        .evtracker
+        .get_matching(|evt| matches!(evt, EventType::ChatProtectionChanged { .. }))
+        .await;
+    assert_eq!(
+        event,
+        EventType::ChatProtectionChanged {
+            chat_id: alice_bob_chat_id,
+            protection: ProtectionStatus::ProtectionBroken
+        }
+    );
+
+    alice_bob_chat_id.accept(&alice).await?;
+    let event = alice
+        .evtracker
+        .get_matching(|evt| matches!(evt, EventType::ChatProtectionChanged { .. }))
+        .await;
+    assert_eq!(
+        event,
+        EventType::ChatProtectionChanged {
+            chat_id: alice_bob_chat_id,
+            protection: ProtectionStatus::Unprotected
+        }
+    );
+
+    Ok(())
+}
+
 // ============== Helper Functions ==============
 
 async fn assert_verified(this: &TestContext, other: &TestContext, protected: ProtectionStatus) {
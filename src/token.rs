@@ -29,21 +29,35 @@ pub async fn save(
     namespace: Namespace,
     foreign_id: Option<ChatId>,
     token: &str,
+) -> Result<()> {
+    save_with_expiry(context, namespace, foreign_id, token, 0).await
+}
+
+/// Saves a token to the database which is valid until the `expires` timestamp.
+///
+/// `expires` set to 0 means that the token never expires.
+/// Expiring tokens are never returned by [`lookup()`].
+pub async fn save_with_expiry(
+    context: &Context,
+    namespace: Namespace,
+    foreign_id: Option<ChatId>,
+    token: &str,
+    expires: i64,
 ) -> Result<()> {
     match foreign_id {
         Some(foreign_id) => context
             .sql
             .execute(
-                "INSERT INTO tokens (namespc, foreign_id, token, timestamp) VALUES (?, ?, ?, ?);",
-                (namespace, foreign_id, token, time()),
+                "INSERT INTO tokens (namespc, foreign_id, token, timestamp, expires) VALUES (?, ?, ?, ?, ?);",
+                (namespace, foreign_id, token, time(), expires),
             )
             .await?,
         None => {
             context
                 .sql
                 .execute(
-                    "INSERT INTO tokens (namespc, token, timestamp) VALUES (?, ?, ?);",
-                    (namespace, token, time()),
+                    "INSERT INTO tokens (namespc, token, timestamp, expires) VALUES (?, ?, ?, ?);",
+                    (namespace, token, time(), expires),
                 )
                 .await?
         }
@@ -70,7 +84,7 @@ pub async fn lookup(
             context
                 .sql
                 .query_get_value(
-                    "SELECT token FROM tokens WHERE namespc=? AND foreign_id=? ORDER BY timestamp DESC LIMIT 1;",
+                    "SELECT token FROM tokens WHERE namespc=? AND foreign_id=? AND expires=0 ORDER BY timestamp DESC LIMIT 1;",
                     (namespace, chat_id),
                 )
                 .await?
@@ -80,7 +94,7 @@ pub async fn lookup(
             context
                 .sql
                 .query_get_value(
-                    "SELECT token FROM tokens WHERE namespc=? AND foreign_id=0 ORDER BY timestamp DESC LIMIT 1;",
+                    "SELECT token FROM tokens WHERE namespc=? AND foreign_id=0 AND expires=0 ORDER BY timestamp DESC LIMIT 1;",
                     (namespace,),
                 )
                 .await?
@@ -103,12 +117,13 @@ pub async fn lookup_or_new(
     Ok(token)
 }
 
+/// Returns true if the token exists and is not expired.
 pub async fn exists(context: &Context, namespace: Namespace, token: &str) -> Result<bool> {
     let exists = context
         .sql
         .exists(
-            "SELECT COUNT(*) FROM tokens WHERE namespc=? AND token=?;",
-            (namespace, token),
+            "SELECT COUNT(*) FROM tokens WHERE namespc=? AND token=? AND (expires=0 OR expires>?);",
+            (namespace, token, time()),
         )
         .await?;
     Ok(exists)
@@ -122,8 +137,8 @@ pub async fn auth_chat_id(context: &Context, token: &str) -> Result<Option<ChatI
     let chat_id: Option<ChatId> = context
         .sql
         .query_row_optional(
-            "SELECT foreign_id FROM tokens WHERE namespc=? AND token=?",
-            (Namespace::Auth, token),
+            "SELECT foreign_id FROM tokens WHERE namespc=? AND token=? AND (expires=0 OR expires>?)",
+            (Namespace::Auth, token, time()),
             |row| {
                 let chat_id: ChatId = row.get(0)?;
                 Ok(chat_id)
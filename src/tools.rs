@@ -4,7 +4,7 @@
 #![allow(missing_docs)]
 
 use std::borrow::Cow;
-use std::io::{Cursor, Write};
+use std::io::{BufReader, Cursor, Read, Seek, Write};
 use std::mem;
 use std::ops::{AddAssign, Deref};
 use std::path::{Path, PathBuf};
@@ -311,6 +311,90 @@ pub fn get_filemeta(buf: &[u8]) -> Result<(u32, u32)> {
     Ok(dimensions)
 }
 
+/// Returns the number of frames of an animated GIF or WebP image.
+///
+/// Only the headers of GIF blocks and WebP chunks are read, image data is skipped.
+/// Returns `None` for still images and other image formats.
+pub(crate) fn get_animation_frames<R: Read + Seek>(file: &mut BufReader<R>) -> Option<u32> {
+    let mut header = [0; 13];
+    file.read_exact(&mut header).ok()?;
+    let frames = if header.starts_with(b"GIF87a") || header.starts_with(b"GIF89a") {
+        let [.., flags, _, _] = header;
+        gif_frames(file, flags).ok()?
+    } else if header.starts_with(b"RIFF") && header.get(8..12) == Some(b"WEBP") {
+        file.seek_relative(-1).ok()?;
+        webp_frames(file).ok()?
+    } else {
+        return None;
+    };
+    (frames > 1).then_some(frames)
+}
+
+/// Counts the image descriptors of a GIF file positioned after the logical screen descriptor.
+fn gif_frames<R: Read + Seek>(file: &mut BufReader<R>, flags: u8) -> std::io::Result<u32> {
+    let read_u8 = |file: &mut BufReader<R>| -> std::io::Result<u8> {
+        let mut buf = [0];
+        file.read_exact(&mut buf)?;
+        let [byte] = buf;
+        Ok(byte)
+    };
+    // Skip global color table.
+    if flags & 0x80 != 0 {
+        file.seek_relative(3 << ((flags & 0x07) + 1))?;
+    }
+    let mut frames = 0;
+    loop {
+        match read_u8(file)? {
+            // Extension label.
+            0x21 => {
+                read_u8(file)?;
+            }
+            // Image descriptor, local color table and LZW minimum code size.
+            0x2c => {
+                let mut descriptor = [0; 9];
+                file.read_exact(&mut descriptor)?;
+                let [.., flags] = descriptor;
+                if flags & 0x80 != 0 {
+                    file.seek_relative(3 << ((flags & 0x07) + 1))?;
+                }
+                read_u8(file)?;
+                frames += 1;
+            }
+            // Trailer.
+            0x3b => return Ok(frames),
+            _ => return Err(std::io::ErrorKind::InvalidData.into()),
+        }
+        // Skip data sub-blocks up to the block terminator.
+        loop {
+            let len = read_u8(file)?;
+            if len == 0 {
+                break;
+            }
+            file.seek_relative(len.into())?;
+        }
+    }
+}
+
+/// Counts the `ANMF` chunks of a WebP file positioned after the file header.
+fn webp_frames<R: Read + Seek>(file: &mut BufReader<R>) -> std::io::Result<u32> {
+    let mut frames = 0;
+    loop {
+        let mut header = [0; 8];
+        match file.read_exact(&mut header) {
+            Ok(()) => {}
+            Err(err) if err.kind() == std::io::ErrorKind::UnexpectedEof => return Ok(frames),
+            Err(err) => return Err(err),
+        }
+        let [a, b, c, d, size @ ..] = header;
+        if [a, b, c, d] == *b"ANMF" {
+            frames += 1;
+        }
+        let size = i64::from(u32::from_le_bytes(size));
+        // Chunks are padded to an even size.
+        file.seek_relative(size + size % 2)?;
+    }
+}
+
 /// Expand paths relative to $BLOBDIR into absolute paths.
 ///
 /// If `path` starts with "$BLOBDIR", replaces it with the blobdir path.
@@ -364,6 +448,34 @@ pub async fn delete_files_in_dir(context: &Context, path: impl AsRef<Path>) -> R
     Ok(())
 }
 
+/// Overwrites the file at `path` with zeroes and deletes it.
+///
+/// Does nothing if the file does not exist.
+pub(crate) async fn wipe_file(path: &Path) -> Result<()> {
+    use tokio::io::AsyncWriteExt;
+
+    let mut file = match fs::OpenOptions::new().write(true).open(path).await {
+        Ok(file) => file,
+        Err(err) if err.kind() == io::ErrorKind::NotFound => return Ok(()),
+        Err(err) => {
+            return Err(err).with_context(|| format!("Cannot open {}", path.display()));
+        }
+    };
+    let zeroes = [0u8; 4096];
+    let mut remaining = file.metadata().await?.len();
+    while remaining > 0 {
+        let n = remaining.min(zeroes.len() as u64) as usize;
+        file.write_all(zeroes.get(..n).unwrap_or_default()).await?;
+        remaining -= n as u64;
+    }
+    file.sync_all().await?;
+    drop(file);
+    fs::remove_file(path)
+        .await
+        .with_context(|| format!("Cannot delete {}", path.display()))?;
+    Ok(())
+}
+
 /// A guard which will remove the path when dropped.
 ///
 /// It implements [`Deref`] so it can be used as a `&Path`.
@@ -970,6 +1082,19 @@ fn test_truncate(
         }
     }
 
+    #[tokio::test(flavor = "multi_thread", worker_threads = 2)]
+    async fn test_wipe_file() -> Result<()> {
+        let dir = tempfile::tempdir()?;
+        let path = dir.path().join("file.txt");
+        fs::write(&path, vec![1u8; 10000]).await?;
+        wipe_file(&path).await?;
+        assert!(!path.exists());
+
+        // Missing files are ignored.
+        wipe_file(&path).await?;
+        Ok(())
+    }
+
     #[tokio::test(flavor = "multi_thread", worker_threads = 2)]
     async fn test_file_handling() {
         let t = TestContext::new().await;
@@ -1066,6 +1191,43 @@ fn test_get_filemeta() {
         assert_eq!(h, 50);
     }
 
+    #[test]
+    fn test_get_animation_frames() {
+        let frames = |data: &[u8]| get_animation_frames(&mut BufReader::new(Cursor::new(data)));
+        let data = include_bytes!("../test-data/image/animation.gif");
+        assert_eq!(frames(data), Some(2));
+        assert_eq!(get_filemeta(data).unwrap(), (1, 1));
+        let data = include_bytes!("../test-data/image/image100x50.gif");
+        assert_eq!(frames(data), None);
+        assert_eq!(frames(&data[..100]), None);
+        assert_eq!(frames(test_utils::AVATAR_900x900_BYTES), None);
+
+        let chunk = |fourcc: &[u8], payload: &[u8]| {
+            let mut chunk = fourcc.to_vec();
+            chunk.extend_from_slice(&u32::try_from(payload.len()).unwrap().to_le_bytes());
+            chunk.extend_from_slice(payload);
+            if payload.len() % 2 == 1 {
+                chunk.push(0);
+            }
+            chunk
+        };
+        let webp = |chunks: &[Vec<u8>]| {
+            let mut data = b"RIFF\0\0\0\0WEBP".to_vec();
+            data.extend(chunks.concat());
+            data
+        };
+        let animated = webp(&[
+            chunk(b"VP8X", &[0x02, 0, 0, 0, 0, 0, 0, 0, 0, 0]),
+            chunk(b"ANIM", &[0; 6]),
+            chunk(b"ANMF", &[0; 17]),
+            chunk(b"ANMF", &[0; 17]),
+            chunk(b"ANMF", &[0; 16]),
+        ]);
+        assert_eq!(frames(&animated), Some(3));
+        let still = webp(&[chunk(b"VP8L", &[0; 5])]);
+        assert_eq!(frames(&still), None);
+    }
+
     #[tokio::test(flavor = "multi_thread", worker_threads = 2)]
     async fn test_maybe_warn_on_bad_time() {
         let t = TestContext::new().await;
@@ -14,12 +14,17 @@
 //! - `first_serial` - serial number of the first status update to send
 //! - `last_serial` - serial number of the last status update to send
 //! - `descr` - text to send along with the updates
+//!
+//! Updates sent within [`STATUS_UPDATE_BATCH_WINDOW`] are batched into one message.
+//! `document` and `summary` superseded by later updates of the same batch are not sent.
 
 mod integration;
 mod maps_integration;
 
 use std::cmp::max;
 use std::path::Path;
+use std::sync::atomic::Ordering;
+use std::time::Duration;
 
 use anyhow::{anyhow, bail, ensure, format_err, Context as _, Result};
 
@@ -205,6 +210,10 @@ fn find_zip_entry<'a>(
 /// Status update JSON size soft limit.
 const STATUS_UPDATE_SIZE_MAX: usize = 100 << 10;
 
+/// Time to wait for more status updates before sending queued ones,
+/// so that updates sent by chatty apps in quick succession end up in one message.
+pub(crate) const STATUS_UPDATE_BATCH_WINDOW: Duration = Duration::from_secs(2);
+
 impl Context {
     /// check if a file is an acceptable webxdc for sending or receiving.
     pub(crate) async fn is_webxdc_file(&self, filename: &str, file: &[u8]) -> Result<bool> {
@@ -509,11 +518,29 @@ pub async fn send_webxdc_status_update_struct(
                  DO UPDATE SET last_serial=excluded.last_serial, descr=excluded.descr",
                 (instance.id, status_update_serial, status_update_serial, descr),
             ).await.context("Failed to insert webxdc update into SMTP queue")?;
-            self.scheduler.interrupt_smtp().await;
+            self.schedule_status_updates_flush();
         }
         Ok(())
     }
 
+    /// Interrupts SMTP loop to send queued status updates
+    /// after [`STATUS_UPDATE_BATCH_WINDOW`], unless this is already scheduled.
+    fn schedule_status_updates_flush(&self) {
+        if self.webxdc_flush_scheduled.swap(true, Ordering::Relaxed) {
+            return;
+        }
+        let context = self.clone();
+        tokio::spawn(async move {
+            tokio::time::sleep(STATUS_UPDATE_BATCH_WINDOW).await;
+            // Reset the flag before the updates are flushed,
+            // so that updates queued while flushing schedule another flush.
+            context
+                .webxdc_flush_scheduled
+                .store(false, Ordering::Relaxed);
+            context.scheduler.interrupt_smtp().await;
+        });
+    }
+
     /// Returns one record of the queued webxdc status updates.
     async fn smtp_status_update_get(
         &self,
@@ -743,7 +770,7 @@ pub(crate) async fn render_webxdc_status_update_object(
         last: StatusUpdateSerial,
         size_max: Option<usize>,
     ) -> Result<(Option<String>, StatusUpdateSerial)> {
-        let (json, first_new) = self
+        let updates = self
             .sql
             .query_map(
                 "SELECT id, update_item FROM msgs_status_updates \
@@ -755,28 +782,17 @@ pub(crate) async fn render_webxdc_status_update_object(
                     Ok((id, update_item))
                 },
                 |rows| {
-                    let mut json = String::default();
-                    for row in rows {
-                        let (id, update_item) = row?;
-                        if !json.is_empty()
-                            && json.len() + update_item.len() >= size_max.unwrap_or(usize::MAX)
-                        {
-                            return Ok((json, id));
-                        }
-                        if !json.is_empty() {
-                            json.push_str(",\n");
-                        }
-                        json.push_str(&update_item);
-                    }
-                    Ok((
-                        json,
-                        // Too late to fail here if an overflow happens. It's still better to send
-                        // the updates.
-                        StatusUpdateSerial::new(last.to_u32().saturating_add(1)),
-                    ))
+                    rows.collect::<std::result::Result<Vec<_>, _>>()
+                        .map_err(Into::into)
                 },
             )
             .await?;
+        let (json, first_new) = render_status_updates(coalesce_status_updates(updates)?, size_max);
+        let first_new = first_new.unwrap_or_else(|| {
+            // Too late to fail here if an overflow happens. It's still better to send
+            // the updates.
+            StatusUpdateSerial::new(last.to_u32().saturating_add(1))
+        });
         let json = match json.is_empty() {
             true => None,
             false => Some(format!(r#"{{"updates":[{json}]}}"#)),
@@ -785,6 +801,70 @@ pub(crate) async fn render_webxdc_status_update_object(
     }
 }
 
+/// Removes `document` and `summary` superseded by later updates from `updates`.
+///
+/// Updates themselves are never dropped, even if their payload is `null`,
+/// as webxdc apps receive every update in order.
+///
+/// `updates` are serials and JSON representations of status updates ordered by serial.
+fn coalesce_status_updates(
+    updates: Vec<(StatusUpdateSerial, String)>,
+) -> Result<Vec<(StatusUpdateSerial, String)>> {
+    let mut has_document = false;
+    let mut has_summary = false;
+    let mut res = Vec::with_capacity(updates.len());
+    for (id, update_item) in updates.into_iter().rev() {
+        let Ok(mut item) = serde_json::from_str::<StatusUpdateItem>(&update_item) else {
+            res.push((id, update_item));
+            continue;
+        };
+        let mut superseded = false;
+        if item.document.is_some() {
+            if has_document {
+                item.document = None;
+                superseded = true;
+            }
+            has_document = true;
+        }
+        if item.summary.is_some() {
+            if has_summary {
+                item.summary = None;
+                superseded = true;
+            }
+            has_summary = true;
+        }
+        if superseded {
+            res.push((id, serde_json::to_string(&item)?));
+        } else {
+            res.push((id, update_item));
+        }
+    }
+    res.reverse();
+    Ok(res)
+}
+
+/// Joins JSON representations of status updates, limiting the result to `size_max` bytes
+/// unless a single update is larger.
+///
+/// Returns the joined updates and the serial of the first update not included
+/// or `None` as the second element if all updates are included.
+fn render_status_updates(
+    updates: Vec<(StatusUpdateSerial, String)>,
+    size_max: Option<usize>,
+) -> (String, Option<StatusUpdateSerial>) {
+    let mut json = String::default();
+    for (id, update_item) in updates {
+        if !json.is_empty() && json.len() + update_item.len() >= size_max.unwrap_or(usize::MAX) {
+            return (json, Some(id));
+        }
+        if !json.is_empty() {
+            json.push_str(",\n");
+        }
+        json.push_str(&update_item);
+    }
+    (json, None)
+}
+
 fn parse_webxdc_manifest(bytes: &[u8]) -> Result<WebxdcManifest> {
     let s = std::str::from_utf8(bytes)?;
     let manifest: WebxdcManifest = toml::from_str(s)?;
@@ -2357,6 +2437,85 @@ async fn test_webxdc_info_summary() -> Result<()> {
         Ok(())
     }
 
+    #[test]
+    fn test_coalesce_status_updates() -> Result<()> {
+        let updates = vec![
+            (
+                StatusUpdateSerial(1),
+                r#"{"payload":1,"summary":"1"}"#.to_string(),
+            ),
+            (
+                StatusUpdateSerial(2),
+                r#"{"payload":null,"summary":"2"}"#.to_string(),
+            ),
+            (
+                StatusUpdateSerial(3),
+                r#"{"payload":null,"document":"a","info":"i"}"#.to_string(),
+            ),
+            (
+                StatusUpdateSerial(4),
+                r#"{"payload":null,"summary":"3","document":"b"}"#.to_string(),
+            ),
+        ];
+        assert_eq!(
+            coalesce_status_updates(updates)?,
+            vec![
+                (StatusUpdateSerial(1), r#"{"payload":1}"#.to_string()),
+                (StatusUpdateSerial(2), r#"{"payload":null}"#.to_string()),
+                (
+                    StatusUpdateSerial(3),
+                    r#"{"payload":null,"info":"i"}"#.to_string()
+                ),
+                (
+                    StatusUpdateSerial(4),
+                    r#"{"payload":null,"summary":"3","document":"b"}"#.to_string()
+                ),
+            ]
+        );
+        Ok(())
+    }
+
+    #[tokio::test(flavor = "multi_thread", worker_threads = 2)]
+    async fn test_webxdc_status_updates_batched() -> Result<()> {
+        let alice = TestContext::new_alice().await;
+        let bob = TestContext::new_bob().await;
+        let alice_chat = alice.create_chat(&bob).await;
+        let alice_instance = send_webxdc_instance(&alice, alice_chat.id).await?;
+        let sent_instance = &alice.pop_sent_msg().await;
+
+        for i in 1..=10 {
+            alice
+                .send_webxdc_status_update(
+                    alice_instance.id,
+                    &format!(r#"{{"summary":"sum: {i}", "payload":null}}"#),
+                    "descr",
+                )
+                .await?;
+        }
+        assert!(alice.webxdc_flush_scheduled.load(Ordering::Relaxed));
+        alice.flush_status_updates().await?;
+        let sent_update = &alice.pop_sent_msg().await;
+        assert!(alice.pop_sent_msg_opt(Duration::ZERO).await.is_none());
+
+        let bob_instance = bob.recv_msg(sent_instance).await;
+        bob.recv_msg_trash(sent_update).await;
+        let info = Message::load_from_db(&bob, bob_instance.id)
+            .await?
+            .get_webxdc_info(&bob)
+            .await?;
+        assert_eq!(info.summary, "sum: 10".to_string());
+
+        // Superseded updates are not sent.
+        assert_eq!(
+            bob.get_webxdc_status_updates(bob_instance.id, StatusUpdateSerial(0))
+                .await?
+                .matches("\"summary\"")
+                .count(),
+            1
+        );
+        Ok(())
+    }
+
     #[tokio::test(flavor = "multi_thread", worker_threads = 2)]
     async fn test_webxdc_document_name() -> Result<()> {
         let alice = TestContext::new_alice().await;
@@ -0,0 +1,285 @@
+//! # Web Key Directory lookup.
+//!
+//! If enabled with [`Config::WkdLookup`], keys of new contacts and of recipients without a known key
+//! are looked up in the [Web Key Directory](https://datatracker.ietf.org/doc/draft-koch-openpgp-webkey-service/)
+//! of their domain.
+//! Lookups run in the background, so sending is not delayed by them;
+//! a key found while sending is used for the next message.
+//!
+//! Keys found this way are provisional: they are stored as gossip keys without a timestamp,
+//! so any key received in an `Autocrypt` or `Autocrypt-Gossip` header replaces them.
+
+use std::time::Duration;
+
+use anyhow::{bail, ensure, Context as _, Result};
+use deltachat_contact_tools::{addr_cmp, EmailAddress};
+use percent_encoding::{utf8_percent_encode, AsciiSet, NON_ALPHANUMERIC};
+use sha1::{Digest, Sha1};
+
+use crate::aheader::EncryptPreference;
+use crate::config::Config;
+use crate::contact::{Contact, Origin};
+use crate::context::Context;
+use crate::events::EventType;
+use crate::key::{DcKey, SignedPublicKey};
+use crate::log::LogExt;
+use crate::net::http::get_client;
+use crate::peerstate::Peerstate;
+use crate::tools::time;
+
+/// Timeout for a single WKD request.
+const WKD_TIMEOUT: Duration = Duration::from_secs(10);
+
+/// Maximum size of a key returned by WKD.
+const WKD_MAX_KEY_SIZE: usize = 64 * 1024;
+
+/// Minimum time in seconds between two lookups for the same address.
+const WKD_LOOKUP_INTERVAL: i64 = 24 * 60 * 60;
+
+/// Alphabet of the z-base-32 encoding used for WKD hashes.
+const ZBASE32_ALPHABET: &[u8; 32] = b"ybndrfg8ejkmcpqxot1uwisza345h769";
+
+/// Characters percent-encoded in the local part passed in the `l` parameter.
+const LOCAL_PART_ENCODE_SET: &AsciiSet = &NON_ALPHANUMERIC
+    .remove(b'.')
+    .remove(b'-')
+    .remove(b'_')
+    .remove(b'~');
+
+/// Encodes `data` using z-base-32 as described in
+/// <https://philzimmermann.com/docs/human-oriented-base-32-encoding.txt>.
+fn zbase32_encode(data: &[u8]) -> String {
+    let mut res = String::with_capacity((data.len() * 8 + 4) / 5);
+    let mut buffer: u32 = 0;
+    let mut bits = 0;
+    for &byte in data {
+        buffer = (buffer << 8) | u32::from(byte);
+        bits += 8;
+        while bits >= 5 {
+            bits -= 5;
+            res.push(ZBASE32_ALPHABET[((buffer >> bits) & 0x1f) as usize] as char);
+        }
+    }
+    if bits > 0 {
+        res.push(ZBASE32_ALPHABET[((buffer << (5 - bits)) & 0x1f) as usize] as char);
+    }
+    res
+}
+
+/// Returns the WKD URLs for `addr`,
+/// first the one for the advanced method, then the one for the direct method.
+fn wkd_urls(addr: &str) -> Result<[String; 2]> {
+    let addr = EmailAddress::new(addr)?;
+    let domain = addr.domain.to_lowercase();
+    let hash = zbase32_encode(&Sha1::digest(addr.local.to_lowercase().as_bytes()));
+    let local = utf8_percent_encode(&addr.local, LOCAL_PART_ENCODE_SET);
+    Ok([
+        format!("https://openpgpkey.{domain}/.well-known/openpgpkey/{domain}/hu/{hash}?l={local}"),
+        format!("https://{domain}/.well-known/openpgpkey/hu/{hash}?l={local}"),
+    ])
+}
+
+/// Parses a key returned by WKD and checks that it belongs to `addr`.
+fn parse_wkd_key(addr: &str, data: &[u8]) -> Result<SignedPublicKey> {
+    let key = SignedPublicKey::from_slice(data).context("Failed to parse WKD key")?;
+    key.verify().context("Invalid WKD key")?;
+    let has_user_id = key.details.users.iter().any(|user| {
+        let user_id = String::from_utf8_lossy(user.id.id().as_ref());
+        let user_addr = match (user_id.rfind('<'), user_id.rfind('>')) {
+            (Some(start), Some(end)) if start < end => {
+                user_id.get(start + 1..end).unwrap_or_default()
+            }
+            _ => &*user_id,
+        };
+        addr_cmp(user_addr, addr)
+    });
+    ensure!(has_user_id, "WKD key has no user ID for {addr}");
+    Ok(key)
+}
+
+/// Fetches the key of `addr` from the Web Key Directory.
+///
+/// Returns the key and the URL it was found at.
+async fn fetch_key(context: &Context, addr: &str) -> Result<Option<(SignedPublicKey, String)>> {
+    let client = get_client(context, true).await?;
+    for url in wkd_urls(addr)? {
+        let response = match tokio::time::timeout(WKD_TIMEOUT, client.get(&url).send()).await {
+            Ok(Ok(response)) => response,
+            Ok(Err(err)) => {
+                info!(context, "WKD request to {url} failed: {err:#}.");
+                continue;
+            }
+            Err(_) => {
+                info!(context, "WKD request to {url} timed out.");
+                continue;
+            }
+        };
+        if !response.status().is_success() {
+            info!(context, "No key at {url}: {}.", response.status());
+            continue;
+        }
+        let mut response = response;
+        let mut data = Vec::new();
+        while let Some(chunk) = response.chunk().await? {
+            ensure!(
+                data.len() + chunk.len() <= WKD_MAX_KEY_SIZE,
+                "WKD key at {url} exceeds {WKD_MAX_KEY_SIZE} bytes"
+            );
+            data.extend_from_slice(&chunk);
+        }
+        return Ok(Some((parse_wkd_key(addr, &data)?, url)));
+    }
+    Ok(None)
+}
+
+/// Stores `key` found at `source` as provisional key of `addr`
+/// and emits [`EventType::ContactKeyDiscovered`].
+async fn import_key(
+    context: &Context,
+    addr: &str,
+    key: &SignedPublicKey,
+    source: String,
+) -> Result<Peerstate> {
+    if Peerstate::from_addr(context, addr).await?.is_some() {
+        bail!("Peerstate for {addr} appeared during WKD lookup");
+    }
+    let mut peerstate = Peerstate::from_public_key(addr, 0, EncryptPreference::Mutual, key);
+    peerstate.last_seen_autocrypt = 0;
+    peerstate.gossip_key = peerstate.public_key.take();
+    peerstate.gossip_key_fingerprint = peerstate.public_key_fingerprint.take();
+    peerstate.save_to_db(&context.sql).await?;
+    info!(
+        context,
+        "Imported key {} for {addr} from {source}.",
+        key.fingerprint()
+    );
+
+    if let Some(contact_id) = Contact::lookup_id_by_addr(context, addr, Origin::Unknown).await? {
+        context.emit_event(EventType::ContactKeyDiscovered { contact_id, source });
+    }
+    Ok(peerstate)
+}
+
+/// Looks up the key of `addr` in the Web Key Directory
+/// if WKD lookups are enabled and there is no peerstate for `addr` yet.
+///
+/// Returns the peerstate with the imported key, if a key was found.
+pub(crate) async fn maybe_lookup_key(context: &Context, addr: &str) -> Result<Option<Peerstate>> {
+    if !context.get_config_bool(Config::WkdLookup).await? {
+        return Ok(None);
+    }
+
+    let now = time();
+    {
+        let mut lookups = context.wkd_lookups.lock().await;
+        let last_lookup = lookups.entry(addr.to_lowercase()).or_default();
+        if now < *last_lookup + WKD_LOOKUP_INTERVAL && *last_lookup <= now {
+            return Ok(None);
+        }
+        *last_lookup = now;
+    }
+
+    match fetch_key(context, addr).await? {
+        Some((key, url)) => Ok(Some(import_key(context, addr, &key, url).await?)),
+        None => Ok(None),
+    }
+}
+
+/// Starts [`maybe_lookup_key`] for `addr` in the background
+/// if WKD lookups are enabled.
+pub(crate) async fn spawn_lookup(context: &Context, addr: &str) -> Result<()> {
+    if !context.get_config_bool(Config::WkdLookup).await? {
+        return Ok(());
+    }
+    let context = context.clone();
+    let addr = addr.to_string();
+    tokio::spawn(async move {
+        maybe_lookup_key(&context, &addr)
+            .await
+            .log_err(&context)
+            .ok();
+    });
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::key::load_self_public_key;
+    use crate::test_utils::TestContextManager;
+
+    #[test]
+    fn test_zbase32_encode() {
+        assert_eq!(zbase32_encode(b""), "");
+        assert_eq!(zbase32_encode(&[0xf0]), "6y");
+        assert_eq!(zbase32_encode(&[0xd4, 0x7a, 0x04]), "4t7ye");
+    }
+
+    #[test]
+    fn test_wkd_urls() -> Result<()> {
+        // Example from the WKD draft.
+        let [advanced, direct] = wkd_urls("Joe.Doe@Example.ORG")?;
+        assert_eq!(
+            advanced,
+            "https://openpgpkey.example.org/.well-known/openpgpkey/example.org/hu/iy9q119eutrkn8s1mk4r39qejnbu3n5q?l=Joe.Doe"
+        );
+        assert_eq!(
+            direct,
+            "https://example.org/.well-known/openpgpkey/hu/iy9q119eutrkn8s1mk4r39qejnbu3n5q?l=Joe.Doe"
+        );
+        Ok(())
+    }
+
+    #[tokio::test(flavor = "multi_thread", worker_threads = 2)]
+    async fn test_import_wkd_key() -> Result<()> {
+        let mut tcm = TestContextManager::new();
+        let alice = &tcm.alice().await;
+        let bob = &tcm.bob().await;
+        let bob_addr = bob.get_primary_self_addr().await?;
+        let bob_key = load_self_public_key(bob).await?;
+        let bob_id = alice.add_or_lookup_contact(bob).await.id;
+
+        let data = DcKey::to_bytes(&bob_key);
+        assert!(parse_wkd_key("fiona@example.net", &data).is_err());
+        let key = parse_wkd_key(&bob_addr, &data)?;
+
+        let source = "https://example.net/.well-known/openpgpkey/hu/x".to_string();
+        import_key(alice, &bob_addr, &key, source.clone()).await?;
+        let peerstate = Peerstate::from_addr(alice, &bob_addr).await?.unwrap();
+        assert!(peerstate.public_key.is_none());
+        assert_eq!(
+            peerstate.gossip_key_fingerprint,
+            Some(bob_key.fingerprint())
+        );
+        assert_eq!(peerstate.gossip_timestamp, 0);
+        assert_eq!(peerstate.prefer_encrypt, EncryptPreference::Mutual);
+        assert_eq!(peerstate.peek_key(false), Some(&bob_key));
+
+        let event = alice
+            .evtracker
+            .get_matching(|evt| matches!(evt, EventType::ContactKeyDiscovered { .. }))
+            .await;
+        assert_eq!(
+            event,
+            EventType::ContactKeyDiscovered {
+                contact_id: bob_id,
+                source
+            }
+        );
+
+        // Existing peerstates are never overwritten.
+        assert!(import_key(alice, &bob_addr, &key, String::new())
+            .await
+            .is_err());
+        Ok(())
+    }
+
+    #[tokio::test(flavor = "multi_thread", worker_threads = 2)]
+    async fn test_wkd_lookup_disabled() -> Result<()> {
+        let mut tcm = TestContextManager::new();
+        let alice = &tcm.alice().await;
+        assert!(!alice.get_config_bool(Config::WkdLookup).await?);
+        assert!(maybe_lookup_key(alice, "bob@example.net").await?.is_none());
+        Ok(())
+    }
+}